@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::async_process::AsyncCommand;
+use crate::git_ops::GitError;
+
+/// Set on git/jj processes to the bridge address; when treq itself is started with it,
+/// it runs as the askpass helper instead of the app
+const HELPER_ADDR_ENV: &str = "TREQ_ASKPASS_ADDR";
+const HELPER_TOKEN_ENV: &str = "TREQ_ASKPASS_TOKEN";
+const HELPER_SESSION_ENV: &str = "TREQ_ASKPASS_SESSION";
+
+/// How long a prompt waits for the user before the git command is failed
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Requests from the helper are a single short JSON line
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+
+/// Payload of the `auth-prompt` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthPrompt {
+    pub id: u64,
+    /// Prompt text from git or ssh, e.g. "Password for 'https://user@github.com': "
+    pub prompt: String,
+    /// The answer should be masked (password, passphrase or token)
+    pub secret: bool,
+    /// What treq was doing, e.g. "push"
+    pub operation: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HelperRequest {
+    token: String,
+    session: u64,
+    prompt: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HelperResponse {
+    answer: Option<String>,
+}
+
+struct PendingPrompt {
+    reply: Sender<(Option<String>, bool)>,
+}
+
+struct SessionInfo {
+    operation: String,
+    /// Background sessions only use remembered answers and never prompt
+    interactive: bool,
+}
+
+struct Bridge {
+    app: AppHandle,
+    addr: SocketAddr,
+    token: String,
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, PendingPrompt>>,
+    sessions: Mutex<HashMap<u64, SessionInfo>>,
+    /// Answers the user chose to remember, by prompt text; kept for the app session only
+    cache: Mutex<HashMap<String, String>>,
+    /// Cached prompts answered in each session, so they can be dropped if rejected
+    used: Mutex<HashMap<u64, Vec<String>>>,
+}
+
+static BRIDGE: OnceLock<Bridge> = OnceLock::new();
+
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes).map_err(|e| format!("Failed to generate askpass token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Start the loopback listener the helper connects to
+pub fn init(app: AppHandle) {
+    if BRIDGE.get().is_some() {
+        return;
+    }
+    let started = generate_token().and_then(|token| {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .map_err(|e| format!("Failed to bind askpass listener: {}", e))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to read askpass address: {}", e))?;
+        Ok((listener, addr, token))
+    });
+    let (listener, addr, token) = match started {
+        Ok(started) => started,
+        Err(e) => {
+            log::warn!("{}; git will not be able to prompt for credentials", e);
+            return;
+        }
+    };
+
+    let _ = BRIDGE.set(Bridge {
+        app,
+        addr,
+        token,
+        next_id: AtomicU64::new(1),
+        pending: Mutex::new(HashMap::new()),
+        sessions: Mutex::new(HashMap::new()),
+        cache: Mutex::new(HashMap::new()),
+        used: Mutex::new(HashMap::new()),
+    });
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(stream) {
+                    log::warn!("Askpass request failed: {}", e);
+                }
+            });
+        }
+    });
+}
+
+fn is_secret_prompt(prompt: &str) -> bool {
+    let lower = prompt.to_lowercase();
+    ["password", "passphrase", "token", "pin"]
+        .iter()
+        .any(|word| lower.contains(word))
+}
+
+fn handle_connection(stream: TcpStream) -> Result<(), String> {
+    let Some(bridge) = BRIDGE.get() else {
+        return Ok(());
+    };
+
+    let mut line = String::new();
+    BufReader::new(
+        stream
+            .try_clone()
+            .map_err(|e| e.to_string())?
+            .take(MAX_REQUEST_BYTES),
+    )
+    .read_line(&mut line)
+    .map_err(|e| format!("Failed to read request: {}", e))?;
+    let request: HelperRequest =
+        serde_json::from_str(&line).map_err(|e| format!("Invalid request: {}", e))?;
+    if request.token != bridge.token {
+        return Err("Invalid askpass token".to_string());
+    }
+
+    let answer = answer_prompt(bridge, request.session, &request.prompt);
+    let mut response = serde_json::to_string(&HelperResponse { answer })
+        .map_err(|e| format!("Failed to serialize response: {}", e))?;
+    response.push('\n');
+    (&stream)
+        .write_all(response.as_bytes())
+        .map_err(|e| format!("Failed to write response: {}", e))
+}
+
+/// Answer from the cache, or ask the frontend and wait for submit_auth_response
+fn answer_prompt(bridge: &Bridge, session: u64, prompt: &str) -> Option<String> {
+    let (operation, interactive) = bridge
+        .sessions
+        .lock()
+        .unwrap()
+        .get(&session)
+        .map(|info| (info.operation.clone(), info.interactive))?;
+
+    if let Some(answer) = bridge.cache.lock().unwrap().get(prompt).cloned() {
+        record_used(bridge, session, prompt);
+        return Some(answer);
+    }
+    if !interactive {
+        return None;
+    }
+
+    let id = bridge.next_id.fetch_add(1, Ordering::SeqCst);
+    let (reply, receiver) = mpsc::channel();
+    bridge
+        .pending
+        .lock()
+        .unwrap()
+        .insert(id, PendingPrompt { reply });
+    let _ = bridge.app.emit(
+        "auth-prompt",
+        AuthPrompt {
+            id,
+            prompt: prompt.to_string(),
+            secret: is_secret_prompt(prompt),
+            operation,
+        },
+    );
+
+    let result = receiver.recv_timeout(PROMPT_TIMEOUT);
+    bridge.pending.lock().unwrap().remove(&id);
+    let (answer, remember) = result.ok()?;
+    if let (Some(answer), true) = (&answer, remember) {
+        bridge
+            .cache
+            .lock()
+            .unwrap()
+            .insert(prompt.to_string(), answer.clone());
+        record_used(bridge, session, prompt);
+    }
+    answer
+}
+
+fn record_used(bridge: &Bridge, session: u64, prompt: &str) {
+    bridge
+        .used
+        .lock()
+        .unwrap()
+        .entry(session)
+        .or_default()
+        .push(prompt.to_string());
+}
+
+/// Deliver the user's answer to a pending prompt. `None` cancels it, failing the git
+/// command. Remembered answers are reused for the same prompt until treq restarts.
+pub fn submit_response(id: u64, answer: Option<String>, remember: bool) -> Result<(), String> {
+    let bridge = BRIDGE
+        .get()
+        .ok_or_else(|| "Credential prompts are not available".to_string())?;
+    let pending = bridge
+        .pending
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| format!("Prompt {} has expired", id))?;
+    pending
+        .reply
+        .send((answer, remember))
+        .map_err(|_| format!("Prompt {} has expired", id))
+}
+
+/// Forget all remembered answers
+pub fn clear_cache() {
+    if let Some(bridge) = BRIDGE.get() {
+        bridge.cache.lock().unwrap().clear();
+    }
+}
+
+/// Prompts for one git/jj invocation. Released on drop.
+pub struct AskpassSession {
+    id: u64,
+}
+
+/// Start a session for a command that may need credentials; `operation` is shown to the
+/// user with each prompt
+pub fn session(operation: &str) -> AskpassSession {
+    start_session(operation, true)
+}
+
+/// Session for work the user did not start, e.g. periodic fetches: remembered answers
+/// are used but the user is never prompted
+pub fn background_session(operation: &str) -> AskpassSession {
+    start_session(operation, false)
+}
+
+fn start_session(operation: &str, interactive: bool) -> AskpassSession {
+    static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+    let id = NEXT_SESSION.fetch_add(1, Ordering::SeqCst);
+    if let Some(bridge) = BRIDGE.get() {
+        bridge.sessions.lock().unwrap().insert(
+            id,
+            SessionInfo {
+                operation: operation.to_string(),
+                interactive,
+            },
+        );
+    }
+    AskpassSession { id }
+}
+
+impl AskpassSession {
+    /// Route the command's credential prompts to the frontend. Without the bridge,
+    /// prompting is disabled so the command fails instead of waiting on a missing TTY.
+    /// Only async commands take a session: a prompt can wait on the user for minutes.
+    pub fn apply_async(&self, command: &mut AsyncCommand) {
+        for (key, value) in self.env() {
+            command.env(key, value);
+        }
+    }
+
+    fn env(&self) -> Vec<(&'static str, OsString)> {
+        let mut env = vec![("GIT_TERMINAL_PROMPT", OsString::from("0"))];
+        let Some(bridge) = BRIDGE.get() else {
+            return env;
+        };
+        let Ok(helper) = std::env::current_exe() else {
+            return env;
+        };
+        env.extend([
+            ("GIT_ASKPASS", helper.clone().into_os_string()),
+            ("SSH_ASKPASS", helper.into_os_string()),
+            ("SSH_ASKPASS_REQUIRE", OsString::from("force")),
+            (HELPER_ADDR_ENV, OsString::from(bridge.addr.to_string())),
+            (HELPER_TOKEN_ENV, OsString::from(&bridge.token)),
+            (HELPER_SESSION_ENV, OsString::from(self.id.to_string())),
+        ]);
+        env
+    }
+
+    /// Drop remembered answers used by this session if git rejected them, so the user is
+    /// asked again next time
+    pub fn check_rejected(&self, stderr: &str) {
+        if !matches!(GitError::from_stderr(stderr), GitError::AuthRequired(_)) {
+            return;
+        }
+        let Some(bridge) = BRIDGE.get() else {
+            return;
+        };
+        let used = bridge.used.lock().unwrap().remove(&self.id);
+        let mut cache = bridge.cache.lock().unwrap();
+        for prompt in used.unwrap_or_default() {
+            cache.remove(&prompt);
+        }
+    }
+}
+
+impl Drop for AskpassSession {
+    fn drop(&mut self) {
+        if let Some(bridge) = BRIDGE.get() {
+            bridge.sessions.lock().unwrap().remove(&self.id);
+            bridge.used.lock().unwrap().remove(&self.id);
+        }
+    }
+}
+
+/// When treq was started by git or ssh as the askpass helper, forward the prompt to the
+/// running app and print the answer. Returns the exit code, or None for a normal start.
+pub fn run_helper_if_requested() -> Option<i32> {
+    let addr = std::env::var(HELPER_ADDR_ENV).ok()?;
+    let prompt = std::env::args().nth(1).unwrap_or_default();
+    let request = HelperRequest {
+        token: std::env::var(HELPER_TOKEN_ENV).unwrap_or_default(),
+        session: std::env::var(HELPER_SESSION_ENV)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0),
+        prompt,
+    };
+
+    match ask_app(&addr, &request) {
+        Ok(Some(answer)) => {
+            println!("{}", answer);
+            Some(0)
+        }
+        Ok(None) => Some(1),
+        Err(e) => {
+            eprintln!("treq askpass: {}", e);
+            Some(1)
+        }
+    }
+}
+
+fn ask_app(addr: &str, request: &HelperRequest) -> Result<Option<String>, String> {
+    let addr: SocketAddr = addr
+        .parse()
+        .map_err(|e| format!("Invalid address: {}", e))?;
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_secs(5))
+        .map_err(|e| format!("Failed to connect to treq: {}", e))?;
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to send prompt: {}", e))?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|e| format!("Failed to read answer: {}", e))?;
+    let response: HelperResponse =
+        serde_json::from_str(&response).map_err(|e| format!("Invalid answer: {}", e))?;
+    Ok(response.answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_secret_prompt() {
+        assert!(is_secret_prompt("Password for 'https://me@github.com': "));
+        assert!(is_secret_prompt(
+            "Enter passphrase for key '/home/me/.ssh/id_ed25519': "
+        ));
+        assert!(!is_secret_prompt("Username for 'https://github.com': "));
+    }
+}
@@ -0,0 +1,259 @@
+//! Async execution for long-running git/jj processes (push, fetch, pull, clone, rebase,
+//! merge), so a slow remote doesn't hold up other commands while it runs.
+//!
+//! Processes still take a slot in the process limiter. They are killed when they run
+//! past their timeout or when the operation that started them is cancelled (see
+//! `operations`), and only the first 16 MiB of each stream are kept. Their stderr is
+//! passed to the operation as progress.
+
+use std::ffi::OsStr;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::process::{Output, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::runtime::{Handle, Runtime};
+
+use crate::operations;
+use crate::paths;
+use crate::process_limiter;
+
+/// Default timeout for commands that talk to a remote. Generous because credential
+/// prompts wait on the user for up to five minutes.
+pub const REMOTE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Default timeout for local history rewrites such as rebases and merges
+pub const LOCAL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Cap on the captured stdout and stderr, each; the rest is read and dropped
+const MAX_OUTPUT: usize = 16 * 1024 * 1024;
+
+/// Longest stderr line passed to a line callback; longer lines are cut
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("treq-process")
+            .enable_all()
+            .build()
+            .expect("Failed to start the process runtime")
+    })
+}
+
+/// Wait for `future` from synchronous code, such as the VCS backends, background
+/// threads and tests. Inside an async command the worker thread is handed back to the
+/// runtime while waiting, and the current operation can still be cancelled.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    match Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => runtime().block_on(future),
+    }
+}
+
+/// Read `reader` to the end, keeping at most `max_output` bytes and passing each line
+/// to `on_line`. Lines end at `\n` or `\r`, so progress meters that redraw in place
+/// arrive as separate lines.
+async fn read_stream<R: AsyncRead + Unpin>(
+    mut reader: R,
+    max_output: usize,
+    mut on_line: impl FnMut(&str),
+) -> io::Result<(Vec<u8>, bool)> {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut line = Vec::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        let room = max_output.saturating_sub(kept.len());
+        kept.extend_from_slice(&chunk[..read.min(room)]);
+        truncated |= read > room;
+
+        for &byte in &chunk[..read] {
+            if byte == b'\n' || byte == b'\r' {
+                if !line.is_empty() {
+                    on_line(&String::from_utf8_lossy(&line));
+                    line.clear();
+                }
+            } else if line.len() < MAX_LINE_BYTES {
+                line.push(byte);
+            }
+        }
+    }
+    if !line.is_empty() {
+        on_line(&String::from_utf8_lossy(&line));
+    }
+    Ok((kept, truncated))
+}
+
+/// Async counterpart of `LimitedCommand` for commands that can run for minutes.
+///
+/// Mirrors its builder methods and adds a timeout.
+pub struct AsyncCommand {
+    inner: Command,
+    timeout: Option<Duration>,
+}
+
+impl AsyncCommand {
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        let mut inner = Command::new(program);
+        inner.kill_on_drop(true);
+        Self {
+            inner,
+            timeout: None,
+        }
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.inner.arg(arg);
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.inner.args(args);
+        self
+    }
+
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.inner.current_dir(paths::for_command(dir.as_ref()));
+        self
+    }
+
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, val: V) -> &mut Self {
+        self.inner.env(key, val);
+        self
+    }
+
+    /// Kill the process when it runs longer than `timeout`
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub async fn output(&mut self) -> io::Result<Output> {
+        self.output_with_stderr_lines(|_| {}).await
+    }
+
+    /// Like `output`, passing each stderr line to `on_line` as it arrives
+    pub async fn output_with_stderr_lines(
+        &mut self,
+        mut on_line: impl FnMut(&str),
+    ) -> io::Result<Output> {
+        let cancel = operations::current_token().unwrap_or_default();
+        if cancel.is_cancelled() {
+            return Err(cancelled_error());
+        }
+
+        let _permit = tokio::task::spawn_blocking(process_limiter::acquire)
+            .await
+            .map_err(io::Error::other)?;
+        let mut child = self
+            .inner
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let timeout = self.timeout;
+        let run = async {
+            let ((stdout, stdout_truncated), (stderr, stderr_truncated)) = tokio::try_join!(
+                read_stream(stdout, MAX_OUTPUT, |_| {}),
+                read_stream(stderr, MAX_OUTPUT, |line| {
+                    operations::report_line(line);
+                    on_line(line);
+                }),
+            )?;
+            if stdout_truncated || stderr_truncated {
+                log::warn!("Process output exceeded {} bytes and was cut", MAX_OUTPUT);
+            }
+            let status = child.wait().await?;
+            Ok(Output {
+                status,
+                stdout,
+                stderr,
+            })
+        };
+        let deadline = async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let result = tokio::select! {
+            result = run => return result,
+            _ = cancel.cancelled() => Err(cancelled_error()),
+            _ = deadline => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "Timed out after {} seconds",
+                    timeout.unwrap_or_default().as_secs()
+                ),
+            )),
+        };
+        let _ = child.kill().await;
+        result
+    }
+}
+
+fn cancelled_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "Operation cancelled")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_output_limits_and_stderr_lines() {
+        let mut lines = Vec::new();
+        let (kept, truncated) = block_on(read_stream(&b"one\rtwo\n\nthree"[..], 6, |line| {
+            lines.push(line.to_string())
+        }))
+        .unwrap();
+        assert_eq!(kept, b"one\rtw");
+        assert!(truncated);
+        assert_eq!(lines, ["one", "two", "three"]);
+
+        let output = block_on(
+            AsyncCommand::new("sh")
+                .args(["-c", "printf out; printf err >&2"])
+                .output(),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            (&output.stdout[..], &output.stderr[..]),
+            (&b"out"[..], &b"err"[..])
+        );
+    }
+
+    #[test]
+    fn test_timeout_kills_the_process() {
+        let started = Instant::now();
+        let error = block_on(
+            AsyncCommand::new("sleep")
+                .arg("10")
+                .timeout(Duration::from_millis(100))
+                .output(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
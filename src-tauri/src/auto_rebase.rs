@@ -1,6 +1,22 @@
+use crate::async_process;
+use crate::automation::{self, HookContext, HookEvent};
+use crate::commit_graph;
 use crate::jj::{self, JjRebaseResult};
 use crate::local_db::{self, Workspace};
+use crate::operations::{self, OperationKind};
+use crate::git_ops;
+use crate::vcs::{self, VcsKind};
+use crate::workspace_config;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+/// Workspace metadata key opting a workspace into rebasing when a fetch moves its target
+pub const AUTO_REBASE_ON_TARGET_UPDATE_KEY: &str = "auto_rebase_on_target_update";
+
+/// Handle used to emit target-update rebase events, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
 
 /// Result for auto-rebase operation on a group of workspaces
 #[derive(Debug)]
@@ -10,24 +26,68 @@ pub struct AutoRebaseResult {
     pub rebase_result: JjRebaseResult,
 }
 
+/// Payload of the `workspace-auto-rebased` and `workspace-rebase-conflict` events
+#[derive(Debug, Serialize, Clone)]
+pub struct TargetUpdateRebase {
+    pub repo_path: String,
+    pub workspace_id: i64,
+    pub workspace_path: String,
+    pub target_branch: String,
+    /// Commit the target branch advanced to
+    pub target_commit: String,
+    /// Empty for `workspace-auto-rebased`
+    pub conflicted_files: Vec<String>,
+    pub message: String,
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
 /// Convert git remote branch format to jj format using centralized logic
 fn convert_to_jj_branch_format(branch: &str, repo_path: &str) -> String {
     jj::convert_git_branch_to_jj_format_public(branch, repo_path)
 }
 
+/// Run the repo's rebase-conflict hooks for a workspace an auto-rebase left conflicted
+fn fire_conflict_hooks(
+    repo_path: &str,
+    workspace: &Workspace,
+    target_branch: &str,
+    conflicted_files: Vec<String>,
+) {
+    automation::fire(
+        repo_path,
+        HookEvent::RebaseConflict,
+        HookContext {
+            workspace_id: Some(workspace.id),
+            workspace_path: Some(workspace.workspace_path.clone()),
+            branch: Some(workspace.branch_name.clone()),
+            target_branch: Some(target_branch.to_string()),
+            conflicted_files,
+            ..Default::default()
+        },
+    );
+}
+
 /// Rebase workspaces targeting a specific branch if they have changes
 pub fn rebase_workspaces_for_target(
     repo_path: &str,
     target_branch: &str,
 ) -> Result<Option<AutoRebaseResult>, String> {
-    // Get all workspaces targeting this branch
-    let workspaces = local_db::get_workspaces_by_target_branch(repo_path, target_branch)?;
-
-    // Filter out workspaces where branch_name == target_branch (self-rebase)
-    let workspaces: Vec<Workspace> = workspaces
+    // Get all workspaces targeting this branch, honoring .treq/workspace.toml overrides
+    let mut workspaces: Vec<Workspace> = local_db::get_workspaces_by_target_branch(repo_path, target_branch)?
         .into_iter()
-        .filter(|w| w.branch_name != target_branch)
+        .filter(|w| workspace_config::effective_target_branch(w).as_deref() == Some(target_branch))
         .collect();
+    let overridden = local_db::get_workspaces(repo_path)?.into_iter().filter(|w| {
+        w.target_branch.as_deref() != Some(target_branch)
+            && workspace_config::target_branch_override(&w.workspace_path).as_deref() == Some(target_branch)
+    });
+    workspaces.extend(overridden);
+
+    // Filter out workspaces where branch_name == target_branch (self-rebase)
+    workspaces.retain(|w| w.branch_name != target_branch);
 
     if workspaces.is_empty() {
         return Ok(None);
@@ -83,17 +143,20 @@ pub fn rebase_workspaces_for_target(
                 // The working copy remains at its current state, working only with committed bookmarks
 
                 // Update DB flags - check for conflicts after rebase
-                let has_conflicts = jj::get_conflicted_files(
+                let conflicted_files = jj::get_conflicted_files(
                     &workspace.workspace_path,
-                    workspace.target_branch.as_deref()
+                    Some(target_branch)
                 )
-                    .map(|files| !files.is_empty())
-                    .unwrap_or(false);
+                    .unwrap_or_default();
+                let has_conflicts = !conflicted_files.is_empty();
                 local_db::update_workspace_has_conflicts(
                     repo_path,
                     workspace.id,
                     has_conflicts,
                 )?;
+                if has_conflicts {
+                    fire_conflict_hooks(repo_path, workspace, target_branch, conflicted_files);
+                }
 
                 local_db::update_workspace_last_rebased_commit(
                     repo_path,
@@ -217,12 +280,12 @@ pub fn check_and_rebase_all(repo_path: &str) -> Result<Vec<AutoRebaseResult>, St
                     // The working copy remains at its current state, working only with committed bookmarks
 
                     // Update DB flags - check for conflicts after rebase
-                    let has_conflicts = jj::get_conflicted_files(
+                    let conflicted_files = jj::get_conflicted_files(
                         &workspace.workspace_path,
                         workspace.target_branch.as_deref()
                     )
-                        .map(|files| !files.is_empty())
-                        .unwrap_or(false);
+                        .unwrap_or_default();
+                    let has_conflicts = !conflicted_files.is_empty();
                     if let Err(e) = local_db::update_workspace_has_conflicts(
                         repo_path,
                         workspace.id,
@@ -233,6 +296,9 @@ pub fn check_and_rebase_all(repo_path: &str) -> Result<Vec<AutoRebaseResult>, St
                             workspace.workspace_name, e
                         );
                     }
+                    if has_conflicts {
+                        fire_conflict_hooks(repo_path, workspace, &target_branch, conflicted_files);
+                    }
 
                     if let Err(e) = local_db::update_workspace_last_rebased_commit(
                         repo_path,
@@ -289,12 +355,9 @@ pub fn rebase_single_workspace(
     let workspace = local_db::get_workspace_by_id(repo_path, workspace_id)?
         .ok_or_else(|| format!("Workspace {} not found", workspace_id))?;
 
-    // Use workspace target_branch if set, otherwise use default_branch
-    let target_branch = workspace
-        .target_branch
-        .as_ref()
-        .unwrap_or(&default_branch.to_string())
-        .clone();
+    // Use the workspace's target (honouring .treq/workspace.toml), otherwise default_branch
+    let target_branch = workspace_config::effective_target_branch(&workspace)
+        .unwrap_or_else(|| default_branch.to_string());
 
     // Skip if branch_name == target_branch (self-rebase)
     if workspace.branch_name == target_branch {
@@ -382,10 +445,206 @@ pub fn rebase_single_workspace(
     }))
 }
 
+/// Whether workspace metadata opts into rebasing on target updates; off unless set
+pub fn auto_rebase_on_target_update(metadata: Option<&str>) -> bool {
+    metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|v| v.get(AUTO_REBASE_ON_TARGET_UPDATE_KEY)?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Write the opt-in into workspace metadata, keeping unrelated keys
+fn merge_auto_rebase_setting(metadata: Option<&str>, enabled: bool) -> Result<String, String> {
+    let mut object = metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|v| v.as_object().cloned())
+        .unwrap_or_default();
+    object.insert(
+        AUTO_REBASE_ON_TARGET_UPDATE_KEY.to_string(),
+        serde_json::Value::Bool(enabled),
+    );
+    serde_json::to_string(&object).map_err(|e| format!("Failed to serialize metadata: {}", e))
+}
+
+pub fn set_auto_rebase_on_target_update(
+    repo_path: &str,
+    workspace_id: i64,
+    enabled: bool,
+) -> Result<(), String> {
+    let workspace = local_db::get_workspace_by_id(repo_path, workspace_id)?
+        .ok_or_else(|| format!("Workspace {} not found", workspace_id))?;
+    let metadata = merge_auto_rebase_setting(workspace.metadata.as_deref(), enabled)?;
+    local_db::update_workspace_metadata(repo_path, workspace_id, &metadata)
+}
+
+/// Opted-in workspaces with the target branch each rebases onto
+fn opted_in_workspaces(repo_path: &str) -> Vec<(Workspace, String)> {
+    let workspaces = match local_db::get_workspaces(repo_path) {
+        Ok(workspaces) => workspaces,
+        Err(e) => {
+            log::warn!("Failed to list workspaces for auto-rebase: {}", e);
+            return Vec::new();
+        }
+    };
+    workspaces
+        .into_iter()
+        .filter(|w| auto_rebase_on_target_update(w.metadata.as_deref()))
+        .filter_map(|w| {
+            let target = workspace_config::effective_target_branch(&w)?;
+            (target != w.branch_name).then_some((w, target))
+        })
+        .collect()
+}
+
+/// Commit a target branch points at, resolved through the repo's backend
+fn target_commit(repo_path: &str, target: &str) -> Result<String, String> {
+    match vcs::backend_kind(repo_path) {
+        VcsKind::JjColocated => {
+            let jj_target = convert_to_jj_branch_format(target, repo_path);
+            jj::jj_get_commit_id(repo_path, &jj_target).map_err(|e| e.to_string())
+        }
+        VcsKind::PlainGit => git_ops::git_resolve_commit(repo_path, target).map_err(|e| e.to_string()),
+    }
+}
+
+/// Commit of each target branch of opted-in workspaces, taken before a fetch so
+/// `rebase_on_target_updates` can tell which targets the fetch advanced
+pub fn snapshot_targets(repo_path: &str) -> HashMap<String, String> {
+    let mut commits = HashMap::new();
+    for (_, target) in opted_in_workspaces(repo_path) {
+        if commits.contains_key(&target) {
+            continue;
+        }
+        if let Ok(commit) = target_commit(repo_path, &target) {
+            commits.insert(target, commit);
+        }
+    }
+    commits
+}
+
+/// Rebase opted-in workspaces whose target branch moved since `before` was taken. A
+/// workspace is left alone while it has conflicts or uncommitted changes. Emits
+/// `workspace-auto-rebased`, or `workspace-rebase-conflict` when the rebase conflicts.
+pub fn rebase_on_target_updates(repo_path: &str, before: &HashMap<String, String>) {
+    for (workspace, target) in opted_in_workspaces(repo_path) {
+        let Some(previous) = before.get(&target) else {
+            continue;
+        };
+        let current = match target_commit(repo_path, &target) {
+            Ok(commit) => commit,
+            Err(e) => {
+                log::warn!("Failed to get commit of target '{}': {}", target, e);
+                continue;
+            }
+        };
+        if &current == previous || workspace.has_conflicts {
+            continue;
+        }
+        match vcs::backend_for_workspace(&workspace.workspace_path).changed_files(&workspace.workspace_path) {
+            Ok(changes) if changes.is_empty() => {}
+            Ok(_) => continue,
+            Err(e) => {
+                log::warn!("Skipping auto-rebase of '{}': {}", workspace.workspace_name, e);
+                continue;
+            }
+        }
+        rebase_onto_updated_target(repo_path, &workspace, &target, &current);
+    }
+}
+
+/// `rebase_on_target_updates` on a background thread, for fetches that shouldn't wait
+/// for the rebases
+pub fn spawn_rebase_on_target_updates(repo_path: String, before: HashMap<String, String>) {
+    if before.is_empty() {
+        return;
+    }
+    std::thread::spawn(move || rebase_on_target_updates(&repo_path, &before));
+}
+
+fn rebase_onto_updated_target(
+    repo_path: &str,
+    workspace: &Workspace,
+    target_branch: &str,
+    target_commit: &str,
+) {
+    let backend = vcs::backend_for_workspace(&workspace.workspace_path);
+    let rebase = async {
+        backend.rebase_onto(&workspace.workspace_path, &workspace.branch_name, target_branch)
+    };
+    let result = async_process::block_on(operations::run_operation(
+        OperationKind::Rebase,
+        &workspace.workspace_path,
+        None,
+        rebase,
+    ));
+    commit_graph::notify_changed(&workspace.workspace_path);
+    let result = match result {
+        Ok(result) if result.success => result,
+        Ok(result) => {
+            log::warn!("Auto-rebase of '{}' failed: {}", workspace.workspace_name, result.message);
+            return;
+        }
+        Err(e) => {
+            log::warn!("Auto-rebase of '{}' failed: {}", workspace.workspace_name, e);
+            return;
+        }
+    };
+
+    let conflicted_files = match backend.kind() {
+        VcsKind::JjColocated => jj::get_conflicted_files(&workspace.workspace_path, Some(target_branch))
+            .unwrap_or_default(),
+        // A git rebase that conflicts is aborted, so a successful one leaves none
+        VcsKind::PlainGit => Vec::new(),
+    };
+    let has_conflicts = !conflicted_files.is_empty();
+    if let Err(e) = local_db::update_workspace_has_conflicts(repo_path, workspace.id, has_conflicts) {
+        log::warn!("Failed to update conflicts flag for '{}': {}", workspace.workspace_name, e);
+    }
+    if let Err(e) = local_db::update_workspace_last_rebased_commit(repo_path, workspace.id, target_commit) {
+        log::warn!("Failed to update last rebased commit for '{}': {}", workspace.workspace_name, e);
+    }
+    if has_conflicts {
+        fire_conflict_hooks(repo_path, workspace, target_branch, conflicted_files.clone());
+    }
+
+    let event = if has_conflicts {
+        "workspace-rebase-conflict"
+    } else {
+        "workspace-auto-rebased"
+    };
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit(
+            event,
+            TargetUpdateRebase {
+                repo_path: repo_path.to_string(),
+                workspace_id: workspace.id,
+                workspace_path: workspace.workspace_path.clone(),
+                target_branch: target_branch.to_string(),
+                target_commit: target_commit.to_string(),
+                conflicted_files,
+                message: result.message,
+            },
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_auto_rebase_on_target_update_setting() {
+        assert!(!auto_rebase_on_target_update(None));
+        assert!(!auto_rebase_on_target_update(Some("not json")));
+
+        let metadata = merge_auto_rebase_setting(Some(r#"{"intent":"ship it"}"#), true).unwrap();
+        assert!(auto_rebase_on_target_update(Some(&metadata)));
+        assert!(metadata.contains(r#""intent":"ship it""#));
+
+        let metadata = merge_auto_rebase_setting(Some(&metadata), false).unwrap();
+        assert!(!auto_rebase_on_target_update(Some(&metadata)));
+    }
+
     #[test]
     fn test_rebase_single_workspace_with_null_target_should_use_default() {
         // This test demonstrates the expected behavior: when target_branch is null,
@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::async_process::AsyncCommand;
+use crate::db::Database;
+use crate::jj;
+use crate::local_db::{self, JournalEntry};
+use crate::process_limiter::LimitedCommand;
+use crate::timestamps;
+use crate::AppState;
+
+/// Repo settings key holding the hook scripts, as a JSON object of event -> scripts
+pub const AUTOMATION_HOOKS_KEY: &str = "automation_hooks";
+
+/// Output kept in the journal per script run
+const MAX_JOURNAL_OUTPUT: usize = 64 * 1024;
+
+/// Handle used to read settings and emit `hook-finished`, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Internal events that can trigger user scripts
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    WorkspaceCreated,
+    CommitMade,
+    RebaseConflict,
+    MergeLanded,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::WorkspaceCreated => "workspace-created",
+            HookEvent::CommitMade => "commit-made",
+            HookEvent::RebaseConflict => "rebase-conflict",
+            HookEvent::MergeLanded => "merge-landed",
+        }
+    }
+}
+
+/// Scripts run for each event, in order
+pub type HookConfig = BTreeMap<HookEvent, Vec<String>>;
+
+/// What the event was about; each set field is passed to scripts as a TREQ_* variable
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub workspace_id: Option<i64>,
+    pub workspace_path: Option<String>,
+    pub branch: Option<String>,
+    pub target_branch: Option<String>,
+    pub commit_id: Option<String>,
+    pub message: Option<String>,
+    pub conflicted_files: Vec<String>,
+}
+
+/// Payload of the `hook-finished` event
+#[derive(Debug, Serialize, Clone)]
+pub struct HookRunResult {
+    pub repo_path: String,
+    pub event: HookEvent,
+    pub entry: JournalEntry,
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+pub fn parse_hooks(value: Option<&str>) -> Result<HookConfig, String> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(HookConfig::new()),
+        Some(value) => serde_json::from_str(value)
+            .map_err(|e| format!("Invalid automation hooks setting: {}", e)),
+    }
+}
+
+pub fn load_hooks(db: &Database, repo_path: &str) -> Result<HookConfig, String> {
+    let value = db
+        .get_repo_setting(repo_path, AUTOMATION_HOOKS_KEY)
+        .map_err(|e| e.to_string())?;
+    parse_hooks(value.as_deref())
+}
+
+/// Save the hooks, dropping blank scripts and events without scripts
+pub fn save_hooks(db: &Database, repo_path: &str, hooks: &HookConfig) -> Result<(), String> {
+    let hooks: HookConfig = hooks
+        .iter()
+        .map(|(event, scripts)| {
+            let scripts: Vec<String> = scripts
+                .iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            (*event, scripts)
+        })
+        .filter(|(_, scripts)| !scripts.is_empty())
+        .collect();
+    let value = serde_json::to_string(&hooks)
+        .map_err(|e| format!("Failed to serialize automation hooks: {}", e))?;
+    db.set_repo_setting(repo_path, AUTOMATION_HOOKS_KEY, &value)
+        .map_err(|e| e.to_string())
+}
+
+fn scripts_for(app: &AppHandle, repo_path: &str, event: HookEvent) -> Vec<String> {
+    let state = app.state::<AppState>();
+    let db = state.db.lock().unwrap();
+    match load_hooks(&db, repo_path) {
+        Ok(mut hooks) => hooks.remove(&event).unwrap_or_default(),
+        Err(e) => {
+            log::warn!("Ignoring automation hooks for {}: {}", repo_path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Whether any script is configured for `event`, so callers can skip work that only
+/// hooks need (e.g. listing conflicted files)
+pub fn has_hooks(repo_path: &str, event: HookEvent) -> bool {
+    APP_HANDLE
+        .get()
+        .is_some_and(|app| !scripts_for(app, repo_path, event).is_empty())
+}
+
+/// Repo path and context for an event in a workspace, or in the home repo when the path
+/// is not under `.treq/workspaces`
+pub fn workspace_context(workspace_path: &str) -> (String, HookContext) {
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    let workspace = local_db::get_workspace_by_path(&repo_path, workspace_path)
+        .ok()
+        .flatten();
+    let context = HookContext {
+        workspace_id: workspace.as_ref().map(|w| w.id),
+        workspace_path: Some(workspace_path.to_string()),
+        branch: workspace.map(|w| w.branch_name),
+        ..Default::default()
+    };
+    (repo_path, context)
+}
+
+/// Environment passed to hook scripts
+fn hook_env(repo_path: &str, event: HookEvent, context: &HookContext) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("TREQ_EVENT".to_string(), event.as_str().to_string()),
+        ("TREQ_REPO_PATH".to_string(), repo_path.to_string()),
+    ];
+    let optional = [
+        (
+            "TREQ_WORKSPACE_ID",
+            context.workspace_id.map(|id| id.to_string()),
+        ),
+        ("TREQ_WORKSPACE_PATH", context.workspace_path.clone()),
+        ("TREQ_BRANCH", context.branch.clone()),
+        ("TREQ_TARGET_BRANCH", context.target_branch.clone()),
+        ("TREQ_COMMIT_ID", context.commit_id.clone()),
+        ("TREQ_MESSAGE", context.message.clone()),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            env.push((key.to_string(), value));
+        }
+    }
+    if !context.conflicted_files.is_empty() {
+        env.push((
+            "TREQ_CONFLICTED_FILES".to_string(),
+            context.conflicted_files.join("\n"),
+        ));
+    }
+    env
+}
+
+/// Command running `script` with the platform shell, counted by the process limiter
+pub(crate) fn shell_command(script: &str) -> LimitedCommand {
+    let mut process = if cfg!(windows) {
+        let mut process = LimitedCommand::new("cmd");
+        process.arg("/C");
+        process
+    } else {
+        let mut process = LimitedCommand::new("sh");
+        process.arg("-c");
+        process
+    };
+    process.arg(script);
+    process
+}
+
+/// `shell_command` for scripts that run inside an operation, so cancelling it kills them
+pub(crate) fn async_shell_command(script: &str) -> AsyncCommand {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut process = AsyncCommand::new(shell);
+    process.arg(flag).arg(script);
+    process
+}
+
+/// Run one script with the shell, returning success and combined output
+fn run_script(script: &str, working_dir: &str, env: &[(String, String)]) -> (bool, String) {
+    let output = shell_command(script)
+        .current_dir(working_dir)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .output();
+
+    match output {
+        Ok(output) => {
+            let mut combined = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            if combined.len() > MAX_JOURNAL_OUTPUT {
+                let mut end = MAX_JOURNAL_OUTPUT;
+                while !combined.is_char_boundary(end) {
+                    end -= 1;
+                }
+                combined.truncate(end);
+                combined.push_str("\n[output truncated]");
+            }
+            (output.status.success(), combined)
+        }
+        Err(e) => (false, format!("Failed to run script: {}", e)),
+    }
+}
+
+/// Run the scripts configured for `event` in the background, recording each run in the
+/// repo's operation journal and emitting `hook-finished`. Scripts run in the workspace
+/// when there is one, otherwise in the repo.
+pub fn fire(repo_path: &str, event: HookEvent, context: HookContext) {
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    let scripts = scripts_for(app, repo_path, event);
+    if scripts.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    let repo_path = repo_path.to_string();
+    std::thread::spawn(move || {
+        let env = hook_env(&repo_path, event, &context);
+        let working_dir = context
+            .workspace_path
+            .clone()
+            .unwrap_or_else(|| repo_path.clone());
+
+        for script in scripts {
+            let started = Instant::now();
+            let (success, output) = run_script(&script, &working_dir, &env);
+            if !success {
+                log::warn!("{} hook '{}' failed: {}", event.as_str(), script, output);
+            }
+
+            let mut entry = JournalEntry {
+                id: 0,
+                kind: "hook".to_string(),
+                name: event.as_str().to_string(),
+                workspace_id: context.workspace_id,
+                command: Some(script),
+                success,
+                output,
+                duration_ms: Some(started.elapsed().as_millis() as i64),
+                created_at: timestamps::now(),
+            };
+            match local_db::add_journal_entry(&repo_path, &entry) {
+                Ok(id) => entry.id = id,
+                Err(e) => log::warn!("Failed to journal {} hook: {}", event.as_str(), e),
+            }
+            let _ = app.emit(
+                "hook-finished",
+                HookRunResult {
+                    repo_path: repo_path.clone(),
+                    event,
+                    entry,
+                },
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_hooks() {
+        let hooks =
+            parse_hooks(Some(r#"{"commit-made":["make lint"],"merge-landed":[]}"#)).unwrap();
+        assert_eq!(
+            hooks.get(&HookEvent::CommitMade),
+            Some(&vec!["make lint".to_string()])
+        );
+        assert!(parse_hooks(None).unwrap().is_empty());
+        assert!(parse_hooks(Some(r#"{"pushed":["x"]}"#)).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_script_receives_context_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let context = HookContext {
+            workspace_id: Some(7),
+            branch: Some("treq/login".to_string()),
+            conflicted_files: vec!["a.rs".to_string(), "b.rs".to_string()],
+            ..Default::default()
+        };
+        let env = hook_env("/repo", HookEvent::RebaseConflict, &context);
+
+        let (success, output) = run_script(
+            "echo \"$TREQ_EVENT $TREQ_WORKSPACE_ID $TREQ_BRANCH ${TREQ_COMMIT_ID:-none}\"; echo \"$TREQ_CONFLICTED_FILES\"",
+            temp_dir.path().to_str().unwrap(),
+            &env,
+        );
+        assert!(success);
+        assert_eq!(output, "rebase-conflict 7 treq/login none\na.rs\nb.rs\n");
+
+        let (success, output) = run_script("echo oops >&2; exit 3", "/", &env);
+        assert!(!success);
+        assert_eq!(output, "oops\n");
+    }
+}
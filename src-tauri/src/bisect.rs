@@ -0,0 +1,235 @@
+//! Guided `git bisect`: start a session between a good and a bad commit, mark each
+//! checked out candidate, and report the candidate and how many steps are left.
+//! git keeps the session state in refs/bisect and BISECT_* files, so a session started
+//! here can be continued from the CLI and the other way around.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::git_ops::{self, run_git, validate_rev_arg, BranchCommitInfo, GitError};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BisectVerdict {
+    Good,
+    Bad,
+    /// The candidate can't be tested, e.g. it doesn't build
+    Skip,
+}
+
+impl BisectVerdict {
+    fn as_arg(self) -> &'static str {
+        match self {
+            BisectVerdict::Good => "good",
+            BisectVerdict::Bad => "bad",
+            BisectVerdict::Skip => "skip",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BisectStatus {
+    pub active: bool,
+    /// Commit checked out for testing; None once the first bad commit is found
+    pub candidate: Option<BranchCommitInfo>,
+    pub first_bad: Option<BranchCommitInfo>,
+    pub bad: Option<String>,
+    pub good: Vec<String>,
+    pub skipped: Vec<String>,
+    /// Commits that could still be the first bad one and have not been skipped
+    pub remaining_revisions: usize,
+    /// Estimated verdicts still needed to find the first bad commit
+    pub remaining_steps: usize,
+}
+
+/// Commits recorded under refs/bisect, as (bad, good, skipped)
+#[derive(Debug, Default, PartialEq)]
+struct BisectRefs {
+    bad: Option<String>,
+    good: Vec<String>,
+    skipped: Vec<String>,
+}
+
+/// Parse `git for-each-ref --format='%(objectname) %(refname)' refs/bisect/` output
+fn parse_bisect_refs(output: &str) -> BisectRefs {
+    let mut refs = BisectRefs::default();
+    for line in output.lines() {
+        let Some((commit, refname)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        let commit = commit.to_string();
+        match refname.strip_prefix("refs/bisect/") {
+            Some("bad") => refs.bad = Some(commit),
+            Some(name) if name.starts_with("good-") => refs.good.push(commit),
+            Some(name) if name.starts_with("skip-") => refs.skipped.push(commit),
+            _ => {}
+        }
+    }
+    refs
+}
+
+/// Verdicts needed in the worst case to narrow `candidates` commits down to one
+fn estimate_steps(candidates: usize) -> usize {
+    candidates.max(1).next_power_of_two().trailing_zeros() as usize
+}
+
+fn bisect_in_progress(repo_path: &str) -> bool {
+    run_git(repo_path, &["rev-parse", "--git-path", "BISECT_START"])
+        .map(|p| Path::new(repo_path).join(p.trim()).exists())
+        .unwrap_or(false)
+}
+
+fn require_bisect(repo_path: &str) -> Result<(), GitError> {
+    if !bisect_in_progress(repo_path) {
+        return Err(GitError::Other("No bisect is in progress".to_string()));
+    }
+    Ok(())
+}
+
+/// Start bisecting between a known `good` and `bad` commit and check out the first
+/// candidate. Fails when a bisect is already in progress.
+pub fn bisect_start(repo_path: &str, good: &str, bad: &str) -> Result<BisectStatus, GitError> {
+    validate_rev_arg(good, "good commit")?;
+    validate_rev_arg(bad, "bad commit")?;
+    if bisect_in_progress(repo_path) {
+        return Err(GitError::Other(
+            "A bisect is already in progress; abort it before starting another".to_string(),
+        ));
+    }
+
+    run_git(repo_path, &["bisect", "start", bad, good, "--"])
+        .map_err(|e| e.context("Failed to start bisect"))?;
+    bisect_status(repo_path)
+}
+
+/// Record the verdict for the checked out candidate and move on to the next one
+pub fn bisect_mark(repo_path: &str, verdict: BisectVerdict) -> Result<BisectStatus, GitError> {
+    require_bisect(repo_path)?;
+    run_git(repo_path, &["bisect", verdict.as_arg()])
+        .map_err(|e| e.context(&format!("Failed to mark commit {}", verdict.as_arg())))?;
+    bisect_status(repo_path)
+}
+
+/// Current bisect session, or an inactive status when none is in progress
+pub fn bisect_status(repo_path: &str) -> Result<BisectStatus, GitError> {
+    if !bisect_in_progress(repo_path) {
+        return Ok(BisectStatus::default());
+    }
+
+    let refs = parse_bisect_refs(&run_git(
+        repo_path,
+        &[
+            "for-each-ref",
+            "--format=%(objectname) %(refname)",
+            "refs/bisect/",
+        ],
+    )?);
+    let mut status = BisectStatus {
+        active: true,
+        ..Default::default()
+    };
+
+    // Until both ends are marked, git has not picked a candidate yet
+    if let (Some(bad), false) = (&refs.bad, refs.good.is_empty()) {
+        let mut args = vec!["rev-list", bad.as_str(), "--not"];
+        args.extend(refs.good.iter().map(String::as_str));
+        let candidates = run_git(repo_path, &args)?;
+        let testable = candidates
+            .lines()
+            .map(str::trim)
+            .filter(|c| !c.is_empty() && *c != bad && !refs.skipped.iter().any(|s| s == c))
+            .count();
+
+        status.remaining_revisions = testable;
+        status.remaining_steps = estimate_steps(testable + 1);
+        if testable > 0 {
+            status.candidate = Some(git_ops::git_commit_info(repo_path, "HEAD")?);
+        } else if candidates.lines().filter(|c| !c.trim().is_empty()).count() == 1 {
+            // Only the bad commit is left; with skipped commits left git can't tell
+            status.first_bad = Some(git_ops::git_commit_info(repo_path, bad)?);
+        }
+    }
+
+    status.bad = refs.bad;
+    status.good = refs.good;
+    status.skipped = refs.skipped;
+    Ok(status)
+}
+
+/// End the bisect session and return to the branch it was started from
+pub fn bisect_abort(repo_path: &str) -> Result<(), GitError> {
+    require_bisect(repo_path)?;
+    run_git(repo_path, &["bisect", "reset"])
+        .map(|_| ())
+        .map_err(|e| e.context("Failed to end bisect"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::tests::{commit_file, setup_git_repo};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_bisect_refs_and_estimate_steps() {
+        let refs = parse_bisect_refs(
+            "aaa refs/bisect/bad\nbbb refs/bisect/good-bbb\nccc refs/bisect/skip-ccc\n",
+        );
+        assert_eq!(
+            refs,
+            BisectRefs {
+                bad: Some("aaa".to_string()),
+                good: vec!["bbb".to_string()],
+                skipped: vec!["ccc".to_string()],
+            }
+        );
+        assert_eq!(estimate_steps(1), 0);
+        assert_eq!(estimate_steps(2), 1);
+        assert_eq!(estimate_steps(5), 3);
+        assert_eq!(estimate_steps(8), 3);
+    }
+
+    #[test]
+    fn test_bisect_finds_first_bad_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = &setup_git_repo(&temp_dir);
+        for i in 0..8 {
+            let state = if i < 5 { "ok" } else { "broken" };
+            fs::write(temp_dir.path().join("state.txt"), state).unwrap();
+            run_git(repo, &["add", "state.txt"]).unwrap();
+            commit_file(repo, "n.txt", &i.to_string(), &format!("Commit {}", i));
+        }
+
+        assert!(!bisect_status(repo).unwrap().active);
+        assert!(bisect_mark(repo, BisectVerdict::Good).is_err());
+
+        let mut status = bisect_start(repo, "HEAD~7", "HEAD").unwrap();
+        assert!(status.active);
+        assert_eq!(status.remaining_revisions, 6);
+        assert_eq!(status.remaining_steps, 3);
+        assert!(bisect_start(repo, "HEAD~7", "HEAD").is_err());
+
+        let mut verdicts = 0;
+        while status.first_bad.is_none() {
+            assert!(status.candidate.is_some());
+            let state = fs::read_to_string(temp_dir.path().join("state.txt")).unwrap();
+            let verdict = if state == "ok" {
+                BisectVerdict::Good
+            } else {
+                BisectVerdict::Bad
+            };
+            status = bisect_mark(repo, verdict).unwrap();
+            verdicts += 1;
+            assert!(verdicts <= 3);
+        }
+        assert_eq!(status.first_bad.unwrap().message, "Commit 5");
+        assert!(status.candidate.is_none());
+        assert_eq!(status.remaining_steps, 0);
+
+        bisect_abort(repo).unwrap();
+        assert!(!bisect_status(repo).unwrap().active);
+        let head = run_git(repo, &["log", "-1", "--format=%s"]).unwrap();
+        assert_eq!(head.trim(), "Commit 7");
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::jj;
+
+/// Repo settings key for the branch name pattern, with `{name}` as the placeholder
+pub const BRANCH_NAME_PATTERN_KEY: &str = "branch_name_pattern";
+
+pub const DEFAULT_BRANCH_NAME_PATTERN: &str = "treq/{name}";
+
+/// Longest slug inserted into the pattern
+const MAX_SLUG_LEN: usize = 50;
+
+/// Suffixes tried before giving up on finding a free name
+const MAX_COLLISION_SUFFIX: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BranchNameSuggestion {
+    pub branch_name: String,
+    /// The pattern applied to the slug, before any collision suffix
+    pub base_name: String,
+    /// `base_name` already existed locally or on the remote
+    pub collided: bool,
+}
+
+/// Lowercase, keep `[a-z0-9-]`, turn whitespace into hyphens; same rules as the
+/// frontend's sanitizeForBranchName
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.to_lowercase().chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_matches('-').chars().take(MAX_SLUG_LEN).collect();
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "unnamed".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Append -2, -3, ... to `base` until `is_taken` returns false
+fn first_available(
+    base: &str,
+    mut is_taken: impl FnMut(&str) -> Result<bool, String>,
+) -> Result<String, String> {
+    if !is_taken(base)? {
+        return Ok(base.to_string());
+    }
+    for n in 2..=MAX_COLLISION_SUFFIX {
+        let candidate = format!("{}-{}", base, n);
+        if !is_taken(&candidate)? {
+            return Ok(candidate);
+        }
+    }
+    Err(format!("No free branch name found for {}", base))
+}
+
+/// The repo's configured branch name pattern, or the default
+pub fn branch_name_pattern(db: &Database, repo_path: &str) -> String {
+    db.get_repo_setting(repo_path, BRANCH_NAME_PATTERN_KEY)
+        .ok()
+        .flatten()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_BRANCH_NAME_PATTERN.to_string())
+}
+
+/// Branch name for a new workspace described by `text`, skipping names that exist
+/// locally or on the remote
+pub fn suggest_workspace_branch_name(
+    repo_path: &str,
+    pattern: &str,
+    text: &str,
+) -> Result<BranchNameSuggestion, String> {
+    let base_name = pattern.replace("{name}", &slugify(text));
+
+    let branch_name = first_available(&base_name, |candidate| {
+        let status = jj::check_branch_exists(repo_path, candidate, None).map_err(|e| e.to_string())?;
+        Ok(status.local_exists || status.remote_exists)
+    })?;
+
+    Ok(BranchNameSuggestion {
+        collided: branch_name != base_name,
+        branch_name,
+        base_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Add dark mode!"), "add-dark-mode");
+        assert_eq!(slugify("  Fix  --  the #42 bug "), "fix-the-42-bug");
+        assert_eq!(slugify("Ünïcode only ✓"), "ncode-only");
+        assert_eq!(slugify("!!!"), "unnamed");
+        let long = slugify(&"word ".repeat(20));
+        assert_eq!(long.len(), 49);
+        assert!(!long.ends_with('-'));
+    }
+
+    #[test]
+    fn test_first_available_appends_suffix() {
+        let taken = ["treq/login", "treq/login-2"];
+        let name = first_available("treq/login", |c| Ok(taken.contains(&c))).unwrap();
+        assert_eq!(name, "treq/login-3");
+        let name = first_available("treq/signup", |c| Ok(taken.contains(&c))).unwrap();
+        assert_eq!(name, "treq/signup");
+        assert!(first_available("x", |_| Ok(true)).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+use crate::forge::{self, CheckRun, ForgeRepo};
+use crate::git_ops;
+use crate::local_db::{self, CachedChecks};
+use crate::timestamps;
+
+/// Handle used to emit `checks-changed`, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Cached results are refetched after this long while checks are still running
+const PENDING_TTL_SECS: i64 = 60;
+
+/// Settled results only change when jobs are re-run, so they are kept longer
+const SETTLED_TTL_SECS: i64 = 600;
+
+/// CI results for one commit
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CommitChecks {
+    pub sha: String,
+    /// Combined state: "success", "pending", "failure" or "error"; None when no checks
+    /// report
+    pub state: Option<String>,
+    pub checks: Vec<CheckRun>,
+    pub fetched_at: String,
+    pub from_cache: bool,
+}
+
+/// Payload of the `checks-changed` event
+#[derive(Debug, Serialize, Clone)]
+pub struct ChecksChanged {
+    pub repo_path: String,
+    pub branch: String,
+    pub checks: CommitChecks,
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+/// Commit of the pushed branch, which is what CI ran on
+fn remote_branch_sha(repo_path: &str, branch: &str) -> Option<String> {
+    git_ops::validate_rev_arg(branch, "branch").ok()?;
+    let rev = format!("refs/remotes/origin/{}^{{commit}}", branch);
+    git_ops::run_git(repo_path, &["rev-parse", "--verify", "--quiet", &rev])
+        .ok()
+        .map(|sha| sha.trim().to_string())
+}
+
+/// Resolve a branch name or commit id, preferring the pushed branch
+fn resolve_sha(repo_path: &str, branch_or_sha: &str) -> Result<String, String> {
+    if let Some(sha) = remote_branch_sha(repo_path, branch_or_sha) {
+        return Ok(sha);
+    }
+    git_ops::validate_rev_arg(branch_or_sha, "branch or commit").map_err(|e| e.to_string())?;
+    let rev = format!("{}^{{commit}}", branch_or_sha);
+    git_ops::run_git(repo_path, &["rev-parse", "--verify", "--quiet", &rev])
+        .map(|sha| sha.trim().to_string())
+        .map_err(|_| format!("Unknown branch or commit: {}", branch_or_sha))
+}
+
+fn is_fresh(cached: &CachedChecks) -> bool {
+    let ttl = if cached.state.as_deref() == Some("pending") {
+        PENDING_TTL_SECS
+    } else {
+        SETTLED_TTL_SECS
+    };
+    timestamps::parse(&cached.fetched_at)
+        .is_some_and(|fetched| (Utc::now() - fetched).num_seconds() < ttl)
+}
+
+fn from_cache(cached: CachedChecks) -> Result<CommitChecks, String> {
+    let checks = serde_json::from_str(&cached.checks)
+        .map_err(|e| format!("Failed to parse cached checks: {}", e))?;
+    Ok(CommitChecks {
+        sha: cached.sha,
+        state: cached.state,
+        checks,
+        fetched_at: cached.fetched_at,
+        from_cache: true,
+    })
+}
+
+/// Fetch the checks of `sha` from the forge and store them in the repo's cache
+fn fetch_checks(repo_path: &str, repo: &ForgeRepo, sha: &str) -> Result<CommitChecks, String> {
+    let checks = forge::provider_for(repo.provider).get_checks(repo, sha)?;
+    let cached = CachedChecks {
+        sha: sha.to_string(),
+        state: forge::combined_state(&checks),
+        checks: serde_json::to_string(&checks)
+            .map_err(|e| format!("Failed to serialize checks: {}", e))?,
+        fetched_at: timestamps::now(),
+    };
+    local_db::save_cached_checks(repo_path, &cached)?;
+    Ok(CommitChecks {
+        from_cache: false,
+        ..from_cache(cached)?
+    })
+}
+
+/// CI checks of a branch or commit, served from the local cache while fresh
+pub fn get_checks(repo_path: &str, branch_or_sha: &str) -> Result<CommitChecks, String> {
+    let repo = forge::detect_provider(repo_path)?.ok_or_else(|| {
+        "The origin remote is not hosted on GitHub, GitLab or Bitbucket".to_string()
+    })?;
+    let sha = resolve_sha(repo_path, branch_or_sha)?;
+    if let Some(cached) = local_db::get_cached_checks(repo_path, &sha)?.filter(is_fresh) {
+        return from_cache(cached);
+    }
+    fetch_checks(repo_path, &repo, &sha)
+}
+
+/// Refresh the checks of every pushed workspace branch after a fetch, emitting
+/// `checks-changed` for branches whose results differ from the cached ones. Does
+/// nothing for repos that are not on a supported forge.
+pub fn refresh_workspace_checks(repo_path: &str) {
+    let Ok(Some(repo)) = forge::detect_provider(repo_path) else {
+        return;
+    };
+    let Ok(workspaces) = local_db::get_workspaces(repo_path) else {
+        return;
+    };
+
+    for workspace in workspaces {
+        let Some(sha) = remote_branch_sha(repo_path, &workspace.branch_name) else {
+            continue;
+        };
+        let previous = local_db::get_cached_checks(repo_path, &sha).ok().flatten();
+        if previous.as_ref().is_some_and(is_fresh) {
+            continue;
+        }
+        let checks = match fetch_checks(repo_path, &repo, &sha) {
+            Ok(checks) => checks,
+            Err(e) => {
+                eprintln!(
+                    "Failed to refresh checks of {}: {}",
+                    workspace.branch_name, e
+                );
+                continue;
+            }
+        };
+
+        let unchanged = previous.is_some_and(|previous| {
+            previous.state == checks.state
+                && serde_json::from_str::<Vec<CheckRun>>(&previous.checks)
+                    .ok()
+                    .as_ref()
+                    == Some(&checks.checks)
+        });
+        if unchanged {
+            continue;
+        }
+        if let Some(app) = APP_HANDLE.get() {
+            let _ = app.emit(
+                "checks-changed",
+                &ChecksChanged {
+                    repo_path: repo_path.to_string(),
+                    branch: workspace.branch_name,
+                    checks,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_pending_results_expire_sooner() {
+        let cached = |state: &str, age_secs: i64| CachedChecks {
+            sha: "abc".to_string(),
+            state: Some(state.to_string()),
+            checks: "[]".to_string(),
+            fetched_at: timestamps::format(Utc::now() - Duration::seconds(age_secs)),
+        };
+        assert!(is_fresh(&cached("pending", 10)));
+        assert!(!is_fresh(&cached("pending", 120)));
+        assert!(is_fresh(&cached("success", 120)));
+        assert!(!is_fresh(&cached("failure", 900)));
+    }
+}
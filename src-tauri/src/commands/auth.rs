@@ -0,0 +1,17 @@
+use crate::askpass;
+
+/// Answer an `auth-prompt` event; `response: None` cancels the git command
+#[tauri::command]
+pub fn submit_auth_response(
+    id: u64,
+    response: Option<String>,
+    remember: Option<bool>,
+) -> Result<(), String> {
+    askpass::submit_response(id, response, remember.unwrap_or(false))
+}
+
+/// Forget credentials remembered during this app session
+#[tauri::command]
+pub fn clear_auth_cache() {
+    askpass::clear_cache();
+}
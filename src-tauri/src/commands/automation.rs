@@ -0,0 +1,34 @@
+use crate::automation::{self, HookConfig};
+use crate::local_db::{self, JournalEntry};
+use crate::AppState;
+use tauri::State;
+
+const DEFAULT_JOURNAL_LIMIT: usize = 100;
+
+#[tauri::command]
+pub fn get_automation_hooks(
+    state: State<AppState>,
+    repo_path: String,
+) -> Result<HookConfig, String> {
+    let db = state.db.lock().unwrap();
+    automation::load_hooks(&db, &repo_path)
+}
+
+#[tauri::command]
+pub fn set_automation_hooks(
+    state: State<AppState>,
+    repo_path: String,
+    hooks: HookConfig,
+) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    automation::save_hooks(&db, &repo_path, &hooks)
+}
+
+/// Recent operation journal entries (hook runs), newest first
+#[tauri::command]
+pub fn get_operation_journal(
+    repo_path: String,
+    limit: Option<usize>,
+) -> Result<Vec<JournalEntry>, String> {
+    local_db::get_journal_entries(&repo_path, limit.unwrap_or(DEFAULT_JOURNAL_LIMIT))
+}
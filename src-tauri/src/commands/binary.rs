@@ -10,6 +10,8 @@ pub struct BinaryPathsResponse {
     pub git: Option<String>,
     pub jj: Option<String>,
     pub claude: Option<String>,
+    /// GitHub CLI, used for forge API requests
+    pub gh: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,12 +21,12 @@ pub struct EditorAppsResponse {
     pub zed: bool,
 }
 
-/// Detect and cache binary paths for required binaries (git, jj, claude)
+/// Detect and cache binary paths for required binaries (git, jj, claude, gh)
 #[tauri::command]
 pub fn detect_binaries(state: State<'_, AppState>) -> Result<BinaryPathsResponse, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
 
-    let binaries = vec!["git", "jj", "claude"];
+    let binaries = vec!["git", "jj", "claude", "gh"];
     let mut detected_paths = HashMap::new();
 
     for binary in &binaries {
@@ -50,12 +52,13 @@ pub fn detect_binaries(state: State<'_, AppState>) -> Result<BinaryPathsResponse
         git: detected_paths.get("git").cloned(),
         jj: detected_paths.get("jj").cloned(),
         claude: detected_paths.get("claude").cloned(),
+        gh: detected_paths.get("gh").cloned(),
     })
 }
 
 /// Load cached binary paths from database on startup
 pub fn load_cached_binary_paths(db: &Database) -> HashMap<String, String> {
-    let binaries = vec!["git", "jj", "claude"];
+    let binaries = vec!["git", "jj", "claude", "gh"];
     let mut paths = HashMap::new();
 
     for binary in binaries {
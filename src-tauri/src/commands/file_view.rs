@@ -1,37 +1,69 @@
+use crate::vcs;
 use crate::{db::FileView, AppState};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use tauri::State;
 
+/// HEAD commit that viewed state is scoped to; empty before the first commit
+fn view_head(workspace_path: &str) -> String {
+    vcs::backend_for_workspace(workspace_path)
+        .head_commit(workspace_path)
+        .unwrap_or_default()
+}
+
+/// Mark a file viewed in the diff of the workspace against `base_ref`. The mark only
+/// holds until the workspace HEAD moves.
 #[tauri::command]
 pub fn mark_file_viewed(
     state: State<AppState>,
     workspace_path: String,
+    base_ref: String,
     file_path: String,
     content_hash: String,
 ) -> Result<(), String> {
+    let head_sha = view_head(&workspace_path);
     let db = state.db.lock().unwrap();
-    db.mark_file_viewed(&workspace_path, &file_path, &content_hash)
-        .map_err(|e| e.to_string())
+    db.mark_file_viewed(
+        &workspace_path,
+        &base_ref,
+        &head_sha,
+        &file_path,
+        &content_hash,
+    )
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn unmark_file_viewed(
     state: State<AppState>,
     workspace_path: String,
+    base_ref: String,
     file_path: String,
 ) -> Result<(), String> {
+    let head_sha = view_head(&workspace_path);
     let db = state.db.lock().unwrap();
-    db.unmark_file_viewed(&workspace_path, &file_path)
+    db.unmark_file_viewed(&workspace_path, &base_ref, &head_sha, &file_path)
         .map_err(|e| e.to_string())
 }
 
+/// Viewed files of the diff against `base_ref` at the current HEAD. Files whose hash
+/// in `current_hashes` no longer matches are un-marked.
 #[tauri::command]
 pub fn get_viewed_files(
     state: State<AppState>,
     workspace_path: String,
+    base_ref: String,
+    current_hashes: Option<HashMap<String, String>>,
 ) -> Result<Vec<FileView>, String> {
+    let head_sha = view_head(&workspace_path);
     let db = state.db.lock().unwrap();
-    db.get_viewed_files(&workspace_path)
-        .map_err(|e| e.to_string())
+    db.get_viewed_files(
+        &workspace_path,
+        &base_ref,
+        &head_sha,
+        &current_hashes.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -43,3 +75,82 @@ pub fn clear_all_viewed_files(
     db.clear_all_viewed_files(&workspace_path)
         .map_err(|e| e.to_string())
 }
+
+/// Review state of one hunk of a file
+#[derive(Debug, Serialize, Clone)]
+pub struct HunkReviewState {
+    pub hunk_id: String,
+    pub content_hash: String,
+    pub summary: String,
+    pub viewed: bool,
+}
+
+/// Per-file rollup of reviewed hunks
+#[derive(Debug, Serialize, Clone)]
+pub struct FileHunkReview {
+    pub file_path: String,
+    pub total_hunks: usize,
+    pub viewed_hunks: usize,
+    pub hunks: Vec<HunkReviewState>,
+}
+
+#[tauri::command]
+pub fn mark_hunk_viewed(
+    state: State<AppState>,
+    workspace_path: String,
+    file_path: String,
+    content_hash: String,
+) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    db.mark_hunk_viewed(&workspace_path, &file_path, &content_hash)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn unmark_hunk_viewed(
+    state: State<AppState>,
+    workspace_path: String,
+    file_path: String,
+    content_hash: String,
+) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    db.unmark_hunk_viewed(&workspace_path, &file_path, &content_hash)
+        .map_err(|e| e.to_string())
+}
+
+/// Current hunks of a file with their review state
+#[tauri::command]
+pub fn get_file_hunk_review(
+    state: State<AppState>,
+    workspace_path: String,
+    file_path: String,
+) -> Result<FileHunkReview, String> {
+    // Compute the diff before taking the db lock
+    let hunks = vcs::backend_for_workspace(&workspace_path)
+        .file_hunks(&workspace_path, &file_path)?
+        .hunks;
+    let viewed: HashSet<String> = {
+        let db = state.db.lock().unwrap();
+        db.get_viewed_hunks(&workspace_path, &file_path)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect()
+    };
+
+    let hunks: Vec<HunkReviewState> = hunks
+        .into_iter()
+        .map(|hunk| HunkReviewState {
+            viewed: viewed.contains(&hunk.content_hash),
+            hunk_id: hunk.id,
+            content_hash: hunk.content_hash,
+            summary: hunk.summary,
+        })
+        .collect();
+
+    Ok(FileHunkReview {
+        file_path,
+        total_hunks: hunks.len(),
+        viewed_hunks: hunks.iter().filter(|h| h.viewed).count(),
+        hunks,
+    })
+}
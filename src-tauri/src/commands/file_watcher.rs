@@ -1,16 +1,162 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tauri::{AppHandle, Emitter, State};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
 
+use crate::commands::workspace;
+use crate::commit_graph;
+use crate::db::Database;
+use crate::file_indexer;
+use crate::git_cache;
+use crate::instance_lock;
+use crate::jj;
+use crate::result_cache::{self, CacheKind};
+use crate::resync;
+use crate::timestamps;
 use crate::AppState;
 
+/// Settings key (global and per-repo) for how long file events are batched, in milliseconds
+pub const WATCHER_DEBOUNCE_MS_KEY: &str = "watcher_debounce_ms";
+
+/// Settings key (global and per-repo) for paths the watcher ignores, one gitignore-style
+/// pattern per line, on top of the workspace's .gitignore
+pub const WATCHER_IGNORE_KEY: &str = "watcher_ignore";
+
+const DEFAULT_DEBOUNCE_MS: u64 = 1000;
+
+/// Minimum time between automatic restarts of one watcher, so a workspace that keeps
+/// overflowing doesn't end up in a restart loop
+const RESTART_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WatcherState {
+    Healthy,
+    /// Events were dropped; the workspace was rescanned and the watcher is restarting.
+    /// Cleared by the next batch delivered in full.
+    Degraded,
+    /// The watcher could not be restarted, so the workspace is no longer watched
+    Failed,
+}
+
+/// Health of a workspace's watcher, returned by `get_watcher_status`
+#[derive(Debug, Serialize, Clone)]
+pub struct WatcherHealth {
+    pub workspace_id: i64,
+    pub workspace_path: String,
+    pub state: WatcherState,
+    /// Times the OS reported dropped events, e.g. an inotify queue overflow
+    pub overflow_count: u32,
+    pub error_count: u32,
+    pub restart_count: u32,
+    pub last_error: Option<String>,
+    pub last_event_at: Option<String>,
+    pub last_degraded_at: Option<String>,
+    #[serde(skip)]
+    repo_path: String,
+    #[serde(skip)]
+    last_restart: Option<Instant>,
+}
+
+impl WatcherHealth {
+    fn new(workspace_id: i64, workspace_path: &str, repo_path: &str) -> Self {
+        Self {
+            workspace_id,
+            workspace_path: workspace_path.to_string(),
+            state: WatcherState::Healthy,
+            overflow_count: 0,
+            error_count: 0,
+            restart_count: 0,
+            last_error: None,
+            last_event_at: None,
+            last_degraded_at: None,
+            repo_path: repo_path.to_string(),
+            last_restart: None,
+        }
+    }
+}
+
+/// Payload of the `watcher-degraded` event
+#[derive(Debug, Serialize, Clone)]
+pub struct WatcherDegraded {
+    pub workspace_id: i64,
+    pub workspace_path: String,
+    pub reason: String,
+}
+
+/// Why a watcher stopped being trustworthy
+enum Degradation {
+    Overflow,
+    Errors(String),
+}
+
+type HealthMap = Arc<Mutex<HashMap<String, WatcherHealth>>>;
+
+/// Debounce and ignore rules for a workspace's watcher
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatcherConfig {
+    pub debounce: Duration,
+    pub ignore_patterns: Vec<String>,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(DEFAULT_DEBOUNCE_MS),
+            ignore_patterns: Vec::new(),
+        }
+    }
+}
+
+impl WatcherConfig {
+    /// Load the watcher settings of a repo, falling back to the global settings
+    pub fn load(db: &Database, repo_path: &str) -> Self {
+        let setting = |key: &str| {
+            db.get_repo_setting(repo_path, key)
+                .ok()
+                .flatten()
+                .or_else(|| db.get_setting(key).ok().flatten())
+        };
+        Self {
+            debounce: parse_debounce_setting(setting(WATCHER_DEBOUNCE_MS_KEY).as_deref()),
+            ignore_patterns: setting(WATCHER_IGNORE_KEY)
+                .map(|value| {
+                    value
+                        .lines()
+                        .map(|l| l.trim().to_string())
+                        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Parse the debounce setting, clamped to 100ms..30s; missing or invalid values use 1s
+pub fn parse_debounce_setting(value: Option<&str>) -> Duration {
+    let ms = value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_DEBOUNCE_MS)
+        .clamp(100, 30_000);
+    Duration::from_millis(ms)
+}
+
+/// An active watcher and the workspace it belongs to
+struct WorkspaceWatcher {
+    workspace_id: i64,
+    config: WatcherConfig,
+    _debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+}
+
 pub struct WatcherManager {
-    watchers: Arc<Mutex<HashMap<String, Debouncer<RecommendedWatcher, FileIdMap>>>>,
+    watchers: Arc<Mutex<HashMap<String, WorkspaceWatcher>>>,
     app_handle: Arc<Mutex<Option<AppHandle>>>,
+    health: HealthMap,
 }
 
 impl WatcherManager {
@@ -18,6 +164,7 @@ impl WatcherManager {
         Self {
             watchers: Arc::new(Mutex::new(HashMap::new())),
             app_handle: Arc::new(Mutex::new(None)),
+            health: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -30,6 +177,7 @@ impl WatcherManager {
         &self,
         workspace_id: i64,
         workspace_path: String,
+        config: WatcherConfig,
     ) -> Result<(), String> {
         let mut watchers = self.watchers.lock().unwrap();
 
@@ -42,19 +190,86 @@ impl WatcherManager {
         }
 
         let app_handle = self.app_handle.clone();
+        let health = self.health.clone();
         let ws_path = workspace_path.clone();
         let ws_id = workspace_id;
+        let repo_path = jj::derive_repo_path_from_workspace(&workspace_path)
+            .unwrap_or_else(|| workspace_path.clone());
+        let health_repo_path = repo_path.clone();
+        let ignore = Mutex::new(build_ignore(&workspace_path, &config.ignore_patterns));
+        let ignore_patterns = config.ignore_patterns.clone();
+        let root_gitignore = path.join(".gitignore");
 
-        // Create debounced watcher with 1s debounce
         let mut debouncer = new_debouncer(
-            Duration::from_millis(1000),
+            config.debounce,
             None,
             move |result: DebounceEventResult| match result {
                 Ok(events) => {
-                    let changed_paths: Vec<String> = events
+                    if let Some(h) = health.lock().unwrap().get_mut(&ws_path) {
+                        h.last_event_at = Some(timestamps::now());
+                    }
+
+                    // The OS dropped events (e.g. inotify queue overflow); only a full rescan
+                    // brings the caches back in sync, and it covers everything in this batch
+                    if events.iter().any(|e| e.need_rescan()) {
+                        recover(
+                            &app_handle,
+                            &health,
+                            ws_id,
+                            &ws_path,
+                            &repo_path,
+                            Degradation::Overflow,
+                        );
+                        return;
+                    }
+                    mark_recovered(&health, &ws_path);
+
+                    // Ref moves from outside the app (e.g. a terminal) update the graph
+                    if events
                         .iter()
                         .flat_map(|e| e.paths.iter())
-                        .filter(|p| !is_ignored_path(p))
+                        .any(|p| commit_graph::is_refs_change(&p.to_string_lossy()))
+                    {
+                        commit_graph::notify_changed(&ws_path);
+                    }
+
+                    // Pick up edits to .gitignore before filtering this batch
+                    if events
+                        .iter()
+                        .flat_map(|e| e.paths.iter())
+                        .any(|p| *p == root_gitignore)
+                    {
+                        *ignore.lock().unwrap() = build_ignore(&ws_path, &ignore_patterns);
+                    }
+
+                    let changed: Vec<PathBuf> = {
+                        let ignore = ignore.lock().unwrap();
+                        events
+                            .iter()
+                            .flat_map(|e| e.paths.iter())
+                            .filter(|p| !is_ignored_path(p) && !is_gitignored(&ignore, p))
+                            .cloned()
+                            .collect()
+                    };
+
+                    if !changed.is_empty() {
+                        git_cache::bump_generation(&ws_path);
+                        result_cache::invalidate(&ws_path, &[CacheKind::Status, CacheKind::Hunks]);
+                    }
+
+                    if workspace::is_workspace_indexed(&ws_path) {
+                        if let Err(e) = file_indexer::update_workspace_files(
+                            &repo_path,
+                            Some(ws_id),
+                            &ws_path,
+                            &changed,
+                        ) {
+                            log::warn!("Failed to update file index for {}: {}", ws_path, e);
+                        }
+                    }
+
+                    let changed_paths: Vec<String> = changed
+                        .iter()
                         .map(|p| p.to_string_lossy().to_string())
                         .collect();
 
@@ -70,6 +285,20 @@ impl WatcherManager {
                 }
                 Err(errors) => {
                     log::error!("Watcher errors for {}: {:?}", ws_path, errors);
+                    let message = errors
+                        .iter()
+                        .map(|e| e.to_string())
+                        .collect::<Vec<_>>()
+                        .join("; ");
+                    // Events may have been lost
+                    recover(
+                        &app_handle,
+                        &health,
+                        ws_id,
+                        &ws_path,
+                        &repo_path,
+                        Degradation::Errors(message),
+                    );
                 }
             },
         )
@@ -80,19 +309,246 @@ impl WatcherManager {
             .watch(&path, RecursiveMode::Recursive)
             .map_err(|e| format!("Failed to watch path: {}", e))?;
 
-        watchers.insert(workspace_path, debouncer);
+        let mut health = self.health.lock().unwrap();
+        let entry = health.entry(workspace_path.clone()).or_insert_with(|| {
+            WatcherHealth::new(workspace_id, &workspace_path, &health_repo_path)
+        });
+        entry.workspace_id = workspace_id;
+        entry.state = WatcherState::Healthy;
+
+        watchers.insert(
+            workspace_path,
+            WorkspaceWatcher {
+                workspace_id,
+                config,
+                _debouncer: debouncer,
+            },
+        );
         Ok(())
     }
 
     pub fn stop_watching(&self, workspace_path: &str) -> Result<(), String> {
         let mut watchers = self.watchers.lock().unwrap();
         watchers.remove(workspace_path);
+        self.health.lock().unwrap().remove(workspace_path);
         Ok(())
     }
+
+    pub fn stop_all(&self) {
+        let mut watchers = self.watchers.lock().unwrap();
+        watchers.clear();
+        self.health.lock().unwrap().clear();
+    }
+
+    /// Health of the watchers of a repo's workspaces, including ones that failed to restart
+    pub fn health_for_repo(&self, repo_path: &str) -> Vec<WatcherHealth> {
+        let mut health: Vec<WatcherHealth> = self
+            .health
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|h| h.repo_path == repo_path || h.workspace_path == repo_path)
+            .cloned()
+            .collect();
+        health.sort_by(|a, b| a.workspace_path.cmp(&b.workspace_path));
+        health
+    }
+
+    /// Recreate a watcher that dropped events, keeping its config
+    fn restart_degraded(&self, workspace_path: &str) {
+        let existing = self
+            .watchers
+            .lock()
+            .unwrap()
+            .get(workspace_path)
+            .map(|w| (w.workspace_id, w.config.clone()));
+        // Stopped in the meantime
+        let Some((workspace_id, config)) = existing else {
+            return;
+        };
+
+        let result = self.start_watching(workspace_id, workspace_path.to_string(), config);
+        if let Some(h) = self.health.lock().unwrap().get_mut(workspace_path) {
+            h.restart_count += 1;
+            if let Err(e) = result {
+                log::error!("Failed to restart watcher for {}: {}", workspace_path, e);
+                h.state = WatcherState::Failed;
+                h.last_error = Some(e);
+            }
+        }
+    }
+
+    /// Workspaces currently being watched, as (workspace_id, workspace_path)
+    pub fn watched_workspaces(&self) -> Vec<(i64, String)> {
+        let watchers = self.watchers.lock().unwrap();
+        watchers
+            .iter()
+            .map(|(path, w)| (w.workspace_id, path.clone()))
+            .collect()
+    }
+
+    /// Recreate every watcher from scratch.
+    ///
+    /// OS watches can be silently dropped (e.g. inotify after sleep/wake), so re-arming
+    /// is cheaper than trying to detect which ones still work. Returns the paths that
+    /// could not be re-armed along with the error.
+    pub fn rearm_all(&self) -> Vec<(String, String)> {
+        let configs: Vec<(i64, String, WatcherConfig)> = {
+            let watchers = self.watchers.lock().unwrap();
+            watchers
+                .iter()
+                .map(|(path, w)| (w.workspace_id, path.clone(), w.config.clone()))
+                .collect()
+        };
+        self.restart(configs)
+    }
+
+    /// Restart every watcher with its repo's current settings, e.g. after they changed
+    pub fn reload_config(&self, db: &Database) -> Vec<(String, String)> {
+        let configs = self
+            .watched_workspaces()
+            .into_iter()
+            .map(|(workspace_id, workspace_path)| {
+                let repo_path = jj::derive_repo_path_from_workspace(&workspace_path)
+                    .unwrap_or_else(|| workspace_path.clone());
+                let config = WatcherConfig::load(db, &repo_path);
+                (workspace_id, workspace_path, config)
+            })
+            .collect();
+        self.restart(configs)
+    }
+
+    fn restart(&self, watchers: Vec<(i64, String, WatcherConfig)>) -> Vec<(String, String)> {
+        let mut failures = Vec::new();
+        for (workspace_id, workspace_path, config) in watchers {
+            if let Err(e) = self.start_watching(workspace_id, workspace_path.clone(), config) {
+                // Drop watchers for workspaces that no longer exist
+                let _ = self.stop_watching(&workspace_path);
+                failures.push((workspace_path, e));
+            }
+        }
+        failures
+    }
+}
+
+/// A batch arrived without dropped events, so a watcher that degraded within the restart
+/// cooldown, and wasn't restarted, is keeping up again
+fn mark_recovered(health: &HealthMap, workspace_path: &str) {
+    if let Some(h) = health.lock().unwrap().get_mut(workspace_path) {
+        if h.state == WatcherState::Degraded {
+            h.state = WatcherState::Healthy;
+        }
+    }
+}
+
+/// Bring a workspace back in sync after its watcher lost events: report it, rescan the
+/// workspace, and restart the watcher unless it was restarted recently
+fn recover(
+    app_handle: &Mutex<Option<AppHandle>>,
+    health: &HealthMap,
+    workspace_id: i64,
+    workspace_path: &str,
+    repo_path: &str,
+    degradation: Degradation,
+) {
+    let reason = match &degradation {
+        Degradation::Overflow => "The OS dropped file events".to_string(),
+        Degradation::Errors(message) => message.clone(),
+    };
+
+    let restart = match health.lock().unwrap().get_mut(workspace_path) {
+        Some(h) => {
+            h.state = WatcherState::Degraded;
+            h.last_degraded_at = Some(timestamps::now());
+            match degradation {
+                Degradation::Overflow => h.overflow_count += 1,
+                Degradation::Errors(_) => {
+                    h.error_count += 1;
+                    h.last_error = Some(reason.clone());
+                }
+            }
+            let restart = h
+                .last_restart
+                .is_none_or(|at| at.elapsed() >= RESTART_COOLDOWN);
+            if restart {
+                h.last_restart = Some(Instant::now());
+            }
+            restart
+        }
+        None => false,
+    };
+
+    file_indexer::mark_dirty(workspace_path);
+    git_cache::bump_generation(workspace_path);
+    if workspace::is_workspace_indexed(workspace_path) {
+        if let Err(e) =
+            file_indexer::update_workspace_files(repo_path, Some(workspace_id), workspace_path, &[])
+        {
+            log::warn!("Failed to rescan {}: {}", workspace_path, e);
+        }
+    }
+    commit_graph::notify_changed(workspace_path);
+
+    let Some(handle) = app_handle.lock().unwrap().clone() else {
+        return;
+    };
+    let _ = handle.emit(
+        "watcher-degraded",
+        WatcherDegraded {
+            workspace_id,
+            workspace_path: workspace_path.to_string(),
+            reason,
+        },
+    );
+    // Listeners reload everything for the workspace root
+    let _ = handle.emit(
+        "workspace-files-changed",
+        serde_json::json!({
+            "workspace_id": workspace_id,
+            "changed_paths": [workspace_path]
+        }),
+    );
+
+    if restart {
+        // Not from the watcher's own callback thread, which the restart drops
+        let workspace_path = workspace_path.to_string();
+        std::thread::spawn(move || {
+            handle
+                .state::<AppState>()
+                .watcher_manager
+                .restart_degraded(&workspace_path);
+        });
+    }
+}
+
+/// Matcher for the workspace's root .gitignore plus the configured patterns
+fn build_ignore(workspace_path: &str, patterns: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(workspace_path);
+    if let Some(e) = builder.add(Path::new(workspace_path).join(".gitignore")) {
+        // A missing .gitignore is the common case, not worth a warning
+        if !matches!(e.io_error(), Some(io) if io.kind() == std::io::ErrorKind::NotFound) {
+            log::warn!("Failed to read .gitignore of {}: {}", workspace_path, e);
+        }
+    }
+    for pattern in patterns {
+        if let Err(e) = builder.add_line(None, pattern) {
+            log::warn!("Ignoring invalid watcher pattern {:?}: {}", pattern, e);
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        log::warn!("Failed to build watcher ignore rules: {}", e);
+        Gitignore::empty()
+    })
 }
 
-// TODO: Implement .gitignore support using the `ignore` crate
-// For now, we use a simple hardcoded list of common ignore patterns
+fn is_gitignored(ignore: &Gitignore, path: &Path) -> bool {
+    path.starts_with(ignore.path())
+        && ignore
+            .matched_path_or_any_parents(path, path.is_dir())
+            .is_ignore()
+}
+
+// Always skipped, whatever the ignore rules say
 fn is_ignored_path(path: &PathBuf) -> bool {
     let path_str = path.to_string_lossy();
     path_str.contains("/.jj/")
@@ -109,9 +565,15 @@ pub fn start_file_watcher(
     workspace_id: i64,
     workspace_path: String,
 ) -> Result<(), String> {
+    // Don't duplicate watchers of an instance that owns the repo
+    let repo_path = jj::derive_repo_path_from_workspace(&workspace_path)
+        .unwrap_or_else(|| workspace_path.clone());
+    instance_lock::ensure_repo_lock(&repo_path)?;
+
+    let config = WatcherConfig::load(&state.db.lock().unwrap(), &repo_path);
     state
         .watcher_manager
-        .start_watching(workspace_id, workspace_path)
+        .start_watching(workspace_id, workspace_path, config)
 }
 
 #[tauri::command]
@@ -122,3 +584,91 @@ pub fn stop_file_watcher(
 ) -> Result<(), String> {
     state.watcher_manager.stop_watching(&workspace_path)
 }
+
+/// Health of the file watchers of a repo's workspaces
+#[tauri::command]
+pub fn get_watcher_status(state: State<AppState>, repo_path: String) -> Vec<WatcherHealth> {
+    state.watcher_manager.health_for_repo(&repo_path)
+}
+
+/// Re-arm watchers and refresh watched workspaces, emitting `resynced` when done
+#[tauri::command]
+pub fn trigger_resync(app: AppHandle) -> Result<Option<resync::ResyncSummary>, String> {
+    Ok(resync::resync(&app, resync::ResyncReason::Manual))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_debounce_setting() {
+        assert_eq!(parse_debounce_setting(None), Duration::from_millis(1000));
+        assert_eq!(
+            parse_debounce_setting(Some(" 250 ")),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            parse_debounce_setting(Some("5")),
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            parse_debounce_setting(Some("fast")),
+            Duration::from_millis(1000)
+        );
+    }
+
+    #[test]
+    fn test_ignore_rules_combine_gitignore_and_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join(".gitignore"), "dist/\n*.log\n").unwrap();
+        fs::create_dir(root.join("dist")).unwrap();
+
+        let ignore = build_ignore(root.to_str().unwrap(), &["coverage/".to_string()]);
+        assert!(is_gitignored(&ignore, &root.join("dist/app.js")));
+        assert!(is_gitignored(&ignore, &root.join("src/debug.log")));
+        assert!(is_gitignored(&ignore, &root.join("coverage/index.html")));
+        assert!(!is_gitignored(&ignore, &root.join("src/main.rs")));
+        // Paths outside the workspace never match
+        assert!(!is_gitignored(&ignore, Path::new("/elsewhere/dist/app.js")));
+    }
+
+    #[test]
+    fn test_overflow_marks_watcher_degraded() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace = temp_dir.path().to_str().unwrap().to_string();
+        let manager = WatcherManager::new();
+        manager
+            .start_watching(7, workspace.clone(), WatcherConfig::default())
+            .unwrap();
+
+        let health = manager.health_for_repo(&workspace);
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].state, WatcherState::Healthy);
+
+        recover(
+            &manager.app_handle,
+            &manager.health,
+            7,
+            &workspace,
+            &workspace,
+            Degradation::Overflow,
+        );
+        let health = manager.health_for_repo(&workspace);
+        assert_eq!(health[0].state, WatcherState::Degraded);
+        assert_eq!(health[0].overflow_count, 1);
+        assert!(health[0].last_degraded_at.is_some());
+
+        mark_recovered(&manager.health, &workspace);
+        assert_eq!(
+            manager.health_for_repo(&workspace)[0].state,
+            WatcherState::Healthy
+        );
+
+        manager.stop_watching(&workspace).unwrap();
+        assert!(manager.health_for_repo(&workspace).is_empty());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::file_guard::{self, FileContent};
+use crate::file_indexer;
+use crate::language_stats;
 use crate::local_db;
 use ignore::WalkBuilder;
 
@@ -16,9 +19,16 @@ pub struct CachedDirectoryEntry {
     pub relative_path: String,
 }
 
+/// Read a text file; binary files and files over the size limit are described instead
 #[tauri::command]
-pub fn read_file(path: String) -> Result<String, String> {
-    std::fs::read_to_string(path).map_err(|e| e.to_string())
+pub fn read_file(path: String) -> Result<FileContent, String> {
+    file_guard::read_guarded(std::path::Path::new(&path))
+}
+
+/// Whether a file would be refused by `read_file`, without reading it
+#[tauri::command]
+pub fn check_file_guard(path: String) -> Result<Option<FileContent>, String> {
+    file_guard::check_file(std::path::Path::new(&path))
 }
 
 #[tauri::command]
@@ -144,6 +154,17 @@ pub struct FileSearchResult {
     pub relative_path: String,
 }
 
+/// Fuzzy quick-open search over the indexed files of a workspace
+#[tauri::command]
+pub fn search_files(
+    repo_path: String,
+    workspace_id: Option<i64>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<file_indexer::FuzzyFileMatch>, String> {
+    file_indexer::search_files(&repo_path, workspace_id, &query, limit.unwrap_or(50))
+}
+
 #[tauri::command]
 pub fn search_workspace_files(
     repo_path: String,
@@ -163,3 +184,18 @@ pub fn search_workspace_files(
         })
         .collect())
 }
+
+#[tauri::command]
+pub fn get_repo_language_stats(
+    repo_path: String,
+    workspace_id: Option<i64>,
+) -> Result<language_stats::RepoLanguageStats, String> {
+    let mut stats = local_db::get_language_stats(&repo_path, workspace_id)?;
+
+    // Compute on demand if the indexer hasn't populated the cache yet
+    if stats.is_empty() {
+        stats = language_stats::refresh_language_stats(&repo_path, workspace_id)?;
+    }
+
+    Ok(language_stats::summarize(stats))
+}
@@ -0,0 +1,100 @@
+use crate::ci_status::{self, CommitChecks};
+use crate::forge::{self, ForgeKind, ForgeRateLimitStatus, ForgeRepo, ForgeResponse, PullRequest};
+use crate::jj;
+use crate::local_db;
+use crate::workspace_config;
+
+/// GET a forge API path through the shared rate-limited, ETag-cached client.
+/// `provider` defaults to GitHub.
+#[tauri::command]
+pub fn forge_api_get(path: String, provider: Option<ForgeKind>) -> Result<ForgeResponse, String> {
+    forge::forge_get(provider.unwrap_or(ForgeKind::Github), &path)
+}
+
+#[tauri::command]
+pub fn forge_rate_limit_status(
+    provider: Option<ForgeKind>,
+) -> Result<ForgeRateLimitStatus, String> {
+    Ok(forge::rate_limit_status(
+        provider.unwrap_or(ForgeKind::Github),
+    ))
+}
+
+/// Forge hosting the repo's `origin` remote; None when it is not a supported host
+#[tauri::command]
+pub fn forge_detect_provider(repo_path: String) -> Result<Option<ForgeRepo>, String> {
+    forge::detect_provider(&repo_path)
+}
+
+fn require_forge_repo(repo_path: &str) -> Result<ForgeRepo, String> {
+    forge::detect_provider(repo_path)?
+        .ok_or_else(|| "The origin remote is not hosted on GitHub, GitLab or Bitbucket".to_string())
+}
+
+/// Open a pull (or merge) request from the workspace's branch. `base` defaults to the workspace's
+/// target branch, then the repository's default branch.
+#[tauri::command]
+pub fn forge_create_pr(
+    workspace_path: String,
+    title: String,
+    body: String,
+    base: Option<String>,
+) -> Result<PullRequest, String> {
+    let repo_path = jj::derive_repo_path_from_workspace(&workspace_path)
+        .unwrap_or_else(|| workspace_path.clone());
+    let repo = require_forge_repo(&repo_path)?;
+
+    let workspace = local_db::get_workspace_by_path(&repo_path, &workspace_path)?;
+    let head = match &workspace {
+        Some(workspace) => workspace.branch_name.clone(),
+        None => jj::get_workspace_branch(&workspace_path).map_err(|e| e.to_string())?,
+    };
+    let base = match base.filter(|b| !b.trim().is_empty()) {
+        Some(base) => base,
+        None => workspace
+            .as_ref()
+            .and_then(workspace_config::effective_target_branch)
+            .map_or_else(
+                || jj::get_default_branch(&repo_path).map_err(|e| e.to_string()),
+                Ok,
+            )?,
+    };
+    // Target branches may be stored as remote refs ("origin/main", "main@origin")
+    let base = base
+        .strip_prefix("origin/")
+        .or_else(|| base.strip_suffix("@origin"))
+        .unwrap_or(&base)
+        .to_string();
+    if head == base {
+        return Err(format!("Branch '{}' is the pull request base", head));
+    }
+
+    forge::provider_for(repo.provider).create_mr(&repo, &head, &base, &title, &body)
+}
+
+/// Most recent pull request from `branch`, with its checks; None if there is none
+#[tauri::command]
+pub fn forge_get_pr_status(
+    repo_path: String,
+    branch: String,
+) -> Result<Option<PullRequest>, String> {
+    let repo = require_forge_repo(&repo_path)?;
+    forge::provider_for(repo.provider).get_mr_status(&repo, &branch)
+}
+
+/// Pull requests from `branch` in any state, newest first
+#[tauri::command]
+pub fn forge_list_prs_for_branch(
+    repo_path: String,
+    branch: String,
+) -> Result<Vec<PullRequest>, String> {
+    let repo = require_forge_repo(&repo_path)?;
+    forge::provider_for(repo.provider).list_mrs_for_branch(&repo, &branch)
+}
+
+/// CI statuses and check runs of a branch or commit, cached in the repo's local
+/// database. Branch names resolve to the pushed branch when there is one.
+#[tauri::command]
+pub fn forge_get_checks(repo_path: String, branch_or_sha: String) -> Result<CommitChecks, String> {
+    ci_status::get_checks(&repo_path, &branch_or_sha)
+}
@@ -0,0 +1,510 @@
+use crate::async_process;
+use crate::bisect::{self, BisectStatus, BisectVerdict};
+use crate::commit_signing;
+use crate::destructive_guard::{self, DestructiveConfirmation, DestructiveOperation};
+use crate::git_ops::{self, GitError, PatchSelection, RepoCapabilities};
+use crate::git_submodules;
+use crate::jj;
+use crate::operations::{self, OperationKind};
+use crate::repo_bootstrap::{
+    self, BootstrapOptions, BootstrapResult, CloneComplete, CloneOptions, CloneProgressEvent,
+};
+use crate::snapshots;
+use crate::vcs::{self, VcsKind};
+use crate::AppState;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+// Git commands
+
+#[tauri::command]
+pub fn prepare_patch_email(
+    repo_path: String,
+    range: String,
+    options: Option<git_ops::PatchEmailOptions>,
+) -> Result<git_ops::PatchEmailResult, GitError> {
+    git_ops::prepare_patch_email(&repo_path, &range, &options.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn get_line_authors(
+    state: State<AppState>,
+    workspace_path: String,
+    file_path: String,
+    start_line: u32,
+    end_line: u32,
+) -> Result<Vec<git_ops::LineAuthor>, GitError> {
+    let ignore_revs = load_blame_ignore_revs(&state, &workspace_path);
+    git_ops::get_line_authors(
+        &workspace_path,
+        &file_path,
+        start_line,
+        end_line,
+        &ignore_revs,
+    )
+}
+
+#[tauri::command]
+pub fn git_blame(
+    state: State<AppState>,
+    workspace_path: String,
+    file_path: String,
+    rev: Option<String>,
+) -> Result<Vec<git_ops::BlameLine>, GitError> {
+    let ignore_revs = load_blame_ignore_revs(&state, &workspace_path);
+    git_ops::git_blame(&workspace_path, &file_path, rev.as_deref(), &ignore_revs)
+}
+
+#[tauri::command]
+pub fn add_blame_ignore_rev(repo_path: String, commit: String) -> Result<String, GitError> {
+    git_ops::add_blame_ignore_rev(&repo_path, &commit)
+}
+
+/// Load the per-repo "blame_ignore_revs" setting (one revision per line)
+fn load_blame_ignore_revs(state: &State<AppState>, workspace_path: &str) -> Vec<String> {
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    let db = state.db.lock().unwrap();
+    db.get_repo_setting(&repo_path, "blame_ignore_revs")
+        .ok()
+        .flatten()
+        .map(|revs| {
+            revs.lines()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty() && !s.starts_with('#'))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn git_commit_fixup(
+    state: State<AppState>,
+    workspace_path: String,
+    target_commit: String,
+    paths: Vec<String>,
+) -> Result<String, GitError> {
+    let signing = {
+        let db = state.db.lock().unwrap();
+        commit_signing::load_workspace_signing_config(&db, &workspace_path)?
+    };
+    git_ops::git_commit_fixup(&workspace_path, &target_commit, &paths, signing.as_ref())
+}
+
+/// Write the commits ahead of `target_branch` as numbered mails for a mailing list
+#[tauri::command]
+pub fn format_patch_series(
+    workspace_path: String,
+    target_branch: String,
+    output_dir: Option<String>,
+    cover_letter: bool,
+) -> Result<git_ops::PatchEmailResult, GitError> {
+    git_ops::format_patch_series(
+        &workspace_path,
+        &target_branch,
+        output_dir.as_deref(),
+        cover_letter,
+    )
+}
+
+/// Undo `commit_hash` with a commit applying its inverse, or with `no_commit` only
+/// stage the inverse changes
+#[tauri::command]
+pub fn git_revert(
+    state: State<AppState>,
+    repo_path: String,
+    commit_hash: String,
+    no_commit: bool,
+) -> Result<git_ops::GitRebaseResult, GitError> {
+    let signing = {
+        let db = state.db.lock().unwrap();
+        commit_signing::load_workspace_signing_config(&db, &repo_path)?
+    };
+    git_ops::git_revert(&repo_path, &commit_hash, no_commit, signing.as_ref())
+}
+
+/// Signature status of each commit in `range`, newest first
+#[tauri::command]
+pub fn verify_commit_signatures(
+    repo_path: String,
+    range: String,
+) -> Result<Vec<git_ops::CommitSignature>, GitError> {
+    git_ops::verify_commit_signatures(&repo_path, &range)
+}
+
+#[tauri::command]
+pub fn git_rebase_autosquash(
+    workspace_path: String,
+    base: String,
+) -> Result<git_ops::GitRebaseResult, GitError> {
+    git_ops::git_rebase_autosquash(&workspace_path, &base)
+}
+
+#[tauri::command]
+pub fn git_stash_list(repo_path: String) -> Result<Vec<git_ops::GitStashEntry>, GitError> {
+    git_ops::git_stash_list(&repo_path)
+}
+
+#[tauri::command]
+pub fn git_stash_push_files(
+    repo_path: String,
+    file_paths: Vec<String>,
+    message: Option<String>,
+    include_untracked: Option<bool>,
+) -> Result<String, GitError> {
+    git_ops::git_stash_push_files(
+        &repo_path,
+        &file_paths,
+        message.as_deref(),
+        include_untracked.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+pub fn git_stash_pop(repo_path: String, index: Option<usize>) -> Result<String, GitError> {
+    git_ops::git_stash_pop(&repo_path, index.unwrap_or(0))
+}
+
+#[tauri::command]
+pub fn git_stash_apply(repo_path: String, index: usize) -> Result<String, GitError> {
+    git_ops::git_stash_apply(&repo_path, index)
+}
+
+#[tauri::command]
+pub fn git_stash_drop(repo_path: String, index: usize) -> Result<String, GitError> {
+    git_ops::git_stash_drop(&repo_path, index)
+}
+
+/// Changed files with per-file insertion/deletion counts
+#[tauri::command]
+pub fn git_get_changed_files_with_stats(
+    workspace_path: String,
+) -> Result<git_ops::ChangedFilesWithStats, GitError> {
+    git_ops::git_get_changed_files_with_stats(&workspace_path)
+}
+
+/// Hunk headers and line counts of a file, for diffs too large to send in one call
+#[tauri::command]
+pub fn git_get_file_hunk_headers(
+    workspace_path: String,
+    file_path: String,
+) -> Result<git_ops::FileHunkHeaders, GitError> {
+    git_ops::git_get_file_hunk_headers(&workspace_path, &file_path)
+}
+
+#[tauri::command]
+pub fn git_get_hunk_body(
+    workspace_path: String,
+    file_path: String,
+    hunk_header: String,
+) -> Result<jj::JjDiffHunk, GitError> {
+    git_ops::git_get_hunk_body(&workspace_path, &file_path, &hunk_header)
+}
+
+/// Revert one hunk of a file in the working tree, leaving the index alone and saving
+/// the file to a snapshot first. In a main repo with workspaces this needs a
+/// `confirm_token` from `request_destructive_confirmation`.
+#[tauri::command]
+pub fn git_discard_hunk(
+    workspace_path: String,
+    file_path: String,
+    patch: String,
+    confirm_token: Option<String>,
+) -> Result<(), GitError> {
+    destructive_guard::check(
+        &workspace_path,
+        DestructiveOperation::DiscardChanges,
+        confirm_token.as_deref(),
+    )?;
+    snapshots::take_before(
+        &workspace_path,
+        "discard hunk",
+        std::slice::from_ref(&file_path),
+    )?;
+    git_ops::git_discard_hunk(&workspace_path, &file_path, &patch)
+}
+
+/// Revert the selected lines of a hunk, given as indices into the hunk's lines. Needs a
+/// `confirm_token` like `git_discard_hunk`.
+#[tauri::command]
+pub fn git_discard_selected_lines(
+    workspace_path: String,
+    file_path: String,
+    patch: String,
+    line_indices: Vec<usize>,
+    confirm_token: Option<String>,
+) -> Result<(), GitError> {
+    destructive_guard::check(
+        &workspace_path,
+        DestructiveOperation::DiscardChanges,
+        confirm_token.as_deref(),
+    )?;
+    snapshots::take_before(
+        &workspace_path,
+        "discard selected lines",
+        std::slice::from_ref(&file_path),
+    )?;
+    git_ops::git_discard_selected_lines(&workspace_path, &file_path, &patch, &line_indices)
+}
+
+#[tauri::command]
+pub fn git_stash_show(
+    state: State<AppState>,
+    repo_path: String,
+    index: usize,
+) -> Result<git_ops::GitStashShow, GitError> {
+    let detection = {
+        let db = state.db.lock().unwrap();
+        git_ops::load_diff_detection(&db, &repo_path)?
+    };
+    git_ops::git_stash_show(&repo_path, index, &detection)
+}
+
+#[tauri::command]
+pub fn git_lfs_status(repo_path: String) -> Result<git_ops::GitLfsStatus, GitError> {
+    git_ops::git_lfs_status(&repo_path)
+}
+
+/// Download LFS objects, limited to `paths` when given
+#[tauri::command]
+pub async fn git_lfs_pull(
+    repo_path: String,
+    paths: Option<Vec<String>>,
+) -> Result<String, GitError> {
+    git_ops::git_lfs_pull(&repo_path, &paths.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub fn git_submodule_list(
+    repo_path: String,
+) -> Result<Vec<git_submodules::GitSubmodule>, GitError> {
+    git_submodules::git_submodule_list(&repo_path)
+}
+
+#[tauri::command]
+pub fn git_submodule_update(
+    repo_path: String,
+    init: Option<bool>,
+    recursive: Option<bool>,
+) -> Result<String, GitError> {
+    git_submodules::git_submodule_update(
+        &repo_path,
+        init.unwrap_or(false),
+        recursive.unwrap_or(false),
+    )
+}
+
+/// The repository a workspace belongs to, and the git dir to point git at when it is a
+/// jj workspace rather than a git worktree
+fn patch_repo(workspace_path: &str) -> (String, Option<PathBuf>) {
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    let git_dir = match vcs::backend_kind(&repo_path) {
+        VcsKind::JjColocated => Some(Path::new(&repo_path).join(".git")),
+        VcsKind::PlainGit => None,
+    };
+    (repo_path, git_dir)
+}
+
+/// Write commits or uncommitted changes of a workspace to a patch file
+#[tauri::command]
+pub fn export_patch(
+    workspace_path: String,
+    selection: PatchSelection,
+    dest_path: String,
+) -> Result<git_ops::PatchExportResult, GitError> {
+    let (repo_path, git_dir) = patch_repo(&workspace_path);
+    let (patch, commit_count) = match &selection {
+        PatchSelection::Revisions { revspec } => {
+            // jj writes every workspace's commits to the repo's git store
+            let git_path = if git_dir.is_some() {
+                &repo_path
+            } else {
+                &workspace_path
+            };
+            git_ops::git_format_patch(git_path, revspec)?
+        }
+        PatchSelection::Files { paths } if git_dir.is_some() => {
+            let (patch, _) = snapshots::working_copy_patch(&repo_path, &workspace_path, paths)?;
+            (patch, 0)
+        }
+        PatchSelection::Files { paths } => {
+            let (patch, _) = git_ops::git_working_tree_patch(&workspace_path, paths)?;
+            (patch, 0)
+        }
+    };
+    git_ops::write_patch_file(&patch, &dest_path, commit_count)
+}
+
+/// Apply a .patch/.diff file to a workspace, or with `dry_run` report which hunks
+/// would fail
+#[tauri::command]
+pub fn apply_patch_file(
+    workspace_path: String,
+    patch_path: String,
+    three_way: bool,
+    dry_run: Option<bool>,
+) -> Result<git_ops::PatchApplyResult, GitError> {
+    let (_, git_dir) = patch_repo(&workspace_path);
+    git_ops::git_apply_patch_file(
+        &workspace_path,
+        git_dir.as_deref(),
+        &patch_path,
+        three_way,
+        dry_run.unwrap_or(false),
+    )
+}
+
+/// Start bisecting between a known good and bad commit
+#[tauri::command]
+pub fn bisect_start(
+    repo_path: String,
+    good: String,
+    bad: String,
+) -> Result<BisectStatus, GitError> {
+    bisect::bisect_start(&repo_path, &good, &bad)
+}
+
+#[tauri::command]
+pub fn bisect_mark(repo_path: String, verdict: BisectVerdict) -> Result<BisectStatus, GitError> {
+    bisect::bisect_mark(&repo_path, verdict)
+}
+
+#[tauri::command]
+pub fn bisect_status(repo_path: String) -> Result<BisectStatus, GitError> {
+    bisect::bisect_status(&repo_path)
+}
+
+#[tauri::command]
+pub fn bisect_abort(repo_path: String) -> Result<(), GitError> {
+    bisect::bisect_abort(&repo_path)
+}
+
+/// Whether the repository is a shallow or partial clone, whose divergence counts may
+/// be incomplete
+#[tauri::command]
+pub fn get_repo_capabilities(repo_path: String) -> Result<RepoCapabilities, String> {
+    git_ops::git_repo_capabilities(&repo_path).map_err(|e| e.to_string())
+}
+
+/// Single-use token that lets one destructive operation run in a main repository
+/// that has workspaces
+#[tauri::command]
+pub fn request_destructive_confirmation(
+    repo_path: String,
+    operation: DestructiveOperation,
+) -> Result<DestructiveConfirmation, String> {
+    destructive_guard::request_confirmation(&repo_path, operation)
+}
+
+#[tauri::command]
+pub fn git_delete_branch(
+    repo_path: String,
+    branch: String,
+    force: Option<bool>,
+    confirm_token: Option<String>,
+) -> Result<String, GitError> {
+    destructive_guard::check(
+        &repo_path,
+        DestructiveOperation::DeleteBranch,
+        confirm_token.as_deref(),
+    )?;
+    git_ops::git_delete_branch(&repo_path, &branch, force.unwrap_or(false))
+}
+
+#[tauri::command]
+pub async fn git_delete_remote_branch(
+    repo_path: String,
+    remote: String,
+    branch: String,
+    confirm_token: Option<String>,
+) -> Result<String, GitError> {
+    destructive_guard::check(
+        &repo_path,
+        DestructiveOperation::DeleteRemoteBranch,
+        confirm_token.as_deref(),
+    )?;
+    git_ops::git_delete_remote_branch(&repo_path, &remote, &branch).await
+}
+
+/// Remotes with their fetch/push URLs and refspecs
+#[tauri::command]
+pub fn git_list_remotes_detailed(repo_path: String) -> Result<Vec<git_ops::GitRemote>, GitError> {
+    git_ops::git_list_remotes_detailed(&repo_path)
+}
+
+#[tauri::command]
+pub async fn git_prune_remote(repo_path: String, remote: String) -> Result<Vec<String>, GitError> {
+    git_ops::git_prune_remote(&repo_path, &remote).await
+}
+
+/// Create a new repository for the "New repository…" flow
+#[tauri::command]
+pub fn bootstrap_repository(
+    state: State<AppState>,
+    path: String,
+    options: Option<BootstrapOptions>,
+) -> Result<BootstrapResult, String> {
+    let db = state.db.lock().unwrap();
+    repo_bootstrap::bootstrap_repository(&db, &path, &options.unwrap_or_default())
+}
+
+/// Start cloning `url` into `dest_path` in the background. Progress arrives as
+/// `clone-progress` events and a `clone-complete` event carries the result; returns the
+/// clone id, which `cancel_operation` also accepts.
+#[tauri::command]
+pub fn git_clone(
+    app: AppHandle,
+    url: String,
+    dest_path: String,
+    options: Option<CloneOptions>,
+) -> Result<String, String> {
+    let clone_id = repo_bootstrap::next_clone_id();
+    let id = clone_id.clone();
+    std::thread::spawn(move || {
+        let options = options.unwrap_or_default();
+        let mut on_progress = |progress| {
+            let _ = app.emit(
+                "clone-progress",
+                CloneProgressEvent {
+                    clone_id: id.clone(),
+                    dest_path: dest_path.clone(),
+                    progress,
+                },
+            );
+        };
+        let state = app.state::<AppState>();
+        let clone = repo_bootstrap::clone_repository(
+            &state.db,
+            &url,
+            &dest_path,
+            &options,
+            &mut on_progress,
+        );
+        let outcome = async_process::block_on(operations::run_operation(
+            OperationKind::Clone,
+            &dest_path,
+            Some(id.clone()),
+            clone,
+        ));
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(e) => (None, Some(e)),
+        };
+        let _ = app.emit(
+            "clone-complete",
+            CloneComplete {
+                clone_id: id,
+                dest_path,
+                result,
+                error,
+            },
+        );
+    });
+    Ok(clone_id)
+}
+
+#[tauri::command]
+pub fn list_gitignore_templates() -> Result<Vec<String>, String> {
+    Ok(repo_bootstrap::gitignore_template_names())
+}
@@ -0,0 +1,103 @@
+use crate::file_guard::FileContent;
+use crate::git_cache::{self, CacheScope};
+use crate::git_ops::{self, GitError};
+use crate::jj;
+use crate::local_db;
+use crate::AppState;
+use tauri::State;
+
+// History browsing commands
+
+#[tauri::command]
+pub fn git_log(
+    repo_path: String,
+    options: Option<git_ops::GitLogOptions>,
+) -> Result<git_ops::GitLogPage, GitError> {
+    let options = options.unwrap_or_default();
+    let params = serde_json::to_string(&options).unwrap_or_default();
+    let revision = options.revision.as_deref().unwrap_or("HEAD");
+    git_cache::cached(
+        &repo_path,
+        "git_log",
+        &params,
+        &[revision],
+        CacheScope::History,
+        || git_ops::git_log(&repo_path, &options),
+    )
+}
+
+/// Commit graph of `refs`, by default the repo's default branch and the branches of
+/// its workspaces
+#[tauri::command]
+pub fn git_get_graph(
+    repo_path: String,
+    refs: Option<Vec<String>>,
+    limit: Option<usize>,
+) -> Result<git_ops::CommitGraph, GitError> {
+    let refs = match refs.filter(|r| !r.is_empty()) {
+        Some(refs) => refs,
+        None => default_graph_refs(&repo_path),
+    };
+    let params = serde_json::to_string(&(&refs, limit)).unwrap_or_default();
+    let revisions: Vec<&str> = refs.iter().map(String::as_str).collect();
+    git_cache::cached(
+        &repo_path,
+        "git_graph",
+        &params,
+        &revisions,
+        CacheScope::History,
+        || git_ops::git_get_graph(&repo_path, &refs, limit),
+    )
+}
+
+/// Default branch and workspace branches that exist in the repo
+fn default_graph_refs(repo_path: &str) -> Vec<String> {
+    let mut refs: Vec<String> = jj::get_default_branch(repo_path).into_iter().collect();
+    for workspace in local_db::get_workspaces(repo_path).unwrap_or_default() {
+        if !refs.contains(&workspace.branch_name) {
+            refs.push(workspace.branch_name);
+        }
+    }
+    refs.retain(|r| git_ops::git_resolve_commit(repo_path, r).is_ok());
+    refs
+}
+
+/// Diff of a commit, with the repo's rename and copy detection settings
+#[tauri::command]
+pub fn git_get_commit_diff(
+    state: State<AppState>,
+    repo_path: String,
+    commit_hash: String,
+) -> Result<git_ops::CommitDiff, GitError> {
+    let detection = {
+        let db = state.db.lock().unwrap();
+        git_ops::load_diff_detection(&db, &repo_path)?
+    };
+    let params = serde_json::to_string(&(&commit_hash, &detection)).unwrap_or_default();
+    git_cache::cached(
+        &repo_path,
+        "commit_diff",
+        &params,
+        &[&commit_hash],
+        CacheScope::History,
+        || git_ops::git_get_commit_diff(&repo_path, &commit_hash, &detection),
+    )
+}
+
+#[tauri::command]
+pub fn browse_revision(
+    repo_path: String,
+    revision: String,
+    path: Option<String>,
+) -> Result<git_ops::RevisionTree, GitError> {
+    git_ops::browse_revision(&repo_path, &revision, path.as_deref().unwrap_or(""))
+}
+
+#[tauri::command]
+pub fn read_file_at_revision(
+    repo_path: String,
+    revision: String,
+    file_path: String,
+) -> Result<FileContent, GitError> {
+    git_ops::read_file_at_revision(&repo_path, &revision, &file_path)
+}
@@ -0,0 +1,13 @@
+use crate::instance_lock::{self, RepoLockStatus};
+
+/// Acquire the repo's instance lock if it's free, reporting which instance holds it otherwise
+#[tauri::command]
+pub fn get_repo_lock_status(repo_path: String) -> Result<RepoLockStatus, String> {
+    instance_lock::acquire_repo_lock(&repo_path)
+}
+
+/// Take the repo's instance lock from another (possibly hung) instance
+#[tauri::command]
+pub fn takeover_repo_lock(repo_path: String) -> Result<RepoLockStatus, String> {
+    instance_lock::takeover_repo_lock(&repo_path)
+}
@@ -1,4 +1,24 @@
+use crate::async_process;
+use crate::automation::{self, HookContext, HookEvent};
+use crate::ci_status;
+use crate::commit_graph;
+use crate::commit_hooks::{self, CommitAttempt};
+use crate::commit_signing;
+use crate::destructive_guard::{self, DestructiveOperation};
+use crate::git_cache::{self, CacheScope};
+use crate::git_ops;
 use crate::jj;
+use crate::jj_config;
+use crate::jj_interactive_rebase::{self, RebaseStep};
+use crate::merge_gates::{self, MergeAttempt, MergeCheckConfig};
+use crate::operations::{self, OperationKind};
+use crate::result_cache::{self, CacheKind, CacheRefreshResult, CachedPayload};
+use crate::shallow_history;
+use crate::snapshots;
+use crate::task_runner;
+use crate::updater::OperationGuard;
+use crate::vcs::{self, VcsKind};
+use crate::workspace_disk_usage;
 use crate::AppState;
 use tauri::{AppHandle, State};
 
@@ -45,18 +65,18 @@ pub fn jj_list_workspaces(
     _state: State<AppState>,
     _app: AppHandle,
     repo_path: String,
-) -> Result<Vec<jj::WorkspaceInfo>, String> {
-    jj::list_workspaces(&repo_path).map_err(|e| e.to_string())
+) -> Result<Vec<jj::WorkspaceInfo>, jj::JjError> {
+    jj::list_workspaces(&repo_path)
 }
 
 #[tauri::command]
-pub fn jj_remove_workspace(repo_path: String, workspace_path: String) -> Result<(), String> {
-    jj::remove_workspace(&repo_path, &workspace_path).map_err(|e| e.to_string())
+pub fn jj_remove_workspace(repo_path: String, workspace_path: String) -> Result<(), jj::JjError> {
+    jj::remove_workspace(&repo_path, &workspace_path)
 }
 
 #[tauri::command]
-pub fn jj_get_workspace_info(workspace_path: String) -> Result<jj::WorkspaceInfo, String> {
-    jj::get_workspace_info(&workspace_path).map_err(|e| e.to_string())
+pub fn jj_get_workspace_info(workspace_path: String) -> Result<jj::WorkspaceInfo, jj::JjError> {
+    jj::get_workspace_info(&workspace_path)
 }
 
 #[tauri::command]
@@ -64,22 +84,107 @@ pub fn jj_squash_to_workspace(
     source_workspace_path: String,
     target_workspace_name: String,
     file_paths: Option<Vec<String>>,
-) -> Result<String, String> {
-    jj::squash_to_workspace(&source_workspace_path, &target_workspace_name, file_paths)
-        .map_err(|e| e.to_string())
+) -> Result<String, jj::JjError> {
+    let result =
+        jj::squash_to_workspace(&source_workspace_path, &target_workspace_name, file_paths)?;
+    commit_graph::notify_changed(&source_workspace_path);
+    Ok(result)
 }
 
 #[tauri::command]
 pub fn jj_get_changed_files(workspace_path: String) -> Result<Vec<jj::JjFileChange>, String> {
-    jj::jj_get_changed_files(&workspace_path).map_err(|e| e.to_string())
+    vcs::backend_for_workspace(&workspace_path).changed_files(&workspace_path)
 }
 
 #[tauri::command]
 pub fn jj_get_file_hunks(
     workspace_path: String,
     file_path: String,
-) -> Result<Vec<jj::JjDiffHunk>, String> {
-    jj::jj_get_file_hunks(&workspace_path, &file_path).map_err(|e| e.to_string())
+) -> Result<jj::FileHunks, String> {
+    vcs::backend_for_workspace(&workspace_path).file_hunks(&workspace_path, &file_path)
+}
+
+// Cached variants return the data with computed_at/max_age so the UI can flag stale results
+
+fn cached_changed_files(
+    workspace_path: &str,
+) -> Result<CachedPayload<Vec<jj::JjFileChange>>, String> {
+    result_cache::get_or_compute(CacheKind::Status, workspace_path, "", || {
+        vcs::backend_for_workspace(workspace_path).changed_files(workspace_path)
+    })
+}
+
+fn cached_sync_status(
+    workspace_path: &str,
+    branch_name: &str,
+) -> Result<CachedPayload<(usize, usize)>, String> {
+    result_cache::get_or_compute(CacheKind::Divergence, workspace_path, branch_name, || {
+        async_process::block_on(shallow_history::ensure_remote_merge_base(workspace_path, branch_name));
+        jj::jj_get_sync_status(workspace_path, branch_name).map_err(|e| e.to_string())
+    })
+}
+
+fn cached_file_hunks(
+    workspace_path: &str,
+    file_path: &str,
+) -> Result<CachedPayload<jj::FileHunks>, String> {
+    result_cache::get_or_compute(CacheKind::Hunks, workspace_path, file_path, || {
+        vcs::backend_for_workspace(workspace_path).file_hunks(workspace_path, file_path)
+    })
+}
+
+#[tauri::command]
+pub fn jj_get_changed_files_cached(
+    workspace_path: String,
+) -> Result<CachedPayload<Vec<jj::JjFileChange>>, String> {
+    cached_changed_files(&workspace_path)
+}
+
+/// May fetch history for the merge base, so it runs off the async runtime
+#[tauri::command]
+pub async fn jj_get_sync_status_cached(
+    workspace_path: String,
+    branch_name: String,
+) -> Result<CachedPayload<(usize, usize)>, String> {
+    tokio::task::spawn_blocking(move || cached_sync_status(&workspace_path, &branch_name))
+        .await
+        .map_err(|e| format!("Sync status task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn jj_get_file_hunks_cached(
+    workspace_path: String,
+    file_path: String,
+) -> Result<CachedPayload<jj::FileHunks>, String> {
+    cached_file_hunks(&workspace_path, &file_path)
+}
+
+/// Recompute every expired cache entry of the given kinds for a workspace
+/// (e.g. after the machine wakes from sleep)
+#[tauri::command]
+pub fn refresh_if_stale(
+    workspace_path: String,
+    kinds: Vec<CacheKind>,
+) -> Result<Vec<CacheRefreshResult>, String> {
+    let results = result_cache::stale_keys(&workspace_path, &kinds)
+        .into_iter()
+        .map(|(kind, key)| {
+            let outcome = match kind {
+                CacheKind::Status => cached_changed_files(&workspace_path).map(|_| ()),
+                CacheKind::Divergence => cached_sync_status(&workspace_path, &key).map(|_| ()),
+                CacheKind::Hunks => cached_file_hunks(&workspace_path, &key).map(|_| ()),
+                CacheKind::DiskUsage => workspace_disk_usage::cached_workspace_disk_usage(&workspace_path).map(|_| ()),
+            };
+            CacheRefreshResult {
+                kind,
+                key,
+                refreshed: outcome.is_ok(),
+                error: outcome.err(),
+            }
+        })
+        .collect();
+
+    Ok(results)
 }
 
 #[tauri::command]
@@ -89,7 +194,7 @@ pub fn jj_get_file_lines(
     from_parent: bool,
     start_line: usize,
     end_line: usize,
-) -> Result<jj::JjFileLines, String> {
+) -> Result<jj::JjFileLines, jj::JjError> {
     jj::jj_get_file_lines(
         &workspace_path,
         &file_path,
@@ -97,25 +202,74 @@ pub fn jj_get_file_lines(
         start_line,
         end_line,
     )
-    .map_err(|e| e.to_string())
 }
 
+/// Discard a file's changes, saving them to a snapshot first
 #[tauri::command]
 pub fn jj_restore_file(workspace_path: String, file_path: String) -> Result<String, String> {
+    snapshots::take_before(&workspace_path, "discard file", std::slice::from_ref(&file_path))?;
     jj::jj_restore_file(&workspace_path, &file_path).map_err(|e| e.to_string())
 }
 
+/// Discard all changes, saving them to a snapshot first. In a main repo with
+/// workspaces this needs a `confirm_token` from `request_destructive_confirmation`.
 #[tauri::command]
-pub fn jj_restore_all(workspace_path: String) -> Result<String, String> {
+pub fn jj_restore_all(workspace_path: String, confirm_token: Option<String>) -> Result<String, String> {
+    destructive_guard::check(
+        &workspace_path,
+        DestructiveOperation::DiscardAllChanges,
+        confirm_token.as_deref(),
+    )?;
+    snapshots::take_before(&workspace_path, "discard all changes", &[])?;
     jj::jj_restore_all(&workspace_path).map_err(|e| e.to_string())
 }
 
+/// Commit the working copy after running the workspace's `pre-commit` and
+/// `commit-msg` hooks, unless `skip_hooks` is set. A rejecting hook is returned in
+/// `hook_failure` rather than as an error.
 #[tauri::command]
-pub fn jj_commit(workspace_path: String, message: String) -> Result<String, String> {
-    let result = jj::jj_commit(&workspace_path, &message).map_err(|e| e.to_string())?;
+pub fn jj_commit(
+    state: State<AppState>,
+    workspace_path: String,
+    message: String,
+    skip_hooks: Option<bool>,
+) -> Result<CommitAttempt, String> {
+    let signing = {
+        let db = state.db.lock().unwrap();
+        commit_signing::load_workspace_signing_config(&db, &workspace_path)?
+    };
+    let backend = vcs::backend_for_workspace(&workspace_path);
+    let mut message = message;
+    if !skip_hooks.unwrap_or(false) {
+        // git's pre-commit checks the index, so stage what git_commit_all will commit
+        if backend.kind() == VcsKind::PlainGit {
+            git_ops::git_stage_all(&workspace_path).map_err(|e| e.to_string())?;
+        }
+        if let Some(failure) = commit_hooks::run_commit_hooks(&workspace_path, &mut message)? {
+            return Ok(failure.into());
+        }
+    }
+    let result = backend.commit(&workspace_path, &message, signing.as_ref())?;
+    commit_graph::notify_changed(&workspace_path);
+
+    let (repo_path, context) = automation::workspace_context(&workspace_path);
+    automation::fire(
+        &repo_path,
+        HookEvent::CommitMade,
+        HookContext {
+            message: Some(message.clone()),
+            ..context
+        },
+    );
+
+    // Auto-rebase relies on jj; plain git workspaces are rebased by the user
+    if backend.kind() != VcsKind::JjColocated {
+        return Ok(result.into());
+    }
 
     // Trigger auto-rebase in background (fire-and-forget)
     std::thread::spawn(move || {
+        let _operation = OperationGuard::start();
         // Derive repo path and get committed branch
         if let Some(repo_path) = jj::derive_repo_path_from_workspace(&workspace_path) {
             if let Ok(branch) = jj::get_workspace_branch(&workspace_path) {
@@ -125,6 +279,40 @@ pub fn jj_commit(workspace_path: String, message: String) -> Result<String, Stri
         }
     });
 
+    Ok(result.into())
+}
+
+/// Change the message of an earlier commit
+#[tauri::command]
+pub fn jj_describe(
+    workspace_path: String,
+    revision: String,
+    message: String,
+) -> Result<String, jj::JjError> {
+    let result = jj::jj_describe(&workspace_path, &revision, &message)?;
+    commit_graph::notify_changed(&workspace_path);
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn jj_get_commit_message(workspace_path: String, revision: String) -> Result<String, jj::JjError> {
+    jj::jj_get_commit_message(&workspace_path, &revision)
+}
+
+/// Undo a landed change with a commit applying its inverse
+#[tauri::command]
+pub fn jj_backout(
+    state: State<AppState>,
+    workspace_path: String,
+    revision: String,
+) -> Result<jj::JjRebaseResult, String> {
+    let signing = {
+        let db = state.db.lock().unwrap();
+        commit_signing::load_workspace_signing_config(&db, &workspace_path)?
+    };
+    let result = jj::jj_backout(&workspace_path, &revision, signing.as_ref())
+        .map_err(|e| e.to_string())?;
+    commit_graph::notify_changed(&workspace_path);
     Ok(result)
 }
 
@@ -133,11 +321,13 @@ pub fn jj_split(
     workspace_path: String,
     message: String,
     file_paths: Vec<String>,
-) -> Result<String, String> {
-    let result = jj::jj_split(&workspace_path, &message, file_paths).map_err(|e| e.to_string())?;
+) -> Result<String, jj::JjError> {
+    let result = jj::jj_split(&workspace_path, &message, file_paths)?;
+    commit_graph::notify_changed(&workspace_path);
 
     // Trigger auto-rebase in background (fire-and-forget)
     std::thread::spawn(move || {
+        let _operation = OperationGuard::start();
         // Derive repo path and get committed branch
         if let Some(repo_path) = jj::derive_repo_path_from_workspace(&workspace_path) {
             if let Ok(branch) = jj::get_workspace_branch(&workspace_path) {
@@ -150,6 +340,28 @@ pub fn jj_split(
     Ok(result)
 }
 
+/// Split only the selected hunks out of the working copy commit
+#[tauri::command]
+pub fn jj_split_hunks(
+    workspace_path: String,
+    message: String,
+    selections: Vec<jj::JjHunkSelection>,
+) -> Result<String, jj::JjError> {
+    let result = jj::jj_split_hunks(&workspace_path, &message, selections)?;
+    commit_graph::notify_changed(&workspace_path);
+
+    // Trigger auto-rebase in background (fire-and-forget), same as jj_split
+    std::thread::spawn(move || {
+        if let Some(repo_path) = jj::derive_repo_path_from_workspace(&workspace_path) {
+            if let Ok(branch) = jj::get_workspace_branch(&workspace_path) {
+                let _ = crate::auto_rebase::rebase_after_commit(&repo_path, &branch);
+            }
+        }
+    });
+
+    Ok(result)
+}
+
 /// Check if a path has a jj workspace
 #[tauri::command]
 pub fn jj_is_workspace(repo_path: String) -> bool {
@@ -157,110 +369,424 @@ pub fn jj_is_workspace(repo_path: String) -> bool {
 }
 
 /// Manually initialize jj for a repository
+/// Returns false without initializing when the repo is set to the plain git backend
 #[tauri::command]
 pub fn jj_init(state: State<AppState>, repo_path: String) -> Result<bool, String> {
     let db = state.db.lock().unwrap();
+    // Repos set to plain git keep working through git worktrees without jj
+    if vcs::load_preference(&db, &repo_path) == Some(VcsKind::PlainGit) {
+        return Ok(false);
+    }
     jj::ensure_jj_initialized(&db, &repo_path).map_err(|e| e.to_string())
 }
 
-/// Rebase workspace onto a target branch
+/// Options set in the repo's jj config layer (`.jj/repo/config.toml`)
+#[tauri::command]
+pub fn get_jj_config(repo_path: String) -> Result<Vec<jj_config::JjConfigEntry>, String> {
+    jj_config::get_repo_config(&repo_path).map_err(|e| e.to_string())
+}
+
+/// Set a repo-level jj option; an empty value removes it
+#[tauri::command]
+pub fn set_jj_config(repo_path: String, key: String, value: String) -> Result<Vec<jj_config::JjConfigEntry>, String> {
+    jj_config::set_repo_config(&repo_path, &key, &value).map_err(|e| e.to_string())?;
+    jj_config::get_repo_config(&repo_path).map_err(|e| e.to_string())
+}
+
+/// Rebase workspace onto a target branch.
+/// `op_id` identifies its operation events and lets `cancel_operation` stop it.
 #[tauri::command]
-pub fn jj_rebase_onto(
+pub async fn jj_rebase_onto(
     workspace_path: String,
     target_branch: String,
-) -> Result<jj::JjRebaseResult, String> {
-    jj::jj_rebase_onto(&workspace_path, &target_branch).map_err(|e| e.to_string())
+    op_id: Option<String>,
+) -> Result<jj::JjRebaseResult, jj::JjError> {
+    let rebase = jj::jj_rebase_onto(&workspace_path, &target_branch);
+    let result = operations::run_operation(OperationKind::Rebase, &workspace_path, op_id, rebase)
+        .await?;
+    commit_graph::notify_changed(&workspace_path);
+
+    let (repo_path, context) = automation::workspace_context(&workspace_path);
+    // Listing conflicts costs another jj call, so only do it when someone listens
+    if automation::has_hooks(&repo_path, HookEvent::RebaseConflict) {
+        let conflicted_files = jj::get_conflicted_files(&workspace_path, Some(&target_branch))
+            .unwrap_or_default();
+        if !conflicted_files.is_empty() {
+            automation::fire(
+                &repo_path,
+                HookEvent::RebaseConflict,
+                HookContext {
+                    target_branch: Some(target_branch),
+                    conflicted_files,
+                    ..context
+                },
+            );
+        }
+    }
+    Ok(result)
+}
+
+/// List commits in target..@ (oldest first) to build an interactive rebase plan
+#[tauri::command]
+pub fn jj_get_rebase_plan(
+    workspace_path: String,
+    target_branch: String,
+) -> Result<Vec<jj_interactive_rebase::RebasePlanCommit>, String> {
+    jj_interactive_rebase::get_rebase_plan(&workspace_path, &target_branch).map_err(|e| e.to_string())
+}
+
+/// Reorder, squash, reword and drop commits in target..@ according to the plan
+#[tauri::command]
+pub fn jj_interactive_rebase(
+    workspace_path: String,
+    target_branch: String,
+    steps: Vec<RebaseStep>,
+) -> Result<jj_interactive_rebase::InteractiveRebaseReport, String> {
+    let _operation = OperationGuard::start();
+    let report = jj_interactive_rebase::execute_plan(&workspace_path, &target_branch, &steps)
+        .map_err(|e| e.to_string())?;
+    commit_graph::notify_changed(&workspace_path);
+    Ok(report)
+}
+
+/// Restore the repo to an operation recorded before an interactive rebase
+#[tauri::command]
+pub fn jj_restore_operation(workspace_path: String, operation_id: String) -> Result<String, String> {
+    let result = jj_interactive_rebase::restore_operation(&workspace_path, &operation_id)
+        .map_err(|e| e.to_string())?;
+    commit_graph::notify_changed(&workspace_path);
+    Ok(result)
 }
 
 /// Get list of conflicted files in workspace
 #[tauri::command]
-pub fn jj_get_conflicted_files(workspace_path: String) -> Result<Vec<String>, String> {
-    jj::get_conflicted_files(&workspace_path, None).map_err(|e| e.to_string())
+pub fn jj_get_conflicted_files(workspace_path: String) -> Result<Vec<String>, jj::JjError> {
+    jj::get_conflicted_files(&workspace_path, None)
+}
+
+/// Get base/left/right sides of a conflicted file
+#[tauri::command]
+pub fn jj_get_conflict(workspace_path: String, file_path: String) -> Result<jj::JjConflict, jj::JjError> {
+    jj::jj_get_conflict(&workspace_path, &file_path)
+}
+
+/// Write the resolved content of a conflicted file
+#[tauri::command]
+pub fn jj_resolve_conflict(
+    workspace_path: String,
+    file_path: String,
+    resolved_content: String,
+) -> Result<jj::JjResolveResult, jj::JjError> {
+    jj::jj_resolve_conflict(&workspace_path, &file_path, &resolved_content)
 }
 
 /// Get the default branch of the repository (main/master)
 #[tauri::command]
-pub fn jj_get_default_branch(repo_path: String) -> Result<String, String> {
-    jj::get_default_branch(&repo_path).map_err(|e| e.to_string())
+pub fn jj_get_default_branch(repo_path: String) -> Result<String, jj::JjError> {
+    jj::get_default_branch(&repo_path)
 }
 
-/// Get the current branch of a workspace
+/// Re-query the remote's default branch after a fetch, retargeting workspaces and
+/// emitting `default-branch-changed` when it was renamed
 #[tauri::command]
-pub fn jj_get_current_branch(workspace_path: String) -> Result<String, String> {
-    jj::get_workspace_branch(&workspace_path).map_err(|e| e.to_string())
+pub async fn refresh_default_branch(
+    state: State<'_, AppState>,
+    repo_path: String,
+) -> Result<String, String> {
+    crate::default_branch::refresh_default_branch(&state.db, &repo_path).await
 }
 
-/// Push changes to remote using jj git push
+/// Get the current branch of a workspace
 #[tauri::command]
-pub fn jj_push(workspace_path: String, force: Option<bool>) -> Result<String, String> {
-    jj::jj_push(&workspace_path, force.unwrap_or(false)).map_err(|e| e.to_string())
+pub fn jj_get_current_branch(workspace_path: String) -> Result<String, jj::JjError> {
+    jj::get_workspace_branch(&workspace_path)
 }
 
-/// Get sync status with remote (ahead/behind counts)
+/// Push changes to `remote` (default: the repo's default remote) using jj git push.
+/// Force pushes from a main repo with workspaces need a `confirm_token` from
+/// `request_destructive_confirmation`, and force pushes of the default branch need the
+/// repo to allow them.
+/// `op_id` identifies its operation events and lets `cancel_operation` stop it.
 #[tauri::command]
-pub fn jj_get_sync_status(workspace_path: String, branch_name: String) -> Result<(usize, usize), String> {
-    jj::jj_get_sync_status(&workspace_path, &branch_name).map_err(|e| e.to_string())
+pub async fn jj_push(
+    state: State<'_, AppState>,
+    workspace_path: String,
+    force: Option<bool>,
+    remote: Option<String>,
+    confirm_token: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, String> {
+    let force = force.unwrap_or(false);
+    if force {
+        let (repo_path, context) = automation::workspace_context(&workspace_path);
+        let branch = context
+            .branch
+            .or_else(|| jj::get_workspace_branch(&workspace_path).ok());
+        if let Some(branch) = branch {
+            let db = state.db.lock().unwrap();
+            destructive_guard::check_force_push_branch(&db, &repo_path, &branch)?;
+        }
+        // Last, so a push refused above doesn't use up the confirmation
+        destructive_guard::check(
+            &workspace_path,
+            DestructiveOperation::ForcePush,
+            confirm_token.as_deref(),
+        )?;
+    }
+
+    let push = jj::jj_push(&workspace_path, force, remote.as_deref());
+    let result = operations::run_operation(OperationKind::Push, &workspace_path, op_id, push)
+        .await
+        .map_err(|e| e.to_string())?;
+    commit_graph::notify_changed(&workspace_path);
+    Ok(result)
 }
 
-/// Fetch remote branches using jj git fetch (without rebasing)
+/// Get sync status with remote (ahead/behind counts), deepening a shallow clone first
 #[tauri::command]
-pub fn jj_git_fetch(repo_path: String) -> Result<String, String> {
-    jj::jj_git_fetch(&repo_path).map_err(|e| e.to_string())
+pub async fn jj_get_sync_status(workspace_path: String, branch_name: String) -> Result<(usize, usize), jj::JjError> {
+    shallow_history::ensure_remote_merge_base(&workspace_path, &branch_name).await;
+    jj::jj_get_sync_status(&workspace_path, &branch_name)
 }
 
-/// Fetch remote branches in background (fire-and-forget)
+/// Fetch remote branches using jj git fetch (without rebasing), then pick up a renamed
+/// default branch. `op_id` identifies its operation events and lets `cancel_operation` stop it.
+#[tauri::command]
+pub async fn jj_git_fetch(
+    repo_path: String,
+    remote: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, String> {
+    let targets = crate::auto_rebase::snapshot_targets(&repo_path);
+    let fetch = jj::jj_git_fetch(&repo_path, remote.as_deref());
+    let output = operations::run_operation(OperationKind::Fetch, &repo_path, op_id, fetch)
+        .await
+        .map_err(|e| e.to_string())?;
+    result_cache::invalidate(&repo_path, &[CacheKind::Divergence]);
+    crate::default_branch::refresh_after_fetch(&repo_path).await;
+    crate::auto_rebase::spawn_rebase_on_target_updates(repo_path, targets);
+    Ok(output)
+}
+
+/// Fetch remote branches in background (fire-and-forget), then refresh CI checks of
+/// pushed workspace branches and rebase workspaces opted into following their target
 #[tauri::command]
 pub fn jj_git_fetch_background(repo_path: String) -> Result<(), String> {
     std::thread::spawn(move || {
-        let _ = jj::jj_git_fetch(&repo_path);
+        let targets = crate::auto_rebase::snapshot_targets(&repo_path);
+        if async_process::block_on(jj::jj_git_fetch_background(&repo_path)).is_ok() {
+            result_cache::invalidate(&repo_path, &[CacheKind::Divergence]);
+            ci_status::refresh_workspace_checks(&repo_path);
+            async_process::block_on(crate::default_branch::refresh_after_fetch(&repo_path));
+            crate::auto_rebase::rebase_on_target_updates(&repo_path, &targets);
+        }
     });
     Ok(())
 }
 
-/// Pull changes from remote using jj git fetch + rebase
+/// Pull changes from remote using jj git fetch + rebase.
+/// `op_id` identifies its operation events and lets `cancel_operation` stop it.
 #[tauri::command]
-pub fn jj_pull(workspace_path: String) -> Result<String, String> {
-    jj::jj_pull(&workspace_path).map_err(|e| e.to_string())
+pub async fn jj_pull(
+    workspace_path: String,
+    remote: Option<String>,
+    op_id: Option<String>,
+) -> Result<String, jj::JjError> {
+    let repo_path = jj::derive_repo_path_from_workspace(&workspace_path)
+        .unwrap_or_else(|| workspace_path.clone());
+    let targets = crate::auto_rebase::snapshot_targets(&repo_path);
+    let pull = jj::jj_pull(&workspace_path, remote.as_deref());
+    let result = operations::run_operation(OperationKind::Pull, &workspace_path, op_id, pull)
+        .await?;
+    result_cache::invalidate(&workspace_path, &[CacheKind::Status, CacheKind::Hunks]);
+    commit_graph::notify_changed(&workspace_path);
+    crate::auto_rebase::spawn_rebase_on_target_updates(repo_path, targets);
+    Ok(result)
 }
 
-/// Get commit log for a workspace
+/// Get commit log for a workspace. The commits ahead of the target branch are only
+/// complete once a shallow clone holds their merge base.
 #[tauri::command]
-pub fn jj_get_log(
+pub async fn jj_get_log(
     workspace_path: String,
     target_branch: String,
     is_home_repo: Option<bool>,
-) -> Result<jj::JjLogResult, String> {
-    jj::jj_get_log(&workspace_path, &target_branch, is_home_repo).map_err(|e| e.to_string())
+) -> Result<jj::JjLogResult, jj::JjError> {
+    if !is_home_repo.unwrap_or(false) {
+        shallow_history::ensure_workspace_merge_base(&workspace_path, &target_branch).await;
+    }
+    let log = jj::jj_get_log(&workspace_path, &target_branch, is_home_repo)?;
+    commit_graph::remember(&workspace_path, &target_branch, is_home_repo, &log);
+    Ok(log)
+}
+
+/// Most recent jj operations first (default 50)
+#[tauri::command]
+pub fn jj_op_log(workspace_path: String, limit: Option<usize>) -> Result<Vec<jj::JjOperation>, jj::JjError> {
+    jj::jj_op_log(&workspace_path, limit.unwrap_or(50))
+}
+
+/// Undo the most recent jj operation, e.g. a bad squash, rebase or discard
+#[tauri::command]
+pub fn jj_op_undo(workspace_path: String) -> Result<String, jj::JjError> {
+    let result = jj::jj_op_undo(&workspace_path)?;
+    commit_graph::notify_changed(&workspace_path);
+    Ok(result)
+}
+
+/// Restore the repo to its state after an earlier operation
+#[tauri::command]
+pub fn jj_op_restore(workspace_path: String, op_id: String) -> Result<String, jj::JjError> {
+    let result = jj::jj_op_restore(&workspace_path, &op_id)?;
+    commit_graph::notify_changed(&workspace_path);
+    Ok(result)
 }
 
 /// Get commits ahead of target branch (commits to be merged)
 #[tauri::command]
-pub fn jj_get_commits_ahead(
+pub async fn jj_get_commits_ahead(
     workspace_path: String,
     target_branch: String,
 ) -> Result<jj::JjCommitsAhead, String> {
-    jj::jj_get_commits_ahead(&workspace_path, &target_branch).map_err(|e| e.to_string())
+    // The working copy commit is part of the range, so file edits invalidate it too
+    git_cache::cached(
+        &workspace_path,
+        "commits_ahead",
+        &target_branch,
+        &[&target_branch],
+        CacheScope::WorkingTree,
+        || {
+            let accurate = async_process::block_on(shallow_history::ensure_workspace_merge_base(
+                &workspace_path,
+                &target_branch,
+            ));
+            jj::jj_get_commits_ahead(&workspace_path, &target_branch)
+                .map(|ahead| jj::JjCommitsAhead { accurate, ..ahead })
+                .map_err(|e| e.to_string())
+        },
+    )
 }
 
-/// Get combined diff between workspace and target branch
+/// Get combined diff between workspace and target branch, with the repo's whitespace
+/// setting
 #[tauri::command]
 pub fn jj_get_merge_diff(
+    state: State<AppState>,
+    repo_path: String,
     workspace_path: String,
     target_branch: String,
 ) -> Result<jj::JjRevisionDiff, String> {
-    jj::jj_get_merge_diff(&workspace_path, &target_branch).map_err(|e| e.to_string())
+    let detection = {
+        let db = state.db.lock().unwrap();
+        git_ops::load_diff_detection(&db, &repo_path)?
+    };
+    let params = serde_json::to_string(&(&target_branch, &detection)).unwrap_or_default();
+    git_cache::cached(
+        &workspace_path,
+        "merge_diff",
+        &params,
+        &[&target_branch],
+        CacheScope::History,
+        || {
+            jj::jj_get_merge_diff(&workspace_path, &target_branch, &detection)
+                .map_err(|e| e.to_string())
+        },
+    )
 }
 
-/// Create a merge commit combining workspace changes with target branch
+/// Commit the target branch points at, for passing back to `jj_create_merge` as the
+/// commit the merge preview was computed against
 #[tauri::command]
-pub fn jj_create_merge(
+pub fn get_merge_target_commit(
+    workspace_path: String,
+    target_branch: String,
+) -> Result<String, String> {
+    merge_gates::target_commit(&workspace_path, &target_branch)
+}
+
+#[tauri::command]
+pub fn get_merge_checks(
+    state: State<AppState>,
+    repo_path: String,
+) -> Result<MergeCheckConfig, String> {
+    let db = state.db.lock().unwrap();
+    merge_gates::load_merge_checks(&db, &repo_path)
+}
+
+#[tauri::command]
+pub fn set_merge_checks(
+    state: State<AppState>,
+    repo_path: String,
+    config: MergeCheckConfig,
+) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    merge_gates::save_merge_checks(&db, &repo_path, &config)
+}
+
+/// Create a merge commit combining workspace changes with target branch.
+/// The repo's merge checks run first; when `expected_target_commit` is given the target
+/// branch must still point at it. A failed gate returns the result with `blocked` set
+/// instead of merging. `op_id` identifies its operation events and lets
+/// `cancel_operation` stop it.
+#[tauri::command]
+pub async fn jj_create_merge(
+    state: State<'_, AppState>,
     workspace_path: String,
     workspace_branch: String,
     target_branch: String,
     message: String,
-) -> Result<jj::JjMergeResult, String> {
-    jj::jj_create_merge_commit(&workspace_path, &workspace_branch, &target_branch, &message)
-        .map_err(|e| e.to_string())
+    expected_target_commit: Option<String>,
+    op_id: Option<String>,
+) -> Result<MergeAttempt, String> {
+    let (repo_path, context) = automation::workspace_context(&workspace_path);
+    let (checks, tasks) = {
+        let db = state.db.lock().unwrap();
+        (
+            merge_gates::load_merge_checks(&db, &repo_path)?,
+            task_runner::load_tasks(&db, &repo_path)?,
+        )
+    };
+    // The checks run as part of the operation, so they report progress and stop when
+    // it is cancelled
+    let merge = async {
+        operations::report_phase("Running merge checks");
+        if let Some(blocked) = merge_gates::check(
+            &workspace_path,
+            &target_branch,
+            expected_target_commit.as_deref(),
+            &checks,
+            &tasks,
+        )
+        .await?
+        {
+            return Ok(MergeAttempt::from(blocked));
+        }
+        operations::report_phase("Merging");
+        vcs::backend_for_workspace(&workspace_path)
+            .merge(&workspace_path, &workspace_branch, &target_branch, &message)
+            .map(MergeAttempt::from)
+    };
+    let attempt = operations::run_operation(OperationKind::Merge, &workspace_path, op_id, merge).await?;
+    if attempt.blocked.is_some() {
+        return Ok(attempt);
+    }
+    let result = &attempt.result;
+    commit_graph::notify_changed(&workspace_path);
+
+    if result.success && !result.has_conflicts {
+        automation::fire(
+            &repo_path,
+            HookEvent::MergeLanded,
+            HookContext {
+                branch: Some(workspace_branch),
+                target_branch: Some(target_branch),
+                commit_id: result.merge_commit_id.clone(),
+                message: Some(message),
+                ..context
+            },
+        );
+    }
+    Ok(attempt)
 }
 
 /// Check if a branch exists locally and/or remotely
@@ -268,20 +794,37 @@ pub fn jj_create_merge(
 pub fn jj_check_branch_exists(
     repo_path: String,
     branch_name: String,
-) -> Result<jj::BranchStatus, String> {
-    jj::check_branch_exists(&repo_path, &branch_name).map_err(|e| e.to_string())
+    remote: Option<String>,
+) -> Result<jj::BranchStatus, jj::JjError> {
+    jj::check_branch_exists(&repo_path, &branch_name, remote.as_deref())
 }
 
 /// Get list of branches in the repository
 #[tauri::command]
-pub fn jj_get_branches(repo_path: String) -> Result<Vec<jj::JjBranch>, String> {
-    jj::get_branches(&repo_path).map_err(|e| e.to_string())
+pub fn jj_get_branches(repo_path: String) -> Result<Vec<jj::JjBranch>, jj::JjError> {
+    jj::get_branches(&repo_path)
+}
+
+/// Get bookmarks, tags, workspaces, remotes and recent changes for revset autocomplete
+#[tauri::command]
+pub fn get_revset_symbols(repo_path: String) -> Result<jj::JjRevsetSymbols, jj::JjError> {
+    jj::get_revset_symbols(&repo_path)
+}
+
+/// Search revisions by id prefix, bookmark or description for revision pickers
+#[tauri::command]
+pub fn search_revisions(
+    repo_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<jj::JjRevisionMatch>, jj::JjError> {
+    jj::search_revisions(&repo_path, &query, limit.unwrap_or(20))
 }
 
 /// Edit/switch to a bookmark (similar to git checkout)
 #[tauri::command]
-pub fn jj_edit_bookmark(repo_path: String, bookmark_name: String) -> Result<String, String> {
-    jj::jj_edit_bookmark(&repo_path, &bookmark_name).map_err(|e| e.to_string())
+pub fn jj_edit_bookmark(repo_path: String, bookmark_name: String) -> Result<String, jj::JjError> {
+    jj::jj_edit_bookmark(&repo_path, &bookmark_name)
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -292,21 +835,22 @@ pub struct BookmarkTrackingResult {
 }
 
 /// Track remote bookmarks for all workspaces in a repository
-/// Used on app startup to ensure bookmarks are properly tracked with origin
+/// Used on app startup to ensure bookmarks are properly tracked with the default remote
 #[tauri::command]
 pub fn jj_track_workspace_bookmarks(
     repo_path: String,
+    remote: Option<String>,
     state: State<AppState>,
 ) -> Result<BookmarkTrackingResult, String> {
 
-    let remote = "origin";
+    let remote = jj::resolve_remote(&repo_path, remote.as_deref()).map_err(|e| e.to_string())?;
+    let remote = remote.as_str();
 
     // Get currently tracked bookmarks
     let tracked_bookmarks = match jj::is_bookmark_tracked(&repo_path, "", remote) {
         Ok(_) => {
             // If we got here, use bookmark list command to get all tracked ones
-            match std::process::Command::new("jj")
-                .current_dir(&repo_path)
+            match jj::jj_command(&repo_path)
                 .args(["bookmark", "list", "--tracked", "--remote", remote])
                 .output()
             {
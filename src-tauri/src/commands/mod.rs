@@ -1,23 +1,49 @@
 // Command modules
+pub mod auth;
+pub mod automation;
 pub mod binary;
 pub mod file_view;
 pub mod file_watcher;
 pub mod filesystem;
+pub mod forge;
+pub mod git_commands;
+pub mod history;
+pub mod instance_lock;
 pub mod jj_commands;
+pub mod operations;
 pub mod pending_review;
+pub mod performance;
 pub mod pty_commands;
+pub mod search;
 pub mod session;
 pub mod settings;
+pub mod snapshots;
+pub mod tasks;
+pub mod updater;
 pub mod workspace;
+pub mod workspace_batch;
 
 // Re-export all commands for convenient access
+pub use auth::*;
+pub use automation::*;
 pub use binary::*;
 pub use file_view::*;
 pub use file_watcher::*;
 pub use filesystem::*;
+pub use forge::*;
+pub use git_commands::*;
+pub use history::*;
+pub use instance_lock::*;
 pub use jj_commands::*;
+pub use operations::*;
 pub use pending_review::*;
+pub use performance::*;
 pub use pty_commands::*;
+pub use search::*;
 pub use session::*;
 pub use settings::*;
+pub use snapshots::*;
+pub use tasks::*;
+pub use updater::*;
 pub use workspace::*;
+pub use workspace_batch::*;
@@ -0,0 +1,8 @@
+use crate::operations;
+
+/// Cancel a running push, pull, fetch, clone, rebase or merge started with `op_id`,
+/// killing its git/jj process. Returns false when the operation already finished.
+#[tauri::command]
+pub fn cancel_operation(op_id: String) -> Result<bool, String> {
+    Ok(operations::cancel_operation(&op_id))
+}
@@ -0,0 +1,16 @@
+use crate::process_limiter;
+use serde::Serialize;
+
+/// Runtime performance diagnostics
+#[derive(Serialize)]
+pub struct PerformanceReport {
+    /// Concurrency and queueing of spawned git/jj processes
+    pub processes: process_limiter::ProcessLimiterMetrics,
+}
+
+#[tauri::command]
+pub fn get_performance_report() -> Result<PerformanceReport, String> {
+    Ok(PerformanceReport {
+        processes: process_limiter::get_metrics(),
+    })
+}
@@ -1,6 +1,32 @@
+use crate::jj;
+use crate::local_db;
+use crate::pty::{PtyEnvironment, PtyManager};
+use crate::workspace_config;
 use crate::AppState;
+use std::path::Path;
 use tauri::{AppHandle, Emitter, State};
 
+/// Repository whose local db stores scrollback for a terminal started in `working_dir`
+fn scrollback_repo(working_dir: &str) -> Option<String> {
+    jj::derive_repo_path_from_workspace(working_dir).or_else(|| {
+        let dir = Path::new(working_dir);
+        (dir.join(".git").exists() || dir.join(".treq").exists()).then(|| working_dir.to_string())
+    })
+}
+
+/// Save a live session's scrollback to its repository's local db
+pub fn persist_scrollback(pty_manager: &PtyManager, session_id: &str) -> Result<(), String> {
+    let Some((Some(working_dir), content)) = pty_manager.scrollback_snapshot(session_id) else {
+        return Ok(());
+    };
+    match scrollback_repo(&working_dir) {
+        Some(repo_path) => {
+            local_db::save_pty_scrollback(&repo_path, session_id, Some(&working_dir), &content)
+        }
+        None => Ok(()),
+    }
+}
+
 #[tauri::command]
 pub fn pty_create_session(
     state: State<AppState>,
@@ -9,19 +35,58 @@ pub fn pty_create_session(
     working_dir: Option<String>,
     shell: Option<String>,
     initial_command: Option<String>,
+    environment: Option<PtyEnvironment>,
 ) -> Result<(), String> {
+    let mut environment = environment.unwrap_or_default();
+
+    // Variables from the workspace's .treq/workspace.toml apply unless the caller overrides them
+    if let Some(dir) = working_dir.as_deref() {
+        match workspace_config::load_workspace_config(dir) {
+            Ok(Some(config)) => {
+                for (key, value) in config.env {
+                    environment.env.entry(key).or_insert(value);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Ignoring workspace config for terminal: {}", e),
+        }
+    }
+
     let pty_manager = state.pty_manager.lock().unwrap();
     let sid = session_id.clone();
+    let repo_path = working_dir.as_deref().and_then(scrollback_repo);
 
     pty_manager.create_session(
-        session_id,
+        session_id.clone(),
         working_dir,
         shell,
         initial_command,
+        environment,
         Box::new(move |data| {
             let _ = app.emit(&format!("pty-data-{}", sid), data);
         }),
-    )
+    )?;
+
+    // Restore output from the session's previous run so pty_get_scrollback includes it
+    if let Some(repo_path) = repo_path {
+        match local_db::get_pty_scrollback(&repo_path, &session_id) {
+            Ok(Some(content)) => pty_manager.restore_scrollback(&session_id, &content)?,
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to load terminal scrollback: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Buffered output of a terminal session, including output restored from its previous run
+#[tauri::command]
+pub fn pty_get_scrollback(
+    state: State<AppState>,
+    session_id: String,
+) -> Result<Option<String>, String> {
+    let pty_manager = state.pty_manager.lock().unwrap();
+    Ok(pty_manager.get_scrollback(&session_id))
 }
 
 #[tauri::command]
@@ -50,5 +115,8 @@ pub fn pty_resize(
 #[tauri::command]
 pub fn pty_close(state: State<AppState>, session_id: String) -> Result<(), String> {
     let pty_manager = state.pty_manager.lock().unwrap();
+    if let Err(e) = persist_scrollback(&pty_manager, &session_id) {
+        log::warn!("Failed to save terminal scrollback: {}", e);
+    }
     pty_manager.close_session(&session_id)
 }
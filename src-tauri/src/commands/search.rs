@@ -0,0 +1,58 @@
+use crate::search::{self, SearchBatch, SearchOptions, SearchSummary};
+use tauri::{AppHandle, Emitter};
+
+/// Start a content search in the background. Matches arrive as `search-results`
+/// events and a `search-complete` event carries the summary; returns the search id.
+#[tauri::command]
+pub fn search_in_workspace(
+    app: AppHandle,
+    workspace_path: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<String, String> {
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+
+    let (search_id, cancelled) = search::start_search();
+    let id = search_id.clone();
+    std::thread::spawn(move || {
+        let options = options.unwrap_or_default();
+        let on_batch = |matches| {
+            let _ = app.emit(
+                "search-results",
+                SearchBatch {
+                    search_id: id.clone(),
+                    matches,
+                },
+            );
+        };
+        let summary = search::search_in_workspace(
+            &id,
+            &workspace_path,
+            &query,
+            &options,
+            &cancelled,
+            &on_batch,
+        )
+        .unwrap_or_else(|e| SearchSummary {
+            search_id: id.clone(),
+            total_matches: 0,
+            files_searched: 0,
+            files_with_matches: 0,
+            truncated: false,
+            cancelled: false,
+            error: Some(e),
+        });
+        search::finish_search(&id);
+        let _ = app.emit("search-complete", summary);
+    });
+
+    Ok(search_id)
+}
+
+/// Stop a running search; returns false if it already finished
+#[tauri::command]
+pub fn cancel_search(search_id: String) -> bool {
+    search::cancel_search(&search_id)
+}
@@ -1,4 +1,7 @@
-use crate::local_db::{self, Session};
+use crate::db::SessionModel;
+use crate::local_db::{self, Session, SessionTab};
+use crate::AppState;
+use tauri::State;
 
 #[tauri::command]
 pub fn create_session(
@@ -35,6 +38,116 @@ pub fn get_session_model(repo_path: String, id: i64) -> Result<Option<String>, S
 }
 
 #[tauri::command]
-pub fn set_session_model(repo_path: String, id: i64, model: Option<String>) -> Result<(), String> {
+pub fn set_session_model(
+    state: State<AppState>,
+    repo_path: String,
+    id: i64,
+    model: Option<String>,
+) -> Result<(), String> {
+    if let Some(model_id) = &model {
+        let db = state.db.lock().unwrap();
+        if !db.has_session_model(model_id).map_err(|e| e.to_string())? {
+            return Err(format!("Unknown model: {}", model_id));
+        }
+    }
     local_db::set_session_model(&repo_path, id, model)
 }
+
+#[tauri::command]
+pub fn list_session_models(state: State<AppState>) -> Result<Vec<SessionModel>, String> {
+    let db = state.db.lock().unwrap();
+    db.list_session_models().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_session_model(
+    state: State<AppState>,
+    provider: String,
+    model_id: String,
+    display_name: String,
+) -> Result<i64, String> {
+    let provider = provider.trim();
+    let model_id = model_id.trim();
+    if provider.is_empty() || model_id.is_empty() {
+        return Err("Provider and model id are required".to_string());
+    }
+    let display_name = match display_name.trim() {
+        "" => model_id,
+        name => name,
+    };
+    let db = state.db.lock().unwrap();
+    db.upsert_session_model(provider, model_id, display_name)
+        .map_err(|e| format!("Failed to save model: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_session_model(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    db.delete_session_model(id)
+        .map_err(|e| format!("Failed to delete model: {}", e))
+}
+
+#[tauri::command]
+pub fn set_default_session_model(state: State<AppState>, id: i64) -> Result<(), String> {
+    let mut db = state.db.lock().unwrap();
+    db.set_default_session_model(id)
+        .map_err(|e| format!("Failed to set default model: {}", e))
+}
+
+/// Open a new terminal tab at the end of a session's tab bar
+#[tauri::command]
+pub fn create_session_tab(
+    repo_path: String,
+    session_id: i64,
+    title: String,
+    cwd: Option<String>,
+) -> Result<SessionTab, String> {
+    local_db::add_session_tab(&repo_path, session_id, &title, cwd.as_deref())
+}
+
+#[tauri::command]
+pub fn get_session_tabs(repo_path: String, session_id: i64) -> Result<Vec<SessionTab>, String> {
+    local_db::get_session_tabs(&repo_path, session_id)
+}
+
+/// Tabs of all sessions of a workspace, for restoring its layout when it is reopened
+#[tauri::command]
+pub fn get_workspace_session_tabs(
+    repo_path: String,
+    workspace_id: Option<i64>,
+) -> Result<Vec<SessionTab>, String> {
+    local_db::get_workspace_session_tabs(&repo_path, workspace_id)
+}
+
+#[tauri::command]
+pub fn rename_session_tab(repo_path: String, tab_id: i64, title: String) -> Result<(), String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err("Tab title cannot be empty".to_string());
+    }
+    local_db::rename_session_tab(&repo_path, tab_id, title)
+}
+
+#[tauri::command]
+pub fn update_session_tab_state(
+    repo_path: String,
+    tab_id: i64,
+    cwd: Option<String>,
+    last_command: Option<String>,
+) -> Result<(), String> {
+    local_db::update_session_tab_state(&repo_path, tab_id, cwd.as_deref(), last_command.as_deref())
+}
+
+#[tauri::command]
+pub fn reorder_session_tabs(
+    repo_path: String,
+    session_id: i64,
+    tab_ids: Vec<i64>,
+) -> Result<(), String> {
+    local_db::reorder_session_tabs(&repo_path, session_id, &tab_ids)
+}
+
+#[tauri::command]
+pub fn delete_session_tab(repo_path: String, tab_id: i64) -> Result<(), String> {
+    local_db::delete_session_tab(&repo_path, tab_id)
+}
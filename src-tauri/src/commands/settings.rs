@@ -1,6 +1,19 @@
+use crate::commands::file_watcher;
+use crate::db::Database;
+use crate::file_guard;
+use crate::forge;
+use crate::jj;
+use crate::local_api::{self, LocalApiStatus};
+use crate::process_limiter;
+use crate::pty;
+use crate::secrets;
+use crate::settings_sync::{self, SettingsImportSummary};
+use crate::snapshots;
+use crate::vcs::{self, VcsKind};
+use crate::workspace_config::{self, EffectiveWorkspaceConfig, WorkspaceConfig, WorkspaceTemplate};
 use crate::AppState;
 use std::collections::HashMap;
-use tauri::State;
+use tauri::{AppHandle, State};
 
 #[tauri::command]
 pub fn get_setting(state: State<AppState>, key: String) -> Result<Option<String>, String> {
@@ -18,9 +31,110 @@ pub fn get_settings_batch(
 }
 
 #[tauri::command]
-pub fn set_setting(state: State<AppState>, key: String, value: String) -> Result<(), String> {
+pub fn set_setting(
+    app: AppHandle,
+    state: State<AppState>,
+    key: String,
+    value: String,
+) -> Result<(), String> {
     let db = state.db.lock().unwrap();
-    db.set_setting(&key, &value).map_err(|e| e.to_string())
+    if secrets::is_token_key(&key) {
+        secrets::set(&key, &value)?;
+    } else {
+        db.set_setting(&key, &value).map_err(|e| e.to_string())?;
+    }
+    apply_setting(&app, &state, &db, &key, &value);
+    Ok(())
+}
+
+/// Read a secret from the OS keychain, or the encrypted file used without one
+#[tauri::command]
+pub fn get_secret(key: String) -> Result<Option<String>, String> {
+    secrets::get(&key)
+}
+
+/// Store a secret; an empty value deletes it
+#[tauri::command]
+pub fn set_secret(
+    app: AppHandle,
+    state: State<AppState>,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    secrets::set(&key, &value)?;
+    let db = state.db.lock().unwrap();
+    apply_setting(&app, &state, &db, &key, &value);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_secret(app: AppHandle, state: State<AppState>, key: String) -> Result<(), String> {
+    secrets::delete(&key)?;
+    let db = state.db.lock().unwrap();
+    apply_setting(&app, &state, &db, &key, "");
+    Ok(())
+}
+
+/// Put a changed global setting into effect without a restart
+fn apply_setting(app: &AppHandle, state: &AppState, db: &Database, key: &str, value: &str) {
+    // Apply process concurrency changes immediately
+    if key == process_limiter::MAX_PARALLEL_PROCESSES_KEY {
+        process_limiter::set_max_concurrent(process_limiter::parse_max_concurrent_setting(Some(
+            value,
+        )));
+    }
+
+    // Apply the viewed/diffed file size limit immediately
+    if key == file_guard::MAX_FILE_SIZE_KEY {
+        file_guard::set_max_file_size(file_guard::parse_max_file_size_setting(Some(value)));
+    }
+
+    if key == snapshots::SNAPSHOT_RETENTION_KEY {
+        snapshots::set_retention(snapshots::parse_retention_setting(Some(value)));
+    }
+
+    if let Some(kind) = forge::ForgeKind::ALL
+        .into_iter()
+        .find(|kind| kind.token_key() == key)
+    {
+        forge::set_token(kind, Some(value));
+    }
+
+    // Apply terminal scrollback size to sessions created from now on
+    if key == pty::SCROLLBACK_BYTES_KEY {
+        state
+            .pty_manager
+            .lock()
+            .unwrap()
+            .set_scrollback_capacity(pty::parse_scrollback_setting(Some(value)));
+    }
+
+    // Start, restart or stop the local HTTP API when its settings change
+    if [
+        local_api::LOCAL_API_ENABLED_KEY,
+        local_api::LOCAL_API_PORT_KEY,
+        local_api::LOCAL_API_TOKEN_KEY,
+    ]
+    .contains(&key)
+    {
+        local_api::apply_settings(app, db);
+    }
+
+    reload_watchers_if_needed(state, db, key);
+}
+
+/// Restart file watchers when their debounce or ignore settings change
+fn reload_watchers_if_needed(state: &AppState, db: &Database, key: &str) {
+    if [
+        file_watcher::WATCHER_DEBOUNCE_MS_KEY,
+        file_watcher::WATCHER_IGNORE_KEY,
+    ]
+    .contains(&key)
+    {
+        for (path, e) in state.watcher_manager.reload_config(db) {
+            log::warn!("Failed to restart watcher for {}: {}", path, e);
+        }
+    }
 }
 
 #[tauri::command]
@@ -43,5 +157,116 @@ pub fn set_repo_setting(
 ) -> Result<(), String> {
     let db = state.db.lock().unwrap();
     db.set_repo_setting(&repo_path, &key, &value)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    apply_repo_setting(&state, &db, &repo_path, &key, &value);
+    Ok(())
+}
+
+fn apply_repo_setting(state: &AppState, db: &Database, repo_path: &str, key: &str, value: &str) {
+    if key == vcs::VCS_BACKEND_KEY {
+        vcs::set_preference(repo_path, Some(value));
+    }
+
+    if key == jj::MAX_NEW_FILE_SIZE_KEY {
+        jj::set_max_new_file_size(repo_path, Some(value));
+    }
+
+    reload_watchers_if_needed(state, db, key);
+}
+
+/// Write global settings, repo settings and workspace templates to a JSON file.
+/// Tokens are left out.
+#[tauri::command]
+pub fn export_settings(state: State<AppState>, dest_path: String) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    let export = settings_sync::export(&db)?;
+    settings_sync::write_export(&dest_path, &export)
+}
+
+/// Apply a file written by `export_settings` on top of the current settings
+#[tauri::command]
+pub fn import_settings(
+    app: AppHandle,
+    state: State<AppState>,
+    src_path: String,
+) -> Result<SettingsImportSummary, String> {
+    let export = settings_sync::read_export(&src_path)?;
+    let db = state.db.lock().unwrap();
+    let summary = settings_sync::import(&db, &export)?;
+
+    for key in &summary.settings {
+        apply_setting(&app, &state, &db, key, &export.settings[key]);
+    }
+    for repo_path in &summary.repos {
+        for (key, value) in &export.repos[repo_path].settings {
+            apply_repo_setting(&state, &db, repo_path, key, value);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Backend used for a repo's workspaces, from its setting or auto-detected
+#[tauri::command]
+pub fn get_vcs_backend(state: State<AppState>, repo_path: String) -> VcsKind {
+    let db = state.db.lock().unwrap();
+    vcs::load_preference(&db, &repo_path);
+    vcs::backend_kind(&repo_path)
+}
+
+#[tauri::command]
+pub fn get_workspace_config(workspace_path: String) -> Result<Option<WorkspaceConfig>, String> {
+    workspace_config::load_workspace_config(&workspace_path)
+}
+
+#[tauri::command]
+pub fn set_workspace_config(workspace_path: String, config: WorkspaceConfig) -> Result<(), String> {
+    workspace_config::save_workspace_config(&workspace_path, &config)
+}
+
+/// Workspace config merged with global and repo settings
+#[tauri::command]
+pub fn get_effective_workspace_config(
+    state: State<AppState>,
+    repo_path: String,
+    workspace_path: String,
+) -> Result<EffectiveWorkspaceConfig, String> {
+    let db = state.db.lock().unwrap();
+    workspace_config::resolve_workspace_config(&db, &repo_path, &workspace_path)
+}
+
+#[tauri::command]
+pub fn list_workspace_templates(
+    state: State<AppState>,
+    repo_path: String,
+) -> Result<Vec<WorkspaceTemplate>, String> {
+    let db = state.db.lock().unwrap();
+    workspace_config::load_templates(&db, &repo_path)
+}
+
+/// Create a workspace template, or replace the one with the same name
+#[tauri::command]
+pub fn save_workspace_template(
+    state: State<AppState>,
+    repo_path: String,
+    template: WorkspaceTemplate,
+) -> Result<Vec<WorkspaceTemplate>, String> {
+    let db = state.db.lock().unwrap();
+    workspace_config::save_template(&db, &repo_path, template)
+}
+
+#[tauri::command]
+pub fn delete_workspace_template(
+    state: State<AppState>,
+    repo_path: String,
+    name: String,
+) -> Result<Vec<WorkspaceTemplate>, String> {
+    let db = state.db.lock().unwrap();
+    workspace_config::delete_template(&db, &repo_path, &name)
+}
+
+#[tauri::command]
+pub fn get_local_api_status(state: State<AppState>) -> Result<LocalApiStatus, String> {
+    let db = state.db.lock().unwrap();
+    Ok(local_api::status(&db))
 }
@@ -0,0 +1,22 @@
+use crate::commit_graph;
+use crate::local_db::Snapshot;
+use crate::snapshots;
+
+/// Snapshots taken before destructive operations, most recent first
+#[tauri::command]
+pub fn list_snapshots(repo_path: String) -> Result<Vec<Snapshot>, String> {
+    snapshots::list(&repo_path)
+}
+
+/// Re-apply a snapshot to the workspace it was taken from
+#[tauri::command]
+pub fn restore_snapshot(repo_path: String, id: i64) -> Result<Snapshot, String> {
+    let snapshot = snapshots::restore(&repo_path, id)?;
+    commit_graph::notify_changed(&snapshot.workspace_path);
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub fn delete_snapshot(repo_path: String, id: i64) -> Result<(), String> {
+    snapshots::delete(&repo_path, id)
+}
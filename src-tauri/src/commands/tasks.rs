@@ -0,0 +1,70 @@
+use crate::automation;
+use crate::local_db::{self, JournalEntry};
+use crate::operations;
+use crate::task_runner::{self, TaskConfig, TASK_JOURNAL_KIND};
+use crate::AppState;
+use tauri::State;
+
+const DEFAULT_TASK_HISTORY_LIMIT: usize = 50;
+
+#[tauri::command]
+pub fn list_tasks(state: State<AppState>, repo_path: String) -> Result<TaskConfig, String> {
+    let db = state.db.lock().unwrap();
+    task_runner::load_tasks(&db, &repo_path)
+}
+
+#[tauri::command]
+pub fn set_tasks(
+    state: State<AppState>,
+    repo_path: String,
+    tasks: TaskConfig,
+) -> Result<(), String> {
+    let db = state.db.lock().unwrap();
+    task_runner::save_tasks(&db, &repo_path, &tasks)
+}
+
+/// Start a configured task in a workspace, returning the run id its `task-output` and
+/// `task-finished` events carry
+#[tauri::command]
+pub fn run_task(
+    state: State<AppState>,
+    workspace_path: String,
+    task_name: String,
+) -> Result<String, String> {
+    let (repo_path, context) = automation::workspace_context(&workspace_path);
+    let task = {
+        let db = state.db.lock().unwrap();
+        task_runner::load_tasks(&db, &repo_path)?
+            .remove(&task_name)
+            .ok_or_else(|| format!("Unknown task: {}", task_name))?
+    };
+    task_runner::run_task(
+        &repo_path,
+        &workspace_path,
+        context.workspace_id,
+        &task_name,
+        task,
+    )
+}
+
+/// Stop a task started with `run_task`, killing its process. Returns false when the run
+/// already finished.
+#[tauri::command]
+pub fn cancel_task(run_id: String) -> Result<bool, String> {
+    Ok(operations::cancel_operation(&run_id))
+}
+
+/// Past runs of a task (or of every task), newest first
+#[tauri::command]
+pub fn get_task_history(
+    repo_path: String,
+    task_name: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<JournalEntry>, String> {
+    local_db::get_journal_entries_of_kind(
+        &repo_path,
+        TASK_JOURNAL_KIND,
+        task_name.as_deref(),
+        limit.unwrap_or(DEFAULT_TASK_HISTORY_LIMIT),
+    )
+}
@@ -0,0 +1,39 @@
+use crate::updater::{self, RestartStatus, UpdateChannel, UpdateCheckResult};
+use crate::AppState;
+use tauri::{AppHandle, State};
+
+/// Check the release feed for a newer version. `channel` overrides the update_channel setting.
+#[tauri::command]
+pub fn check_for_updates(
+    state: State<AppState>,
+    channel: Option<String>,
+) -> Result<UpdateCheckResult, String> {
+    let channel = match channel {
+        Some(channel) => UpdateChannel::from_setting(Some(&channel)),
+        None => {
+            let db = state.db.lock().unwrap();
+            let value = db
+                .get_setting(updater::UPDATE_CHANNEL_KEY)
+                .map_err(|e| e.to_string())?;
+            UpdateChannel::from_setting(value.as_deref())
+        }
+    };
+    updater::check_for_updates(channel)
+}
+
+/// Restart to apply an update as soon as no agent run, rebase or index write is in flight
+#[tauri::command]
+pub fn schedule_update_restart(app: AppHandle) -> Result<RestartStatus, String> {
+    Ok(updater::schedule_restart(&app))
+}
+
+#[tauri::command]
+pub fn cancel_update_restart(app: AppHandle) -> Result<RestartStatus, String> {
+    updater::cancel_restart();
+    Ok(updater::restart_status(&app))
+}
+
+#[tauri::command]
+pub fn get_update_restart_status(app: AppHandle) -> Result<RestartStatus, String> {
+    Ok(updater::restart_status(&app))
+}
@@ -1,14 +1,36 @@
+use crate::async_process;
+use crate::automation::{self, HookContext, HookEvent};
+use crate::branch_names::{self, BranchNameSuggestion};
 use crate::jj::{self, JjRebaseResult};
-use crate::local_db::{self, Workspace};
+use crate::db::Database;
+use crate::local_db::{self, ArchivedWorkspace, Workspace};
+use crate::operations::{self, OperationKind};
+use crate::result_cache::CachedPayload;
+use crate::target_branch::{self, TargetBranchPreview};
+use crate::updater::OperationGuard;
+use crate::vcs;
+use crate::workspace_archive::{self, UnarchiveResult};
+use crate::workspace_config::{self, PostCreateCommandResult};
+use crate::workspace_disk_usage::{self, CleanResult, CleanablePath, WorkspaceDiskUsage};
+use crate::workspace_intent::{self, WorkspaceIntent};
+use crate::workspace_registry::{self, RegistryMismatch};
 use crate::AppState;
 use std::collections::HashSet;
 use std::path::Path;
 use std::sync::{Mutex, OnceLock};
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager};
 
 // Track which workspaces have been indexed this session
 static INDEXED_WORKSPACES: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
 
+/// Whether the file cache of a workspace was built this session, so watcher events
+/// can be applied to it incrementally
+pub(crate) fn is_workspace_indexed(workspace_path: &str) -> bool {
+    INDEXED_WORKSPACES
+        .get()
+        .is_some_and(|indexed| indexed.lock().unwrap().contains(workspace_path))
+}
+
 #[tauri::command]
 pub fn get_workspaces(repo_path: String) -> Result<Vec<Workspace>, String> {
     // Auto-recover stale workspaces when loading a repo
@@ -47,41 +69,105 @@ pub fn add_workspace_to_db(
     )
 }
 
-/// Combined command: creates jj workspace + adds to database atomically
+/// Payload of the `workspace-post-create` event, emitted when a template's
+/// post-create commands finish
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct WorkspacePostCreateResult {
+    pub workspace_id: i64,
+    pub template: String,
+    pub results: Vec<PostCreateCommandResult>,
+}
+
+/// Branch name for a new workspace from its intent, avoiding existing local and remote branches
 #[tauri::command]
+pub fn suggest_workspace_branch_name(
+    state: tauri::State<AppState>,
+    repo_path: String,
+    text: String,
+) -> Result<BranchNameSuggestion, String> {
+    let pattern = {
+        let db = state.db.lock().unwrap();
+        branch_names::branch_name_pattern(&db, &repo_path)
+    };
+    branch_names::suggest_workspace_branch_name(&repo_path, &pattern, &text)
+}
+
+/// Untracked files copied into new workspaces, from the "included_copy_files" setting
+fn inclusion_patterns(db: &Database, repo_path: &str) -> Option<Vec<String>> {
+    db.get_repo_setting(repo_path, "included_copy_files")
+        .ok()
+        .flatten()
+        .map(|patterns_str| {
+            patterns_str
+                .lines()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<String>>()
+        })
+}
+
+/// Combined command: creates jj workspace + adds to database atomically.
+/// `template` names a workspace template whose setup is applied to the new workspace.
+/// The workspace is created on `branch_name`; its steps are reported as operation
+/// events under `op_id`, or a generated id.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn create_workspace(
-    state: State<AppState>,
+    app: AppHandle,
     repo_path: String,
     branch_name: String,
     new_branch: bool,
     source_branch: Option<String>,
     metadata: Option<String>,
+    template: Option<String>,
+    op_id: Option<String>,
 ) -> Result<i64, String> {
-    // Load inclusion patterns from database
-    let inclusion_patterns = {
+    let target = repo_path.clone();
+    let create = async move {
+        create_workspace_steps(app, repo_path, branch_name, new_branch, source_branch, metadata, template)
+    };
+    async_process::block_on(operations::run_operation(OperationKind::WorkspaceCreate, &target, op_id, create))
+}
+
+fn create_workspace_steps(
+    app: AppHandle,
+    repo_path: String,
+    branch_name: String,
+    new_branch: bool,
+    source_branch: Option<String>,
+    metadata: Option<String>,
+    template: Option<String>,
+) -> Result<i64, String> {
+    // Load inclusion patterns and the template from database
+    let (inclusion_patterns, template) = {
+        let state = app.state::<AppState>();
         let db = state.db.lock().unwrap();
-        db.get_repo_setting(&repo_path, "included_copy_files")
-            .ok()
-            .flatten()
-            .map(|patterns_str| {
-                patterns_str
-                    .lines()
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect::<Vec<String>>()
-            })
+        vcs::load_preference(&db, &repo_path);
+        let template = template
+            .map(|name| workspace_config::find_template(&db, &repo_path, &name))
+            .transpose()?;
+        let mut patterns = inclusion_patterns(&db, &repo_path);
+        if let Some(template) = template.as_ref().filter(|t| !t.inclusion_patterns.is_empty()) {
+            let patterns = patterns.get_or_insert_with(Vec::new);
+            for pattern in &template.inclusion_patterns {
+                if !patterns.contains(pattern) {
+                    patterns.push(pattern.clone());
+                }
+            }
+        }
+        (patterns, template)
     };
 
-    // Create the jj workspace (returns sanitized workspace name)
-    let workspace_name = jj::create_workspace(
+    // Create the jj workspace or git worktree (returns sanitized workspace name)
+    operations::report_phase("Creating workspace");
+    let workspace_name = vcs::backend_for_repo(&repo_path).create_workspace(
         &repo_path,
         &branch_name, // Use branch name as workspace name
         &branch_name,
         new_branch,
         source_branch.as_deref(),
         inclusion_patterns,
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     // Derive workspace path
     let workspace_path = Path::new(&repo_path)
@@ -92,11 +178,12 @@ pub fn create_workspace(
         .to_string();
 
     // Add to database
+    operations::report_phase("Registering workspace");
     let workspace_id = local_db::add_workspace(
         &repo_path,
         workspace_name,
-        workspace_path,
-        branch_name,
+        workspace_path.clone(),
+        branch_name.clone(),
         metadata,
     )?;
 
@@ -107,6 +194,44 @@ pub fn create_workspace(
         "",  // Empty = will trigger rebase
     )?;
 
+    automation::fire(
+        &repo_path,
+        HookEvent::WorkspaceCreated,
+        HookContext {
+            workspace_id: Some(workspace_id),
+            workspace_path: Some(workspace_path.clone()),
+            branch: Some(branch_name),
+            ..Default::default()
+        },
+    );
+
+    if let Some(template) = template {
+        operations::report_phase("Applying template");
+        if let Some(target_branch) = template.target_branch.as_deref().filter(|b| !b.trim().is_empty()) {
+            local_db::update_workspace_target_branch(&repo_path, workspace_id, target_branch)?;
+        }
+        workspace_config::apply_template_env(&workspace_path, &template)?;
+
+        // Setup commands (installs, builds) can take minutes, so don't block creation on them
+        if !template.post_create_commands.is_empty() {
+            std::thread::spawn(move || {
+                let _operation = OperationGuard::start();
+                let results = workspace_config::run_post_create_commands(&workspace_path, &template);
+                if let Some(failed) = results.iter().find(|r| !r.success) {
+                    log::warn!("Post-create command '{}' failed for {}: {}", failed.command, workspace_path, failed.output);
+                }
+                let _ = app.emit(
+                    "workspace-post-create",
+                    WorkspacePostCreateResult {
+                        workspace_id,
+                        template: template.name,
+                        results,
+                    },
+                );
+            });
+        }
+    }
+
     Ok(workspace_id)
 }
 
@@ -122,15 +247,73 @@ pub fn delete_workspace_from_db(repo_path: String, id: i64) -> Result<(), String
 #[tauri::command]
 pub fn delete_workspace(repo_path: String, workspace_path: String, id: i64) -> Result<(), String> {
     // Step 1: Try to remove workspace files (best effort - log but don't fail)
-    if let Err(e) = jj::remove_workspace(&repo_path, &workspace_path) {
+    if let Err(e) = vcs::backend_for_repo(&repo_path).remove_workspace(&repo_path, &workspace_path) {
         eprintln!("Warning: Failed to remove workspace directory: {}", e);
         // Continue anyway - we still want to clean up DB
     }
+    workspace_disk_usage::invalidate_disk_usage(&repo_path);
 
     // Step 2: Always delete from database (cascade deletes sessions via foreign key)
     local_db::delete_workspace(&repo_path, id)
 }
 
+/// Save a workspace's uncommitted changes and remove its directory, keeping the branch
+#[tauri::command]
+pub fn archive_workspace(repo_path: String, workspace_id: i64) -> Result<ArchivedWorkspace, String> {
+    workspace_archive::archive_workspace(&repo_path, workspace_id)
+}
+
+/// Recreate an archived workspace and re-apply its saved changes
+#[tauri::command]
+pub fn unarchive_workspace(
+    app: AppHandle,
+    repo_path: String,
+    archive_id: i64,
+) -> Result<UnarchiveResult, String> {
+    let patterns = {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().unwrap();
+        inclusion_patterns(&db, &repo_path)
+    };
+    workspace_archive::unarchive_workspace(&repo_path, archive_id, patterns)
+}
+
+/// Size of each workspace directory, largest first. Walking every workspace can take a
+/// while, so it runs off the main thread and results are cached for ten minutes.
+#[tauri::command]
+pub async fn get_workspace_disk_usage(
+    repo_path: String,
+) -> Result<CachedPayload<Vec<WorkspaceDiskUsage>>, String> {
+    tokio::task::spawn_blocking(move || {
+        workspace_disk_usage::cached_workspace_disk_usage(&repo_path)
+    })
+    .await
+    .map_err(|e| format!("Disk usage task failed: {}", e))?
+}
+
+/// Gitignored directories of a workspace large enough to be worth deleting
+#[tauri::command]
+pub fn list_cleanable_paths(workspace_path: String) -> Result<Vec<CleanablePath>, String> {
+    workspace_disk_usage::list_cleanable_paths(&workspace_path)
+}
+
+/// Delete build artifacts (paths relative to the workspace); refuses paths outside it
+#[tauri::command]
+pub fn clean_paths(workspace_path: String, paths: Vec<String>) -> Result<CleanResult, String> {
+    workspace_disk_usage::clean_paths(&workspace_path, &paths)
+}
+
+#[tauri::command]
+pub fn list_archived_workspaces(repo_path: String) -> Result<Vec<ArchivedWorkspace>, String> {
+    workspace_archive::list_archived_workspaces(&repo_path)
+}
+
+/// Discard an archived workspace and its saved changes
+#[tauri::command]
+pub fn delete_archived_workspace(repo_path: String, archive_id: i64) -> Result<(), String> {
+    workspace_archive::delete_archived_workspace(&repo_path, archive_id)
+}
+
 /// Clean up stale workspace directories that don't have corresponding database entries
 /// This should be called on app startup to clean up any orphaned directories
 #[tauri::command]
@@ -138,6 +321,11 @@ pub fn cleanup_stale_workspaces(repo_path: String) -> Result<(), String> {
     use std::collections::HashSet;
     use std::path::Path;
 
+    // Orphaned directories are left by interrupted creates/deletes; none after a clean exit
+    if crate::shutdown::previous_shutdown_was_clean() {
+        return Ok(());
+    }
+
     let workspaces_dir = Path::new(&repo_path).join(".treq").join("workspaces");
 
     // If workspaces directory doesn't exist, nothing to clean up
@@ -241,7 +429,35 @@ pub fn check_and_update_stale_workspaces(
 
 #[tauri::command]
 pub fn rebuild_workspaces(repo_path: String) -> Result<Vec<Workspace>, String> {
-    local_db::rebuild_workspaces_from_filesystem(&repo_path)
+    let workspaces = local_db::rebuild_workspaces_from_filesystem(&repo_path)?;
+
+    // Rebuild only adds missing rows; report what it can't fix, e.g. jj-side orphans
+    match workspace_registry::reconcile_workspace_registry(&repo_path) {
+        Ok(mismatches) => {
+            for mismatch in mismatches {
+                log::warn!(
+                    "Workspace registry mismatch for '{}': {:?} (suggested fix: {:?})",
+                    mismatch.workspace_name,
+                    mismatch.kind,
+                    mismatch.suggested_fix
+                );
+            }
+        }
+        Err(e) => log::warn!("Failed to reconcile workspace registry: {}", e),
+    }
+
+    Ok(workspaces)
+}
+
+/// Cross-check jj, the filesystem and local_db, returning mismatches with suggested fixes
+#[tauri::command]
+pub fn reconcile_workspace_registry(repo_path: String) -> Result<Vec<RegistryMismatch>, String> {
+    workspace_registry::reconcile_workspace_registry(&repo_path)
+}
+
+#[tauri::command]
+pub fn apply_workspace_registry_fix(repo_path: String, mismatch: RegistryMismatch) -> Result<(), String> {
+    workspace_registry::apply_fix(&repo_path, &mismatch)
 }
 
 #[tauri::command]
@@ -253,6 +469,35 @@ pub fn update_workspace_metadata(
     local_db::update_workspace_metadata(&repo_path, id, &metadata)
 }
 
+#[tauri::command]
+pub fn get_workspace_intent(repo_path: String, id: i64) -> Result<WorkspaceIntent, String> {
+    workspace_intent::get_workspace_intent(&repo_path, id)
+}
+
+/// Replace the goal, acceptance criteria and linked issue, keeping other metadata
+#[tauri::command]
+pub fn set_workspace_intent(
+    repo_path: String,
+    id: i64,
+    intent: WorkspaceIntent,
+) -> Result<(), String> {
+    workspace_intent::set_workspace_intent(&repo_path, id, &intent)
+}
+
+/// Opt a workspace into being rebased in the background when a fetch advances its
+/// target branch; stored in its metadata
+#[tauri::command]
+pub fn set_workspace_auto_rebase(repo_path: String, id: i64, enabled: bool) -> Result<(), String> {
+    crate::auto_rebase::set_auto_rebase_on_target_update(&repo_path, id, enabled)
+}
+
+/// Markdown with the workspace intent, target branch and changed files, for pasting
+/// into an agent session
+#[tauri::command]
+pub fn export_workspace_context(repo_path: String, id: i64) -> Result<String, String> {
+    workspace_intent::export_workspace_context(&repo_path, id)
+}
+
 #[tauri::command]
 pub fn update_workspace_conflicts(
     repo_path: String,
@@ -271,9 +516,10 @@ pub fn list_conflicted_workspace_ids(repo_path: String) -> Result<Vec<i64>, Stri
 
     for workspace in workspaces {
         // Check actual conflict status from jj directly
+        let target_branch = crate::workspace_config::effective_target_branch(&workspace);
         let conflicted_files = jj::get_conflicted_files(
             &workspace.workspace_path,
-            workspace.target_branch.as_deref()
+            target_branch.as_deref()
         ).unwrap_or_default();
 
         if !conflicted_files.is_empty() {
@@ -329,6 +575,18 @@ pub fn ensure_workspace_indexed(
     Ok(true)
 }
 
+/// Validate a new target branch and show how the workspace diverges from it, so the
+/// change can be confirmed before `set_workspace_target_branch` rebases onto it
+#[tauri::command]
+pub async fn preview_workspace_target_branch(
+    repo_path: String,
+    id: i64,
+    target_branch: String,
+) -> Result<TargetBranchPreview, String> {
+    target_branch::preview_target_branch(&repo_path, id, &target_branch).await
+}
+
+/// Rebase the workspace onto an existing branch and save it as the target
 #[tauri::command]
 pub fn set_workspace_target_branch(
     repo_path: String,
@@ -336,12 +594,14 @@ pub fn set_workspace_target_branch(
     id: i64,
     target_branch: String,
 ) -> Result<JjRebaseResult, String> {
+    target_branch::validate_target_branch(&repo_path, &target_branch)?;
+
     // Convert Git remote branch format (origin/main) to jj format (main@origin)
     let jj_branch_name = crate::jj::convert_git_branch_to_jj_format_public(&target_branch, &repo_path);
 
     // Perform rebase
-    let rebase_result =
-        jj::jj_rebase_onto(&workspace_path, &jj_branch_name).map_err(|e| e.to_string())?;
+    let rebase_result = async_process::block_on(jj::jj_rebase_onto(&workspace_path, &jj_branch_name))
+        .map_err(|e| e.to_string())?;
 
     // If rebase succeeded, save the target branch (in Git format for UI)
     if rebase_result.success {
@@ -370,6 +630,7 @@ pub fn check_and_rebase_workspaces(
     default_branch: Option<String>,
     force: Option<bool>,
 ) -> Result<SingleRebaseResult, String> {
+    let _operation = OperationGuard::start();
     // If workspace_id provided, only rebase that workspace
     if let Some(id) = workspace_id {
         let default_branch = default_branch.unwrap_or_else(|| "main".to_string());
@@ -422,6 +683,138 @@ pub fn check_and_rebase_workspaces(
     }
 }
 
+/// Action applied by `bulk_workspace_action`
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkWorkspaceAction {
+    Fetch,
+    Rebase,
+    Push,
+    Archive,
+    Delete,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BulkWorkspaceResult {
+    pub workspace_id: i64,
+    pub workspace_name: Option<String>,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Payload of the `bulk-workspace-progress` event, emitted after each workspace
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BulkWorkspaceProgress {
+    pub action: BulkWorkspaceAction,
+    pub completed: usize,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub current: BulkWorkspaceResult,
+}
+
+fn run_bulk_action(
+    repo_path: &str,
+    workspace: &Workspace,
+    action: BulkWorkspaceAction,
+    default_branch: &str,
+    fetch_result: &Option<Result<String, String>>,
+) -> Result<String, String> {
+    match action {
+        BulkWorkspaceAction::Fetch => fetch_result
+            .clone()
+            .unwrap_or_else(|| Err("Fetch did not run".to_string())),
+        BulkWorkspaceAction::Rebase => {
+            match crate::auto_rebase::rebase_single_workspace(repo_path, workspace.id, default_branch, true)? {
+                Some(result) if result.rebase_result.success => Ok(result.rebase_result.message),
+                Some(result) => Err(result.rebase_result.message),
+                None => Ok("No rebase needed".to_string()),
+            }
+        }
+        BulkWorkspaceAction::Push => {
+            async_process::block_on(jj::jj_push(&workspace.workspace_path, false, None)).map_err(|e| e.to_string())
+        }
+        BulkWorkspaceAction::Archive => {
+            let archive = workspace_archive::archive_workspace(repo_path, workspace.id)?;
+            Ok(format!("Archived with {} changed file(s)", archive.file_count))
+        }
+        BulkWorkspaceAction::Delete => {
+            delete_workspace(repo_path.to_string(), workspace.workspace_path.clone(), workspace.id)?;
+            Ok("Deleted".to_string())
+        }
+    }
+}
+
+/// Apply one action to several workspaces, continuing past failures.
+/// Emits `bulk-workspace-progress` after each workspace and returns per-workspace results.
+#[tauri::command]
+pub fn bulk_workspace_action(
+    app: AppHandle,
+    repo_path: String,
+    workspace_ids: Vec<i64>,
+    action: BulkWorkspaceAction,
+) -> Result<Vec<BulkWorkspaceResult>, String> {
+    let default_branch = jj::get_default_branch(&repo_path).unwrap_or_else(|_| "main".to_string());
+
+    // Workspaces share one jj repo, so a single fetch updates all of them
+    let fetch_result = (action == BulkWorkspaceAction::Fetch && !workspace_ids.is_empty()).then(|| {
+        let targets = crate::auto_rebase::snapshot_targets(&repo_path);
+        let fetched = async_process::block_on(jj::jj_git_fetch(&repo_path, None)).map_err(|e| e.to_string());
+        if fetched.is_ok() {
+            crate::auto_rebase::spawn_rebase_on_target_updates(repo_path.clone(), targets);
+        }
+        fetched
+    });
+
+    let total = workspace_ids.len();
+    let mut results = Vec::with_capacity(total);
+    let mut succeeded = 0;
+
+    for id in workspace_ids {
+        let workspace = local_db::get_workspace_by_id(&repo_path, id)
+            .and_then(|w| w.ok_or_else(|| format!("Workspace {} not found", id)));
+
+        let result = match workspace {
+            Ok(workspace) => {
+                let outcome = run_bulk_action(&repo_path, &workspace, action, &default_branch, &fetch_result);
+                BulkWorkspaceResult {
+                    workspace_id: id,
+                    workspace_name: Some(workspace.workspace_name),
+                    success: outcome.is_ok(),
+                    message: outcome.unwrap_or_else(|e| e),
+                }
+            }
+            Err(e) => BulkWorkspaceResult {
+                workspace_id: id,
+                workspace_name: None,
+                success: false,
+                message: e,
+            },
+        };
+
+        if result.success {
+            succeeded += 1;
+        } else {
+            eprintln!("Warning: Bulk {:?} failed for workspace {}: {}", action, id, result.message);
+        }
+
+        let _ = app.emit(
+            "bulk-workspace-progress",
+            BulkWorkspaceProgress {
+                action,
+                completed: results.len() + 1,
+                total,
+                succeeded,
+                failed: results.len() + 1 - succeeded,
+                current: result.clone(),
+            },
+        );
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
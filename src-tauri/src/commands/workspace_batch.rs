@@ -0,0 +1,268 @@
+use crate::auto_rebase;
+use crate::jj::{self, JjFileChange};
+use crate::local_db::{self, Workspace};
+use crate::process_limiter;
+use crate::timestamps;
+use crate::updater::OperationGuard;
+use crate::vcs::{self, VcsKind};
+use crate::workspace_config;
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Outcome of a batch operation for one workspace
+#[derive(Debug, Serialize, Clone)]
+pub struct WorkspaceBatchResult<T> {
+    pub workspace_id: i64,
+    pub workspace_name: String,
+    pub success: bool,
+    pub result: Option<T>,
+    pub error: Option<String>,
+}
+
+/// Commits a workspace branch is ahead of / behind its remote counterpart
+#[derive(Debug, Serialize, Clone)]
+pub struct WorkspaceSyncStatus {
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WorkspaceStatusSummary {
+    pub branch_name: String,
+    pub target_branch: Option<String>,
+    pub changed_files: usize,
+    pub ahead: usize,
+    pub behind: usize,
+    pub has_conflicts: bool,
+}
+
+/// Everything the dashboard shows for one workspace
+#[derive(Debug, Serialize, Clone)]
+pub struct WorkspaceCard {
+    pub workspace_path: String,
+    pub branch_name: String,
+    pub target_branch: Option<String>,
+    /// Commits ahead of / behind the remote branch
+    pub ahead: usize,
+    pub behind: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub changed_files: Vec<JjFileChange>,
+    pub has_conflicts: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DashboardSnapshot {
+    pub repo_path: String,
+    pub generated_at: String,
+    pub workspaces: Vec<WorkspaceBatchResult<WorkspaceCard>>,
+}
+
+/// Run `op` for every workspace on a bounded pool of threads, returning results in
+/// workspace order. The pool is sized to the process limiter so batch work doesn't
+/// just queue behind it.
+fn run_batch<T, F>(workspaces: &[Workspace], op: F) -> Vec<WorkspaceBatchResult<T>>
+where
+    T: Send,
+    F: Fn(&Workspace) -> Result<T, String> + Sync,
+{
+    let workers = process_limiter::get_metrics()
+        .max_concurrent
+        .min(workspaces.len())
+        .max(1);
+    let next = AtomicUsize::new(0);
+    let outcomes: Vec<Mutex<Option<Result<T, String>>>> =
+        workspaces.iter().map(|_| Mutex::new(None)).collect();
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(workspace) = workspaces.get(index) else {
+                    break;
+                };
+                *outcomes[index].lock().unwrap() = Some(op(workspace));
+            });
+        }
+    });
+
+    workspaces
+        .iter()
+        .zip(outcomes)
+        .map(|(workspace, outcome)| {
+            let outcome = outcome
+                .into_inner()
+                .unwrap()
+                .unwrap_or_else(|| Err("Operation did not run".to_string()));
+            if let Err(e) = &outcome {
+                log::warn!(
+                    "Batch operation failed for workspace {}: {}",
+                    workspace.workspace_name,
+                    e
+                );
+            }
+            WorkspaceBatchResult {
+                workspace_id: workspace.id,
+                workspace_name: workspace.workspace_name.clone(),
+                success: outcome.is_ok(),
+                error: outcome.as_ref().err().cloned(),
+                result: outcome.ok(),
+            }
+        })
+        .collect()
+}
+
+/// Rebase every workspace of the repo onto `target_branch` concurrently
+#[tauri::command]
+pub fn workspaces_rebase_all_onto(
+    repo_path: String,
+    target_branch: String,
+) -> Result<Vec<WorkspaceBatchResult<String>>, String> {
+    if target_branch.is_empty() || target_branch.starts_with('-') || target_branch.contains('\0') {
+        return Err("Invalid target branch name".to_string());
+    }
+
+    let _operation = OperationGuard::start();
+    let backend = vcs::backend_for_repo(&repo_path);
+    let workspaces = local_db::get_workspaces(&repo_path)?;
+
+    Ok(run_batch(&workspaces, |workspace| {
+        if workspace.branch_name == target_branch {
+            return Ok("Workspace is on the target branch".to_string());
+        }
+
+        let result = backend.rebase_onto(
+            &workspace.workspace_path,
+            &workspace.branch_name,
+            &target_branch,
+        )?;
+
+        // jj records conflicts in the rebased commits instead of stopping
+        if backend.kind() == VcsKind::JjColocated {
+            let has_conflicts =
+                jj::get_conflicted_files(&workspace.workspace_path, Some(&target_branch))
+                    .map(|files| !files.is_empty())
+                    .unwrap_or(false);
+            local_db::update_workspace_has_conflicts(&repo_path, workspace.id, has_conflicts)?;
+        }
+
+        if result.success {
+            Ok(result.message)
+        } else {
+            Err(result.message)
+        }
+    }))
+}
+
+/// Fetch once for the repo, then report each workspace's ahead/behind counts
+#[tauri::command]
+pub fn workspaces_fetch_all(
+    repo_path: String,
+) -> Result<Vec<WorkspaceBatchResult<WorkspaceSyncStatus>>, String> {
+    let backend = vcs::backend_for_repo(&repo_path);
+    let targets = auto_rebase::snapshot_targets(&repo_path);
+    backend.fetch(&repo_path)?;
+    auto_rebase::spawn_rebase_on_target_updates(repo_path.clone(), targets);
+
+    let workspaces = local_db::get_workspaces(&repo_path)?;
+    Ok(run_batch(&workspaces, |workspace| {
+        let (ahead, behind) =
+            backend.sync_status(&workspace.workspace_path, &workspace.branch_name)?;
+        Ok(WorkspaceSyncStatus { ahead, behind })
+    }))
+}
+
+/// Changed files, remote sync and conflict state of every workspace
+#[tauri::command]
+pub fn workspaces_status_all(
+    repo_path: String,
+) -> Result<Vec<WorkspaceBatchResult<WorkspaceStatusSummary>>, String> {
+    let backend = vcs::backend_for_repo(&repo_path);
+    let workspaces = local_db::get_workspaces(&repo_path)?;
+
+    Ok(run_batch(&workspaces, |workspace| {
+        let changed_files = backend.changed_files(&workspace.workspace_path)?.len();
+        let (ahead, behind) =
+            backend.sync_status(&workspace.workspace_path, &workspace.branch_name)?;
+        Ok(WorkspaceStatusSummary {
+            branch_name: workspace.branch_name.clone(),
+            target_branch: workspace_config::effective_target_branch(workspace),
+            changed_files,
+            ahead,
+            behind,
+            has_conflicts: workspace.has_conflicts,
+        })
+    }))
+}
+
+/// Branch, remote divergence, line stats and changed files of every workspace in one
+/// call, so the dashboard doesn't issue several requests per workspace
+#[tauri::command]
+pub fn get_dashboard_snapshot(repo_path: String) -> Result<DashboardSnapshot, String> {
+    let backend = vcs::backend_for_repo(&repo_path);
+    let workspaces = local_db::get_workspaces(&repo_path)?;
+
+    let cards = run_batch(&workspaces, |workspace| {
+        let changed_files = backend.changed_files(&workspace.workspace_path)?;
+        let (insertions, deletions) = if changed_files.is_empty() {
+            (0, 0)
+        } else {
+            backend.diff_stat(&workspace.workspace_path)?
+        };
+        let (ahead, behind) =
+            backend.sync_status(&workspace.workspace_path, &workspace.branch_name)?;
+        Ok(WorkspaceCard {
+            workspace_path: workspace.workspace_path.clone(),
+            branch_name: workspace.branch_name.clone(),
+            target_branch: workspace_config::effective_target_branch(workspace),
+            ahead,
+            behind,
+            insertions,
+            deletions,
+            changed_files,
+            has_conflicts: workspace.has_conflicts,
+        })
+    });
+
+    Ok(DashboardSnapshot {
+        repo_path,
+        generated_at: timestamps::now(),
+        workspaces: cards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_db::tests::workspace_row;
+
+    #[test]
+    fn test_run_batch_keeps_order_and_reports_failures() {
+        let workspaces: Vec<Workspace> = (1..=12)
+            .map(|id| workspace_row(id, &format!("ws-{}", id)))
+            .collect();
+        let results = run_batch(&workspaces, |w| {
+            if w.id % 5 == 0 {
+                Err(format!("failed {}", w.id))
+            } else {
+                Ok(w.id * 10)
+            }
+        });
+
+        assert_eq!(results.len(), 12);
+        for (i, result) in results.iter().enumerate() {
+            let id = i as i64 + 1;
+            assert_eq!(result.workspace_id, id);
+            if id % 5 == 0 {
+                assert!(!result.success);
+                assert_eq!(
+                    result.error.as_deref(),
+                    Some(format!("failed {}", id).as_str())
+                );
+            } else {
+                assert_eq!(result.result, Some(id * 10));
+            }
+        }
+    }
+}
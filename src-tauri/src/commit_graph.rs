@@ -0,0 +1,250 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+
+use crate::jj::{self, JjLogCommit, JjLogResult};
+use crate::result_cache::{self, CacheKind};
+
+/// Handle used to emit `graph-delta` events, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Last log returned to the frontend per workspace path
+static GRAPH_CACHE: OnceLock<Mutex<HashMap<String, CachedGraph>>> = OnceLock::new();
+
+/// A cached log and the parameters needed to recompute it
+struct CachedGraph {
+    target_branch: String,
+    is_home_repo: Option<bool>,
+    log: JjLogResult,
+}
+
+/// A parent link in the commit graph
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphEdge {
+    pub child_id: String,
+    pub parent_id: String,
+}
+
+/// Payload of the `graph-delta` event: what changed since the last log the frontend saw
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct GraphDelta {
+    pub workspace_path: String,
+    pub added: Vec<JjLogCommit>,
+    pub removed: Vec<String>,
+    /// Commits whose id is unchanged but whose bookmarks, working copy flag or stats moved
+    pub updated: Vec<JjLogCommit>,
+    pub added_edges: Vec<GraphEdge>,
+    pub removed_edges: Vec<GraphEdge>,
+    pub target_branch: String,
+    pub workspace_branch: String,
+}
+
+impl GraphDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.updated.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+fn cache() -> &'static Mutex<HashMap<String, CachedGraph>> {
+    GRAPH_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+/// Record the log returned to the frontend as the base for later deltas
+pub fn remember(
+    workspace_path: &str,
+    target_branch: &str,
+    is_home_repo: Option<bool>,
+    log: &JjLogResult,
+) {
+    cache().lock().unwrap().insert(
+        workspace_path.to_string(),
+        CachedGraph {
+            target_branch: target_branch.to_string(),
+            is_home_repo,
+            log: log.clone(),
+        },
+    );
+}
+
+fn edges(commits: &[JjLogCommit]) -> HashSet<GraphEdge> {
+    commits
+        .iter()
+        .flat_map(|c| {
+            c.parent_ids.iter().map(|parent| GraphEdge {
+                child_id: c.commit_id.clone(),
+                parent_id: parent.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Diff two logs of the same workspace; commits keep the order of `new`
+fn compute_delta(workspace_path: &str, old: &JjLogResult, new: &JjLogResult) -> GraphDelta {
+    let old_commits: HashMap<&str, &JjLogCommit> = old
+        .commits
+        .iter()
+        .map(|c| (c.commit_id.as_str(), c))
+        .collect();
+    let new_ids: HashSet<&str> = new.commits.iter().map(|c| c.commit_id.as_str()).collect();
+
+    let mut added = Vec::new();
+    let mut updated = Vec::new();
+    for commit in &new.commits {
+        match old_commits.get(commit.commit_id.as_str()) {
+            None => added.push(commit.clone()),
+            Some(old_commit) if *old_commit != commit => updated.push(commit.clone()),
+            Some(_) => {}
+        }
+    }
+    let removed = old
+        .commits
+        .iter()
+        .filter(|c| !new_ids.contains(c.commit_id.as_str()))
+        .map(|c| c.commit_id.clone())
+        .collect();
+
+    let old_edges = edges(&old.commits);
+    let new_edges = edges(&new.commits);
+    let mut added_edges: Vec<GraphEdge> = new_edges.difference(&old_edges).cloned().collect();
+    let mut removed_edges: Vec<GraphEdge> = old_edges.difference(&new_edges).cloned().collect();
+    added_edges.sort();
+    removed_edges.sort();
+
+    GraphDelta {
+        workspace_path: workspace_path.to_string(),
+        added,
+        removed,
+        updated,
+        added_edges,
+        removed_edges,
+        target_branch: new.target_branch.clone(),
+        workspace_branch: new.workspace_branch.clone(),
+    }
+}
+
+/// Recompute the log of a workspace the frontend has loaded and emit `graph-delta`
+/// if it changed. Runs in the background; workspaces without a cached log are skipped.
+/// Cached ahead/behind counts of the workspace are dropped either way.
+pub fn notify_changed(workspace_path: &str) {
+    result_cache::invalidate(workspace_path, &[CacheKind::Divergence]);
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    let (target_branch, is_home_repo) = {
+        let cache = cache().lock().unwrap();
+        match cache.get(workspace_path) {
+            Some(cached) => (cached.target_branch.clone(), cached.is_home_repo),
+            None => return,
+        }
+    };
+
+    let app = app.clone();
+    let workspace_path = workspace_path.to_string();
+    std::thread::spawn(move || {
+        let log = match jj::jj_get_log(&workspace_path, &target_branch, is_home_repo) {
+            Ok(log) => log,
+            Err(e) => {
+                log::warn!(
+                    "Failed to refresh commit graph for {}: {}",
+                    workspace_path,
+                    e
+                );
+                return;
+            }
+        };
+
+        let delta = {
+            let mut cache = cache().lock().unwrap();
+            // Dropped or re-fetched with other parameters while the log was running
+            let Some(cached) = cache.get_mut(&workspace_path) else {
+                return;
+            };
+            if cached.target_branch != target_branch || cached.is_home_repo != is_home_repo {
+                return;
+            }
+            let delta = compute_delta(&workspace_path, &cached.log, &log);
+            cached.log = log;
+            delta
+        };
+
+        if !delta.is_empty() {
+            let _ = app.emit("graph-delta", &delta);
+        }
+    });
+}
+
+/// Whether a watcher event path is repository metadata that moves refs or heads
+pub fn is_refs_change(path: &str) -> bool {
+    path.contains("/.git/refs/")
+        || path.ends_with("/.git/HEAD")
+        || path.ends_with("/.git/packed-refs")
+        || path.contains("/.jj/repo/op_heads/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, parents: &[&str], bookmarks: &[&str]) -> JjLogCommit {
+        JjLogCommit {
+            commit_id: id.to_string(),
+            short_id: id.to_string(),
+            change_id: format!("change-{}", id),
+            description: String::new(),
+            author_name: "Test".to_string(),
+            timestamp: String::new(),
+            sort_key: 0,
+            parent_ids: parents.iter().map(|p| p.to_string()).collect(),
+            is_working_copy: false,
+            bookmarks: bookmarks.iter().map(|b| b.to_string()).collect(),
+            insertions: 0,
+            deletions: 0,
+        }
+    }
+
+    fn log(commits: Vec<JjLogCommit>) -> JjLogResult {
+        JjLogResult {
+            commits,
+            target_branch: "main".to_string(),
+            workspace_branch: "feature".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compute_delta() {
+        let old = log(vec![
+            commit("c", &["b"], &["feature"]),
+            commit("b", &["a"], &[]),
+            commit("a", &[], &["main"]),
+        ]);
+        // "c" was rewritten as "d" and "main" moved to "b"
+        let new = log(vec![
+            commit("d", &["b"], &["feature"]),
+            commit("b", &["a"], &["main"]),
+            commit("a", &[], &[]),
+        ]);
+
+        let delta = compute_delta("/ws", &old, &new);
+        assert_eq!(delta.added, vec![commit("d", &["b"], &["feature"])]);
+        assert_eq!(delta.removed, vec!["c".to_string()]);
+        let updated: Vec<&str> = delta.updated.iter().map(|c| c.commit_id.as_str()).collect();
+        assert_eq!(updated, ["b", "a"]);
+        let edge = |child: &str, parent: &str| GraphEdge {
+            child_id: child.to_string(),
+            parent_id: parent.to_string(),
+        };
+        assert_eq!(delta.added_edges, vec![edge("d", "b")]);
+        assert_eq!(delta.removed_edges, vec![edge("c", "b")]);
+
+        assert!(compute_delta("/ws", &new, &new).is_empty());
+    }
+}
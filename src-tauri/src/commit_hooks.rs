@@ -0,0 +1,227 @@
+//! Client-side git hooks for commits made through treq.
+//!
+//! jj never runs git hooks, and git's own failure output is easy to lose, so the
+//! `pre-commit` and `commit-msg` hooks are run here before committing, from the
+//! directory git would use (honouring `core.hooksPath`), with their output captured.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git_ops;
+use crate::jj;
+use crate::process_limiter::LimitedCommand;
+
+pub const PRE_COMMIT: &str = "pre-commit";
+pub const COMMIT_MSG: &str = "commit-msg";
+
+/// A hook that rejected a commit
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct HookFailure {
+    pub hook: String,
+    pub exit_code: Option<i32>,
+    /// Stdout followed by stderr
+    pub output: String,
+}
+
+impl HookFailure {
+    pub fn summary(&self) -> String {
+        match self.exit_code {
+            Some(code) => format!("{} hook exited with {}", self.hook, code),
+            None => format!("{} hook was terminated", self.hook),
+        }
+    }
+}
+
+/// Result of `jj_commit`: the commit output, or the hook that stopped the commit
+#[derive(Debug, Serialize, Clone)]
+pub struct CommitAttempt {
+    pub message: String,
+    pub hook_failure: Option<HookFailure>,
+}
+
+impl From<String> for CommitAttempt {
+    fn from(message: String) -> Self {
+        CommitAttempt {
+            message,
+            hook_failure: None,
+        }
+    }
+}
+
+impl From<HookFailure> for CommitAttempt {
+    fn from(failure: HookFailure) -> Self {
+        CommitAttempt {
+            message: format!("Commit rejected: {}", failure.summary()),
+            hook_failure: Some(failure),
+        }
+    }
+}
+
+/// Resolve a path inside the git directory, as git would for the worktree at `dir`
+fn git_path(dir: &str, name: &str) -> Option<PathBuf> {
+    let path = git_ops::run_git(dir, &["rev-parse", "--git-path", name]).ok()?;
+    Some(Path::new(dir).join(path.trim()))
+}
+
+/// Git directory path `name` for a workspace. jj workspaces other than the main repo
+/// have no git directory of their own and use the main repo's.
+fn workspace_git_path(workspace_path: &str, name: &str) -> Option<PathBuf> {
+    git_path(workspace_path, name).or_else(|| {
+        jj::derive_repo_path_from_workspace(workspace_path)
+            .and_then(|repo_path| git_path(&repo_path, name))
+    })
+}
+
+/// File the commit message is handed to `commit-msg` in. jj workspaces without a git
+/// directory of their own keep it under their `.jj` directory rather than sharing the
+/// main repo's `COMMIT_EDITMSG` between workspaces.
+fn commit_message_file(workspace_path: &str) -> Option<PathBuf> {
+    git_path(workspace_path, "COMMIT_EDITMSG").or_else(|| {
+        let jj_dir = Path::new(workspace_path).join(".jj");
+        jj_dir.is_dir().then(|| jj_dir.join("COMMIT_EDITMSG"))
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The hook script git would run for `name`, if one is installed
+pub fn find_hook(workspace_path: &str, name: &str) -> Option<PathBuf> {
+    let hooks_dir = workspace_git_path(workspace_path, "hooks")?;
+    let hook = hooks_dir.join(name);
+    is_executable(&hook).then_some(hook)
+}
+
+fn run_hook(
+    workspace_path: &str,
+    name: &str,
+    hook: &Path,
+    args: &[&str],
+) -> Result<Option<HookFailure>, String> {
+    // Hooks are shell scripts without an interpreter git for Windows would supply
+    let mut command = if cfg!(windows) {
+        let mut command = LimitedCommand::new("sh");
+        command.arg(hook);
+        command
+    } else {
+        LimitedCommand::new(hook)
+    };
+    let output = command
+        .current_dir(workspace_path)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {} hook: {}", name, e))?;
+    if output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(HookFailure {
+        hook: name.to_string(),
+        exit_code: output.status.code(),
+        output: format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+    }))
+}
+
+/// Run the `pre-commit` and `commit-msg` hooks for committing `message` from the
+/// workspace. Plain git worktrees must be staged first so `pre-commit` sees what
+/// will be committed. Returns the hook that failed, or None when the commit may proceed;
+/// `message` is replaced by the one `commit-msg` left behind.
+pub fn run_commit_hooks(
+    workspace_path: &str,
+    message: &mut String,
+) -> Result<Option<HookFailure>, String> {
+    if let Some(hook) = find_hook(workspace_path, PRE_COMMIT) {
+        if let Some(failure) = run_hook(workspace_path, PRE_COMMIT, &hook, &[])? {
+            return Ok(Some(failure));
+        }
+    }
+
+    let Some(hook) = find_hook(workspace_path, COMMIT_MSG) else {
+        return Ok(None);
+    };
+    let message_file = commit_message_file(workspace_path)
+        .ok_or_else(|| "Failed to locate the git directory".to_string())?;
+    fs::write(&message_file, format!("{}\n", message.trim_end()))
+        .map_err(|e| format!("Failed to write commit message: {}", e))?;
+    let file_arg = message_file.to_string_lossy();
+    if let Some(failure) = run_hook(workspace_path, COMMIT_MSG, &hook, &[&file_arg])? {
+        return Ok(Some(failure));
+    }
+    let edited = fs::read_to_string(&message_file)
+        .map_err(|e| format!("Failed to read commit message: {}", e))?;
+    if !edited.trim().is_empty() {
+        *message = edited.trim_end().to_string();
+    }
+    Ok(None)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::git_ops::tests::setup_git_repo;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn install_hook(dir: &Path, name: &str, script: &str) {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_commit_hooks_respect_hooks_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = &setup_git_repo(&temp_dir);
+
+        let mut message = "Add feature".to_string();
+        assert_eq!(run_commit_hooks(repo, &mut message).unwrap(), None);
+
+        // Hooks in .git/hooks are ignored once core.hooksPath points elsewhere
+        install_hook(&temp_dir.path().join(".git/hooks"), PRE_COMMIT, "exit 1");
+        git_ops::run_git(repo, &["config", "core.hooksPath", "githooks"]).unwrap();
+        let hooks = temp_dir.path().join("githooks");
+        install_hook(&hooks, COMMIT_MSG, r#"echo "Signed-off-by: Test" >> "$1""#);
+        assert_eq!(run_commit_hooks(repo, &mut message).unwrap(), None);
+        assert_eq!(message, "Add feature\nSigned-off-by: Test");
+
+        install_hook(
+            &hooks,
+            PRE_COMMIT,
+            "echo lint failed; echo details >&2; exit 3",
+        );
+        let failure = run_commit_hooks(repo, &mut message).unwrap().unwrap();
+        assert_eq!(
+            failure,
+            HookFailure {
+                hook: PRE_COMMIT.to_string(),
+                exit_code: Some(3),
+                output: "lint failed\ndetails\n".to_string(),
+            }
+        );
+        assert_eq!(failure.summary(), "pre-commit hook exited with 3");
+    }
+
+    #[test]
+    fn test_commit_message_file_is_per_workspace() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace = temp_dir.path().join("workspace");
+        fs::create_dir_all(workspace.join(".jj")).unwrap();
+        assert_eq!(
+            commit_message_file(workspace.to_str().unwrap()),
+            Some(workspace.join(".jj/COMMIT_EDITMSG"))
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::jj;
+
+/// Settings key for the signature format: "gpg", "ssh", or "none". Read from the repo
+/// settings first, then the global settings; unset means commits are not signed.
+pub const SIGNING_FORMAT_KEY: &str = "commit_signing_format";
+
+/// Settings key for the signing key: a GPG key id, or an SSH public key or key file.
+/// Unset leaves the choice to git's `user.signingkey`.
+pub const SIGNING_KEY_KEY: &str = "commit_signing_key";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningFormat {
+    Gpg,
+    Ssh,
+}
+
+impl SigningFormat {
+    /// Value of git's `gpg.format`
+    fn git_format(&self) -> &'static str {
+        match self {
+            SigningFormat::Gpg => "openpgp",
+            SigningFormat::Ssh => "ssh",
+        }
+    }
+
+    /// Value of jj's `signing.backend`
+    fn jj_backend(&self) -> &'static str {
+        match self {
+            SigningFormat::Gpg => "gpg",
+            SigningFormat::Ssh => "ssh",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SigningConfig {
+    pub format: SigningFormat,
+    pub key: Option<String>,
+}
+
+/// Parse the SIGNING_FORMAT_KEY setting. Returns None when the value is unset, so the
+/// next level applies, and Some(None) when signing is turned off.
+fn parse_format(value: Option<&str>) -> Result<Option<Option<SigningFormat>>, String> {
+    match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") => Ok(None),
+        Some("none") => Ok(Some(None)),
+        Some("gpg") | Some("openpgp") => Ok(Some(Some(SigningFormat::Gpg))),
+        Some("ssh") => Ok(Some(Some(SigningFormat::Ssh))),
+        Some(other) => Err(format!("Unknown commit signing format: {}", other)),
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+}
+
+/// Combine global and repo settings; each repo value that is set overrides the global one
+pub fn resolve_signing_config(
+    global_format: Option<&str>,
+    global_key: Option<&str>,
+    repo_format: Option<&str>,
+    repo_key: Option<&str>,
+) -> Result<Option<SigningConfig>, String> {
+    let format = match parse_format(repo_format)? {
+        Some(format) => format,
+        None => parse_format(global_format)?.flatten(),
+    };
+    let Some(format) = format else {
+        return Ok(None);
+    };
+
+    let key = non_empty(repo_key).or_else(|| non_empty(global_key));
+    if key.as_deref().is_some_and(|k| k.contains('\0')) {
+        return Err("Invalid commit signing key".to_string());
+    }
+    Ok(Some(SigningConfig { format, key }))
+}
+
+/// Signing configuration for commits made in `repo_path`, or None when signing is off
+pub fn load_signing_config(
+    db: &Database,
+    repo_path: &str,
+) -> Result<Option<SigningConfig>, String> {
+    let global = |key| db.get_setting(key).map_err(|e| e.to_string());
+    let repo = |key| {
+        db.get_repo_setting(repo_path, key)
+            .map_err(|e| e.to_string())
+    };
+    resolve_signing_config(
+        global(SIGNING_FORMAT_KEY)?.as_deref(),
+        global(SIGNING_KEY_KEY)?.as_deref(),
+        repo(SIGNING_FORMAT_KEY)?.as_deref(),
+        repo(SIGNING_KEY_KEY)?.as_deref(),
+    )
+}
+
+/// Signing configuration for commits made in a workspace, from its repo's settings
+pub fn load_workspace_signing_config(
+    db: &Database,
+    workspace_path: &str,
+) -> Result<Option<SigningConfig>, String> {
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    load_signing_config(db, &repo_path)
+}
+
+impl SigningConfig {
+    /// `-c` options placed before the git subcommand; pair with `-S` on the commit
+    pub fn git_config_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-c".to_string(),
+            format!("gpg.format={}", self.format.git_format()),
+        ];
+        if let Some(key) = &self.key {
+            args.push("-c".to_string());
+            args.push(format!("user.signingkey={}", key));
+        }
+        args
+    }
+
+    /// `--config` options placed before the jj subcommand so the commits it writes are
+    /// signed
+    pub fn jj_config_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--config".to_string(),
+            "signing.behavior=own".to_string(),
+            "--config".to_string(),
+            format!("signing.backend={}", self.format.jj_backend()),
+        ];
+        if let Some(key) = &self.key {
+            args.push("--config".to_string());
+            args.push(format!("signing.key={}", key));
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_signing_config() {
+        assert_eq!(
+            resolve_signing_config(None, None, None, None).unwrap(),
+            None
+        );
+
+        let global = resolve_signing_config(Some("gpg"), Some("ABCD1234"), None, Some(" "))
+            .unwrap()
+            .unwrap();
+        assert_eq!(global.format, SigningFormat::Gpg);
+        assert_eq!(global.key.as_deref(), Some("ABCD1234"));
+
+        let repo = resolve_signing_config(
+            Some("gpg"),
+            Some("ABCD1234"),
+            Some("SSH"),
+            Some("~/.ssh/id_ed25519.pub"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            repo.git_config_args(),
+            vec![
+                "-c",
+                "gpg.format=ssh",
+                "-c",
+                "user.signingkey=~/.ssh/id_ed25519.pub"
+            ]
+        );
+
+        assert_eq!(
+            resolve_signing_config(Some("ssh"), None, Some("none"), None).unwrap(),
+            None
+        );
+        assert!(resolve_signing_config(Some("x509"), None, None, None).is_err());
+    }
+}
@@ -1,42 +1,90 @@
-use chrono::Utc;
 use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 // Import Session type from local_db for internal use
 use crate::local_db::Session;
+use crate::migrations::{self, Migration};
+use crate::timestamps;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileView {
     pub id: i64,
     pub workspace_path: String,
+    /// Ref the file was diffed against when it was marked viewed
+    pub base_ref: String,
+    /// Workspace HEAD commit when it was marked viewed
+    pub head_sha: String,
     pub file_path: String,
     pub viewed_at: String,
     pub content_hash: String,
 }
 
-pub struct Database {
-    conn: Connection,
+/// A model that sessions can run with, as offered by the model picker
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionModel {
+    pub id: i64,
+    pub provider: String,
+    pub model_id: String,
+    pub display_name: String,
+    /// Model sessions of this provider run with when none is chosen for them
+    pub is_default: bool,
 }
 
-impl Database {
-    pub fn new(db_path: PathBuf) -> Result<Self> {
-        let conn = Connection::open(&db_path)?;
-        Ok(Database { conn })
-    }
+/// Models seeded into an empty registry: (provider, model id, display name, default)
+const BUILTIN_SESSION_MODELS: &[(&str, &str, &str, bool)] = &[
+    ("claude", "sonnet", "Sonnet", true),
+    ("claude", "opus", "Opus", false),
+    ("claude", "haiku", "Haiku", false),
+    ("claude", "sonnet[1m]", "Sonnet (1M)", false),
+    ("claude", "opusplan", "Opus Plan", false),
+];
+
+/// Schema changes for the app database, applied in order by `Database::init`
+const APP_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline schema",
+        apply: baseline_schema,
+    },
+    Migration {
+        version: 2,
+        name: "record repo paths",
+        apply: create_repo_paths,
+    },
+];
+
+/// The schema as it was before migrations were versioned. Each step is idempotent, so
+/// it also upgrades any older unversioned layout in place.
+fn baseline_schema(conn: &Connection) -> std::result::Result<(), String> {
+    create_tables(conn).map_err(|e| e.to_string())
+}
 
-    pub fn init(&self) -> Result<()> {
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS settings (
+/// Repo settings are stored under a hash of the repo path; keep the paths themselves so
+/// the settings can be listed per repo. Only the last opened repo is known for settings
+/// written before this table existed.
+fn create_repo_paths(conn: &Connection) -> std::result::Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS repo_paths (repo_path TEXT PRIMARY KEY);
+         INSERT OR IGNORE INTO repo_paths (repo_path)
+         SELECT value FROM settings WHERE key = 'repo_path' AND value <> '';",
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
                 key TEXT PRIMARY KEY,
                 value TEXT NOT NULL
             )",
-            [],
-        )?;
+        [],
+    )?;
 
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS sessions (
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sessions (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 workspace_id INTEGER,
                 type TEXT NOT NULL,
@@ -46,21 +94,29 @@ impl Database {
                 model TEXT,
                 FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
             )",
+        [],
+    )?;
+
+    // Columns added after the table was first created
+    if !migrations::has_column(conn, "sessions", "model")? {
+        conn.execute("ALTER TABLE sessions ADD COLUMN model TEXT", [])?;
+    }
+    if !migrations::has_column(conn, "sessions", "sort_key")? {
+        conn.execute(
+            "ALTER TABLE sessions ADD COLUMN sort_key INTEGER NOT NULL DEFAULT 0",
             [],
         )?;
+    }
 
-        // Migration: Add model column if it doesn't exist
-        let _ = self
-            .conn
-            .execute("ALTER TABLE sessions ADD COLUMN model TEXT", []);
+    // sessions references a workspaces table that only exists in the per-repo
+    // databases, which SQLite reports when deleting here; the cleanup is best effort
+    let _ = conn.execute(
+        "DELETE FROM sessions WHERE type IS NULL OR type <> 'session'",
+        [],
+    );
 
-        let _ = self.conn.execute(
-            "DELETE FROM sessions WHERE type IS NULL OR type <> 'session'",
-            [],
-        );
-
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS git_cache (
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS git_cache (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 workspace_path TEXT NOT NULL,
                 file_path TEXT,
@@ -69,22 +125,22 @@ impl Database {
                 updated_at TEXT NOT NULL,
                 UNIQUE(workspace_path, file_path, cache_type)
             )",
-            [],
-        )?;
-
-        // Migration: Rename worktree_path to workspace_path if needed
-        // First, check if the old column exists
-        let has_worktree_col: Result<i64, _> = self.conn.query_row(
-            "SELECT COUNT(*) FROM pragma_table_info('git_cache') WHERE name='worktree_path'",
-            [],
-            |row| row.get(0),
-        );
-
-        if let Ok(count) = has_worktree_col {
-            if count > 0 {
-                // Old schema exists, need to migrate
-                self.conn.execute(
-                    "CREATE TABLE git_cache_new (
+        [],
+    )?;
+
+    // Migration: Rename worktree_path to workspace_path if needed
+    // First, check if the old column exists
+    let has_worktree_col: Result<i64, _> = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('git_cache') WHERE name='worktree_path'",
+        [],
+        |row| row.get(0),
+    );
+
+    if let Ok(count) = has_worktree_col {
+        if count > 0 {
+            // Old schema exists, need to migrate
+            conn.execute(
+                "CREATE TABLE git_cache_new (
                         id INTEGER PRIMARY KEY AUTOINCREMENT,
                         workspace_path TEXT NOT NULL,
                         file_path TEXT,
@@ -93,50 +149,51 @@ impl Database {
                         updated_at TEXT NOT NULL,
                         UNIQUE(workspace_path, file_path, cache_type)
                     )",
-                    [],
-                )?;
+                [],
+            )?;
 
-                self.conn.execute(
+            conn.execute(
                     "INSERT INTO git_cache_new (id, workspace_path, file_path, cache_type, data, updated_at)
                      SELECT id, worktree_path, file_path, cache_type, data, updated_at FROM git_cache",
                     [],
                 )?;
 
-                self.conn.execute("DROP TABLE git_cache", [])?;
-                self.conn
-                    .execute("ALTER TABLE git_cache_new RENAME TO git_cache", [])?;
-            }
+            conn.execute("DROP TABLE git_cache", [])?;
+            conn.execute("ALTER TABLE git_cache_new RENAME TO git_cache", [])?;
         }
+    }
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_git_cache_workspace ON git_cache(workspace_path)",
-            [],
-        )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_git_cache_workspace ON git_cache(workspace_path)",
+        [],
+    )?;
 
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS file_views (
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS file_views (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 workspace_path TEXT NOT NULL,
+                base_ref TEXT NOT NULL DEFAULT '',
+                head_sha TEXT NOT NULL DEFAULT '',
                 file_path TEXT NOT NULL,
                 viewed_at TEXT NOT NULL,
                 content_hash TEXT NOT NULL DEFAULT '',
-                UNIQUE(workspace_path, file_path)
+                UNIQUE(workspace_path, base_ref, head_sha, file_path)
             )",
-            [],
-        )?;
-
-        // Migration: Rename worktree_path to workspace_path in file_views if needed
-        let has_worktree_col_fv: Result<i64, _> = self.conn.query_row(
-            "SELECT COUNT(*) FROM pragma_table_info('file_views') WHERE name='worktree_path'",
-            [],
-            |row| row.get(0),
-        );
-
-        if let Ok(count) = has_worktree_col_fv {
-            if count > 0 {
-                // Old schema exists, need to migrate
-                self.conn.execute(
-                    "CREATE TABLE file_views_new (
+        [],
+    )?;
+
+    // Migration: Rename worktree_path to workspace_path in file_views if needed
+    let has_worktree_col_fv: Result<i64, _> = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('file_views') WHERE name='worktree_path'",
+        [],
+        |row| row.get(0),
+    );
+
+    if let Ok(count) = has_worktree_col_fv {
+        if count > 0 {
+            // Old schema exists, need to migrate
+            conn.execute(
+                "CREATE TABLE file_views_new (
                         id INTEGER PRIMARY KEY AUTOINCREMENT,
                         workspace_path TEXT NOT NULL,
                         file_path TEXT NOT NULL,
@@ -144,47 +201,152 @@ impl Database {
                         content_hash TEXT NOT NULL DEFAULT '',
                         UNIQUE(workspace_path, file_path)
                     )",
-                    [],
-                )?;
-
-                // Check if content_hash exists in old table
-                let has_content_hash: Result<i64, _> = self.conn.query_row(
-                    "SELECT COUNT(*) FROM pragma_table_info('file_views') WHERE name='content_hash'",
-                    [],
-                    |row| row.get(0),
-                );
-
-                if let Ok(1) = has_content_hash {
-                    self.conn.execute(
+                [],
+            )?;
+
+            // Check if content_hash exists in old table
+            let has_content_hash: Result<i64, _> = conn.query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('file_views') WHERE name='content_hash'",
+                [],
+                |row| row.get(0),
+            );
+
+            if let Ok(1) = has_content_hash {
+                conn.execute(
                         "INSERT INTO file_views_new (id, workspace_path, file_path, viewed_at, content_hash)
                          SELECT id, worktree_path, file_path, viewed_at, content_hash FROM file_views",
                         [],
                     )?;
-                } else {
-                    self.conn.execute(
+            } else {
+                conn.execute(
                         "INSERT INTO file_views_new (id, workspace_path, file_path, viewed_at, content_hash)
                          SELECT id, worktree_path, file_path, viewed_at, '' FROM file_views",
                         [],
                     )?;
-                }
-
-                self.conn.execute("DROP TABLE file_views", [])?;
-                self.conn
-                    .execute("ALTER TABLE file_views_new RENAME TO file_views", [])?;
             }
+
+            conn.execute("DROP TABLE file_views", [])?;
+            conn.execute("ALTER TABLE file_views_new RENAME TO file_views", [])?;
         }
+    }
 
-        // Migration: Add content_hash column if it doesn't exist
-        let _ = self.conn.execute(
+    // Migration: Add content_hash column if it doesn't exist
+    if !migrations::has_column(conn, "file_views", "content_hash")? {
+        conn.execute(
             "ALTER TABLE file_views ADD COLUMN content_hash TEXT NOT NULL DEFAULT ''",
             [],
-        );
-
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_file_views_workspace ON file_views(workspace_path)",
-            [],
         )?;
+    }
 
+    // Migration: Scope viewed files by diff base and head commit. The unique key
+    // changes, so the table is rebuilt; existing rows keep an empty scope.
+    let has_base_ref: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('file_views') WHERE name='base_ref'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_base_ref == 0 {
+        scope_file_views(conn)?;
+    }
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_file_views_workspace ON file_views(workspace_path)",
+        [],
+    )?;
+
+    // Reviewed hunks, keyed by hunk content so progress survives rebases
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS hunk_views (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                workspace_path TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                viewed_at TEXT NOT NULL,
+                UNIQUE(workspace_path, file_path, content_hash)
+            )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_hunk_views_file ON hunk_views(workspace_path, file_path)",
+        [],
+    )?;
+
+    // Session model registry, driving the model picker and set_session_model validation
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_models (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                provider TEXT NOT NULL,
+                model_id TEXT NOT NULL,
+                display_name TEXT NOT NULL,
+                is_default INTEGER NOT NULL DEFAULT 0,
+                UNIQUE(provider, model_id)
+            )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Rebuild `file_views` with the diff scope columns. Runs in a savepoint so a failure
+/// never leaves the table dropped, with or without an enclosing transaction.
+fn scope_file_views(conn: &Connection) -> Result<()> {
+    conn.execute_batch("SAVEPOINT scope_file_views")?;
+    let rebuilt = conn.execute_batch(
+        "CREATE TABLE file_views_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                workspace_path TEXT NOT NULL,
+                base_ref TEXT NOT NULL DEFAULT '',
+                head_sha TEXT NOT NULL DEFAULT '',
+                file_path TEXT NOT NULL,
+                viewed_at TEXT NOT NULL,
+                content_hash TEXT NOT NULL DEFAULT '',
+                UNIQUE(workspace_path, base_ref, head_sha, file_path)
+            );
+         INSERT INTO file_views_new (id, workspace_path, file_path, viewed_at, content_hash)
+             SELECT id, workspace_path, file_path, viewed_at, content_hash FROM file_views;
+         DROP TABLE file_views;
+         ALTER TABLE file_views_new RENAME TO file_views;",
+    );
+    match rebuilt {
+        Ok(()) => conn.execute_batch("RELEASE scope_file_views"),
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO scope_file_views; RELEASE scope_file_views");
+            Err(e)
+        }
+    }
+}
+
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    pub fn new(db_path: PathBuf) -> Result<Self> {
+        let conn = Connection::open(&db_path)?;
+        Ok(Database { conn })
+    }
+
+    /// Create or migrate the schema, then seed the built-in session models
+    pub fn init(&self) -> std::result::Result<(), String> {
+        migrations::migrate(&self.conn, APP_MIGRATIONS)?;
+        self.seed_session_models()
+            .map_err(|e| format!("Failed to seed session models: {}", e))
+    }
+
+    fn seed_session_models(&self) -> Result<()> {
+        let model_count: i64 =
+            self.conn
+                .query_row("SELECT COUNT(*) FROM session_models", [], |row| row.get(0))?;
+        if model_count == 0 {
+            for (provider, model_id, display_name, is_default) in BUILTIN_SESSION_MODELS {
+                self.conn.execute(
+                    "INSERT INTO session_models (provider, model_id, display_name, is_default)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![provider, model_id, display_name, is_default],
+                )?;
+            }
+        }
         Ok(())
     }
 
@@ -209,6 +371,12 @@ impl Database {
         Ok(())
     }
 
+    pub fn delete_setting(&self, key: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM settings WHERE key = ?1", [key])?;
+        Ok(())
+    }
+
     pub fn get_settings_batch(
         &self,
         keys: &[String],
@@ -248,13 +416,62 @@ impl Database {
         Ok(result)
     }
 
+    /// Every global setting, excluding repo settings
+    pub fn list_settings(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM settings ORDER BY key")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let settings = rows.collect::<Result<Vec<(String, String)>>>()?;
+        Ok(settings
+            .into_iter()
+            .filter(|(key, _)| !Self::is_repo_key(key))
+            .collect())
+    }
+
     // Helper function to create composite key for repo-specific settings
     fn make_repo_key(repo_path: &str, key: &str) -> String {
+        format!("{}{}", Self::repo_key_prefix(repo_path), key)
+    }
+
+    fn repo_key_prefix(repo_path: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(repo_path.as_bytes());
         let hash = hasher.finalize();
         let hash_hex = format!("{:x}", hash);
-        format!("repo_{}_{}", &hash_hex[..16], key) // Use first 16 chars of hash
+        format!("repo_{}_", &hash_hex[..16]) // Use first 16 chars of hash
+    }
+
+    /// Whether a settings key was made by `make_repo_key`
+    fn is_repo_key(key: &str) -> bool {
+        key.strip_prefix("repo_")
+            .and_then(|rest| rest.get(..17))
+            .is_some_and(|hash| {
+                hash.ends_with('_') && hash[..16].bytes().all(|b| b.is_ascii_hexdigit())
+            })
+    }
+
+    /// Repos that have had a repo setting written
+    pub fn list_repo_paths(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT repo_path FROM repo_paths ORDER BY repo_path")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Every setting of one repo, keyed without the repo prefix
+    pub fn list_repo_settings(&self, repo_path: &str) -> Result<Vec<(String, String)>> {
+        let prefix = Self::repo_key_prefix(repo_path);
+        let mut stmt = self.conn.prepare(
+            "SELECT substr(key, ?2), value FROM settings
+             WHERE substr(key, 1, ?3) = ?1 ORDER BY key",
+        )?;
+        let rows = stmt.query_map(
+            params![prefix, prefix.len() as i64 + 1, prefix.len() as i64],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        rows.collect()
     }
 
     pub fn get_repo_setting(&self, repo_path: &str, key: &str) -> Result<Option<String>> {
@@ -264,20 +481,26 @@ impl Database {
 
     pub fn set_repo_setting(&self, repo_path: &str, key: &str, value: &str) -> Result<()> {
         let composite_key = Self::make_repo_key(repo_path, key);
-        self.set_setting(&composite_key, value)
+        self.set_setting(&composite_key, value)?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO repo_paths (repo_path) VALUES (?1)",
+            [repo_path],
+        )?;
+        Ok(())
     }
 
     #[allow(dead_code)]
     pub fn add_session(&self, session: &Session) -> Result<i64> {
         self.conn.execute(
-            "INSERT INTO sessions (workspace_id, type, name, created_at, last_accessed, model)
-             VALUES (?1, 'session', ?2, ?3, ?4, ?5)",
+            "INSERT INTO sessions (workspace_id, type, name, created_at, last_accessed, model, sort_key)
+             VALUES (?1, 'session', ?2, ?3, ?4, ?5, ?6)",
             (
                 &session.workspace_id,
                 &session.name,
                 &session.created_at,
                 &session.last_accessed,
                 &session.model,
+                &session.sort_key,
             ),
         )?;
         Ok(self.conn.last_insert_rowid())
@@ -286,8 +509,8 @@ impl Database {
     #[allow(dead_code)]
     pub fn get_sessions(&self) -> Result<Vec<Session>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, workspace_id, name, created_at, last_accessed, model
-             FROM sessions ORDER BY created_at ASC",
+            "SELECT id, workspace_id, name, created_at, last_accessed, model, sort_key
+             FROM sessions ORDER BY sort_key ASC, id ASC",
         )?;
 
         let sessions = stmt.query_map([], |row| {
@@ -298,6 +521,7 @@ impl Database {
                 created_at: row.get(3)?,
                 last_accessed: row.get(4)?,
                 model: row.get(5)?,
+                sort_key: row.get(6)?,
             })
         })?;
 
@@ -329,51 +553,83 @@ impl Database {
         Ok(())
     }
 
-    // File view tracking methods
+    // File view tracking methods. Viewed state is scoped to a diff: the workspace, the
+    // ref it is compared against and the workspace HEAD at the time.
     pub fn mark_file_viewed(
         &self,
         workspace_path: &str,
+        base_ref: &str,
+        head_sha: &str,
         file_path: &str,
         content_hash: &str,
     ) -> Result<()> {
-        let viewed_at = Utc::now().to_rfc3339();
+        let viewed_at = timestamps::now();
         self.conn.execute(
-            "INSERT INTO file_views (workspace_path, file_path, viewed_at, content_hash)
-             VALUES (?1, ?2, ?3, ?4)
-             ON CONFLICT(workspace_path, file_path)
+            "INSERT INTO file_views (workspace_path, base_ref, head_sha, file_path, viewed_at, content_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(workspace_path, base_ref, head_sha, file_path)
              DO UPDATE SET viewed_at = excluded.viewed_at, content_hash = excluded.content_hash",
-            params![workspace_path, file_path, viewed_at, content_hash],
+            params![workspace_path, base_ref, head_sha, file_path, viewed_at, content_hash],
         )?;
         Ok(())
     }
 
-    pub fn unmark_file_viewed(&self, workspace_path: &str, file_path: &str) -> Result<()> {
+    pub fn unmark_file_viewed(
+        &self,
+        workspace_path: &str,
+        base_ref: &str,
+        head_sha: &str,
+        file_path: &str,
+    ) -> Result<()> {
         self.conn.execute(
-            "DELETE FROM file_views WHERE workspace_path = ?1 AND file_path = ?2",
-            params![workspace_path, file_path],
+            "DELETE FROM file_views
+             WHERE workspace_path = ?1 AND base_ref = ?2 AND head_sha = ?3 AND file_path = ?4",
+            params![workspace_path, base_ref, head_sha, file_path],
         )?;
         Ok(())
     }
 
-    pub fn get_viewed_files(&self, workspace_path: &str) -> Result<Vec<FileView>> {
+    /// Viewed files of one diff. Files whose content hash differs from the one in
+    /// `current_hashes` changed since they were viewed, so their rows are dropped.
+    pub fn get_viewed_files(
+        &self,
+        workspace_path: &str,
+        base_ref: &str,
+        head_sha: &str,
+        current_hashes: &HashMap<String, String>,
+    ) -> Result<Vec<FileView>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, workspace_path, file_path, viewed_at, content_hash
+            "SELECT id, workspace_path, base_ref, head_sha, file_path, viewed_at, content_hash
              FROM file_views
-             WHERE workspace_path = ?1
+             WHERE workspace_path = ?1 AND base_ref = ?2 AND head_sha = ?3
              ORDER BY viewed_at DESC",
         )?;
 
-        let views = stmt.query_map([workspace_path], |row| {
-            Ok(FileView {
-                id: row.get(0)?,
-                workspace_path: row.get(1)?,
-                file_path: row.get(2)?,
-                viewed_at: row.get(3)?,
-                content_hash: row.get(4)?,
-            })
-        })?;
-
-        views.collect()
+        let views = stmt
+            .query_map(params![workspace_path, base_ref, head_sha], |row| {
+                Ok(FileView {
+                    id: row.get(0)?,
+                    workspace_path: row.get(1)?,
+                    base_ref: row.get(2)?,
+                    head_sha: row.get(3)?,
+                    file_path: row.get(4)?,
+                    viewed_at: row.get(5)?,
+                    content_hash: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        let (current, changed): (Vec<FileView>, Vec<FileView>) =
+            views.into_iter().partition(|view| {
+                current_hashes
+                    .get(&view.file_path)
+                    .is_none_or(|hash| *hash == view.content_hash)
+            });
+        for view in &changed {
+            self.conn
+                .execute("DELETE FROM file_views WHERE id = ?1", [view.id])?;
+        }
+        Ok(current)
     }
 
     pub fn clear_all_viewed_files(&self, workspace_path: &str) -> Result<()> {
@@ -381,6 +637,204 @@ impl Database {
             "DELETE FROM file_views WHERE workspace_path = ?1",
             [workspace_path],
         )?;
+        self.conn.execute(
+            "DELETE FROM hunk_views WHERE workspace_path = ?1",
+            [workspace_path],
+        )?;
+        Ok(())
+    }
+
+    // Hunk view tracking methods
+    pub fn mark_hunk_viewed(
+        &self,
+        workspace_path: &str,
+        file_path: &str,
+        content_hash: &str,
+    ) -> Result<()> {
+        let viewed_at = timestamps::now();
+        self.conn.execute(
+            "INSERT INTO hunk_views (workspace_path, file_path, content_hash, viewed_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(workspace_path, file_path, content_hash)
+             DO UPDATE SET viewed_at = excluded.viewed_at",
+            params![workspace_path, file_path, content_hash, viewed_at],
+        )?;
         Ok(())
     }
+
+    pub fn unmark_hunk_viewed(
+        &self,
+        workspace_path: &str,
+        file_path: &str,
+        content_hash: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM hunk_views
+             WHERE workspace_path = ?1 AND file_path = ?2 AND content_hash = ?3",
+            params![workspace_path, file_path, content_hash],
+        )?;
+        Ok(())
+    }
+
+    /// Content hashes of the reviewed hunks of a file
+    pub fn get_viewed_hunks(&self, workspace_path: &str, file_path: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT content_hash FROM hunk_views WHERE workspace_path = ?1 AND file_path = ?2",
+        )?;
+        let hashes = stmt.query_map(params![workspace_path, file_path], |row| row.get(0))?;
+        hashes.collect()
+    }
+
+    // Session model registry methods
+    pub fn list_session_models(&self) -> Result<Vec<SessionModel>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, provider, model_id, display_name, is_default
+             FROM session_models ORDER BY provider, id",
+        )?;
+        let models = stmt.query_map([], |row| {
+            Ok(SessionModel {
+                id: row.get(0)?,
+                provider: row.get(1)?,
+                model_id: row.get(2)?,
+                display_name: row.get(3)?,
+                is_default: row.get(4)?,
+            })
+        })?;
+        models.collect()
+    }
+
+    /// Whether any provider registers `model_id`
+    pub fn has_session_model(&self, model_id: &str) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM session_models WHERE model_id = ?1)",
+            [model_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Add a model, or rename it if the provider already registers `model_id`
+    pub fn upsert_session_model(
+        &self,
+        provider: &str,
+        model_id: &str,
+        display_name: &str,
+    ) -> Result<i64> {
+        self.conn.query_row(
+            "INSERT INTO session_models (provider, model_id, display_name)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(provider, model_id)
+             DO UPDATE SET display_name = excluded.display_name
+             RETURNING id",
+            params![provider, model_id, display_name],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn delete_session_model(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM session_models WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Make `id` the only default model of its provider
+    pub fn set_default_session_model(&mut self, id: i64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let provider: String = tx.query_row(
+            "SELECT provider FROM session_models WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "UPDATE session_models SET is_default = (id = ?1) WHERE provider = ?2",
+            params![id, provider],
+        )?;
+        tx.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_db() -> Database {
+        let db = Database {
+            conn: Connection::open_in_memory().unwrap(),
+        };
+        db.init().unwrap();
+        db
+    }
+
+    fn viewed_paths(views: &[FileView]) -> Vec<&str> {
+        views.iter().map(|v| v.file_path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_file_views_migration_keeps_unscoped_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE file_views (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                workspace_path TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                viewed_at TEXT NOT NULL,
+                content_hash TEXT NOT NULL DEFAULT '',
+                UNIQUE(workspace_path, file_path)
+            );
+            INSERT INTO file_views (workspace_path, file_path, viewed_at, content_hash)
+            VALUES ('/ws', 'a.rs', '2024-01-01T00:00:00Z', 'h1');",
+        )
+        .unwrap();
+        let db = Database { conn };
+        db.init().unwrap();
+
+        assert!(migrations::has_column(&db.conn, "file_views", "base_ref").unwrap());
+        assert!(!migrations::has_column(&db.conn, "file_views_new", "id").unwrap());
+        let views = db.get_viewed_files("/ws", "", "", &HashMap::new()).unwrap();
+        assert_eq!(viewed_paths(&views), vec!["a.rs"]);
+        assert_eq!(views[0].content_hash, "h1");
+    }
+
+    #[test]
+    fn test_file_views_rebuild_rolls_back_on_failure() {
+        let conn = Connection::open_in_memory().unwrap();
+        // Copying fails after file_views_new was created
+        conn.execute(
+            "CREATE TABLE file_views (id INTEGER PRIMARY KEY, workspace_path TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+
+        assert!(scope_file_views(&conn).is_err());
+        assert!(migrations::has_column(&conn, "file_views", "workspace_path").unwrap());
+        assert!(!migrations::has_column(&conn, "file_views_new", "id").unwrap());
+        assert!(conn.is_autocommit());
+    }
+
+    #[test]
+    fn test_get_viewed_files_drops_changed_hashes() {
+        let db = open_db();
+        db.mark_file_viewed("/ws", "main", "c1", "a.rs", "h1")
+            .unwrap();
+        db.mark_file_viewed("/ws", "main", "c1", "b.rs", "h2")
+            .unwrap();
+
+        // Views belong to one diff scope
+        let other_head = db
+            .get_viewed_files("/ws", "main", "c2", &HashMap::new())
+            .unwrap();
+        assert!(other_head.is_empty());
+
+        let current = HashMap::from([
+            ("a.rs".to_string(), "h1".to_string()),
+            ("b.rs".to_string(), "changed".to_string()),
+        ]);
+        let views = db.get_viewed_files("/ws", "main", "c1", &current).unwrap();
+        assert_eq!(viewed_paths(&views), vec!["a.rs"]);
+
+        // The changed file stays unviewed even once its hash is no longer passed
+        let views = db
+            .get_viewed_files("/ws", "main", "c1", &HashMap::new())
+            .unwrap();
+        assert_eq!(viewed_paths(&views), vec!["a.rs"]);
+    }
 }
@@ -0,0 +1,201 @@
+//! Tracks the default branch a repository's remote advertises, so a rename upstream
+//! (e.g. master -> main) is noticed and workspaces targeting the old name follow it.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::Database;
+use crate::git_ops;
+use crate::jj;
+use crate::local_db;
+use crate::AppState;
+
+/// Repo settings key holding the last default branch seen on the remote
+pub const DEFAULT_BRANCH_KEY: &str = "default_branch";
+
+/// Handle used to read settings and emit `default-branch-changed`, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Payload of the `default-branch-changed` event
+#[derive(Debug, Serialize, Clone)]
+pub struct DefaultBranchChanged {
+    pub repo_path: String,
+    pub previous: String,
+    pub current: String,
+    /// Workspaces whose target branch was moved from the old name to the new one
+    pub retargeted_workspace_ids: Vec<i64>,
+}
+
+/// DEFAULT_BRANCH_KEY of each repo, for `jj::get_default_branch`, which only receives
+/// a path
+static RECORDED: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn recorded_branches() -> &'static Mutex<HashMap<String, String>> {
+    RECORDED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The default branch last seen on the repo's remote, if it was ever refreshed
+pub fn recorded(repo_path: &str) -> Option<String> {
+    recorded_branches().lock().unwrap().get(repo_path).cloned()
+}
+
+fn set_recorded(repo_path: &str, branch: &str) {
+    recorded_branches()
+        .lock()
+        .unwrap()
+        .insert(repo_path.to_string(), branch.to_string());
+}
+
+/// Load the recorded default branch of every repo with settings
+pub fn load_recorded(db: &Database) {
+    for repo_path in db.list_repo_paths().unwrap_or_default() {
+        if let Some(branch) = db
+            .get_repo_setting(&repo_path, DEFAULT_BRANCH_KEY)
+            .ok()
+            .flatten()
+            .filter(|b| !b.is_empty())
+        {
+            set_recorded(&repo_path, &branch);
+        }
+    }
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+/// The target branch `target` becomes when the default branch is renamed, if it named
+/// the old default branch locally or on `remote`
+fn renamed_target(target: &str, remote: &str, previous: &str, current: &str) -> Option<String> {
+    if target == previous {
+        return Some(current.to_string());
+    }
+    let branch = target.strip_prefix(remote)?.strip_prefix('/')?;
+    (branch == previous).then(|| format!("{}/{}", remote, current))
+}
+
+/// Point workspaces targeting `previous` at `current`, returning their ids
+fn retarget_workspaces(repo_path: &str, remote: &str, previous: &str, current: &str) -> Vec<i64> {
+    let workspaces = match local_db::get_workspaces(repo_path) {
+        Ok(workspaces) => workspaces,
+        Err(e) => {
+            log::warn!("Failed to list workspaces to retarget: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut retargeted = Vec::new();
+    for workspace in workspaces {
+        let Some(target) = workspace.target_branch.as_deref() else {
+            continue;
+        };
+        let Some(new_target) = renamed_target(target, remote, previous, current) else {
+            continue;
+        };
+        match local_db::update_workspace_target_branch(repo_path, workspace.id, &new_target) {
+            Ok(()) => retargeted.push(workspace.id),
+            Err(e) => log::warn!(
+                "Failed to retarget workspace '{}': {}",
+                workspace.workspace_name,
+                e
+            ),
+        }
+    }
+    retargeted
+}
+
+/// Re-query the remote's default branch and remember it. When it differs from the one
+/// seen last, workspaces targeting the old branch are moved to the new one and
+/// `default-branch-changed` is emitted. Returns the current default branch. The
+/// database is only locked once the remote has answered.
+pub async fn refresh_default_branch(
+    db: &Mutex<Database>,
+    repo_path: &str,
+) -> Result<String, String> {
+    let remote = jj::default_remote(repo_path);
+    let current = match git_ops::git_update_remote_head(repo_path, &remote)
+        .await
+        .map_err(|e| format!("Failed to query default branch: {}", e))?
+    {
+        Some(branch) => branch,
+        None => jj::get_default_branch(repo_path).map_err(|e| e.to_string())?,
+    };
+
+    let previous = {
+        let db = db.lock().unwrap();
+        let previous = db
+            .get_repo_setting(repo_path, DEFAULT_BRANCH_KEY)
+            .map_err(|e| e.to_string())?
+            .filter(|b| !b.is_empty());
+        if previous.as_deref() == Some(current.as_str()) {
+            set_recorded(repo_path, &current);
+            return Ok(current);
+        }
+        db.set_repo_setting(repo_path, DEFAULT_BRANCH_KEY, &current)
+            .map_err(|e| e.to_string())?;
+        set_recorded(repo_path, &current);
+        previous
+    };
+
+    // The first refresh only records the branch
+    if let Some(previous) = previous {
+        log::info!(
+            "Default branch of {} changed from {} to {}",
+            repo_path,
+            previous,
+            current
+        );
+        let retargeted_workspace_ids = retarget_workspaces(repo_path, &remote, &previous, &current);
+        if let Some(app) = APP_HANDLE.get() {
+            let _ = app.emit(
+                "default-branch-changed",
+                DefaultBranchChanged {
+                    repo_path: repo_path.to_string(),
+                    previous,
+                    current: current.clone(),
+                    retargeted_workspace_ids,
+                },
+            );
+        }
+    }
+    Ok(current)
+}
+
+/// `refresh_default_branch` for callers without the database at hand, e.g. after a
+/// fetch; failures are logged
+pub async fn refresh_after_fetch(repo_path: &str) {
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    if let Err(e) = refresh_default_branch(&app.state::<AppState>().db, repo_path).await {
+        log::warn!("Default branch refresh failed for {}: {}", repo_path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renamed_target() {
+        assert_eq!(
+            renamed_target("master", "origin", "master", "main"),
+            Some("main".to_string())
+        );
+        assert_eq!(
+            renamed_target("origin/master", "origin", "master", "main"),
+            Some("origin/main".to_string())
+        );
+        assert_eq!(
+            renamed_target("origin/master-2", "origin", "master", "main"),
+            None
+        );
+        assert_eq!(
+            renamed_target("upstream/master", "origin", "master", "main"),
+            None
+        );
+        assert_eq!(renamed_target("develop", "origin", "master", "main"), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Confirmation for destructive operations on a main repository that has workspaces.
+//!
+//! Discarding changes, force pushing and deleting branches in the main repo can
+//! pull the rug out from under its workspaces, so those commands require a single-use
+//! token from `request_destructive_confirmation`. Force pushes to the default branch
+//! are refused everywhere unless the repo allows them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::db::Database;
+use crate::jj;
+use crate::local_db;
+
+/// Repo settings key that, when "true", allows force pushes to the default branch
+pub const ALLOW_FORCE_PUSH_DEFAULT_BRANCH_KEY: &str = "allow_force_push_default_branch";
+
+/// How long a confirmation token can be used
+const TOKEN_TTL: Duration = Duration::from_secs(120);
+
+/// Outstanding tokens and what they confirm
+static TOKENS: OnceLock<Mutex<HashMap<String, PendingConfirmation>>> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DestructiveOperation {
+    DiscardAllChanges,
+    /// Discarding a hunk or selected lines
+    DiscardChanges,
+    ForcePush,
+    DeleteBranch,
+    DeleteRemoteBranch,
+}
+
+impl DestructiveOperation {
+    fn label(&self) -> &'static str {
+        match self {
+            DestructiveOperation::DiscardAllChanges => "discard all changes",
+            DestructiveOperation::DiscardChanges => "discard changes",
+            DestructiveOperation::ForcePush => "force push",
+            DestructiveOperation::DeleteBranch => "delete a branch",
+            DestructiveOperation::DeleteRemoteBranch => "delete a remote branch",
+        }
+    }
+}
+
+struct PendingConfirmation {
+    repo_path: String,
+    operation: DestructiveOperation,
+    issued_at: Instant,
+}
+
+/// Token returned by `request_destructive_confirmation`
+#[derive(Debug, Serialize, Clone)]
+pub struct DestructiveConfirmation {
+    pub token: String,
+    pub operation: DestructiveOperation,
+    /// Workspaces of the repo, for the confirmation prompt
+    pub workspace_names: Vec<String>,
+    pub expires_in_secs: u64,
+}
+
+fn tokens() -> &'static Mutex<HashMap<String, PendingConfirmation>> {
+    TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| format!("Failed to generate confirmation token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Workspaces depending on `path` when it is a main repository; empty for workspace
+/// paths and repos without workspaces
+fn dependent_workspaces(path: &str) -> Vec<String> {
+    if jj::derive_repo_path_from_workspace(path).is_some() {
+        return Vec::new();
+    }
+    local_db::get_workspaces(path)
+        .map(|workspaces| workspaces.into_iter().map(|w| w.workspace_name).collect())
+        .unwrap_or_default()
+}
+
+/// Issue a single-use token confirming `operation` on `repo_path`
+pub fn request_confirmation(
+    repo_path: &str,
+    operation: DestructiveOperation,
+) -> Result<DestructiveConfirmation, String> {
+    let token = generate_token()?;
+    let mut pending = tokens().lock().unwrap();
+    pending.retain(|_, p| p.issued_at.elapsed() < TOKEN_TTL);
+    pending.insert(
+        token.clone(),
+        PendingConfirmation {
+            repo_path: repo_path.to_string(),
+            operation,
+            issued_at: Instant::now(),
+        },
+    );
+    Ok(DestructiveConfirmation {
+        token,
+        operation,
+        workspace_names: dependent_workspaces(repo_path),
+        expires_in_secs: TOKEN_TTL.as_secs(),
+    })
+}
+
+/// Use up `token` if it confirms `operation` on `path` and has not expired
+fn consume_token(path: &str, operation: DestructiveOperation, token: &str) -> bool {
+    let mut pending = tokens().lock().unwrap();
+    match pending.remove(token) {
+        Some(p) => {
+            p.repo_path == path && p.operation == operation && p.issued_at.elapsed() < TOKEN_TTL
+        }
+        None => false,
+    }
+}
+
+/// Allow `operation` on `path`, which needs a valid `confirm_token` when `path` is a
+/// main repository with workspaces
+pub fn check(
+    path: &str,
+    operation: DestructiveOperation,
+    confirm_token: Option<&str>,
+) -> Result<(), String> {
+    let workspaces = dependent_workspaces(path);
+    if workspaces.is_empty() {
+        return Ok(());
+    }
+    match confirm_token {
+        Some(token) if consume_token(path, operation, token) => Ok(()),
+        Some(_) => Err(format!(
+            "Confirmation to {} expired or does not match; request a new one",
+            operation.label()
+        )),
+        None => Err(format!(
+            "Refusing to {} in the main repository while it has {} workspace(s) without confirmation",
+            operation.label(),
+            workspaces.len()
+        )),
+    }
+}
+
+/// Refuse force pushes of the default branch unless the repo allows them
+pub fn check_force_push_branch(db: &Database, repo_path: &str, branch: &str) -> Result<(), String> {
+    let default_branch = jj::get_default_branch(repo_path).map_err(|e| e.to_string())?;
+    if branch != default_branch {
+        return Ok(());
+    }
+    let allowed = db
+        .get_repo_setting(repo_path, ALLOW_FORCE_PUSH_DEFAULT_BRANCH_KEY)
+        .map_err(|e| e.to_string())?
+        .is_some_and(|v| v.trim() == "true");
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "Force pushing the default branch '{}' is disabled for this repository",
+            branch
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tokens_are_single_use_and_scoped() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().to_str().unwrap();
+        let request = || {
+            request_confirmation(repo, DestructiveOperation::ForcePush)
+                .unwrap()
+                .token
+        };
+
+        let token = request();
+        assert!(!consume_token(
+            "/other",
+            DestructiveOperation::ForcePush,
+            &token
+        ));
+        // A mismatched attempt still uses the token up
+        assert!(!consume_token(
+            repo,
+            DestructiveOperation::ForcePush,
+            &token
+        ));
+
+        let token = request();
+        assert!(!consume_token(
+            repo,
+            DestructiveOperation::DeleteBranch,
+            &token
+        ));
+
+        let token = request();
+        assert!(consume_token(repo, DestructiveOperation::ForcePush, &token));
+        assert!(!consume_token(
+            repo,
+            DestructiveOperation::ForcePush,
+            &token
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Settings key: largest file, in bytes, that is loaded for viewing or diffing
+pub const MAX_FILE_SIZE_KEY: &str = "max_file_view_bytes";
+
+/// Default size limit (1 MiB)
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Bytes inspected for binary detection, same window git uses
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+static MAX_FILE_SIZE: AtomicU64 = AtomicU64::new(DEFAULT_MAX_FILE_SIZE);
+
+/// Why a changed file has no hunks, like the flags of `git_ops::BranchDiffFileDiff`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct SkippedDiff {
+    pub is_binary: bool,
+    /// Stored with Git LFS; the diff would only show the pointer file
+    pub is_lfs: bool,
+    /// Larger than the file size limit
+    pub too_large: bool,
+}
+
+/// File content, or why it was not loaded
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileContent {
+    Text {
+        content: String,
+    },
+    FileTooLarge {
+        size: u64,
+        max_size: u64,
+        mime: Option<String>,
+    },
+    BinaryFile {
+        size: u64,
+        mime: Option<String>,
+    },
+}
+
+/// Set the size limit. `None` restores the default.
+pub fn set_max_file_size(max: Option<u64>) {
+    MAX_FILE_SIZE.store(max.unwrap_or(DEFAULT_MAX_FILE_SIZE), Ordering::Relaxed);
+}
+
+pub fn max_file_size() -> u64 {
+    MAX_FILE_SIZE.load(Ordering::Relaxed)
+}
+
+/// Parse the MAX_FILE_SIZE_KEY setting; empty or invalid values mean "use the default"
+pub fn parse_max_file_size_setting(value: Option<&str>) -> Option<u64> {
+    value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|n| *n > 0)
+}
+
+/// NUL bytes or invalid UTF-8 in the leading bytes mean binary content
+fn is_binary(prefix: &[u8]) -> bool {
+    if prefix.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(prefix) {
+        Ok(_) => false,
+        // A multi-byte character cut off at the end of the window is still text
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+/// Guess a MIME type from magic bytes, falling back to the extension
+fn guess_mime(path: &Path, prefix: &[u8]) -> Option<String> {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"\0asm", "application/wasm"),
+        (b"SQLite format 3\0", "application/vnd.sqlite3"),
+    ];
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| prefix.starts_with(magic)) {
+        return Some(mime.to_string());
+    }
+
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "mp4" => "video/mp4",
+        "mp3" => "audio/mpeg",
+        "wasm" => "application/wasm",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "txt" | "log" => "text/plain",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
+
+/// Read the first `limit` bytes of a file
+fn read_prefix(path: &Path, limit: usize) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut prefix = Vec::with_capacity(limit);
+    file.take(limit as u64)
+        .read_to_end(&mut prefix)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(prefix)
+}
+
+/// Check size and leading bytes without loading the file. Returns the reason the file
+/// should not be loaded, or None when it is safe to read as text.
+pub fn check_file(path: &Path) -> Result<Option<FileContent>, String> {
+    let size = path
+        .metadata()
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+    let prefix = read_prefix(path, BINARY_SNIFF_BYTES)?;
+    Ok(check_prefix(path, size, &prefix))
+}
+
+/// Same check for content that is not on disk, e.g. a git blob. `prefix` holds the
+/// leading bytes; pass an empty slice when the content was not loaded.
+pub fn check_prefix(path: &Path, size: u64, prefix: &[u8]) -> Option<FileContent> {
+    let prefix = &prefix[..prefix.len().min(BINARY_SNIFF_BYTES)];
+    let mime = guess_mime(path, prefix);
+
+    if is_binary(prefix) {
+        return Some(FileContent::BinaryFile { size, mime });
+    }
+    let max_size = max_file_size();
+    if size > max_size {
+        return Some(FileContent::FileTooLarge {
+            size,
+            max_size,
+            mime,
+        });
+    }
+    None
+}
+
+/// Read a text file, refusing binary files and files over the size limit
+pub fn read_guarded(path: &Path) -> Result<FileContent, String> {
+    if let Some(guarded) = check_file(path)? {
+        return Ok(guarded);
+    }
+
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    match String::from_utf8(bytes) {
+        Ok(content) => Ok(FileContent::Text { content }),
+        // Invalid UTF-8 past the sniffed prefix
+        Err(e) => Ok(FileContent::BinaryFile {
+            size: e.as_bytes().len() as u64,
+            mime: guess_mime(path, e.as_bytes()),
+        }),
+    }
+}
+
+/// Why a worktree file should be left out of hunk parsing, or None to diff it. Missing
+/// files (e.g. deletions) are diffed normally.
+pub fn skipped_diff(workspace_path: &str, file_path: &str) -> Option<SkippedDiff> {
+    match check_file(&Path::new(workspace_path).join(file_path)) {
+        Ok(Some(FileContent::BinaryFile { .. })) => Some(SkippedDiff {
+            is_binary: true,
+            ..Default::default()
+        }),
+        Ok(Some(FileContent::FileTooLarge { .. })) => Some(SkippedDiff {
+            too_large: true,
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_guarded() {
+        let temp_dir = TempDir::new().unwrap();
+        let text = temp_dir.path().join("notes.md");
+        std::fs::write(&text, "héllo\n").unwrap();
+        assert_eq!(
+            read_guarded(&text).unwrap(),
+            FileContent::Text {
+                content: "héllo\n".to_string()
+            }
+        );
+
+        let image = temp_dir.path().join("logo.bin");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        assert_eq!(
+            read_guarded(&image).unwrap(),
+            FileContent::BinaryFile {
+                size: 16,
+                mime: Some("image/png".to_string())
+            }
+        );
+
+        let large = temp_dir.path().join("dump.json");
+        std::fs::write(&large, "x".repeat(DEFAULT_MAX_FILE_SIZE as usize + 1)).unwrap();
+        assert_eq!(
+            read_guarded(&large).unwrap(),
+            FileContent::FileTooLarge {
+                size: DEFAULT_MAX_FILE_SIZE + 1,
+                max_size: DEFAULT_MAX_FILE_SIZE,
+                mime: Some("application/json".to_string())
+            }
+        );
+        let workspace = temp_dir.path().to_str().unwrap();
+        assert_eq!(
+            skipped_diff(workspace, "dump.json"),
+            Some(SkippedDiff {
+                too_large: true,
+                ..Default::default()
+            })
+        );
+        assert!(skipped_diff(workspace, "logo.bin").unwrap().is_binary);
+        assert_eq!(skipped_diff(workspace, "deleted.txt"), None);
+    }
+
+    #[test]
+    fn test_is_binary_allows_split_multibyte_character() {
+        let text = "é".repeat(10);
+        assert!(!is_binary(&text.as_bytes()[..5]));
+        assert!(is_binary(b"abc\xffdef"));
+        assert!(is_binary(b"abc\0def"));
+    }
+}
@@ -1,28 +1,61 @@
-use crate::binary_paths;
+use crate::jj;
+use crate::language_stats;
 use crate::local_db::{self, CachedWorkspaceFile};
-use chrono::Utc;
-use std::collections::HashSet;
+use crate::timestamps;
+use ignore::gitignore::Gitignore;
+use ignore::{Match, WalkBuilder};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-/// Helper function to create Command for a binary using cached path
-fn command_for(binary: &str) -> Command {
-    let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
-    Command::new(path)
+/// Index writes in progress, so shutdown can wait for them instead of leaving a half-synced cache
+static PENDING_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+struct PendingWrite;
+
+impl PendingWrite {
+    fn start() -> Self {
+        PENDING_WRITES.fetch_add(1, Ordering::SeqCst);
+        PendingWrite
+    }
+}
+
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        PENDING_WRITES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Number of index writes currently in progress
+pub fn pending_write_count() -> usize {
+    PENDING_WRITES.load(Ordering::SeqCst)
+}
+
+/// Wait until in-progress index writes finish; returns false if the timeout elapsed first
+pub fn wait_for_pending_writes(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while PENDING_WRITES.load(Ordering::SeqCst) > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    true
 }
 
 /// Get list of all tracked files in a workspace using jj file list
 pub fn get_jj_tracked_files(workspace_path: &str) -> Result<Vec<String>, String> {
-    let output = command_for("jj")
+    let output = jj::jj_command(workspace_path)
         .args(["file", "list", "--quiet"])
-        .current_dir(workspace_path)
         .output()
         .map_err(|e| format!("Failed to run jj file list: {}", e))?;
 
     if !output.status.success() {
         return Err(format!(
             "jj file list failed: {}",
-            String::from_utf8_lossy(&output.stderr)
+            jj::command_error(&output.stderr)
         ));
     }
 
@@ -53,7 +86,7 @@ fn build_file_tree(
     let workspace_path_buf = Path::new(workspace_path);
     let mut cached_files = Vec::new();
     let mut directories_seen = HashSet::new();
-    let cached_at = Utc::now().to_rfc3339();
+    let cached_at = timestamps::now();
 
     // First pass: create entries for all files
     for file_path in files {
@@ -152,13 +185,345 @@ pub fn index_workspace_files(
     }
 
     // Sync to database
+    let _pending = PendingWrite::start();
     local_db::sync_workspace_files(repo_path, workspace_id, cached_files)?;
 
+    // Refresh the file type breakdown from the new cache
+    if let Err(e) = language_stats::refresh_language_stats(repo_path, workspace_id) {
+        log::warn!("Failed to refresh language stats: {}", e);
+    }
+
     Ok(())
 }
 
+/// A quick-open result with the matched character positions for highlighting
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct FuzzyFileMatch {
+    pub file_path: String,
+    pub relative_path: String,
+    pub score: i64,
+    /// Char indices into relative_path of the matched query characters
+    pub matched_indices: Vec<usize>,
+}
+
+/// Files modified within this window get a recency bonus, scaled by age
+const RECENCY_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+const RECENCY_MAX_BONUS: i64 = 20;
+
+/// Bonus for a match at position `i`, by what precedes it
+fn boundary_bonus(chars: &[char], i: usize) -> i64 {
+    let Some(&prev) = i.checked_sub(1).and_then(|p| chars.get(p)) else {
+        return 24;
+    };
+    match prev {
+        '/' => 24,
+        '_' | '-' | '.' | ' ' => 16,
+        _ if prev.is_lowercase() && chars[i].is_uppercase() => 12,
+        _ => 0,
+    }
+}
+
+/// Match `query` as a subsequence of `chars[start..]`, optionally skipping ahead to
+/// segment boundaries. Returns the score and matched indices.
+fn score_from(
+    query: &[char],
+    chars: &[char],
+    start: usize,
+    prefer_boundaries: bool,
+) -> Option<(i64, Vec<usize>)> {
+    let mut indices = Vec::with_capacity(query.len());
+    let mut pos = start;
+    for q in query {
+        let matches = |i: &usize| chars[*i].to_lowercase().eq(q.to_lowercase());
+        let next = (pos..chars.len()).find(matches)?;
+        // Jump ahead to a boundary occurrence if the next one isn't on a boundary
+        let next = if prefer_boundaries
+            && boundary_bonus(chars, next) == 0
+            && indices.last() != next.checked_sub(1).as_ref()
+        {
+            (next..chars.len())
+                .filter(matches)
+                .find(|&i| boundary_bonus(chars, i) > 0)
+                .unwrap_or(next)
+        } else {
+            next
+        };
+        indices.push(next);
+        pos = next + 1;
+    }
+
+    let mut score = 0;
+    for (n, &i) in indices.iter().enumerate() {
+        score += 16 + boundary_bonus(chars, i);
+        if n > 0 {
+            let gap = (i - indices[n - 1] - 1) as i64;
+            score += if gap == 0 { 8 } else { -gap.min(8) };
+        }
+    }
+    Some((score, indices))
+}
+
+/// Fuzzy-score `path` against `query`: a subsequence match, weighted toward segment
+/// starts, consecutive characters and the file name. None if it doesn't match.
+pub fn fuzzy_score(query: &str, path: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return None;
+    }
+    let chars: Vec<char> = path.chars().collect();
+    let name_start = chars.iter().rposition(|&c| c == '/').map(|i| i + 1).unwrap_or(0);
+
+    // Skipping ahead to boundaries can leave later characters unmatched
+    let best_from = |start| {
+        score_from(&query, &chars, start, true).or_else(|| score_from(&query, &chars, start, false))
+    };
+
+    // A match entirely within the file name beats one spread over directories
+    let in_name = best_from(name_start).map(|(s, i)| (s + 8 * query.len() as i64, i));
+    let best = match (in_name, best_from(0)) {
+        (Some(a), Some(b)) if b.0 > a.0 => b,
+        (a, b) => a.or(b)?,
+    };
+    // Shorter paths win ties
+    Some((best.0 - chars.len() as i64 / 8, best.1))
+}
+
+fn recency_bonus(mtime: Option<i64>, now: i64) -> i64 {
+    let Some(mtime) = mtime else {
+        return 0;
+    };
+    let age = (now - mtime).clamp(0, RECENCY_WINDOW_SECS);
+    RECENCY_MAX_BONUS * (RECENCY_WINDOW_SECS - age) / RECENCY_WINDOW_SECS
+}
+
+fn rank_files(
+    files: Vec<CachedWorkspaceFile>,
+    query: &str,
+    limit: usize,
+    now: i64,
+) -> Vec<FuzzyFileMatch> {
+    let mut matches: Vec<FuzzyFileMatch> = files
+        .into_iter()
+        .filter_map(|file| {
+            let (score, matched_indices) = fuzzy_score(query, &file.relative_path)?;
+            Some(FuzzyFileMatch {
+                score: score + recency_bonus(file.mtime, now),
+                file_path: file.file_path,
+                relative_path: file.relative_path,
+                matched_indices,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.relative_path.len().cmp(&b.relative_path.len()))
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+/// Quick-open search over the indexed files of a workspace, best matches first
+pub fn search_files(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<FuzzyFileMatch>, String> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let files = local_db::get_cached_files(repo_path, workspace_id)?;
+    Ok(rank_files(files, query, limit, chrono::Utc::now().timestamp()))
+}
+
+/// Directories never indexed from watcher events
+const SKIPPED_DIRS: &[&str] = &[".git", ".jj", ".treq"];
+
+/// Changed paths in one watcher batch above which a full rescan is cheaper than
+/// per-path updates
+const MAX_INCREMENTAL_PATHS: usize = 5000;
+
+/// Workspaces whose cache missed watcher events and must be rebuilt by a full rescan
+static DIRTY_WORKSPACES: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn dirty_workspaces() -> &'static Mutex<HashSet<String>> {
+    DIRTY_WORKSPACES.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Flag a workspace for a full rescan, e.g. after a watcher queue overflow
+pub fn mark_dirty(workspace_path: &str) {
+    dirty_workspaces()
+        .lock()
+        .unwrap()
+        .insert(workspace_path.to_string());
+}
+
+fn take_dirty(workspace_path: &str) -> bool {
+    dirty_workspaces().lock().unwrap().remove(workspace_path)
+}
+
+/// How a batch of watcher events was applied to the cache
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexUpdate {
+    Incremental { upserted: usize, deleted: usize },
+    FullRescan,
+}
+
+/// Gitignore rules of the workspace, loaded per directory as paths need them
+struct IgnoreRules {
+    workspace: PathBuf,
+    loaded: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreRules {
+    fn new(workspace_path: &Path) -> Self {
+        Self {
+            workspace: workspace_path.to_path_buf(),
+            loaded: HashMap::new(),
+        }
+    }
+
+    /// Whether the closest .gitignore with a matching rule ignores `path`
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if !current.starts_with(&self.workspace) {
+                break;
+            }
+            let rules = self.loaded.entry(current.to_path_buf()).or_insert_with(|| {
+                let file = current.join(".gitignore");
+                file.is_file().then(|| Gitignore::new(&file).0)
+            });
+            if let Some(rules) = rules {
+                match rules.matched_path_or_any_parents(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+            dir = current.parent();
+        }
+        false
+    }
+}
+
+/// Split watcher paths into relative files to (re)index and relative paths to drop.
+/// New directories are walked, since moving a directory in only reports the directory.
+fn classify_changes(workspace_path: &str, changed_paths: &[PathBuf]) -> (Vec<String>, Vec<String>) {
+    let workspace = Path::new(workspace_path);
+    let mut rules = IgnoreRules::new(workspace);
+    let mut upserts = HashSet::new();
+    let mut deletes = HashSet::new();
+
+    for path in changed_paths {
+        let Ok(relative) = path.strip_prefix(workspace) else {
+            continue;
+        };
+        let Some(relative_str) = relative.to_str().filter(|r| !r.is_empty()) else {
+            continue;
+        };
+        if relative
+            .components()
+            .any(|c| SKIPPED_DIRS.iter().any(|d| c.as_os_str() == *d))
+        {
+            continue;
+        }
+
+        if path.is_file() {
+            if !rules.is_ignored(path, false) {
+                upserts.insert(relative_str.to_string());
+            }
+        } else if path.is_dir() {
+            if rules.is_ignored(path, true) {
+                continue;
+            }
+            let walker = WalkBuilder::new(path)
+                .hidden(false)
+                .require_git(false)
+                .filter_entry(|entry| !SKIPPED_DIRS.iter().any(|d| entry.file_name() == *d))
+                .build();
+            for entry in walker.flatten() {
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    continue;
+                }
+                if let Some(file) = entry
+                    .path()
+                    .strip_prefix(workspace)
+                    .ok()
+                    .and_then(|p| p.to_str())
+                {
+                    upserts.insert(file.to_string());
+                }
+            }
+        } else {
+            deletes.insert(relative_str.to_string());
+        }
+    }
+
+    let mut upserts: Vec<String> = upserts.into_iter().collect();
+    let mut deletes: Vec<String> = deletes.into_iter().collect();
+    upserts.sort();
+    deletes.sort();
+    (upserts, deletes)
+}
+
 /// Incrementally update specific files in the index
-/// Only updates the files that have actually changed, instead of full replacement
+/// Only updates the files that have actually changed, instead of full replacement.
+/// Falls back to a full rescan when the workspace was marked dirty or the batch is
+/// too large to be worth applying path by path.
+pub fn update_workspace_files(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+    workspace_path: &str,
+    changed_paths: &[PathBuf],
+) -> Result<IndexUpdate, String> {
+    if changed_paths.len() > MAX_INCREMENTAL_PATHS {
+        mark_dirty(workspace_path);
+    }
+    if take_dirty(workspace_path) {
+        if let Err(e) = index_workspace_files(repo_path, workspace_id, workspace_path) {
+            mark_dirty(workspace_path);
+            return Err(e);
+        }
+        return Ok(IndexUpdate::FullRescan);
+    }
+
+    let (upserts, deletes) = classify_changes(workspace_path, changed_paths);
+    if upserts.is_empty() && deletes.is_empty() {
+        return Ok(IndexUpdate::Incremental {
+            upserted: 0,
+            deleted: 0,
+        });
+    }
+
+    let upserted = upserts.len();
+    let mut cached_files = build_file_tree(workspace_path, upserts)?;
+    for file in &mut cached_files {
+        file.workspace_id = workspace_id;
+    }
+
+    let _pending = PendingWrite::start();
+    let deleted_paths: Vec<String> = deletes
+        .iter()
+        .map(|p| {
+            Path::new(workspace_path)
+                .join(p)
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    let deleted = local_db::delete_workspace_files(repo_path, workspace_id, &deleted_paths)?;
+    local_db::upsert_workspace_files(repo_path, workspace_id, &cached_files)?;
+
+    if let Err(e) = language_stats::refresh_language_stats(repo_path, workspace_id) {
+        log::warn!("Failed to refresh language stats: {}", e);
+    }
+
+    Ok(IndexUpdate::Incremental { upserted, deleted })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,9 +535,8 @@ mod tests {
         let path = temp_dir.path().to_str().unwrap().to_string();
 
         // Initialize jj repo
-        command_for("jj")
+        jj::jj_command(&path)
             .args(["git", "init"])
-            .current_dir(&path)
             .output()
             .expect("Failed to init jj repo");
 
@@ -191,9 +555,8 @@ mod tests {
         fs::write(temp_dir.path().join("subdir/file3.txt"), "content3").unwrap();
 
         // Snapshot the working copy
-        command_for("jj")
+        jj::jj_command(&repo_path)
             .args(["status"])
-            .current_dir(&repo_path)
             .output()
             .expect("Failed to run jj status");
 
@@ -206,6 +569,111 @@ mod tests {
         assert!(files.contains(&"subdir/file3.txt".to_string()));
     }
 
+    fn cached(relative_path: &str, mtime: Option<i64>) -> CachedWorkspaceFile {
+        CachedWorkspaceFile {
+            id: 0,
+            workspace_id: None,
+            file_path: format!("/ws/{}", relative_path),
+            relative_path: relative_path.to_string(),
+            is_directory: false,
+            parent_path: None,
+            cached_at: String::new(),
+            mtime,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_boundaries_and_file_names() {
+        assert!(fuzzy_score("xyz", "src/main.rs").is_none());
+        assert_eq!(fuzzy_score("mr", "src/main.rs").unwrap().1, vec![4, 9]);
+        // The boundary "b" leaves nothing for "c"; fall back to the plain match
+        assert_eq!(fuzzy_score("bc", "abc/b").unwrap().1, vec![1, 2]);
+
+        let (file_name, _) = fuzzy_score("fb", "src/components/FileBrowser.tsx").unwrap();
+        let (spread, _) = fuzzy_score("fb", "src/features/lib/about.ts").unwrap();
+        assert!(file_name > spread);
+    }
+
+    #[test]
+    fn test_rank_files_uses_recency_for_ties() {
+        let now = 1_700_000_000;
+        let files = vec![
+            cached("src/api.ts", Some(now - RECENCY_WINDOW_SECS)),
+            cached("lib/api.ts", Some(now - 60)),
+            cached("docs/rapid.md", None),
+            cached("README.md", None),
+        ];
+        let ranked: Vec<String> = rank_files(files, "api", 10, now)
+            .into_iter()
+            .map(|m| m.relative_path)
+            .collect();
+        assert_eq!(ranked, ["lib/api.ts", "src/api.ts", "docs/rapid.md"]);
+    }
+
+    #[test]
+    fn test_update_workspace_files_applies_watcher_paths() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let workspace = temp_dir.path();
+        let workspace_path = workspace.to_str().unwrap();
+        let cached = build_file_tree(
+            workspace_path,
+            vec!["src/a.rs".to_string(), "old/gone.rs".to_string()],
+        )
+        .unwrap();
+        local_db::sync_workspace_files(workspace_path, None, cached).unwrap();
+
+        fs::write(workspace.join(".gitignore"), "build/\n*.log\n").unwrap();
+        fs::create_dir_all(workspace.join("src")).unwrap();
+        fs::create_dir_all(workspace.join("build")).unwrap();
+        fs::create_dir_all(workspace.join("docs/api")).unwrap();
+        fs::write(workspace.join("src/a.rs"), "fn a() {}").unwrap();
+        fs::write(workspace.join("notes.log"), "log").unwrap();
+        fs::write(workspace.join("build/out.bin"), "bin").unwrap();
+        fs::write(workspace.join("docs/api/guide.md"), "# Guide").unwrap();
+
+        // A moved-in directory is reported as the directory alone
+        let changed: Vec<PathBuf> = [
+            "src/a.rs",
+            "notes.log",
+            "build/out.bin",
+            "docs",
+            "old/gone.rs",
+        ]
+        .iter()
+        .map(|p| workspace.join(p))
+        .collect();
+        let update =
+            update_workspace_files(workspace_path, None, workspace_path, &changed).unwrap();
+        assert_eq!(
+            update,
+            IndexUpdate::Incremental {
+                upserted: 2,
+                deleted: 2
+            }
+        );
+
+        let mut files: Vec<String> = local_db::get_cached_files(workspace_path, None)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.relative_path)
+            .collect();
+        files.sort();
+        assert_eq!(files, ["docs/api/guide.md", "src/a.rs"]);
+
+        // The emptied "old" directory is pruned
+        let root: Vec<String> =
+            local_db::get_cached_directory_listing(workspace_path, None, workspace_path)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.relative_path)
+                .collect();
+        assert_eq!(root, ["docs", "src"]);
+
+        mark_dirty(workspace_path);
+        assert!(take_dirty(workspace_path));
+        assert!(!take_dirty(workspace_path));
+    }
+
     #[test]
     fn test_get_jj_tracked_files_includes_unchanged_committed_files() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -213,9 +681,8 @@ mod tests {
 
         // Create and commit a file
         fs::write(temp_dir.path().join("committed.txt"), "committed").unwrap();
-        command_for("jj")
+        jj::jj_command(&repo_path)
             .args(["commit", "-m", "initial"])
-            .current_dir(&repo_path)
             .output()
             .expect("Failed to commit");
 
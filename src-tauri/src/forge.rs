@@ -0,0 +1,820 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Output;
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::binary_paths;
+use crate::git_ops;
+use crate::process_limiter::LimitedCommand;
+
+pub mod bitbucket;
+pub mod github;
+pub mod gitlab;
+
+/// Settings key: GitHub token passed to `gh` as GH_TOKEN; unset uses gh's own login
+pub const GITHUB_TOKEN_KEY: &str = "github_token";
+
+/// Settings key: GitLab token passed to `glab` as GITLAB_TOKEN; unset uses glab's own login
+pub const GITLAB_TOKEN_KEY: &str = "gitlab_token";
+
+/// Settings key: Bitbucket access token, or `username:app_password` for basic auth
+pub const BITBUCKET_TOKEN_KEY: &str = "bitbucket_token";
+
+const BITBUCKET_API_URL: &str = "https://api.bitbucket.org/2.0/";
+
+/// Helper function to create Command for a binary using cached path
+fn command_for(binary: &str) -> LimitedCommand {
+    let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
+    LimitedCommand::new(path)
+}
+
+/// Set the token used for requests to `kind`. Cached responses from that forge are
+/// dropped since they may not be visible to the new token.
+pub fn set_token(kind: ForgeKind, token: Option<&str>) {
+    let token = token.map(str::trim).filter(|t| !t.is_empty());
+    let mut guard = state().lock().unwrap();
+    match token {
+        Some(token) => guard.tokens.insert(kind, token.to_string()),
+        None => guard.tokens.remove(&kind),
+    };
+    guard.cache.retain(|(k, _), _| *k != kind);
+}
+
+/// Start backing off before the quota is fully spent so interactive requests still work
+const LOW_REMAINING_THRESHOLD: u64 = 50;
+
+/// Backoff applied after a 403/429 that carries no Retry-After or reset header
+const DEFAULT_BACKOFF_SECS: i64 = 60;
+
+/// Response from the forge API
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForgeResponse {
+    pub status: u16,
+    pub body: String,
+    /// Served from the ETag cache (304 or rate-limit backoff)
+    pub from_cache: bool,
+}
+
+/// Rate-limit state reported by the forge's response headers
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ForgeRateLimitStatus {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// Unix timestamp when the quota resets
+    pub reset_at: Option<i64>,
+    /// Unix timestamp until which non-cached requests are refused
+    pub backoff_until: Option<i64>,
+    pub is_limited: bool,
+    pub cached_entries: usize,
+    /// Requests that waited on an identical in-flight request instead of hitting the API
+    pub coalesced_requests: u64,
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    etag: String,
+    status: u16,
+    body: String,
+}
+
+type InFlight = Arc<(Mutex<Option<Result<ForgeResponse, String>>>, Condvar)>;
+
+/// Cache and in-flight requests are keyed by forge and API path
+type RequestKey = (ForgeKind, String);
+
+/// Held by the request that fetches for coalesced waiters. Dropping it frees the key and
+/// wakes the waiters, with an error if the fetch never stored an outcome (it panicked).
+struct InFlightLeader {
+    key: RequestKey,
+    slot: InFlight,
+    outcome: Option<Result<ForgeResponse, String>>,
+}
+
+impl Drop for InFlightLeader {
+    fn drop(&mut self) {
+        state()
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .in_flight
+            .remove(&self.key);
+        let outcome = self
+            .outcome
+            .take()
+            .unwrap_or_else(|| Err("Forge request failed".to_string()));
+        let (result, ready) = &*self.slot;
+        *result.lock().unwrap_or_else(PoisonError::into_inner) = Some(outcome);
+        ready.notify_all();
+    }
+}
+
+#[derive(Default)]
+struct ForgeState {
+    cache: HashMap<RequestKey, CachedResponse>,
+    in_flight: HashMap<RequestKey, InFlight>,
+    rate_limits: HashMap<ForgeKind, ForgeRateLimitStatus>,
+    tokens: HashMap<ForgeKind, String>,
+}
+
+static FORGE_STATE: OnceLock<Mutex<ForgeState>> = OnceLock::new();
+
+fn state() -> &'static Mutex<ForgeState> {
+    FORGE_STATE.get_or_init(|| Mutex::new(ForgeState::default()))
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Split `gh api`/`glab api`/`curl` `--include` output into status, lowercased headers and body.
+/// Skips interim header blocks such as `100 Continue` or proxy CONNECT responses.
+fn parse_http_response(raw: &str) -> Option<(u16, HashMap<String, String>, String)> {
+    let mut rest = raw;
+    loop {
+        let (head, body) = rest
+            .split_once("\r\n\r\n")
+            .or_else(|| rest.split_once("\n\n"))
+            .unwrap_or((rest, ""));
+
+        let mut lines = head.lines();
+        let status: u16 = lines
+            .next()?
+            .strip_prefix("HTTP/")?
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()?;
+
+        if body.starts_with("HTTP/") && (status < 200 || head.contains("Connection established")) {
+            rest = body;
+            continue;
+        }
+
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+            .collect();
+        return Some((status, headers, body.to_string()));
+    }
+}
+
+/// Update rate-limit state from response headers, entering backoff when the quota is
+/// nearly spent or the forge rejected the request
+fn apply_rate_limit_headers(
+    rate_limit: &mut ForgeRateLimitStatus,
+    status: u16,
+    headers: &HashMap<String, String>,
+    now: i64,
+) {
+    // GitLab sends the same headers without the `x-` prefix
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .or_else(|| headers.get(name.trim_start_matches("x-")))
+            .and_then(|v| v.parse::<i64>().ok())
+    };
+
+    if let Some(limit) = header("x-ratelimit-limit") {
+        rate_limit.limit = Some(limit.max(0) as u64);
+    }
+    if let Some(remaining) = header("x-ratelimit-remaining") {
+        rate_limit.remaining = Some(remaining.max(0) as u64);
+    }
+    if let Some(reset) = header("x-ratelimit-reset") {
+        rate_limit.reset_at = Some(reset);
+    }
+
+    let exhausted = rate_limit
+        .remaining
+        .is_some_and(|r| r < LOW_REMAINING_THRESHOLD);
+    let rejected =
+        status == 429 || (status == 403 && (exhausted || headers.contains_key("retry-after")));
+
+    rate_limit.backoff_until = if let Some(retry_after) = header("retry-after").filter(|_| rejected)
+    {
+        Some(now + retry_after)
+    } else if rejected || exhausted {
+        Some(
+            rate_limit
+                .reset_at
+                .filter(|r| *r > now)
+                .unwrap_or(now + DEFAULT_BACKOFF_SECS),
+        )
+    } else {
+        None
+    };
+    rate_limit.is_limited = rate_limit.backoff_until.is_some();
+}
+
+/// Quote a value for a curl config file
+fn curl_config_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    format!("\"{}\"", escaped)
+}
+
+/// Run one API request with the forge's client. GitHub and GitLab go through `gh api`
+/// and `glab api`, which fall back to their own login when no token is configured.
+/// Bitbucket has no CLI, so curl reads the URL and credentials from a config on stdin,
+/// keeping the token out of the process list.
+fn run_request(
+    kind: ForgeKind,
+    method: &str,
+    path: &str,
+    headers: &[String],
+    body: Option<&str>,
+) -> Result<Output, String> {
+    let token = state().lock().unwrap().tokens.get(&kind).cloned();
+    match kind {
+        ForgeKind::Github | ForgeKind::Gitlab => {
+            let (binary, token_env) = match kind {
+                ForgeKind::Github => ("gh", "GH_TOKEN"),
+                _ => ("glab", "GITLAB_TOKEN"),
+            };
+            let mut command = command_for(binary);
+            command.args(["api", "--include", "--method", method, path]);
+            for header in headers {
+                command.args(["-H", header.as_str()]);
+            }
+            if let Some(token) = &token {
+                command.env(token_env, token);
+            }
+            let output = match body {
+                Some(body) => command
+                    .args(["--input", "-"])
+                    .output_with_input(body.as_bytes()),
+                None => command.output(),
+            };
+            output.map_err(|e| format!("Failed to execute {}: {}", binary, e))
+        }
+        ForgeKind::Bitbucket => {
+            let url = format!("{}{}", BITBUCKET_API_URL, path);
+            let mut config = format!(
+                "url = {}\nrequest = {}\nheader = \"Accept: application/json\"\n",
+                curl_config_quote(&url),
+                curl_config_quote(method)
+            );
+            for header in headers {
+                config += &format!("header = {}\n", curl_config_quote(header));
+            }
+            match token.as_deref() {
+                // App passwords are configured as `username:app_password`
+                Some(credentials) if credentials.contains(':') => {
+                    config += &format!("user = {}\n", curl_config_quote(credentials));
+                }
+                Some(token) => {
+                    let header = format!("Authorization: Bearer {}", token);
+                    config += &format!("header = {}\n", curl_config_quote(&header));
+                }
+                None => {}
+            }
+            if let Some(body) = body {
+                config += "header = \"Content-Type: application/json\"\n";
+                config += &format!("data-binary = {}\n", curl_config_quote(body));
+            }
+            command_for("curl")
+                .args(["--silent", "--show-error", "--include", "--config", "-"])
+                .output_with_input(config.as_bytes())
+                .map_err(|e| format!("Failed to execute curl: {}", e))
+        }
+    }
+}
+
+/// Parse a client's `--include` output. The CLIs exit non-zero for 304 and error
+/// statuses but still print the response.
+fn parse_output(output: &Output) -> Result<(u16, HashMap<String, String>, String), String> {
+    parse_http_response(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| {
+        format!(
+            "Forge API request failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    })
+}
+
+fn validate_path(path: &str) -> Result<&str, String> {
+    let path = path.trim_start_matches('/');
+    if path.is_empty() || path.starts_with('-') || path.contains('\0') {
+        return Err(format!("Invalid forge API path: {}", path));
+    }
+    Ok(path)
+}
+
+fn backoff_error(kind: ForgeKind) -> Option<String> {
+    let until = state()
+        .lock()
+        .unwrap()
+        .rate_limits
+        .get(&kind)?
+        .backoff_until
+        .filter(|u| *u > now_secs())?;
+    Some(format!("Forge API rate limited until {}", until))
+}
+
+fn fetch(kind: ForgeKind, path: &str) -> Result<ForgeResponse, String> {
+    let key = (kind, path.to_string());
+    let cached = state().lock().unwrap().cache.get(&key).cloned();
+
+    // During backoff only cached data is served
+    if let Some(error) = backoff_error(kind) {
+        return match cached {
+            Some(entry) => Ok(ForgeResponse {
+                status: entry.status,
+                body: entry.body,
+                from_cache: true,
+            }),
+            None => Err(error),
+        };
+    }
+
+    let headers: Vec<String> = cached
+        .iter()
+        .map(|entry| format!("If-None-Match: {}", entry.etag))
+        .collect();
+    let output = run_request(kind, "GET", path, &headers, None)?;
+    let (status, headers, body) = parse_output(&output)?;
+
+    let mut guard = state().lock().unwrap();
+    apply_rate_limit_headers(
+        guard.rate_limits.entry(kind).or_default(),
+        status,
+        &headers,
+        now_secs(),
+    );
+
+    if status == 304 {
+        if let Some(entry) = cached {
+            return Ok(ForgeResponse {
+                status: entry.status,
+                body: entry.body,
+                from_cache: true,
+            });
+        }
+    }
+
+    if (200..300).contains(&status) {
+        if let Some(etag) = headers.get("etag") {
+            guard.cache.insert(
+                key,
+                CachedResponse {
+                    etag: etag.clone(),
+                    status,
+                    body: body.clone(),
+                },
+            );
+        }
+    }
+
+    Ok(ForgeResponse {
+        status,
+        body,
+        from_cache: false,
+    })
+}
+
+/// GET a forge API path (e.g. `repos/owner/name/pulls`), using ETag revalidation,
+/// coalescing identical concurrent requests and respecting rate-limit backoff.
+pub fn forge_get(kind: ForgeKind, path: &str) -> Result<ForgeResponse, String> {
+    let path = validate_path(path)?;
+    let key = (kind, path.to_string());
+
+    let (slot, is_leader) = {
+        let mut guard = state().lock().unwrap();
+        match guard.in_flight.get(&key) {
+            Some(slot) => {
+                let slot = slot.clone();
+                guard
+                    .rate_limits
+                    .entry(kind)
+                    .or_default()
+                    .coalesced_requests += 1;
+                (slot, false)
+            }
+            None => {
+                let slot: InFlight = Arc::new((Mutex::new(None), Condvar::new()));
+                guard.in_flight.insert(key.clone(), slot.clone());
+                (slot, true)
+            }
+        }
+    };
+
+    let (result, ready) = &*slot;
+    if !is_leader {
+        let mut result = result.lock().unwrap();
+        while result.is_none() {
+            result = ready.wait(result).unwrap();
+        }
+        return result.clone().unwrap();
+    }
+
+    let mut leader = InFlightLeader {
+        key,
+        slot: slot.clone(),
+        outcome: None,
+    };
+    let outcome = fetch(kind, path);
+    leader.outcome = Some(outcome.clone());
+    outcome
+}
+
+/// Send a request that changes forge state (e.g. `POST repos/owner/name/pulls`) with
+/// a JSON body. Not cached or coalesced.
+pub fn forge_send(
+    kind: ForgeKind,
+    method: &str,
+    path: &str,
+    body: &serde_json::Value,
+) -> Result<ForgeResponse, String> {
+    let path = validate_path(path)?;
+    if let Some(error) = backoff_error(kind) {
+        return Err(error);
+    }
+
+    let output = run_request(kind, method, path, &[], Some(&body.to_string()))?;
+    let (status, headers, body) = parse_output(&output)?;
+    apply_rate_limit_headers(
+        state().lock().unwrap().rate_limits.entry(kind).or_default(),
+        status,
+        &headers,
+        now_secs(),
+    );
+
+    Ok(ForgeResponse {
+        status,
+        body,
+        from_cache: false,
+    })
+}
+
+/// Hosting service of a repository's remote
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    Github,
+    Gitlab,
+    Bitbucket,
+}
+
+impl ForgeKind {
+    pub const ALL: [ForgeKind; 3] = [ForgeKind::Github, ForgeKind::Gitlab, ForgeKind::Bitbucket];
+
+    /// Settings key holding this forge's token
+    pub fn token_key(self) -> &'static str {
+        match self {
+            ForgeKind::Github => GITHUB_TOKEN_KEY,
+            ForgeKind::Gitlab => GITLAB_TOKEN_KEY,
+            ForgeKind::Bitbucket => BITBUCKET_TOKEN_KEY,
+        }
+    }
+}
+
+/// Repository on a forge, identified from the `origin` remote. GitLab owners may be
+/// nested groups (`group/subgroup`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ForgeRepo {
+    pub provider: ForgeKind,
+    pub owner: String,
+    pub name: String,
+    pub web_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestState {
+    Open,
+    Closed,
+    Merged,
+}
+
+/// Pull request (GitHub, Bitbucket) or merge request (GitLab)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PullRequest {
+    /// Number shown in the forge's UI (GitLab's `iid`)
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub state: PullRequestState,
+    pub draft: bool,
+    pub head_branch: String,
+    pub base_branch: String,
+    pub head_sha: String,
+    /// Combined checks of the head commit: "success", "pending", "failure" or "error";
+    /// None when no checks report statuses
+    pub checks: Option<String>,
+}
+
+/// One commit status or check run reported by CI
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CheckRun {
+    pub name: String,
+    /// "success", "pending", "failure" or "error"
+    pub state: String,
+    pub url: Option<String>,
+}
+
+/// Combine check states the way forges report a commit's overall status: any failure
+/// wins, then errors, then checks still running; None when nothing reports
+pub fn combined_state(checks: &[CheckRun]) -> Option<String> {
+    let any = |state: &str| checks.iter().any(|c| c.state == state);
+    let state = if checks.is_empty() {
+        return None;
+    } else if any("failure") {
+        "failure"
+    } else if any("error") {
+        "error"
+    } else if any("pending") {
+        "pending"
+    } else {
+        "success"
+    };
+    Some(state.to_string())
+}
+
+/// Merge-request operations of one forge. Requests go through the shared rate-limited
+/// client, so implementations hold no state.
+pub trait ForgeProvider: Sync {
+    /// Repository for a remote on this forge, from the remote URL's host and path
+    fn parse_remote(&self, host: &str, path: &str) -> Option<ForgeRepo>;
+
+    /// Open a merge request from `head` into `base`. The head branch must already be pushed.
+    fn create_mr(
+        &self,
+        repo: &ForgeRepo,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest, String>;
+
+    /// Merge requests from `branch` in any state, newest first, without checks
+    fn list_mrs_for_branch(
+        &self,
+        repo: &ForgeRepo,
+        branch: &str,
+    ) -> Result<Vec<PullRequest>, String>;
+
+    /// Most recent merge request from `branch` with the head commit's checks
+    fn get_mr_status(&self, repo: &ForgeRepo, branch: &str) -> Result<Option<PullRequest>, String>;
+
+    /// Commit statuses and check runs reported for `sha`
+    fn get_checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<CheckRun>, String>;
+}
+
+static GITHUB: github::Github = github::Github;
+static GITLAB: gitlab::Gitlab = gitlab::Gitlab;
+static BITBUCKET: bitbucket::Bitbucket = bitbucket::Bitbucket;
+
+pub fn provider_for(kind: ForgeKind) -> &'static dyn ForgeProvider {
+    match kind {
+        ForgeKind::Github => &GITHUB,
+        ForgeKind::Gitlab => &GITLAB,
+        ForgeKind::Bitbucket => &BITBUCKET,
+    }
+}
+
+/// Split a remote URL in any form git accepts (`git@host:o/r.git`,
+/// `ssh://git@host:22/o/r.git`, `https://user@host/o/r`) into host and repository path
+fn split_remote_url(url: &str) -> Option<(&str, &str)> {
+    let (host, path) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            if !matches!(scheme, "https" | "http" | "ssh" | "git") {
+                return None;
+            }
+            let (authority, path) = rest.split_once('/')?;
+            // Drop credentials and port
+            let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+            (host.split(':').next()?, path)
+        }
+        // scp-like syntax: [user@]host:path
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+            (host, path)
+        }
+    };
+    let path = path.trim_matches('/');
+    Some((host, path.strip_suffix(".git").unwrap_or(path)))
+}
+
+/// Forge repository for a remote URL, or None for hosts no provider recognises
+pub fn repo_for_remote_url(url: &str) -> Option<ForgeRepo> {
+    let (host, path) = split_remote_url(url)?;
+    ForgeKind::ALL
+        .into_iter()
+        .find_map(|kind| provider_for(kind).parse_remote(host, path))
+}
+
+/// Split `owner/name` with single, non-empty segments
+fn split_owner_name(path: &str) -> Option<(&str, &str)> {
+    let (owner, name) = path.split_once('/')?;
+    if owner.is_empty() || name.is_empty() || name.contains('/') {
+        return None;
+    }
+    Some((owner, name))
+}
+
+/// Percent-encode a query value; branch names may contain `&`, `#` or `+`
+fn encode_query_value(value: &str) -> String {
+    percent_encode(value, b"/")
+}
+
+/// Percent-encode a single path segment, including any `/`
+fn encode_path_segment(value: &str) -> String {
+    percent_encode(value, b"")
+}
+
+fn percent_encode(value: &str, keep: &[u8]) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ if keep.contains(&b) => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Forge hosting the repo's `origin` remote, or None for remotes on unsupported hosts
+pub fn detect_provider(repo_path: &str) -> Result<Option<ForgeRepo>, String> {
+    let url = match git_ops::run_git(repo_path, &["remote", "get-url", "origin"]) {
+        Ok(url) => url,
+        Err(git_ops::GitError::NotFound(_)) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read origin remote: {}", e)),
+    };
+    Ok(repo_for_remote_url(url.trim()))
+}
+
+/// Current rate-limit and cache state of one forge
+pub fn rate_limit_status(kind: ForgeKind) -> ForgeRateLimitStatus {
+    let guard = state().lock().unwrap();
+    let mut status = guard.rate_limits.get(&kind).cloned().unwrap_or_default();
+    status.cached_entries = guard.cache.keys().filter(|(k, _)| *k == kind).count();
+    status.is_limited = status.backoff_until.is_some_and(|u| u > now_secs());
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_http_response() {
+        let raw = "HTTP/2.0 200 OK\r\nEtag: \"abc\"\r\nX-Ratelimit-Remaining: 4999\r\n\r\n[{\"number\":1}]";
+        let (status, headers, body) = parse_http_response(raw).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(headers.get("etag").unwrap(), "\"abc\"");
+        assert_eq!(headers.get("x-ratelimit-remaining").unwrap(), "4999");
+        assert_eq!(body, "[{\"number\":1}]");
+
+        let continued =
+            "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 304 Not Modified\r\nEtag: \"abc\"\r\n\r\n";
+        assert_eq!(parse_http_response(continued).unwrap().0, 304);
+        assert!(parse_http_response("gh: Not Found").is_none());
+    }
+
+    #[test]
+    fn test_apply_rate_limit_headers() {
+        let now = 1_000;
+        let mut rate_limit = ForgeRateLimitStatus::default();
+
+        apply_rate_limit_headers(
+            &mut rate_limit,
+            200,
+            &headers(&[
+                ("x-ratelimit-limit", "5000"),
+                ("x-ratelimit-remaining", "4000"),
+                ("x-ratelimit-reset", "2000"),
+            ]),
+            now,
+        );
+        assert_eq!(rate_limit.remaining, Some(4000));
+        assert!(!rate_limit.is_limited);
+
+        // Nearly exhausted quota backs off until the reset time
+        apply_rate_limit_headers(
+            &mut rate_limit,
+            200,
+            &headers(&[
+                ("x-ratelimit-remaining", "10"),
+                ("x-ratelimit-reset", "2000"),
+            ]),
+            now,
+        );
+        assert_eq!(rate_limit.backoff_until, Some(2000));
+
+        // Secondary rate limits use Retry-After
+        apply_rate_limit_headers(
+            &mut rate_limit,
+            403,
+            &headers(&[("x-ratelimit-remaining", "4000"), ("retry-after", "30")]),
+            now,
+        );
+        assert_eq!(rate_limit.backoff_until, Some(1030));
+        assert!(rate_limit.is_limited);
+    }
+
+    #[test]
+    fn test_gitlab_rate_limit_headers() {
+        let mut rate_limit = ForgeRateLimitStatus::default();
+        apply_rate_limit_headers(
+            &mut rate_limit,
+            200,
+            &headers(&[("ratelimit-remaining", "5"), ("ratelimit-reset", "2000")]),
+            1_000,
+        );
+        assert_eq!(rate_limit.remaining, Some(5));
+        assert_eq!(rate_limit.backoff_until, Some(2000));
+    }
+
+    #[test]
+    fn test_repo_for_remote_url_unsupported_host() {
+        assert_eq!(
+            split_remote_url("ssh://git@example.com:2222/team/app.git/"),
+            Some(("example.com", "team/app"))
+        );
+        assert!(repo_for_remote_url("git@example.com:team/app.git").is_none());
+        assert!(repo_for_remote_url("/srv/git/app.git").is_none());
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(encode_query_value("treq/dark-mode"), "treq/dark-mode");
+        assert_eq!(encode_query_value("fix&#1+é"), "fix%26%231%2B%C3%A9");
+        assert_eq!(encode_path_segment("group/sub/app"), "group%2Fsub%2Fapp");
+    }
+
+    #[test]
+    fn test_curl_config_quote() {
+        assert_eq!(
+            curl_config_quote(r#"{"title":"a \"b\"\nc"}"#),
+            r#""{\"title\":\"a \\\"b\\\"\\nc\"}""#
+        );
+    }
+
+    #[test]
+    fn test_panicking_leader_releases_waiters() {
+        let key = (ForgeKind::Github, "test/leader-panic".to_string());
+        let slot: InFlight = Arc::new((Mutex::new(None), Condvar::new()));
+        state()
+            .lock()
+            .unwrap()
+            .in_flight
+            .insert(key.clone(), slot.clone());
+
+        let waiter = {
+            let slot = slot.clone();
+            std::thread::spawn(move || {
+                let (result, ready) = &*slot;
+                let mut result = result.lock().unwrap();
+                while result.is_none() {
+                    result = ready.wait(result).unwrap();
+                }
+                result.clone().unwrap()
+            })
+        };
+        let panicked = std::panic::catch_unwind(|| {
+            let _leader = InFlightLeader {
+                key: key.clone(),
+                slot,
+                outcome: None,
+            };
+            panic!("fetch panicked");
+        });
+
+        assert!(panicked.is_err());
+        assert!(waiter.join().unwrap().is_err());
+        assert!(!state().lock().unwrap().in_flight.contains_key(&key));
+    }
+
+    #[test]
+    fn test_combined_state() {
+        let check = |state: &str| CheckRun {
+            name: state.to_string(),
+            state: state.to_string(),
+            url: None,
+        };
+        assert_eq!(combined_state(&[]), None);
+        assert_eq!(
+            combined_state(&[check("success"), check("pending")]).as_deref(),
+            Some("pending")
+        );
+        assert_eq!(
+            combined_state(&[check("error"), check("failure")]).as_deref(),
+            Some("failure")
+        );
+    }
+}
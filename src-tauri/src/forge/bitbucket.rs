@@ -0,0 +1,306 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    combined_state, encode_query_value, forge_get, forge_send, split_owner_name, CheckRun,
+    ForgeKind, ForgeProvider, ForgeRepo, PullRequest, PullRequestState,
+};
+
+const BITBUCKET_HOST: &str = "bitbucket.org";
+
+/// The subset of Bitbucket Cloud's pull request object that treq reads
+#[derive(Deserialize)]
+struct ApiPullRequest {
+    id: u64,
+    title: String,
+    /// "OPEN", "MERGED", "DECLINED" or "SUPERSEDED"
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    links: ApiLinks,
+    source: ApiEndpoint,
+    destination: ApiEndpoint,
+}
+
+#[derive(Deserialize)]
+struct ApiLinks {
+    html: ApiLink,
+}
+
+#[derive(Deserialize)]
+struct ApiLink {
+    href: String,
+}
+
+#[derive(Deserialize)]
+struct ApiEndpoint {
+    branch: ApiBranch,
+    commit: Option<ApiCommit>,
+}
+
+#[derive(Deserialize)]
+struct ApiBranch {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ApiCommit {
+    hash: String,
+}
+
+/// One page of a paginated listing
+#[derive(Deserialize)]
+struct ApiPage<T> {
+    values: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct ApiCommitStatus {
+    key: String,
+    name: Option<String>,
+    /// "SUCCESSFUL", "FAILED", "INPROGRESS" or "STOPPED"
+    state: String,
+    url: Option<String>,
+}
+
+impl From<ApiCommitStatus> for CheckRun {
+    fn from(status: ApiCommitStatus) -> Self {
+        let state = match status.state.as_str() {
+            "SUCCESSFUL" => "success",
+            "FAILED" => "failure",
+            "STOPPED" => "error",
+            _ => "pending",
+        };
+        CheckRun {
+            name: status.name.filter(|n| !n.is_empty()).unwrap_or(status.key),
+            state: state.to_string(),
+            url: status.url,
+        }
+    }
+}
+
+impl From<ApiPullRequest> for PullRequest {
+    fn from(pr: ApiPullRequest) -> Self {
+        let state = match pr.state.as_str() {
+            "OPEN" => PullRequestState::Open,
+            "MERGED" => PullRequestState::Merged,
+            _ => PullRequestState::Closed,
+        };
+        PullRequest {
+            number: pr.id,
+            title: pr.title,
+            url: pr.links.html.href,
+            state,
+            draft: pr.draft,
+            head_branch: pr.source.branch.name,
+            base_branch: pr.destination.branch.name,
+            head_sha: pr.source.commit.map(|c| c.hash).unwrap_or_default(),
+            checks: None,
+        }
+    }
+}
+
+/// Error message from a failed Bitbucket API response, including per-field errors
+fn api_error(action: &str, status: u16, body: &str) -> String {
+    let value: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let error = &value["error"];
+    let mut message = error["message"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| format!("HTTP {}", status));
+    let details: Vec<String> = error["fields"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .flat_map(|(field, errors)| {
+            errors
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.as_str())
+                .map(move |e| format!("{}: {}", field, e))
+        })
+        .collect();
+    if !details.is_empty() {
+        message = format!("{} ({})", message, details.join("; "));
+    }
+    format!("Failed to {}: {}", action, message)
+}
+
+fn pull_requests_for_branch(
+    repo: &ForgeRepo,
+    branch: &str,
+    page_len: u32,
+) -> Result<Vec<PullRequest>, String> {
+    // Without explicit states only open pull requests are listed
+    let query = format!(
+        "source.branch.name=\"{}\"",
+        branch.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    let path = format!(
+        "repositories/{}/{}/pullrequests?q={}&sort=-created_on&pagelen={}&state=OPEN&state=MERGED&state=DECLINED&state=SUPERSEDED",
+        repo.owner,
+        repo.name,
+        encode_query_value(&query),
+        page_len
+    );
+    let response = forge_get(ForgeKind::Bitbucket, &path)?;
+    if response.status != 200 {
+        return Err(api_error(
+            "load pull requests",
+            response.status,
+            &response.body,
+        ));
+    }
+    let page: ApiPage<ApiPullRequest> = serde_json::from_str(&response.body)
+        .map_err(|e| format!("Failed to parse pull requests: {}", e))?;
+    Ok(page.values.into_iter().map(PullRequest::from).collect())
+}
+
+pub struct Bitbucket;
+
+impl ForgeProvider for Bitbucket {
+    fn parse_remote(&self, host: &str, path: &str) -> Option<ForgeRepo> {
+        if !host.eq_ignore_ascii_case(BITBUCKET_HOST) {
+            return None;
+        }
+        let (workspace, slug) = split_owner_name(path)?;
+        Some(ForgeRepo {
+            provider: ForgeKind::Bitbucket,
+            owner: workspace.to_string(),
+            name: slug.to_string(),
+            web_url: format!("https://{}/{}/{}", BITBUCKET_HOST, workspace, slug),
+        })
+    }
+
+    fn create_mr(
+        &self,
+        repo: &ForgeRepo,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest, String> {
+        if title.trim().is_empty() {
+            return Err("Pull request title is required".to_string());
+        }
+        let path = format!("repositories/{}/{}/pullrequests", repo.owner, repo.name);
+        let response = forge_send(
+            ForgeKind::Bitbucket,
+            "POST",
+            &path,
+            &json!({
+                "title": title.trim(),
+                "description": body,
+                "source": { "branch": { "name": head } },
+                "destination": { "branch": { "name": base } },
+            }),
+        )?;
+        if response.status != 201 {
+            return Err(api_error(
+                "create pull request",
+                response.status,
+                &response.body,
+            ));
+        }
+        serde_json::from_str::<ApiPullRequest>(&response.body)
+            .map(PullRequest::from)
+            .map_err(|e| format!("Failed to parse pull request: {}", e))
+    }
+
+    fn list_mrs_for_branch(
+        &self,
+        repo: &ForgeRepo,
+        branch: &str,
+    ) -> Result<Vec<PullRequest>, String> {
+        pull_requests_for_branch(repo, branch, 30)
+    }
+
+    fn get_mr_status(&self, repo: &ForgeRepo, branch: &str) -> Result<Option<PullRequest>, String> {
+        let Some(mut pr) = pull_requests_for_branch(repo, branch, 1)?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+        if !pr.head_sha.is_empty() {
+            pr.checks = self
+                .get_checks(repo, &pr.head_sha)
+                .ok()
+                .and_then(|checks| combined_state(&checks));
+        }
+        Ok(Some(pr))
+    }
+
+    fn get_checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<CheckRun>, String> {
+        let path = format!(
+            "repositories/{}/{}/commit/{}/statuses?pagelen=100",
+            repo.owner, repo.name, sha
+        );
+        let response = forge_get(ForgeKind::Bitbucket, &path)?;
+        if response.status != 200 {
+            return Err(api_error(
+                "load commit statuses",
+                response.status,
+                &response.body,
+            ));
+        }
+        let page: ApiPage<ApiCommitStatus> = serde_json::from_str(&response.body)
+            .map_err(|e| format!("Failed to parse commit statuses: {}", e))?;
+        Ok(page.values.into_iter().map(CheckRun::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::repo_for_remote_url;
+
+    #[test]
+    fn test_parse_remote_url() {
+        for url in [
+            "git@bitbucket.org:acme/treq.git",
+            "https://dev@bitbucket.org/acme/treq.git",
+        ] {
+            let repo = repo_for_remote_url(url).unwrap_or_else(|| panic!("{}", url));
+            assert_eq!(repo.provider, ForgeKind::Bitbucket);
+            assert_eq!((repo.owner.as_str(), repo.name.as_str()), ("acme", "treq"));
+            assert_eq!(repo.web_url, "https://bitbucket.org/acme/treq");
+        }
+    }
+
+    #[test]
+    fn test_pull_request_from_api() {
+        let body = r#"{"values":[{"id":3,"title":"Dark mode","state":"DECLINED",
+            "links":{"html":{"href":"https://bitbucket.org/o/r/pull-requests/3"}},
+            "source":{"branch":{"name":"treq/dark-mode"},"commit":{"hash":"abc123"}},
+            "destination":{"branch":{"name":"main"},"commit":{"hash":"def456"}}}]}"#;
+        let page: ApiPage<ApiPullRequest> = serde_json::from_str(body).unwrap();
+        let pr = PullRequest::from(page.values.into_iter().next().unwrap());
+        assert_eq!(pr.state, PullRequestState::Closed);
+        assert_eq!(pr.head_sha, "abc123");
+        assert_eq!(pr.base_branch, "main");
+
+        let statuses = r#"{"values":[{"key":"ci","name":"Build","state":"STOPPED","url":null},
+            {"key":"lint","name":null,"state":"INPROGRESS","url":"https://ci.example/1"}]}"#;
+        let page: ApiPage<ApiCommitStatus> = serde_json::from_str(statuses).unwrap();
+        let checks: Vec<CheckRun> = page.values.into_iter().map(CheckRun::from).collect();
+        assert_eq!(
+            (checks[0].name.as_str(), checks[0].state.as_str()),
+            ("Build", "error")
+        );
+        assert_eq!(
+            (checks[1].name.as_str(), checks[1].state.as_str()),
+            ("lint", "pending")
+        );
+
+        assert_eq!(
+            api_error(
+                "create pull request",
+                400,
+                r#"{"type":"error","error":{"message":"Bad request","fields":{"source":["branch not found"]}}}"#,
+            ),
+            "Failed to create pull request: Bad request (source: branch not found)"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    combined_state, encode_query_value, forge_get, forge_send, split_owner_name, CheckRun,
+    ForgeKind, ForgeProvider, ForgeRepo, PullRequest, PullRequestState,
+};
+
+const GITHUB_HOST: &str = "github.com";
+
+/// The subset of GitHub's pull request object that treq reads
+#[derive(Deserialize)]
+struct ApiPullRequest {
+    number: u64,
+    title: String,
+    html_url: String,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    merged_at: Option<String>,
+    head: ApiRef,
+    base: ApiRef,
+}
+
+#[derive(Deserialize)]
+struct ApiRef {
+    #[serde(rename = "ref")]
+    branch: String,
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct ApiCombinedStatus {
+    statuses: Vec<ApiStatus>,
+}
+
+/// Legacy commit status, reported by integrations that don't use the checks API
+#[derive(Deserialize)]
+struct ApiStatus {
+    context: String,
+    /// "error", "failure", "pending" or "success"
+    state: String,
+    target_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiCheckRuns {
+    check_runs: Vec<ApiCheckRun>,
+}
+
+#[derive(Deserialize)]
+struct ApiCheckRun {
+    name: String,
+    /// "queued", "in_progress" or "completed"
+    status: String,
+    conclusion: Option<String>,
+    html_url: Option<String>,
+}
+
+impl From<ApiStatus> for CheckRun {
+    fn from(status: ApiStatus) -> Self {
+        CheckRun {
+            name: status.context,
+            state: status.state,
+            url: status.target_url,
+        }
+    }
+}
+
+impl From<ApiCheckRun> for CheckRun {
+    fn from(run: ApiCheckRun) -> Self {
+        // Neutral and skipped runs don't block merging, so they count as passing
+        let state = match (run.status.as_str(), run.conclusion.as_deref()) {
+            ("completed", Some("success" | "neutral" | "skipped")) => "success",
+            ("completed", Some("cancelled" | "stale")) => "error",
+            ("completed", _) => "failure",
+            _ => "pending",
+        };
+        CheckRun {
+            name: run.name,
+            state: state.to_string(),
+            url: run.html_url,
+        }
+    }
+}
+
+impl From<ApiPullRequest> for PullRequest {
+    fn from(pr: ApiPullRequest) -> Self {
+        let state = if pr.merged_at.is_some() {
+            PullRequestState::Merged
+        } else if pr.state == "open" {
+            PullRequestState::Open
+        } else {
+            PullRequestState::Closed
+        };
+        PullRequest {
+            number: pr.number,
+            title: pr.title,
+            url: pr.html_url,
+            state,
+            draft: pr.draft,
+            head_branch: pr.head.branch,
+            base_branch: pr.base.branch,
+            head_sha: pr.head.sha,
+            checks: None,
+        }
+    }
+}
+
+/// Error message from a failed GitHub API response, including validation details such
+/// as "A pull request already exists for owner:branch"
+fn api_error(action: &str, status: u16, body: &str) -> String {
+    let value: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let mut message = value["message"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| format!("HTTP {}", status));
+    let details: Vec<&str> = value["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e["message"].as_str())
+        .collect();
+    if !details.is_empty() {
+        message = format!("{}: {}", message, details.join("; "));
+    }
+    format!("Failed to {}: {}", action, message)
+}
+
+/// Pull requests from `branch` in any state, newest first
+fn pulls_for_branch(
+    repo: &ForgeRepo,
+    branch: &str,
+    per_page: u32,
+) -> Result<Vec<PullRequest>, String> {
+    let path = format!(
+        "repos/{}/{}/pulls?state=all&per_page={}&head={}:{}",
+        repo.owner,
+        repo.name,
+        per_page,
+        repo.owner,
+        encode_query_value(branch)
+    );
+    let response = forge_get(ForgeKind::Github, &path)?;
+    if response.status != 200 {
+        return Err(api_error(
+            "load pull requests",
+            response.status,
+            &response.body,
+        ));
+    }
+    let pulls: Vec<ApiPullRequest> = serde_json::from_str(&response.body)
+        .map_err(|e| format!("Failed to parse pull requests: {}", e))?;
+    Ok(pulls.into_iter().map(PullRequest::from).collect())
+}
+
+pub struct Github;
+
+impl ForgeProvider for Github {
+    fn parse_remote(&self, host: &str, path: &str) -> Option<ForgeRepo> {
+        if !host.eq_ignore_ascii_case(GITHUB_HOST) {
+            return None;
+        }
+        let (owner, name) = split_owner_name(path)?;
+        Some(ForgeRepo {
+            provider: ForgeKind::Github,
+            owner: owner.to_string(),
+            name: name.to_string(),
+            web_url: format!("https://{}/{}/{}", GITHUB_HOST, owner, name),
+        })
+    }
+
+    fn create_mr(
+        &self,
+        repo: &ForgeRepo,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest, String> {
+        if title.trim().is_empty() {
+            return Err("Pull request title is required".to_string());
+        }
+        let path = format!("repos/{}/{}/pulls", repo.owner, repo.name);
+        let response = forge_send(
+            ForgeKind::Github,
+            "POST",
+            &path,
+            &json!({ "title": title.trim(), "head": head, "base": base, "body": body }),
+        )?;
+        if response.status != 201 {
+            return Err(api_error(
+                "create pull request",
+                response.status,
+                &response.body,
+            ));
+        }
+        serde_json::from_str::<ApiPullRequest>(&response.body)
+            .map(PullRequest::from)
+            .map_err(|e| format!("Failed to parse pull request: {}", e))
+    }
+
+    fn list_mrs_for_branch(
+        &self,
+        repo: &ForgeRepo,
+        branch: &str,
+    ) -> Result<Vec<PullRequest>, String> {
+        pulls_for_branch(repo, branch, 30)
+    }
+
+    fn get_mr_status(&self, repo: &ForgeRepo, branch: &str) -> Result<Option<PullRequest>, String> {
+        let Some(mut pr) = pulls_for_branch(repo, branch, 1)?.into_iter().next() else {
+            return Ok(None);
+        };
+
+        pr.checks = self
+            .get_checks(repo, &pr.head_sha)
+            .ok()
+            .and_then(|checks| combined_state(&checks));
+        Ok(Some(pr))
+    }
+
+    fn get_checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<CheckRun>, String> {
+        let status_path = format!("repos/{}/{}/commits/{}/status", repo.owner, repo.name, sha);
+        let response = forge_get(ForgeKind::Github, &status_path)?;
+        if response.status != 200 {
+            return Err(api_error(
+                "load commit status",
+                response.status,
+                &response.body,
+            ));
+        }
+        let combined: ApiCombinedStatus = serde_json::from_str(&response.body)
+            .map_err(|e| format!("Failed to parse commit status: {}", e))?;
+
+        let runs_path = format!(
+            "repos/{}/{}/commits/{}/check-runs?per_page=100",
+            repo.owner, repo.name, sha
+        );
+        let response = forge_get(ForgeKind::Github, &runs_path)?;
+        if response.status != 200 {
+            return Err(api_error(
+                "load check runs",
+                response.status,
+                &response.body,
+            ));
+        }
+        let runs: ApiCheckRuns = serde_json::from_str(&response.body)
+            .map_err(|e| format!("Failed to parse check runs: {}", e))?;
+
+        Ok(combined
+            .statuses
+            .into_iter()
+            .map(CheckRun::from)
+            .chain(runs.check_runs.into_iter().map(CheckRun::from))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::repo_for_remote_url;
+
+    #[test]
+    fn test_parse_remote_url() {
+        for url in [
+            "git@github.com:Ziinc/treq.git",
+            "https://github.com/Ziinc/treq",
+            "https://token@github.com/Ziinc/treq.git/",
+            "ssh://git@github.com:22/Ziinc/treq.git",
+        ] {
+            let repo = repo_for_remote_url(url).unwrap_or_else(|| panic!("{}", url));
+            assert_eq!(repo.provider, ForgeKind::Github);
+            assert_eq!((repo.owner.as_str(), repo.name.as_str()), ("Ziinc", "treq"));
+            assert_eq!(repo.web_url, "https://github.com/Ziinc/treq");
+        }
+        assert_eq!(
+            repo_for_remote_url("https://github.com/42org/app.git")
+                .unwrap()
+                .owner,
+            "42org"
+        );
+        assert!(repo_for_remote_url("https://github.com/a/b/c").is_none());
+        assert!(repo_for_remote_url("/srv/git/treq.git").is_none());
+        assert!(repo_for_remote_url("file:///srv/github.com/a/b").is_none());
+    }
+
+    #[test]
+    fn test_pull_request_from_api() {
+        let body = r#"[{"number":12,"title":"Dark mode","html_url":"https://github.com/o/r/pull/12",
+            "state":"closed","draft":false,"merged_at":"2024-05-01T00:00:00Z",
+            "head":{"ref":"treq/dark-mode","sha":"abc"},"base":{"ref":"main","sha":"def"}}]"#;
+        let pulls: Vec<ApiPullRequest> = serde_json::from_str(body).unwrap();
+        let pr = PullRequest::from(pulls.into_iter().next().unwrap());
+        assert_eq!(pr.state, PullRequestState::Merged);
+        assert_eq!(pr.head_branch, "treq/dark-mode");
+        assert_eq!(pr.base_branch, "main");
+
+        let error = api_error(
+            "create pull request",
+            422,
+            r#"{"message":"Validation Failed","errors":[{"message":"A pull request already exists for o:treq/dark-mode."}]}"#,
+        );
+        assert_eq!(
+            error,
+            "Failed to create pull request: Validation Failed: A pull request already exists for o:treq/dark-mode."
+        );
+    }
+
+    #[test]
+    fn test_checks_from_api() {
+        let body = r#"{"total_count":2,"check_runs":[
+            {"name":"build","status":"completed","conclusion":"skipped","html_url":null},
+            {"name":"test","status":"completed","conclusion":"timed_out","html_url":"https://github.com/o/r/runs/1"},
+            {"name":"deploy","status":"queued","conclusion":null,"html_url":null}]}"#;
+        let runs: ApiCheckRuns = serde_json::from_str(body).unwrap();
+        let states: Vec<String> = runs
+            .check_runs
+            .into_iter()
+            .map(|run| CheckRun::from(run).state)
+            .collect();
+        assert_eq!(states, vec!["success", "failure", "pending"]);
+    }
+}
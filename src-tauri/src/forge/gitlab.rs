@@ -0,0 +1,295 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    encode_path_segment, encode_query_value, forge_get, forge_send, CheckRun, ForgeKind,
+    ForgeProvider, ForgeRepo, PullRequest, PullRequestState,
+};
+
+const GITLAB_HOST: &str = "gitlab.com";
+
+/// The subset of GitLab's merge request object that treq reads
+#[derive(Deserialize)]
+struct ApiMergeRequest {
+    iid: u64,
+    title: String,
+    web_url: String,
+    /// "opened", "closed", "locked" or "merged"
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    source_branch: String,
+    target_branch: String,
+    sha: Option<String>,
+    /// Only present on single merge request responses
+    head_pipeline: Option<ApiPipeline>,
+}
+
+#[derive(Deserialize)]
+struct ApiPipeline {
+    status: String,
+}
+
+/// Status of one job or external check on a commit
+#[derive(Deserialize)]
+struct ApiCommitStatus {
+    name: String,
+    status: String,
+    target_url: Option<String>,
+}
+
+impl From<ApiMergeRequest> for PullRequest {
+    fn from(mr: ApiMergeRequest) -> Self {
+        let state = match mr.state.as_str() {
+            "opened" => PullRequestState::Open,
+            "merged" => PullRequestState::Merged,
+            _ => PullRequestState::Closed,
+        };
+        PullRequest {
+            number: mr.iid,
+            title: mr.title,
+            url: mr.web_url,
+            state,
+            draft: mr.draft,
+            head_branch: mr.source_branch,
+            base_branch: mr.target_branch,
+            head_sha: mr.sha.unwrap_or_default(),
+            checks: mr.head_pipeline.and_then(|p| pipeline_checks(&p.status)),
+        }
+    }
+}
+
+/// Map a pipeline or job status onto the check states shared with the other forges;
+/// None for skipped jobs and jobs that only run when started by hand
+fn pipeline_checks(status: &str) -> Option<String> {
+    let checks = match status {
+        "success" => "success",
+        "failed" => "failure",
+        "canceled" => "error",
+        "skipped" | "manual" => return None,
+        _ => "pending",
+    };
+    Some(checks.to_string())
+}
+
+/// Error message from a failed GitLab API response. `message` is a string, a list of
+/// strings or a map of field names to errors depending on the endpoint.
+fn api_error(action: &str, status: u16, body: &str) -> String {
+    let value: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let message = match &value["message"] {
+        serde_json::Value::String(message) => message.clone(),
+        serde_json::Value::Array(messages) => messages
+            .iter()
+            .filter_map(|m| m.as_str())
+            .collect::<Vec<_>>()
+            .join("; "),
+        serde_json::Value::Object(fields) => fields
+            .iter()
+            .flat_map(|(field, errors)| {
+                errors
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| e.as_str())
+                    .map(move |e| format!("{} {}", field, e))
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => value["error"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| format!("HTTP {}", status)),
+    };
+    format!("Failed to {}: {}", action, message)
+}
+
+/// API id of a project: its URL-encoded full path
+fn project_id(repo: &ForgeRepo) -> String {
+    encode_path_segment(&format!("{}/{}", repo.owner, repo.name))
+}
+
+fn merge_requests_for_branch(
+    repo: &ForgeRepo,
+    branch: &str,
+    per_page: u32,
+) -> Result<Vec<PullRequest>, String> {
+    let path = format!(
+        "projects/{}/merge_requests?source_branch={}&order_by=created_at&sort=desc&per_page={}",
+        project_id(repo),
+        encode_query_value(branch),
+        per_page
+    );
+    let response = forge_get(ForgeKind::Gitlab, &path)?;
+    if response.status != 200 {
+        return Err(api_error(
+            "load merge requests",
+            response.status,
+            &response.body,
+        ));
+    }
+    let merge_requests: Vec<ApiMergeRequest> = serde_json::from_str(&response.body)
+        .map_err(|e| format!("Failed to parse merge requests: {}", e))?;
+    Ok(merge_requests.into_iter().map(PullRequest::from).collect())
+}
+
+pub struct Gitlab;
+
+impl ForgeProvider for Gitlab {
+    fn parse_remote(&self, host: &str, path: &str) -> Option<ForgeRepo> {
+        if !host.eq_ignore_ascii_case(GITLAB_HOST) {
+            return None;
+        }
+        // Projects can live in nested groups: group/subgroup/project
+        let (owner, name) = path.rsplit_once('/')?;
+        if owner.split('/').any(str::is_empty) || name.is_empty() {
+            return None;
+        }
+        Some(ForgeRepo {
+            provider: ForgeKind::Gitlab,
+            owner: owner.to_string(),
+            name: name.to_string(),
+            web_url: format!("https://{}/{}/{}", GITLAB_HOST, owner, name),
+        })
+    }
+
+    fn create_mr(
+        &self,
+        repo: &ForgeRepo,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest, String> {
+        if title.trim().is_empty() {
+            return Err("Merge request title is required".to_string());
+        }
+        let path = format!("projects/{}/merge_requests", project_id(repo));
+        let response = forge_send(
+            ForgeKind::Gitlab,
+            "POST",
+            &path,
+            &json!({
+                "source_branch": head,
+                "target_branch": base,
+                "title": title.trim(),
+                "description": body,
+            }),
+        )?;
+        if response.status != 201 {
+            return Err(api_error(
+                "create merge request",
+                response.status,
+                &response.body,
+            ));
+        }
+        serde_json::from_str::<ApiMergeRequest>(&response.body)
+            .map(PullRequest::from)
+            .map_err(|e| format!("Failed to parse merge request: {}", e))
+    }
+
+    fn list_mrs_for_branch(
+        &self,
+        repo: &ForgeRepo,
+        branch: &str,
+    ) -> Result<Vec<PullRequest>, String> {
+        merge_requests_for_branch(repo, branch, 30)
+    }
+
+    fn get_mr_status(&self, repo: &ForgeRepo, branch: &str) -> Result<Option<PullRequest>, String> {
+        let Some(mr) = merge_requests_for_branch(repo, branch, 1)?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        // The list endpoint omits pipelines; the single merge request carries the head one
+        let path = format!("projects/{}/merge_requests/{}", project_id(repo), mr.number);
+        let detailed = forge_get(ForgeKind::Gitlab, &path)
+            .ok()
+            .filter(|r| r.status == 200)
+            .and_then(|r| serde_json::from_str::<ApiMergeRequest>(&r.body).ok())
+            .map(PullRequest::from);
+        Ok(Some(detailed.unwrap_or(mr)))
+    }
+
+    fn get_checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<CheckRun>, String> {
+        // Only the latest status of each job is returned, so retried jobs don't linger
+        let path = format!(
+            "projects/{}/repository/commits/{}/statuses?per_page=100",
+            project_id(repo),
+            sha
+        );
+        let response = forge_get(ForgeKind::Gitlab, &path)?;
+        if response.status != 200 {
+            return Err(api_error(
+                "load commit statuses",
+                response.status,
+                &response.body,
+            ));
+        }
+        let statuses: Vec<ApiCommitStatus> = serde_json::from_str(&response.body)
+            .map_err(|e| format!("Failed to parse commit statuses: {}", e))?;
+        Ok(statuses
+            .into_iter()
+            .filter_map(|status| {
+                Some(CheckRun {
+                    state: pipeline_checks(&status.status)?,
+                    name: status.name,
+                    url: status.target_url,
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::repo_for_remote_url;
+
+    #[test]
+    fn test_parse_remote_url() {
+        let repo = repo_for_remote_url("git@gitlab.com:acme/platform/treq.git").unwrap();
+        assert_eq!(repo.provider, ForgeKind::Gitlab);
+        assert_eq!(
+            (repo.owner.as_str(), repo.name.as_str()),
+            ("acme/platform", "treq")
+        );
+        assert_eq!(repo.web_url, "https://gitlab.com/acme/platform/treq");
+        assert_eq!(project_id(&repo), "acme%2Fplatform%2Ftreq");
+
+        assert!(repo_for_remote_url("https://gitlab.com/acme").is_none());
+        assert!(repo_for_remote_url("https://gitlab.com/acme//treq").is_none());
+    }
+
+    #[test]
+    fn test_merge_request_from_api() {
+        let body = r#"{"iid":7,"title":"Dark mode","web_url":"https://gitlab.com/o/r/-/merge_requests/7",
+            "state":"opened","draft":true,"source_branch":"treq/dark-mode","target_branch":"main",
+            "sha":"abc","head_pipeline":{"status":"failed"}}"#;
+        let mr = PullRequest::from(serde_json::from_str::<ApiMergeRequest>(body).unwrap());
+        assert_eq!(mr.number, 7);
+        assert_eq!(mr.state, PullRequestState::Open);
+        assert!(mr.draft);
+        assert_eq!(mr.head_branch, "treq/dark-mode");
+        assert_eq!(mr.checks.as_deref(), Some("failure"));
+
+        assert_eq!(
+            api_error(
+                "create merge request",
+                409,
+                r#"{"message":["Another open merge request already exists for this source branch: !7"]}"#,
+            ),
+            "Failed to create merge request: Another open merge request already exists for this source branch: !7"
+        );
+        assert_eq!(
+            api_error(
+                "create merge request",
+                400,
+                r#"{"message":{"title":["can't be blank"]}}"#
+            ),
+            "Failed to create merge request: title can't be blank"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! In-process git queries through libgit2 for paths the file watcher hits constantly.
+//! Callers fall back to the git CLI when these fail.
+
+use git2::{BranchType, ErrorCode, Repository, Status, StatusOptions};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::jj::JjFileChange;
+
+type SharedRepository = Arc<Mutex<Repository>>;
+
+static REPOSITORIES: OnceLock<Mutex<HashMap<String, SharedRepository>>> = OnceLock::new();
+
+fn repositories() -> &'static Mutex<HashMap<String, SharedRepository>> {
+    REPOSITORIES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Run `f` with the cached repository for `workspace_path`, opening it on first use.
+/// The entry is dropped when `f` fails so a moved or broken repository is reopened.
+fn with_repository<T>(
+    workspace_path: &str,
+    f: impl FnOnce(&Repository) -> Result<T, git2::Error>,
+) -> Result<T, git2::Error> {
+    let existing = repositories().lock().unwrap().get(workspace_path).cloned();
+    let repository = match existing {
+        Some(repository) => repository,
+        None => {
+            let repository = Arc::new(Mutex::new(Repository::open(workspace_path)?));
+            repositories()
+                .lock()
+                .unwrap()
+                .insert(workspace_path.to_string(), repository.clone());
+            repository
+        }
+    };
+
+    let result = f(&repository.lock().unwrap());
+    if result.is_err() {
+        forget(workspace_path);
+    }
+    result
+}
+
+/// Drop the cached repository, e.g. after its worktree was removed
+pub fn forget(workspace_path: &str) {
+    repositories().lock().unwrap().remove(workspace_path);
+}
+
+/// Changed files in a worktree, including untracked files, matching
+/// `git status --porcelain --untracked-files=all`
+pub fn changed_files(workspace_path: &str) -> Result<Vec<JjFileChange>, String> {
+    with_repository(workspace_path, |repo| {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false)
+            .renames_head_to_index(true);
+        let statuses = repo.statuses(Some(&mut options))?;
+
+        Ok(statuses
+            .iter()
+            .filter(|entry| !entry.status().is_ignored())
+            .map(|entry| {
+                let rename = entry
+                    .head_to_index()
+                    .filter(|_| entry.status().is_index_renamed());
+                let path = rename
+                    .as_ref()
+                    .and_then(|delta| delta.new_file().path_bytes())
+                    .unwrap_or_else(|| entry.path_bytes());
+                let previous_path = rename
+                    .as_ref()
+                    .and_then(|delta| delta.old_file().path_bytes())
+                    .map(|p| String::from_utf8_lossy(p).to_string());
+                JjFileChange {
+                    path: String::from_utf8_lossy(path).to_string(),
+                    status: status_code(entry.status()).to_string(),
+                    previous_path,
+                    submodule: None,
+                    index_status: index_status(entry.status()).map(str::to_string),
+                    worktree_status: worktree_status(entry.status()).map(str::to_string),
+                }
+            })
+            .collect())
+    })
+    .map_err(|e| format!("Failed to read git status: {}", e))
+}
+
+/// Same mapping as the porcelain parser: additions, deletions, everything else modified
+fn status_code(status: Status) -> &'static str {
+    if status.intersects(Status::WT_NEW | Status::INDEX_NEW) {
+        "A"
+    } else if status.intersects(Status::WT_DELETED | Status::INDEX_DELETED) {
+        "D"
+    } else {
+        "M"
+    }
+}
+
+/// Staged side of a status as a porcelain letter
+fn index_status(status: Status) -> Option<&'static str> {
+    if status.is_conflicted() {
+        Some("U")
+    } else if status.is_index_new() {
+        Some("A")
+    } else if status.is_index_deleted() {
+        Some("D")
+    } else if status.is_index_renamed() {
+        Some("R")
+    } else if status.is_index_typechange() {
+        Some("T")
+    } else if status.is_index_modified() {
+        Some("M")
+    } else {
+        None
+    }
+}
+
+/// Unstaged side of a status as a porcelain letter, "?" for untracked files
+fn worktree_status(status: Status) -> Option<&'static str> {
+    if status.is_conflicted() {
+        Some("U")
+    } else if status.is_wt_new() {
+        Some("?")
+    } else if status.is_wt_deleted() {
+        Some("D")
+    } else if status.is_wt_renamed() {
+        Some("R")
+    } else if status.is_wt_typechange() {
+        Some("T")
+    } else if status.is_wt_modified() {
+        Some("M")
+    } else {
+        None
+    }
+}
+
+/// Commits `branch_name` is ahead of and behind its upstream, as (ahead, behind).
+/// Missing branches and branches without an upstream report (0, 0).
+pub fn ahead_behind(workspace_path: &str, branch_name: &str) -> Result<(usize, usize), String> {
+    with_repository(workspace_path, |repo| {
+        let branch = match repo.find_branch(branch_name, BranchType::Local) {
+            Ok(branch) => branch,
+            Err(e) if e.code() == ErrorCode::NotFound => return Ok((0, 0)),
+            Err(e) => return Err(e),
+        };
+        let upstream = match branch.upstream() {
+            Ok(upstream) => upstream,
+            Err(e) if e.code() == ErrorCode::NotFound => return Ok((0, 0)),
+            Err(e) => return Err(e),
+        };
+        let (Some(local), Some(upstream)) = (branch.get().target(), upstream.get().target()) else {
+            return Ok((0, 0));
+        };
+        repo.graph_ahead_behind(local, upstream)
+    })
+    .map_err(|e| format!("Failed to count commits against upstream: {}", e))
+}
+
+/// HEAD commit (None while the branch is unborn) and the index file's modification
+/// time in milliseconds, for cache invalidation
+pub fn head_state(workspace_path: &str) -> Result<(Option<String>, Option<i64>), String> {
+    with_repository(workspace_path, |repo| {
+        let head = match repo.head() {
+            Ok(head) => head.target().map(|oid| oid.to_string()),
+            Err(e) if matches!(e.code(), ErrorCode::UnbornBranch | ErrorCode::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+        let index_mtime = std::fs::metadata(repo.path().join("index"))
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64);
+        Ok((head, index_mtime))
+    })
+    .map_err(|e| format!("Failed to read HEAD: {}", e))
+}
+
+/// Commit ids of `revisions`, with "?" for ones git can't resolve (e.g. jj revsets)
+pub fn resolve_revisions(workspace_path: &str, revisions: &[&str]) -> Result<Vec<String>, String> {
+    with_repository(workspace_path, |repo| {
+        Ok(revisions
+            .iter()
+            .map(|rev| {
+                repo.revparse_single(rev)
+                    .map(|object| object.id().to_string())
+                    .unwrap_or_else(|_| "?".to_string())
+            })
+            .collect())
+    })
+    .map_err(|e| format!("Failed to resolve revisions: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::run_git;
+    use crate::git_ops::tests::{commit_file, setup_git_repo};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_changed_files_matches_porcelain() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = &setup_git_repo(&temp_dir);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            commit_file(repo, name, &format!("{}\n", name), name);
+        }
+
+        fs::write(Path::new(repo).join("a.txt"), "changed\n").unwrap();
+        fs::remove_file(Path::new(repo).join("b.txt")).unwrap();
+        run_git(repo, &["mv", "c.txt", "d.txt"]).unwrap();
+        fs::create_dir(Path::new(repo).join("new")).unwrap();
+        fs::write(Path::new(repo).join("new/e.txt"), "e\n").unwrap();
+
+        let mut changes = changed_files(repo).unwrap();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        let summary: Vec<(&str, &str, Option<&str>)> = changes
+            .iter()
+            .map(|c| {
+                (
+                    c.path.as_str(),
+                    c.status.as_str(),
+                    c.previous_path.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a.txt", "M", None),
+                ("b.txt", "D", None),
+                ("d.txt", "M", Some("c.txt")),
+                ("new/e.txt", "A", None),
+            ]
+        );
+        let sides: Vec<(Option<&str>, Option<&str>)> = changes
+            .iter()
+            .map(|c| (c.index_status.as_deref(), c.worktree_status.as_deref()))
+            .collect();
+        assert_eq!(
+            sides,
+            vec![
+                (None, Some("M")),
+                (None, Some("D")),
+                (Some("R"), None),
+                (None, Some("?")),
+            ]
+        );
+
+        assert_eq!(ahead_behind(repo, "main").unwrap(), (0, 0));
+        assert_eq!(ahead_behind(repo, "missing").unwrap(), (0, 0));
+        forget(repo);
+    }
+}
@@ -0,0 +1,210 @@
+//! Persistent cache for expensive git/jj queries such as history and branch diffs.
+//!
+//! Entries are stored in the repo's local db together with a state key built from HEAD,
+//! the index mtime, the jj operation head, the repo's jj config and the commits the
+//! query names. A cached result is served only while the key is unchanged, so no
+//! explicit invalidation is needed when commits move or the config changes.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::git2_ops;
+use crate::jj;
+use crate::jj_config;
+use crate::local_db;
+
+/// What a cached result depends on besides committed history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheScope {
+    /// Only commits and refs, e.g. a log or a diff between two revisions
+    History,
+    /// Also the working tree; invalidated by every file watcher event
+    WorkingTree,
+}
+
+static GENERATIONS: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+
+fn generations() -> &'static Mutex<HashMap<String, u64>> {
+    GENERATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Identifies this process, so working-tree entries from an earlier run, which may
+/// have missed edits made while treq was closed, are never served
+fn process_epoch() -> u64 {
+    static EPOCH: OnceLock<u64> = OnceLock::new();
+    *EPOCH.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+    })
+}
+
+/// Record that files in the workspace changed, invalidating its working-tree entries
+pub fn bump_generation(workspace_path: &str) {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let generation = NEXT.fetch_add(1, Ordering::SeqCst);
+    generations()
+        .lock()
+        .unwrap()
+        .insert(workspace_path.to_string(), generation);
+}
+
+fn generation(workspace_path: &str) -> u64 {
+    generations()
+        .lock()
+        .unwrap()
+        .get(workspace_path)
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Current jj operation heads; every jj command that changes the repo moves them
+fn jj_op_heads(repo_path: &str) -> Option<String> {
+    let dir = Path::new(repo_path)
+        .join(".jj")
+        .join("repo")
+        .join("op_heads")
+        .join("heads");
+    let mut heads: Vec<String> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    heads.sort();
+    Some(heads.join(","))
+}
+
+/// Key describing the repository state a result was computed from. jj workspaces are
+/// not git worktrees, so their HEAD and index are read from the repo root.
+fn state_key(
+    repo_path: &str,
+    workspace_path: &str,
+    revisions: &[&str],
+    scope: CacheScope,
+) -> Result<String, String> {
+    let (git_path, (head, index_mtime)) = match git2_ops::head_state(workspace_path) {
+        Ok(state) => (workspace_path, state),
+        Err(_) => (repo_path, git2_ops::head_state(repo_path)?),
+    };
+    let mut key = format!(
+        "{}:{}:{}:{}:{}",
+        head.as_deref().unwrap_or("unborn"),
+        index_mtime.unwrap_or_default(),
+        jj_op_heads(repo_path).unwrap_or_default(),
+        jj_config::repo_config_stamp(repo_path).unwrap_or_default(),
+        git2_ops::resolve_revisions(git_path, revisions)?.join(",")
+    );
+    if scope == CacheScope::WorkingTree {
+        key.push_str(&format!(
+            ":{}-{}",
+            process_epoch(),
+            generation(workspace_path)
+        ));
+    }
+    Ok(key)
+}
+
+/// Serve `kind`/`params` from the cache while the repository state, including the
+/// commits `revisions` point to, is unchanged; otherwise run `compute` and store its
+/// result. Cache failures are logged and fall through to `compute`, so callers behave
+/// as if there were no cache.
+pub fn cached<T, E, F>(
+    workspace_path: &str,
+    kind: &str,
+    params: &str,
+    revisions: &[&str],
+    scope: CacheScope,
+    compute: F,
+) -> Result<T, E>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Result<T, E>,
+{
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    let key = match state_key(&repo_path, workspace_path, revisions, scope) {
+        Ok(key) => key,
+        Err(e) => {
+            log::debug!("Not caching {} for {}: {}", kind, workspace_path, e);
+            return compute();
+        }
+    };
+
+    if let Ok(Some((stored_key, data))) =
+        local_db::get_git_cache(&repo_path, workspace_path, kind, params)
+    {
+        if stored_key == key {
+            match serde_json::from_str(&data) {
+                Ok(value) => return Ok(value),
+                Err(e) => log::warn!("Discarding unreadable {} cache entry: {}", kind, e),
+            }
+        }
+    }
+
+    let value = compute()?;
+    // Re-read the key: the state may have moved while computing, and storing under
+    // the old key would then serve an outdated result
+    match (
+        serde_json::to_string(&value),
+        state_key(&repo_path, workspace_path, revisions, scope),
+    ) {
+        (Ok(data), Ok(after)) if after == key => {
+            if let Err(e) =
+                local_db::set_git_cache(&repo_path, workspace_path, kind, params, &key, &data)
+            {
+                log::warn!("Failed to cache {} for {}: {}", kind, workspace_path, e);
+            }
+        }
+        (Err(e), _) => log::warn!("Failed to encode {} for caching: {}", kind, e),
+        _ => {}
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::tests::{commit_file, setup_git_repo};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cached_until_head_or_generation_moves() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = &setup_git_repo(&temp_dir);
+        commit_file(repo, "one.txt", "one", "One");
+        local_db::init_local_db(repo).unwrap();
+
+        let history = |n: i32| {
+            cached(repo, "test", "p", &[], CacheScope::History, || {
+                Ok::<_, String>(n)
+            })
+        };
+        assert_eq!(history(1).unwrap(), 1);
+        assert_eq!(history(2).unwrap(), 1);
+        let working = |n: i32| {
+            cached(repo, "test", "w", &[], CacheScope::WorkingTree, || {
+                Ok::<_, String>(n)
+            })
+        };
+        assert_eq!(working(1).unwrap(), 1);
+        bump_generation(repo);
+        assert_eq!(working(2).unwrap(), 2);
+        assert_eq!(history(3).unwrap(), 1);
+
+        commit_file(repo, "two.txt", "two", "Two");
+        assert_eq!(history(4).unwrap(), 4);
+        let failed: Result<i32, &str> =
+            cached(repo, "test", "e", &["main"], CacheScope::History, || {
+                Err("boom")
+            });
+        assert_eq!(failed, Err("boom"));
+        git2_ops::forget(repo);
+    }
+}
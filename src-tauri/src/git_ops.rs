@@ -0,0 +1,4862 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::askpass;
+use crate::async_process::{self, AsyncCommand};
+use crate::binary_paths;
+use crate::commit_signing::SigningConfig;
+use crate::db::Database;
+use crate::file_guard::{self, SkippedDiff};
+use crate::git2_ops;
+use crate::git_submodules;
+use crate::jj::{self, FileHunks, JjDiffHunk};
+use crate::local_db;
+use crate::patch_model::Hunk;
+use crate::paths;
+use crate::process_limiter::LimitedCommand;
+use crate::timestamps;
+
+/// Helper function to create Command for a binary using cached path
+fn command_for(binary: &str) -> LimitedCommand {
+    let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
+    LimitedCommand::new(path)
+}
+
+/// Like `command_for`, for commands that can run for minutes
+fn async_command_for(binary: &str) -> AsyncCommand {
+    let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
+    AsyncCommand::new(path)
+}
+
+/// Error type for git operations, serialized as `{ kind, message }` so the frontend can
+/// tell failures apart
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum GitError {
+    Conflict(String),
+    AuthRequired(String),
+    DetachedHead(String),
+    NetworkError(String),
+    NotFound(String),
+    Other(String),
+}
+
+impl GitError {
+    /// Classify a failed git command by its stderr
+    pub fn from_stderr(stderr: &str) -> GitError {
+        let message = stderr.trim().to_string();
+        let lower = message.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+        if matches(&[
+            "conflict",
+            "needs merge",
+            "unmerged paths",
+            "could not apply",
+        ]) {
+            GitError::Conflict(message)
+        } else if matches(&[
+            "authentication failed",
+            "permission denied (publickey",
+            "could not read username",
+            "could not read password",
+            "terminal prompts disabled",
+            "invalid username or password",
+            "the requested url returned error: 403",
+        ]) {
+            GitError::AuthRequired(message)
+        } else if matches(&[
+            "not currently on any branch",
+            "not currently on a branch",
+            "head detached",
+            "ref head is not a symbolic ref",
+        ]) {
+            GitError::DetachedHead(message)
+        } else if matches(&[
+            "could not resolve host",
+            "connection timed out",
+            "connection refused",
+            "network is unreachable",
+            "operation timed out",
+            "unable to access",
+            "early eof",
+        ]) {
+            GitError::NetworkError(message)
+        } else if matches(&[
+            "not a git repository",
+            "unknown revision",
+            "bad revision",
+            "bad object",
+            "did not match any",
+            "does not exist",
+            "no such",
+            "invalid reference",
+            "needed a single revision",
+            "not a valid",
+        ]) {
+            GitError::NotFound(message)
+        } else {
+            GitError::Other(message)
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            GitError::Conflict(m)
+            | GitError::AuthRequired(m)
+            | GitError::DetachedHead(m)
+            | GitError::NetworkError(m)
+            | GitError::NotFound(m)
+            | GitError::Other(m) => m,
+        }
+    }
+
+    /// Prefix the message while keeping the kind
+    pub fn context(self, prefix: &str) -> GitError {
+        let wrap = |m: String| format!("{}: {}", prefix, m);
+        match self {
+            GitError::Conflict(m) => GitError::Conflict(wrap(m)),
+            GitError::AuthRequired(m) => GitError::AuthRequired(wrap(m)),
+            GitError::DetachedHead(m) => GitError::DetachedHead(wrap(m)),
+            GitError::NetworkError(m) => GitError::NetworkError(wrap(m)),
+            GitError::NotFound(m) => GitError::NotFound(wrap(m)),
+            GitError::Other(m) => GitError::Other(wrap(m)),
+        }
+    }
+}
+
+impl std::fmt::Display for GitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl From<String> for GitError {
+    fn from(message: String) -> Self {
+        GitError::Other(message)
+    }
+}
+
+impl From<&str> for GitError {
+    fn from(message: &str) -> Self {
+        GitError::Other(message.to_string())
+    }
+}
+
+/// Run a git command in `repo_path` and return stdout, or its stderr classified as a
+/// `GitError`
+pub(crate) fn run_git(repo_path: &str, args: &[&str]) -> Result<String, GitError> {
+    let stdout = run_git_bytes(repo_path, args)?;
+    Ok(String::from_utf8_lossy(&stdout).to_string())
+}
+
+/// Like run_git, but returns stdout unmodified, e.g. for blob contents
+fn run_git_bytes(repo_path: &str, args: &[&str]) -> Result<Vec<u8>, GitError> {
+    let output = command_for("git")
+        .current_dir(repo_path)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(GitError::from_stderr(&String::from_utf8_lossy(
+            &output.stderr,
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Like run_git, for commands that talk to a remote: credential prompts are forwarded
+/// to the frontend, labelled with `operation`, without blocking a thread meanwhile
+async fn run_git_remote(
+    repo_path: &str,
+    args: &[&str],
+    operation: &str,
+) -> Result<String, GitError> {
+    let session = askpass::session(operation);
+    let mut command = async_command_for("git");
+    command
+        .timeout(async_process::REMOTE_TIMEOUT)
+        .current_dir(repo_path)
+        .args(args);
+    session.apply_async(&mut command);
+    let output = command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        session.check_rejected(&stderr);
+        return Err(GitError::from_stderr(&stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Reject revision arguments that git would interpret as options
+pub(crate) fn validate_rev_arg(rev: &str, label: &str) -> Result<(), GitError> {
+    if rev.is_empty() || rev.starts_with('-') || rev.contains('\0') {
+        return Err(GitError::Other(format!("Invalid {}", label)));
+    }
+    Ok(())
+}
+
+/// Run `git commit` with `args`, signed with `signing` when it is set
+fn run_git_commit(
+    workspace_path: &str,
+    args: &[&str],
+    signing: Option<&SigningConfig>,
+) -> Result<String, GitError> {
+    let mut full_args = signing.map(|s| s.git_config_args()).unwrap_or_default();
+    full_args.push("commit".to_string());
+    if signing.is_some() {
+        full_args.push("-S".to_string());
+    }
+    full_args.extend(args.iter().map(|a| a.to_string()));
+    let full_args: Vec<&str> = full_args.iter().map(String::as_str).collect();
+    run_git(workspace_path, &full_args)
+}
+
+// ============================================================================
+// Email Patch Workflow
+// ============================================================================
+
+/// Options for preparing (and optionally sending) a patch series by email
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PatchEmailOptions {
+    /// Generate a 0000-cover-letter.patch in front of the series
+    #[serde(default)]
+    pub cover_letter: bool,
+    /// Directory for the generated files (defaults to .treq/patches/email-<timestamp>)
+    pub output_dir: Option<String>,
+    /// Subject prefix, e.g. "PATCH v2" (defaults to git's "PATCH")
+    pub subject_prefix: Option<String>,
+    /// Invoke git send-email after generating the files
+    #[serde(default)]
+    pub send: bool,
+    #[serde(default)]
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+}
+
+/// A single generated patch file and its send status
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatchEmailFile {
+    pub path: String,
+    pub subject: String,
+    pub is_cover_letter: bool,
+    pub sent: bool,
+    pub error: Option<String>,
+}
+
+/// Result of prepare_patch_email
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatchEmailResult {
+    pub output_dir: String,
+    pub patches: Vec<PatchEmailFile>,
+    pub send_attempted: bool,
+    pub send_output: Option<String>,
+}
+
+/// Extract the Subject header from a format-patch file, unfolding continuation lines
+fn read_patch_subject(content: &str) -> String {
+    let mut subject = String::new();
+    let mut in_subject = false;
+
+    for line in content.lines() {
+        if in_subject {
+            if line.starts_with(' ') || line.starts_with('\t') {
+                subject.push(' ');
+                subject.push_str(line.trim());
+                continue;
+            }
+            break;
+        }
+        if let Some(rest) = line.strip_prefix("Subject: ") {
+            subject.push_str(rest.trim());
+            in_subject = true;
+        } else if line.is_empty() {
+            // End of headers
+            break;
+        }
+    }
+
+    subject
+}
+
+/// Parse `git send-email` output into one status per message, in send order.
+/// Each message ends with a "Result: ..." line ("Result: OK" or "Result: 250 ...").
+fn parse_send_email_results(output: &str) -> Vec<Result<(), String>> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Result: "))
+        .map(|result| {
+            let result = result.trim();
+            if result == "OK" || result.starts_with('2') {
+                Ok(())
+            } else {
+                Err(result.to_string())
+            }
+        })
+        .collect()
+}
+
+/// Check that git send-email has somewhere to deliver mail
+fn has_send_email_config(repo_path: &str) -> bool {
+    ["sendemail.smtpserver", "sendemail.sendmailcmd"]
+        .iter()
+        .any(|key| {
+            run_git(repo_path, &["config", "--get", key])
+                .map(|v| !v.trim().is_empty())
+                .unwrap_or(false)
+        })
+}
+
+/// Fresh `.treq/patches/<kind>-<timestamp>` directory path for generated patch files
+fn default_patch_dir(repo_path: &str, kind: &str) -> String {
+    Path::new(repo_path)
+        .join(".treq")
+        .join("patches")
+        .join(format!("{}-{}", kind, Utc::now().format("%Y%m%d-%H%M%S")))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Run `git format-patch` for `revisions` into `output_dir` and read back the subject of
+/// each generated file, in series order
+fn format_patch_files(
+    repo_path: &str,
+    output_dir: &str,
+    cover_letter: bool,
+    subject_prefix: Option<&str>,
+    revisions: &[&str],
+) -> Result<Vec<PatchEmailFile>, GitError> {
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create patch directory: {}", e))?;
+
+    let mut args: Vec<String> = vec![
+        "format-patch".to_string(),
+        "--output-directory".to_string(),
+        output_dir.to_string(),
+    ];
+    if cover_letter {
+        args.push("--cover-letter".to_string());
+    }
+    if let Some(prefix) = subject_prefix {
+        args.push(format!("--subject-prefix={}", prefix));
+    }
+    args.extend(revisions.iter().map(|r| r.to_string()));
+
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let stdout = run_git(repo_path, &arg_refs).map_err(|e| e.context("git format-patch failed"))?;
+
+    // format-patch prints one generated path per line
+    Ok(stdout
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|path| {
+            let full_path = if Path::new(path).is_absolute() {
+                path.to_string()
+            } else {
+                Path::new(repo_path)
+                    .join(path)
+                    .to_string_lossy()
+                    .to_string()
+            };
+            let subject = fs::read_to_string(&full_path)
+                .map(|content| read_patch_subject(&content))
+                .unwrap_or_default();
+            PatchEmailFile {
+                is_cover_letter: full_path.ends_with("0000-cover-letter.patch"),
+                path: full_path,
+                subject,
+                sent: false,
+                error: None,
+            }
+        })
+        .collect())
+}
+
+/// Generate a format-patch series for `range` and optionally send it with git send-email.
+///
+/// SMTP settings are taken from the repository's `sendemail.*` git config, so the
+/// same configuration used on the command line applies here.
+pub fn prepare_patch_email(
+    repo_path: &str,
+    range: &str,
+    options: &PatchEmailOptions,
+) -> Result<PatchEmailResult, GitError> {
+    validate_rev_arg(range, "revision range")?;
+
+    let output_dir = match &options.output_dir {
+        Some(dir) => dir.clone(),
+        None => default_patch_dir(repo_path, "email"),
+    };
+    let mut patches = format_patch_files(
+        repo_path,
+        &output_dir,
+        options.cover_letter,
+        options.subject_prefix.as_deref(),
+        &[range],
+    )?;
+
+    if patches.is_empty() {
+        return Err(GitError::Other(format!(
+            "No commits found in range '{}'",
+            range
+        )));
+    }
+
+    if !options.send {
+        return Ok(PatchEmailResult {
+            output_dir,
+            patches,
+            send_attempted: false,
+            send_output: None,
+        });
+    }
+
+    if options.to.is_empty() {
+        return Err(GitError::Other(
+            "At least one recipient is required to send patches".to_string(),
+        ));
+    }
+
+    if !has_send_email_config(repo_path) {
+        return Err(GitError::Other(
+            "git send-email is not configured (set sendemail.smtpServer in git config)".to_string(),
+        ));
+    }
+
+    let mut cmd = command_for("git");
+    cmd.current_dir(repo_path)
+        .args(["send-email", "--confirm=never"]);
+    for to in &options.to {
+        cmd.arg(format!("--to={}", to));
+    }
+    for cc in &options.cc {
+        cmd.arg(format!("--cc={}", cc));
+    }
+    for patch in &patches {
+        cmd.arg(&patch.path);
+    }
+
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to execute git send-email: {}", e))?;
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Messages are sent in argument order; anything without a result line was not sent
+    let results = parse_send_email_results(&combined);
+    for (i, patch) in patches.iter_mut().enumerate() {
+        match results.get(i) {
+            Some(Ok(())) => patch.sent = true,
+            Some(Err(e)) => patch.error = Some(e.clone()),
+            None => {
+                patch.error = Some(if output.status.success() {
+                    "No send result reported".to_string()
+                } else {
+                    "Not sent: git send-email failed".to_string()
+                })
+            }
+        }
+    }
+
+    Ok(PatchEmailResult {
+        output_dir,
+        patches,
+        send_attempted: true,
+        send_output: Some(combined),
+    })
+}
+
+/// Write the commits of `target_branch..@` as numbered mails into `output_dir`
+/// (`.treq/patches/series-<timestamp>` by default) for mailing-list workflows. The
+/// working copy commit is left out while it has no description, since its changes are
+/// not committed yet.
+pub fn format_patch_series(
+    workspace_path: &str,
+    target_branch: &str,
+    output_dir: Option<&str>,
+    cover_letter: bool,
+) -> Result<PatchEmailResult, GitError> {
+    let ahead = jj::jj_get_commits_ahead(workspace_path, target_branch)
+        .map_err(|e| GitError::Other(e.to_string()))?;
+    let commits: Vec<&jj::JjLogCommit> = ahead
+        .commits
+        .iter()
+        .filter(|c| !(c.is_working_copy && c.description == "(no description)"))
+        .collect();
+    // jj lists the range newest first
+    let Some(newest) = commits.first() else {
+        return Err(GitError::Other(format!(
+            "No commits ahead of '{}'",
+            target_branch
+        )));
+    };
+
+    // jj workspaces are not git worktrees, but their commits are in the repo's git store
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    let output_dir = match output_dir {
+        Some(dir) => dir.to_string(),
+        None => default_patch_dir(&repo_path, "series"),
+    };
+    // A count like -3 would walk into merged-in history, so pass the range jj listed
+    let target = jj::jj_get_commit_id(workspace_path, target_branch)
+        .map_err(|e| GitError::Other(e.to_string()))?;
+    let range = format!("{}..{}", target, newest.commit_id);
+    let patches = format_patch_files(&repo_path, &output_dir, cover_letter, None, &[&range])?;
+
+    Ok(PatchEmailResult {
+        output_dir,
+        patches,
+        send_attempted: false,
+        send_output: None,
+    })
+}
+
+// ============================================================================
+// Patch Files
+// ============================================================================
+
+/// What `export_patch` writes out
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PatchSelection {
+    /// Commits as `git format-patch` mails, so authorship survives `git am`: a single
+    /// commit, or a range such as "main..HEAD"
+    Revisions { revspec: String },
+    /// Uncommitted changes to these files (all changes when empty), including untracked
+    /// files
+    Files { paths: Vec<String> },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatchExportResult {
+    pub dest_path: String,
+    pub files: Vec<String>,
+    /// Commits in the patch; 0 for uncommitted changes
+    pub commit_count: usize,
+}
+
+/// A hunk `git apply` could not place
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PatchHunkFailure {
+    pub path: String,
+    /// Line of the original file the hunk starts at
+    pub line: usize,
+    /// The hunk's "@@ -a,b +c,d @@" header, when it could be found in the patch
+    pub header: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct PatchApplyResult {
+    /// Applied, or with `dry_run` would apply, without failed hunks or conflicts
+    pub success: bool,
+    pub dry_run: bool,
+    pub files: Vec<String>,
+    /// Hunks that don't apply; without a three-way merge nothing is written then
+    pub failed_hunks: Vec<PatchHunkFailure>,
+    /// Files a three-way apply merged with conflict markers
+    pub conflicted_files: Vec<String>,
+    /// Other problems git reported, e.g. a file the patch creates already exists
+    pub errors: Vec<String>,
+}
+
+/// Commits of `revspec` as `git format-patch` mails, with the number of commits
+pub fn git_format_patch(repo_path: &str, revspec: &str) -> Result<(Vec<u8>, usize), GitError> {
+    validate_rev_arg(revspec, "revision range")?;
+    let mut args = vec!["format-patch", "--stdout", "--binary"];
+    if !revspec.contains("..") {
+        args.push("-1");
+    }
+    args.push(revspec);
+
+    let output = command_for("git")
+        .current_dir(repo_path)
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to execute git format-patch: {}", e))?;
+    if !output.status.success() {
+        return Err(
+            GitError::from_stderr(&String::from_utf8_lossy(&output.stderr))
+                .context("git format-patch failed"),
+        );
+    }
+
+    // Every mail starts with git's fixed mbox "From <commit> Mon Sep 17 00:00:00 2001" line
+    let commit_count = output
+        .stdout
+        .split(|b| *b == b'\n')
+        .filter(|line| line.starts_with(b"From ") && line.ends_with(b" 2001"))
+        .count();
+    if commit_count == 0 {
+        return Err(GitError::Other(format!("No commits in '{}'", revspec)));
+    }
+    Ok((output.stdout, commit_count))
+}
+
+/// Files a git patch touches, by their new path, in patch order
+fn patch_file_paths(patch: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for line in patch.lines() {
+        if let Some((_, path)) = line
+            .strip_prefix("diff --git ")
+            .and_then(|rest| rest.rsplit_once(" b/"))
+        {
+            if !paths.iter().any(|p| p == path) {
+                paths.push(path.to_string());
+            }
+        }
+    }
+    paths
+}
+
+/// Write `patch` to `dest_path`, creating its directory
+pub fn write_patch_file(
+    patch: &[u8],
+    dest_path: &str,
+    commit_count: usize,
+) -> Result<PatchExportResult, GitError> {
+    if patch.is_empty() {
+        return Err(GitError::Other("Nothing to export".to_string()));
+    }
+    if let Some(parent) = Path::new(dest_path).parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create patch directory: {}", e))?;
+    }
+    fs::write(dest_path, patch).map_err(|e| format!("Failed to write patch: {}", e))?;
+
+    Ok(PatchExportResult {
+        dest_path: dest_path.to_string(),
+        files: patch_file_paths(&String::from_utf8_lossy(patch)),
+        commit_count,
+    })
+}
+
+/// Header of the hunk of `path` starting at original line `line`. Handles both
+/// `diff --git` patches and plain `diff -u` output.
+fn find_hunk_header(patch: &str, path: &str, line: usize) -> Option<String> {
+    let mut in_file = false;
+    for patch_line in patch.lines() {
+        if let Some(rest) = patch_line.strip_prefix("diff --git ") {
+            in_file = rest.ends_with(&format!(" b/{}", path))
+                || rest.starts_with(&format!("a/{} ", path));
+        } else if let Some(rest) = patch_line.strip_prefix("+++ ") {
+            let name = rest.split('\t').next().unwrap_or(rest).trim();
+            in_file = in_file || name.strip_prefix("b/").unwrap_or(name) == path;
+        } else if in_file && patch_line.starts_with("@@ -") {
+            if let Some((old_start, _, _)) = parse_hunk_header(patch_line) {
+                if old_start == line {
+                    return Some(patch_line.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Collect files, failed hunks, conflicts and errors from `git apply -v` output
+fn parse_apply_output(output: &str, patch: &str, result: &mut PatchApplyResult) {
+    let mut merged: Vec<&str> = Vec::new();
+    for line in output.lines() {
+        if let Some(path) = line
+            .strip_prefix("Checking patch ")
+            .and_then(|rest| rest.strip_suffix("..."))
+        {
+            if !result.files.iter().any(|f| f == path) {
+                result.files.push(path.to_string());
+            }
+        } else if let Some(path) = line
+            .strip_prefix("Applied patch to '")
+            .and_then(|rest| rest.strip_suffix("' with conflicts."))
+        {
+            result.conflicted_files.push(path.to_string());
+        } else if let Some(path) = line
+            .strip_prefix("Applied patch to '")
+            .and_then(|rest| rest.strip_suffix("' cleanly."))
+        {
+            merged.push(path);
+        } else if let Some(location) = line.strip_prefix("error: patch failed: ") {
+            if let Some((path, start)) = location.rsplit_once(':') {
+                let line = start.trim().parse().unwrap_or(0);
+                result.failed_hunks.push(PatchHunkFailure {
+                    path: path.to_string(),
+                    line,
+                    header: find_hunk_header(patch, path, line),
+                });
+            }
+        } else if let Some(error) = line.strip_prefix("error: ") {
+            // Context of a failed hunk and its per-file summary are already covered
+            if error != "while searching for:" && !error.ends_with(": patch does not apply") {
+                result.errors.push(error.to_string());
+            }
+        }
+    }
+
+    // Hunks that failed to apply directly but were then merged three-way did land
+    result.failed_hunks.retain(|hunk| {
+        !merged.contains(&hunk.path.as_str()) && !result.conflicted_files.contains(&hunk.path)
+    });
+}
+
+/// Apply a .patch/.diff file, plain or `git format-patch` output, to a worktree's
+/// files. With `three_way` hunks that don't apply are merged, leaving conflict markers;
+/// otherwise nothing is written unless every hunk applies. `dry_run` only reports what
+/// would happen. The index is never touched: a three-way apply stages through a
+/// temporary one.
+pub fn git_apply_patch_file(
+    workspace_path: &str,
+    git_dir: Option<&Path>,
+    patch_path: &str,
+    three_way: bool,
+    dry_run: bool,
+) -> Result<PatchApplyResult, GitError> {
+    if patch_path.is_empty() || patch_path.starts_with('-') || patch_path.contains('\0') {
+        return Err(GitError::Other("Invalid patch path".to_string()));
+    }
+    let patch = fs::read(patch_path)
+        .map_err(|e| GitError::NotFound(format!("Failed to read patch file: {}", e)))?;
+
+    let index = three_way.then(TempIndex::new);
+    let mut command = git_in(workspace_path, git_dir);
+    if let Some(index) = &index {
+        // --3way works through the index, which must match the files being patched
+        index.run(workspace_path, git_dir, &["read-tree", "HEAD"])?;
+        index.run(workspace_path, git_dir, &["add", "-A"])?;
+        command.env("GIT_INDEX_FILE", &index.0);
+    }
+    command.args(["apply", "-v", "--binary", "--whitespace=nowarn"]);
+    if three_way {
+        command.arg("--3way");
+    }
+    if dry_run {
+        command.arg("--check");
+    }
+    let output = command
+        .arg(patch_path)
+        .output()
+        .map_err(|e| format!("Failed to execute git apply: {}", e))?;
+
+    let mut result = PatchApplyResult {
+        dry_run,
+        ..Default::default()
+    };
+    parse_apply_output(
+        &format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        &String::from_utf8_lossy(&patch),
+        &mut result,
+    );
+    if !output.status.success()
+        && result.failed_hunks.is_empty()
+        && result.conflicted_files.is_empty()
+        && result.errors.is_empty()
+    {
+        result.errors.push("git apply failed".to_string());
+    }
+    result.success = output.status.success()
+        && result.failed_hunks.is_empty()
+        && result.conflicted_files.is_empty()
+        && result.errors.is_empty();
+    Ok(result)
+}
+
+// ============================================================================
+// Blame
+// ============================================================================
+
+/// Blame annotation for a single line
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BlameLine {
+    /// 1-based line number in the blamed revision of the file
+    pub line_number: u32,
+    pub commit_hash: String,
+    pub author: String,
+    pub author_email: String,
+    /// Author time (unix timestamp)
+    pub timestamp: i64,
+    pub summary: String,
+    pub content: String,
+}
+
+/// An author ranked by how many lines they own in a range
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LineAuthor {
+    pub name: String,
+    pub email: String,
+    pub line_count: usize,
+    /// Most recent commit by this author touching the range (unix timestamp)
+    pub last_commit_time: i64,
+    pub last_commit_id: String,
+    pub last_commit_summary: String,
+}
+
+/// Parse `git blame --porcelain` output into one entry per line.
+///
+/// Porcelain output only lists a commit's metadata the first time the commit appears,
+/// so metadata is remembered per commit and reused for later lines.
+fn parse_blame_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut commits: std::collections::HashMap<String, BlameLine> =
+        std::collections::HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<BlameLine> = None;
+
+    for line in output.lines() {
+        if let Some(info) = current.as_mut() {
+            if let Some(content) = line.strip_prefix('\t') {
+                // Content line terminates the record
+                let mut done = current.take().unwrap();
+                done.content = content.to_string();
+                commits
+                    .entry(done.commit_hash.clone())
+                    .or_insert_with(|| done.clone());
+                lines.push(done);
+            } else if let Some(v) = line.strip_prefix("author ") {
+                info.author = v.to_string();
+            } else if let Some(v) = line.strip_prefix("author-mail ") {
+                info.author_email = v.trim_start_matches('<').trim_end_matches('>').to_string();
+            } else if let Some(v) = line.strip_prefix("author-time ") {
+                info.timestamp = v.trim().parse().unwrap_or(0);
+            } else if let Some(v) = line.strip_prefix("summary ") {
+                info.summary = v.to_string();
+            }
+        } else {
+            // Header: "<sha> <orig-line> <final-line> [<num-lines>]"
+            let mut parts = line.split_whitespace();
+            let sha = parts.next().unwrap_or("");
+            if sha.len() < 40 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+            let line_number = parts.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            let mut info = commits.get(sha).cloned().unwrap_or_else(|| BlameLine {
+                line_number: 0,
+                commit_hash: sha.to_string(),
+                author: String::new(),
+                author_email: String::new(),
+                timestamp: 0,
+                summary: String::new(),
+                content: String::new(),
+            });
+            info.line_number = line_number;
+            current = Some(info);
+        }
+    }
+
+    lines
+}
+
+/// Aggregate blamed lines into authors ranked by line count, then recency.
+/// Uncommitted lines (all-zero commit id) are skipped.
+fn rank_line_authors(lines: &[BlameLine]) -> Vec<LineAuthor> {
+    let mut authors: Vec<LineAuthor> = Vec::new();
+
+    for line in lines {
+        if line.commit_hash.chars().all(|c| c == '0') {
+            continue;
+        }
+
+        let key = if line.author_email.is_empty() {
+            &line.author
+        } else {
+            &line.author_email
+        };
+        let existing = authors.iter_mut().find(|a| {
+            if a.email.is_empty() {
+                &a.name == key
+            } else {
+                &a.email == key
+            }
+        });
+
+        match existing {
+            Some(author) => {
+                author.line_count += 1;
+                if line.timestamp > author.last_commit_time {
+                    author.last_commit_time = line.timestamp;
+                    author.last_commit_id = line.commit_hash.clone();
+                    author.last_commit_summary = line.summary.clone();
+                }
+            }
+            None => authors.push(LineAuthor {
+                name: line.author.clone(),
+                email: line.author_email.clone(),
+                line_count: 1,
+                last_commit_time: line.timestamp,
+                last_commit_id: line.commit_hash.clone(),
+                last_commit_summary: line.summary.clone(),
+            }),
+        }
+    }
+
+    authors.sort_by(|a, b| {
+        b.line_count
+            .cmp(&a.line_count)
+            .then_with(|| b.last_commit_time.cmp(&a.last_commit_time))
+    });
+    authors
+}
+
+/// Standard file listing revisions blame should skip (e.g. mass reformat commits)
+pub const BLAME_IGNORE_REVS_FILE: &str = ".git-blame-ignore-revs";
+
+/// Build `--ignore-revs-file` / `--ignore-rev` arguments for git blame.
+///
+/// Uses the workspace's `.git-blame-ignore-revs` when present, plus any extra revisions
+/// configured for the repository. Revisions that don't resolve are skipped, since git
+/// blame refuses to run with an unknown `--ignore-rev`.
+fn blame_ignore_args(workspace_path: &str, extra_revs: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+
+    let ignore_file = Path::new(workspace_path).join(BLAME_IGNORE_REVS_FILE);
+    if ignore_file.is_file() {
+        args.push("--ignore-revs-file".to_string());
+        args.push(ignore_file.to_string_lossy().to_string());
+    }
+
+    for rev in extra_revs.iter().map(|r| r.trim()) {
+        if validate_rev_arg(rev, "revision").is_err() {
+            continue;
+        }
+        let resolved = run_git(
+            workspace_path,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{commit}}", rev),
+            ],
+        );
+        if let Ok(sha) = resolved {
+            args.push("--ignore-rev".to_string());
+            args.push(sha.trim().to_string());
+        }
+    }
+
+    args
+}
+
+/// Append a commit to the repository's `.git-blame-ignore-revs` file.
+///
+/// The commit is resolved to its full id and recorded with its subject as a comment.
+/// Returns the full commit id; adding a commit that is already listed is a no-op.
+pub fn add_blame_ignore_rev(repo_path: &str, commit: &str) -> Result<String, GitError> {
+    validate_rev_arg(commit, "commit")?;
+
+    let sha = run_git(
+        repo_path,
+        &["rev-parse", "--verify", &format!("{}^{{commit}}", commit)],
+    )
+    .map_err(|e| e.context(&format!("Invalid commit '{}'", commit)))?
+    .trim()
+    .to_string();
+
+    let file_path = Path::new(repo_path).join(BLAME_IGNORE_REVS_FILE);
+    let existing = fs::read_to_string(&file_path).unwrap_or_default();
+    if existing.lines().any(|line| line.trim() == sha) {
+        return Ok(sha);
+    }
+
+    let subject = run_git(repo_path, &["log", "-1", "--format=%s", &sha])
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default();
+
+    let mut content = existing;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    content.push_str(&format!("# {}\n{}\n", subject, sha));
+
+    fs::write(&file_path, content)
+        .map_err(|e| format!("Failed to write {}: {}", BLAME_IGNORE_REVS_FILE, e))?;
+
+    Ok(sha)
+}
+
+/// Rank the authors of lines `start_line..=end_line` (1-based) in a file.
+///
+/// Used by the diff view to suggest reviewers for a selected region. Commits listed in
+/// `.git-blame-ignore-revs` or `ignore_revs` are looked through.
+pub fn get_line_authors(
+    workspace_path: &str,
+    file_path: &str,
+    start_line: u32,
+    end_line: u32,
+    ignore_revs: &[String],
+) -> Result<Vec<LineAuthor>, GitError> {
+    if start_line == 0 || end_line < start_line {
+        return Err(GitError::Other(format!(
+            "Invalid line range {}-{}",
+            start_line, end_line
+        )));
+    }
+    if file_path.is_empty() || file_path.contains('\0') {
+        return Err(GitError::Other("Invalid file path".to_string()));
+    }
+
+    let range = format!("{},{}", start_line, end_line);
+    let mut args: Vec<String> = vec![
+        "blame".to_string(),
+        "--porcelain".to_string(),
+        "-w".to_string(),
+    ];
+    args.extend(blame_ignore_args(workspace_path, ignore_revs));
+    args.extend([
+        "-L".to_string(),
+        range,
+        "--".to_string(),
+        file_path.to_string(),
+    ]);
+
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = run_git(workspace_path, &arg_refs).map_err(|e| e.context("git blame failed"))?;
+
+    Ok(rank_line_authors(&parse_blame_porcelain(&output)))
+}
+
+/// Blame every line of a file, optionally at a specific revision (defaults to the working tree).
+/// Commits listed in `.git-blame-ignore-revs` or `ignore_revs` are looked through.
+pub fn git_blame(
+    workspace_path: &str,
+    file_path: &str,
+    rev: Option<&str>,
+    ignore_revs: &[String],
+) -> Result<Vec<BlameLine>, GitError> {
+    if file_path.is_empty() || file_path.contains('\0') {
+        return Err(GitError::Other("Invalid file path".to_string()));
+    }
+
+    let mut args: Vec<String> = vec!["blame".to_string(), "--porcelain".to_string()];
+    args.extend(blame_ignore_args(workspace_path, ignore_revs));
+    if let Some(rev) = rev.filter(|r| !r.is_empty()) {
+        validate_rev_arg(rev, "revision")?;
+        args.push(rev.to_string());
+    }
+    args.push("--".to_string());
+    args.push(file_path.to_string());
+
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = run_git(workspace_path, &arg_refs).map_err(|e| e.context("git blame failed"))?;
+
+    Ok(parse_blame_porcelain(&output))
+}
+
+// ============================================================================
+// Fixup Commits
+// ============================================================================
+
+/// Result of an autosquash rebase
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitRebaseResult {
+    pub success: bool,
+    pub message: String,
+    /// Files left conflicted when the rebase stopped (the rebase is aborted)
+    pub conflicted_files: Vec<String>,
+}
+
+/// List files with unresolved merge conflicts
+fn get_unmerged_files(workspace_path: &str) -> Vec<String> {
+    run_git(workspace_path, &["diff", "--name-only", "--diff-filter=U"])
+        .map(|out| {
+            out.lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Create a `fixup!` commit targeting `target_commit`.
+///
+/// When `paths` is empty the current index is committed; otherwise only the given
+/// paths are staged and committed. Returns the new commit id.
+pub fn git_commit_fixup(
+    workspace_path: &str,
+    target_commit: &str,
+    paths: &[String],
+    signing: Option<&SigningConfig>,
+) -> Result<String, GitError> {
+    validate_rev_arg(target_commit, "target commit")?;
+    if paths.iter().any(|p| p.is_empty() || p.contains('\0')) {
+        return Err(GitError::Other("Invalid file path".to_string()));
+    }
+
+    // Resolve up front so a bad target fails before anything is staged
+    let target = run_git(
+        workspace_path,
+        &[
+            "rev-parse",
+            "--verify",
+            &format!("{}^{{commit}}", target_commit),
+        ],
+    )
+    .map_err(|e| e.context(&format!("Invalid target commit '{}'", target_commit)))?;
+    let target = target.trim();
+
+    let fixup_arg = format!("--fixup={}", target);
+    if paths.is_empty() {
+        run_git_commit(workspace_path, &["--no-edit", &fixup_arg], signing)
+            .map_err(|e| e.context("Failed to create fixup commit"))?;
+    } else {
+        let mut add_args = vec!["add", "--"];
+        add_args.extend(paths.iter().map(|p| p.as_str()));
+        run_git(workspace_path, &add_args).map_err(|e| e.context("Failed to stage files"))?;
+
+        let mut commit_args = vec!["--no-edit", &fixup_arg, "--"];
+        commit_args.extend(paths.iter().map(|p| p.as_str()));
+        run_git_commit(workspace_path, &commit_args, signing)
+            .map_err(|e| e.context("Failed to create fixup commit"))?;
+    }
+
+    run_git(workspace_path, &["rev-parse", "HEAD"]).map(|s| s.trim().to_string())
+}
+
+/// Run `git rebase -i --autosquash <base>` without opening an editor.
+///
+/// On conflict the rebase is aborted so the workspace is left as it was, and the
+/// conflicted files are reported back.
+pub fn git_rebase_autosquash(
+    workspace_path: &str,
+    base: &str,
+) -> Result<GitRebaseResult, GitError> {
+    validate_rev_arg(base, "base revision")?;
+
+    let output = command_for("git")
+        .current_dir(workspace_path)
+        // Accept the generated todo list and any commit messages as-is
+        .env("GIT_SEQUENCE_EDITOR", "true")
+        .env("GIT_EDITOR", "true")
+        .args(["rebase", "-i", "--autosquash", base])
+        .output()
+        .map_err(|e| format!("Failed to execute git rebase: {}", e))?;
+
+    if output.status.success() {
+        return Ok(GitRebaseResult {
+            success: true,
+            message: "Autosquash rebase completed".to_string(),
+            conflicted_files: Vec::new(),
+        });
+    }
+
+    Ok(abort_failed_rebase(
+        workspace_path,
+        &String::from_utf8_lossy(&output.stderr),
+        "Autosquash rebase",
+    ))
+}
+
+/// Undo `commit_hash` with a new commit applying its inverse, or with `no_commit` only
+/// apply the inverse to the index and working tree.
+///
+/// On conflict the revert is aborted so the workspace is left as it was, and the
+/// conflicted files are reported back.
+pub fn git_revert(
+    repo_path: &str,
+    commit_hash: &str,
+    no_commit: bool,
+    signing: Option<&SigningConfig>,
+) -> Result<GitRebaseResult, GitError> {
+    validate_rev_arg(commit_hash, "commit")?;
+
+    let mut args = signing.map(|s| s.git_config_args()).unwrap_or_default();
+    args.extend(["revert".to_string(), "--no-edit".to_string()]);
+    if no_commit {
+        args.push("--no-commit".to_string());
+    } else if signing.is_some() {
+        args.push("-S".to_string());
+    }
+    args.push(commit_hash.to_string());
+
+    let output = command_for("git")
+        .current_dir(repo_path)
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to execute git revert: {}", e))?;
+
+    if output.status.success() {
+        let message = if no_commit {
+            format!("Applied the inverse of {}", commit_hash)
+        } else {
+            format!("Reverted {}", commit_hash)
+        };
+        return Ok(GitRebaseResult {
+            success: true,
+            message,
+            conflicted_files: Vec::new(),
+        });
+    }
+
+    Ok(abort_failed_operation(
+        repo_path,
+        &String::from_utf8_lossy(&output.stderr),
+        "Revert",
+        "REVERT_HEAD",
+        "revert",
+    ))
+}
+
+/// Collect conflicts from a failed rebase and abort it so the workspace is left as it was
+fn abort_failed_rebase(workspace_path: &str, stderr: &str, label: &str) -> GitRebaseResult {
+    abort_failed_operation(workspace_path, stderr, label, "rebase-merge", "rebase")
+}
+
+/// Collect conflicts from a failed `git <command>` and run `git <command> --abort` if
+/// `state_path` under the git dir shows it is still in progress
+fn abort_failed_operation(
+    workspace_path: &str,
+    stderr: &str,
+    label: &str,
+    state_path: &str,
+    command: &str,
+) -> GitRebaseResult {
+    let conflicted_files = get_unmerged_files(workspace_path);
+
+    // Only abort if the operation is actually in progress
+    let in_progress = run_git(workspace_path, &["rev-parse", "--git-path", state_path])
+        .map(|p| Path::new(workspace_path).join(p.trim()).exists())
+        .unwrap_or(false);
+    if in_progress {
+        if let Err(e) = run_git(workspace_path, &[command, "--abort"]) {
+            eprintln!("Warning: Failed to abort {}: {}", command, e);
+        }
+    }
+
+    let message = if conflicted_files.is_empty() {
+        format!("{} failed: {}", label, stderr.trim())
+    } else {
+        format!(
+            "{} stopped on conflicts in {} file(s) and was aborted",
+            label,
+            conflicted_files.len()
+        )
+    };
+
+    GitRebaseResult {
+        success: false,
+        message,
+        conflicted_files,
+    }
+}
+
+// ============================================================================
+// History
+// ============================================================================
+
+/// A commit in a branch history listing
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BranchCommitInfo {
+    pub hash: String,
+    pub short_hash: String,
+    pub author_name: String,
+    pub author_email: String,
+    /// Author date, RFC3339 UTC
+    pub date: String,
+    /// Author date in epoch milliseconds, for ordering
+    pub sort_key: i64,
+    pub message: String,
+    pub parent_hashes: Vec<String>,
+}
+
+/// Filters and pagination for git_log
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GitLogOptions {
+    /// Revision to list history from (defaults to HEAD)
+    pub revision: Option<String>,
+    #[serde(default)]
+    pub skip: usize,
+    pub limit: Option<usize>,
+    /// Author name/email pattern (passed to --author)
+    pub author: Option<String>,
+    /// Lower date bound in any format git understands (e.g. "2024-01-01", "2 weeks ago")
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+/// A page of git_log results
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLogPage {
+    pub commits: Vec<BranchCommitInfo>,
+    pub has_more: bool,
+}
+
+/// Default page size for git_log
+const GIT_LOG_DEFAULT_LIMIT: usize = 100;
+
+/// Field/record separators used in git log pretty formats
+const FIELD_SEP: char = '\x1f';
+const RECORD_SEP: char = '\x1e';
+
+/// Pretty format matching [`parse_branch_commits`]
+const BRANCH_COMMIT_FORMAT: &str = "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%s%x1f%P%x1e";
+
+/// [`BRANCH_COMMIT_FORMAT`] followed by the ref names pointing at the commit
+const GRAPH_COMMIT_FORMAT: &str = "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%s%x1f%P%x1f%D%x1e";
+
+/// Parse one record of [`BRANCH_COMMIT_FORMAT`] output; trailing fields are ignored
+fn parse_branch_commit(fields: &[&str]) -> Option<BranchCommitInfo> {
+    if fields.len() < 7 {
+        return None;
+    }
+    Some(BranchCommitInfo {
+        hash: fields[0].to_string(),
+        short_hash: fields[1].to_string(),
+        author_name: fields[2].to_string(),
+        author_email: fields[3].to_string(),
+        date: timestamps::normalize(fields[4]),
+        sort_key: timestamps::sort_key(fields[4]),
+        message: fields[5].to_string(),
+        parent_hashes: fields[6]
+            .split_whitespace()
+            .map(|p| p.to_string())
+            .collect(),
+    })
+}
+
+/// Fields of each non-empty record of a `git log` output
+fn log_records(output: &str) -> impl Iterator<Item = Vec<&str>> {
+    output
+        .split(RECORD_SEP)
+        .map(|record| record.trim_start_matches('\n'))
+        .filter(|record| !record.is_empty())
+        .map(|record| record.split(FIELD_SEP).collect())
+}
+
+/// Parse `git log` output produced with [`BRANCH_COMMIT_FORMAT`]
+fn parse_branch_commits(output: &str) -> Vec<BranchCommitInfo> {
+    log_records(output)
+        .filter_map(|fields| parse_branch_commit(&fields))
+        .collect()
+}
+
+/// Page through the commit history of a branch (HEAD by default), newest first.
+pub fn git_log(repo_path: &str, options: &GitLogOptions) -> Result<GitLogPage, GitError> {
+    let limit = options.limit.unwrap_or(GIT_LOG_DEFAULT_LIMIT);
+    let revision = options.revision.as_deref().unwrap_or("HEAD");
+    validate_rev_arg(revision, "revision")?;
+
+    let mut args: Vec<String> = vec![
+        "log".to_string(),
+        BRANCH_COMMIT_FORMAT.to_string(),
+        format!("--skip={}", options.skip),
+        // Fetch one extra to know whether another page exists
+        format!("--max-count={}", limit + 1),
+    ];
+    if let Some(author) = options.author.as_deref().filter(|a| !a.is_empty()) {
+        args.push(format!("--author={}", author));
+    }
+    if let Some(since) = options.since.as_deref().filter(|s| !s.is_empty()) {
+        args.push(format!("--since={}", since));
+    }
+    if let Some(until) = options.until.as_deref().filter(|s| !s.is_empty()) {
+        args.push(format!("--until={}", until));
+    }
+    args.push(revision.to_string());
+    args.push("--".to_string());
+
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = run_git(repo_path, &arg_refs).map_err(|e| e.context("git log failed"))?;
+
+    let mut commits = parse_branch_commits(&output);
+    let has_more = commits.len() > limit;
+    commits.truncate(limit);
+
+    Ok(GitLogPage { commits, has_more })
+}
+
+/// A commit in a commit graph and where it is drawn
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphNode {
+    #[serde(flatten)]
+    pub commit: BranchCommitInfo,
+    /// Branch, remote branch and tag names pointing at the commit
+    pub refs: Vec<String>,
+    /// Column of the commit, 0 being the leftmost
+    pub lane: usize,
+    /// Column each edge to a parent runs in, in `parent_hashes` order
+    pub parent_lanes: Vec<usize>,
+}
+
+/// Commit DAG of several refs, newest first in topological order
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Number of columns needed to draw the graph
+    pub lane_count: usize,
+    pub has_more: bool,
+}
+
+/// Default number of commits in a commit graph
+const GRAPH_DEFAULT_LIMIT: usize = 200;
+
+/// Ref names from a `%D` decoration, e.g. "HEAD -> main, origin/main, tag: v1"
+fn parse_decorations(decorations: &str) -> Vec<String> {
+    decorations
+        .split(", ")
+        .map(|name| name.trim())
+        .map(|name| name.strip_prefix("HEAD -> ").unwrap_or(name))
+        .map(|name| name.strip_prefix("tag: ").unwrap_or(name))
+        .filter(|name| !name.is_empty() && *name != "HEAD")
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Assign each commit a lane so that edges run straight down until they reach their
+/// parent. `commits` must be in topological order, children first. Returns each
+/// commit's lane and parent lanes, and the number of lanes used.
+fn assign_lanes(commits: &[BranchCommitInfo]) -> (Vec<(usize, Vec<usize>)>, usize) {
+    // Commit each lane is waiting for, None when the lane is free
+    let mut lanes: Vec<Option<&str>> = Vec::new();
+    let mut lane_count = 0;
+    let mut assigned = Vec::with_capacity(commits.len());
+
+    fn free_lane(lanes: &mut Vec<Option<&str>>) -> usize {
+        match lanes.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                lanes.push(None);
+                lanes.len() - 1
+            }
+        }
+    }
+
+    for commit in commits {
+        let hash = commit.hash.as_str();
+        let lane = match lanes.iter().position(|l| *l == Some(hash)) {
+            Some(lane) => lane,
+            None => free_lane(&mut lanes),
+        };
+        // Other edges into this commit end here
+        for other in lanes.iter_mut() {
+            if *other == Some(hash) {
+                *other = None;
+            }
+        }
+
+        let mut parent_lanes = Vec::with_capacity(commit.parent_hashes.len());
+        for (index, parent) in commit.parent_hashes.iter().enumerate() {
+            let parent = parent.as_str();
+            let parent_lane = if index == 0 {
+                lanes[lane] = Some(parent);
+                lane
+            } else if let Some(existing) = lanes.iter().position(|l| *l == Some(parent)) {
+                existing
+            } else {
+                let free = free_lane(&mut lanes);
+                lanes[free] = Some(parent);
+                free
+            };
+            parent_lanes.push(parent_lane);
+        }
+
+        lane_count = lane_count.max(lanes.len());
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+        assigned.push((lane, parent_lanes));
+    }
+    (assigned, lane_count)
+}
+
+/// Commit graph of `refs`, e.g. the default branch and workspace branches, with lanes
+/// precomputed for drawing. Lists up to `limit` commits reachable from any of the refs.
+pub fn git_get_graph(
+    repo_path: &str,
+    refs: &[String],
+    limit: Option<usize>,
+) -> Result<CommitGraph, GitError> {
+    if refs.is_empty() {
+        return Ok(CommitGraph {
+            nodes: Vec::new(),
+            lane_count: 0,
+            has_more: false,
+        });
+    }
+    for r in refs {
+        validate_rev_arg(r, "ref")?;
+    }
+    let limit = limit.unwrap_or(GRAPH_DEFAULT_LIMIT);
+
+    let mut args: Vec<String> = vec![
+        "log".to_string(),
+        "--topo-order".to_string(),
+        GRAPH_COMMIT_FORMAT.to_string(),
+        format!("--max-count={}", limit + 1),
+    ];
+    args.extend(refs.iter().cloned());
+    args.push("--".to_string());
+
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = run_git(repo_path, &arg_refs).map_err(|e| e.context("git log failed"))?;
+
+    let mut records: Vec<(BranchCommitInfo, Vec<String>)> = log_records(&output)
+        .filter_map(|fields| {
+            let commit = parse_branch_commit(&fields)?;
+            let refs = parse_decorations(fields.get(7).copied().unwrap_or(""));
+            Some((commit, refs))
+        })
+        .collect();
+    let has_more = records.len() > limit;
+    records.truncate(limit);
+
+    let (commits, commit_refs): (Vec<_>, Vec<_>) = records.into_iter().unzip();
+    let (lanes, lane_count) = assign_lanes(&commits);
+    let nodes = commits
+        .into_iter()
+        .zip(commit_refs)
+        .zip(lanes)
+        .map(|((commit, refs), (lane, parent_lanes))| GraphNode {
+            commit,
+            refs,
+            lane,
+            parent_lanes,
+        })
+        .collect();
+
+    Ok(CommitGraph {
+        nodes,
+        lane_count,
+        has_more,
+    })
+}
+
+// ============================================================================
+// Commit Signatures
+// ============================================================================
+
+/// Most commits checked by one verify_commit_signatures call
+const MAX_VERIFIED_COMMITS: usize = 500;
+
+/// Result of checking a commit's signature, from git's `%G?`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Good,
+    Bad,
+    /// Valid signature from a key that is not trusted
+    UnknownValidity,
+    /// Valid signature that has expired
+    Expired,
+    /// Valid signature made by a key that has since expired
+    ExpiredKey,
+    /// Valid signature made by a key that has since been revoked
+    RevokedKey,
+    /// Signed, but the key is not available to check it (or, for SSH, no allowed
+    /// signers file is configured)
+    CannotVerify,
+    Unsigned,
+}
+
+impl SignatureStatus {
+    fn from_code(code: &str) -> SignatureStatus {
+        match code {
+            "G" => SignatureStatus::Good,
+            "B" => SignatureStatus::Bad,
+            "U" => SignatureStatus::UnknownValidity,
+            "X" => SignatureStatus::Expired,
+            "Y" => SignatureStatus::ExpiredKey,
+            "R" => SignatureStatus::RevokedKey,
+            "E" => SignatureStatus::CannotVerify,
+            _ => SignatureStatus::Unsigned,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitSignature {
+    pub hash: String,
+    pub short_hash: String,
+    pub subject: String,
+    pub status: SignatureStatus,
+    /// Signer name as reported by the signing tool
+    pub signer: Option<String>,
+    /// Key id or fingerprint that made the signature
+    pub key: Option<String>,
+}
+
+/// Pretty format matching [`parse_commit_signatures`]
+const SIGNATURE_FORMAT: &str = "--format=%H%x1f%h%x1f%G?%x1f%GS%x1f%GK%x1f%s%x1e";
+
+fn parse_commit_signatures(output: &str) -> Vec<CommitSignature> {
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    output
+        .split(RECORD_SEP)
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start_matches('\n').split(FIELD_SEP).collect();
+            if fields.len() < 6 {
+                return None;
+            }
+            Some(CommitSignature {
+                hash: fields[0].to_string(),
+                short_hash: fields[1].to_string(),
+                subject: fields[5].to_string(),
+                status: SignatureStatus::from_code(fields[2]),
+                signer: non_empty(fields[3]),
+                key: non_empty(fields[4]),
+            })
+        })
+        .collect()
+}
+
+/// Check the signature of each commit in `range` (e.g. "main..HEAD", or a single
+/// revision for its history), newest first
+pub fn verify_commit_signatures(
+    repo_path: &str,
+    range: &str,
+) -> Result<Vec<CommitSignature>, GitError> {
+    validate_rev_arg(range, "commit range")?;
+    let max_count = format!("--max-count={}", MAX_VERIFIED_COMMITS);
+    let output = run_git(
+        repo_path,
+        &["log", &max_count, SIGNATURE_FORMAT, range, "--"],
+    )
+    .map_err(|e| e.context("Failed to verify commit signatures"))?;
+    Ok(parse_commit_signatures(&output))
+}
+
+// ============================================================================
+// Commit Diff
+// ============================================================================
+
+/// Hash of git's empty tree, used as the base when diffing a root commit
+const EMPTY_TREE_HASH: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Diff of a single file with its hunks
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BranchDiffFileDiff {
+    pub path: String,
+    /// Single-letter status: A, M, D, R or C (copied)
+    pub status: String,
+    pub previous_path: Option<String>,
+    pub is_binary: bool,
+    /// Stored with Git LFS; hunks are omitted since they would only show the pointer file
+    pub is_lfs: bool,
+    /// The diff exceeds the file size limit; hunks are omitted
+    #[serde(default)]
+    pub too_large: bool,
+    /// How similar a renamed or copied file is to `previous_path`, in percent
+    #[serde(default)]
+    pub similarity: Option<u8>,
+    pub additions: u32,
+    pub deletions: u32,
+    pub hunks: Vec<JjDiffHunk>,
+}
+
+/// Full diff of one commit against its first parent
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitDiff {
+    pub commit: BranchCommitInfo,
+    pub files: Vec<BranchDiffFileDiff>,
+}
+
+/// Repo setting: similarity in percent a deleted and an added file need to be shown as a
+/// rename, or "off". Unset uses git's default of 50%.
+pub const DIFF_RENAME_THRESHOLD_KEY: &str = "diff_rename_threshold";
+
+/// Repo setting: "true" to also detect files copied from files changed in the same diff
+pub const DIFF_FIND_COPIES_KEY: &str = "diff_find_copies";
+
+/// Repo setting: "true" to ignore whitespace changes when comparing lines
+pub const DIFF_IGNORE_WHITESPACE_KEY: &str = "diff_ignore_whitespace";
+
+const DEFAULT_RENAME_THRESHOLD: u8 = 50;
+
+/// Rename, copy and whitespace handling of per-file diffs
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiffDetection {
+    /// Rename similarity threshold in percent; None turns rename detection off
+    pub find_renames: Option<u8>,
+    pub find_copies: bool,
+    pub ignore_whitespace: bool,
+}
+
+impl Default for DiffDetection {
+    fn default() -> Self {
+        DiffDetection {
+            find_renames: Some(DEFAULT_RENAME_THRESHOLD),
+            find_copies: false,
+            ignore_whitespace: false,
+        }
+    }
+}
+
+impl DiffDetection {
+    /// Options for `git diff` and `git stash show -p`
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.find_copies {
+            // Copy detection implies rename detection
+            let threshold = self.find_renames.unwrap_or(DEFAULT_RENAME_THRESHOLD);
+            args.push(format!("--find-copies={}%", threshold));
+        } else {
+            match self.find_renames {
+                Some(threshold) => args.push(format!("--find-renames={}%", threshold)),
+                None => args.push("--no-renames".to_string()),
+            }
+        }
+        if self.ignore_whitespace {
+            args.push("--ignore-all-space".to_string());
+        }
+        args
+    }
+}
+
+/// Build the diff options from the repo's settings values
+fn resolve_diff_detection(
+    rename_threshold: Option<&str>,
+    find_copies: Option<&str>,
+    ignore_whitespace: Option<&str>,
+) -> Result<DiffDetection, String> {
+    let find_renames = match rename_threshold.map(str::trim).filter(|v| !v.is_empty()) {
+        None => Some(DEFAULT_RENAME_THRESHOLD),
+        Some(value) if value.eq_ignore_ascii_case("off") => None,
+        Some(value) => {
+            let threshold = value.trim_end_matches('%').parse::<u8>().ok();
+            match threshold.filter(|t| *t <= 100) {
+                Some(threshold) => Some(threshold),
+                None => {
+                    return Err(format!(
+                    "Invalid rename threshold '{}': expected a percentage from 0 to 100 or \"off\"",
+                    value
+                ))
+                }
+            }
+        }
+    };
+    Ok(DiffDetection {
+        find_renames,
+        find_copies: find_copies.map(str::trim) == Some("true"),
+        ignore_whitespace: ignore_whitespace.map(str::trim) == Some("true"),
+    })
+}
+
+/// Diff options configured for a repo
+pub fn load_diff_detection(db: &Database, repo_path: &str) -> Result<DiffDetection, String> {
+    let repo = |key| {
+        db.get_repo_setting(repo_path, key)
+            .map_err(|e| e.to_string())
+    };
+    resolve_diff_detection(
+        repo(DIFF_RENAME_THRESHOLD_KEY)?.as_deref(),
+        repo(DIFF_FIND_COPIES_KEY)?.as_deref(),
+        repo(DIFF_IGNORE_WHITESPACE_KEY)?.as_deref(),
+    )
+}
+
+/// Strip the a/ or b/ prefix git adds to diff paths
+fn strip_diff_prefix(path: &str) -> &str {
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+}
+
+/// Split a multi-file `git diff` into per-file diffs
+fn parse_multi_file_diff(diff: &str) -> Result<Vec<BranchDiffFileDiff>, String> {
+    let mut sections: Vec<Vec<&str>> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") || sections.is_empty() {
+            sections.push(Vec::new());
+        }
+        if let Some(section) = sections.last_mut() {
+            section.push(line);
+        }
+    }
+
+    let mut files = Vec::new();
+    for section in sections {
+        let Some(header) = section.first().and_then(|l| l.strip_prefix("diff --git ")) else {
+            continue;
+        };
+
+        // "a/<old> b/<new>"; fall back to splitting at " b/" for paths with spaces
+        let (mut old_path, mut new_path) = match header.find(" b/") {
+            Some(idx) => (
+                strip_diff_prefix(&header[..idx]).to_string(),
+                strip_diff_prefix(&header[idx + 1..]).to_string(),
+            ),
+            None => (header.to_string(), header.to_string()),
+        };
+
+        let mut status = "M";
+        let mut is_binary = false;
+        let mut similarity = None;
+        for line in section.iter().take_while(|l| !l.starts_with("@@")) {
+            if line.starts_with("new file mode") {
+                status = "A";
+            } else if line.starts_with("deleted file mode") {
+                status = "D";
+            } else if let Some(from) = line.strip_prefix("rename from ") {
+                status = "R";
+                old_path = from.to_string();
+            } else if let Some(to) = line.strip_prefix("rename to ") {
+                new_path = to.to_string();
+            } else if let Some(from) = line.strip_prefix("copy from ") {
+                status = "C";
+                old_path = from.to_string();
+            } else if let Some(to) = line.strip_prefix("copy to ") {
+                new_path = to.to_string();
+            } else if let Some(index) = line.strip_prefix("similarity index ") {
+                similarity = index.trim_end_matches('%').parse().ok();
+            } else if line.starts_with("Binary files ") || line.starts_with("GIT binary patch") {
+                is_binary = true;
+            }
+        }
+
+        let section = section.join("\n");
+        let too_large = section.len() as u64 > file_guard::max_file_size();
+        let hunks = if too_large {
+            Vec::new()
+        } else {
+            jj::parse_git_diff_hunks(&section).map_err(|e| e.to_string())?
+        };
+        let additions = hunks
+            .iter()
+            .flat_map(|h| h.lines.iter())
+            .filter(|l| l.starts_with('+'))
+            .count() as u32;
+        let deletions = hunks
+            .iter()
+            .flat_map(|h| h.lines.iter())
+            .filter(|l| l.starts_with('-'))
+            .count() as u32;
+
+        files.push(BranchDiffFileDiff {
+            previous_path: if status == "R" || status == "C" {
+                Some(old_path)
+            } else {
+                None
+            },
+            path: new_path,
+            status: status.to_string(),
+            is_binary,
+            is_lfs: false,
+            too_large,
+            similarity,
+            additions,
+            deletions,
+            hunks,
+        });
+    }
+
+    Ok(files)
+}
+
+/// Metadata of the single commit `commit_hash` resolves to
+pub(crate) fn git_commit_info(
+    repo_path: &str,
+    commit_hash: &str,
+) -> Result<BranchCommitInfo, GitError> {
+    validate_rev_arg(commit_hash, "commit hash")?;
+    let commit_rev = format!("{}^{{commit}}", commit_hash);
+
+    let log_output = run_git(repo_path, &["log", "-1", BRANCH_COMMIT_FORMAT, &commit_rev])
+        .map_err(|e| e.context(&format!("Commit '{}' not found", commit_hash)))?;
+    let commit = parse_branch_commits(&log_output)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Commit '{}' not found", commit_hash))?;
+    Ok(commit)
+}
+
+/// Get the full diff of `commit_hash` against its first parent (or the empty tree for a root commit).
+pub fn git_get_commit_diff(
+    repo_path: &str,
+    commit_hash: &str,
+    detection: &DiffDetection,
+) -> Result<CommitDiff, GitError> {
+    let commit = git_commit_info(repo_path, commit_hash)?;
+
+    let base = commit
+        .parent_hashes
+        .first()
+        .cloned()
+        .unwrap_or_else(|| EMPTY_TREE_HASH.to_string());
+
+    let detection_args = detection.args();
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+    args.extend(detection_args.iter().map(String::as_str));
+    args.extend([base.as_str(), commit.hash.as_str()]);
+    let diff = run_git(repo_path, &args).map_err(|e| e.context("git diff failed"))?;
+
+    let mut files = parse_multi_file_diff(&diff)?;
+    mark_lfs_files(repo_path, &mut files);
+
+    Ok(CommitDiff { files, commit })
+}
+
+// ============================================================================
+// Revision Browsing
+// ============================================================================
+
+/// An entry of a tree at some revision
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RevisionTreeEntry {
+    pub name: String,
+    /// Path from the repository root
+    pub path: String,
+    /// "file", "directory", "symlink" or "submodule"
+    pub kind: String,
+    /// Blob size in bytes; None for directories and submodules
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevisionTree {
+    /// Full hash of the commit the revision resolved to
+    pub commit_id: String,
+    /// Directory that was listed, "" for the root
+    pub path: String,
+    /// Directories first, then files, each sorted by name
+    pub entries: Vec<RevisionTreeEntry>,
+}
+
+/// Resolve a git revision to a full commit hash. In jj repos, jj revisions such as
+/// change ids are accepted too.
+fn resolve_revision(repo_path: &str, revision: &str) -> Result<String, GitError> {
+    validate_rev_arg(revision, "revision")?;
+    let not_found = format!("Revision '{}' not found", revision);
+
+    let commit_rev = format!("{}^{{commit}}", revision);
+    let git_error = match run_git(repo_path, &["rev-parse", "--verify", &commit_rev]) {
+        Ok(hash) => return Ok(hash.trim().to_string()),
+        Err(e) => e,
+    };
+    if !jj::is_jj_workspace(repo_path) {
+        return Err(git_error.context(&not_found));
+    }
+
+    let short_id = jj::jj_get_commit_id(repo_path, revision)
+        .map_err(|e| GitError::NotFound(format!("{}: {}", not_found, e)))?;
+    let commit_rev = format!("{}^{{commit}}", short_id);
+    run_git(repo_path, &["rev-parse", "--verify", &commit_rev])
+        .map(|hash| hash.trim().to_string())
+        .map_err(|e| e.context(&not_found))
+}
+
+/// Reject absolute paths and parent components; "" and "." mean the root
+fn normalize_tree_path(path: &str) -> Result<String, GitError> {
+    let trimmed = path.trim_matches('/');
+    if trimmed == "." || trimmed.is_empty() {
+        return Ok(String::new());
+    }
+    if path.starts_with('/')
+        || path.contains('\0')
+        || trimmed
+            .split('/')
+            .any(|c| c.is_empty() || c == "." || c == "..")
+    {
+        return Err(GitError::Other(format!("Invalid path: {}", path)));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Parse `git ls-tree -z -l` output: `<mode> <type> <object> <size>\t<path>` records
+fn parse_ls_tree(output: &str) -> Vec<(RevisionTreeEntry, String)> {
+    output
+        .split('\0')
+        .filter_map(|record| {
+            let (meta, path) = record.split_once('\t')?;
+            let mut fields = meta.split_whitespace();
+            let mode = fields.next()?;
+            let object_type = fields.next()?;
+            let object = fields.next()?;
+            let size = fields.next().and_then(|s| s.parse::<u64>().ok());
+
+            let kind = match (mode, object_type) {
+                ("120000", _) => "symlink",
+                (_, "tree") => "directory",
+                (_, "commit") => "submodule",
+                _ => "file",
+            };
+            let name = path.rsplit('/').next().unwrap_or(path).to_string();
+            let entry = RevisionTreeEntry {
+                name,
+                path: path.to_string(),
+                kind: kind.to_string(),
+                size,
+            };
+            Some((entry, object.to_string()))
+        })
+        .collect()
+}
+
+/// List a directory of `revision` straight from the object database, without
+/// touching any working copy
+pub fn browse_revision(
+    repo_path: &str,
+    revision: &str,
+    path: &str,
+) -> Result<RevisionTree, GitError> {
+    let commit_id = resolve_revision(repo_path, revision)?;
+    let path = normalize_tree_path(path)?;
+
+    let mut args = vec!["ls-tree", "-z", "-l", "--full-tree", &commit_id];
+    let pathspec = format!("{}/", path);
+    if !path.is_empty() {
+        args.extend(["--", &pathspec]);
+    }
+    let output = run_git(repo_path, &args)?;
+
+    let mut entries: Vec<RevisionTreeEntry> = parse_ls_tree(&output)
+        .into_iter()
+        .map(|(entry, _)| entry)
+        .collect();
+    // Only the root tree can be empty; anything else is a missing directory
+    if entries.is_empty() && !path.is_empty() {
+        return Err(GitError::NotFound(format!(
+            "Directory '{}' not found at {}",
+            path, revision
+        )));
+    }
+    entries.sort_by(|a, b| {
+        (a.kind != "directory")
+            .cmp(&(b.kind != "directory"))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(RevisionTree {
+        commit_id,
+        path,
+        entries,
+    })
+}
+
+/// Read a file as of `revision` from the object database, with the same binary and
+/// size guards as worktree reads
+pub fn read_file_at_revision(
+    repo_path: &str,
+    revision: &str,
+    file_path: &str,
+) -> Result<file_guard::FileContent, GitError> {
+    let commit_id = resolve_revision(repo_path, revision)?;
+    let path = normalize_tree_path(file_path)?;
+    if path.is_empty() {
+        return Err(GitError::Other("A file path is required".to_string()));
+    }
+
+    let output = run_git(
+        repo_path,
+        &[
+            "ls-tree",
+            "-z",
+            "-l",
+            "--full-tree",
+            &commit_id,
+            "--",
+            &path,
+        ],
+    )?;
+    let (entry, object) = parse_ls_tree(&output)
+        .into_iter()
+        .find(|(entry, _)| entry.path == path)
+        .ok_or_else(|| GitError::NotFound(format!("File '{}' not found at {}", path, revision)))?;
+    if entry.kind == "directory" || entry.kind == "submodule" {
+        return Err(GitError::Other(format!("'{}' is a {}", path, entry.kind)));
+    }
+
+    let size = entry.size.unwrap_or(0);
+    let file_path = Path::new(&path);
+    // Size alone decides for large blobs, so they are never loaded
+    if let Some(guarded) = file_guard::check_prefix(file_path, size, &[]) {
+        return Ok(guarded);
+    }
+    let bytes = run_git_bytes(repo_path, &["cat-file", "blob", &object])?;
+    if let Some(guarded) = file_guard::check_prefix(file_path, size, &bytes) {
+        return Ok(guarded);
+    }
+    match String::from_utf8(bytes) {
+        Ok(content) => Ok(file_guard::FileContent::Text { content }),
+        Err(_) => Ok(file_guard::FileContent::BinaryFile { size, mime: None }),
+    }
+}
+
+// ============================================================================
+// Git LFS
+// ============================================================================
+
+/// First line of every LFS pointer file
+const LFS_POINTER_PREFIX: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// A file tracked by LFS in the checked out tree
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLfsFile {
+    pub path: String,
+    pub oid: String,
+    /// False when only the pointer file is checked out
+    pub downloaded: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLfsStatus {
+    /// Whether the git-lfs extension is available
+    pub installed: bool,
+    /// `.gitattributes` patterns using the lfs filter
+    pub tracked_patterns: Vec<String>,
+    /// Empty when git-lfs is not installed
+    pub files: Vec<GitLfsFile>,
+}
+
+/// Parse `git check-attr -z filter` output into the paths whose filter is lfs
+fn parse_lfs_check_attr(output: &str) -> HashSet<String> {
+    let fields: Vec<&str> = output.split('\0').collect();
+    fields
+        .chunks(3)
+        .filter(|entry| entry.len() == 3 && entry[1] == "filter" && entry[2] == "lfs")
+        .map(|entry| entry[0].to_string())
+        .collect()
+}
+
+/// Paths (relative to `repo_path`) that `.gitattributes` routes through the lfs filter
+fn lfs_tracked_paths(repo_path: &str, paths: &[&str]) -> HashSet<String> {
+    if paths.is_empty() {
+        return HashSet::new();
+    }
+    let mut args = vec!["check-attr", "-z", "filter", "--"];
+    args.extend(paths);
+    run_git(repo_path, &args)
+        .map(|output| parse_lfs_check_attr(&output))
+        .unwrap_or_default()
+}
+
+/// Whether `.gitattributes` routes `file_path` through the lfs filter
+fn is_lfs_tracked(repo_path: &str, file_path: &str) -> bool {
+    !lfs_tracked_paths(repo_path, &[file_path]).is_empty()
+}
+
+/// Why the hunks of a worktree file aren't shown, or None to diff it. Artifacts and
+/// blobs can't be shown anyway, and the diff of an LFS file is just its pointer.
+pub(crate) fn skipped_worktree_diff(workspace_path: &str, file_path: &str) -> Option<SkippedDiff> {
+    if is_lfs_tracked(workspace_path, file_path) {
+        return Some(SkippedDiff {
+            is_lfs: true,
+            ..Default::default()
+        });
+    }
+    file_guard::skipped_diff(workspace_path, file_path)
+}
+
+/// Whether a diff only changes an LFS pointer file
+pub(crate) fn is_lfs_pointer_diff(hunks: &[JjDiffHunk]) -> bool {
+    hunks
+        .iter()
+        .flat_map(|h| h.lines.iter())
+        .any(|l| l.get(1..) == Some(LFS_POINTER_PREFIX))
+}
+
+/// Flag LFS files and drop their pointer-file hunks
+fn mark_lfs_files(repo_path: &str, files: &mut [BranchDiffFileDiff]) {
+    let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    let tracked = lfs_tracked_paths(repo_path, &paths);
+
+    for file in files.iter_mut() {
+        if tracked.contains(&file.path) || is_lfs_pointer_diff(&file.hunks) {
+            file.is_lfs = true;
+            file.additions = 0;
+            file.deletions = 0;
+            file.hunks.clear();
+        }
+    }
+}
+
+/// Parse `.gitattributes` lines using the lfs filter into their patterns
+fn parse_lfs_patterns(gitattributes: &str) -> Vec<String> {
+    gitattributes
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?;
+            parts
+                .any(|attr| attr == "filter=lfs")
+                .then(|| pattern.to_string())
+        })
+        .collect()
+}
+
+/// Parse `git lfs ls-files --long` output ("<oid> <*|-> <path>")
+fn parse_lfs_ls_files(output: &str) -> Vec<GitLfsFile> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (oid, rest) = line.split_once(' ')?;
+            let (marker, path) = rest.split_once(' ')?;
+            Some(GitLfsFile {
+                path: path.to_string(),
+                oid: oid.to_string(),
+                downloaded: marker == "*",
+            })
+        })
+        .collect()
+}
+
+fn lfs_installed(repo_path: &str) -> bool {
+    run_git(repo_path, &["lfs", "version"]).is_ok()
+}
+
+/// LFS patterns and files of a repository or worktree, and which files still need a pull
+pub fn git_lfs_status(repo_path: &str) -> Result<GitLfsStatus, GitError> {
+    let gitattributes =
+        fs::read_to_string(Path::new(repo_path).join(".gitattributes")).unwrap_or_default();
+    let installed = lfs_installed(repo_path);
+
+    let files = if installed {
+        let output = run_git(repo_path, &["lfs", "ls-files", "--long"])
+            .map_err(|e| e.context("git lfs ls-files failed"))?;
+        parse_lfs_ls_files(&output)
+    } else {
+        Vec::new()
+    };
+
+    Ok(GitLfsStatus {
+        installed,
+        tracked_patterns: parse_lfs_patterns(&gitattributes),
+        files,
+    })
+}
+
+/// Download LFS objects and replace pointer files in the working copy. Only `paths`
+/// are pulled when given.
+pub async fn git_lfs_pull(repo_path: &str, paths: &[String]) -> Result<String, GitError> {
+    if paths
+        .iter()
+        .any(|p| p.is_empty() || p.starts_with('-') || p.contains('\0'))
+    {
+        return Err(GitError::Other("Invalid file path".to_string()));
+    }
+    if !lfs_installed(repo_path) {
+        return Err(GitError::Other(
+            "Git LFS is not installed. Install git-lfs to download large files.".to_string(),
+        ));
+    }
+
+    let include = format!("--include={}", paths.join(","));
+    let mut args = vec!["lfs", "pull"];
+    if !paths.is_empty() {
+        args.push(&include);
+    }
+    run_git_remote(repo_path, &args, "lfs pull")
+        .await
+        .map_err(|e| e.context("git lfs pull failed"))
+}
+
+// ============================================================================
+// Stash
+// ============================================================================
+
+/// An entry in `git stash list`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitStashEntry {
+    pub index: usize,
+    /// Reflog selector, e.g. `stash@{0}`
+    pub reference: String,
+    pub hash: String,
+    pub message: String,
+    /// Branch the stash was created on, if git recorded one
+    pub branch: Option<String>,
+    /// Creation date, RFC3339 UTC
+    pub date: String,
+}
+
+/// A stash entry with its diff against the commit it was created on
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitStashShow {
+    pub entry: GitStashEntry,
+    pub files: Vec<BranchDiffFileDiff>,
+}
+
+/// Pretty format matching [`parse_stash_list`]
+const STASH_LIST_FORMAT: &str = "--format=%gd%x1f%H%x1f%aI%x1f%gs%x1e";
+
+fn stash_ref(index: usize) -> String {
+    format!("stash@{{{}}}", index)
+}
+
+/// Split a stash reflog subject ("WIP on main: abc123 msg" or "On main: msg") into
+/// branch and message
+fn parse_stash_subject(subject: &str) -> (Option<String>, String) {
+    let rest = subject
+        .strip_prefix("WIP on ")
+        .or_else(|| subject.strip_prefix("On "));
+    match rest.and_then(|r| r.split_once(": ")) {
+        Some((branch, message)) if branch != "(no branch)" => {
+            (Some(branch.to_string()), message.to_string())
+        }
+        Some((_, message)) => (None, message.to_string()),
+        None => (None, subject.to_string()),
+    }
+}
+
+/// Parse `git stash list` output produced with [`STASH_LIST_FORMAT`]
+fn parse_stash_list(output: &str) -> Vec<GitStashEntry> {
+    output
+        .split(RECORD_SEP)
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start_matches('\n').split(FIELD_SEP).collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            let index = fields[0]
+                .strip_prefix("stash@{")?
+                .strip_suffix('}')?
+                .parse()
+                .ok()?;
+            let (branch, message) = parse_stash_subject(fields[3]);
+            Some(GitStashEntry {
+                index,
+                reference: fields[0].to_string(),
+                hash: fields[1].to_string(),
+                date: timestamps::normalize(fields[2]),
+                message,
+                branch,
+            })
+        })
+        .collect()
+}
+
+/// List stashes, most recent first
+pub fn git_stash_list(repo_path: &str) -> Result<Vec<GitStashEntry>, GitError> {
+    let output = run_git(repo_path, &["stash", "list", STASH_LIST_FORMAT])
+        .map_err(|e| e.context("git stash list failed"))?;
+    Ok(parse_stash_list(&output))
+}
+
+/// Stash changes to the given files (all changes if empty)
+pub fn git_stash_push_files(
+    repo_path: &str,
+    file_paths: &[String],
+    message: Option<&str>,
+    include_untracked: bool,
+) -> Result<String, GitError> {
+    let mut args: Vec<&str> = vec!["stash", "push"];
+    if include_untracked {
+        args.push("--include-untracked");
+    }
+    if let Some(message) = message.filter(|m| !m.trim().is_empty()) {
+        args.push("-m");
+        args.push(message);
+    }
+    if !file_paths.is_empty() {
+        args.push("--");
+        args.extend(file_paths.iter().map(|p| p.as_str()));
+    }
+    run_git(repo_path, &args).map_err(|e| e.context("git stash push failed"))
+}
+
+/// Apply a stash and remove it from the stash list
+pub fn git_stash_pop(repo_path: &str, index: usize) -> Result<String, GitError> {
+    run_git(repo_path, &["stash", "pop", &stash_ref(index)])
+        .map_err(|e| e.context("git stash pop failed"))
+}
+
+/// Apply a stash, keeping it in the stash list
+pub fn git_stash_apply(repo_path: &str, index: usize) -> Result<String, GitError> {
+    run_git(repo_path, &["stash", "apply", &stash_ref(index)])
+        .map_err(|e| e.context("git stash apply failed"))
+}
+
+/// Delete a stash without applying it
+pub fn git_stash_drop(repo_path: &str, index: usize) -> Result<String, GitError> {
+    run_git(repo_path, &["stash", "drop", &stash_ref(index)])
+        .map_err(|e| e.context("git stash drop failed"))
+}
+
+/// Show the changes recorded in a stash as per-file hunks
+pub fn git_stash_show(
+    repo_path: &str,
+    index: usize,
+    detection: &DiffDetection,
+) -> Result<GitStashShow, GitError> {
+    let entry = git_stash_list(repo_path)?
+        .into_iter()
+        .find(|e| e.index == index)
+        .ok_or_else(|| format!("Stash {} not found", stash_ref(index)))?;
+
+    let detection_args = detection.args();
+    let mut args = vec!["stash", "show", "-p", "--no-color", "--no-ext-diff"];
+    args.extend(detection_args.iter().map(String::as_str));
+    args.push(&entry.reference);
+    let diff = run_git(repo_path, &args).map_err(|e| e.context("git stash show failed"))?;
+
+    let mut files = parse_multi_file_diff(&diff)?;
+    mark_lfs_files(repo_path, &mut files);
+
+    Ok(GitStashShow { files, entry })
+}
+
+// ============================================================================
+// Plain Git Workspaces
+// ============================================================================
+
+/// Add a worktree at `workspace_path` checking out `branch_name`. With `new_branch`
+/// the branch is created from `source_branch` (or HEAD).
+pub fn git_worktree_add(
+    repo_path: &str,
+    workspace_path: &str,
+    branch_name: &str,
+    new_branch: bool,
+    source_branch: Option<&str>,
+) -> Result<(), GitError> {
+    validate_rev_arg(branch_name, "branch name")?;
+    let mut args = vec!["worktree", "add"];
+    if new_branch {
+        args.extend(["-b", branch_name, workspace_path]);
+        if let Some(source) = source_branch {
+            validate_rev_arg(source, "source branch")?;
+            args.push(source);
+        }
+    } else {
+        args.extend([workspace_path, branch_name]);
+    }
+
+    paths::ensure_git_longpaths(repo_path, workspace_path)?;
+    run_git(repo_path, &args)
+        .map(|_| ())
+        .map_err(|e| e.context("git worktree add failed"))
+}
+
+/// Remove a worktree and its files, pruning stale worktree metadata
+pub fn git_worktree_remove(repo_path: &str, workspace_path: &str) -> Result<(), GitError> {
+    git2_ops::forget(workspace_path);
+
+    // Fails if the directory is already gone or not a worktree; cleanup below still runs
+    let _ = run_git(
+        repo_path,
+        &["worktree", "remove", "--force", workspace_path],
+    );
+
+    if Path::new(workspace_path).exists() {
+        fs::remove_dir_all(workspace_path)
+            .map_err(|e| format!("Failed to remove workspace directory: {}", e))?;
+    }
+
+    run_git(repo_path, &["worktree", "prune"])
+        .map(|_| ())
+        .map_err(|e| e.context("git worktree prune failed"))
+}
+
+/// A throwaway index file, so staging done only to diff or three-way apply leaves the
+/// worktree's own index alone. The file is removed on drop.
+struct TempIndex(PathBuf);
+
+impl TempIndex {
+    fn new() -> Self {
+        TempIndex(std::env::temp_dir().join(format!(
+            "treq-index-{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        )))
+    }
+
+    /// Run git with this index, returning stdout
+    fn run(
+        &self,
+        workspace_path: &str,
+        git_dir: Option<&Path>,
+        args: &[&str],
+    ) -> Result<Vec<u8>, GitError> {
+        let output = git_in(workspace_path, git_dir)
+            .env("GIT_INDEX_FILE", &self.0)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to execute git: {}", e))?;
+        if !output.status.success() {
+            return Err(GitError::from_stderr(&String::from_utf8_lossy(
+                &output.stderr,
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// git for a worktree; jj workspaces are not git worktrees and live inside the main
+/// repo, so for them `git_dir` points git at the repository explicitly
+fn git_in(workspace_path: &str, git_dir: Option<&Path>) -> LimitedCommand {
+    let mut command = command_for("git");
+    command.current_dir(workspace_path);
+    if let Some(git_dir) = git_dir {
+        command
+            .arg(format!("--git-dir={}", git_dir.display()))
+            .arg(format!("--work-tree={}", workspace_path));
+    }
+    command
+}
+
+/// Binary patch of a worktree's uncommitted changes to `paths` (all changes when empty)
+/// against HEAD, including untracked files, with the HEAD commit it applies to. Staged
+/// through a temporary index so the worktree's own index is left alone.
+pub fn git_working_tree_patch(
+    workspace_path: &str,
+    paths: &[String],
+) -> Result<(Vec<u8>, String), GitError> {
+    if paths.iter().any(|p| p.is_empty() || p.contains('\0')) {
+        return Err(GitError::Other("Invalid file path".to_string()));
+    }
+    let head = run_git(workspace_path, &["rev-parse", "HEAD"])?
+        .trim()
+        .to_string();
+
+    let index = TempIndex::new();
+    let with_paths = |args: &[&'static str]| -> Vec<&str> {
+        let mut args: Vec<&str> = args.to_vec();
+        args.push("--");
+        args.extend(paths.iter().map(String::as_str));
+        args
+    };
+    index.run(workspace_path, None, &["read-tree", "HEAD"])?;
+    index.run(workspace_path, None, &with_paths(&["add", "-A"]))?;
+    let patch = index.run(
+        workspace_path,
+        None,
+        &with_paths(&[
+            "diff",
+            "--cached",
+            "--binary",
+            "--no-color",
+            "--no-ext-diff",
+            "HEAD",
+        ]),
+    )?;
+    Ok((patch, head))
+}
+
+/// One side of a porcelain status code; None when that side is unchanged
+fn porcelain_side(code: u8) -> Option<String> {
+    (code != b' ').then(|| (code as char).to_string())
+}
+
+/// Parse `git status --porcelain -z` output into file changes
+fn parse_status_porcelain(output: &str) -> Vec<jj::JjFileChange> {
+    let mut entries = output.split('\0').filter(|e| !e.is_empty());
+    let mut changes = Vec::new();
+
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let (code, path) = entry.split_at(3);
+        let code = code.as_bytes();
+
+        // Renames and copies are followed by the original path
+        let previous_path = if code[0] == b'R' || code[0] == b'C' {
+            entries.next().map(|p| p.to_string())
+        } else {
+            None
+        };
+        let status = match (code[0], code[1]) {
+            (b'?', _) | (b'A', _) => "A",
+            (b'D', _) | (_, b'D') => "D",
+            _ => "M",
+        };
+
+        // Untracked files show "??", which is only a worktree change
+        let index_status = if code[0] == b'?' {
+            None
+        } else {
+            porcelain_side(code[0])
+        };
+
+        changes.push(jj::JjFileChange {
+            path: path.to_string(),
+            status: status.to_string(),
+            previous_path,
+            submodule: None,
+            index_status,
+            worktree_status: porcelain_side(code[1]),
+        });
+    }
+
+    changes
+}
+
+/// List changed files in a worktree, including untracked files
+pub fn git_get_changed_files(workspace_path: &str) -> Result<Vec<jj::JjFileChange>, GitError> {
+    // libgit2 avoids a process spawn per watcher event; the CLI covers what it can't read
+    let mut changes = match git2_ops::changed_files(workspace_path) {
+        Ok(changes) => changes,
+        Err(e) => {
+            log::warn!("{}; falling back to git status", e);
+            let output = run_git(
+                workspace_path,
+                &["status", "--porcelain", "-z", "--untracked-files=all"],
+            )
+            .map_err(|e| e.context("git status failed"))?;
+            parse_status_porcelain(&output)
+        }
+    };
+    // Best-effort: a submodule git can't read shouldn't hide the other changes
+    if let Err(e) = git_submodules::tag_submodule_changes(workspace_path, &mut changes) {
+        log::warn!("Failed to tag submodule changes: {}", e);
+    }
+    Ok(changes)
+}
+
+/// A changed file with its line counts against HEAD
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangedFileStats {
+    #[serde(flatten)]
+    pub change: jj::JjFileChange,
+    pub insertions: u64,
+    pub deletions: u64,
+    /// Line counts are zero for binary files
+    pub is_binary: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangedFilesWithStats {
+    pub files: Vec<ChangedFileStats>,
+    pub total_insertions: u64,
+    pub total_deletions: u64,
+}
+
+/// Parse `git diff --numstat -z` output into path -> (insertions, deletions), with None
+/// for binary files. Renames are keyed by their new path.
+fn parse_numstat(output: &str) -> HashMap<String, Option<(u64, u64)>> {
+    let mut records = output.split('\0');
+    let mut stats = HashMap::new();
+
+    while let Some(record) = records.next() {
+        let mut fields = record.splitn(3, '\t');
+        let (Some(insertions), Some(deletions), Some(path)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        // A rename has an empty path followed by the old and new paths
+        let path = if path.is_empty() {
+            let _old_path = records.next();
+            match records.next() {
+                Some(new_path) => new_path,
+                None => break,
+            }
+        } else {
+            path
+        };
+        let counts = insertions.parse().ok().zip(deletions.parse().ok());
+        stats.insert(path.to_string(), counts);
+    }
+
+    stats
+}
+
+/// Count lines of an untracked file; None for binary files
+fn count_untracked_lines(path: &Path) -> Option<u64> {
+    if let Ok(Some(file_guard::FileContent::BinaryFile { .. })) = file_guard::check_file(path) {
+        return None;
+    }
+    let mut reader = BufReader::new(fs::File::open(path).ok()?);
+    let mut lines = 0;
+    let mut last_byte = b'\n';
+    loop {
+        let buffer = reader.fill_buf().ok()?;
+        if buffer.is_empty() {
+            break;
+        }
+        lines += buffer.iter().filter(|&&b| b == b'\n').count() as u64;
+        last_byte = buffer[buffer.len() - 1];
+        let consumed = buffer.len();
+        reader.consume(consumed);
+    }
+    // A final line without a trailing newline still counts
+    if last_byte != b'\n' {
+        lines += 1;
+    }
+    Some(lines)
+}
+
+/// Changed files of a worktree with insertion/deletion counts for staged, unstaged and
+/// untracked changes, in one call
+pub fn git_get_changed_files_with_stats(
+    workspace_path: &str,
+) -> Result<ChangedFilesWithStats, GitError> {
+    let changes = git_get_changed_files(workspace_path)?;
+
+    // Unborn branches have no HEAD; diff against the empty tree instead
+    let base = if run_git(workspace_path, &["rev-parse", "--verify", "-q", "HEAD"]).is_ok() {
+        "HEAD"
+    } else {
+        EMPTY_TREE_HASH
+    };
+    let numstat = run_git(
+        workspace_path,
+        &[
+            "diff",
+            base,
+            "--numstat",
+            "-z",
+            "--no-color",
+            "--no-ext-diff",
+        ],
+    )
+    .map_err(|e| e.context("git diff --numstat failed"))?;
+    let stats = parse_numstat(&numstat);
+
+    let files: Vec<ChangedFileStats> = changes
+        .into_iter()
+        .map(|change| {
+            let counts = match stats.get(&change.path) {
+                Some(counts) => *counts,
+                // Untracked files are not part of the diff
+                None if change.status == "A" => {
+                    count_untracked_lines(&Path::new(workspace_path).join(&change.path))
+                        .map(|lines| (lines, 0))
+                }
+                None => Some((0, 0)),
+            };
+            let (insertions, deletions) = counts.unwrap_or((0, 0));
+            ChangedFileStats {
+                change,
+                insertions,
+                deletions,
+                is_binary: counts.is_none(),
+            }
+        })
+        .collect();
+
+    Ok(ChangedFilesWithStats {
+        total_insertions: files.iter().map(|f| f.insertions).sum(),
+        total_deletions: files.iter().map(|f| f.deletions).sum(),
+        files,
+    })
+}
+
+/// Diff hunks of a file in a worktree against HEAD; untracked files diff against /dev/null
+pub fn git_get_file_hunks(workspace_path: &str, file_path: &str) -> Result<FileHunks, GitError> {
+    if let Some(skipped) = skipped_worktree_diff(workspace_path, file_path) {
+        return Ok(FileHunks::skipped(skipped));
+    }
+    let diff = file_diff(workspace_path, file_path)?;
+    jj::parse_git_diff_hunks(&diff)
+        .map(FileHunks::parsed)
+        .map_err(|e| GitError::Other(e.to_string()))
+}
+
+/// Header and line counts of a hunk, without its lines
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiffHunkHeader {
+    pub id: String,
+    pub header: String,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Lines in the hunk body, including context
+    pub line_count: usize,
+}
+
+/// Hunk headers of a file, or why it has none
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileHunkHeaders {
+    pub headers: Vec<DiffHunkHeader>,
+    #[serde(flatten)]
+    pub skipped: SkippedDiff,
+}
+
+/// Hunk headers of a file without their bodies, so huge diffs can be listed first and
+/// each hunk fetched with `git_get_hunk_body` when it is shown
+pub fn git_get_file_hunk_headers(
+    workspace_path: &str,
+    file_path: &str,
+) -> Result<FileHunkHeaders, GitError> {
+    if let Some(skipped) = skipped_worktree_diff(workspace_path, file_path) {
+        return Ok(FileHunkHeaders {
+            headers: Vec::new(),
+            skipped,
+        });
+    }
+    let diff = cached_file_diff(workspace_path, file_path)?;
+
+    let mut headers: Vec<DiffHunkHeader> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            headers.push(DiffHunkHeader {
+                id: format!("hunk-{}", headers.len()),
+                header: line.to_string(),
+                additions: 0,
+                deletions: 0,
+                line_count: 0,
+            });
+            continue;
+        }
+        // Same metadata filtering as jj::parse_git_diff_hunks
+        let Some(current) = headers.last_mut() else {
+            continue;
+        };
+        if line.starts_with("diff --git")
+            || line.starts_with("index ")
+            || line.starts_with("--- ")
+            || line.starts_with("+++ ")
+        {
+            continue;
+        }
+        current.line_count += 1;
+        if line.starts_with('+') {
+            current.additions += 1;
+        } else if line.starts_with('-') {
+            current.deletions += 1;
+        }
+    }
+    Ok(FileHunkHeaders {
+        headers,
+        skipped: SkippedDiff::default(),
+    })
+}
+
+/// One hunk of a file, looked up by its header as returned by `git_get_file_hunk_headers`
+pub fn git_get_hunk_body(
+    workspace_path: &str,
+    file_path: &str,
+    hunk_header: &str,
+) -> Result<JjDiffHunk, GitError> {
+    let not_found = || {
+        GitError::NotFound(format!(
+            "Hunk {} no longer exists in {}",
+            hunk_header, file_path
+        ))
+    };
+    if skipped_worktree_diff(workspace_path, file_path).is_some() {
+        return Err(not_found());
+    }
+    let diff = cached_file_diff(workspace_path, file_path)?;
+
+    // Only parse the requested hunk; the rest of a huge diff is skipped
+    let mut index = 0;
+    let mut body: Option<Vec<&str>> = None;
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            if body.is_some() {
+                break;
+            }
+            if line == hunk_header {
+                body = Some(vec![line]);
+            } else {
+                index += 1;
+            }
+        } else if let Some(body) = body.as_mut() {
+            body.push(line);
+        }
+    }
+    let body = body.ok_or_else(not_found)?;
+
+    let mut hunk = jj::parse_git_diff_hunks(&body.join("\n"))
+        .map_err(|e| GitError::Other(e.to_string()))?
+        .into_iter()
+        .next()
+        .ok_or_else(not_found)?;
+    hunk.id = format!("hunk-{}", index);
+    Ok(hunk)
+}
+
+/// What a file's diff against HEAD depends on: HEAD, the index and the file itself
+#[derive(PartialEq)]
+struct FileDiffStamp {
+    workspace_path: String,
+    file_path: String,
+    head: (Option<String>, Option<i64>),
+    file: Option<(SystemTime, u64)>,
+}
+
+impl FileDiffStamp {
+    fn read(workspace_path: &str, file_path: &str) -> Option<Self> {
+        let head = git2_ops::head_state(workspace_path).ok()?;
+        let file = fs::metadata(Path::new(workspace_path).join(file_path))
+            .ok()
+            .and_then(|m| Some((m.modified().ok()?, m.len())));
+        Some(FileDiffStamp {
+            workspace_path: workspace_path.to_string(),
+            file_path: file_path.to_string(),
+            head,
+            file,
+        })
+    }
+}
+
+/// The last file diffed for hunk headers or bodies. A viewer fetches the headers and
+/// then each body of one file, which would otherwise diff the whole file every time.
+static LAST_FILE_DIFF: Mutex<Option<(FileDiffStamp, Arc<String>)>> = Mutex::new(None);
+
+/// `file_diff`, reused while HEAD, the index and the file are unchanged
+fn cached_file_diff(workspace_path: &str, file_path: &str) -> Result<Arc<String>, GitError> {
+    let stamp = FileDiffStamp::read(workspace_path, file_path);
+    if let (Some(stamp), Some((cached, diff))) = (&stamp, &*LAST_FILE_DIFF.lock().unwrap()) {
+        if stamp == cached {
+            return Ok(diff.clone());
+        }
+    }
+    let diff = Arc::new(file_diff(workspace_path, file_path)?);
+    if let Some(stamp) = stamp {
+        *LAST_FILE_DIFF.lock().unwrap() = Some((stamp, diff.clone()));
+    }
+    Ok(diff)
+}
+
+/// Raw diff of a file against HEAD; callers check `skipped_worktree_diff` first
+fn file_diff(workspace_path: &str, file_path: &str) -> Result<String, GitError> {
+    if file_path.is_empty() || file_path.contains('\0') {
+        return Err(GitError::Other("Invalid file path".to_string()));
+    }
+
+    let diff = run_git(
+        workspace_path,
+        &[
+            "diff",
+            "HEAD",
+            "--no-color",
+            "--no-ext-diff",
+            "--",
+            file_path,
+        ],
+    )
+    .map_err(|e| e.context("git diff failed"))?;
+
+    let diff = if diff.trim().is_empty() {
+        // --no-index exits with 1 when the files differ
+        let output = command_for("git")
+            .current_dir(workspace_path)
+            .args([
+                "diff",
+                "--no-index",
+                "--no-color",
+                "--no-ext-diff",
+                "--",
+                "/dev/null",
+                file_path,
+            ])
+            .output()
+            .map_err(|e| format!("Failed to execute git: {}", e))?;
+        String::from_utf8_lossy(&output.stdout).to_string()
+    } else {
+        diff
+    };
+
+    Ok(diff)
+}
+
+/// Revert one hunk of `file_path` in the working tree by reverse-applying it. `patch` is
+/// a hunk as returned by `git_get_file_hunks`; the index is left untouched.
+pub fn git_discard_hunk(
+    workspace_path: &str,
+    file_path: &str,
+    patch: &str,
+) -> Result<(), GitError> {
+    reverse_apply_hunk(workspace_path, file_path, patch)
+}
+
+/// Revert only the selected lines of a hunk in the working tree. `selected` are indices
+/// into the hunk's lines; unselected additions stay and unselected removals stay removed.
+pub fn git_discard_selected_lines(
+    workspace_path: &str,
+    file_path: &str,
+    patch: &str,
+    selected: &[usize],
+) -> Result<(), GitError> {
+    let partial = Hunk::parse(patch)?.select_for_discard(selected)?;
+    reverse_apply_hunk(workspace_path, file_path, &partial.to_text())
+}
+
+/// Start lines and the trailing section heading of a "@@ -a,b +c,d @@ heading" header
+fn parse_hunk_header(header: &str) -> Option<(usize, usize, &str)> {
+    let rest = header.strip_prefix("@@ -")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let start = |range: &str| range.split(',').next()?.parse().ok();
+    Some((start(old)?, start(new)?, section))
+}
+
+fn reverse_apply_hunk(workspace_path: &str, file_path: &str, hunk: &str) -> Result<(), GitError> {
+    if file_path.is_empty() || file_path.contains(['\0', '\n']) {
+        return Err(GitError::Other("Invalid file path".to_string()));
+    }
+    let mut hunk = Hunk::parse(hunk)?;
+    // Hunks from `git_get_file_hunks` lose the "\r" of CRLF lines, which git apply needs
+    if let Ok(content) = fs::read_to_string(Path::new(workspace_path).join(file_path)) {
+        hunk.restore_crlf(&content);
+    }
+    let patch = format!(
+        "--- a/{}\n+++ b/{}\n{}",
+        file_path,
+        file_path,
+        hunk.to_text()
+    );
+
+    let output = command_for("git")
+        .current_dir(workspace_path)
+        .args(["apply", "-R", "--whitespace=nowarn", "-"])
+        .output_with_input(patch.as_bytes())
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(
+            GitError::from_stderr(&String::from_utf8_lossy(&output.stderr))
+                .context("Failed to discard changes"),
+        );
+    }
+    Ok(())
+}
+
+/// Stage everything in a worktree, including untracked and deleted files
+pub fn git_stage_all(workspace_path: &str) -> Result<(), GitError> {
+    run_git(workspace_path, &["add", "-A"]).map_err(|e| e.context("git add failed"))?;
+    Ok(())
+}
+
+/// Stage everything in a worktree and commit it to the checked out branch. The
+/// `pre-commit` and `commit-msg` hooks are skipped; `commit_hooks` runs them beforehand.
+pub fn git_commit_all(
+    workspace_path: &str,
+    message: &str,
+    signing: Option<&SigningConfig>,
+) -> Result<String, GitError> {
+    if message.trim().is_empty() || message.contains('\0') {
+        return Err(GitError::Other("Invalid commit message".to_string()));
+    }
+
+    let branch = run_git(workspace_path, &["symbolic-ref", "--short", "-q", "HEAD"])
+        .map(|b| b.trim().to_string())
+        .map_err(|_| {
+            GitError::DetachedHead(
+                "Git is not checked out to a branch. Please checkout a branch before committing."
+                    .to_string(),
+            )
+        })?;
+
+    git_stage_all(workspace_path)?;
+    run_git_commit(workspace_path, &["-q", "--no-verify", "-m", message], signing)
+        .map_err(|e| e.context("git commit failed"))?;
+
+    Ok(format!("Committed successfully to branch '{}'", branch))
+}
+
+/// Path of the worktree that has `branch` checked out, if any
+fn worktree_for_branch(repo_path: &str, branch: &str) -> Option<String> {
+    let output = run_git(repo_path, &["worktree", "list", "--porcelain"]).ok()?;
+    let branch_ref = format!("refs/heads/{}", branch);
+
+    output.split("\n\n").find_map(|block| {
+        let mut path = None;
+        let mut checked_out = false;
+        for line in block.lines() {
+            if let Some(p) = line.strip_prefix("worktree ") {
+                path = Some(p.to_string());
+            } else if line.strip_prefix("branch ") == Some(branch_ref.as_str()) {
+                checked_out = true;
+            }
+        }
+        path.filter(|_| checked_out)
+    })
+}
+
+/// Merge `workspace_branch` into `target_branch` with a merge commit.
+///
+/// Conflicts are detected up front with `git merge-tree` so nothing is written when the
+/// merge would not be clean. If the target branch is checked out in a worktree the merge
+/// runs there to keep its working copy in sync; otherwise the commit is created directly.
+pub async fn git_merge_branches(
+    workspace_path: &str,
+    workspace_branch: &str,
+    target_branch: &str,
+    message: &str,
+) -> Result<jj::JjMergeResult, GitError> {
+    validate_rev_arg(workspace_branch, "workspace branch name")?;
+    validate_rev_arg(target_branch, "target branch name")?;
+    if message.contains('\0') {
+        return Err(GitError::Other("Invalid commit message".to_string()));
+    }
+    if message.len() > 10000 {
+        return Err(GitError::Other(
+            "Commit message too long (max 10000 characters)".to_string(),
+        ));
+    }
+
+    // Exit code 1 means the merge has conflicts
+    let merge_tree = async_command_for("git")
+        .timeout(async_process::LOCAL_TIMEOUT)
+        .current_dir(workspace_path)
+        .args([
+            "merge-tree",
+            "--write-tree",
+            "--name-only",
+            "--no-messages",
+            target_branch,
+            workspace_branch,
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+    let stdout = String::from_utf8_lossy(&merge_tree.stdout).to_string();
+    let mut lines = stdout.lines();
+    let tree = lines.next().unwrap_or("").trim().to_string();
+
+    match merge_tree.status.code() {
+        Some(0) => {}
+        Some(1) => {
+            let conflicted_files: Vec<String> = lines
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect();
+            return Ok(jj::JjMergeResult {
+                success: false,
+                message: format!(
+                    "Merging '{}' into '{}' has conflicts in {} file(s)",
+                    workspace_branch,
+                    target_branch,
+                    conflicted_files.len()
+                ),
+                has_conflicts: true,
+                conflicted_files,
+                merge_commit_id: None,
+            });
+        }
+        _ => {
+            return Err(
+                GitError::from_stderr(&String::from_utf8_lossy(&merge_tree.stderr))
+                    .context("git merge-tree failed"),
+            )
+        }
+    }
+
+    let merge_commit_id =
+        if let Some(target_worktree) = worktree_for_branch(workspace_path, target_branch) {
+            run_git(
+                &target_worktree,
+                &["merge", "--no-ff", "-q", "-m", message, workspace_branch],
+            )
+            .map_err(|e| e.context("git merge failed"))?;
+            run_git(&target_worktree, &["rev-parse", "HEAD"])?
+        } else {
+            let target_ref = format!("refs/heads/{}", target_branch);
+            let old_target = run_git(workspace_path, &["rev-parse", "--verify", &target_ref])?;
+            let old_target = old_target.trim();
+            let commit = run_git(
+                workspace_path,
+                &[
+                    "commit-tree",
+                    &tree,
+                    "-p",
+                    old_target,
+                    "-p",
+                    workspace_branch,
+                    "-m",
+                    message,
+                ],
+            )
+            .map_err(|e| e.context("git commit-tree failed"))?;
+            let commit = commit.trim().to_string();
+            run_git(
+                workspace_path,
+                &["update-ref", &target_ref, &commit, old_target],
+            )
+            .map_err(|e| e.context(&format!("Failed to update '{}'", target_branch)))?;
+            commit
+        };
+
+    Ok(jj::JjMergeResult {
+        success: true,
+        message: format!("Merged '{}' into '{}'", workspace_branch, target_branch),
+        has_conflicts: false,
+        conflicted_files: Vec::new(),
+        merge_commit_id: Some(merge_commit_id.trim().chars().take(12).collect()),
+    })
+}
+
+/// Rebase the worktree's checked out branch onto `target_branch`, aborting on conflicts
+pub async fn git_rebase_onto(
+    workspace_path: &str,
+    target_branch: &str,
+) -> Result<GitRebaseResult, GitError> {
+    validate_rev_arg(target_branch, "target branch name")?;
+
+    let output = async_command_for("git")
+        .timeout(async_process::LOCAL_TIMEOUT)
+        .current_dir(workspace_path)
+        .args(["rebase", target_branch])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute git rebase: {}", e))?;
+
+    if output.status.success() {
+        return Ok(GitRebaseResult {
+            success: true,
+            message: format!("Rebased onto '{}'", target_branch),
+            conflicted_files: Vec::new(),
+        });
+    }
+
+    Ok(abort_failed_rebase(
+        workspace_path,
+        &String::from_utf8_lossy(&output.stderr),
+        "Rebase",
+    ))
+}
+
+/// A configured remote with its URLs and refspecs
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GitRemote {
+    pub name: String,
+    pub fetch_url: Option<String>,
+    /// `remote.<name>.pushurl`, or the fetch URL when unset
+    pub push_url: Option<String>,
+    pub fetch_refspecs: Vec<String>,
+    pub push_refspecs: Vec<String>,
+}
+
+/// Remotes in configuration order, with URLs and refspecs
+pub fn git_list_remotes_detailed(repo_path: &str) -> Result<Vec<GitRemote>, GitError> {
+    let output = command_for("git")
+        .current_dir(repo_path)
+        .args([
+            "config",
+            "-z",
+            "--get-regexp",
+            r"^remote\..*\.(url|pushurl|fetch|push)$",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+    // Exit code 1 means no remote is configured
+    if !output.status.success() && output.status.code() != Some(1) {
+        return Err(GitError::from_stderr(&String::from_utf8_lossy(
+            &output.stderr,
+        )));
+    }
+    Ok(parse_remote_config(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parse `git config -z --get-regexp` output, where each entry is "key\nvalue\0"
+fn parse_remote_config(output: &str) -> Vec<GitRemote> {
+    let mut remotes: Vec<GitRemote> = Vec::new();
+    let mut push_urls: HashMap<String, String> = HashMap::new();
+
+    for entry in output.split('\0').filter(|e| !e.is_empty()) {
+        let (key, value) = entry.split_once('\n').unwrap_or((entry, ""));
+        // Remote names may contain dots, the variable name never does
+        let Some((name, variable)) = key
+            .strip_prefix("remote.")
+            .and_then(|rest| rest.rsplit_once('.'))
+        else {
+            continue;
+        };
+
+        let index = match remotes.iter().position(|r| r.name == name) {
+            Some(index) => index,
+            None => {
+                remotes.push(GitRemote {
+                    name: name.to_string(),
+                    fetch_url: None,
+                    push_url: None,
+                    fetch_refspecs: Vec::new(),
+                    push_refspecs: Vec::new(),
+                });
+                remotes.len() - 1
+            }
+        };
+        let remote = &mut remotes[index];
+        match variable.to_ascii_lowercase().as_str() {
+            "url" => remote.fetch_url = Some(value.to_string()),
+            "pushurl" => {
+                push_urls.insert(name.to_string(), value.to_string());
+            }
+            "fetch" => remote.fetch_refspecs.push(value.to_string()),
+            "push" => remote.push_refspecs.push(value.to_string()),
+            _ => {}
+        }
+    }
+
+    for remote in &mut remotes {
+        remote.push_url = push_urls
+            .remove(&remote.name)
+            .or_else(|| remote.fetch_url.clone());
+    }
+    remotes
+}
+
+/// One progress update from `git clone --progress`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CloneProgress {
+    /// e.g. "Counting objects", "Receiving objects", "Resolving deltas"
+    pub phase: String,
+    pub percent: Option<u32>,
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+    /// Bytes received so far, only reported while receiving objects
+    pub received_bytes: Option<u64>,
+    pub done: bool,
+}
+
+/// Clone `url` into `dest_path`, passing progress to `on_progress` as git reports it.
+/// `extra_args` go before the URL, e.g. `--branch` or `--depth`.
+pub async fn git_clone(
+    url: &str,
+    dest_path: &str,
+    extra_args: &[String],
+    on_progress: &mut dyn FnMut(CloneProgress),
+) -> Result<(), GitError> {
+    validate_rev_arg(url, "clone URL")?;
+    validate_rev_arg(dest_path, "clone destination")?;
+    let dest = Path::new(dest_path);
+    let parent = dest
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    // git runs in `parent`, where a relative `dest_path` would resolve a second time
+    let name = dest
+        .file_name()
+        .ok_or_else(|| GitError::Other(format!("Invalid clone destination: {}", dest_path)))?;
+
+    // No timeout: large clones take as long as they take, and can be cancelled instead
+    let session = askpass::session("clone");
+    let mut command = async_command_for("git");
+    command
+        .current_dir(parent)
+        .args(["clone", "--progress"])
+        .args(extra_args)
+        .args(["--", url])
+        .arg(name);
+    session.apply_async(&mut command);
+    let output = command
+        .output_with_stderr_lines(|line| {
+            if let Some(progress) = parse_git_progress(line) {
+                on_progress(progress);
+            }
+        })
+        .await
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        session.check_rejected(&stderr);
+        return Err(GitError::from_stderr(&stderr).context("git clone failed"));
+    }
+    Ok(())
+}
+
+/// Parse a progress line of clone, fetch or push such as
+/// "Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s".
+/// Other output, like "Cloning into 'repo'...", returns None.
+pub(crate) fn parse_git_progress(line: &str) -> Option<CloneProgress> {
+    let line = line.trim();
+    let line = line.strip_prefix("remote:").unwrap_or(line).trim();
+    let (phase, rest) = line.split_once(": ")?;
+    if phase.is_empty() || !phase.chars().all(|c| c.is_ascii_alphabetic() || c == ' ') {
+        return None;
+    }
+    let rest = rest.trim();
+    let done = rest.ends_with(", done.") || rest.ends_with("done.");
+
+    let mut percent = None;
+    let mut current = None;
+    let mut total = None;
+    if let Some((value, _)) = rest.split_once('%') {
+        percent = value.trim().parse::<u32>().ok();
+        if let Some((counts, _)) = rest
+            .split_once('(')
+            .and_then(|(_, after)| after.split_once(')'))
+        {
+            if let Some((cur, tot)) = counts.split_once('/') {
+                current = cur.trim().parse().ok();
+                total = tot.trim().parse().ok();
+            }
+        }
+    } else {
+        // "Enumerating objects: 1234, done." reports only a count
+        current = rest
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .and_then(|n| n.parse().ok());
+    }
+    if percent.is_none() && current.is_none() {
+        return None;
+    }
+
+    let received_bytes = rest
+        .split_once("), ")
+        .map(|(_, after)| after.split('|').next().unwrap_or(after))
+        .and_then(parse_byte_size);
+
+    Some(CloneProgress {
+        phase: phase.to_string(),
+        percent,
+        current,
+        total,
+        received_bytes,
+        done,
+    })
+}
+
+/// Parse git's human-readable sizes, e.g. "1.20 MiB" or "512 bytes"
+fn parse_byte_size(text: &str) -> Option<u64> {
+    let text = text.trim().trim_end_matches(", done.");
+    let (number, unit) = text.split_once(' ')?;
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit.trim() {
+        "bytes" | "byte" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * multiplier) as u64)
+}
+
+/// Fetch all remotes. Worktrees share the repository's refs, so one fetch covers them all.
+pub async fn git_fetch_all(repo_path: &str) -> Result<String, GitError> {
+    let session = askpass::session("fetch");
+    let mut command = async_command_for("git");
+    command
+        .timeout(async_process::REMOTE_TIMEOUT)
+        .current_dir(repo_path)
+        .args(["fetch", "--all", "--prune"]);
+    session.apply_async(&mut command);
+    let output = command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        session.check_rejected(&stderr);
+        return Err(GitError::from_stderr(&stderr).context("git fetch failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Ask `remote` for its default branch and point `refs/remotes/<remote>/HEAD` at it.
+/// Returns the branch name, or None when the remote does not advertise one. Never
+/// prompts for credentials.
+pub async fn git_update_remote_head(
+    repo_path: &str,
+    remote: &str,
+) -> Result<Option<String>, GitError> {
+    validate_rev_arg(remote, "remote")?;
+    let session = askpass::background_session("fetch");
+    let mut command = async_command_for("git");
+    command
+        .timeout(async_process::REMOTE_TIMEOUT)
+        .current_dir(repo_path)
+        .args(["remote", "set-head", remote, "--auto"]);
+    session.apply_async(&mut command);
+    let output = command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        session.check_rejected(&stderr);
+        return Err(GitError::from_stderr(&stderr).context("git remote set-head failed"));
+    }
+
+    let head_ref = format!("refs/remotes/{}/HEAD", remote);
+    let prefix = format!("{}/", remote);
+    Ok(run_git(repo_path, &["symbolic-ref", "--short", &head_ref])
+        .ok()
+        .and_then(|head| head.trim().strip_prefix(&prefix).map(str::to_string)))
+}
+
+/// Full commit id a revision resolves to, e.g. the tip of a branch
+pub fn git_resolve_commit(repo_path: &str, revision: &str) -> Result<String, GitError> {
+    resolve_revision(repo_path, revision)
+}
+
+/// Commit HEAD points at. In a colocated jj workspace this is the parent of the
+/// working-copy commit.
+pub fn git_head_commit(workspace_path: &str) -> Result<String, GitError> {
+    run_git(workspace_path, &["rev-parse", "--verify", "HEAD"]).map(|s| s.trim().to_string())
+}
+
+/// Commits `branch_name` is ahead of and behind its upstream, as (ahead, behind).
+/// Branches without an upstream report (0, 0).
+pub fn git_ahead_behind(
+    workspace_path: &str,
+    branch_name: &str,
+) -> Result<(usize, usize), GitError> {
+    validate_rev_arg(branch_name, "branch name")?;
+
+    match git2_ops::ahead_behind(workspace_path, branch_name) {
+        Ok(counts) => return Ok(counts),
+        Err(e) => log::warn!("{}; falling back to git rev-list", e),
+    }
+
+    let upstream = format!("{}@{{upstream}}", branch_name);
+    if run_git(
+        workspace_path,
+        &["rev-parse", "--verify", "--quiet", &upstream],
+    )
+    .is_err()
+    {
+        return Ok((0, 0));
+    }
+
+    let counts = run_git(
+        workspace_path,
+        &[
+            "rev-list",
+            "--left-right",
+            "--count",
+            &format!("{}...{}", branch_name, upstream),
+        ],
+    )?;
+    let mut parts = counts
+        .split_whitespace()
+        .map(|n| n.parse::<usize>().unwrap_or(0));
+    Ok((parts.next().unwrap_or(0), parts.next().unwrap_or(0)))
+}
+
+/// Commits `head` has that `base` lacks and the reverse, as (ahead, behind)
+pub fn git_divergence(repo_path: &str, base: &str, head: &str) -> Result<(usize, usize), GitError> {
+    validate_rev_arg(base, "base")?;
+    validate_rev_arg(head, "head")?;
+    let counts = run_git(
+        repo_path,
+        &[
+            "rev-list",
+            "--left-right",
+            "--count",
+            &format!("{}...{}", head, base),
+            "--",
+        ],
+    )?;
+    let mut parts = counts
+        .split_whitespace()
+        .map(|n| n.parse::<usize>().unwrap_or(0));
+    Ok((parts.next().unwrap_or(0), parts.next().unwrap_or(0)))
+}
+
+/// How complete the local history of a clone is. Divergence counts and `a..b` logs are
+/// only trustworthy when the commits they walk were fetched.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct RepoCapabilities {
+    /// History was cut at a depth (`.git/shallow` exists)
+    pub shallow: bool,
+    /// Objects are fetched on demand from promisor remotes
+    pub partial: bool,
+    pub promisor_remotes: Vec<String>,
+}
+
+/// Remotes marked as promisors in `git config --get-regexp` output
+fn parse_promisor_remotes(config: &str) -> Vec<String> {
+    let mut remotes: Vec<String> = Vec::new();
+    for line in config.lines() {
+        let Some((key, value)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        let remote = if key == "extensions.partialclone" {
+            Some(value.trim())
+        } else if value.trim() == "true" {
+            key.strip_prefix("remote.")
+                .and_then(|k| k.strip_suffix(".promisor"))
+        } else {
+            None
+        };
+        if let Some(remote) = remote.filter(|r| !r.is_empty()) {
+            if !remotes.iter().any(|r| r == remote) {
+                remotes.push(remote.to_string());
+            }
+        }
+    }
+    remotes
+}
+
+/// Whether the repository is a shallow and/or partial clone
+pub fn git_repo_capabilities(repo_path: &str) -> Result<RepoCapabilities, GitError> {
+    let shallow = run_git(repo_path, &["rev-parse", "--is-shallow-repository"])
+        .map_err(|e| e.context("Failed to inspect repository"))?
+        .trim()
+        == "true";
+    // Exits with 1 when nothing matches
+    let config = run_git(
+        repo_path,
+        &[
+            "config",
+            "--get-regexp",
+            r"^(remote\..*\.promisor|extensions\.partialclone)$",
+        ],
+    )
+    .unwrap_or_default();
+    let promisor_remotes = parse_promisor_remotes(&config);
+    Ok(RepoCapabilities {
+        shallow,
+        partial: !promisor_remotes.is_empty(),
+        promisor_remotes,
+    })
+}
+
+/// Whether `a` and `b` share an ancestor in the fetched history
+pub fn git_has_merge_base(repo_path: &str, a: &str, b: &str) -> bool {
+    validate_rev_arg(a, "revision").is_ok()
+        && validate_rev_arg(b, "revision").is_ok()
+        && run_git(repo_path, &["merge-base", a, b, "--"]).is_ok()
+}
+
+/// Fetch `commits` more commits of history from `remote` into a shallow clone. Never
+/// prompts for credentials.
+pub async fn git_deepen(repo_path: &str, remote: &str, commits: usize) -> Result<(), GitError> {
+    validate_rev_arg(remote, "remote")?;
+    let session = askpass::background_session("fetch");
+    let mut command = async_command_for("git");
+    command
+        .timeout(async_process::REMOTE_TIMEOUT)
+        .current_dir(repo_path)
+        .args(["fetch", "--quiet", &format!("--deepen={}", commits), remote]);
+    session.apply_async(&mut command);
+    let output = command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        session.check_rejected(&stderr);
+        return Err(GitError::from_stderr(&stderr).context("git fetch --deepen failed"));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Branch Cleanup
+// ============================================================================
+
+/// Default branch of `remote` from its HEAD symref, falling back to the repository default
+fn remote_default_branch(repo_path: &str, remote: &str) -> String {
+    let head = format!("refs/remotes/{}/HEAD", remote);
+    run_git(repo_path, &["symbolic-ref", "--short", "-q", &head])
+        .ok()
+        .and_then(|r| {
+            r.trim()
+                .strip_prefix(&format!("{}/", remote))
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| jj::get_default_branch(repo_path).unwrap_or_else(|_| "main".into()))
+}
+
+/// Delete a local branch. Refuses the default branch, branches checked out in any
+/// worktree and branches of treq workspaces; unmerged branches need `force`.
+pub fn git_delete_branch(repo_path: &str, branch: &str, force: bool) -> Result<String, GitError> {
+    validate_rev_arg(branch, "branch name")?;
+
+    let default_branch = jj::get_default_branch(repo_path).unwrap_or_else(|_| "main".into());
+    if branch == default_branch {
+        return Err(GitError::Other(format!(
+            "Cannot delete the default branch '{}'",
+            branch
+        )));
+    }
+    // jj workspaces aren't git worktrees, so only treq's registry knows their branches
+    let workspaces = local_db::get_workspaces(repo_path).map_err(GitError::Other)?;
+    if let Some(workspace) = workspaces.iter().find(|w| w.branch_name == branch) {
+        return Err(GitError::Other(format!(
+            "Branch '{}' is used by workspace '{}'",
+            branch, workspace.workspace_name
+        )));
+    }
+    if let Some(path) = worktree_for_branch(repo_path, branch) {
+        return Err(GitError::Other(format!(
+            "Branch '{}' is checked out in {}",
+            branch, path
+        )));
+    }
+
+    let flag = if force { "-D" } else { "-d" };
+    run_git(repo_path, &["branch", flag, branch])
+        .map_err(|e| e.context(&format!("Failed to delete branch '{}'", branch)))
+}
+
+/// Delete a branch on a remote. Refuses the remote's default branch.
+pub async fn git_delete_remote_branch(
+    repo_path: &str,
+    remote: &str,
+    branch: &str,
+) -> Result<String, GitError> {
+    validate_rev_arg(remote, "remote name")?;
+    validate_rev_arg(branch, "branch name")?;
+
+    if branch == remote_default_branch(repo_path, remote) {
+        return Err(GitError::Other(format!(
+            "Cannot delete the default branch '{}' of '{}'",
+            branch, remote
+        )));
+    }
+
+    run_git_remote(repo_path, &["push", remote, "--delete", branch], "push")
+        .await
+        .map_err(|e| {
+            e.context(&format!(
+                "Failed to delete branch '{}' on '{}'",
+                branch, remote
+            ))
+        })
+}
+
+/// Remove remote-tracking branches whose branch no longer exists on `remote`,
+/// returning the pruned refs (e.g. "origin/feature")
+pub async fn git_prune_remote(repo_path: &str, remote: &str) -> Result<Vec<String>, GitError> {
+    validate_rev_arg(remote, "remote name")?;
+
+    let output = run_git_remote(repo_path, &["remote", "prune", remote], "prune")
+        .await
+        .map_err(|e| e.context(&format!("Failed to prune '{}'", remote)))?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("* [pruned] "))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_git_error_classification() {
+        let err = GitError::from_stderr(
+            "error: could not apply abc123... fix\nCONFLICT (content): Merge conflict in a.txt\n",
+        );
+        assert!(matches!(err, GitError::Conflict(_)));
+        assert!(matches!(
+            GitError::from_stderr(
+                "fatal: Authentication failed for 'https://example.com/repo.git/'"
+            ),
+            GitError::AuthRequired(_)
+        ));
+        assert!(matches!(
+            GitError::from_stderr("fatal: unable to access 'https://example.com/': Could not resolve host: example.com"),
+            GitError::NetworkError(_)
+        ));
+        assert!(matches!(
+            GitError::from_stderr("fatal: ambiguous argument 'nope': unknown revision or path not in the working tree."),
+            GitError::NotFound(_)
+        ));
+
+        let err = GitError::from_stderr("fatal: ref HEAD is not a symbolic ref")
+            .context("Failed to push");
+        assert!(matches!(err, GitError::DetachedHead(_)));
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "detached_head",
+                "message": "Failed to push: fatal: ref HEAD is not a symbolic ref"
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_patch_subject_unfolds_continuation_lines() {
+        let content = "From abc Mon Sep 17 00:00:00 2001\nFrom: A <a@example.com>\nSubject: [PATCH 1/2] Fix the\n very long subject\nDate: now\n\nbody";
+        assert_eq!(
+            read_patch_subject(content),
+            "[PATCH 1/2] Fix the very long subject"
+        );
+    }
+
+    #[test]
+    fn test_parse_send_email_results() {
+        let output = "Subject: [PATCH 0/2] cover\nResult: OK\nSubject: [PATCH 1/2] a\nResult: 250 2.0.0 queued\nSubject: [PATCH 2/2] b\nResult: 550 rejected\n";
+        let results = parse_send_email_results(output);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        assert_eq!(results[2], Err("550 rejected".to_string()));
+    }
+
+    #[test]
+    fn test_get_line_authors_ranking() {
+        let output = "\
+1111111111111111111111111111111111111111 1 1 1
+author Alice
+author-mail <alice@example.com>
+author-time 100
+summary Old change
+\tline one
+2222222222222222222222222222222222222222 2 2 1
+author Bob
+author-mail <bob@example.com>
+author-time 300
+summary Bob change
+\tline two
+3333333333333333333333333333333333333333 3 3 1
+author Alice
+author-mail <alice@example.com>
+author-time 200
+summary Newer change
+\tline three
+0000000000000000000000000000000000000000 4 4 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 400
+summary Version of file.txt from file.txt
+\tline four
+";
+        let authors = rank_line_authors(&parse_blame_porcelain(output));
+
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors[0].name, "Alice");
+        assert_eq!(authors[0].line_count, 2);
+        assert_eq!(authors[0].last_commit_time, 200);
+        assert_eq!(authors[0].last_commit_summary, "Newer change");
+        assert_eq!(authors[1].email, "bob@example.com");
+    }
+
+    #[test]
+    fn test_parse_blame_porcelain_reuses_commit_metadata() {
+        let output = "\
+1111111111111111111111111111111111111111 1 1 2
+author Alice
+author-mail <alice@example.com>
+author-time 100
+summary First
+filename a.txt
+\tfirst line
+1111111111111111111111111111111111111111 2 2
+\tsecond line
+";
+        let lines = parse_blame_porcelain(output);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].line_number, 2);
+        assert_eq!(lines[1].author, "Alice");
+        assert_eq!(lines[1].summary, "First");
+        assert_eq!(lines[1].content, "second line");
+    }
+
+    /// A git repo on `main` with a test identity, for tests across modules
+    pub(crate) fn setup_git_repo(temp_dir: &TempDir) -> String {
+        let path = temp_dir.path().to_str().unwrap().to_string();
+        for args in [
+            vec!["init", "-q", "-b", "main"],
+            vec!["config", "user.email", "test@example.com"],
+            vec!["config", "user.name", "Test"],
+        ] {
+            run_git(&path, &args).unwrap();
+        }
+        path
+    }
+
+    pub(crate) fn commit_file(repo: &str, name: &str, content: &str, message: &str) {
+        fs::write(Path::new(repo).join(name), content).unwrap();
+        run_git(repo, &["add", name]).unwrap();
+        run_git(repo, &["commit", "-q", "-m", message]).unwrap();
+    }
+
+    #[test]
+    fn test_commit_fixup_and_autosquash() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "base.txt", "base", "Base");
+        commit_file(&repo, "a.txt", "a", "Add a");
+        commit_file(&repo, "b.txt", "b", "Add b");
+
+        let target = run_git(&repo, &["rev-parse", "HEAD~1"]).unwrap();
+        fs::write(Path::new(&repo).join("a.txt"), "a fixed").unwrap();
+        git_commit_fixup(&repo, target.trim(), &["a.txt".to_string()], None).unwrap();
+
+        let result = git_rebase_autosquash(&repo, "HEAD~3").unwrap();
+        assert!(result.success, "{}", result.message);
+
+        let log = run_git(&repo, &["log", "--format=%s"]).unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            vec!["Add b", "Add a", "Base"]
+        );
+        let content = run_git(&repo, &["show", "HEAD~1:a.txt"]).unwrap();
+        assert_eq!(content, "a fixed");
+    }
+
+    #[test]
+    fn test_add_blame_ignore_rev_is_respected_by_blame() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "one\ntwo\n", "Add a");
+        run_git(&repo, &["config", "user.name", "Formatter"]).unwrap();
+        commit_file(&repo, "a.txt", "ONE\ntwo\n", "Reformat");
+
+        let before = get_line_authors(&repo, "a.txt", 1, 2, &[]).unwrap();
+        assert!(before.iter().any(|a| a.name == "Formatter"));
+
+        let sha = add_blame_ignore_rev(&repo, "HEAD").unwrap();
+        // Adding twice does not duplicate the entry
+        add_blame_ignore_rev(&repo, "HEAD").unwrap();
+        let file = fs::read_to_string(Path::new(&repo).join(BLAME_IGNORE_REVS_FILE)).unwrap();
+        assert_eq!(file.matches(&sha).count(), 1);
+        assert!(file.contains("# Reformat"));
+
+        let after = get_line_authors(&repo, "a.txt", 1, 2, &[]).unwrap();
+        assert!(after.iter().all(|a| a.name == "Test"));
+    }
+
+    #[test]
+    fn test_verify_commit_signatures() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "a", "First");
+        commit_file(&repo, "b.txt", "b", "Second");
+
+        let signatures = verify_commit_signatures(&repo, "HEAD~1..HEAD").unwrap();
+        assert_eq!(signatures.len(), 1);
+        assert_eq!(signatures[0].subject, "Second");
+        assert_eq!(signatures[0].status, SignatureStatus::Unsigned);
+        assert_eq!(signatures[0].signer, None);
+        assert!(verify_commit_signatures(&repo, "--all").is_err());
+
+        let parsed = parse_commit_signatures(
+            "abc\x1fab\x1fG\x1fTest <test@example.com>\x1fSHA256:xyz\x1fSigned\x1e\n",
+        );
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].status, SignatureStatus::Good);
+        assert_eq!(parsed[0].key.as_deref(), Some("SHA256:xyz"));
+    }
+
+    #[test]
+    fn test_git_get_graph_assigns_lanes_to_branches() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "base.txt", "base", "Base");
+        run_git(&repo, &["checkout", "-q", "-b", "feature"]).unwrap();
+        commit_file(&repo, "f.txt", "f", "Feature");
+        run_git(&repo, &["checkout", "-q", "main"]).unwrap();
+        commit_file(&repo, "m.txt", "m", "Main");
+        run_git(&repo, &["tag", "v1"]).unwrap();
+
+        let refs = vec!["main".to_string(), "feature".to_string()];
+        let graph = git_get_graph(&repo, &refs, None).unwrap();
+        assert!(!graph.has_more);
+        assert_eq!(graph.lane_count, 2);
+        assert_eq!(graph.nodes.len(), 3);
+
+        let tips = &graph.nodes[..2];
+        assert_ne!(tips[0].lane, tips[1].lane);
+        let main = tips.iter().find(|n| n.commit.message == "Main").unwrap();
+        assert_eq!(main.refs, vec!["main", "v1"]);
+        let base = &graph.nodes[2];
+        assert_eq!(base.commit.message, "Base");
+        assert_eq!(base.lane, 0);
+        assert!(base.parent_lanes.is_empty());
+        for tip in tips {
+            assert_eq!(tip.parent_lanes, vec![tip.lane]);
+        }
+
+        let graph = git_get_graph(&repo, &refs, Some(1)).unwrap();
+        assert!(graph.has_more);
+        assert!(git_get_graph(&repo, &["--all".to_string()], None).is_err());
+    }
+
+    #[test]
+    fn test_assign_lanes_reuses_lanes_after_merge() {
+        let commit = |hash: &str, parents: &[&str]| BranchCommitInfo {
+            hash: hash.to_string(),
+            short_hash: hash.to_string(),
+            author_name: String::new(),
+            author_email: String::new(),
+            date: String::new(),
+            sort_key: 0,
+            message: String::new(),
+            parent_hashes: parents.iter().map(|p| p.to_string()).collect(),
+        };
+        // m merges b into a; both branch off base
+        let commits = vec![
+            commit("m", &["a", "b"]),
+            commit("a", &["base"]),
+            commit("b", &["base"]),
+            commit("base", &[]),
+        ];
+        let (lanes, lane_count) = assign_lanes(&commits);
+        assert_eq!(
+            lanes,
+            vec![(0, vec![0, 1]), (0, vec![0]), (1, vec![1]), (0, vec![]),]
+        );
+        assert_eq!(lane_count, 2);
+    }
+
+    #[test]
+    fn test_git_update_remote_head_follows_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let upstream = setup_git_repo(&temp_dir);
+        commit_file(&upstream, "a.txt", "a", "First");
+        let clone_dir = TempDir::new().unwrap();
+        let clone = clone_dir.path().to_str().unwrap();
+        run_git(&upstream, &["clone", "-q", &upstream, clone]).unwrap();
+
+        assert_eq!(
+            async_process::block_on(git_update_remote_head(clone, "origin")).unwrap(),
+            Some("main".to_string())
+        );
+        run_git(&upstream, &["branch", "-m", "main", "trunk"]).unwrap();
+        run_git(clone, &["fetch", "-q", "origin"]).unwrap();
+        assert_eq!(
+            async_process::block_on(git_update_remote_head(clone, "origin")).unwrap(),
+            Some("trunk".to_string())
+        );
+        assert!(async_process::block_on(git_update_remote_head(clone, "-x")).is_err());
+    }
+
+    #[test]
+    fn test_repo_capabilities_and_deepen() {
+        let temp_dir = TempDir::new().unwrap();
+        let upstream = setup_git_repo(&temp_dir);
+        commit_file(&upstream, "a.txt", "a", "First");
+        run_git(&upstream, &["checkout", "-q", "-b", "feature"]).unwrap();
+        commit_file(&upstream, "b.txt", "b", "Second");
+        run_git(&upstream, &["checkout", "-q", "main"]).unwrap();
+        commit_file(&upstream, "c.txt", "c", "Third");
+        commit_file(&upstream, "d.txt", "d", "Fourth");
+
+        let clone_dir = TempDir::new().unwrap();
+        let clone = clone_dir.path().to_str().unwrap();
+        let url = format!("file://{}", upstream);
+        run_git(
+            &upstream,
+            &["clone", "-q", "--depth=1", "--no-single-branch", &url, clone],
+        )
+        .unwrap();
+
+        let capabilities = git_repo_capabilities(clone).unwrap();
+        assert!(capabilities.shallow && !capabilities.partial);
+        assert!(!git_has_merge_base(clone, "origin/main", "origin/feature"));
+
+        async_process::block_on(git_deepen(clone, "origin", 2)).unwrap();
+        assert!(git_has_merge_base(clone, "origin/main", "origin/feature"));
+        assert_eq!(
+            git_divergence(clone, "origin/main", "origin/feature").unwrap(),
+            (1, 2)
+        );
+        assert!(!git_repo_capabilities(&upstream).unwrap().shallow);
+
+        assert_eq!(
+            parse_promisor_remotes(
+                "remote.origin.promisor true\nremote.fork.promisor false\nextensions.partialclone origin\n"
+            ),
+            vec!["origin"]
+        );
+    }
+
+    #[test]
+    fn test_git_log_pagination_and_author_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "a", "First");
+        commit_file(&repo, "b.txt", "b", "Second");
+        run_git(&repo, &["config", "user.name", "Other"]).unwrap();
+        commit_file(&repo, "c.txt", "c", "Third");
+
+        let options = GitLogOptions {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = git_log(&repo, &options).unwrap();
+        assert!(page.has_more);
+        assert_eq!(page.commits[0].message, "Third");
+        assert_eq!(page.commits[0].parent_hashes.len(), 1);
+
+        let options = GitLogOptions {
+            skip: 2,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = git_log(&repo, &options).unwrap();
+        assert!(!page.has_more);
+        assert_eq!(page.commits.len(), 1);
+        assert_eq!(page.commits[0].message, "First");
+        assert!(page.commits[0].parent_hashes.is_empty());
+
+        let options = GitLogOptions {
+            author: Some("Other".to_string()),
+            ..Default::default()
+        };
+        let page = git_log(&repo, &options).unwrap();
+        assert_eq!(page.commits.len(), 1);
+        assert_eq!(page.commits[0].author_name, "Other");
+    }
+
+    #[test]
+    fn test_git_get_commit_diff() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "one\ntwo\n", "Add a");
+
+        // Root commit diffs against the empty tree
+        let root = git_get_commit_diff(&repo, "HEAD", &DiffDetection::default()).unwrap();
+        assert_eq!(root.files.len(), 1);
+        assert_eq!(root.files[0].status, "A");
+        assert_eq!(root.files[0].additions, 2);
+
+        run_git(&repo, &["mv", "a.txt", "b.txt"]).unwrap();
+        fs::write(Path::new(&repo).join("b.txt"), "one\ntwo\nthree\n").unwrap();
+        run_git(&repo, &["add", "b.txt"]).unwrap();
+        commit_file(&repo, "c.txt", "c\n", "Rename and add");
+
+        let diff = git_get_commit_diff(&repo, "HEAD", &DiffDetection::default()).unwrap();
+        assert_eq!(diff.commit.message, "Rename and add");
+        let renamed = diff.files.iter().find(|f| f.path == "b.txt").unwrap();
+        assert_eq!(renamed.status, "R");
+        assert_eq!(renamed.previous_path.as_deref(), Some("a.txt"));
+        assert_eq!(renamed.additions, 1);
+        assert!(diff
+            .files
+            .iter()
+            .any(|f| f.path == "c.txt" && f.status == "A"));
+    }
+
+    #[test]
+    fn test_commit_diff_detection_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(
+            &repo,
+            "lib.rs",
+            "fn main() {\n    one();\n    two();\n    three();\n    four();\n}\n",
+            "Add lib",
+        );
+
+        // Moved and mostly rewritten, about 40% similar
+        run_git(&repo, &["mv", "lib.rs", "moved.rs"]).unwrap();
+        commit_file(
+            &repo,
+            "moved.rs",
+            "fn main() {\n    uno();\n    dos();\n    tres();\n    four();\n}\n",
+            "Move lib",
+        );
+
+        let statuses = |detection: &DiffDetection| {
+            let diff = git_get_commit_diff(&repo, "HEAD", detection).unwrap();
+            let mut files: Vec<(String, String, Option<String>, Option<u8>)> = diff
+                .files
+                .into_iter()
+                .map(|f| (f.status, f.path, f.previous_path, f.similarity))
+                .collect();
+            files.sort_by(|a, b| a.1.cmp(&b.1));
+            files
+        };
+
+        let default = statuses(&DiffDetection::default());
+        assert!(default.iter().any(|f| f.0 == "D" && f.1 == "lib.rs"));
+        assert!(default.iter().any(|f| f.0 == "A" && f.1 == "moved.rs"));
+
+        let loose = DiffDetection {
+            find_renames: Some(30),
+            ..Default::default()
+        };
+        let renamed = statuses(&loose);
+        assert_eq!(
+            renamed,
+            vec![(
+                "R".to_string(),
+                "moved.rs".to_string(),
+                Some("lib.rs".to_string()),
+                Some(42)
+            )]
+        );
+
+        // Copies are found from files changed in the same commit
+        let original = "pub fn helper() {}\n";
+        commit_file(&repo, "helper.rs", original, "Add helper");
+        fs::write(Path::new(&repo).join("copy.rs"), original).unwrap();
+        run_git(&repo, &["add", "copy.rs"]).unwrap();
+        commit_file(
+            &repo,
+            "helper.rs",
+            "pub fn helper() {}\n// edited\n",
+            "Copy helper",
+        );
+
+        assert!(statuses(&loose)
+            .iter()
+            .any(|f| f.0 == "A" && f.1 == "copy.rs"));
+        let copies = statuses(&DiffDetection {
+            find_copies: true,
+            ..loose
+        });
+        let copy = copies.iter().find(|f| f.1 == "copy.rs").unwrap();
+        assert_eq!(copy.0, "C");
+        assert_eq!(copy.2.as_deref(), Some("helper.rs"));
+        assert_eq!(copy.3, Some(100));
+
+        assert_eq!(
+            resolve_diff_detection(Some("off"), Some("true"), None).unwrap(),
+            DiffDetection {
+                find_renames: None,
+                find_copies: true,
+                ignore_whitespace: false,
+            }
+        );
+        assert_eq!(
+            resolve_diff_detection(Some(" 75% "), None, Some("true"))
+                .unwrap()
+                .args(),
+            vec!["--find-renames=75%", "--ignore-all-space"]
+        );
+        assert!(resolve_diff_detection(Some("150"), None, None).is_err());
+    }
+
+    #[test]
+    fn test_browse_revision() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        fs::create_dir(Path::new(&repo).join("src")).unwrap();
+        commit_file(&repo, "src/lib.rs", "fn old() {}\n", "Add lib");
+        fs::write(Path::new(&repo).join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        run_git(&repo, &["add", "logo.png"]).unwrap();
+        commit_file(&repo, "src/lib.rs", "fn new() {}\n", "Update lib");
+        fs::write(Path::new(&repo).join("src/lib.rs"), "uncommitted\n").unwrap();
+
+        let root = browse_revision(&repo, "HEAD", "").unwrap();
+        let names: Vec<(&str, &str)> = root
+            .entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.kind.as_str()))
+            .collect();
+        assert_eq!(names, [("src", "directory"), ("logo.png", "file")]);
+
+        let src = browse_revision(&repo, "HEAD~1", "src/").unwrap();
+        assert_eq!(src.entries.len(), 1);
+        assert_eq!(src.entries[0].path, "src/lib.rs");
+        assert_eq!(src.entries[0].size, Some(12));
+        assert!(browse_revision(&repo, "HEAD~1", "missing").is_err());
+        assert!(browse_revision(&repo, "HEAD", "../etc").is_err());
+        assert!(browse_revision(&repo, "--all", "").is_err());
+
+        assert_eq!(
+            read_file_at_revision(&repo, "HEAD~1", "src/lib.rs").unwrap(),
+            file_guard::FileContent::Text {
+                content: "fn old() {}\n".to_string()
+            }
+        );
+        assert_eq!(
+            read_file_at_revision(&repo, "HEAD", "logo.png").unwrap(),
+            file_guard::FileContent::BinaryFile {
+                size: 10,
+                mime: Some("image/png".to_string())
+            }
+        );
+        assert!(read_file_at_revision(&repo, "HEAD~1", "logo.png").is_err());
+        assert!(read_file_at_revision(&repo, "HEAD", "src").is_err());
+    }
+
+    #[test]
+    fn test_parse_numstat() {
+        let stats = parse_numstat(concat!(
+            "3\t1\tsrc/a.rs\0",
+            "-\t-\tlogo.png\0",
+            "1\t2\t\0old.rs\0new.rs\0"
+        ));
+        assert_eq!(stats.get("src/a.rs"), Some(&Some((3, 1))));
+        assert_eq!(stats.get("logo.png"), Some(&None));
+        assert_eq!(stats.get("new.rs"), Some(&Some((1, 2))));
+        assert!(!stats.contains_key("old.rs"));
+    }
+
+    #[test]
+    fn test_changed_files_with_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "one\ntwo\nthree\n", "Add a");
+
+        fs::write(Path::new(&repo).join("a.txt"), "one\n2\nthree\nfour\n").unwrap();
+        fs::write(Path::new(&repo).join("staged.txt"), "x\ny\n").unwrap();
+        run_git(&repo, &["add", "staged.txt"]).unwrap();
+        fs::write(Path::new(&repo).join("untracked.txt"), "a\nb\nno newline").unwrap();
+        fs::write(Path::new(&repo).join("blob.bin"), b"\0\x01\x02").unwrap();
+
+        let result = git_get_changed_files_with_stats(&repo).unwrap();
+        let stats = |path: &str| {
+            let file = result.files.iter().find(|f| f.change.path == path).unwrap();
+            (file.insertions, file.deletions, file.is_binary)
+        };
+        assert_eq!(stats("a.txt"), (2, 1, false));
+        assert_eq!(stats("staged.txt"), (2, 0, false));
+        assert_eq!(stats("untracked.txt"), (3, 0, false));
+        assert_eq!(stats("blob.bin"), (0, 0, true));
+        assert_eq!(result.total_insertions, 7);
+        assert_eq!(result.total_deletions, 1);
+    }
+
+    #[test]
+    fn test_parse_stash_subject() {
+        assert_eq!(
+            parse_stash_subject("On main: save work"),
+            (Some("main".to_string()), "save work".to_string())
+        );
+        assert_eq!(
+            parse_stash_subject("WIP on feature/x: abc1234 Add x"),
+            (Some("feature/x".to_string()), "abc1234 Add x".to_string())
+        );
+        assert_eq!(
+            parse_stash_subject("On (no branch): detached"),
+            (None, "detached".to_string())
+        );
+    }
+
+    #[test]
+    fn test_stash_lifecycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "a\n", "Add a");
+        commit_file(&repo, "b.txt", "b\n", "Add b");
+
+        fs::write(Path::new(&repo).join("a.txt"), "a changed\n").unwrap();
+        git_stash_push_files(&repo, &["a.txt".to_string()], Some("first"), false).unwrap();
+        fs::write(Path::new(&repo).join("b.txt"), "b changed\n").unwrap();
+        git_stash_push_files(&repo, &[], Some("second"), false).unwrap();
+
+        let stashes = git_stash_list(&repo).unwrap();
+        assert_eq!(stashes.len(), 2);
+        assert_eq!(stashes[0].index, 0);
+        assert_eq!(stashes[0].message, "second");
+        assert_eq!(stashes[1].message, "first");
+        assert_eq!(stashes[1].branch.as_deref(), Some("main"));
+
+        let show = git_stash_show(&repo, 1, &DiffDetection::default()).unwrap();
+        assert_eq!(show.files.len(), 1);
+        assert_eq!(show.files[0].path, "a.txt");
+        assert_eq!(show.files[0].additions, 1);
+
+        git_stash_apply(&repo, 1).unwrap();
+        assert_eq!(
+            fs::read_to_string(Path::new(&repo).join("a.txt")).unwrap(),
+            "a changed\n"
+        );
+        assert_eq!(git_stash_list(&repo).unwrap().len(), 2);
+
+        git_stash_drop(&repo, 1).unwrap();
+        git_stash_pop(&repo, 0).unwrap();
+        assert!(git_stash_list(&repo).unwrap().is_empty());
+        assert_eq!(
+            fs::read_to_string(Path::new(&repo).join("b.txt")).unwrap(),
+            "b changed\n"
+        );
+    }
+
+    #[test]
+    fn test_discard_hunk_and_selected_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        commit_file(&repo, "a.txt", &original, "Add a");
+
+        let changed = original
+            .replace("line 2\n", "line 2 changed\n")
+            .replace("line 18\n", "line 18\nline 18.5\n");
+        fs::write(Path::new(&repo).join("a.txt"), &changed).unwrap();
+
+        let hunks = git_get_file_hunks(&repo, "a.txt").unwrap().hunks;
+        assert_eq!(hunks.len(), 2);
+        git_discard_hunk(&repo, "a.txt", &hunks[1].patch).unwrap();
+        let content = fs::read_to_string(Path::new(&repo).join("a.txt")).unwrap();
+        assert_eq!(content, original.replace("line 2\n", "line 2 changed\n"));
+        // The index is untouched
+        assert!(run_git(&repo, &["diff", "--cached"]).unwrap().is_empty());
+
+        // Discard only the added line of the remaining "-line 2 / +line 2 changed" pair
+        let hunk = &git_get_file_hunks(&repo, "a.txt").unwrap().hunks[0];
+        let added = hunk.lines.iter().position(|l| l.starts_with('+')).unwrap();
+        git_discard_selected_lines(&repo, "a.txt", &hunk.patch, &[added]).unwrap();
+        let content = fs::read_to_string(Path::new(&repo).join("a.txt")).unwrap();
+        assert_eq!(content, original.replace("line 2\n", ""));
+
+        let hunk = &git_get_file_hunks(&repo, "a.txt").unwrap().hunks[0];
+        assert!(git_discard_selected_lines(&repo, "a.txt", &hunk.patch, &[0]).is_err());
+    }
+
+    #[test]
+    fn test_discard_selected_lines_keeps_crlf_and_missing_newline() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        run_git(&repo, &["config", "core.autocrlf", "false"]).unwrap();
+        commit_file(&repo, "a.txt", "one\r\ntwo\r\nthree", "Add a");
+
+        fs::write(Path::new(&repo).join("a.txt"), "one\r\n2\r\nthree\r\nfour").unwrap();
+        let hunk = &git_get_file_hunks(&repo, "a.txt").unwrap().hunks[0];
+        // Discard "-two / +2" only, keeping the new last lines
+        let selected: Vec<usize> = hunk
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.starts_with("-two") || l.starts_with("+2"))
+            .map(|(i, _)| i)
+            .collect();
+        git_discard_selected_lines(&repo, "a.txt", &hunk.patch, &selected).unwrap();
+        let content = fs::read_to_string(Path::new(&repo).join("a.txt")).unwrap();
+        assert_eq!(content, "one\r\ntwo\r\nthree\r\nfour");
+
+        let hunk = &git_get_file_hunks(&repo, "a.txt").unwrap().hunks[0];
+        git_discard_hunk(&repo, "a.txt", &hunk.patch).unwrap();
+        let content = fs::read_to_string(Path::new(&repo).join("a.txt")).unwrap();
+        assert_eq!(content, "one\r\ntwo\r\nthree");
+    }
+
+    #[test]
+    fn test_hunk_headers_and_lazy_bodies() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        let original: String = (1..=40).map(|i| format!("line {}\n", i)).collect();
+        commit_file(&repo, "a.txt", &original, "Add a");
+        let changed = original
+            .replace("line 3\n", "line 3 changed\n")
+            .replace("line 30\n", "line 30\nline 30.5\n");
+        fs::write(Path::new(&repo).join("a.txt"), &changed).unwrap();
+
+        let headers = git_get_file_hunk_headers(&repo, "a.txt").unwrap().headers;
+        let hunks = git_get_file_hunks(&repo, "a.txt").unwrap().hunks;
+        assert_eq!(headers.len(), 2);
+        assert_eq!((headers[0].additions, headers[0].deletions), (1, 1));
+        assert_eq!((headers[1].additions, headers[1].deletions), (1, 0));
+
+        for (header, hunk) in headers.iter().zip(&hunks) {
+            assert_eq!(header.header, hunk.header);
+            assert_eq!(header.line_count, hunk.lines.len());
+            let body = git_get_hunk_body(&repo, "a.txt", &header.header).unwrap();
+            assert_eq!(
+                (body.id.as_str(), body.patch.as_str()),
+                (hunk.id.as_str(), hunk.patch.as_str())
+            );
+        }
+
+        let missing = git_get_hunk_body(&repo, "a.txt", "@@ -1,2 +1,2 @@");
+        assert!(matches!(missing, Err(GitError::NotFound(_))));
+
+        // The diff reused between calls is dropped once the file changes
+        fs::write(Path::new(&repo).join("a.txt"), original.replace("line 3\n", "")).unwrap();
+        let headers = git_get_file_hunk_headers(&repo, "a.txt").unwrap().headers;
+        assert_eq!(headers.len(), 1);
+        assert_eq!((headers[0].additions, headers[0].deletions), (0, 1));
+    }
+
+    #[test]
+    fn test_list_remotes_detailed() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        assert!(git_list_remotes_detailed(&repo).unwrap().is_empty());
+
+        run_git(
+            &repo,
+            &["remote", "add", "origin", "https://example.com/o/r.git"],
+        )
+        .unwrap();
+        run_git(
+            &repo,
+            &["remote", "add", "my.fork", "git@example.com:me/r.git"],
+        )
+        .unwrap();
+        run_git(
+            &repo,
+            &[
+                "config",
+                "remote.my.fork.pushurl",
+                "git@example.com:me/r-push.git",
+            ],
+        )
+        .unwrap();
+        run_git(
+            &repo,
+            &[
+                "config",
+                "--add",
+                "remote.my.fork.push",
+                "refs/heads/*:refs/heads/me/*",
+            ],
+        )
+        .unwrap();
+
+        let remotes = git_list_remotes_detailed(&repo).unwrap();
+        assert_eq!(remotes.len(), 2);
+        assert_eq!(remotes[0].name, "origin");
+        assert_eq!(
+            remotes[0].push_url.as_deref(),
+            Some("https://example.com/o/r.git")
+        );
+        assert_eq!(
+            remotes[0].fetch_refspecs,
+            vec!["+refs/heads/*:refs/remotes/origin/*"]
+        );
+        assert_eq!(remotes[1].name, "my.fork");
+        assert_eq!(
+            remotes[1].push_url.as_deref(),
+            Some("git@example.com:me/r-push.git")
+        );
+        assert_eq!(
+            remotes[1].push_refspecs,
+            vec!["refs/heads/*:refs/heads/me/*"]
+        );
+    }
+
+    #[test]
+    fn test_parse_git_progress() {
+        assert_eq!(
+            parse_git_progress("Receiving objects:  45% (450/1000), 1.50 MiB | 2.00 MiB/s"),
+            Some(CloneProgress {
+                phase: "Receiving objects".to_string(),
+                percent: Some(45),
+                current: Some(450),
+                total: Some(1000),
+                received_bytes: Some(1572864),
+                done: false,
+            })
+        );
+        let counted = parse_git_progress("remote: Counting objects: 100% (50/50), done.").unwrap();
+        assert_eq!(counted.phase, "Counting objects");
+        assert_eq!(counted.total, Some(50));
+        assert!(counted.done);
+        let enumerated = parse_git_progress("remote: Enumerating objects: 1234, done.").unwrap();
+        assert_eq!(enumerated.current, Some(1234));
+        assert_eq!(enumerated.percent, None);
+        assert_eq!(parse_git_progress("Cloning into 'repo'..."), None);
+        assert_eq!(
+            parse_git_progress("warning: You appear to have cloned an empty repository."),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_status_porcelain() {
+        let output =
+            " M src/lib.rs\0A  new.rs\0D  old.rs\0R  renamed.rs\0original.rs\0?? notes.txt\0";
+        let changes = parse_status_porcelain(output);
+        let summary: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.status.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src/lib.rs", "M"),
+                ("new.rs", "A"),
+                ("old.rs", "D"),
+                ("renamed.rs", "M"),
+                ("notes.txt", "A"),
+            ]
+        );
+        assert_eq!(changes[3].previous_path.as_deref(), Some("original.rs"));
+
+        let sides: Vec<(Option<&str>, Option<&str>)> = changes
+            .iter()
+            .map(|c| (c.index_status.as_deref(), c.worktree_status.as_deref()))
+            .collect();
+        assert_eq!(
+            sides,
+            vec![
+                (None, Some("M")),
+                (Some("A"), None),
+                (Some("D"), None),
+                (Some("R"), None),
+                (None, Some("?")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_worktree_commit_and_merge() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "a\n", "Add a");
+
+        let workspace = temp_dir.path().join("ws").to_str().unwrap().to_string();
+        git_worktree_add(&repo, &workspace, "feature", true, Some("main")).unwrap();
+
+        fs::write(Path::new(&workspace).join("a.txt"), "a\nmore\n").unwrap();
+        fs::write(Path::new(&workspace).join("b.txt"), "b\n").unwrap();
+        let changes = git_get_changed_files(&workspace).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(git_get_file_hunks(&workspace, "a.txt").unwrap().hunks.len(), 1);
+        assert_eq!(git_get_file_hunks(&workspace, "b.txt").unwrap().hunks.len(), 1);
+
+        let message = git_commit_all(&workspace, "Feature work", None).unwrap();
+        assert!(message.contains("feature"));
+        assert!(git_get_changed_files(&workspace).unwrap().is_empty());
+
+        // main is checked out in the repo, so the merge updates its working copy
+        let result = async_process::block_on(git_merge_branches(
+            &workspace,
+            "feature",
+            "main",
+            "Merge feature",
+        ))
+        .unwrap();
+        assert!(result.success);
+        assert!(result.merge_commit_id.is_some());
+        assert_eq!(
+            fs::read_to_string(Path::new(&repo).join("b.txt")).unwrap(),
+            "b\n"
+        );
+
+        git_worktree_remove(&repo, &workspace).unwrap();
+        assert!(!Path::new(&workspace).exists());
+    }
+
+    #[test]
+    fn test_rebase_onto_aborts_on_conflict() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "a\n", "Add a");
+
+        let workspace = temp_dir.path().join("ws").to_str().unwrap().to_string();
+        git_worktree_add(&repo, &workspace, "feature", true, Some("main")).unwrap();
+        fs::write(Path::new(&workspace).join("b.txt"), "b\n").unwrap();
+        git_commit_all(&workspace, "Add b", None).unwrap();
+
+        commit_file(&repo, "c.txt", "c\n", "Add c");
+        let result = async_process::block_on(git_rebase_onto(&workspace, "main")).unwrap();
+        assert!(result.success);
+        assert!(Path::new(&workspace).join("c.txt").exists());
+        // No upstream configured
+        assert_eq!(git_ahead_behind(&workspace, "feature").unwrap(), (0, 0));
+
+        fs::write(Path::new(&workspace).join("a.txt"), "feature\n").unwrap();
+        git_commit_all(&workspace, "Change a on feature", None).unwrap();
+        commit_file(&repo, "a.txt", "main\n", "Change a on main");
+
+        let result = async_process::block_on(git_rebase_onto(&workspace, "main")).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.conflicted_files, vec!["a.txt"]);
+        assert_eq!(
+            fs::read_to_string(Path::new(&workspace).join("a.txt")).unwrap(),
+            "feature\n"
+        );
+    }
+
+    #[test]
+    fn test_revert_commits_inverse_and_aborts_on_conflict() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "one\n", "Add a");
+        commit_file(&repo, "b.txt", "b\n", "Add b");
+
+        let result = git_revert(&repo, "HEAD", false, None).unwrap();
+        assert!(result.success);
+        assert!(!Path::new(&repo).join("b.txt").exists());
+        let subject = run_git(&repo, &["log", "-1", "--format=%s"]).unwrap();
+        assert_eq!(subject.trim(), "Revert \"Add b\"");
+
+        commit_file(&repo, "a.txt", "two\n", "Change a");
+        commit_file(&repo, "a.txt", "three\n", "Change a again");
+        let head = run_git(&repo, &["rev-parse", "HEAD"]).unwrap();
+        let result = git_revert(&repo, "HEAD~1", true, None).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.conflicted_files, vec!["a.txt"]);
+        assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).unwrap(), head);
+        assert_eq!(
+            fs::read_to_string(Path::new(&repo).join("a.txt")).unwrap(),
+            "three\n"
+        );
+        assert!(git_revert(&repo, "--abort", false, None).is_err());
+    }
+
+    #[test]
+    fn test_export_and_apply_patch_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        let lines: String = (1..=30).map(|i| format!("{}\n", i)).collect();
+        commit_file(&repo, "a.txt", &lines, "Add a");
+        commit_file(&repo, "b.txt", "x\n", "Add b");
+        assert_eq!(git_format_patch(&repo, "HEAD").unwrap().1, 1);
+        assert_eq!(git_format_patch(&repo, "HEAD~1..HEAD").unwrap().1, 1);
+
+        let changed = lines
+            .replace("\n3\n", "\nthree\n")
+            .replace("\n25\n", "\nXXV\n");
+        fs::write(Path::new(&repo).join("a.txt"), &changed).unwrap();
+        fs::write(Path::new(&repo).join("b.txt"), "y\n").unwrap();
+        fs::write(Path::new(&repo).join("c.txt"), "new\n").unwrap();
+        let out_dir = TempDir::new().unwrap();
+        let dest = out_dir.path().join("out").join("changes.diff");
+        let dest = dest.to_str().unwrap();
+        let (patch, _) = git_working_tree_patch(&repo, &["b.txt".to_string()]).unwrap();
+        assert_eq!(
+            write_patch_file(&patch, dest, 0).unwrap().files,
+            vec!["b.txt"]
+        );
+        let (patch, _) = git_working_tree_patch(&repo, &[]).unwrap();
+        let exported = write_patch_file(&patch, dest, 0).unwrap();
+        assert_eq!(exported.files, vec!["a.txt", "b.txt", "c.txt"]);
+
+        run_git(&repo, &["reset", "-q", "--hard"]).unwrap();
+        fs::remove_file(Path::new(&repo).join("c.txt")).unwrap();
+        commit_file(
+            &repo,
+            "a.txt",
+            &lines.replace("\n25\n", "\nXX\n"),
+            "Change a",
+        );
+
+        let check = git_apply_patch_file(&repo, None, dest, false, true).unwrap();
+        assert!(!check.success && check.dry_run);
+        assert_eq!(check.files, vec!["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(
+            check.failed_hunks,
+            vec![PatchHunkFailure {
+                path: "a.txt".to_string(),
+                line: 22,
+                header: Some("@@ -22,7 +22,7 @@".to_string()),
+            }]
+        );
+        assert!(!Path::new(&repo).join("c.txt").exists());
+
+        let applied = git_apply_patch_file(&repo, None, dest, true, false).unwrap();
+        assert!(!applied.success);
+        assert_eq!(applied.conflicted_files, vec!["a.txt"]);
+        assert!(applied.failed_hunks.is_empty());
+        assert!(Path::new(&repo).join("c.txt").exists());
+        assert_eq!(
+            fs::read_to_string(Path::new(&repo).join("b.txt")).unwrap(),
+            "y\n"
+        );
+        assert!(run_git(&repo, &["diff", "--cached", "--name-only"])
+            .unwrap()
+            .trim()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lfs_files_are_flagged_without_pointer_hunks() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(
+            &repo,
+            ".gitattributes",
+            "# assets\n*.bin filter=lfs diff=lfs merge=lfs -text\n*.txt text\n",
+            "Track bin files with LFS",
+        );
+        let pointer = format!(
+            "{}\noid sha256:{}\nsize 1048576\n",
+            LFS_POINTER_PREFIX,
+            "a".repeat(64)
+        );
+        fs::write(Path::new(&repo).join("notes.txt"), "notes\n").unwrap();
+        commit_file(&repo, "asset.bin", &pointer, "Add asset");
+
+        let hash = run_git(&repo, &["rev-parse", "HEAD"]).unwrap();
+        let diff = git_get_commit_diff(&repo, hash.trim(), &DiffDetection::default()).unwrap();
+        let asset = diff.files.iter().find(|f| f.path == "asset.bin").unwrap();
+        assert!(asset.is_lfs);
+        assert!(asset.hunks.is_empty());
+        assert_eq!(asset.additions, 0);
+
+        fs::write(Path::new(&repo).join("asset.bin"), "changed").unwrap();
+        let asset = git_get_file_hunks(&repo, "asset.bin").unwrap();
+        assert!(asset.skipped.is_lfs);
+        assert!(asset.hunks.is_empty());
+        assert!(!git_get_file_hunks(&repo, "notes.txt").unwrap().hunks.is_empty());
+
+        let status = git_lfs_status(&repo).unwrap();
+        assert_eq!(status.tracked_patterns, vec!["*.bin"]);
+    }
+
+    #[test]
+    fn test_parse_lfs_ls_files() {
+        let output = format!(
+            "{} * assets/logo.png\n{} - assets/big file.psd\n",
+            "1".repeat(64),
+            "2".repeat(64)
+        );
+        let files = parse_lfs_ls_files(&output);
+        assert_eq!(files.len(), 2);
+        assert!(files[0].downloaded);
+        assert_eq!(files[1].path, "assets/big file.psd");
+        assert!(!files[1].downloaded);
+    }
+
+    #[test]
+    fn test_delete_branch_protects_default_and_checked_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "a\n", "Add a");
+        run_git(&repo, &["branch", "merged"]).unwrap();
+        run_git(&repo, &["branch", "unmerged"]).unwrap();
+
+        let workspace = temp_dir.path().join("ws").to_str().unwrap().to_string();
+        git_worktree_add(&repo, &workspace, "unmerged", false, None).unwrap();
+        fs::write(Path::new(&workspace).join("b.txt"), "b\n").unwrap();
+        git_commit_all(&workspace, "Add b", None).unwrap();
+
+        assert!(git_delete_branch(&repo, "main", true).is_err());
+        let err = git_delete_branch(&repo, "unmerged", true).unwrap_err();
+        assert!(err.to_string().contains("checked out"));
+
+        git_delete_branch(&repo, "merged", false).unwrap();
+        git_worktree_remove(&repo, &workspace).unwrap();
+        assert!(git_delete_branch(&repo, "unmerged", false).is_err());
+        git_delete_branch(&repo, "unmerged", true).unwrap();
+
+        let branches = run_git(&repo, &["branch", "--format=%(refname:short)"]).unwrap();
+        assert_eq!(branches.trim(), "main");
+    }
+
+    #[test]
+    fn test_delete_branch_protects_workspace_branches() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&temp_dir);
+        commit_file(&repo, "a.txt", "a\n", "Add a");
+        run_git(&repo, &["branch", "feature"]).unwrap();
+        let workspace = temp_dir.path().join(".treq/workspaces/feature");
+        local_db::add_workspace(
+            &repo,
+            "feature".to_string(),
+            workspace.to_string_lossy().to_string(),
+            "feature".to_string(),
+            None,
+        )
+        .unwrap();
+
+        let err = git_delete_branch(&repo, "feature", true).unwrap_err();
+        assert!(err.to_string().contains("used by workspace 'feature'"));
+    }
+
+    #[test]
+    fn test_delete_remote_branch_and_prune() {
+        let temp_dir = TempDir::new().unwrap();
+        let remote = temp_dir.path().join("remote.git");
+        let remote = remote.to_str().unwrap();
+        run_git(
+            temp_dir.path().to_str().unwrap(),
+            &["init", "-q", "--bare", "-b", "main", remote],
+        )
+        .unwrap();
+
+        let clone_dir = TempDir::new().unwrap();
+        let repo = setup_git_repo(&clone_dir);
+        commit_file(&repo, "a.txt", "a\n", "Add a");
+        run_git(&repo, &["remote", "add", "origin", remote]).unwrap();
+        run_git(&repo, &["branch", "feature-a"]).unwrap();
+        run_git(&repo, &["branch", "feature-b"]).unwrap();
+        run_git(
+            &repo,
+            &["push", "-q", "origin", "main", "feature-a", "feature-b"],
+        )
+        .unwrap();
+        run_git(&repo, &["remote", "set-head", "origin", "main"]).unwrap();
+
+        assert!(
+            async_process::block_on(git_delete_remote_branch(&repo, "origin", "main")).is_err()
+        );
+        async_process::block_on(git_delete_remote_branch(&repo, "origin", "feature-a")).unwrap();
+
+        // Delete feature-b behind the clone's back, leaving a stale tracking branch
+        run_git(remote, &["branch", "-D", "feature-b"]).unwrap();
+        assert_eq!(
+            async_process::block_on(git_prune_remote(&repo, "origin")).unwrap(),
+            vec!["origin/feature-b"]
+        );
+        assert!(async_process::block_on(git_prune_remote(&repo, "-x")).is_err());
+    }
+}
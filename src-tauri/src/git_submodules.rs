@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::git_ops::{run_git, GitError};
+use crate::jj::JjFileChange;
+
+/// Checkout state of a submodule, from the prefix `git submodule status` prints
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmoduleState {
+    /// Checked out at the recorded commit
+    Clean,
+    /// Checked out at a different commit than the superproject records
+    Modified,
+    Uninitialized,
+    /// Has merge conflicts
+    Conflict,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitSubmodule {
+    pub path: String,
+    pub url: Option<String>,
+    /// Checked out commit, or the recorded commit when uninitialized
+    pub commit: String,
+    pub state: SubmoduleState,
+    /// `git describe` of the checked out commit, e.g. "heads/main"
+    pub describe: Option<String>,
+}
+
+/// Commits a changed submodule entry moves between
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SubmoduleChange {
+    /// Commit recorded in HEAD; None for a newly added submodule
+    pub old_commit: Option<String>,
+    /// Commit checked out in the working copy; None when removed or uninitialized
+    pub new_commit: Option<String>,
+}
+
+/// Parse `git submodule status` lines ("<prefix><sha> <path> (<describe>)")
+fn parse_submodule_status(output: &str) -> Vec<GitSubmodule> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut chars = line.chars();
+            let state = match chars.next()? {
+                ' ' => SubmoduleState::Clean,
+                '+' => SubmoduleState::Modified,
+                '-' => SubmoduleState::Uninitialized,
+                'U' => SubmoduleState::Conflict,
+                _ => return None,
+            };
+            let (commit, rest) = chars.as_str().split_once(' ')?;
+            let (path, describe) = match rest.rsplit_once(" (") {
+                Some((path, describe)) if describe.ends_with(')') => {
+                    (path, Some(describe.trim_end_matches(')').to_string()))
+                }
+                _ => (rest, None),
+            };
+            Some(GitSubmodule {
+                path: path.to_string(),
+                url: None,
+                commit: commit.to_string(),
+                state,
+                describe,
+            })
+        })
+        .collect()
+}
+
+/// Map submodule paths to URLs from `.gitmodules`
+fn submodule_urls(repo_path: &str) -> HashMap<String, String> {
+    let output = run_git(
+        repo_path,
+        &[
+            "config",
+            "-f",
+            ".gitmodules",
+            "--get-regexp",
+            r"^submodule\..*\.(path|url)$",
+        ],
+    )
+    .unwrap_or_default();
+
+    // Keys are submodule.<name>.path / submodule.<name>.url
+    let mut paths: HashMap<String, String> = HashMap::new();
+    let mut urls: HashMap<String, String> = HashMap::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(key) = key.strip_prefix("submodule.") else {
+            continue;
+        };
+        if let Some(name) = key.strip_suffix(".path") {
+            paths.insert(name.to_string(), value.to_string());
+        } else if let Some(name) = key.strip_suffix(".url") {
+            urls.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    paths
+        .into_iter()
+        .filter_map(|(name, path)| urls.remove(&name).map(|url| (path, url)))
+        .collect()
+}
+
+/// List the submodules of a repository or workspace
+pub fn git_submodule_list(repo_path: &str) -> Result<Vec<GitSubmodule>, GitError> {
+    let output = run_git(repo_path, &["submodule", "status"])
+        .map_err(|e| e.context("git submodule status failed"))?;
+    let mut urls = submodule_urls(repo_path);
+
+    Ok(parse_submodule_status(&output)
+        .into_iter()
+        .map(|mut submodule| {
+            submodule.url = urls.remove(&submodule.path);
+            submodule
+        })
+        .collect())
+}
+
+/// Check out the recorded commit of every submodule, cloning missing ones with `init`
+pub fn git_submodule_update(
+    repo_path: &str,
+    init: bool,
+    recursive: bool,
+) -> Result<String, GitError> {
+    let mut args = vec!["submodule", "update"];
+    if init {
+        args.push("--init");
+    }
+    if recursive {
+        args.push("--recursive");
+    }
+    run_git(repo_path, &args).map_err(|e| e.context("git submodule update failed"))
+}
+
+/// Parse `git ls-tree -z` output into the commits recorded for gitlink entries
+fn parse_recorded_commits(output: &str) -> HashMap<String, String> {
+    output
+        .split('\0')
+        .filter_map(|entry| {
+            let (meta, path) = entry.split_once('\t')?;
+            let mut fields = meta.split_whitespace();
+            let _mode = fields.next()?;
+            if fields.next()? != "commit" {
+                return None;
+            }
+            Some((path.to_string(), fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+/// Attach old/new commits to the entries of `changes` that are submodules
+pub fn tag_submodule_changes(
+    workspace_path: &str,
+    changes: &mut [JjFileChange],
+) -> Result<(), GitError> {
+    // Most repositories have no submodules; skip the extra git calls
+    if !Path::new(workspace_path).join(".gitmodules").exists() {
+        return Ok(());
+    }
+
+    let checked_out: HashMap<String, GitSubmodule> = git_submodule_list(workspace_path)?
+        .into_iter()
+        .map(|s| (s.path.clone(), s))
+        .collect();
+    let changed_paths: Vec<&str> = changes
+        .iter()
+        .filter(|c| checked_out.contains_key(&c.path))
+        .map(|c| c.path.as_str())
+        .collect();
+    if changed_paths.is_empty() {
+        return Ok(());
+    }
+
+    let mut args = vec!["ls-tree", "-z", "HEAD", "--"];
+    args.extend(changed_paths);
+    // Fails without a HEAD commit; every submodule is then new
+    let recorded = run_git(workspace_path, &args)
+        .map(|output| parse_recorded_commits(&output))
+        .unwrap_or_default();
+
+    for change in changes.iter_mut() {
+        let Some(submodule) = checked_out.get(&change.path) else {
+            continue;
+        };
+        change.submodule = Some(SubmoduleChange {
+            old_commit: recorded.get(&change.path).cloned(),
+            new_commit: match (change.status.as_str(), submodule.state) {
+                ("D", _) | (_, SubmoduleState::Uninitialized) => None,
+                _ => Some(submodule.commit.clone()),
+            },
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::git_get_changed_files;
+    use crate::git_ops::tests::{commit_file, setup_git_repo};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_submodule_status() {
+        let output = format!(
+            " {a} libs/clean (v1.0)\n+{b} libs/moved (heads/main)\n-{a} libs/missing\nU{b} libs/conflict\n",
+            a = "a".repeat(40),
+            b = "b".repeat(40)
+        );
+        let submodules = parse_submodule_status(&output);
+        assert_eq!(submodules.len(), 4);
+        assert_eq!(submodules[0].state, SubmoduleState::Clean);
+        assert_eq!(submodules[0].describe.as_deref(), Some("v1.0"));
+        assert_eq!(submodules[1].state, SubmoduleState::Modified);
+        assert_eq!(submodules[2].path, "libs/missing");
+        assert_eq!(submodules[2].describe, None);
+        assert_eq!(submodules[3].state, SubmoduleState::Conflict);
+    }
+
+    #[test]
+    fn test_changed_submodule_is_tagged_with_commits() {
+        let sub_dir = TempDir::new().unwrap();
+        let top_dir = TempDir::new().unwrap();
+        let sub = &setup_git_repo(&sub_dir);
+        let top = &setup_git_repo(&top_dir);
+        commit_file(sub, "first.txt", "first", "First");
+        run_git(
+            top,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                "-q",
+                sub,
+                "libs/sub",
+            ],
+        )
+        .unwrap();
+        run_git(top, &["commit", "-q", "-m", "Add submodule"]).unwrap();
+
+        let checkout = top_dir.path().join("libs/sub");
+        let checkout = checkout.to_str().unwrap();
+        let head = || {
+            run_git(checkout, &["rev-parse", "HEAD"])
+                .unwrap()
+                .trim()
+                .to_string()
+        };
+        let recorded = head();
+        commit_file(sub, "second.txt", "second", "Second");
+        run_git(checkout, &["pull", "-q", "--ff-only"]).unwrap();
+        let moved = head();
+        fs::write(top_dir.path().join("README.md"), "readme\n").unwrap();
+
+        let submodules = git_submodule_list(top).unwrap();
+        assert_eq!(submodules.len(), 1);
+        assert_eq!(submodules[0].state, SubmoduleState::Modified);
+        assert_eq!(submodules[0].url.as_deref(), Some(sub.as_str()));
+
+        let changes = git_get_changed_files(top).unwrap();
+        let submodule = changes.iter().find(|c| c.path == "libs/sub").unwrap();
+        assert_eq!(
+            submodule.submodule,
+            Some(SubmoduleChange {
+                old_commit: Some(recorded),
+                new_commit: Some(moved),
+            })
+        );
+        let readme = changes.iter().find(|c| c.path == "README.md").unwrap();
+        assert_eq!(readme.submodule, None);
+    }
+}
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often held locks are refreshed
+const HEARTBEAT_INTERVAL_SECS: u64 = 5;
+
+/// A lock whose heartbeat is older than this is considered abandoned (crash, kill -9)
+const STALE_AFTER_SECS: i64 = 20;
+
+/// Contents of `.treq/instance.lock`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RepoLockOwner {
+    pub pid: u32,
+    pub instance_id: String,
+    /// Unix timestamp of the owner's last heartbeat
+    pub heartbeat_at: i64,
+}
+
+/// Lock state of a repository as seen by this instance
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoLockStatus {
+    pub held_by_us: bool,
+    /// Another live instance holding the lock, if any
+    pub other_owner: Option<RepoLockOwner>,
+}
+
+static INSTANCE_ID: OnceLock<String> = OnceLock::new();
+static HELD_LOCKS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+static HEARTBEAT_STARTED: OnceLock<()> = OnceLock::new();
+
+/// Random id distinguishing this process from other instances (pids can be reused)
+fn instance_id() -> &'static str {
+    INSTANCE_ID.get_or_init(|| {
+        let mut bytes = [0u8; 8];
+        if getrandom::fill(&mut bytes).is_err() {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or(0);
+            bytes = (nanos as u64).to_le_bytes();
+        }
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    })
+}
+
+fn held_locks() -> &'static Mutex<HashSet<String>> {
+    HELD_LOCKS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn get_lock_path(repo_path: &str) -> PathBuf {
+    Path::new(repo_path).join(".treq").join("instance.lock")
+}
+
+fn our_owner() -> RepoLockOwner {
+    RepoLockOwner {
+        pid: std::process::id(),
+        instance_id: instance_id().to_string(),
+        heartbeat_at: now_secs(),
+    }
+}
+
+fn read_owner(repo_path: &str) -> Option<RepoLockOwner> {
+    let content = fs::read_to_string(get_lock_path(repo_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn is_stale(owner: &RepoLockOwner, now: i64) -> bool {
+    now - owner.heartbeat_at > STALE_AFTER_SECS
+}
+
+fn ensure_treq_dir(repo_path: &str) -> Result<(), String> {
+    fs::create_dir_all(Path::new(repo_path).join(".treq"))
+        .map_err(|e| format!("Failed to create .treq directory: {}", e))
+}
+
+/// Write the lock file via a temp file + rename so readers never see partial content
+fn write_owner(repo_path: &str, owner: &RepoLockOwner) -> Result<(), String> {
+    let lock_path = get_lock_path(repo_path);
+    let content = serde_json::to_string(owner)
+        .map_err(|e| format!("Failed to serialize instance lock: {}", e))?;
+    let tmp_path = lock_path.with_extension(format!("lock.{}", owner.instance_id));
+    fs::write(&tmp_path, content).map_err(|e| format!("Failed to write instance lock: {}", e))?;
+    fs::rename(&tmp_path, &lock_path).map_err(|e| format!("Failed to write instance lock: {}", e))
+}
+
+/// Create the lock file only if none exists, so two instances starting together can't
+/// both win
+fn create_owner(repo_path: &str, owner: &RepoLockOwner) -> Result<bool, String> {
+    let lock_path = get_lock_path(repo_path);
+    ensure_treq_dir(repo_path)?;
+    let content = serde_json::to_string(owner)
+        .map_err(|e| format!("Failed to serialize instance lock: {}", e))?;
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&lock_path)
+    {
+        Ok(mut file) => file
+            .write_all(content.as_bytes())
+            .map(|_| true)
+            .map_err(|e| format!("Failed to write instance lock: {}", e)),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(format!("Failed to create instance lock: {}", e)),
+    }
+}
+
+fn mark_held(repo_path: &str) {
+    held_locks().lock().unwrap().insert(repo_path.to_string());
+    start_heartbeat();
+}
+
+/// Refresh every held lock; locks taken over by another instance are dropped
+fn heartbeat() {
+    let repos: Vec<String> = held_locks().lock().unwrap().iter().cloned().collect();
+    for repo_path in repos {
+        let still_ours = read_owner(&repo_path).is_some_and(|o| o.instance_id == instance_id());
+        if !still_ours {
+            log::warn!(
+                "Instance lock for {} was taken over by another treq instance",
+                repo_path
+            );
+            held_locks().lock().unwrap().remove(&repo_path);
+            continue;
+        }
+        if let Err(e) = write_owner(&repo_path, &our_owner()) {
+            log::warn!("Failed to refresh instance lock for {}: {}", repo_path, e);
+        }
+    }
+}
+
+fn start_heartbeat() {
+    HEARTBEAT_STARTED.get_or_init(|| {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+            heartbeat();
+        });
+    });
+}
+
+/// Try to take the repo's instance lock, reporting the other owner if it's held elsewhere
+pub fn acquire_repo_lock(repo_path: &str) -> Result<RepoLockStatus, String> {
+    if held_locks().lock().unwrap().contains(repo_path) {
+        return Ok(RepoLockStatus {
+            held_by_us: true,
+            other_owner: None,
+        });
+    }
+
+    if !create_owner(repo_path, &our_owner())? {
+        match read_owner(repo_path) {
+            Some(owner) if owner.instance_id != instance_id() && !is_stale(&owner, now_secs()) => {
+                return Ok(RepoLockStatus {
+                    held_by_us: false,
+                    other_owner: Some(owner),
+                });
+            }
+            // Ours from earlier, abandoned or unreadable: replace it
+            _ => write_owner(repo_path, &our_owner())?,
+        }
+    }
+
+    mark_held(repo_path);
+    Ok(RepoLockStatus {
+        held_by_us: true,
+        other_owner: None,
+    })
+}
+
+/// Require the repo's instance lock before migrating its local.db or watching its files
+pub fn ensure_repo_lock(repo_path: &str) -> Result<(), String> {
+    let status = acquire_repo_lock(repo_path)?;
+    match status.other_owner {
+        Some(owner) => Err(format!(
+            "Repository is open in another treq instance (pid {}). Take over the repository lock to use it here.",
+            owner.pid
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Take the lock regardless of its current owner; the other instance stops using the
+/// repo at its next heartbeat
+pub fn takeover_repo_lock(repo_path: &str) -> Result<RepoLockStatus, String> {
+    ensure_treq_dir(repo_path)?;
+    write_owner(repo_path, &our_owner())?;
+    mark_held(repo_path);
+    Ok(RepoLockStatus {
+        held_by_us: true,
+        other_owner: None,
+    })
+}
+
+/// Remove the repo's lock file if this instance still owns it
+fn release_repo_lock(repo_path: &str) {
+    held_locks().lock().unwrap().remove(repo_path);
+    if read_owner(repo_path).is_some_and(|o| o.instance_id == instance_id()) {
+        let _ = fs::remove_file(get_lock_path(repo_path));
+    }
+}
+
+/// Release every lock held by this instance (on exit)
+pub fn release_all() {
+    let repos: Vec<String> = held_locks().lock().unwrap().iter().cloned().collect();
+    for repo_path in repos {
+        release_repo_lock(&repo_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_foreign_owner(repo_path: &str, heartbeat_at: i64) {
+        ensure_treq_dir(repo_path).unwrap();
+        let owner = RepoLockOwner {
+            pid: 1,
+            instance_id: "other-instance".to_string(),
+            heartbeat_at,
+        };
+        write_owner(repo_path, &owner).unwrap();
+    }
+
+    #[test]
+    fn test_acquire_and_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_path = temp_dir.path().to_str().unwrap();
+
+        assert!(acquire_repo_lock(repo_path).unwrap().held_by_us);
+        assert_eq!(
+            read_owner(repo_path).unwrap().instance_id,
+            instance_id().to_string()
+        );
+        assert!(ensure_repo_lock(repo_path).is_ok());
+
+        release_repo_lock(repo_path);
+        assert!(!get_lock_path(repo_path).exists());
+    }
+
+    #[test]
+    fn test_live_foreign_lock_blocks_until_takeover() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_path = temp_dir.path().to_str().unwrap();
+        write_foreign_owner(repo_path, now_secs());
+
+        let status = acquire_repo_lock(repo_path).unwrap();
+        assert!(!status.held_by_us);
+        assert_eq!(status.other_owner.unwrap().instance_id, "other-instance");
+        assert!(ensure_repo_lock(repo_path).is_err());
+
+        assert!(takeover_repo_lock(repo_path).unwrap().held_by_us);
+        assert!(ensure_repo_lock(repo_path).is_ok());
+
+        // Losing the lock to another instance is noticed at the next heartbeat
+        write_foreign_owner(repo_path, now_secs());
+        heartbeat();
+        assert!(ensure_repo_lock(repo_path).is_err());
+    }
+
+    #[test]
+    fn test_stale_foreign_lock_is_replaced() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_path = temp_dir.path().to_str().unwrap();
+        write_foreign_owner(repo_path, now_secs() - STALE_AFTER_SECS - 1);
+
+        assert!(acquire_repo_lock(repo_path).unwrap().held_by_us);
+        assert_eq!(read_owner(repo_path).unwrap().pid, std::process::id());
+    }
+}
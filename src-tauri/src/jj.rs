@@ -2,18 +2,189 @@ use jj_lib::config::{ConfigLayer, ConfigSource, StackedConfig};
 use jj_lib::settings::UserSettings;
 use jj_lib::workspace::Workspace;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::Path;
-use std::process::Command;
+use std::sync::{Mutex, OnceLock};
 
+use crate::askpass;
+use crate::async_process::{self, AsyncCommand};
 use crate::binary_paths;
+use crate::commit_signing::SigningConfig;
+use crate::file_guard::SkippedDiff;
+use crate::git_ops::{self, DiffDetection};
+use crate::git_submodules::{self, SubmoduleChange};
 use crate::local_db;
+use crate::paths;
+use crate::process_limiter::LimitedCommand;
+use crate::timestamps;
 
 /// Helper function to create Command for a binary using cached path
-fn command_for(binary: &str) -> Command {
+fn command_for(binary: &str) -> LimitedCommand {
     let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
-    Command::new(path)
+    LimitedCommand::new(path)
+}
+
+/// Like `command_for`, for commands that can run for minutes
+fn async_command_for(binary: &str, timeout: std::time::Duration) -> AsyncCommand {
+    let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
+    let mut command = AsyncCommand::new(path);
+    command.timeout(timeout);
+    command
+}
+
+/// Repo settings key: `snapshot.max-new-file-size` passed to every jj command run in the
+/// repo, in bytes or with a unit such as "20MiB"; unset uses jj's own setting
+pub const MAX_NEW_FILE_SIZE_KEY: &str = "jj_max_new_file_size";
+
+/// MAX_NEW_FILE_SIZE_KEY per repo path, kept in memory so commands need no db access
+static MAX_NEW_FILE_SIZES: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn max_new_file_sizes() -> &'static Mutex<HashMap<String, String>> {
+    MAX_NEW_FILE_SIZES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Update the snapshot size limit for a repo from its setting
+pub fn set_max_new_file_size(repo_path: &str, value: Option<&str>) {
+    let mut sizes = max_new_file_sizes().lock().unwrap();
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(value) => sizes.insert(repo_path.to_string(), value.to_string()),
+        None => sizes.remove(repo_path),
+    };
+}
+
+/// Load the snapshot size limits of every repo with settings
+pub fn load_max_new_file_sizes(db: &crate::db::Database) {
+    for repo_path in db.list_repo_paths().unwrap_or_default() {
+        let value = db.get_repo_setting(&repo_path, MAX_NEW_FILE_SIZE_KEY).ok().flatten();
+        set_max_new_file_size(&repo_path, value.as_deref());
+    }
+}
+
+/// `--config` arguments for jj commands run in `dir`
+fn repo_config_args(dir: &Path) -> Vec<String> {
+    let dir = dir.to_string_lossy();
+    let repo_path = derive_repo_path_from_workspace(&dir).unwrap_or_else(|| dir.to_string());
+    let sizes = max_new_file_sizes().lock().unwrap();
+    let Some(size) = sizes.get(&repo_path) else {
+        return Vec::new();
+    };
+    // Plain byte counts are integers to jj; anything else is a string such as "20MiB"
+    let value = if size.bytes().all(|b| b.is_ascii_digit()) {
+        size.clone()
+    } else {
+        toml::Value::String(size.clone()).to_string()
+    };
+    vec!["--config".to_string(), format!("snapshot.max-new-file-size={}", value)]
+}
+
+/// jj command running in `dir`, with the repo's config overrides
+pub(crate) fn jj_command(dir: impl AsRef<Path>) -> LimitedCommand {
+    let mut command = command_for("jj");
+    command.current_dir(dir.as_ref()).args(repo_config_args(dir.as_ref()));
+    command
+}
+
+/// Like `jj_command`, for commands that can run for minutes
+fn async_jj_command(dir: impl AsRef<Path>, timeout: std::time::Duration) -> AsyncCommand {
+    let mut command = async_command_for("jj", timeout);
+    command.current_dir(dir.as_ref()).args(repo_config_args(dir.as_ref()));
+    command
+}
+
+/// Parse a jj size such as "11.0MiB" or "512B" into bytes
+fn parse_jj_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = size.split_at(split);
+    let multiplier: u64 = match unit {
+        "B" => 1,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        _ => return None,
+    };
+    Some((number.parse::<f64>().ok()? * multiplier as f64) as u64)
+}
+
+/// Recognize jj refusing to snapshot a new file over `snapshot.max-new-file-size`
+fn parse_snapshot_too_large(stderr: &str) -> Option<JjError> {
+    for line in stderr.lines() {
+        let line = line.trim();
+        // "Caused by: New file big.bin of size ~11.0MiB exceeds snapshot.max-new-file-size (1.0MiB)"
+        if let Some(rest) = line.split("New file ").nth(1) {
+            if let Some((path, rest)) = rest.split_once(" of size ~") {
+                if let Some((size, rest)) = rest.split_once(" exceeds snapshot.max-new-file-size (") {
+                    return Some(JjError::SnapshotFileTooLarge {
+                        path: path.to_string(),
+                        size: parse_jj_size(size)?,
+                        limit: parse_jj_size(rest.trim_end_matches(')'))?,
+                    });
+                }
+            }
+        }
+        // "big.bin: 11.0MiB (11534336 bytes); the maximum size allowed is 1.0MiB (1048576 bytes)"
+        if let Some((file, limit)) = line.split_once("; the maximum size allowed is ") {
+            let bytes = |text: &str| -> Option<u64> {
+                let inner = text.rsplit_once('(')?.1;
+                inner.strip_suffix(" bytes)")?.parse().ok()
+            };
+            let (path, size) = file.rsplit_once(": ")?;
+            return Some(JjError::SnapshotFileTooLarge {
+                path: path.to_string(),
+                size: bytes(size)?,
+                limit: bytes(limit)?,
+            });
+        }
+    }
+    None
+}
+
+/// Error for a failed jj command, classified by its stderr
+pub(crate) fn command_error(stderr: &[u8]) -> JjError {
+    let stderr = String::from_utf8_lossy(stderr);
+    if let Some(error) = parse_snapshot_too_large(&stderr) {
+        return error;
+    }
+    let message = stderr.trim().to_string();
+    let lower = message.to_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+    // Checked most specific first: an immutable-commit error can mention conflicts
+    // in its hints, and a missing bookmark is also a revset that doesn't resolve
+    if matches(&["is immutable", "immutable commit"]) {
+        JjError::ImmutableCommit(message)
+    } else if matches(&[
+        "concurrent modification",
+        "concurrent operation",
+        "failed to lock",
+        "the working copy is stale",
+    ]) {
+        JjError::ConcurrentOperation(message)
+    } else if matches(&["no such bookmark", "no matching bookmarks"])
+        || (lower.contains("bookmark") && lower.contains("doesn't exist"))
+    {
+        JjError::BookmarkNotFound(message)
+    } else if matches(&[
+        "failed to parse revset",
+        "failed to evaluate revset",
+        "revset resolved to more than one revision",
+        "revset resolved to no revisions",
+        "function not found",
+    ]) || (lower.contains("revision") && lower.contains("doesn't exist"))
+    {
+        JjError::RevsetEvaluationError(message)
+    } else if matches(&[
+        // jj git push refusing a conflicted commit or bookmark
+        "since it has conflicts",
+        "is conflicted",
+    ]) {
+        JjError::ConflictDetected(message)
+    } else {
+        JjError::IoError(message)
+    }
 }
 
 /// Convert git remote branch format to jj bookmark format
@@ -43,7 +214,8 @@ pub fn convert_git_branch_to_jj_format_public(branch: &str, repo_path: &str) ->
     convert_git_branch_to_jj_format(branch, repo_path)
 }
 
-/// Error type for jj operations
+/// Error type for jj operations, serialized as `{ kind, message }` (plus the details of
+/// `SnapshotFileTooLarge`) so the frontend can tell failures apart
 #[derive(Debug)]
 pub enum JjError {
     AlreadyInitialized,
@@ -53,6 +225,53 @@ pub enum JjError {
     WorkspaceNotFound(String),
     GitWorkspaceError(String),
     IoError(String),
+    /// A new file over `snapshot.max-new-file-size`, which jj refuses to snapshot
+    SnapshotFileTooLarge { path: String, size: u64, limit: u64 },
+    /// The operation stopped on, or refused because of, conflicted files
+    ConflictDetected(String),
+    /// The operation would rewrite a commit in `immutable_heads()`
+    ImmutableCommit(String),
+    BookmarkNotFound(String),
+    /// A revset failed to parse or names revisions that don't exist
+    RevsetEvaluationError(String),
+    /// Another jj process changed the repo or holds its lock
+    ConcurrentOperation(String),
+}
+
+impl JjError {
+    /// Stable name of the variant, the `kind` the frontend matches on
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JjError::AlreadyInitialized => "already_initialized",
+            JjError::NotGitRepository => "not_git_repository",
+            JjError::InitFailed(_) => "init_failed",
+            JjError::ConfigError(_) => "config_error",
+            JjError::WorkspaceNotFound(_) => "workspace_not_found",
+            JjError::GitWorkspaceError(_) => "git_workspace_error",
+            JjError::IoError(_) => "io_error",
+            JjError::SnapshotFileTooLarge { .. } => "snapshot_file_too_large",
+            JjError::ConflictDetected(_) => "conflict_detected",
+            JjError::ImmutableCommit(_) => "immutable_commit",
+            JjError::BookmarkNotFound(_) => "bookmark_not_found",
+            JjError::RevsetEvaluationError(_) => "revset_evaluation_error",
+            JjError::ConcurrentOperation(_) => "concurrent_operation",
+        }
+    }
+}
+
+impl Serialize for JjError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("JjError", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let JjError::SnapshotFileTooLarge { path, size, limit } = self {
+            state.serialize_field("path", path)?;
+            state.serialize_field("size", size)?;
+            state.serialize_field("limit", limit)?;
+        }
+        state.end()
+    }
 }
 
 /// Information about a jj workspace
@@ -64,6 +283,31 @@ pub struct WorkspaceInfo {
     pub is_colocated: bool,
 }
 
+/// Hunks of a file in the working copy. Files whose diff isn't parsed have no hunks and
+/// say why in `skipped`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileHunks {
+    pub hunks: Vec<JjDiffHunk>,
+    #[serde(flatten)]
+    pub skipped: SkippedDiff,
+}
+
+impl FileHunks {
+    pub fn parsed(hunks: Vec<JjDiffHunk>) -> Self {
+        FileHunks {
+            hunks,
+            skipped: SkippedDiff::default(),
+        }
+    }
+
+    pub fn skipped(skipped: SkippedDiff) -> Self {
+        FileHunks {
+            hunks: Vec::new(),
+            skipped,
+        }
+    }
+}
+
 /// A diff hunk from jj diff output
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JjDiffHunk {
@@ -71,6 +315,22 @@ pub struct JjDiffHunk {
     pub header: String,
     pub lines: Vec<String>,
     pub patch: String,
+    /// Removed lines and the added lines that replace them
+    #[serde(default)]
+    pub line_pairs: Vec<DiffLinePair>,
+    /// Plain-language description, e.g. "3 added, 2 removed in function foo"
+    #[serde(default)]
+    pub summary: String,
+    /// Hash of the hunk lines without the header, stable when the hunk only moves
+    #[serde(default)]
+    pub content_hash: String,
+}
+
+/// A removed line paired with the added line replacing it, as indices into `JjDiffHunk::lines`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiffLinePair {
+    pub deletion: usize,
+    pub addition: usize,
 }
 
 /// File change status in JJ working copy
@@ -78,7 +338,19 @@ pub struct JjDiffHunk {
 pub struct JjFileChange {
     pub path: String,
     pub status: String,
+    /// Source path of a rename ("R") or copy ("C")
     pub previous_path: Option<String>,
+    /// Set for submodule entries
+    #[serde(default)]
+    pub submodule: Option<SubmoduleChange>,
+    /// Staged change in plain git repositories, as a porcelain status letter
+    /// ("M", "A", "D", "R", "C", "T", "U"); None when nothing is staged
+    #[serde(default)]
+    pub index_status: Option<String>,
+    /// Unstaged change in plain git repositories, with "?" for untracked files;
+    /// None when the working tree matches the index
+    #[serde(default)]
+    pub worktree_status: Option<String>,
 }
 
 /// File content lines for context expansion
@@ -89,6 +361,27 @@ pub struct JjFileLines {
     pub end_line: usize,
 }
 
+/// Sides of a conflicted file reconstructed from materialized conflict markers
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JjConflict {
+    pub file_path: String,
+    /// Conflict description from `jj resolve --list`, e.g. "2-sided conflict"
+    pub description: String,
+    pub base: String,
+    pub left: String,
+    pub right: String,
+    pub conflict_count: usize,
+    /// Raw file content including conflict markers
+    pub materialized: String,
+}
+
+/// Result of writing a conflict resolution
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JjResolveResult {
+    pub resolved: bool,
+    pub remaining_conflicts: Vec<String>,
+}
+
 /// Result of a rebase operation
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JjRebaseResult {
@@ -97,14 +390,17 @@ pub struct JjRebaseResult {
 }
 
 /// A single commit in the log
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JjLogCommit {
     pub commit_id: String,
     pub short_id: String,
     pub change_id: String,
     pub description: String,
     pub author_name: String,
+    /// Author time, RFC3339 UTC
     pub timestamp: String,
+    /// Author time in epoch milliseconds, for ordering
+    pub sort_key: i64,
     pub parent_ids: Vec<String>,
     pub is_working_copy: bool,
     pub bookmarks: Vec<String>,
@@ -125,6 +421,8 @@ pub struct JjLogResult {
 pub struct JjCommitsAhead {
     pub commits: Vec<JjLogCommit>,
     pub total_count: usize,
+    /// False when a shallow clone lacks the merge base, so the range over-counts
+    pub accurate: bool,
 }
 
 /// Result of merge operation
@@ -161,6 +459,16 @@ impl std::fmt::Display for JjError {
             JjError::WorkspaceNotFound(name) => write!(f, "Workspace '{}' not found", name),
             JjError::GitWorkspaceError(e) => write!(f, "Git workspace error: {}", e),
             JjError::IoError(e) => write!(f, "IO error: {}", e),
+            JjError::SnapshotFileTooLarge { path, size, limit } => write!(
+                f,
+                "{} is too large to snapshot ({} bytes, the limit is {} bytes); add it to .gitignore or raise the repository's max new file size",
+                path, size, limit
+            ),
+            JjError::ConflictDetected(e) => write!(f, "Conflict detected: {}", e),
+            JjError::ImmutableCommit(e) => write!(f, "Commit is immutable: {}", e),
+            JjError::BookmarkNotFound(e) => write!(f, "Bookmark not found: {}", e),
+            JjError::RevsetEvaluationError(e) => write!(f, "Invalid revset: {}", e),
+            JjError::ConcurrentOperation(e) => write!(f, "Concurrent jj operation: {}", e),
         }
     }
 }
@@ -194,7 +502,8 @@ fn get_git_user_config(repo_path: &str) -> (String, String) {
 }
 
 /// Create UserSettings with reasonable defaults for Treq
-/// Uses git config values if available, otherwise uses defaults
+/// Uses git config values if available, otherwise uses defaults, and applies the repo's
+/// `.jj/repo/config.toml` on top
 fn create_user_settings(repo_path: &str) -> Result<UserSettings, JjError> {
     // Get user info from git config
     let (user_name, user_email) = get_git_user_config(repo_path);
@@ -227,6 +536,14 @@ username = "{}"
         .map_err(|e| JjError::ConfigError(e.to_string()))?;
     config.add_layer(layer);
 
+    // The repo's own config (`jj config set --repo`) overrides ours, as it does for jj
+    let repo_config = Path::new(repo_path).join(".jj").join("repo").join("config.toml");
+    if repo_config.is_file() {
+        let layer = ConfigLayer::load_from_file(ConfigSource::Repo, repo_config)
+            .map_err(|e| JjError::ConfigError(e.to_string()))?;
+        config.add_layer(layer);
+    }
+
     UserSettings::from_config(config).map_err(|e| JjError::ConfigError(e.to_string()))
 }
 
@@ -400,11 +717,11 @@ pub fn create_workspace(
         .join(&sanitized_name);
 
     let workspace_path_str = workspace_dir.to_string_lossy().to_string();
+    paths::ensure_git_longpaths(repo_path, &workspace_path_str).map_err(JjError::IoError)?;
 
     // Use jj workspace add for all cases (handles both new and existing bookmarks)
-    let mut jj_cmd = command_for("jj");
-    jj_cmd.current_dir(repo_path)
-        .args(["workspace", "add", &workspace_path_str]);
+    let mut jj_cmd = jj_command(repo_path);
+    jj_cmd.args(["workspace", "add", &workspace_path_str]);
 
     // Determine revision to start from and extract remote name if applicable
     // Convert git format (origin/branch) to jj format (branch@origin)
@@ -445,25 +762,26 @@ pub fn create_workspace(
         // Don't fail workspace creation for bookmark errors
     }
 
-    // Always track the bookmark with origin remote
+    // Always track the bookmark with the default remote
     // This ensures bookmarks are tracked even for new local branches
-    match is_bookmark_tracked(&workspace_path_str, branch_name, "origin") {
+    let remote = default_remote(repo_path);
+    match is_bookmark_tracked(&workspace_path_str, branch_name, &remote) {
         Ok(true) => {
-            eprintln!("Bookmark '{}' is already tracked with origin", branch_name);
+            eprintln!("Bookmark '{}' is already tracked with {}", branch_name, remote);
         }
         Ok(false) => {
-            if let Err(e) = jj_bookmark_track(&workspace_path_str, branch_name, "origin") {
-                eprintln!("Warning: Failed to track bookmark '{}@origin': {}", branch_name, e);
+            if let Err(e) = jj_bookmark_track(&workspace_path_str, branch_name, &remote) {
+                eprintln!("Warning: Failed to track bookmark '{}@{}': {}", branch_name, remote, e);
                 // Don't fail workspace creation for tracking errors
             } else {
-                eprintln!("Successfully set up tracking for '{}@origin'", branch_name);
+                eprintln!("Successfully set up tracking for '{}@{}'", branch_name, remote);
             }
         }
         Err(e) => {
             eprintln!("Warning: Could not determine tracking status: {}", e);
             // Attempt to track anyway
-            if let Err(e) = jj_bookmark_track(&workspace_path_str, branch_name, "origin") {
-                eprintln!("Warning: Failed to track bookmark '{}@origin': {}", branch_name, e);
+            if let Err(e) = jj_bookmark_track(&workspace_path_str, branch_name, &remote) {
+                eprintln!("Warning: Failed to track bookmark '{}@{}': {}", branch_name, remote, e);
                 // Don't fail workspace creation for tracking errors
             }
         }
@@ -541,6 +859,86 @@ pub fn get_workspace_branch(workspace_path: &str) -> Result<String, JjError> {
 }
 
 
+/// Names of the workspaces jj tracks, excluding the default workspace at the repo root
+pub fn list_jj_workspace_names(repo_path: &str) -> Result<Vec<String>, JjError> {
+    let output = jj_command(repo_path)
+        .args(["workspace", "list"])
+        .output()
+        .map_err(|e| JjError::IoError(format!("Failed to execute jj workspace list: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    // Lines look like "name: <change id> <commit id> <description>"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(": ").map(|(name, _)| name.trim().to_string()))
+        .filter(|name| !name.is_empty() && name != "default")
+        .collect())
+}
+
+/// Stop jj tracking a workspace; a workspace jj doesn't know is not an error
+pub fn forget_workspace(repo_path: &str, workspace_name: &str) -> Result<(), JjError> {
+    let output = jj_command(repo_path)
+        .args(["workspace", "forget", workspace_name])
+        .output()
+        .map_err(|e| JjError::IoError(format!("Failed to execute jj workspace forget: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Only return error if it's not a "workspace not found" error
+        if !stderr.contains("No such workspace") {
+            return Err(JjError::IoError(
+                format!("Failed to forget workspace: {}", stderr)
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Make jj track an existing workspace directory again without touching its files.
+/// A fresh workspace is added next to it at `revision`, its `.jj` metadata replaces
+/// the directory's stale one, and the directory's contents are snapshotted as working
+/// copy changes on top of `revision`.
+pub fn reattach_workspace(
+    repo_path: &str,
+    workspace_name: &str,
+    workspace_path: &str,
+    revision: &str,
+) -> Result<(), JjError> {
+    let workspace_dir = Path::new(workspace_path);
+    if !workspace_dir.is_dir() {
+        return Err(JjError::WorkspaceNotFound(workspace_path.to_string()));
+    }
+    let staging_dir = workspace_dir.with_file_name(format!("{}.reattach", workspace_name));
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).map_err(|e| JjError::IoError(e.to_string()))?;
+    }
+
+    forget_workspace(repo_path, workspace_name)?;
+    let staging_path = staging_dir.to_string_lossy().to_string();
+    let output = jj_command(repo_path)
+        .args(["workspace", "add", "--name", workspace_name])
+        .args(["--revision", revision, &staging_path])
+        .output()
+        .map_err(|e| JjError::IoError(format!("Failed to execute jj workspace add: {}", e)))?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&staging_dir);
+        return Err(command_error(&output.stderr));
+    }
+
+    // Only jj's own metadata is replaced; the staging checkout holds no user work
+    let stale_jj = workspace_dir.join(".jj");
+    if stale_jj.exists() {
+        fs::remove_dir_all(&stale_jj).map_err(|e| JjError::IoError(e.to_string()))?;
+    }
+    fs::rename(staging_dir.join(".jj"), &stale_jj).map_err(|e| JjError::IoError(e.to_string()))?;
+    fs::remove_dir_all(&staging_dir).map_err(|e| JjError::IoError(e.to_string()))?;
+    Ok(())
+}
+
 /// Remove a workspace (jj workspace + files)
 pub fn remove_workspace(repo_path: &str, workspace_path: &str) -> Result<(), JjError> {
     let workspace_dir = Path::new(workspace_path);
@@ -554,22 +952,7 @@ pub fn remove_workspace(repo_path: &str, workspace_path: &str) -> Result<(), JjE
     // Always try to forget the jj workspace first
     // This ensures jj stops tracking it even if directory is already gone
     if !workspace_name.is_empty() {
-        let output = command_for("jj")
-            .current_dir(repo_path)
-            .args(&["workspace", "forget", workspace_name])
-            .output()
-            .map_err(|e| JjError::IoError(format!("Failed to execute jj workspace forget: {}", e)))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            // Only return error if it's not a "workspace not found" error
-            if !stderr.contains("No such workspace") {
-                return Err(JjError::IoError(
-                    format!("Failed to forget workspace: {}", stderr)
-                ));
-            }
-            // If workspace not found in jj, that's fine - continue with directory cleanup
-        }
+        forget_workspace(repo_path, workspace_name)?;
     }
 
     // Remove directory if it exists
@@ -616,8 +999,7 @@ pub fn squash_to_workspace(
     let target_ref = format!("{}@", target_workspace_name);
 
     // Build the jj squash command
-    let mut cmd = command_for("jj");
-    cmd.current_dir(source_workspace_path);
+    let mut cmd = jj_command(source_workspace_path);
     cmd.args(["squash", "--from", "@", "--into", &target_ref]);
 
     // If specific file paths are provided, add them
@@ -651,8 +1033,7 @@ pub fn squash_to_workspace(
 pub fn jj_edit_workspace_working_copy(workspace_path: &str, branch_name: &str) -> Result<(), JjError> {
     // 1. Try: jj edit <branch>+
     let branch_plus = format!("{}+", branch_name);
-    let result = command_for("jj")
-        .current_dir(workspace_path)
+    let result = jj_command(workspace_path)
         .args(["edit", &branch_plus])
         .output();
 
@@ -677,8 +1058,7 @@ pub fn jj_edit_workspace_working_copy(workspace_path: &str, branch_name: &str) -
 
     // 3. Fallback: jj edit <branch> then jj new
     // This happens when there's no child and bookmark != working copy
-    let edit_result = command_for("jj")
-        .current_dir(workspace_path)
+    let edit_result = jj_command(workspace_path)
         .args(["edit", branch_name])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -692,8 +1072,7 @@ pub fn jj_edit_workspace_working_copy(workspace_path: &str, branch_name: &str) -
     }
 
     // Create a new working copy on top of the bookmark
-    let new_result = command_for("jj")
-        .current_dir(workspace_path)
+    let new_result = jj_command(workspace_path)
         .args(["new"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -715,8 +1094,7 @@ pub fn jj_edit_workspace_working_copy(workspace_path: &str, branch_name: &str) -
 /// Check if a workspace has a stale working copy
 /// Returns true if the workspace is stale
 pub fn is_workspace_stale(workspace_path: &str) -> Result<bool, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["status", "--no-pager"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -729,8 +1107,7 @@ pub fn is_workspace_stale(workspace_path: &str) -> Result<bool, JjError> {
 
 /// Update a stale working copy using jj workspace update-stale
 pub fn jj_workspace_update_stale(workspace_path: &str) -> Result<String, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["workspace", "update-stale"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -754,20 +1131,22 @@ pub fn jj_workspace_update_stale(workspace_path: &str) -> Result<String, JjError
 /// Get list of changed files in working copy using jj status
 /// This is faster than git status for large repos
 pub fn jj_get_changed_files(workspace_path: &str) -> Result<Vec<JjFileChange>, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["status", "--no-pager"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     let status_output = String::from_utf8_lossy(&output.stdout);
-    parse_jj_status(&status_output)
+    let mut changes = parse_jj_status(&status_output)?;
+    // Best-effort: a submodule git can't read shouldn't hide the other changes
+    if let Err(e) = git_submodules::tag_submodule_changes(workspace_path, &mut changes) {
+        eprintln!("Warning: Failed to tag submodule changes: {}", e);
+    }
+    Ok(changes)
 }
 
 /// Parse jj status output into file changes
@@ -783,21 +1162,26 @@ fn parse_jj_status(status: &str) -> Result<Vec<JjFileChange>, JjError> {
             continue;
         }
 
-        // Parse lines like "M file.txt" or "A new.txt" or "D removed.txt"
+        // Parse lines like "M file.txt", "A new.txt", "D removed.txt",
+        // "R src/{old.rs => new.rs}" or "C src/{a.rs => b.rs}"
         if let Some((status_char, rest)) = line.split_once(' ') {
             let status = match status_char {
                 "M" => "M", // Modified
                 "A" => "A", // Added
                 "D" => "D", // Deleted
-                "R" => "M", // Renamed (treat as modified for now)
+                "R" => "R", // Renamed
+                "C" => "C", // Copied
                 _ => continue,
             };
 
-            let path = rest.trim().to_string();
+            let (path, previous_path) = split_summary_path(status, rest.trim());
             changes.push(JjFileChange {
                 path,
                 status: status.to_string(),
-                previous_path: None,
+                previous_path,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
             });
         }
     }
@@ -810,26 +1194,73 @@ fn parse_jj_status(status: &str) -> Result<Vec<JjFileChange>, JjError> {
 pub fn jj_get_file_hunks(
     workspace_path: &str,
     file_path: &str,
-) -> Result<Vec<JjDiffHunk>, JjError> {
+) -> Result<FileHunks, JjError> {
+    if let Some(skipped) = git_ops::skipped_worktree_diff(workspace_path, file_path) {
+        return Ok(FileHunks::skipped(skipped));
+    }
+
     // Use jj diff --git to get hunks in git-compatible format
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["diff", "--git", "--no-pager", "--", file_path])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     let diff_output = String::from_utf8_lossy(&output.stdout);
-    parse_git_diff_hunks(&diff_output)
+    let hunks = parse_git_diff_hunks(&diff_output)?;
+    // Workspaces without a .git can't check attributes, but the pointer gives it away
+    if git_ops::is_lfs_pointer_diff(&hunks) {
+        return Ok(FileHunks::skipped(SkippedDiff {
+            is_lfs: true,
+            ..Default::default()
+        }));
+    }
+    Ok(FileHunks::parsed(hunks))
+}
+
+/// Lines added and removed in the working copy, as (insertions, deletions)
+pub fn jj_diff_stat(workspace_path: &str) -> Result<(usize, usize), JjError> {
+    let output = jj_command(workspace_path)
+        .args(["diff", "--stat", "--no-pager"])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    Ok(parse_diff_stat_totals(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Totals from the last line of `--stat` output,
+/// e.g. "2 files changed, 3 insertions(+), 1 deletion(-)"
+fn parse_diff_stat_totals(stat: &str) -> (usize, usize) {
+    let Some(summary) = stat.lines().rev().find(|l| l.contains("changed")) else {
+        return (0, 0);
+    };
+    let mut insertions = 0;
+    let mut deletions = 0;
+    for part in summary.split(',') {
+        let part = part.trim();
+        let count = part
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(0);
+        if part.contains("insertion") {
+            insertions = count;
+        } else if part.contains("deletion") {
+            deletions = count;
+        }
+    }
+    (insertions, deletions)
 }
 
 /// Parse git diff output into hunks
-fn parse_git_diff_hunks(diff: &str) -> Result<Vec<JjDiffHunk>, JjError> {
+pub(crate) fn parse_git_diff_hunks(diff: &str) -> Result<Vec<JjDiffHunk>, JjError> {
     let mut hunks = Vec::new();
     let mut current_hunk: Option<(String, Vec<String>)> = None;
     let mut hunk_index = 0;
@@ -838,12 +1269,7 @@ fn parse_git_diff_hunks(diff: &str) -> Result<Vec<JjDiffHunk>, JjError> {
         if line.starts_with("@@") {
             // Save previous hunk if exists
             if let Some((header, lines)) = current_hunk.take() {
-                hunks.push(JjDiffHunk {
-                    id: format!("hunk-{}", hunk_index),
-                    header: header.clone(),
-                    lines: lines.clone(),
-                    patch: format!("{}\n{}", header, lines.join("\n")),
-                });
+                hunks.push(build_hunk(hunk_index, header, lines));
                 hunk_index += 1;
             }
 
@@ -863,17 +1289,133 @@ fn parse_git_diff_hunks(diff: &str) -> Result<Vec<JjDiffHunk>, JjError> {
 
     // Save last hunk
     if let Some((header, lines)) = current_hunk {
-        hunks.push(JjDiffHunk {
-            id: format!("hunk-{}", hunk_index),
-            header: header.clone(),
-            lines: lines.clone(),
-            patch: format!("{}\n{}", header, lines.join("\n")),
-        });
+        hunks.push(build_hunk(hunk_index, header, lines));
     }
 
     Ok(hunks)
 }
 
+fn build_hunk(index: usize, header: String, lines: Vec<String>) -> JjDiffHunk {
+    let line_pairs = pair_changed_lines(&lines);
+    let summary = summarize_hunk(&header, &lines);
+    let content_hash = hunk_content_hash(&lines);
+    JjDiffHunk {
+        id: format!("hunk-{}", index),
+        patch: format!("{}\n{}", header, lines.join("\n")),
+        header,
+        lines,
+        line_pairs,
+        summary,
+        content_hash,
+    }
+}
+
+/// Hash the lines of a hunk, ignoring the header so review state survives rebases that
+/// only shift line numbers
+pub fn hunk_content_hash(lines: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for line in lines {
+        hasher.update(line.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Pair each run of removed lines with the run of added lines that follows it, in order.
+/// Surplus lines on either side are pure additions or removals.
+fn pair_changed_lines(lines: &[String]) -> Vec<DiffLinePair> {
+    let mut pairs = Vec::new();
+    let mut deletions: Vec<usize> = Vec::new();
+    let mut additions: Vec<usize> = Vec::new();
+
+    let mut flush = |deletions: &mut Vec<usize>, additions: &mut Vec<usize>| {
+        pairs.extend(
+            deletions
+                .iter()
+                .zip(additions.iter())
+                .map(|(&deletion, &addition)| DiffLinePair { deletion, addition }),
+        );
+        deletions.clear();
+        additions.clear();
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.starts_with('-') {
+            // A removal after additions starts a new change block
+            if !additions.is_empty() {
+                flush(&mut deletions, &mut additions);
+            }
+            deletions.push(index);
+        } else if line.starts_with('+') {
+            additions.push(index);
+        } else if !line.starts_with('\\') {
+            // "\ No newline at end of file" doesn't end a block
+            flush(&mut deletions, &mut additions);
+        }
+    }
+    flush(&mut deletions, &mut additions);
+
+    pairs
+}
+
+/// Name of the enclosing symbol from the section heading git prints after a hunk range,
+/// e.g. "pub fn foo(bar: u32) {" becomes "function foo"
+fn hunk_scope(header: &str) -> Option<String> {
+    let heading = header.splitn(3, "@@").nth(2)?.trim();
+    if heading.is_empty() {
+        return None;
+    }
+
+    let words: Vec<&str> = heading.split_whitespace().collect();
+    for (i, word) in words.iter().enumerate() {
+        let kind = match *word {
+            "fn" | "function" | "def" | "func" => "function",
+            "class" => "class",
+            "struct" => "struct",
+            "enum" => "enum",
+            "trait" => "trait",
+            "impl" => "impl",
+            "interface" => "interface",
+            "mod" | "module" => "module",
+            _ => continue,
+        };
+        let name: String = words
+            .get(i + 1)?
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+            .collect();
+        if !name.is_empty() {
+            return Some(format!("{} {}", kind, name));
+        }
+    }
+
+    Some(format!("\"{}\"", heading))
+}
+
+/// Describe a hunk's changes for screen readers
+fn summarize_hunk(header: &str, lines: &[String]) -> String {
+    let added = lines.iter().filter(|l| l.starts_with('+')).count();
+    let removed = lines.iter().filter(|l| l.starts_with('-')).count();
+
+    let mut parts = Vec::new();
+    if added > 0 {
+        parts.push(format!("{} added", added));
+    }
+    if removed > 0 {
+        parts.push(format!("{} removed", removed));
+    }
+    let changes = if parts.is_empty() {
+        "No changes".to_string()
+    } else {
+        parts.join(", ")
+    };
+
+    match hunk_scope(header) {
+        Some(scope) => format!("{} in {}", changes, scope),
+        None => changes,
+    }
+}
+
 /// Get file content at specific lines for context expansion
 pub fn jj_get_file_lines(
     workspace_path: &str,
@@ -891,9 +1433,7 @@ pub fn jj_get_file_lines(
             .map_err(|e| JjError::IoError(e.to_string()))?;
 
         if !output.status.success() {
-            return Err(JjError::IoError(
-                String::from_utf8_lossy(&output.stderr).to_string(),
-            ));
+            return Err(command_error(&output.stderr));
         }
 
         String::from_utf8_lossy(&output.stdout).to_string()
@@ -927,16 +1467,13 @@ pub fn jj_get_file_lines(
 /// Restore a file to parent state (discard changes)
 /// Uses CLI as jj-lib mutation APIs are complex
 pub fn jj_restore_file(workspace_path: &str, file_path: &str) -> Result<String, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["restore", file_path])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
@@ -944,16 +1481,13 @@ pub fn jj_restore_file(workspace_path: &str, file_path: &str) -> Result<String,
 
 /// Restore all changes
 pub fn jj_restore_all(workspace_path: &str) -> Result<String, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["restore"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
@@ -966,16 +1500,13 @@ pub fn jj_set_bookmark(
     bookmark_name: &str,
     revision: &str,
 ) -> Result<(), JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["bookmark", "set", bookmark_name, "-r", revision, "--allow-backwards"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     Ok(())
@@ -989,16 +1520,13 @@ pub fn jj_bookmark_track(
     remote_name: &str,
 ) -> Result<(), JjError> {
     let tracking_ref = format!("{}@{}", bookmark_name, remote_name);
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["bookmark", "track", &tracking_ref])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     Ok(())
@@ -1012,8 +1540,7 @@ pub fn is_bookmark_tracked(
     bookmark_name: &str,
     remote_name: &str,
 ) -> Result<bool, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["bookmark", "list", "--all-remotes"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -1065,16 +1592,13 @@ pub fn is_bookmark_tracked(
 /// For colocated repos, also syncs git HEAD
 pub fn jj_edit_bookmark(repo_path: &str, bookmark_name: &str) -> Result<String, JjError> {
     // Run jj edit <bookmark>
-    let output = command_for("jj")
-        .current_dir(repo_path)
+    let output = jj_command(repo_path)
         .args(["edit", bookmark_name])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     // For colocated repos, sync git HEAD to keep git in sync
@@ -1108,38 +1632,49 @@ pub fn derive_repo_path_from_workspace(workspace_path: &str) -> Option<String> {
     None
 }
 
-/// Commit with message and create new working copy
-pub fn jj_commit(workspace_path: &str, message: &str) -> Result<String, JjError> {
-    let repo_path = derive_repo_path_from_workspace(workspace_path);
-
-    // Get branch name - different logic for workspaces vs main repo
-    let branch = if let Some(ref rp) = repo_path {
+/// Branch a commit in `workspace_path` belongs to: the workspace's branch from the db,
+/// or the branch git has checked out in the main repo
+fn commit_branch(workspace_path: &str, repo_path: Option<&str>) -> Result<String, JjError> {
+    if let Some(rp) = repo_path {
         // For workspaces: get branch_name from the workspace record in db
         let workspace = local_db::get_workspace_by_path(rp, workspace_path)
             .map_err(|e| JjError::IoError(format!("Failed to query workspace: {}", e)))?
             .ok_or_else(|| JjError::WorkspaceNotFound(workspace_path.to_string()))?;
-        workspace.branch_name
-    } else {
-        // For main repo: require git to be on a branch
-        let git_branch = get_workspace_branch(workspace_path).map_err(|e| {
-            JjError::IoError(format!(
-                "Failed to determine current git branch: {}",
-                e
-            ))
-        })?;
+        return Ok(workspace.branch_name);
+    }
 
-        if git_branch.is_empty() || git_branch == "HEAD" {
-            return Err(JjError::IoError(
-                "Git is not checked out to a branch. Please checkout a branch before committing."
-                    .to_string(),
-            ));
-        }
-        git_branch
-    };
+    // For main repo: require git to be on a branch
+    let git_branch = get_workspace_branch(workspace_path).map_err(|e| {
+        JjError::IoError(format!(
+            "Failed to determine current git branch: {}",
+            e
+        ))
+    })?;
+
+    if git_branch.is_empty() || git_branch == "HEAD" {
+        return Err(JjError::IoError(
+            "Git is not checked out to a branch. Please checkout a branch before committing."
+                .to_string(),
+        ));
+    }
+    Ok(git_branch)
+}
+
+/// Commit with message and create new working copy, signing the commit when `signing`
+/// is set
+pub fn jj_commit(
+    workspace_path: &str,
+    message: &str,
+    signing: Option<&SigningConfig>,
+) -> Result<String, JjError> {
+    let repo_path = derive_repo_path_from_workspace(workspace_path);
+
+    // Get branch name - different logic for workspaces vs main repo
+    let branch = commit_branch(workspace_path, repo_path.as_deref())?;
 
     // Now commit with message (sets message on current change and creates new empty change)
-    let commit = command_for("jj")
-        .current_dir(workspace_path)
+    let commit = jj_command(workspace_path)
+        .args(signing.map(|s| s.jj_config_args()).unwrap_or_default())
         .args(["commit", "-m", message])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -1168,42 +1703,149 @@ pub fn jj_commit(workspace_path: &str, message: &str) -> Result<String, JjError>
     Ok(format!("Committed successfully to branch '{}'", branch))
 }
 
-/// Split selected files from working copy into a new parent commit
-/// Uses: jj split -r @ -m <message> <file_paths...>
-pub fn jj_split(
-    workspace_path: &str,
-    message: &str,
-    file_paths: Vec<String>,
-) -> Result<String, JjError> {
-    let repo_path = derive_repo_path_from_workspace(workspace_path);
+/// Reject revisions jj would parse as options
+fn validate_revision(revision: &str) -> Result<(), JjError> {
+    if revision.trim().is_empty() || revision.starts_with('-') || revision.contains('\0') {
+        return Err(JjError::IoError(format!("Invalid revision '{}'", revision)));
+    }
+    Ok(())
+}
 
-    // Get branch name - different logic for workspaces vs main repo
-    let branch = if let Some(ref rp) = repo_path {
-        // For workspaces: get branch_name from the workspace record in db
-        let workspace = local_db::get_workspace_by_path(rp, workspace_path)
-            .map_err(|e| JjError::IoError(format!("Failed to query workspace: {}", e)))?
-            .ok_or_else(|| JjError::WorkspaceNotFound(workspace_path.to_string()))?;
-        workspace.branch_name
-    } else {
-        let git_branch = get_workspace_branch(workspace_path).map_err(|e| {
-            JjError::IoError(format!(
-                "Failed to determine current git branch: {}",
-                e
-            ))
-        })?;
+/// Change id of the single commit `revision` resolves to
+fn resolve_single_revision(workspace_path: &str, revision: &str) -> Result<String, JjError> {
+    validate_revision(revision)?;
+    let output = jj_command(workspace_path)
+        .args(["log", "-r", revision, "--no-graph", "--limit", "2", "-T", "change_id ++ \"\\n\""])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
 
-        if git_branch.is_empty() || git_branch == "HEAD" {
-            return Err(JjError::IoError(
-                "Git is not checked out to a branch. Please checkout a branch before committing."
-                    .to_string(),
-            ));
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let ids: Vec<&str> = stdout.lines().filter(|l| !l.is_empty()).collect();
+    match ids.as_slice() {
+        [id] => Ok(id.to_string()),
+        [] => Err(JjError::IoError(format!("Revision '{}' matches no commit", revision))),
+        _ => Err(JjError::IoError(format!(
+            "Revision '{}' matches more than one commit",
+            revision
+        ))),
+    }
+}
+
+/// Full description of a commit
+pub fn jj_get_commit_message(workspace_path: &str, revision: &str) -> Result<String, JjError> {
+    let change_id = resolve_single_revision(workspace_path, revision)?;
+    let output = jj_command(workspace_path)
+        .args(["log", "-r", &change_id, "--no-graph", "-T", "description"])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Change the message of any mutable commit; descendants are rebased by jj.
+/// Describing `@-` re-points the branch bookmark at it, as `jj_commit` does.
+pub fn jj_describe(workspace_path: &str, revision: &str, message: &str) -> Result<String, JjError> {
+    let change_id = resolve_single_revision(workspace_path, revision)?;
+    let output = jj_command(workspace_path)
+        .args(["describe", "-r", &change_id, "-m", message])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    if revision.trim() == "@-" {
+        advance_branch_to_parent(workspace_path)?;
+    }
+
+    Ok(format!("Updated message of {}", revision))
+}
+
+/// Point the workspace's branch bookmark at `@-` after it was rewritten or replaced
+fn advance_branch_to_parent(workspace_path: &str) -> Result<(), JjError> {
+    let repo_path = derive_repo_path_from_workspace(workspace_path);
+    let branch = commit_branch(workspace_path, repo_path.as_deref())?;
+    jj_set_bookmark(workspace_path, &branch, "@-")
+        .map_err(|e| JjError::IoError(format!("Failed to advance bookmark '{}': {}", branch, e)))?;
+
+    // Only checkout branch in git for main repo (not workspaces)
+    if repo_path.is_none() {
+        let checkout = command_for("git")
+            .current_dir(workspace_path)
+            .args(["checkout", &branch])
+            .output();
+        if let Err(e) = checkout {
+            eprintln!("Warning: Failed to checkout git branch '{}': {}", branch, e);
         }
-        git_branch
-    };
+    }
+    Ok(())
+}
+
+/// Undo `revision` with a new commit applying its inverse, inserted below the working
+/// copy so the branch bookmark moves onto it. jj records conflicts in commits, so a
+/// backout that doesn't apply cleanly is still made; it is reported as unsuccessful
+/// with the conflicted files so they get resolved.
+/// Uses: jj revert -r <revision> --insert-before @
+pub fn jj_backout(
+    workspace_path: &str,
+    revision: &str,
+    signing: Option<&SigningConfig>,
+) -> Result<JjRebaseResult, JjError> {
+    let change_id = resolve_single_revision(workspace_path, revision)?;
+    let output = jj_command(workspace_path)
+        .args(signing.map(|s| s.jj_config_args()).unwrap_or_default())
+        .args(["revert", "-r", &change_id, "--insert-before", "@"])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    advance_branch_to_parent(workspace_path)?;
+
+    let conflicts = jj_resolve_list(workspace_path, "@-")?;
+    if conflicts.is_empty() {
+        return Ok(JjRebaseResult {
+            success: true,
+            message: format!("Backed out {}", revision),
+        });
+    }
+    let paths: Vec<String> = conflicts.into_iter().map(|(path, _)| path).collect();
+    Ok(JjRebaseResult {
+        success: false,
+        message: format!(
+            "Backout of {} has conflicts in {} file(s): {}",
+            revision,
+            paths.len(),
+            paths.join(", ")
+        ),
+    })
+}
+
+/// Split selected files from working copy into a new parent commit
+/// Uses: jj split -r @ -m <message> <file_paths...>
+pub fn jj_split(
+    workspace_path: &str,
+    message: &str,
+    file_paths: Vec<String>,
+) -> Result<String, JjError> {
+    let repo_path = derive_repo_path_from_workspace(workspace_path);
+
+    // Get branch name - different logic for workspaces vs main repo
+    let branch = commit_branch(workspace_path, repo_path.as_deref())?;
 
     // Build and execute the jj split command
-    let mut cmd = command_for("jj");
-    cmd.current_dir(workspace_path);
+    let mut cmd = jj_command(workspace_path);
     cmd.args(["split", "-r", "@", "-m", message]);
     for path in &file_paths {
         cmd.arg(path);
@@ -1212,9 +1854,7 @@ pub fn jj_split(
     let output = cmd.output().map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     // Set the bookmark to point at @- (critical - same as jj_commit)
@@ -1235,16 +1875,171 @@ pub fn jj_split(
     Ok(format!("Committed successfully to branch '{}'", branch))
 }
 
+/// Hunks of a single file to move into the first commit of a split
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JjHunkSelection {
+    pub file_path: String,
+    /// Hunk ids as returned by jj_get_file_hunks (e.g. "hunk-0")
+    pub hunk_ids: Vec<String>,
+}
+
+/// Parse the old-side range from a hunk header "@@ -start,count +start,count @@"
+fn parse_hunk_old_range(header: &str) -> Option<(usize, usize)> {
+    let old = header.strip_prefix("@@ -")?.split_whitespace().next()?;
+    let mut parts = old.splitn(2, ',');
+    let start = parts.next()?.parse().ok()?;
+    let count = match parts.next() {
+        Some(c) => c.parse().ok()?,
+        None => 1,
+    };
+    Some((start, count))
+}
+
+/// Build file content from `old` with only the selected hunks applied.
+/// Unselected hunks keep their original (old) lines.
+fn apply_selected_hunks(
+    old: &str,
+    new_ends_with_newline: bool,
+    hunks: &[JjDiffHunk],
+    selected: &std::collections::HashSet<&str>,
+) -> Result<String, JjError> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let mut result: Vec<&str> = Vec::new();
+    let mut cursor = 0; // index into old_lines
+    let mut ends_with_newline = old.is_empty() || old.ends_with('\n');
+
+    for hunk in hunks {
+        let (old_start, old_count) = parse_hunk_old_range(&hunk.header)
+            .ok_or_else(|| JjError::IoError(format!("Invalid hunk header: {}", hunk.header)))?;
+        // For pure insertions git reports the line *before* the insertion point
+        let hunk_begin = if old_count == 0 { old_start } else { old_start.saturating_sub(1) };
+        if hunk_begin < cursor || hunk_begin > old_lines.len() {
+            return Err(JjError::IoError(format!("Hunk out of range: {}", hunk.header)));
+        }
+
+        result.extend_from_slice(&old_lines[cursor..hunk_begin]);
+
+        let is_selected = selected.contains(hunk.id.as_str());
+        for line in &hunk.lines {
+            if let Some(text) = line.strip_prefix('+') {
+                if is_selected {
+                    result.push(text);
+                }
+            } else if let Some(text) = line.strip_prefix('-') {
+                if !is_selected {
+                    result.push(text);
+                }
+            } else if let Some(text) = line.strip_prefix(' ') {
+                result.push(text);
+            } else if line.is_empty() {
+                // Some tools strip the leading space from empty context lines
+                result.push("");
+            }
+        }
+
+        cursor = hunk_begin + old_count;
+        if is_selected && cursor >= old_lines.len() {
+            ends_with_newline = new_ends_with_newline;
+        }
+    }
+
+    result.extend_from_slice(&old_lines[cursor.min(old_lines.len())..]);
+
+    let mut content = result.join("\n");
+    if ends_with_newline && !result.is_empty() {
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Split the working copy commit, moving only the selected hunks into the first commit.
+///
+/// Partially selected files are temporarily rewritten on disk to contain just the selected
+/// hunks, the regular path-based split is run, and the full contents are then restored so
+/// the remaining hunks stay in the new working copy commit.
+pub fn jj_split_hunks(
+    workspace_path: &str,
+    message: &str,
+    selections: Vec<JjHunkSelection>,
+) -> Result<String, JjError> {
+    let mut split_paths = Vec::new();
+    let mut originals: Vec<(std::path::PathBuf, Vec<u8>)> = Vec::new();
+    let mut partial_contents: Vec<(std::path::PathBuf, String)> = Vec::new();
+
+    for selection in &selections {
+        if selection.hunk_ids.is_empty() {
+            continue;
+        }
+        if selection.file_path.starts_with('-') || selection.file_path.contains('\0') {
+            return Err(JjError::IoError(format!("Invalid file path: {}", selection.file_path)));
+        }
+
+        let hunks = jj_get_file_hunks(workspace_path, &selection.file_path)?.hunks;
+        let selected: std::collections::HashSet<&str> =
+            selection.hunk_ids.iter().map(|id| id.as_str()).collect();
+        split_paths.push(selection.file_path.clone());
+
+        // Whole file selected: the plain path-based split handles it
+        if hunks.iter().all(|h| selected.contains(h.id.as_str())) {
+            continue;
+        }
+
+        let full_path = Path::new(workspace_path).join(&selection.file_path);
+        let current = fs::read(&full_path).map_err(|e| {
+            JjError::IoError(format!("Failed to read {}: {}", selection.file_path, e))
+        })?;
+
+        // Parent content is empty for newly added files
+        let parent = jj_command(workspace_path)
+            .args(["file", "show", "-r", "@-", "--", &selection.file_path])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+            .unwrap_or_default();
+
+        let partial = apply_selected_hunks(&parent, current.ends_with(b"\n"), &hunks, &selected)?;
+        partial_contents.push((full_path.clone(), partial));
+        originals.push((full_path, current));
+    }
+
+    if split_paths.is_empty() {
+        return Err(JjError::IoError("No hunks selected".to_string()));
+    }
+
+    let mut write_error = None;
+    for (path, content) in &partial_contents {
+        if let Err(e) = fs::write(path, content) {
+            write_error = Some(JjError::IoError(format!("Failed to write partial content: {}", e)));
+            break;
+        }
+    }
+
+    let result = match write_error {
+        Some(e) => Err(e),
+        None => jj_split(workspace_path, message, split_paths),
+    };
+
+    // Always restore the full contents so unselected hunks are never lost
+    for (path, content) in &originals {
+        if let Err(e) = fs::write(path, content) {
+            eprintln!("Warning: Failed to restore {}: {}", path.display(), e);
+        }
+    }
+
+    result
+}
+
 /// Rebase the current workspace onto a target branch
 /// Uses: jj rebase -d <target_branch>
-pub fn jj_rebase_onto(
+pub async fn jj_rebase_onto(
     workspace_path: &str,
     target_branch: &str,
 ) -> Result<JjRebaseResult, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = async_jj_command(workspace_path, async_process::LOCAL_TIMEOUT)
         .args(["rebase", "-d", target_branch])
         .output()
+        .await
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -1293,8 +2088,7 @@ pub fn get_conflicted_files(
     }
 
     // Fallback approach: use jj st to check for conflicts
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["st"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -1315,16 +2109,13 @@ fn get_conflicted_files_from_diff(
     workspace_path: &str,
     jj_branch: &str,
 ) -> Result<Vec<String>, JjError> {
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["diff", "--from", jj_branch, "--to", "@", "--summary"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     let summary = String::from_utf8_lossy(&output.stdout);
@@ -1388,6 +2179,215 @@ fn parse_conflicted_files_from_status(status: &str) -> Result<Vec<String>, JjErr
     Ok(conflicts)
 }
 
+/// Parse `jj resolve --list` output into (path, description) pairs
+/// Format: "<file_path>    <conflict_description>"
+fn parse_resolve_list(output: &str) -> Vec<(String, String)> {
+    output.lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once("  ") {
+            Some((path, description)) => (path.trim().to_string(), description.trim().to_string()),
+            None => (line.trim().to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// List conflicted paths in `revision` via `jj resolve --list`
+fn jj_resolve_list(workspace_path: &str, revision: &str) -> Result<Vec<(String, String)>, JjError> {
+    let output = jj_command(workspace_path)
+        .args(["resolve", "--list", "-r", revision])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    // jj exits non-zero with "No conflicts found" when nothing is conflicted
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("No conflicts") {
+            return Ok(Vec::new());
+        }
+        return Err(JjError::IoError(stderr.to_string()));
+    }
+
+    Ok(parse_resolve_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Reject absolute paths and parent traversal so writes stay inside the workspace
+fn validate_workspace_file_path(file_path: &str) -> Result<(), JjError> {
+    let path = Path::new(file_path);
+    if file_path.is_empty()
+        || file_path.contains('\0')
+        || path.is_absolute()
+        || path.components().any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(JjError::IoError(format!("Invalid file path: {}", file_path)));
+    }
+    Ok(())
+}
+
+/// Reconstruct base/left/right from a file with jj conflict markers.
+///
+/// Supports jj's "diff" style (%%%%%%% sections), "snapshot" style (------- base sections)
+/// and git style (||||||| / =======). Only the first two sides are returned.
+fn parse_conflict_markers(content: &str) -> (String, String, String, usize) {
+    #[derive(PartialEq)]
+    enum Section { Outside, Diff, Base, Side, GitLeft, GitBase, GitRight }
+
+    let mut base = String::new();
+    let mut left = String::new();
+    let mut right = String::new();
+    let mut conflict_count = 0;
+    let mut section = Section::Outside;
+    // Number of sides seen in the current conflict
+    let mut side = 0;
+
+    let push_side = |side: usize, line: &str, left: &mut String, right: &mut String| {
+        match side {
+            1 => left.push_str(line),
+            2 => right.push_str(line),
+            _ => {}
+        }
+    };
+
+    for line in content.split_inclusive('\n') {
+        let marker = line.trim_end_matches(['\n', '\r']);
+
+        if section == Section::Outside {
+            if marker.starts_with("<<<<<<<") {
+                conflict_count += 1;
+                side = 0;
+                // Git style puts side #1 directly after the opening marker; jj style
+                // always starts with a section header, which switches the mode below
+                section = Section::GitLeft;
+                continue;
+            }
+            base.push_str(line);
+            left.push_str(line);
+            right.push_str(line);
+            continue;
+        }
+
+        if marker.starts_with(">>>>>>>") {
+            section = Section::Outside;
+            continue;
+        }
+        if marker.starts_with("%%%%%%%") {
+            side += 1;
+            section = Section::Diff;
+            continue;
+        }
+        if marker.starts_with("+++++++") {
+            side += 1;
+            section = Section::Side;
+            continue;
+        }
+        if marker.starts_with("-------") {
+            section = Section::Base;
+            continue;
+        }
+        if marker.starts_with("\\\\\\\\\\\\\\") {
+            // Second header line of a diff section ("to: side #N")
+            continue;
+        }
+        if marker.starts_with("|||||||") {
+            section = Section::GitBase;
+            continue;
+        }
+        if marker.starts_with("=======") && section != Section::Diff && section != Section::Side {
+            section = Section::GitRight;
+            continue;
+        }
+
+        match section {
+            Section::GitLeft => left.push_str(line),
+            Section::GitBase => base.push_str(line),
+            Section::GitRight => right.push_str(line),
+            Section::Base => {
+                if side <= 1 {
+                    base.push_str(line);
+                }
+            }
+            Section::Side => push_side(side, line, &mut left, &mut right),
+            Section::Diff => {
+                let (prefix, rest) = line.split_at(line.chars().next().map(|c| c.len_utf8()).unwrap_or(0));
+                match prefix {
+                    "-" => {
+                        if side <= 1 {
+                            base.push_str(rest);
+                        }
+                    }
+                    "+" => push_side(side, rest, &mut left, &mut right),
+                    " " => {
+                        if side <= 1 {
+                            base.push_str(rest);
+                        }
+                        push_side(side, rest, &mut left, &mut right);
+                    }
+                    // Blank line inside a diff (context with trailing whitespace trimmed)
+                    _ => {
+                        if side <= 1 {
+                            base.push_str(line);
+                        }
+                        push_side(side, line, &mut left, &mut right);
+                    }
+                }
+            }
+            Section::Outside => {}
+        }
+    }
+
+    (base, left, right, conflict_count)
+}
+
+/// Read the sides of a conflicted file in the working copy
+pub fn jj_get_conflict(workspace_path: &str, file_path: &str) -> Result<JjConflict, JjError> {
+    validate_workspace_file_path(file_path)?;
+
+    let conflicts = jj_resolve_list(workspace_path, "@")?;
+    let description = conflicts.into_iter()
+        .find(|(path, _)| path == file_path)
+        .map(|(_, description)| description)
+        .ok_or_else(|| JjError::IoError(format!("File is not conflicted: {}", file_path)))?;
+
+    let full_path = Path::new(workspace_path).join(file_path);
+    let materialized = fs::read_to_string(&full_path)
+        .map_err(|e| JjError::IoError(format!("Failed to read file: {}", e)))?;
+    let (base, left, right, conflict_count) = parse_conflict_markers(&materialized);
+
+    Ok(JjConflict {
+        file_path: file_path.to_string(),
+        description,
+        base,
+        left,
+        right,
+        conflict_count,
+        materialized,
+    })
+}
+
+/// Write resolved content for a conflicted file and let jj snapshot it
+/// The next jj command snapshots the working copy, which records the resolution
+pub fn jj_resolve_conflict(
+    workspace_path: &str,
+    file_path: &str,
+    resolved_content: &str,
+) -> Result<JjResolveResult, JjError> {
+    validate_workspace_file_path(file_path)?;
+
+    let full_path = Path::new(workspace_path).join(file_path);
+    fs::write(&full_path, resolved_content)
+        .map_err(|e| JjError::IoError(format!("Failed to write file: {}", e)))?;
+
+    let remaining_conflicts: Vec<String> = jj_resolve_list(workspace_path, "@")?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+
+    Ok(JjResolveResult {
+        resolved: !remaining_conflicts.iter().any(|path| path == file_path),
+        remaining_conflicts,
+    })
+}
+
 /// Get all commit IDs for a potentially conflicted bookmark
 /// Returns a vector of commit IDs - will have 1 item for normal bookmarks,
 /// 2+ items for conflicted bookmarks
@@ -1396,8 +2396,7 @@ fn get_all_commits_for_revision(repo_path: &str, revision: &str) -> Result<Vec<S
     let bookmark_name = revision.split('@').next().unwrap_or(revision);
     let exact_query = format!("bookmarks(exact:{})", bookmark_name);
 
-    let output = command_for("jj")
-        .current_dir(repo_path)
+    let output = jj_command(repo_path)
         .args([
             "log",
             "-r",
@@ -1410,7 +2409,7 @@ fn get_all_commits_for_revision(repo_path: &str, revision: &str) -> Result<Vec<S
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(String::from_utf8_lossy(&output.stderr).to_string()));
+        return Err(command_error(&output.stderr));
     }
 
     let commit_ids: Vec<String> = String::from_utf8_lossy(&output.stdout)
@@ -1426,8 +2425,7 @@ fn get_all_commits_for_revision(repo_path: &str, revision: &str) -> Result<Vec<S
 /// Uses: jj log -r <revision> --no-graph -T 'commit_id.short(12)'
 /// Returns error if the bookmark is conflicted (with details about all conflicting commits)
 pub fn jj_get_commit_id(repo_path: &str, revision: &str) -> Result<String, JjError> {
-    let output = command_for("jj")
-        .current_dir(repo_path)
+    let output = jj_command(repo_path)
         .args([
             "log",
             "-r",
@@ -1484,8 +2482,7 @@ pub fn jj_rebase_with_revset(
     target_branch: &str,
     _branch_name: &str,  // No longer used after switching to bookmark-only rebasing
 ) -> Result<JjRebaseResult, JjError> {
-    let output = command_for("jj")
-        .current_dir(working_dir)
+    let output = jj_command(working_dir)
         .args(["rebase", "-s", revset, "-d", target_branch])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -1509,7 +2506,12 @@ pub fn jj_rebase_with_revset(
 /// Get the default branch of the repository (main/master)
 /// Checks git symbolic-ref for origin/HEAD, falls back to checking for main/master
 pub fn get_default_branch(repo_path: &str) -> Result<String, JjError> {
-    // Try origin/HEAD first
+    // The branch the default remote advertised at the last refresh
+    if let Some(branch) = crate::default_branch::recorded(repo_path) {
+        return Ok(branch);
+    }
+
+    // Then origin/HEAD
     let output = command_for("git")
         .current_dir(repo_path)
         .args(["symbolic-ref", "refs/remotes/origin/HEAD"])
@@ -1541,8 +2543,10 @@ pub fn get_default_branch(repo_path: &str) -> Result<String, JjError> {
     Ok("main".to_string())
 }
 
-/// Push changes to remote using jj git push
-pub fn jj_push(workspace_path: &str, force: bool) -> Result<String, JjError> {
+/// Push changes to remote using jj git push, to `remote` or the default remote
+pub async fn jj_push(workspace_path: &str, force: bool, remote: Option<&str>) -> Result<String, JjError> {
+    let remote = resolve_remote(workspace_path, remote)?;
+
     // Get current branch name to check/ensure tracking
     let branch_name = get_workspace_branch(workspace_path)?;
 
@@ -1550,7 +2554,7 @@ pub fn jj_push(workspace_path: &str, force: bool) -> Result<String, JjError> {
     // This helps avoid "Non-tracking remote bookmark" warnings
     let mut tracking_message = String::new();
 
-    match is_bookmark_tracked(workspace_path, &branch_name, "origin") {
+    match is_bookmark_tracked(workspace_path, &branch_name, &remote) {
         Ok(true) => {
             // Already tracked, proceed normally
         }
@@ -1561,7 +2565,7 @@ pub fn jj_push(workspace_path: &str, force: bool) -> Result<String, JjError> {
                 branch_name
             ));
 
-            if let Err(e) = jj_bookmark_track(workspace_path, &branch_name, "origin") {
+            if let Err(e) = jj_bookmark_track(workspace_path, &branch_name, &remote) {
                 tracking_message.push_str(&format!(
                     "Warning: Could not set up tracking: {}. Attempting push anyway...\n",
                     e
@@ -1580,38 +2584,39 @@ pub fn jj_push(workspace_path: &str, force: bool) -> Result<String, JjError> {
     }
 
     // Execute the push
-    let mut cmd = command_for("jj");
-    cmd.current_dir(workspace_path);
+    let session = askpass::session("push");
+    let mut cmd = async_jj_command(workspace_path, async_process::REMOTE_TIMEOUT);
+    session.apply_async(&mut cmd);
 
+    cmd.args(["git", "push", "--remote", &remote]);
     if force {
-        cmd.args(["git", "push", "--force"]);
-    } else {
-        cmd.args(["git", "push"]);
+        cmd.arg("--force");
     }
 
     let output = cmd
         .output()
+        .await
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
 
     if !output.status.success() {
+        session.check_rejected(&stderr);
         return Err(JjError::IoError(format!("{}{}{}", tracking_message, stdout, stderr)));
     }
 
     Ok(format!("{}{}{}", tracking_message, stdout, stderr))
 }
 
-/// Get sync status with remote (ahead/behind counts)
+/// Get sync status with the default remote (ahead/behind counts)
 /// Returns (ahead_count, behind_count)
 pub fn jj_get_sync_status(workspace_path: &str, branch_name: &str) -> Result<(usize, usize), JjError> {
-    let remote_branch = format!("{}@origin", branch_name);
+    let remote_branch = format!("{}@{}", branch_name, default_remote(workspace_path));
 
     // Count commits ahead (local has, remote doesn't)
     // Using: jj log -r '<remote>..<local>' --no-graph -T 'commit_id\n'
-    let ahead_output = command_for("jj")
-        .current_dir(workspace_path)
+    let ahead_output = jj_command(workspace_path)
         .args(["log", "-r", &format!("{}..{}", remote_branch, branch_name), "--no-graph", "-T", "commit_id\n"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -1627,8 +2632,7 @@ pub fn jj_get_sync_status(workspace_path: &str, branch_name: &str) -> Result<(us
 
     // Count commits behind (remote has, local doesn't)
     // Using: jj log -r '<local>..<remote>' --no-graph -T 'commit_id\n'
-    let behind_output = command_for("jj")
-        .current_dir(workspace_path)
+    let behind_output = jj_command(workspace_path)
         .args(["log", "-r", &format!("{}..{}", branch_name, remote_branch), "--no-graph", "-T", "commit_id\n"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -1646,12 +2650,34 @@ pub fn jj_get_sync_status(workspace_path: &str, branch_name: &str) -> Result<(us
 }
 
 /// Fetch remote branches using jj git fetch (without rebasing)
-/// This updates remote tracking refs and makes remote branches available
-pub fn jj_git_fetch(repo_path: &str) -> Result<String, JjError> {
-    let output = command_for("jj")
-        .current_dir(repo_path)
-        .args(["git", "fetch"])
+/// This updates remote tracking refs and makes remote branches available.
+/// Without `remote`, jj fetches its configured default remotes.
+pub async fn jj_git_fetch(repo_path: &str, remote: Option<&str>) -> Result<String, JjError> {
+    let remote = remote
+        .map(|r| resolve_remote(repo_path, Some(r)))
+        .transpose()?;
+    run_git_fetch(repo_path, remote.as_deref(), askpass::session("fetch")).await
+}
+
+/// Fetch without prompting for credentials, for fetches the user did not start
+pub async fn jj_git_fetch_background(repo_path: &str) -> Result<String, JjError> {
+    run_git_fetch(repo_path, None, askpass::background_session("fetch")).await
+}
+
+async fn run_git_fetch(
+    repo_path: &str,
+    remote: Option<&str>,
+    session: askpass::AskpassSession,
+) -> Result<String, JjError> {
+    let mut cmd = async_jj_command(repo_path, async_process::REMOTE_TIMEOUT);
+    cmd.args(["git", "fetch"]);
+    if let Some(remote) = remote {
+        cmd.args(["--remote", remote]);
+    }
+    session.apply_async(&mut cmd);
+    let output = cmd
         .output()
+        .await
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -1660,6 +2686,7 @@ pub fn jj_git_fetch(repo_path: &str) -> Result<String, JjError> {
     // Note: jj git fetch may have warnings in stderr even on success
     // So we only fail if the command itself failed
     if !output.status.success() {
+        session.check_rejected(&stderr);
         return Err(JjError::IoError(format!("{}{}", stdout, stderr)));
     }
 
@@ -1667,19 +2694,26 @@ pub fn jj_git_fetch(repo_path: &str) -> Result<String, JjError> {
 }
 
 /// Pull changes from remote using jj git fetch + rebase
-/// Fetches from origin and rebases current workspace onto tracking branch
-pub fn jj_pull(workspace_path: &str) -> Result<String, JjError> {
+/// Fetches from `remote` (or the default remote) and rebases the current workspace onto
+/// its tracking branch there
+pub async fn jj_pull(workspace_path: &str, remote: Option<&str>) -> Result<String, JjError> {
+    let remote = resolve_remote(workspace_path, remote)?;
+
     // First, fetch from remote
-    let fetch_output = command_for("jj")
-        .current_dir(workspace_path)
-        .args(["git", "fetch"])
+    let session = askpass::session("pull");
+    let mut fetch = async_jj_command(workspace_path, async_process::REMOTE_TIMEOUT);
+    fetch.args(["git", "fetch", "--remote", &remote]);
+    session.apply_async(&mut fetch);
+    let fetch_output = fetch
         .output()
+        .await
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     let fetch_stdout = String::from_utf8_lossy(&fetch_output.stdout);
     let fetch_stderr = String::from_utf8_lossy(&fetch_output.stderr);
 
     if !fetch_output.status.success() {
+        session.check_rejected(&fetch_stderr);
         return Err(JjError::IoError(format!(
             "{}{}",
             fetch_stdout, fetch_stderr
@@ -1694,12 +2728,12 @@ pub fn jj_pull(workspace_path: &str) -> Result<String, JjError> {
         return Ok(format!("{}{}", fetch_stdout, fetch_stderr));
     }
 
-    // Rebase onto the tracking branch (branch@origin)
-    let tracking_branch = format!("{}@origin", branch_name);
-    let rebase_output = command_for("jj")
-        .current_dir(workspace_path)
+    // Rebase onto the tracking branch (branch@remote)
+    let tracking_branch = format!("{}@{}", branch_name, remote);
+    let rebase_output = async_jj_command(workspace_path, async_process::LOCAL_TIMEOUT)
         .args(["rebase", "-d", &tracking_branch])
         .output()
+        .await
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     let rebase_stdout = String::from_utf8_lossy(&rebase_output.stdout);
@@ -1728,9 +2762,13 @@ pub struct BranchStatus {
 }
 
 /// Check if a branch exists locally and/or remotely
-/// Uses git rev-parse to check refs/heads/{branch} and refs/remotes/{remote}/{branch}
-/// Currently only checks 'origin' remote
-pub fn check_branch_exists(repo_path: &str, branch_name: &str) -> Result<BranchStatus, JjError> {
+/// Uses git rev-parse to check refs/heads/{branch} and refs/remotes/{remote}/{branch}.
+/// Without `remote`, the default remote is checked first, then the others by name.
+pub fn check_branch_exists(
+    repo_path: &str,
+    branch_name: &str,
+    remote: Option<&str>,
+) -> Result<BranchStatus, JjError> {
     // Check local branch existence
     let local_ref = format!("refs/heads/{}", branch_name);
     let local_check = command_for("git")
@@ -1741,50 +2779,87 @@ pub fn check_branch_exists(repo_path: &str, branch_name: &str) -> Result<BranchS
 
     let local_exists = local_check.status.success();
 
-    // Check remote branch existence (origin)
-    // In the future, could check all remotes from `git remote` output
-    let remote_name = "origin";
-    let remote_ref = format!("refs/remotes/{}/{}", remote_name, branch_name);
-    let remote_check = command_for("git")
-        .current_dir(repo_path)
-        .args(["rev-parse", "--verify", &remote_ref])
-        .output()
-        .map_err(|e| JjError::IoError(e.to_string()))?;
+    let candidates = match remote {
+        Some(remote) => vec![resolve_remote(repo_path, Some(remote))?],
+        None => {
+            let remotes = get_git_remotes(repo_path);
+            let default = pick_default_remote(&remotes);
+            let mut others: Vec<String> = remotes.into_iter().filter(|r| *r != default).collect();
+            others.sort();
+            std::iter::once(default).chain(others).collect()
+        }
+    };
 
-    let remote_exists = remote_check.status.success();
+    for remote_name in candidates {
+        let remote_ref = format!("refs/remotes/{}/{}", remote_name, branch_name);
+        let remote_check = command_for("git")
+            .current_dir(repo_path)
+            .args(["rev-parse", "--verify", &remote_ref])
+            .output()
+            .map_err(|e| JjError::IoError(e.to_string()))?;
 
-    let remote_ref_short = if remote_exists {
-        Some(format!("{}/{}", remote_name, branch_name))
-    } else {
-        None
-    };
+        if remote_check.status.success() {
+            return Ok(BranchStatus {
+                local_exists,
+                remote_exists: true,
+                remote_ref: Some(format!("{}/{}", remote_name, branch_name)),
+                remote_name: Some(remote_name),
+            });
+        }
+    }
 
     Ok(BranchStatus {
         local_exists,
-        remote_exists,
-        remote_name: if remote_exists { Some(remote_name.to_string()) } else { None },
-        remote_ref: remote_ref_short,
+        remote_exists: false,
+        remote_name: None,
+        remote_ref: None,
     })
 }
 
+/// Remote used when none is picked: "origin" if it exists (or nothing could be listed),
+/// otherwise the first remote by name
+pub fn default_remote(repo_path: &str) -> String {
+    pick_default_remote(&get_git_remotes(repo_path))
+}
+
+fn pick_default_remote(remotes: &std::collections::HashSet<String>) -> String {
+    if remotes.is_empty() || remotes.contains("origin") {
+        return "origin".to_string();
+    }
+    remotes.iter().min().cloned().unwrap_or_else(|| "origin".to_string())
+}
+
+/// The given remote if it exists, or the default remote
+pub fn resolve_remote(repo_path: &str, remote: Option<&str>) -> Result<String, JjError> {
+    let Some(remote) = remote.map(str::trim).filter(|r| !r.is_empty()) else {
+        return Ok(default_remote(repo_path));
+    };
+    let remotes = get_git_remotes(repo_path);
+    // An empty list means the remotes couldn't be read; let jj report the problem
+    if !remotes.is_empty() && !remotes.contains(remote) {
+        return Err(JjError::IoError(format!("Unknown remote '{}'", remote)));
+    }
+    Ok(remote.to_string())
+}
+
 /// Get list of git remotes in the repository with graceful fallback
-/// Uses jj git remote list which returns format: "<remote_name> <remote_url>"
+/// Uses jj git remote list which returns format: "<remote_name> <remote_url>", and
+/// `git remote` for plain git repositories
 pub fn get_git_remotes(repo_path: &str) -> std::collections::HashSet<String> {
-    let output = match command_for("jj")
-        .current_dir(repo_path)
+    let output = match jj_command(repo_path)
         .args(["git", "remote", "list"])
         .output()
     {
         Ok(output) => output,
         Err(e) => {
             eprintln!("Warning: Failed to execute jj git remote list: {}", e);
-            return std::collections::HashSet::new();
+            return git_remote_names(repo_path);
         }
     };
 
     if !output.status.success() {
         eprintln!("Warning: jj git remote list failed: {}", String::from_utf8_lossy(&output.stderr));
-        return std::collections::HashSet::new();
+        return git_remote_names(repo_path);
     }
 
     // Parse output: "origin git@github.com:user/repo.git"
@@ -1803,6 +2878,23 @@ pub fn get_git_remotes(repo_path: &str) -> std::collections::HashSet<String> {
         .collect()
 }
 
+fn git_remote_names(repo_path: &str) -> std::collections::HashSet<String> {
+    command_for("git")
+        .current_dir(repo_path)
+        .arg("remote")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// Information about a jj bookmark/branch
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JjBranch {
@@ -1813,8 +2905,7 @@ pub struct JjBranch {
 /// Get list of branches in the repository
 /// Uses jj bookmark list to get local bookmarks
 pub fn get_branches(repo_path: &str) -> Result<Vec<JjBranch>, JjError> {
-    let output = command_for("jj")
-        .current_dir(repo_path)
+    let output = jj_command(repo_path)
         .args(["bookmark", "list"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -1830,37 +2921,294 @@ pub fn get_branches(repo_path: &str) -> Result<Vec<JjBranch>, JjError> {
     let stdout = String::from_utf8_lossy(&output.stdout);
     let mut branches = Vec::new();
 
-    // Parse jj bookmark list output
-    // Format is typically: "branch_name: commit_id"
-    // or "branch_name (deleted)"
-    // Current bookmark might be marked with * or similar
-    for line in stdout.lines() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
+    // Parse jj bookmark list output
+    // Format is typically: "branch_name: commit_id"
+    // or "branch_name (deleted)"
+    // Current bookmark might be marked with * or similar
+    for line in stdout.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // Check if this is the current bookmark (marked with *)
+        let is_current = line.starts_with('*');
+        let line = if is_current {
+            line.trim_start_matches('*').trim()
+        } else {
+            line
+        };
+
+        // Extract branch name (everything before the colon)
+        if let Some(colon_pos) = line.find(':') {
+            let branch_name = line[..colon_pos].trim().to_string();
+            if !branch_name.is_empty() {
+                branches.push(JjBranch {
+                    name: branch_name,
+                    is_current,
+                });
+            }
+        }
+    }
+
+    Ok(branches)
+}
+
+/// A recent change offered as a revset completion
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JjRevsetChange {
+    /// Shortest unique change id prefix
+    pub change_id: String,
+    pub commit_id: String,
+    pub description: String,
+}
+
+/// Symbols usable in revset/branch inputs, gathered in one call for autocomplete
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JjRevsetSymbols {
+    pub bookmarks: Vec<String>,
+    /// Remote bookmarks in jj format (name@remote)
+    pub remote_bookmarks: Vec<String>,
+    pub tags: Vec<String>,
+    /// Workspace working-copy symbols (name@)
+    pub workspaces: Vec<String>,
+    pub remotes: Vec<String>,
+    pub recent_changes: Vec<JjRevsetChange>,
+}
+
+/// Number of recent changes included in revset symbols
+const REVSET_RECENT_CHANGES: usize = 30;
+
+/// Run a jj command for symbol lookup, returning stdout or None on failure
+fn jj_symbol_query(repo_path: &str, args: &[&str]) -> Option<String> {
+    match jj_command(repo_path).args(args).output() {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        Ok(output) => {
+            eprintln!("Warning: jj {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+            None
+        }
+        Err(e) => {
+            eprintln!("Warning: Failed to execute jj {}: {}", args.join(" "), e);
+            None
+        }
+    }
+}
+
+/// Parse "name\tremote" lines from `jj bookmark list --all-remotes` into
+/// (local bookmarks, remote bookmarks in name@remote format).
+/// The internal "git" remote is skipped.
+fn parse_bookmark_symbols(output: &str) -> (Vec<String>, Vec<String>) {
+    let mut local = Vec::new();
+    let mut remote = Vec::new();
+
+    for line in output.lines() {
+        let mut parts = line.splitn(2, '\t');
+        let name = parts.next().unwrap_or("").trim();
+        let remote_name = parts.next().unwrap_or("").trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        if remote_name.is_empty() {
+            if !local.iter().any(|b| b == name) {
+                local.push(name.to_string());
+            }
+        } else if remote_name != "git" {
+            let symbol = format!("{}@{}", name, remote_name);
+            if !remote.contains(&symbol) {
+                remote.push(symbol);
+            }
+        }
+    }
+
+    (local, remote)
+}
+
+/// Collect bookmarks, tags, workspaces, remotes and recent change ids for revset autocomplete.
+/// Each source is queried independently so one failing lookup doesn't hide the rest.
+pub fn get_revset_symbols(repo_path: &str) -> Result<JjRevsetSymbols, JjError> {
+    if !is_jj_workspace(repo_path) {
+        return Err(JjError::WorkspaceNotFound(repo_path.to_string()));
+    }
+
+    let (bookmarks, remote_bookmarks) = jj_symbol_query(
+        repo_path,
+        &["bookmark", "list", "--all-remotes", "-T", "name ++ \"\\t\" ++ remote ++ \"\\n\""],
+    )
+    .map(|out| parse_bookmark_symbols(&out))
+    .unwrap_or_default();
+
+    let tags = jj_symbol_query(repo_path, &["tag", "list", "-T", "name ++ \"\\n\""])
+        .map(|out| {
+            out.lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Default output is "name: <change> <commit> <description>"
+    let workspaces = jj_symbol_query(repo_path, &["workspace", "list"])
+        .map(|out| {
+            out.lines()
+                .filter_map(|l| l.split_once(':'))
+                .map(|(name, _)| format!("{}@", name.trim()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut remotes: Vec<String> = get_git_remotes(repo_path).into_iter().collect();
+    remotes.sort();
+
+    let revset = format!("latest(all() ~ root(), {})", REVSET_RECENT_CHANGES);
+    let template = concat!(
+        "change_id.shortest(8) ++ \"\\t\" ++ ",
+        "commit_id.short(12) ++ \"\\t\" ++ ",
+        "description.first_line() ++ \"\\n\""
+    );
+    let recent_changes = jj_symbol_query(repo_path, &["log", "-r", &revset, "--no-graph", "-T", template])
+        .map(|out| {
+            out.lines()
+                .filter_map(|line| {
+                    let parts: Vec<&str> = line.splitn(3, '\t').collect();
+                    if parts.len() < 2 || parts[0].is_empty() {
+                        return None;
+                    }
+                    Some(JjRevsetChange {
+                        change_id: parts[0].to_string(),
+                        commit_id: parts[1].to_string(),
+                        description: parts.get(2).unwrap_or(&"").to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(JjRevsetSymbols {
+        bookmarks,
+        remote_bookmarks,
+        tags,
+        workspaces,
+        remotes,
+        recent_changes,
+    })
+}
+
+/// A revision matched by search_revisions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JjRevisionMatch {
+    pub change_id: String,
+    pub commit_id: String,
+    pub description: String,
+    pub bookmarks: Vec<String>,
+    /// Author time, RFC3339 UTC
+    pub timestamp: String,
+    /// Author time in epoch milliseconds, for ordering
+    pub sort_key: i64,
+    /// What matched: "change_id", "commit_id", "bookmark" or "description"
+    pub match_kind: String,
+}
+
+/// Quote a user string as a jj revset string literal
+fn revset_string_literal(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Classify why a revision matched the query; lower ranks sort first
+fn classify_revision_match(query: &str, revision: &JjRevisionMatch) -> (u8, &'static str) {
+    let query_lower = query.to_lowercase();
+    if revision.change_id.starts_with(&query_lower) {
+        (0, "change_id")
+    } else if revision.commit_id.starts_with(&query_lower) {
+        (1, "commit_id")
+    } else if revision
+        .bookmarks
+        .iter()
+        .any(|b| b.to_lowercase().contains(&query_lower))
+    {
+        (2, "bookmark")
+    } else {
+        (3, "description")
+    }
+}
+
+/// Search revisions by change-id prefix, commit hash prefix, bookmark name or message substring.
+/// Id matches are listed first, then bookmarks, then description matches (newest first).
+pub fn search_revisions(repo_path: &str, query: &str, limit: usize) -> Result<Vec<JjRevisionMatch>, JjError> {
+    let query = query.trim();
+    if query.is_empty() || query.contains('\0') {
+        return Ok(Vec::new());
+    }
+
+    let literal = revset_string_literal(query);
+    let text_revset = format!(
+        "description(substring-i:{lit}) | bookmarks(substring-i:{lit})",
+        lit = literal
+    );
+    let looks_like_id = query.chars().all(|c| c.is_ascii_alphanumeric());
+
+    let template = concat!(
+        "change_id.short(12) ++ \"\\t\" ++ ",
+        "commit_id.short(12) ++ \"\\t\" ++ ",
+        "description.first_line() ++ \"\\t\" ++ ",
+        "bookmarks.map(|b| b.name()).join(\",\") ++ \"\\t\" ++ ",
+        "author.timestamp().utc().format(\"%Y-%m-%dT%H:%M:%S%.3fZ\") ++ \"\\n\""
+    );
+
+    let run_search = |revset: &str| -> Result<String, JjError> {
+        let revset = format!("latest({}, {})", revset, limit.max(1));
+        let output = jj_command(repo_path)
+            .args(["log", "-r", &revset, "--no-graph", "--ignore-working-copy", "-T", template])
+            .output()
+            .map_err(|e| JjError::IoError(e.to_string()))?;
+
+        if !output.status.success() {
+            return Err(command_error(&output.stderr));
         }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    };
 
-        // Check if this is the current bookmark (marked with *)
-        let is_current = line.starts_with('*');
-        let line = if is_current {
-            line.trim_start_matches('*').trim()
-        } else {
-            line
-        };
+    // An ambiguous id prefix makes the whole revset fail, so retry with text matches only
+    let stdout = if looks_like_id {
+        match run_search(&format!("present({}) | {}", literal, text_revset)) {
+            Ok(out) => out,
+            Err(_) => run_search(&text_revset)?,
+        }
+    } else {
+        run_search(&text_revset)?
+    };
 
-        // Extract branch name (everything before the colon)
-        if let Some(colon_pos) = line.find(':') {
-            let branch_name = line[..colon_pos].trim().to_string();
-            if !branch_name.is_empty() {
-                branches.push(JjBranch {
-                    name: branch_name,
-                    is_current,
-                });
+    let mut matches: Vec<(u8, JjRevisionMatch)> = stdout
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('\t').collect();
+            if parts.len() < 5 {
+                return None;
             }
-        }
-    }
+            let mut revision = JjRevisionMatch {
+                change_id: parts[0].to_string(),
+                commit_id: parts[1].to_string(),
+                description: parts[2].to_string(),
+                bookmarks: parts[3]
+                    .split(',')
+                    .filter(|b| !b.is_empty())
+                    .map(|b| b.to_string())
+                    .collect(),
+                timestamp: timestamps::normalize(parts[4]),
+                sort_key: timestamps::sort_key(parts[4]),
+                match_kind: String::new(),
+            };
+            let (rank, kind) = classify_revision_match(query, &revision);
+            revision.match_kind = kind.to_string();
+            Some((rank, revision))
+        })
+        .collect();
 
-    Ok(branches)
+    // Stable sort keeps jj's newest-first order within each rank
+    matches.sort_by_key(|(rank, _)| *rank);
+    Ok(matches.into_iter().map(|(_, r)| r).take(limit).collect())
 }
 
 /// Get commit log from fork point to HEAD for a workspace
@@ -1934,15 +3282,14 @@ pub fn jj_get_log(workspace_path: &str, target_branch: &str, is_home_repo: Optio
         "change_id.short(12) ++ \"\\t\" ++ ",
         "if(description, description.first_line(), \"(no description)\") ++ \"\\t\" ++ ",
         "author.name() ++ \"\\t\" ++ ",
-        "author.timestamp() ++ \"\\t\" ++ ",
+        "author.timestamp().utc().format(\"%Y-%m-%dT%H:%M:%S%.3fZ\") ++ \"\\t\" ++ ",
         "parents.map(|p| p.commit_id().short(12)).join(\",\") ++ \"\\t\" ++ ",
         "if(working_copies, \"true\", \"false\") ++ \"\\t\" ++ ",
         "bookmarks.map(|b| b.name()).join(\",\") ++ \"\\t\" ++ ",
         "diff.stat() ++ \"\\n\""
     );
 
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args([
             "log",
             "-r",
@@ -1955,9 +3302,7 @@ pub fn jj_get_log(workspace_path: &str, target_branch: &str, is_home_repo: Optio
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -1978,7 +3323,7 @@ pub fn jj_get_log(workspace_path: &str, target_branch: &str, is_home_repo: Optio
         let change_id = parts[1].to_string();
         let description = parts[2].to_string();
         let author_name = parts[3].to_string();
-        let timestamp = parts[4].to_string();
+        let timestamp = timestamps::normalize(parts[4]);
         let parent_ids_str = parts[5];
         let is_working_copy = parts[6] == "true";
         let bookmarks_str = parts[7];
@@ -2007,6 +3352,7 @@ pub fn jj_get_log(workspace_path: &str, target_branch: &str, is_home_repo: Optio
             change_id,
             description,
             author_name,
+            sort_key: timestamps::sort_key(&timestamp),
             timestamp,
             parent_ids,
             is_working_copy,
@@ -2043,23 +3389,20 @@ pub fn jj_get_commits_ahead(
         "change_id.short(12) ++ \"\\t\" ++ ",
         "if(description, description.first_line(), \"(no description)\") ++ \"\\t\" ++ ",
         "author.name() ++ \"\\t\" ++ ",
-        "author.timestamp() ++ \"\\t\" ++ ",
+        "author.timestamp().utc().format(\"%Y-%m-%dT%H:%M:%S%.3fZ\") ++ \"\\t\" ++ ",
         "parents.map(|p| p.commit_id().short(12)).join(\",\") ++ \"\\t\" ++ ",
         "if(working_copies, \"true\", \"false\") ++ \"\\t\" ++ ",
         "bookmarks.map(|b| b.name()).join(\",\") ++ \"\\t\" ++ ",
         "diff.stat() ++ \"\\n\""
     );
 
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = jj_command(workspace_path)
         .args(["log", "-r", &revset, "--no-graph", "-T", template])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+        return Err(command_error(&output.stderr));
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -2080,7 +3423,7 @@ pub fn jj_get_commits_ahead(
         let change_id = parts[1].to_string();
         let description = parts[2].to_string();
         let author_name = parts[3].to_string();
-        let timestamp = parts[4].to_string();
+        let timestamp = timestamps::normalize(parts[4]);
         let parent_ids_str = parts[5];
         let is_working_copy = parts[6] == "true";
         let bookmarks_str = parts[7];
@@ -2107,6 +3450,7 @@ pub fn jj_get_commits_ahead(
             change_id,
             description,
             author_name,
+            sort_key: timestamps::sort_key(&timestamp),
             timestamp,
             parent_ids,
             is_working_copy,
@@ -2121,11 +3465,48 @@ pub fn jj_get_commits_ahead(
     Ok(JjCommitsAhead {
         commits,
         total_count,
+        accurate: true,
     })
 }
 
+/// Expand a rename or copy as jj prints it, "src/{old.rs => new.rs}", or in the plain
+/// "old -> new" form, into the new and old paths
+fn parse_rename(path: &str) -> Option<(String, String)> {
+    if let (Some(open), Some(close)) = (path.find('{'), path.rfind('}')) {
+        if open < close {
+            if let Some((old, new)) = path[open + 1..close].split_once(" => ") {
+                let (prefix, suffix) = (&path[..open], &path[close + 1..]);
+                // "src/{ => nested}/a.rs" leaves a doubled or leading separator
+                let join = |middle: &str| {
+                    format!("{}{}{}", prefix, middle, suffix)
+                        .replace("//", "/")
+                        .trim_start_matches('/')
+                        .to_string()
+                };
+                return Some((join(new), join(old)));
+            }
+        }
+    }
+    let (old, new) = path
+        .split_once(" => ")
+        .or_else(|| path.split_once(" -> "))?;
+    Some((new.to_string(), old.to_string()))
+}
+
+/// Path and previous path of a status or summary entry; only renames and copies have
+/// the latter
+fn split_summary_path(status: &str, path: &str) -> (String, Option<String>) {
+    if status == "R" || status == "C" {
+        if let Some((new, old)) = parse_rename(path) {
+            return (new, Some(old));
+        }
+    }
+    (path.to_string(), None)
+}
+
 /// Parse diff summary output from jj diff --summary
-/// Format: "M file.txt", "A new.txt", "D removed.txt"
+/// Format: "M file.txt", "A new.txt", "D removed.txt", "R src/{old.rs => new.rs}",
+/// "C src/{a.rs => b.rs}"
 fn parse_diff_summary(summary: &str) -> Result<Vec<JjFileChange>, JjError> {
     let mut files = Vec::new();
 
@@ -2142,12 +3523,15 @@ fn parse_diff_summary(summary: &str) -> Result<Vec<JjFileChange>, JjError> {
         }
 
         let status = parts[0].to_string();
-        let path = parts[1].to_string();
+        let (path, previous_path) = split_summary_path(&status, parts[1]);
 
         files.push(JjFileChange {
             path,
             status,
-            previous_path: None,
+            previous_path,
+            submodule: None,
+            index_status: None,
+            worktree_status: None,
         });
     }
 
@@ -2163,11 +3547,65 @@ fn extract_conflicted_files_from_summary(files: Vec<JjFileChange>) -> Vec<String
         .collect()
 }
 
+/// Split a multi-file `diff --git` into sections keyed by path. Each section is keyed by
+/// both its old and new path, so renamed and deleted files are found either way.
+fn split_git_diff_by_file(diff: &str) -> HashMap<&str, String> {
+    // (paths, lines) of each file, in diff order
+    let mut files: Vec<(Vec<&str>, Vec<&str>)> = Vec::new();
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            // "a/<old> b/<new>"; split at " b/" so paths with spaces survive
+            let paths = match header.split_once(" b/") {
+                Some((old, new)) => vec![old.strip_prefix("a/").unwrap_or(old), new],
+                None => Vec::new(),
+            };
+            files.push((paths, Vec::new()));
+        }
+        if let Some((_, lines)) = files.last_mut() {
+            lines.push(line);
+        }
+    }
+
+    let mut sections = HashMap::new();
+    for (paths, lines) in files {
+        let section = lines.join("\n");
+        for path in paths {
+            sections.insert(path, section.clone());
+        }
+    }
+    sections
+}
+
+/// Pair each file of a `--summary` with its hunks from one whole-range `diff --git`.
+/// Files missing from the diff (e.g. binary-only changes) get no hunks.
+fn build_revision_diff(files: Vec<JjFileChange>, diff: &str) -> Result<JjRevisionDiff, JjError> {
+    let sections = split_git_diff_by_file(diff);
+    let mut hunks_by_file = Vec::with_capacity(files.len());
+    for file in &files {
+        let hunks = match sections.get(file.path.as_str()) {
+            Some(section) => parse_git_diff_hunks(section)?,
+            None => Vec::new(),
+        };
+        hunks_by_file.push(JjFileDiff {
+            path: file.path.clone(),
+            hunks,
+        });
+    }
+    Ok(JjRevisionDiff {
+        files,
+        hunks_by_file,
+    })
+}
+
 /// Get combined diff of all changes between target branch and workspace HEAD
-/// Uses: jj diff --from target_branch --to @- --git
+/// Uses: jj diff --from target_branch --to @- --git, once for the whole range
+///
+/// Only the whitespace setting of `detection` applies: jj has no rename threshold or
+/// copy detection options and reports renames from its own copy tracking.
 pub fn jj_get_merge_diff(
     workspace_path: &str,
     target_branch: &str,
+    detection: &DiffDetection,
 ) -> Result<JjRevisionDiff, JjError> {
     // Validate target_branch to prevent injection
     if target_branch.starts_with('-') || target_branch.contains('\0') || target_branch.is_empty() {
@@ -2175,56 +3613,45 @@ pub fn jj_get_merge_diff(
     }
 
     // First get list of changed files
-    let status_output = command_for("jj")
-        .current_dir(workspace_path)
+    let status_output = jj_command(workspace_path)
         .args(["diff", "--from", target_branch, "--to", "@-", "--summary"])
         .output()
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     if !status_output.status.success() {
-        return Err(JjError::IoError(
-            String::from_utf8_lossy(&status_output.stderr).to_string(),
-        ));
+        return Err(command_error(&status_output.stderr));
     }
 
     let summary = String::from_utf8_lossy(&status_output.stdout);
     let files = parse_diff_summary(&summary)?;
+    if files.is_empty() {
+        return Ok(JjRevisionDiff {
+            files,
+            hunks_by_file: Vec::new(),
+        });
+    }
 
-    // For each file, get the hunks
-    let mut hunks_by_file = Vec::new();
-    for file in &files {
-        let diff_output = command_for("jj")
-            .current_dir(workspace_path)
-            .args([
-                "diff",
-                "--from", target_branch,
-                "--to", "@-",
-                "--git",
-                "--no-pager",
-                "--",
-                &file.path,
-            ])
-            .output()
-            .map_err(|e| JjError::IoError(e.to_string()))?;
-
-        if !diff_output.status.success() {
-            // If diff fails for a file, skip it but continue with others
-            continue;
-        }
-
-        let diff_text = String::from_utf8_lossy(&diff_output.stdout);
-        let hunks = parse_git_diff_hunks(&diff_text)?;
+    // One diff for the whole range; a process per file took seconds on large merges
+    let mut diff_command = jj_command(workspace_path);
+    diff_command.args([
+        "diff",
+        "--from", target_branch,
+        "--to", "@-",
+        "--git",
+        "--no-pager",
+    ]);
+    if detection.ignore_whitespace {
+        diff_command.arg("--ignore-all-space");
+    }
+    let diff_output = diff_command
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
 
-        hunks_by_file.push(JjFileDiff {
-            path: file.path.clone(),
-            hunks,
-        });
+    if !diff_output.status.success() {
+        return Err(command_error(&diff_output.stderr));
     }
 
-    Ok(JjRevisionDiff {
-        files,
-        hunks_by_file,
-    })
+    build_revision_diff(files, &String::from_utf8_lossy(&diff_output.stdout))
 }
 
 /// Create a merge commit using jj new
@@ -2234,7 +3661,7 @@ pub fn jj_get_merge_diff(
 /// 2. jj new @ - create new working copy on top
 /// 3. jj bookmark set target_branch -r @- - move target_branch to merge commit
 /// This is executed in the context of the workspace directory, @ refers to workspace HEAD
-pub fn jj_create_merge_commit(
+pub async fn jj_create_merge_commit(
     workspace_path: &str,
     workspace_branch: &str,
     target_branch: &str,
@@ -2258,10 +3685,10 @@ pub fn jj_create_merge_commit(
 
     // Step 1: Create merge commit with workspace_branch and target_branch+ as parents
     let target_revset = format!("{}+", target_branch);
-    let output = command_for("jj")
-        .current_dir(workspace_path)
+    let output = async_jj_command(workspace_path, async_process::LOCAL_TIMEOUT)
         .args(["new", workspace_branch, &target_revset, "-m", message])
         .output()
+        .await
         .map_err(|e| JjError::IoError(e.to_string()))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -2278,8 +3705,7 @@ pub fn jj_create_merge_commit(
 
     let merge_commit_id = if output.status.success() {
         // Step 2: Create new working copy on top of merge
-        let new_wc_output = command_for("jj")
-            .current_dir(workspace_path)
+        let new_wc_output = jj_command(workspace_path)
             .args(["new", "@"])
             .output()
             .map_err(|e| JjError::IoError(e.to_string()))?;
@@ -2295,8 +3721,7 @@ pub fn jj_create_merge_commit(
         }
 
         // Get merge commit ID (now at @-)
-        command_for("jj")
-            .current_dir(workspace_path)
+        jj_command(workspace_path)
             .args(["log", "-r", "@-", "--no-graph", "-T", "commit_id.short(12)"])
             .output()
             .ok()
@@ -2322,6 +3747,95 @@ pub fn jj_create_merge_commit(
     })
 }
 
+/// An entry in jj's operation log
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JjOperation {
+    pub id: String,
+    pub description: String,
+    /// When the operation finished, RFC3339 UTC
+    pub time: String,
+    /// user@host that ran the operation
+    pub user: String,
+    /// The operation the repo is currently at
+    pub is_current: bool,
+}
+
+/// Fields are tab-separated with the description last, since it is free text
+const OP_LOG_TEMPLATE: &str = concat!(
+    "id ++ \"\\t\" ++ ",
+    "if(current_operation, \"1\", \"0\") ++ \"\\t\" ++ ",
+    "time.end().utc().format(\"%Y-%m-%dT%H:%M:%S%.3fZ\") ++ \"\\t\" ++ ",
+    "user ++ \"\\t\" ++ ",
+    "description.first_line() ++ \"\\n\""
+);
+
+fn parse_op_log(output: &str) -> Vec<JjOperation> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(5, '\t');
+            let id = parts.next()?.to_string();
+            let is_current = parts.next()? == "1";
+            let time = timestamps::normalize(parts.next()?);
+            let user = parts.next()?.to_string();
+            let description = parts.next()?.to_string();
+            Some(JjOperation { id, description, time, user, is_current })
+        })
+        .collect()
+}
+
+/// Most recent operations first
+/// Uses: jj op log --no-graph --limit <limit>
+pub fn jj_op_log(workspace_path: &str, limit: usize) -> Result<Vec<JjOperation>, JjError> {
+    let limit = limit.max(1).to_string();
+    let output = jj_command(workspace_path)
+        .args(["op", "log", "--no-graph", "--ignore-working-copy", "--limit", &limit, "-T", OP_LOG_TEMPLATE])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    Ok(parse_op_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Undo the most recent operation. Repeating it steps further back.
+/// Uses: jj undo
+pub fn jj_op_undo(workspace_path: &str) -> Result<String, JjError> {
+    let output = jj_command(workspace_path)
+        .args(["undo"])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    // jj reports what it undid on stderr
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+/// Restore the repo to the state after `op_id`, recorded as a new operation so it can
+/// itself be undone
+/// Uses: jj op restore <op_id>
+pub fn jj_op_restore(workspace_path: &str, op_id: &str) -> Result<String, JjError> {
+    if op_id.is_empty() || !op_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(JjError::IoError(format!("Invalid operation id: {}", op_id)));
+    }
+
+    let output = jj_command(workspace_path)
+        .args(["op", "restore", op_id])
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(command_error(&output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2447,6 +3961,172 @@ mod tests {
         assert_eq!(files[2].path, "src/old.ts");
     }
 
+    #[test]
+    fn test_user_settings_apply_repo_config() {
+        let (_temp, repo_path) = setup_test_dir();
+        let repo_config = repo_path.join(".jj").join("repo");
+        fs::create_dir_all(&repo_config).unwrap();
+        fs::write(
+            repo_config.join("config.toml"),
+            "[user]\nemail = \"repo@example.com\"\n",
+        )
+        .unwrap();
+
+        let settings = create_user_settings(repo_path.to_str().unwrap()).unwrap();
+        assert_eq!(settings.user_email(), "repo@example.com");
+    }
+
+    #[test]
+    fn test_parse_renames() {
+        let summary = "R src/{old.rs => new.rs}\nR {a.txt => docs/a.txt}\nR lib/{ => nested}/mod.rs\nR before.md -> after.md\nM src/{braces}.rs";
+        let files = parse_diff_summary(summary).unwrap();
+        let renames: Vec<(&str, &str, Option<&str>)> = files
+            .iter()
+            .map(|f| (f.status.as_str(), f.path.as_str(), f.previous_path.as_deref()))
+            .collect();
+        assert_eq!(
+            renames,
+            vec![
+                ("R", "src/new.rs", Some("src/old.rs")),
+                ("R", "docs/a.txt", Some("a.txt")),
+                ("R", "lib/nested/mod.rs", Some("lib/mod.rs")),
+                ("R", "after.md", Some("before.md")),
+                ("M", "src/{braces}.rs", None),
+            ]
+        );
+
+        let status = "Working copy changes:\nR src/{util.rs => helpers.rs}\nM src/main.rs\nC {a.txt => b.txt}\nWorking copy  (@) : abc";
+        let changes = parse_jj_status(status).unwrap();
+        assert_eq!(changes[0].status, "R");
+        assert_eq!(changes[0].path, "src/helpers.rs");
+        assert_eq!(changes[0].previous_path.as_deref(), Some("src/util.rs"));
+        assert_eq!(changes[1].previous_path, None);
+        assert_eq!(changes[2].status, "C");
+        assert_eq!(changes[2].path, "b.txt");
+        assert_eq!(changes[2].previous_path.as_deref(), Some("a.txt"));
+    }
+
+    #[test]
+    fn test_parse_bookmark_symbols() {
+        let output = "main\t\nmain\tgit\nmain\torigin\nfeature\t\nfeature\tupstream\n";
+        let (local, remote) = parse_bookmark_symbols(output);
+
+        assert_eq!(local, vec!["main", "feature"]);
+        assert_eq!(remote, vec!["main@origin", "feature@upstream"]);
+    }
+
+    #[test]
+    fn test_revset_string_literal_escapes() {
+        assert_eq!(revset_string_literal("fix"), "\"fix\"");
+        assert_eq!(revset_string_literal("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(revset_string_literal("a\\b"), "\"a\\\\b\"");
+    }
+
+    #[test]
+    fn test_classify_revision_match() {
+        let revision = JjRevisionMatch {
+            change_id: "kxyzabcd1234".to_string(),
+            commit_id: "abc123def456".to_string(),
+            description: "Fix login".to_string(),
+            bookmarks: vec!["feature-login".to_string()],
+            timestamp: String::new(),
+            sort_key: 0,
+            match_kind: String::new(),
+        };
+
+        assert_eq!(classify_revision_match("kxy", &revision).1, "change_id");
+        assert_eq!(classify_revision_match("ABC1", &revision).1, "commit_id");
+        assert_eq!(classify_revision_match("Login", &revision).1, "bookmark");
+        assert_eq!(classify_revision_match("fix", &revision).1, "description");
+    }
+
+    #[test]
+    fn test_apply_selected_hunks_keeps_unselected_changes_out() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let hunks = vec![
+            JjDiffHunk {
+                id: "hunk-0".to_string(),
+                header: "@@ -1,3 +1,3 @@".to_string(),
+                lines: vec![" a".to_string(), "-b".to_string(), "+B".to_string(), " c".to_string()],
+                patch: String::new(),
+                line_pairs: Vec::new(),
+                summary: String::new(),
+                content_hash: String::new(),
+            },
+            JjDiffHunk {
+                id: "hunk-1".to_string(),
+                header: "@@ -8,3 +8,4 @@".to_string(),
+                lines: vec![" h".to_string(), " i".to_string(), "+new".to_string(), " j".to_string()],
+                patch: String::new(),
+                line_pairs: Vec::new(),
+                summary: String::new(),
+                content_hash: String::new(),
+            },
+        ];
+
+        let only_second: std::collections::HashSet<&str> = ["hunk-1"].into_iter().collect();
+        let content = apply_selected_hunks(old, true, &hunks, &only_second).unwrap();
+        assert_eq!(content, "a\nb\nc\nd\ne\nf\ng\nh\ni\nnew\nj\n");
+
+        let only_first: std::collections::HashSet<&str> = ["hunk-0"].into_iter().collect();
+        let content = apply_selected_hunks(old, true, &hunks, &only_first).unwrap();
+        assert_eq!(content, "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\n");
+    }
+
+    #[test]
+    fn test_parse_git_diff_hunks_pairs_and_summarizes() {
+        let diff = "@@ -10,6 +10,7 @@ pub fn foo(bar: u32) -> u32 {\n let a = 1;\n-let b = 2;\n-let c = 3;\n+let b = 20;\n+let c = 30;\n+let d = 40;\n let e = 5;\n-let f = 6;\n@@ -40,2 +41,2 @@\n-x\n\\ No newline at end of file\n+y\n";
+        let hunks = parse_git_diff_hunks(diff).unwrap();
+        assert_eq!(hunks.len(), 2);
+
+        assert_eq!(
+            hunks[0].line_pairs,
+            vec![
+                DiffLinePair { deletion: 1, addition: 3 },
+                DiffLinePair { deletion: 2, addition: 4 },
+            ]
+        );
+        assert_eq!(hunks[0].summary, "3 added, 3 removed in function foo");
+
+        assert_eq!(hunks[1].line_pairs, vec![DiffLinePair { deletion: 0, addition: 2 }]);
+        assert_eq!(hunks[1].summary, "1 added, 1 removed");
+        assert_eq!(hunk_scope("@@ -1 +1 @@ <template>").as_deref(), Some("\"<template>\""));
+    }
+
+    #[test]
+    fn test_hunk_content_hash_ignores_line_numbers() {
+        let before = parse_git_diff_hunks("@@ -3,2 +3,2 @@\n a\n-b\n+B\n").unwrap();
+        let shifted = parse_git_diff_hunks("@@ -30,2 +31,2 @@ fn x()\n a\n-b\n+B\n").unwrap();
+        let edited = parse_git_diff_hunks("@@ -3,2 +3,2 @@\n a\n-b\n+C\n").unwrap();
+        assert_eq!(before[0].content_hash.len(), 64);
+        assert_eq!(before[0].content_hash, shifted[0].content_hash);
+        assert_ne!(before[0].content_hash, edited[0].content_hash);
+    }
+
+    #[test]
+    fn test_parse_hunk_old_range() {
+        assert_eq!(parse_hunk_old_range("@@ -12,4 +12,6 @@ fn main()"), Some((12, 4)));
+        assert_eq!(parse_hunk_old_range("@@ -3 +3,2 @@"), Some((3, 1)));
+        assert_eq!(parse_hunk_old_range("@@ -0,0 +1,2 @@"), Some((0, 0)));
+        assert_eq!(parse_hunk_old_range("not a header"), None);
+    }
+
+    #[test]
+    fn test_describe_rejects_option_like_revisions() {
+        for revision in ["", "--help", "@-\0"] {
+            assert!(jj_describe("/nonexistent", revision, "msg").is_err());
+        }
+        assert!(validate_revision("main@origin").is_ok());
+    }
+
+    #[test]
+    fn test_parse_diff_stat_totals() {
+        let stat = "src/a.rs | 4 +++-\nb.txt    | 1 -\n2 files changed, 3 insertions(+), 2 deletions(-)\n";
+        assert_eq!(parse_diff_stat_totals(stat), (3, 2));
+        assert_eq!(parse_diff_stat_totals("1 file changed, 1 deletion(-)\n"), (0, 1));
+        assert_eq!(parse_diff_stat_totals(""), (0, 0));
+    }
+
     #[test]
     fn test_parse_diff_summary_empty() {
         let summary = "";
@@ -2460,6 +4140,7 @@ mod tests {
         let result = JjCommitsAhead {
             commits: vec![],
             total_count: 0,
+            accurate: true,
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("total_count"));
@@ -2526,8 +4207,7 @@ mod tests {
             .unwrap();
 
         // Initialize jj in the repo
-        let jj_init = command_for("jj")
-            .current_dir(&repo_path)
+        let jj_init = jj_command(&repo_path)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -2594,7 +4274,7 @@ mod tests {
 
         // Try to commit - THIS SHOULD CURRENTLY FAIL with "Git is not checked out to a branch"
         eprintln!("Attempting to commit...");
-        let commit_result = jj_commit(workspace_path_str, "Test commit");
+        let commit_result = jj_commit(workspace_path_str, "Test commit", None);
 
         eprintln!("Commit result: {:?}", commit_result);
 
@@ -2685,21 +4365,33 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
                 path: "src/file1.ts".to_string(),
                 status: "M".to_string(),
                 previous_path: None,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
             },
             JjFileChange {
                 path: "src/conflict.ts".to_string(),
                 status: "C".to_string(),
                 previous_path: None,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
             },
             JjFileChange {
                 path: "src/another_conflict.rs".to_string(),
                 status: "C".to_string(),
                 previous_path: None,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
             },
             JjFileChange {
                 path: "src/added.ts".to_string(),
                 status: "A".to_string(),
                 previous_path: None,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
             },
         ];
 
@@ -2717,11 +4409,17 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
                 path: "src/file1.ts".to_string(),
                 status: "M".to_string(),
                 previous_path: None,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
             },
             JjFileChange {
                 path: "src/added.ts".to_string(),
                 status: "A".to_string(),
                 previous_path: None,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
             },
         ];
 
@@ -2812,8 +4510,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         let local_repo_str = local_repo.to_str().unwrap();
 
         // Initialize jj in the local repo
-        let jj_init = command_for("jj")
-            .current_dir(&local_repo)
+        let jj_init = jj_command(&local_repo)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -2830,7 +4527,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         }
 
         // Fetch remote branches
-        let fetch_result = jj_git_fetch(local_repo_str);
+        let fetch_result = async_process::block_on(jj_git_fetch(local_repo_str, None));
         if fetch_result.is_err() {
             eprintln!("Skipping test: jj git fetch failed: {:?}", fetch_result);
             return;
@@ -2870,8 +4567,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
 
         // Check that the bookmark is tracked
         // Run: jj bookmark list in the workspace
-        let bookmark_list = command_for("jj")
-            .current_dir(&workspace_path)
+        let bookmark_list = jj_command(&workspace_path)
             .args(["bookmark", "list"])
             .output()
             .unwrap();
@@ -2946,8 +4642,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
             .unwrap();
 
         // Initialize jj
-        let jj_init = command_for("jj")
-            .current_dir(&repo_path)
+        let jj_init = jj_command(&repo_path)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -2965,7 +4660,8 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         // Since there are NO commits on main (main = main), the result should be EMPTY
         let result = jj_get_merge_diff(
             repo_path.to_str().unwrap(),
-            "main"
+            "main",
+            &DiffDetection::default(),
         );
 
         assert!(result.is_ok(), "jj_get_merge_diff should succeed");
@@ -3028,8 +4724,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
             .unwrap();
 
         // Initialize jj
-        let jj_init = command_for("jj")
-            .current_dir(&repo_path)
+        let jj_init = jj_command(&repo_path)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -3043,8 +4738,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         fs::write(repo_path.join("committed.txt"), "committed file").unwrap();
 
         // Commit this file using jj
-        let jj_commit = command_for("jj")
-            .current_dir(&repo_path)
+        let jj_commit = jj_command(&repo_path)
             .args(["new", "-m", "Add committed file"])
             .output();
 
@@ -3059,7 +4753,8 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         // Call jj_get_merge_diff
         let result = jj_get_merge_diff(
             repo_path.to_str().unwrap(),
-            "main"
+            "main",
+            &DiffDetection::default(),
         );
 
         assert!(result.is_ok(), "jj_get_merge_diff should succeed");
@@ -3145,8 +4840,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         let local_repo_str = local_repo.to_str().unwrap();
 
         // Initialize jj in the local repo
-        let jj_init = command_for("jj")
-            .current_dir(&local_repo)
+        let jj_init = jj_command(&local_repo)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -3167,8 +4861,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         fs::create_dir_all(&workspace_path).unwrap();
 
         // Create workspace with jj
-        let workspace_setup = command_for("jj")
-            .current_dir(&local_repo)
+        let workspace_setup = jj_command(&local_repo)
             .args(["workspace", "add", "--name", "test-workspace", "-r", "main"])
             .output()
             .unwrap();
@@ -3181,15 +4874,13 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         let workspace_path_str = workspace_path.to_str().unwrap();
 
         // Set a bookmark and track it
-        let _ = command_for("jj")
-            .current_dir(&workspace_path)
+        let _ = jj_command(&workspace_path)
             .args(["bookmark", "set", "test-branch", "-r", "@", "--allow-backwards"])
             .output()
             .unwrap();
 
         // Track the bookmark
-        let track_result = command_for("jj")
-            .current_dir(&workspace_path)
+        let track_result = jj_command(&workspace_path)
             .args(["bookmark", "track", "test-branch@origin"])
             .output()
             .unwrap();
@@ -3278,8 +4969,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         let local_repo_str = local_repo.to_str().unwrap();
 
         // Initialize jj in the local repo
-        let jj_init = command_for("jj")
-            .current_dir(&local_repo)
+        let jj_init = jj_command(&local_repo)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -3300,8 +4990,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         fs::create_dir_all(&workspace_path).unwrap();
 
         // Create workspace with jj
-        let workspace_setup = command_for("jj")
-            .current_dir(&local_repo)
+        let workspace_setup = jj_command(&local_repo)
             .args(["workspace", "add", "--name", "test-workspace-2", "-r", "main"])
             .output()
             .unwrap();
@@ -3314,8 +5003,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         let workspace_path_str = workspace_path.to_str().unwrap();
 
         // Set a bookmark but DO NOT track it
-        let _ = command_for("jj")
-            .current_dir(&workspace_path)
+        let _ = jj_command(&workspace_path)
             .args(["bookmark", "set", "untracked-branch", "-r", "@", "--allow-backwards"])
             .output()
             .unwrap();
@@ -3423,8 +5111,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         let local_repo_str = local_repo.to_str().unwrap();
 
         // Initialize jj in the local repo
-        let jj_init = command_for("jj")
-            .current_dir(&local_repo)
+        let jj_init = jj_command(&local_repo)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -3464,8 +5151,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         eprintln!("✓ Workspace created: {}", workspace_name);
 
         // Debug: print the bookmark list
-        let debug_list = command_for("jj")
-            .current_dir(&workspace_path)
+        let debug_list = jj_command(&workspace_path)
             .args(["bookmark", "list", "--all-remotes"])
             .output()
             .unwrap();
@@ -3540,8 +5226,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
             .unwrap();
 
         // Initialize jj
-        let jj_init = command_for("jj")
-            .current_dir(&local_repo)
+        let jj_init = jj_command(&local_repo)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -3629,8 +5314,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
             .unwrap();
 
         // Initialize jj
-        let jj_init = command_for("jj")
-            .current_dir(&repo)
+        let jj_init = jj_command(&repo)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -3642,14 +5326,13 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
         let repo_str = repo.to_str().unwrap();
 
         // Create a bookmark without tracking
-        let _ = command_for("jj")
-            .current_dir(&repo)
+        let _ = jj_command(&repo)
             .args(["bookmark", "set", "test-branch", "-r", "@", "--allow-backwards"])
             .output()
             .unwrap();
 
         // Call jj_push - it should not panic regardless of success/failure
-        let push_result = jj_push(repo_str, false);
+        let push_result = async_process::block_on(jj_push(repo_str, false, None));
 
         // The important thing is the function doesn't crash
         match push_result {
@@ -3690,8 +5373,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
             .expect("Failed to init git");
 
         // Initialize jj colocated
-        let jj_init = command_for("jj")
-            .current_dir(&repo_path)
+        let jj_init = jj_command(&repo_path)
             .args(["git", "init", "--colocate"])
             .output();
 
@@ -3732,8 +5414,7 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
             .output()
             .expect("Failed to init git");
 
-        command_for("jj")
-            .current_dir(&repo_path)
+        jj_command(&repo_path)
             .args(["git", "init", "--colocate"])
             .output()
             .ok();
@@ -3815,4 +5496,186 @@ target/debug/deps/lib.so    2-sided conflict including 1 deletion and an executa
             );
         }
     }
+
+    #[test]
+    fn test_parse_resolve_list() {
+        let output = "src/a.rs    2-sided conflict\nsrc/b file.ts    2-sided conflict including 1 deletion\n";
+        let entries = parse_resolve_list(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], ("src/a.rs".to_string(), "2-sided conflict".to_string()));
+        assert_eq!(entries[1].0, "src/b file.ts");
+        assert_eq!(entries[1].1, "2-sided conflict including 1 deletion");
+    }
+
+    #[test]
+    fn test_parse_op_log() {
+        let output = "8f1e2d\t1\t2024-05-01T10:00:00.000Z\tdev@laptop\tsquash commits into 1a2b\tfrom ui\n3c4d5e\t0\t2024-05-01T09:59:00.000Z\tdev@laptop\tsnapshot working copy\n";
+        let ops = parse_op_log(output);
+        assert_eq!(ops.len(), 2);
+        assert!(ops[0].is_current);
+        assert_eq!(ops[0].description, "squash commits into 1a2b\tfrom ui");
+        assert_eq!(ops[0].time, "2024-05-01T10:00:00.000Z");
+        assert_eq!(ops[1].user, "dev@laptop");
+        assert!(!ops[1].is_current);
+        assert!(matches!(jj_op_restore(".", "--at-op"), Err(JjError::IoError(_))));
+    }
+
+    #[test]
+    fn test_pick_default_remote() {
+        let remotes = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        assert_eq!(pick_default_remote(&remotes(&[])), "origin");
+        assert_eq!(pick_default_remote(&remotes(&["upstream", "origin"])), "origin");
+        assert_eq!(pick_default_remote(&remotes(&["upstream", "fork"])), "fork");
+    }
+
+    #[test]
+    fn test_parse_conflict_markers_diff_style() {
+        let content = "header\n<<<<<<< Conflict 1 of 1\n%%%%%%% Changes from base to side #1\n-old\n+left\n keep\n+++++++ Contents of side #2\nright\nkeep\n>>>>>>> Conflict 1 of 1 ends\nfooter\n";
+        let (base, left, right, count) = parse_conflict_markers(content);
+        assert_eq!(count, 1);
+        assert_eq!(base, "header\nold\nkeep\nfooter\n");
+        assert_eq!(left, "header\nleft\nkeep\nfooter\n");
+        assert_eq!(right, "header\nright\nkeep\nfooter\n");
+    }
+
+    #[test]
+    fn test_parse_conflict_markers_snapshot_and_git_style() {
+        let snapshot = "<<<<<<< conflict 1 of 1\n+++++++ side #1\nleft\n------- base\nold\n+++++++ side #2\nright\n>>>>>>> conflict 1 of 1 ends\n";
+        let (base, left, right, _) = parse_conflict_markers(snapshot);
+        assert_eq!((base.as_str(), left.as_str(), right.as_str()), ("old\n", "left\n", "right\n"));
+
+        let git = "a\n<<<<<<< side #1\nleft\n||||||| base\nold\n=======\nright\n>>>>>>> side #2\nb\n";
+        let (base, left, right, count) = parse_conflict_markers(git);
+        assert_eq!(count, 1);
+        assert_eq!(base, "a\nold\nb\n");
+        assert_eq!(left, "a\nleft\nb\n");
+        assert_eq!(right, "a\nright\nb\n");
+    }
+
+    #[test]
+    fn test_validate_workspace_file_path() {
+        assert!(validate_workspace_file_path("src/main.rs").is_ok());
+        assert!(validate_workspace_file_path("../outside.rs").is_err());
+        assert!(validate_workspace_file_path("/etc/passwd").is_err());
+        assert!(validate_workspace_file_path("").is_err());
+    }
+
+    #[test]
+    fn test_parse_snapshot_too_large() {
+        let current = "Warning: Refused to snapshot some files:\n  assets/big model.bin: 11.0MiB (11534336 bytes); the maximum size allowed is 1.0MiB (1048576 bytes)\nHint: This is to prevent large files from being added by accident.";
+        assert!(matches!(
+            parse_snapshot_too_large(current),
+            Some(JjError::SnapshotFileTooLarge { path, size: 11534336, limit: 1048576 }) if path == "assets/big model.bin"
+        ));
+
+        let older = "Error: Failed to snapshot the working copy\nCaused by: New file dist/app.js of size ~2.5MiB exceeds snapshot.max-new-file-size (1.0MiB)";
+        assert!(matches!(
+            parse_snapshot_too_large(older),
+            Some(JjError::SnapshotFileTooLarge { path, size: 2621440, limit: 1048576 }) if path == "dist/app.js"
+        ));
+
+    }
+
+    #[test]
+    fn test_build_revision_diff_splits_large_diff_quickly() {
+        let file_count = 300;
+        let mut summary = String::new();
+        let mut diff = String::new();
+        for i in 0..file_count {
+            let path = format!("src/dir {}/file_{}.rs", i % 7, i);
+            summary.push_str(&format!("M {}\n", path));
+            diff.push_str(&format!(
+                "diff --git a/{p} b/{p}\nindex 1111111..2222222 100644\n--- a/{p}\n+++ b/{p}\n",
+                p = path
+            ));
+            for hunk in 0..3 {
+                let start = hunk * 40 + 1;
+                diff.push_str(&format!("@@ -{s},3 +{s},3 @@\n context\n-old {i}\n+new {i}\n context\n", s = start, i = i));
+            }
+        }
+        summary.push_str("A assets/logo.png\n");
+        diff.push_str("diff --git a/assets/logo.png b/assets/logo.png\nnew file mode 100644\nBinary files /dev/null and b/assets/logo.png differ\n");
+        summary.push_str("D old name.txt\n");
+        diff.push_str("diff --git a/old name.txt b/old name.txt\ndeleted file mode 100644\n--- a/old name.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-gone\n");
+
+        let files = parse_diff_summary(&summary).unwrap();
+        let started = std::time::Instant::now();
+        let result = build_revision_diff(files, &diff).unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(result.hunks_by_file.len(), file_count + 2);
+        for (i, file) in result.hunks_by_file.iter().take(file_count).enumerate() {
+            assert_eq!(file.path, format!("src/dir {}/file_{}.rs", i % 7, i));
+            assert_eq!(file.hunks.len(), 3);
+            assert!(file.hunks[0].lines.contains(&format!("+new {}", i)));
+        }
+        assert!(result.hunks_by_file[file_count].hunks.is_empty());
+        assert_eq!(result.hunks_by_file[file_count + 1].hunks[0].lines, vec!["-gone"]);
+        // Splitting is linear; the old per-file loop also re-spawned jj for every file
+        assert!(elapsed < std::time::Duration::from_secs(2), "took {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_command_error_classifies_stderr() {
+        let kind = |stderr: &str| command_error(stderr.as_bytes()).kind();
+        assert_eq!(
+            kind("Error: Commit 3f2a9c1b is immutable\nHint: Could not modify commit: qpvuntsm 3f2a9c1b main | init\nHint: Pass `--ignore-immutable` or configure the set of immutable commits via `revset-aliases.immutable_heads()`."),
+            "immutable_commit"
+        );
+        assert_eq!(
+            kind("Error: Concurrent checkout\nInternal error: Failed to lock working copy"),
+            "concurrent_operation"
+        );
+        assert_eq!(kind("Error: No such bookmark: feature/x"), "bookmark_not_found");
+        assert_eq!(
+            kind("Error: No matching bookmarks for names: feature/x"),
+            "bookmark_not_found"
+        );
+        assert_eq!(kind("Error: Revision `nope` doesn't exist"), "revset_evaluation_error");
+        assert_eq!(
+            kind("Error: Failed to parse revset: Syntax error\nCaused by:  --> 1:5"),
+            "revset_evaluation_error"
+        );
+        assert_eq!(
+            kind("Error: Won't push commit 3f2a9c1b since it has conflicts"),
+            "conflict_detected"
+        );
+        assert_eq!(
+            kind("Error: Bookmark main is conflicted\nHint: Run `jj bookmark list` to inspect, and use `jj bookmark set` to fix it up."),
+            "conflict_detected"
+        );
+        // Mentioning conflicts isn't enough
+        assert_eq!(kind("Error: No conflicts found at this revision"), "io_error");
+        assert!(matches!(command_error(b"Error: Failed to write tree\n"), JjError::IoError(e) if e == "Error: Failed to write tree"));
+
+        let json = serde_json::to_value(JjError::BookmarkNotFound("Error: No such bookmark: x".to_string())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "kind": "bookmark_not_found", "message": "Bookmark not found: Error: No such bookmark: x" })
+        );
+        let json = serde_json::to_value(JjError::SnapshotFileTooLarge { path: "a.bin".to_string(), size: 2, limit: 1 }).unwrap();
+        assert_eq!(json["kind"], "snapshot_file_too_large");
+        assert_eq!(json["path"], "a.bin");
+        assert_eq!(json["limit"], 1);
+    }
+
+    #[test]
+    fn test_repo_config_args_for_workspaces() {
+        let repo = "/tmp/treq-size-limit-repo";
+        let workspace = Path::new(repo).join(".treq").join("workspaces").join("feature");
+        assert!(repo_config_args(&workspace).is_empty());
+
+        set_max_new_file_size(repo, Some("20MiB"));
+        assert_eq!(
+            repo_config_args(&workspace),
+            vec!["--config", "snapshot.max-new-file-size=\"20MiB\""]
+        );
+        set_max_new_file_size(repo, Some("4194304"));
+        assert_eq!(
+            repo_config_args(Path::new(repo)),
+            vec!["--config", "snapshot.max-new-file-size=4194304"]
+        );
+        set_max_new_file_size(repo, Some(" "));
+        assert!(repo_config_args(Path::new(repo)).is_empty());
+    }
 }
@@ -0,0 +1,167 @@
+//! The repo-level jj config layer, `.jj/repo/config.toml`.
+//!
+//! Entries are read straight from the file and written through `jj config set --repo`,
+//! which validates the value and keeps the file's formatting and comments. Cached query
+//! results include the file's modification time in their state key, so changing an
+//! option such as a revset alias is never answered from results computed before it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::jj::{self, JjError};
+
+/// One option of the repo config, e.g. `snapshot.max-new-file-size = "10MiB"`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JjConfigEntry {
+    /// Dotted key, with segments quoted where TOML requires it
+    pub key: String,
+    /// The value as TOML, so strings keep their quotes
+    pub value: String,
+}
+
+fn repo_config_path(repo_path: &str) -> PathBuf {
+    Path::new(repo_path)
+        .join(".jj")
+        .join("repo")
+        .join("config.toml")
+}
+
+/// Modification time of the repo config, for cache keys; None when there is none
+pub fn repo_config_stamp(repo_path: &str) -> Option<u128> {
+    let modified = fs::metadata(repo_config_path(repo_path))
+        .ok()?
+        .modified()
+        .ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_nanos())
+}
+
+fn key_segment(segment: &str) -> String {
+    let bare = !segment.is_empty()
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if bare {
+        segment.to_string()
+    } else {
+        toml::Value::String(segment.to_string()).to_string()
+    }
+}
+
+fn flatten(prefix: &str, table: &toml::Table, entries: &mut Vec<JjConfigEntry>) {
+    for (name, value) in table {
+        let key = if prefix.is_empty() {
+            key_segment(name)
+        } else {
+            format!("{}.{}", prefix, key_segment(name))
+        };
+        match value {
+            toml::Value::Table(table) => flatten(&key, table, entries),
+            value => entries.push(JjConfigEntry {
+                key,
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// Every option set in the repo's config layer
+pub fn get_repo_config(repo_path: &str) -> Result<Vec<JjConfigEntry>, JjError> {
+    let path = repo_config_path(repo_path);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(JjError::IoError(e.to_string())),
+    };
+    let table: toml::Table = toml::from_str(&content)
+        .map_err(|e| JjError::ConfigError(format!("{}: {}", path.display(), e)))?;
+    let mut entries = Vec::new();
+    flatten("", &table, &mut entries);
+    Ok(entries)
+}
+
+/// Set an option in the repo's config layer. `value` is TOML, or a plain string as
+/// `jj config set` accepts; an empty value removes the option.
+pub fn set_repo_config(repo_path: &str, key: &str, value: &str) -> Result<(), JjError> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(JjError::ConfigError("Config key is required".to_string()));
+    }
+    if !Path::new(repo_path).join(".jj").is_dir() {
+        return Err(JjError::ConfigError(format!(
+            "{} is not a jj repository",
+            repo_path
+        )));
+    }
+    let args: Vec<&str> = if value.is_empty() {
+        vec!["config", "unset", "--repo", key]
+    } else {
+        vec!["config", "set", "--repo", key, value]
+    };
+    let output = jj::jj_command(repo_path)
+        .args(&args)
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(JjError::ConfigError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_get_repo_config_flattens_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().to_str().unwrap();
+        assert!(get_repo_config(repo).unwrap().is_empty());
+        assert_eq!(repo_config_stamp(repo), None);
+
+        fs::create_dir_all(temp_dir.path().join(".jj/repo")).unwrap();
+        fs::write(
+            repo_config_path(repo),
+            r#"
+snapshot.max-new-file-size = "10MiB"
+
+[revset-aliases]
+"mine()" = "author(exact:'me@example.com')"
+
+[git]
+fetch = ["origin", "upstream"]
+"#,
+        )
+        .unwrap();
+        let entries = get_repo_config(repo).unwrap();
+        let pairs: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("git.fetch", r#"["origin", "upstream"]"#),
+                (
+                    "revset-aliases.\"mine()\"",
+                    r#""author(exact:'me@example.com')""#
+                ),
+                ("snapshot.max-new-file-size", r#""10MiB""#),
+            ]
+        );
+        assert!(repo_config_stamp(repo).is_some());
+
+        fs::write(repo_config_path(repo), "[broken").unwrap();
+        assert!(matches!(
+            get_repo_config(repo),
+            Err(JjError::ConfigError(_))
+        ));
+    }
+}
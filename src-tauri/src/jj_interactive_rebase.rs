@@ -0,0 +1,408 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::jj::{self, JjError};
+
+/// A commit in the `target..@` range that can be rearranged
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RebasePlanCommit {
+    pub change_id: String,
+    pub commit_id: String,
+    pub description: String,
+    pub is_empty: bool,
+    pub is_working_copy: bool,
+}
+
+/// One entry of an interactive rebase plan, applied in list order (oldest first)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RebaseStep {
+    /// Keep the commit at this position
+    Pick { change_id: String },
+    /// Keep the commit at this position with a new description
+    Reword { change_id: String, message: String },
+    /// Fold the commit into the nearest preceding kept commit
+    Squash { change_id: String },
+    /// Abandon the commit
+    Drop { change_id: String },
+}
+
+impl RebaseStep {
+    pub fn change_id(&self) -> &str {
+        match self {
+            RebaseStep::Pick { change_id }
+            | RebaseStep::Reword { change_id, .. }
+            | RebaseStep::Squash { change_id }
+            | RebaseStep::Drop { change_id } => change_id,
+        }
+    }
+
+    fn action_name(&self) -> &'static str {
+        match self {
+            RebaseStep::Pick { .. } => "pick",
+            RebaseStep::Reword { .. } => "reword",
+            RebaseStep::Squash { .. } => "squash",
+            RebaseStep::Drop { .. } => "drop",
+        }
+    }
+}
+
+/// Outcome of a single jj operation performed while executing the plan
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RebaseStepResult {
+    pub change_id: String,
+    pub action: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Report returned after executing an interactive rebase plan
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InteractiveRebaseReport {
+    pub success: bool,
+    pub message: String,
+    pub steps: Vec<RebaseStepResult>,
+    /// Change ids in the range that ended up conflicted
+    pub conflicted_changes: Vec<String>,
+    /// Conflicted files in the working copy
+    pub conflicted_files: Vec<String>,
+    /// Operation id to pass to `jj op restore` to undo the whole plan
+    pub restore_operation: Option<String>,
+}
+
+fn validate_arg(value: &str, label: &str) -> Result<(), JjError> {
+    if value.is_empty() || value.starts_with('-') || value.contains('\0') {
+        return Err(JjError::IoError(format!("Invalid {}: {}", label, value)));
+    }
+    Ok(())
+}
+
+fn run_jj(workspace_path: &str, args: &[&str]) -> Result<String, JjError> {
+    let output = jj::jj_command(workspace_path)
+        .args(args)
+        .output()
+        .map_err(|e| JjError::IoError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(jj::command_error(&output.stderr));
+    }
+
+    Ok(format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ))
+}
+
+fn resolve_target(workspace_path: &str, target_branch: &str) -> Result<String, JjError> {
+    validate_arg(target_branch, "target branch")?;
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    Ok(jj::convert_git_branch_to_jj_format_public(
+        target_branch,
+        &repo_path,
+    ))
+}
+
+fn parse_plan_commits(output: &str) -> Vec<RebasePlanCommit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.splitn(5, '\t').collect();
+            if parts.len() < 5 {
+                return None;
+            }
+            Some(RebasePlanCommit {
+                change_id: parts[0].to_string(),
+                commit_id: parts[1].to_string(),
+                is_empty: parts[2] == "true",
+                is_working_copy: parts[3] == "true",
+                description: parts[4].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// List the commits in `target..@`, oldest first, as the starting point for a plan
+pub fn get_rebase_plan(
+    workspace_path: &str,
+    target_branch: &str,
+) -> Result<Vec<RebasePlanCommit>, JjError> {
+    let target = resolve_target(workspace_path, target_branch)?;
+    let revset = format!("{}..@", target);
+    let template = concat!(
+        "change_id.short(12) ++ \"\\t\" ++ ",
+        "commit_id.short(12) ++ \"\\t\" ++ ",
+        "empty ++ \"\\t\" ++ ",
+        "current_working_copy ++ \"\\t\" ++ ",
+        "description.first_line() ++ \"\\n\""
+    );
+    let stdout = run_jj(
+        workspace_path,
+        &[
+            "log",
+            "-r",
+            &revset,
+            "--no-graph",
+            "--reversed",
+            "-T",
+            template,
+        ],
+    )?;
+    Ok(parse_plan_commits(&stdout))
+}
+
+/// Check that the plan covers every commit in the range exactly once and can be applied
+fn validate_plan(range: &[RebasePlanCommit], steps: &[RebaseStep]) -> Result<(), String> {
+    let range_ids: HashSet<&str> = range.iter().map(|c| c.change_id.as_str()).collect();
+    let mut seen = HashSet::new();
+
+    for step in steps {
+        let id = step.change_id();
+        if validate_arg(id, "change id").is_err() {
+            return Err(format!("Invalid change id: {}", id));
+        }
+        if !range_ids.contains(id) {
+            return Err(format!("Change {} is not in the rebase range", id));
+        }
+        if !seen.insert(id) {
+            return Err(format!("Change {} appears more than once in the plan", id));
+        }
+    }
+
+    if let Some(missing) = range.iter().find(|c| !seen.contains(c.change_id.as_str())) {
+        return Err(format!("Plan is missing change {}", missing.change_id));
+    }
+
+    let first_kept = steps.iter().find(|s| !matches!(s, RebaseStep::Drop { .. }));
+    if let Some(RebaseStep::Squash { change_id }) = first_kept {
+        return Err(format!(
+            "Change {} has no earlier commit to squash into",
+            change_id
+        ));
+    }
+
+    Ok(())
+}
+
+fn current_operation(workspace_path: &str) -> Option<String> {
+    run_jj(
+        workspace_path,
+        &["op", "log", "-n", "1", "--no-graph", "-T", "id.short(16)"],
+    )
+    .ok()
+    .map(|s| s.trim().to_string())
+    .filter(|s| !s.is_empty())
+}
+
+/// Execute an interactive rebase plan over `target..@`.
+///
+/// Drops are applied first, then kept commits are re-parented one by one in plan order,
+/// then squashes and rewords. Execution stops at the first failing jj command; the
+/// report's `restore_operation` can be used to roll the whole plan back.
+pub fn execute_plan(
+    workspace_path: &str,
+    target_branch: &str,
+    steps: &[RebaseStep],
+) -> Result<InteractiveRebaseReport, JjError> {
+    let target = resolve_target(workspace_path, target_branch)?;
+    let range = get_rebase_plan(workspace_path, target_branch)?;
+    validate_plan(&range, steps).map_err(JjError::IoError)?;
+
+    let restore_operation = current_operation(workspace_path);
+    let mut results: Vec<RebaseStepResult> = Vec::new();
+
+    let mut record = |step: &RebaseStep, outcome: Result<String, JjError>| -> bool {
+        let success = outcome.is_ok();
+        results.push(RebaseStepResult {
+            change_id: step.change_id().to_string(),
+            action: step.action_name().to_string(),
+            success,
+            message: match outcome {
+                Ok(out) => out.trim().to_string(),
+                Err(e) => e.to_string(),
+            },
+        });
+        success
+    };
+
+    let mut ok = true;
+
+    // 1. Abandon dropped commits; their descendants move onto their parents
+    for step in steps
+        .iter()
+        .filter(|s| matches!(s, RebaseStep::Drop { .. }))
+    {
+        ok = record(step, run_jj(workspace_path, &["abandon", step.change_id()]));
+        if !ok {
+            break;
+        }
+    }
+
+    // 2. Re-parent every remaining commit onto the previous one to get the new order
+    let kept: Vec<&RebaseStep> = steps
+        .iter()
+        .filter(|s| !matches!(s, RebaseStep::Drop { .. }))
+        .collect();
+    if ok {
+        let mut parent = target.clone();
+        for step in &kept {
+            let id = step.change_id();
+            let outcome = run_jj(workspace_path, &["rebase", "-r", id, "-d", &parent]);
+            if outcome.is_err() {
+                ok = record(step, outcome);
+                break;
+            }
+            parent = id.to_string();
+        }
+    }
+
+    // 3. Fold squashed commits into the nearest preceding kept commit, then reword
+    if ok {
+        let mut into: Option<&str> = None;
+        for step in &kept {
+            let outcome = match step {
+                RebaseStep::Squash { change_id } => {
+                    let dest = into.unwrap_or_default();
+                    run_jj(
+                        workspace_path,
+                        &[
+                            "squash",
+                            "--from",
+                            change_id,
+                            "--into",
+                            dest,
+                            "--use-destination-message",
+                        ],
+                    )
+                }
+                RebaseStep::Reword { change_id, message } => {
+                    into = Some(change_id);
+                    run_jj(
+                        workspace_path,
+                        &["describe", "-r", change_id, "-m", message],
+                    )
+                }
+                _ => {
+                    into = Some(step.change_id());
+                    Ok("Picked".to_string())
+                }
+            };
+            ok = record(step, outcome);
+            if !ok {
+                break;
+            }
+        }
+    }
+
+    let conflict_revset = format!("({}..@) & conflicts()", target);
+    let conflicted_changes: Vec<String> = run_jj(
+        workspace_path,
+        &[
+            "log",
+            "-r",
+            &conflict_revset,
+            "--no-graph",
+            "-T",
+            "change_id.short(12) ++ \"\\n\"",
+        ],
+    )
+    .map(|out| {
+        out.lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    })
+    .unwrap_or_default();
+    let conflicted_files = jj::get_conflicted_files(workspace_path, None).unwrap_or_default();
+
+    let message = if !ok {
+        "Interactive rebase stopped at a failing step".to_string()
+    } else if !conflicted_changes.is_empty() {
+        format!(
+            "Interactive rebase completed with {} conflicted change(s)",
+            conflicted_changes.len()
+        )
+    } else {
+        "Interactive rebase completed".to_string()
+    };
+
+    Ok(InteractiveRebaseReport {
+        success: ok && conflicted_changes.is_empty(),
+        message,
+        steps: results,
+        conflicted_changes,
+        conflicted_files,
+        restore_operation,
+    })
+}
+
+/// Undo an interactive rebase by restoring the operation recorded before it ran
+pub fn restore_operation(workspace_path: &str, operation_id: &str) -> Result<String, JjError> {
+    jj::jj_op_restore(workspace_path, operation_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str) -> RebasePlanCommit {
+        RebasePlanCommit {
+            change_id: id.to_string(),
+            commit_id: format!("c{}", id),
+            description: String::new(),
+            is_empty: false,
+            is_working_copy: false,
+        }
+    }
+
+    fn pick(id: &str) -> RebaseStep {
+        RebaseStep::Pick {
+            change_id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_plan_commits() {
+        let output = "aaa\t111\tfalse\tfalse\tfirst\tline\nbbb\t222\ttrue\ttrue\t\n";
+        let commits = parse_plan_commits(output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].description, "first\tline");
+        assert!(commits[1].is_empty && commits[1].is_working_copy);
+    }
+
+    #[test]
+    fn test_validate_plan() {
+        let range = vec![commit("a"), commit("b"), commit("c")];
+
+        assert!(validate_plan(&range, &[pick("c"), pick("a"), pick("b")]).is_ok());
+        assert!(validate_plan(&range, &[pick("a"), pick("b")]).is_err());
+        assert!(validate_plan(&range, &[pick("a"), pick("a"), pick("b"), pick("c")]).is_err());
+        assert!(validate_plan(&range, &[pick("a"), pick("b"), pick("x")]).is_err());
+
+        let squash_first = [
+            RebaseStep::Drop {
+                change_id: "a".to_string(),
+            },
+            RebaseStep::Squash {
+                change_id: "b".to_string(),
+            },
+            pick("c"),
+        ];
+        assert!(validate_plan(&range, &squash_first).is_err());
+    }
+
+    #[test]
+    fn test_rebase_step_deserialize() {
+        let step: RebaseStep =
+            serde_json::from_str(r#"{"action":"reword","change_id":"abc","message":"msg"}"#)
+                .unwrap();
+        assert_eq!(
+            step,
+            RebaseStep::Reword {
+                change_id: "abc".to_string(),
+                message: "msg".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::local_db::{self, ExtensionStat};
+use crate::timestamps;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Aggregated file count and size for a language
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LanguageStat {
+    pub language: String,
+    pub file_count: i64,
+    pub total_bytes: i64,
+    pub extensions: Vec<String>,
+}
+
+/// File type breakdown for a repository or workspace
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoLanguageStats {
+    pub languages: Vec<LanguageStat>,
+    pub extensions: Vec<ExtensionStat>,
+    pub total_files: i64,
+    pub total_bytes: i64,
+    pub computed_at: Option<String>,
+}
+
+/// Map a file name to a language, following GitHub linguist naming where possible
+pub fn language_for_path(path: &str) -> &'static str {
+    let file_name = Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path);
+
+    // Well-known extensionless files
+    match file_name {
+        "Dockerfile" | "Containerfile" => return "Dockerfile",
+        "Makefile" | "GNUmakefile" => return "Makefile",
+        "Cargo.lock" => return "TOML",
+        "CMakeLists.txt" => return "CMake",
+        _ => {}
+    }
+
+    match extension_key(path).as_str() {
+        "rs" => "Rust",
+        "ts" | "mts" | "cts" => "TypeScript",
+        "tsx" => "TSX",
+        "js" | "mjs" | "cjs" => "JavaScript",
+        "jsx" => "JSX",
+        "py" | "pyi" => "Python",
+        "go" => "Go",
+        "rb" => "Ruby",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "swift" => "Swift",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "C++",
+        "cs" => "C#",
+        "m" | "mm" => "Objective-C",
+        "php" => "PHP",
+        "scala" => "Scala",
+        "ex" | "exs" => "Elixir",
+        "erl" | "hrl" => "Erlang",
+        "hs" => "Haskell",
+        "ml" | "mli" => "OCaml",
+        "clj" | "cljs" | "cljc" => "Clojure",
+        "lua" => "Lua",
+        "dart" => "Dart",
+        "zig" => "Zig",
+        "sh" | "bash" | "zsh" | "fish" => "Shell",
+        "ps1" => "PowerShell",
+        "sql" => "SQL",
+        "html" | "htm" => "HTML",
+        "css" => "CSS",
+        "scss" | "sass" => "SCSS",
+        "less" => "Less",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "json" | "jsonc" => "JSON",
+        "yaml" | "yml" => "YAML",
+        "toml" => "TOML",
+        "xml" => "XML",
+        "md" | "mdx" | "markdown" => "Markdown",
+        "rst" => "reStructuredText",
+        "txt" => "Text",
+        "proto" => "Protocol Buffer",
+        "graphql" | "gql" => "GraphQL",
+        "nix" => "Nix",
+        "tf" => "HCL",
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "ico" | "webp" | "icns" => "Image",
+        _ => "Other",
+    }
+}
+
+/// Lowercased extension used as the grouping key ("" for files without one)
+fn extension_key(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Compute per-extension stats from the workspace_files cache and store them in local_db.
+/// Called by the file indexer after each full index.
+pub fn refresh_language_stats(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+) -> Result<Vec<ExtensionStat>, String> {
+    let paths = local_db::list_workspace_file_paths(repo_path, workspace_id)?;
+    let stats = compute_extension_stats(&paths);
+    local_db::save_language_stats(repo_path, workspace_id, &stats)?;
+    Ok(stats)
+}
+
+/// Group file paths by extension, summing on-disk sizes
+fn compute_extension_stats(paths: &[String]) -> Vec<ExtensionStat> {
+    let computed_at = timestamps::now();
+    let mut by_extension: HashMap<String, ExtensionStat> = HashMap::new();
+
+    for path in paths {
+        // Files deleted since the last index simply count as zero bytes
+        let size = std::fs::metadata(path).map(|m| m.len() as i64).unwrap_or(0);
+        let entry = by_extension
+            .entry(extension_key(path))
+            .or_insert_with(|| ExtensionStat {
+                extension: extension_key(path),
+                language: language_for_path(path).to_string(),
+                file_count: 0,
+                total_bytes: 0,
+                computed_at: computed_at.clone(),
+            });
+        entry.file_count += 1;
+        entry.total_bytes += size;
+    }
+
+    let mut stats: Vec<ExtensionStat> = by_extension.into_values().collect();
+    stats.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    stats
+}
+
+/// Roll per-extension stats up into per-language totals
+pub fn summarize(extensions: Vec<ExtensionStat>) -> RepoLanguageStats {
+    let mut by_language: HashMap<String, LanguageStat> = HashMap::new();
+
+    for ext in &extensions {
+        let entry = by_language
+            .entry(ext.language.clone())
+            .or_insert_with(|| LanguageStat {
+                language: ext.language.clone(),
+                file_count: 0,
+                total_bytes: 0,
+                extensions: Vec::new(),
+            });
+        entry.file_count += ext.file_count;
+        entry.total_bytes += ext.total_bytes;
+        entry.extensions.push(ext.extension.clone());
+    }
+
+    let mut languages: Vec<LanguageStat> = by_language.into_values().collect();
+    languages.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.language.cmp(&b.language))
+    });
+
+    RepoLanguageStats {
+        total_files: extensions.iter().map(|e| e.file_count).sum(),
+        total_bytes: extensions.iter().map(|e| e.total_bytes).sum(),
+        computed_at: extensions.first().map(|e| e.computed_at.clone()),
+        languages,
+        extensions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_language_for_path() {
+        assert_eq!(language_for_path("src/main.rs"), "Rust");
+        assert_eq!(language_for_path("src/App.TSX"), "TSX");
+        assert_eq!(language_for_path("docker/Dockerfile"), "Dockerfile");
+        assert_eq!(language_for_path("LICENSE"), "Other");
+    }
+
+    #[test]
+    fn test_compute_and_summarize_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let paths = vec![
+            write("a.ts", "12345"),
+            write("b.mts", "123"),
+            write("c.rs", "1"),
+        ];
+
+        let summary = summarize(compute_extension_stats(&paths));
+
+        assert_eq!(summary.total_files, 3);
+        assert_eq!(summary.total_bytes, 9);
+        assert_eq!(summary.languages[0].language, "TypeScript");
+        assert_eq!(summary.languages[0].file_count, 2);
+        assert_eq!(summary.languages[0].total_bytes, 8);
+        assert_eq!(summary.extensions[0].extension, "ts");
+    }
+}
@@ -1,11 +1,59 @@
+mod askpass;
+mod async_process;
 mod auto_rebase;
+mod automation;
 mod binary_paths;
+mod bisect;
+mod branch_names;
+mod ci_status;
 mod commands;
+mod commit_graph;
+mod commit_hooks;
+mod commit_signing;
 mod db;
+mod default_branch;
+mod destructive_guard;
+mod file_guard;
 mod file_indexer;
+mod forge;
+mod git2_ops;
+mod git_cache;
+mod git_ops;
+mod git_submodules;
+mod instance_lock;
 mod jj;
+mod jj_config;
+mod jj_interactive_rebase;
+mod language_stats;
+mod local_api;
 mod local_db;
+mod mcp;
+mod merge_gates;
+mod migrations;
+mod operations;
+mod patch_model;
+mod paths;
+mod process_limiter;
 mod pty;
+mod repo_bootstrap;
+mod resync;
+mod search;
+mod result_cache;
+mod secrets;
+mod settings_sync;
+mod shallow_history;
+mod shutdown;
+mod snapshots;
+mod target_branch;
+mod task_runner;
+mod timestamps;
+mod updater;
+mod vcs;
+mod workspace_archive;
+mod workspace_config;
+mod workspace_disk_usage;
+mod workspace_intent;
+mod workspace_registry;
 
 use commands::file_watcher::WatcherManager;
 use db::Database;
@@ -36,6 +84,11 @@ pub fn emit_to_focused<S: serde::Serialize + Clone>(app: &AppHandle, event: &str
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // git and ssh start treq as their askpass helper; answer and exit without the UI
+    if let Some(code) = askpass::run_helper_if_requested() {
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
         .plugin(
             tauri_plugin_log::Builder::new()
@@ -62,6 +115,9 @@ pub fn run() {
             std::fs::create_dir_all(&app_dir).expect("Failed to create app data directory");
             let db_path = app_dir.join("treq.db");
 
+            // Crash-recovery scans can be skipped when the last run exited cleanly
+            shutdown::check_previous_shutdown(&app_dir);
+
             let db = Database::new(db_path).expect("Failed to open database");
             db.init().expect("Failed to initialize database");
 
@@ -73,7 +129,52 @@ pub fn run() {
             let editor_apps = commands::load_cached_editor_apps(&db);
             binary_paths::init_editor_apps_cache(editor_apps);
 
+            // Apply configured limit on concurrent git/jj processes
+            let max_processes = db
+                .get_setting(process_limiter::MAX_PARALLEL_PROCESSES_KEY)
+                .ok()
+                .flatten();
+            process_limiter::set_max_concurrent(process_limiter::parse_max_concurrent_setting(
+                max_processes.as_deref(),
+            ));
+
+            // Apply configured size limit for viewed and diffed files
+            let max_file_size = db.get_setting(file_guard::MAX_FILE_SIZE_KEY).ok().flatten();
+            file_guard::set_max_file_size(file_guard::parse_max_file_size_setting(
+                max_file_size.as_deref(),
+            ));
+
+            // Apply how many safety snapshots are kept per repository
+            let snapshot_retention = db.get_setting(snapshots::SNAPSHOT_RETENTION_KEY).ok().flatten();
+            snapshots::set_retention(snapshots::parse_retention_setting(
+                snapshot_retention.as_deref(),
+            ));
+
+            // Pass each repo's snapshot size limit to jj
+            jj::load_max_new_file_sizes(&db);
+
+            // Commands resolve the backend from a path alone
+            vcs::load_preferences(&db);
+
+            // The default branch each repo's remote advertised when last fetched
+            default_branch::load_recorded(&db);
+
+            // Tokens live in the OS keychain; move any left in settings there
+            secrets::init(&app_dir, &db);
+
+            // Authenticate forge requests with the configured tokens
+            for kind in forge::ForgeKind::ALL {
+                forge::set_token(kind, secrets::get(kind.token_key()).ok().flatten().as_deref());
+            }
+
+            // Start the local HTTP API if enabled
+            local_api::apply_settings(app.handle(), &db);
+
             let pty_manager = PtyManager::new();
+            let scrollback_bytes = db.get_setting(pty::SCROLLBACK_BYTES_KEY).ok().flatten();
+            pty_manager.set_scrollback_capacity(pty::parse_scrollback_setting(
+                scrollback_bytes.as_deref(),
+            ));
 
             // Initialize file watcher
             let watcher_manager = WatcherManager::new();
@@ -87,6 +188,20 @@ pub fn run() {
 
             app.manage(app_state);
 
+            // Emit graph-delta events after operations that move commits
+            commit_graph::init(app.handle().clone());
+            automation::init(app.handle().clone());
+            askpass::init(app.handle().clone());
+            ci_status::init(app.handle().clone());
+            operations::init(app.handle().clone());
+            task_runner::init(app.handle().clone());
+            auto_rebase::init(app.handle().clone());
+            default_branch::init(app.handle().clone());
+            shallow_history::init(app.handle().clone());
+
+            // Resync watchers and status after the machine wakes from sleep
+            resync::start_wake_monitor(app.handle().clone());
+
             // Create menu
             #[cfg(target_os = "macos")]
             {
@@ -274,28 +389,85 @@ pub fn run() {
 
             Ok(())
         })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Focused(focused) = event {
+                resync::handle_focus_changed(window.app_handle(), *focused);
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             commands::detect_binaries,
             commands::detect_editor_apps,
             commands::get_workspaces,
             commands::add_workspace_to_db,
             commands::create_workspace,
+            commands::suggest_workspace_branch_name,
             commands::delete_workspace_from_db,
             commands::delete_workspace,
             commands::cleanup_stale_workspaces,
             commands::rebuild_workspaces,
+            commands::reconcile_workspace_registry,
+            commands::apply_workspace_registry_fix,
             commands::update_workspace_metadata,
+            commands::get_workspace_intent,
+            commands::set_workspace_intent,
+            commands::set_workspace_auto_rebase,
+            commands::export_workspace_context,
             commands::update_workspace_conflicts,
             commands::list_conflicted_workspace_ids,
             commands::list_workspaces_with_changes,
+            commands::preview_workspace_target_branch,
             commands::set_workspace_target_branch,
             commands::check_and_rebase_workspaces,
+            commands::bulk_workspace_action,
+            commands::archive_workspace,
+            commands::unarchive_workspace,
+            commands::list_archived_workspaces,
+            commands::get_workspace_disk_usage,
+            commands::list_cleanable_paths,
+            commands::clean_paths,
+            commands::delete_archived_workspace,
+            commands::workspaces_rebase_all_onto,
+            commands::workspaces_fetch_all,
+            commands::workspaces_status_all,
+            commands::get_dashboard_snapshot,
             commands::ensure_workspace_indexed,
             commands::get_setting,
+            commands::get_secret,
+            commands::set_secret,
+            commands::delete_secret,
             commands::get_settings_batch,
             commands::set_setting,
             commands::get_repo_setting,
             commands::set_repo_setting,
+            commands::get_workspace_config,
+            commands::set_workspace_config,
+            commands::get_effective_workspace_config,
+            commands::list_workspace_templates,
+            commands::get_automation_hooks,
+            commands::set_automation_hooks,
+            commands::get_operation_journal,
+            commands::list_tasks,
+            commands::set_tasks,
+            commands::run_task,
+            commands::cancel_task,
+            commands::get_task_history,
+            commands::submit_auth_response,
+            commands::clear_auth_cache,
+            commands::save_workspace_template,
+            commands::delete_workspace_template,
+            commands::get_vcs_backend,
+            commands::get_local_api_status,
+            commands::export_settings,
+            commands::import_settings,
+            commands::get_performance_report,
+            commands::cancel_operation,
+            commands::forge_api_get,
+            commands::forge_rate_limit_status,
+            commands::forge_detect_provider,
+            commands::forge_create_pr,
+            commands::forge_get_pr_status,
+            commands::forge_list_prs_for_branch,
+            commands::forge_get_checks,
             commands::jj_create_workspace,
             commands::jj_list_workspaces,
             commands::jj_remove_workspace,
@@ -303,16 +475,35 @@ pub fn run() {
             commands::jj_squash_to_workspace,
             commands::jj_get_changed_files,
             commands::jj_get_file_hunks,
+            commands::jj_get_changed_files_cached,
+            commands::jj_get_sync_status_cached,
+            commands::jj_get_file_hunks_cached,
+            commands::refresh_if_stale,
             commands::jj_get_file_lines,
             commands::jj_restore_file,
             commands::jj_restore_all,
+            commands::list_snapshots,
+            commands::restore_snapshot,
+            commands::delete_snapshot,
             commands::jj_commit,
+            commands::jj_describe,
+            commands::jj_backout,
+            commands::jj_get_commit_message,
             commands::jj_split,
+            commands::jj_split_hunks,
             commands::jj_is_workspace,
             commands::jj_init,
+            commands::get_jj_config,
+            commands::set_jj_config,
             commands::jj_rebase_onto,
+            commands::jj_get_rebase_plan,
+            commands::jj_interactive_rebase,
+            commands::jj_restore_operation,
             commands::jj_get_conflicted_files,
+            commands::jj_get_conflict,
+            commands::jj_resolve_conflict,
             commands::jj_get_default_branch,
+            commands::refresh_default_branch,
             commands::jj_get_current_branch,
             commands::jj_push,
             commands::jj_get_sync_status,
@@ -320,23 +511,81 @@ pub fn run() {
             commands::jj_git_fetch_background,
             commands::jj_pull,
             commands::jj_get_log,
+            commands::jj_op_log,
+            commands::jj_op_undo,
+            commands::jj_op_restore,
             commands::jj_get_commits_ahead,
             commands::jj_get_merge_diff,
             commands::jj_create_merge,
+            commands::get_merge_target_commit,
+            commands::get_merge_checks,
+            commands::set_merge_checks,
             commands::jj_check_branch_exists,
             commands::jj_get_branches,
+            commands::get_revset_symbols,
+            commands::search_revisions,
             commands::jj_edit_bookmark,
             commands::jj_track_workspace_bookmarks,
+            commands::prepare_patch_email,
+            commands::format_patch_series,
+            commands::get_line_authors,
+            commands::git_blame,
+            commands::add_blame_ignore_rev,
+            commands::git_commit_fixup,
+            commands::git_revert,
+            commands::verify_commit_signatures,
+            commands::git_rebase_autosquash,
+            commands::git_stash_list,
+            commands::git_stash_push_files,
+            commands::git_stash_pop,
+            commands::git_stash_apply,
+            commands::git_stash_drop,
+            commands::git_stash_show,
+            commands::git_get_changed_files_with_stats,
+            commands::git_get_file_hunk_headers,
+            commands::git_get_hunk_body,
+            commands::git_discard_hunk,
+            commands::git_discard_selected_lines,
+            commands::git_lfs_status,
+            commands::git_lfs_pull,
+            commands::git_submodule_list,
+            commands::git_submodule_update,
+            commands::export_patch,
+            commands::apply_patch_file,
+            commands::bisect_start,
+            commands::bisect_mark,
+            commands::bisect_status,
+            commands::bisect_abort,
+            commands::request_destructive_confirmation,
+            commands::get_repo_capabilities,
+            commands::git_delete_branch,
+            commands::git_delete_remote_branch,
+            commands::git_prune_remote,
+            commands::git_list_remotes_detailed,
+            commands::bootstrap_repository,
+            commands::git_clone,
+            commands::list_gitignore_templates,
+            commands::git_log,
+            commands::git_get_graph,
+            commands::git_get_commit_diff,
+            commands::browse_revision,
+            commands::read_file_at_revision,
             commands::pty_create_session,
             commands::pty_session_exists,
             commands::pty_write,
             commands::pty_resize,
             commands::pty_close,
+            commands::pty_get_scrollback,
             commands::read_file,
+            commands::check_file_guard,
             commands::list_directory,
             commands::list_directory_cached,
             commands::get_change_indicators,
             commands::search_workspace_files,
+            commands::search_files,
+            commands::search_in_workspace,
+            commands::cancel_search,
+            commands::get_repo_language_stats,
             commands::create_session,
             commands::get_sessions,
             commands::update_session_access,
@@ -344,16 +593,43 @@ pub fn run() {
             commands::delete_session,
             commands::get_session_model,
             commands::set_session_model,
+            commands::list_session_models,
+            commands::save_session_model,
+            commands::delete_session_model,
+            commands::set_default_session_model,
+            commands::create_session_tab,
+            commands::get_session_tabs,
+            commands::get_workspace_session_tabs,
+            commands::rename_session_tab,
+            commands::update_session_tab_state,
+            commands::reorder_session_tabs,
+            commands::delete_session_tab,
             commands::mark_file_viewed,
             commands::unmark_file_viewed,
             commands::get_viewed_files,
             commands::clear_all_viewed_files,
+            commands::mark_hunk_viewed,
+            commands::unmark_hunk_viewed,
+            commands::get_file_hunk_review,
             commands::start_file_watcher,
             commands::stop_file_watcher,
+            commands::get_watcher_status,
+            commands::get_repo_lock_status,
+            commands::takeover_repo_lock,
+            commands::trigger_resync,
             commands::load_pending_review,
             commands::save_pending_review,
             commands::clear_pending_review,
+            commands::check_for_updates,
+            commands::schedule_update_restart,
+            commands::cancel_update_restart,
+            commands::get_update_restart_status,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                shutdown::run_exit_sequence(app);
+            }
+        });
 }
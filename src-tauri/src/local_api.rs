@@ -0,0 +1,548 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Take, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::commands;
+use crate::db::Database;
+use crate::file_indexer;
+use crate::jj;
+use crate::local_db;
+use crate::mcp;
+use crate::secrets;
+use crate::AppState;
+
+/// Settings key: "true" to run the local HTTP API
+pub const LOCAL_API_ENABLED_KEY: &str = "local_api_enabled";
+
+/// Settings key: port the local HTTP API listens on
+pub const LOCAL_API_PORT_KEY: &str = "local_api_port";
+
+/// Settings key: bearer token clients must send; generated on first enable
+pub const LOCAL_API_TOKEN_KEY: &str = "local_api_token";
+
+const DEFAULT_PORT: u16 = 47821;
+
+/// Requests with larger bodies are rejected
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Request line and headers together; larger requests are rejected before authentication
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+
+/// Requests with more header lines are rejected before authentication
+const MAX_HEADERS: usize = 64;
+
+/// Connections handled at once; further ones are answered with 503
+const MAX_CONNECTIONS: usize = 16;
+
+/// How often the accept loop checks for shutdown
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Current state of the local HTTP API
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LocalApiStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    pub token: Option<String>,
+    pub error: Option<String>,
+}
+
+struct RunningServer {
+    port: u16,
+    token: String,
+    shutdown: Arc<AtomicBool>,
+    /// The port is free again once the accept loop has returned
+    accept_loop: JoinHandle<()>,
+}
+
+static SERVER: OnceLock<Mutex<Option<RunningServer>>> = OnceLock::new();
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+static OPEN_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a connection against MAX_CONNECTIONS until dropped
+struct ConnectionSlot;
+
+impl ConnectionSlot {
+    fn acquire() -> Option<Self> {
+        OPEN_CONNECTIONS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < MAX_CONNECTIONS).then_some(open + 1)
+            })
+            .ok()
+            .map(|_| ConnectionSlot)
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        OPEN_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn server() -> &'static Mutex<Option<RunningServer>> {
+    SERVER.get_or_init(|| Mutex::new(None))
+}
+
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes).map_err(|e| format!("Failed to generate API token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn parse_port(value: Option<&str>) -> u16 {
+    value
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .filter(|p| *p >= 1024)
+        .unwrap_or(DEFAULT_PORT)
+}
+
+/// Stop the server, waiting for the accept loop to drop the listener so the port can
+/// be bound again right away
+fn stop() {
+    let running = server().lock().unwrap().take();
+    if let Some(running) = running {
+        running.shutdown.store(true, Ordering::SeqCst);
+        if running.accept_loop.join().is_err() {
+            log::warn!("Local API accept loop panicked");
+        }
+        log::info!("Stopped local API on port {}", running.port);
+    }
+}
+
+/// Start, restart or stop the server to match the stored settings
+pub fn apply_settings(app: &AppHandle, db: &Database) {
+    let enabled = db
+        .get_setting(LOCAL_API_ENABLED_KEY)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    if !enabled {
+        stop();
+        return;
+    }
+
+    let port = parse_port(db.get_setting(LOCAL_API_PORT_KEY).ok().flatten().as_deref());
+    let token = match secrets::get(LOCAL_API_TOKEN_KEY).ok().flatten() {
+        Some(token) if !token.trim().is_empty() => token,
+        _ => {
+            let token = match generate_token() {
+                Ok(token) => token,
+                Err(e) => {
+                    *LAST_ERROR.lock().unwrap() = Some(e);
+                    return;
+                }
+            };
+            if let Err(e) = secrets::set(LOCAL_API_TOKEN_KEY, &token) {
+                log::warn!("Failed to save local API token: {}", e);
+            }
+            token
+        }
+    };
+
+    if let Some(running) = server().lock().unwrap().as_ref() {
+        if running.port == port && running.token == token {
+            return;
+        }
+    }
+    stop();
+
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .and_then(|l| l.set_nonblocking(true).map(|_| l))
+    {
+        Ok(listener) => listener,
+        Err(e) => {
+            let message = format!("Failed to bind local API to port {}: {}", port, e);
+            log::warn!("{}", message);
+            *LAST_ERROR.lock().unwrap() = Some(message);
+            return;
+        }
+    };
+    *LAST_ERROR.lock().unwrap() = None;
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let accept_loop = {
+        let (app, token, shutdown) = (app.clone(), token.clone(), shutdown.clone());
+        std::thread::spawn(move || {
+            log::info!("Local API listening on 127.0.0.1:{}", port);
+            while !shutdown.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((mut stream, _)) => {
+                        let Some(slot) = ConnectionSlot::acquire() else {
+                            let _ = stream.set_nonblocking(false);
+                            let error = json!({ "error": "Too many connections" });
+                            write_response(&mut stream, 503, Some(&error));
+                            continue;
+                        };
+                        let app = app.clone();
+                        let token = token.clone();
+                        std::thread::spawn(move || {
+                            let _slot = slot;
+                            handle_connection(&app, stream, &token)
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                    }
+                    Err(e) => log::warn!("Local API accept failed: {}", e),
+                }
+            }
+        })
+    };
+    *server().lock().unwrap() = Some(RunningServer {
+        port,
+        token,
+        shutdown,
+        accept_loop,
+    });
+}
+
+/// Current server state for the settings UI
+pub fn status(db: &Database) -> LocalApiStatus {
+    let guard = server().lock().unwrap();
+    LocalApiStatus {
+        enabled: db
+            .get_setting(LOCAL_API_ENABLED_KEY)
+            .ok()
+            .flatten()
+            .is_some_and(|v| v == "true"),
+        running: guard.is_some(),
+        port: guard.as_ref().map(|s| s.port).unwrap_or_else(|| {
+            parse_port(db.get_setting(LOCAL_API_PORT_KEY).ok().flatten().as_deref())
+        }),
+        token: secrets::get(LOCAL_API_TOKEN_KEY).ok().flatten(),
+        error: LAST_ERROR.lock().unwrap().clone(),
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(b)) => {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+            (b'+', None) => out.push(b' '),
+            (b, None) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect()
+}
+
+fn headers_too_large() -> (u16, String) {
+    (431, "Request headers too large".to_string())
+}
+
+/// Read one line of the request head, failing once the head passes MAX_HEADER_BYTES
+fn read_head_line<R: BufRead>(head: &mut Take<R>) -> Result<String, (u16, String)> {
+    let mut line = String::new();
+    head.read_line(&mut line)
+        .map_err(|e| (400, e.to_string()))?;
+    if !line.ends_with('\n') && head.limit() == 0 {
+        return Err(headers_too_large());
+    }
+    Ok(line)
+}
+
+/// Parse a request; errors carry the status to answer with
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, (u16, String)> {
+    let mut head = reader.by_ref().take(MAX_HEADER_BYTES);
+    let request_line = read_head_line(&mut head)?;
+    let mut parts = request_line.split_whitespace();
+    let bad_request = |e: &str| (400, e.to_string());
+    let method = parts
+        .next()
+        .ok_or_else(|| bad_request("Empty request"))?
+        .to_string();
+    let target = parts
+        .next()
+        .ok_or_else(|| bad_request("Missing request target"))?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut headers = HashMap::new();
+    for count in 0.. {
+        let line = read_head_line(&mut head)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(headers_too_large());
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.insert(k.trim().to_ascii_lowercase(), v.trim().to_string());
+        }
+    }
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err(bad_request("Request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| (400, e.to_string()))?;
+
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query: parse_query(query),
+        headers,
+        body,
+    })
+}
+
+/// Compare tokens without short-circuiting on the first mismatching byte
+fn is_authorized(headers: &HashMap<String, String>, token: &str) -> bool {
+    let Some(provided) = headers
+        .get("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    provided.len() == token.len()
+        && provided
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[derive(Deserialize)]
+struct CreateWorkspaceBody {
+    repo_path: String,
+    branch_name: String,
+    #[serde(default)]
+    new_branch: bool,
+    source_branch: Option<String>,
+    metadata: Option<String>,
+    /// Name of a workspace template to apply
+    template: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RescanBody {
+    repo_path: String,
+    workspace_path: String,
+    workspace_id: Option<i64>,
+}
+
+fn required_query<'a>(request: &'a Request, key: &str) -> Result<&'a str, (u16, String)> {
+    request
+        .query
+        .get(key)
+        .map(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| (400, format!("Missing query parameter: {}", key)))
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(request: &Request) -> Result<T, (u16, String)> {
+    serde_json::from_slice(&request.body).map_err(|e| (400, format!("Invalid JSON body: {}", e)))
+}
+
+fn route(app: &AppHandle, request: &Request) -> Result<Value, (u16, String)> {
+    let internal = |e: String| (500, e);
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/health") => Ok(json!({ "ok": true })),
+        ("GET", "/api/workspaces") => {
+            let repo_path = required_query(request, "repo_path")?;
+            local_db::get_workspaces(repo_path)
+                .map(|w| json!(w))
+                .map_err(internal)
+        }
+        ("POST", "/api/workspaces") => {
+            let body: CreateWorkspaceBody = parse_body(request)?;
+            let id = commands::create_workspace(
+                app.clone(),
+                body.repo_path,
+                body.branch_name,
+                body.new_branch,
+                body.source_branch,
+                body.metadata,
+                body.template,
+                None,
+            )
+            .map_err(internal)?;
+            Ok(json!({ "id": id }))
+        }
+        ("GET", "/api/changed-files") => {
+            let workspace_path = required_query(request, "workspace_path")?;
+            jj::jj_get_changed_files(workspace_path)
+                .map(|files| json!(files))
+                .map_err(|e| internal(e.to_string()))
+        }
+        ("POST", "/api/rescan") => {
+            let body: RescanBody = parse_body(request)?;
+            file_indexer::index_workspace_files(
+                &body.repo_path,
+                body.workspace_id,
+                &body.workspace_path,
+            )
+            .map_err(internal)?;
+            Ok(json!({ "ok": true }))
+        }
+        _ => Err((404, "Not found".to_string())),
+    }
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: Option<&Value>) {
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn handle_connection(app: &AppHandle, mut stream: TcpStream, token: &str) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+
+    let request = match stream
+        .try_clone()
+        .map_err(|e| (400, e.to_string()))
+        .and_then(|s| read_request(&mut BufReader::new(s)))
+    {
+        Ok(request) => request,
+        Err((status, e)) => {
+            write_response(&mut stream, status, Some(&json!({ "error": e })));
+            return;
+        }
+    };
+
+    if !is_authorized(&request.headers, token) {
+        write_response(&mut stream, 401, Some(&json!({ "error": "Unauthorized" })));
+        return;
+    }
+
+    // MCP clients talk JSON-RPC over the same authenticated server
+    if request.method == "POST" && request.path == mcp::MCP_PATH && mcp::is_enabled(app) {
+        let (status, body) = mcp::handle_request(app, &request.body);
+        write_response(&mut stream, status, body.as_ref());
+        return;
+    }
+
+    match route(app, &request) {
+        Ok(body) => write_response(&mut stream, 200, Some(&body)),
+        Err((status, error)) => {
+            write_response(&mut stream, status, Some(&json!({ "error": error })))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_request_parses_query_and_body() {
+        let raw = "POST /api/rescan?repo_path=%2Ftmp%2Fmy+repo&x=1 HTTP/1.1\r\nAuthorization: Bearer abc\r\nContent-Length: 2\r\n\r\n{}";
+        let request = read_request(&mut Cursor::new(raw)).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/rescan");
+        assert_eq!(request.query.get("repo_path").unwrap(), "/tmp/my repo");
+        assert_eq!(request.body, b"{}");
+        assert!(is_authorized(&request.headers, "abc"));
+        assert!(!is_authorized(&request.headers, "abd"));
+        assert!(!is_authorized(&HashMap::new(), "abc"));
+    }
+
+    #[test]
+    fn test_read_request_rejects_oversized_head() {
+        let status = |raw: String| read_request(&mut Cursor::new(raw)).err().map(|(s, _)| s);
+
+        let long_line = format!(
+            "GET /{} HTTP/1.1\r\n\r\n",
+            "a".repeat(MAX_HEADER_BYTES as usize)
+        );
+        assert_eq!(status(long_line), Some(431));
+
+        let many_headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-A: b\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(status(many_headers), Some(431));
+
+        let body_too_large = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        assert_eq!(status(body_too_large), Some(400));
+
+        let at_limit = format!("GET / HTTP/1.1\r\n{}\r\n", "X-A: b\r\n".repeat(MAX_HEADERS));
+        assert!(read_request(&mut Cursor::new(at_limit)).is_ok());
+    }
+
+    #[test]
+    fn test_connection_slots_are_limited() {
+        let slots: Vec<_> = std::iter::from_fn(ConnectionSlot::acquire)
+            .take(MAX_CONNECTIONS + 1)
+            .collect();
+        assert_eq!(slots.len(), MAX_CONNECTIONS);
+        drop(slots);
+        assert!(ConnectionSlot::acquire().is_some());
+    }
+
+    #[test]
+    fn test_parse_port_and_token() {
+        assert_eq!(parse_port(Some("5000")), 5000);
+        assert_eq!(parse_port(Some("80")), DEFAULT_PORT);
+        assert_eq!(parse_port(None), DEFAULT_PORT);
+
+        let token = generate_token().unwrap();
+        assert_eq!(token.len(), 48);
+        assert_ne!(token, generate_token().unwrap());
+    }
+}
@@ -1,4 +1,3 @@
-use chrono::Utc;
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -6,6 +5,9 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 
+use crate::instance_lock;
+use crate::migrations::{self, Migration};
+use crate::timestamps;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Workspace {
@@ -28,10 +30,44 @@ pub struct Session {
     pub created_at: String,
     pub last_accessed: String,
     pub model: Option<String>,
+    /// Strictly increasing creation order, independent of clock changes
+    pub sort_key: i64,
+}
+
+/// A named terminal tab of a session
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionTab {
+    pub id: i64,
+    pub session_id: i64,
+    pub title: String,
+    pub cwd: Option<String>,
+    pub last_command: Option<String>,
+    /// Order of the tab within its session
+    pub position: i64,
+    /// Id of the PTY backing the tab, stable across restarts
+    pub pty_session_id: String,
+    pub created_at: String,
 }
 
 static INITIALIZED_DBS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
 
+/// `PRAGMA user_version` of databases whose timestamps were normalized before
+/// versioned migrations
+const TIMESTAMPS_NORMALIZED_VERSION: i64 = 1;
+
+/// Timestamp columns rewritten by the normalization migration
+const TIMESTAMP_COLUMNS: &[(&str, &str)] = &[
+    ("workspaces", "created_at"),
+    ("sessions", "created_at"),
+    ("sessions", "last_accessed"),
+    ("changed_files", "updated_at"),
+    ("workspace_files", "cached_at"),
+    ("language_stats", "computed_at"),
+    ("pending_reviews", "created_at"),
+    ("pending_reviews", "updated_at"),
+    ("pty_scrollback", "updated_at"),
+];
+
 /// Cached file information for workspace file indexing
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CachedWorkspaceFile {
@@ -46,6 +82,16 @@ pub struct CachedWorkspaceFile {
     pub mtime: Option<i64>,
 }
 
+/// Cached file count and size for a single file extension
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExtensionStat {
+    pub extension: String,
+    pub language: String,
+    pub file_count: i64,
+    pub total_bytes: i64,
+    pub computed_at: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PendingReview {
     pub id: i64,
@@ -63,8 +109,10 @@ pub fn get_local_db_path(repo_path: &str) -> PathBuf {
 
 /// Initialize the local database for a repository.
 ///
-/// Creates tables for workspaces, sessions, changed_files, and workspace_files.
-/// Handles schema migrations for backward compatibility.
+/// Creates tables for workspaces, sessions, session_tabs, changed_files, workspace_files,
+/// language_stats, pending_reviews, pty_scrollback, operation_journal, forge_checks, and
+/// snapshots.
+/// Schema changes are applied through `LOCAL_MIGRATIONS`.
 pub fn init_local_db(repo_path: &str) -> Result<(), String> {
     let db_path = get_local_db_path(repo_path);
     if let Some(parent) = db_path.parent() {
@@ -73,7 +121,32 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
     }
 
     let conn = Connection::open(db_path).map_err(|e| format!("Failed to open local db: {}", e))?;
+    migrations::migrate(&conn, LOCAL_MIGRATIONS)?;
+    Ok(())
+}
 
+/// Schema migrations of local.db, oldest first
+const LOCAL_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline schema",
+        apply: create_tables,
+    },
+    Migration {
+        version: 2,
+        name: "normalize timestamps",
+        apply: normalize_timestamps,
+    },
+    Migration {
+        version: 3,
+        name: "drop unused workspaces.archived",
+        apply: drop_workspace_archived_column,
+    },
+];
+
+/// The schema as it was before versioned migrations. Every step tolerates the older
+/// layouts found in databases created before then.
+fn create_tables(conn: &Connection) -> Result<(), String> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS workspaces (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -94,9 +167,9 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create workspaces branch index: {}", e))?;
 
-    let _ = conn.execute("ALTER TABLE workspaces ADD COLUMN target_branch TEXT", []);
-    let _ = conn.execute("ALTER TABLE workspaces ADD COLUMN has_conflicts BOOLEAN DEFAULT 0", []);
-    let _ = conn.execute("ALTER TABLE workspaces ADD COLUMN archived BOOLEAN DEFAULT 0", []);
+    migrations::add_column_if_missing(conn, "workspaces", "target_branch", "TEXT")?;
+    migrations::add_column_if_missing(conn, "workspaces", "has_conflicts", "BOOLEAN DEFAULT 0")?;
+    migrations::add_column_if_missing(conn, "workspaces", "archived", "BOOLEAN DEFAULT 0")?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS sessions (
@@ -148,7 +221,8 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
         }
     }
 
-    let _ = conn.execute("ALTER TABLE sessions ADD COLUMN model TEXT", []);
+    migrations::add_column_if_missing(conn, "sessions", "model", "TEXT")?;
+    migrations::add_column_if_missing(conn, "sessions", "sort_key", "INTEGER NOT NULL DEFAULT 0")?;
 
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_sessions_workspace ON sessions(workspace_id)",
@@ -156,10 +230,13 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create sessions workspace index: {}", e))?;
 
-    let _ = conn.execute("DROP TABLE IF EXISTS git_file_hunks", []);
-    let _ = conn.execute("DROP TABLE IF EXISTS git_changed_files", []);
-    let _ = conn.execute("DROP INDEX IF EXISTS idx_git_file_hunks_workspace", []);
-    let _ = conn.execute("DROP INDEX IF EXISTS idx_git_changed_files_workspace", []);
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS git_file_hunks;
+         DROP TABLE IF EXISTS git_changed_files;
+         DROP INDEX IF EXISTS idx_git_file_hunks_workspace;
+         DROP INDEX IF EXISTS idx_git_changed_files_workspace;",
+    )
+    .map_err(|e| format!("Failed to drop legacy git cache tables: {}", e))?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS changed_files (
@@ -200,7 +277,7 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create workspace_files table: {}", e))?;
 
-    let _ = conn.execute("ALTER TABLE workspace_files ADD COLUMN mtime INTEGER", []);
+    migrations::add_column_if_missing(conn, "workspace_files", "mtime", "INTEGER")?;
 
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_workspace_files_workspace ON workspace_files(workspace_id)",
@@ -214,6 +291,22 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create workspace_files parent index: {}", e))?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS language_stats (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            workspace_id INTEGER,
+            extension TEXT NOT NULL,
+            language TEXT NOT NULL,
+            file_count INTEGER NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            computed_at TEXT NOT NULL,
+            FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+            UNIQUE(workspace_id, extension)
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create language_stats table: {}", e))?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS pending_reviews (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -292,6 +385,190 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
         }
     }
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pty_scrollback (
+            session_id TEXT PRIMARY KEY,
+            working_dir TEXT,
+            content TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create pty_scrollback table: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_tabs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            cwd TEXT,
+            last_command TEXT,
+            position INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create session_tabs table: {}", e))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_tabs_session ON session_tabs(session_id, position)",
+        [],
+    )
+    .map_err(|e| format!("Failed to create session_tabs session index: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS operation_journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            name TEXT NOT NULL,
+            workspace_id INTEGER,
+            command TEXT,
+            success INTEGER NOT NULL,
+            output TEXT NOT NULL,
+            duration_ms INTEGER,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create operation_journal table: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS forge_checks (
+            sha TEXT PRIMARY KEY,
+            state TEXT,
+            checks TEXT NOT NULL,
+            fetched_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create forge_checks table: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            workspace_id INTEGER,
+            workspace_path TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            base_commit TEXT NOT NULL,
+            patch_file TEXT NOT NULL,
+            file_count INTEGER NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create snapshots table: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS workspace_archives (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            workspace_name TEXT NOT NULL,
+            branch_name TEXT NOT NULL,
+            target_branch TEXT,
+            metadata TEXT,
+            base_commit TEXT NOT NULL,
+            patch_file TEXT,
+            file_count INTEGER NOT NULL,
+            archived_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create workspace_archives table: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS git_cache (
+            workspace_path TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            params TEXT NOT NULL,
+            state_key TEXT NOT NULL,
+            data TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (workspace_path, kind, params)
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create git_cache table: {}", e))?;
+
+    Ok(())
+}
+
+/// Rewrite timestamps stored before they were standardized (local offsets, SQLite
+/// `CURRENT_TIMESTAMP`) as RFC3339 UTC and backfill session ordering keys.
+/// Databases normalized before versioned migrations recorded it in `PRAGMA user_version`
+/// and are skipped, so reordered sessions keep their order.
+fn normalize_timestamps(conn: &Connection) -> Result<(), String> {
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+    if version >= TIMESTAMPS_NORMALIZED_VERSION {
+        return Ok(());
+    }
+
+    for (table, column) in TIMESTAMP_COLUMNS {
+        let rows: Vec<(i64, String)> = {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT rowid, {column} FROM {table} WHERE {column} IS NOT NULL"
+                ))
+                .map_err(|e| format!("Failed to read {}.{}: {}", table, column, e))?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| format!("Failed to read {}.{}: {}", table, column, e))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+
+        for (rowid, value) in rows {
+            let normalized = timestamps::normalize(&value);
+            if normalized != value {
+                conn.execute(
+                    &format!("UPDATE {table} SET {column} = ?1 WHERE rowid = ?2"),
+                    params![normalized, rowid],
+                )
+                .map_err(|e| format!("Failed to normalize {}.{}: {}", table, column, e))?;
+            }
+        }
+    }
+
+    // Order existing sessions by creation time, keeping keys strictly increasing
+    let sessions: Vec<(i64, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, created_at FROM sessions ORDER BY id ASC")
+            .map_err(|e| format!("Failed to read sessions: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to read sessions: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    let mut sessions: Vec<(i64, i64)> = sessions
+        .into_iter()
+        .map(|(id, created_at)| (timestamps::sort_key(&created_at), id))
+        .collect();
+    sessions.sort();
+    let mut last_key = 0;
+    for (key, id) in sessions {
+        last_key = key.max(last_key + 1);
+        conn.execute(
+            "UPDATE sessions SET sort_key = ?1 WHERE id = ?2",
+            params![last_key, id],
+        )
+        .map_err(|e| format!("Failed to backfill session order: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// `workspaces.archived` was never set; archived workspaces are rows of
+/// `workspace_archives` instead
+fn drop_workspace_archived_column(conn: &Connection) -> Result<(), String> {
+    let exists = migrations::has_column(conn, "workspaces", "archived")
+        .map_err(|e| format!("Failed to inspect workspaces: {}", e))?;
+    if exists {
+        conn.execute("ALTER TABLE workspaces DROP COLUMN archived", [])
+            .map_err(|e| format!("Failed to drop workspaces.archived: {}", e))?;
+    }
     Ok(())
 }
 
@@ -300,6 +577,9 @@ pub fn init_local_db(repo_path: &str) -> Result<(), String> {
 /// Ensures the database is initialized before returning the connection.
 /// Uses a cache to avoid re-initializing databases that have already been set up in this session.
 fn get_connection(repo_path: &str) -> Result<Connection, String> {
+    // Another instance owning this repo may be migrating or writing the same db
+    instance_lock::ensure_repo_lock(repo_path)?;
+
     let initialized = INITIALIZED_DBS.get_or_init(|| Mutex::new(HashSet::new()));
     let db_key = repo_path.to_string();
 
@@ -403,7 +683,7 @@ pub fn add_workspace(
     metadata: Option<String>,
 ) -> Result<i64, String> {
     let conn = get_connection(repo_path)?;
-    let created_at = Utc::now().to_rfc3339();
+    let created_at = timestamps::now();
 
     conn.execute(
         "INSERT INTO workspaces (workspace_name, workspace_path, branch_name, created_at, metadata)
@@ -423,6 +703,11 @@ pub fn add_workspace(
 
 pub fn delete_workspace(repo_path: &str, id: i64) -> Result<(), String> {
     let conn = get_connection(repo_path)?;
+    conn.execute(
+        "DELETE FROM git_cache WHERE workspace_path = (SELECT workspace_path FROM workspaces WHERE id = ?1)",
+        [id],
+    )
+    .map_err(|e| format!("Failed to clear git cache: {}", e))?;
     conn.execute("DELETE FROM workspaces WHERE id = ?1", [id])
         .map_err(|e| format!("Failed to delete workspace: {}", e))?;
     Ok(())
@@ -621,7 +906,7 @@ pub fn rebuild_workspaces_from_filesystem(repo_path: &str) -> Result<Vec<Workspa
             workspace_name,
             workspace_path,
             branch_name,
-            created_at: Utc::now().to_rfc3339(),
+            created_at: timestamps::now(),
             metadata: None,
             target_branch: None,
             has_conflicts: false,
@@ -640,12 +925,12 @@ pub fn rebuild_workspaces_from_filesystem(repo_path: &str) -> Result<Vec<Workspa
 /// Returns the branch name, or "HEAD" if in detached state with no bookmark.
 fn get_workspace_branch(workspace_path: &str) -> Result<String, String> {
     use crate::binary_paths;
-    use std::process::Command;
+    use crate::process_limiter::LimitedCommand;
 
     /// Helper function to create Command for a binary using cached path
-    fn command_for(binary: &str) -> Command {
+    fn command_for(binary: &str) -> LimitedCommand {
         let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
-        Command::new(path)
+        LimitedCommand::new(path)
     }
 
     let output = command_for("git")
@@ -661,8 +946,7 @@ fn get_workspace_branch(workspace_path: &str) -> Result<String, String> {
             return Ok(branch);
         }
 
-        if let Ok(jj_output) = command_for("jj")
-            .current_dir(workspace_path)
+        if let Ok(jj_output) = crate::jj::jj_command(workspace_path)
             .args(["bookmark", "list", "--no-pager"])
             .output()
         {
@@ -692,7 +976,7 @@ fn get_workspace_branch(workspace_path: &str) -> Result<String, String> {
 pub fn get_sessions(repo_path: &str) -> Result<Vec<Session>, String> {
     let conn = get_connection(repo_path)?;
     let mut stmt = conn
-        .prepare("SELECT id, workspace_id, name, created_at, last_accessed, model FROM sessions ORDER BY created_at ASC")
+        .prepare("SELECT id, workspace_id, name, created_at, last_accessed, model, sort_key FROM sessions ORDER BY sort_key ASC, id ASC")
         .map_err(|e| format!("Failed to prepare sessions query: {}", e))?;
 
     let sessions = stmt
@@ -704,6 +988,7 @@ pub fn get_sessions(repo_path: &str) -> Result<Vec<Session>, String> {
                 created_at: row.get(3)?,
                 last_accessed: row.get(4)?,
                 model: row.get(5)?,
+                sort_key: row.get(6)?,
             })
         })
         .map_err(|e| format!("Failed to query sessions: {}", e))?;
@@ -719,12 +1004,14 @@ pub fn add_session(
     name: String,
 ) -> Result<i64, String> {
     let conn = get_connection(repo_path)?;
-    let now = Utc::now().to_rfc3339();
+    let now = timestamps::now();
+    let now_ms = timestamps::sort_key(&now);
 
+    // The wall clock can go backwards; keep creation order strictly increasing
     conn.execute(
-        "INSERT INTO sessions (workspace_id, name, created_at, last_accessed, model)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![workspace_id, name, now, now, None::<String>],
+        "INSERT INTO sessions (workspace_id, name, created_at, last_accessed, model, sort_key)
+         VALUES (?1, ?2, ?3, ?4, ?5, MAX(?6, COALESCE((SELECT MAX(sort_key) FROM sessions), 0) + 1))",
+        params![workspace_id, name, now, now, None::<String>, now_ms],
     )
     .map_err(|e| format!("Failed to insert session: {}", e))?;
 
@@ -733,7 +1020,7 @@ pub fn add_session(
 
 pub fn update_session_access(repo_path: &str, id: i64) -> Result<(), String> {
     let conn = get_connection(repo_path)?;
-    let now = Utc::now().to_rfc3339();
+    let now = timestamps::now();
 
     conn.execute(
         "UPDATE sessions SET last_accessed = ?1 WHERE id = ?2",
@@ -786,6 +1073,175 @@ pub fn set_session_model(repo_path: &str, id: i64, model: Option<String>) -> Res
     Ok(())
 }
 
+// ============================================================================
+// Session Tab Functions
+// ============================================================================
+
+const SESSION_TAB_COLUMNS: &str =
+    "id, session_id, title, cwd, last_command, position, created_at";
+
+fn session_tab_from_row(row: &rusqlite::Row) -> rusqlite::Result<SessionTab> {
+    let id: i64 = row.get(0)?;
+    let session_id: i64 = row.get(1)?;
+    Ok(SessionTab {
+        id,
+        session_id,
+        title: row.get(2)?,
+        cwd: row.get(3)?,
+        last_command: row.get(4)?,
+        position: row.get(5)?,
+        pty_session_id: format!("session-{}-tab-{}", session_id, id),
+        created_at: row.get(6)?,
+    })
+}
+
+fn get_session_tab(conn: &Connection, tab_id: i64) -> Result<SessionTab, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM session_tabs WHERE id = ?1", SESSION_TAB_COLUMNS),
+        [tab_id],
+        session_tab_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get session tab: {}", e))?
+    .ok_or_else(|| format!("Session tab {} not found", tab_id))
+}
+
+/// Add a tab after the last tab of a session
+pub fn add_session_tab(
+    repo_path: &str,
+    session_id: i64,
+    title: &str,
+    cwd: Option<&str>,
+) -> Result<SessionTab, String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute(
+        "INSERT INTO session_tabs (session_id, title, cwd, position, created_at)
+         VALUES (?1, ?2, ?3, COALESCE((SELECT MAX(position) FROM session_tabs WHERE session_id = ?1), -1) + 1, ?4)",
+        params![session_id, title, cwd, timestamps::now()],
+    )
+    .map_err(|e| format!("Failed to add session tab: {}", e))?;
+
+    get_session_tab(&conn, conn.last_insert_rowid())
+}
+
+/// Tabs of a session in display order
+pub fn get_session_tabs(repo_path: &str, session_id: i64) -> Result<Vec<SessionTab>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM session_tabs WHERE session_id = ?1 ORDER BY position ASC, id ASC",
+            SESSION_TAB_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare session tabs query: {}", e))?;
+
+    let tabs = stmt
+        .query_map([session_id], session_tab_from_row)
+        .map_err(|e| format!("Failed to query session tabs: {}", e))?;
+
+    tabs.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Tabs of every session of a workspace (None = main repository), grouped by session
+/// in session order, to restore the tab layout when the workspace is reopened
+pub fn get_workspace_session_tabs(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+) -> Result<Vec<SessionTab>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id, t.session_id, t.title, t.cwd, t.last_command, t.position, t.created_at
+             FROM session_tabs t
+             JOIN sessions s ON s.id = t.session_id
+             WHERE s.workspace_id IS ?1
+             ORDER BY s.sort_key ASC, s.id ASC, t.position ASC, t.id ASC",
+        )
+        .map_err(|e| format!("Failed to prepare session tabs query: {}", e))?;
+
+    let tabs = stmt
+        .query_map(params![workspace_id], session_tab_from_row)
+        .map_err(|e| format!("Failed to query session tabs: {}", e))?;
+
+    tabs.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+pub fn rename_session_tab(repo_path: &str, tab_id: i64, title: &str) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute(
+        "UPDATE session_tabs SET title = ?1 WHERE id = ?2",
+        params![title, tab_id],
+    )
+    .map_err(|e| format!("Failed to rename session tab: {}", e))?;
+
+    Ok(())
+}
+
+/// Remember where a tab's shell is and what it last ran; None leaves a field as is
+pub fn update_session_tab_state(
+    repo_path: &str,
+    tab_id: i64,
+    cwd: Option<&str>,
+    last_command: Option<&str>,
+) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute(
+        "UPDATE session_tabs
+         SET cwd = COALESCE(?1, cwd), last_command = COALESCE(?2, last_command)
+         WHERE id = ?3",
+        params![cwd, last_command, tab_id],
+    )
+    .map_err(|e| format!("Failed to update session tab: {}", e))?;
+
+    Ok(())
+}
+
+/// Put the tabs of a session in the order of `tab_ids`, which must list each of its
+/// tabs once
+pub fn reorder_session_tabs(
+    repo_path: &str,
+    session_id: i64,
+    tab_ids: &[i64],
+) -> Result<(), String> {
+    let mut current: Vec<i64> = get_session_tabs(repo_path, session_id)?
+        .iter()
+        .map(|tab| tab.id)
+        .collect();
+    let mut requested = tab_ids.to_vec();
+    current.sort_unstable();
+    requested.sort_unstable();
+    if current != requested {
+        return Err(format!(
+            "Tab order must list each tab of session {} exactly once",
+            session_id
+        ));
+    }
+
+    let mut conn = get_connection(repo_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    for (position, tab_id) in tab_ids.iter().enumerate() {
+        tx.execute(
+            "UPDATE session_tabs SET position = ?1 WHERE id = ?2",
+            params![position as i64, tab_id],
+        )
+        .map_err(|e| format!("Failed to reorder session tabs: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(())
+}
+
+pub fn delete_session_tab(repo_path: &str, tab_id: i64) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute("DELETE FROM session_tabs WHERE id = ?1", [tab_id])
+        .map_err(|e| format!("Failed to delete session tab: {}", e))?;
+    Ok(())
+}
+
 // ============================================================================
 // Workspace Files Cache Functions
 // ============================================================================
@@ -885,6 +1341,40 @@ pub fn search_workspace_files(
         .map_err(|e| e.to_string())
 }
 
+/// All cached (non-directory) files of a workspace, for ranking outside SQL
+pub fn get_cached_files(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+) -> Result<Vec<CachedWorkspaceFile>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, workspace_id, file_path, relative_path, is_directory, parent_path, cached_at, mtime
+             FROM workspace_files
+             WHERE workspace_id IS ?1 AND is_directory = 0",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let files = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok(CachedWorkspaceFile {
+                id: row.get(0)?,
+                workspace_id: row.get(1)?,
+                file_path: row.get(2)?,
+                relative_path: row.get(3)?,
+                is_directory: row.get::<_, i64>(4)? != 0,
+                parent_path: row.get(5)?,
+                cached_at: row.get(6)?,
+                mtime: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query cached files: {}", e))?;
+
+    files
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
 /// Batch update all cached files for a workspace.
 ///
 /// Deletes all existing entries for the workspace and inserts the provided files.
@@ -920,97 +1410,761 @@ pub fn sync_workspace_files(
                 &file.mtime,
             ],
         )
-        .map_err(|e| format!("Failed to insert file: {}", e))?;
-    }
+        .map_err(|e| format!("Failed to insert file: {}", e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(())
+}
+
+/// Insert or replace cached entries by path, leaving the rest of the workspace cache intact.
+///
+/// Used for incremental updates from the file watcher; directory entries for new parents
+/// should be included in `files`.
+pub fn upsert_workspace_files(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+    files: &[CachedWorkspaceFile],
+) -> Result<(), String> {
+    let mut conn = get_connection(repo_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for file in files {
+        // Delete-then-insert: UNIQUE(workspace_id, file_path) never conflicts on a NULL workspace_id
+        tx.execute(
+            "DELETE FROM workspace_files WHERE workspace_id IS ?1 AND file_path = ?2",
+            params![workspace_id, &file.file_path],
+        )
+        .map_err(|e| format!("Failed to replace file: {}", e))?;
+        tx.execute(
+            "INSERT INTO workspace_files
+             (workspace_id, file_path, relative_path, is_directory, parent_path, cached_at, mtime)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                workspace_id,
+                &file.file_path,
+                &file.relative_path,
+                if file.is_directory { 1 } else { 0 },
+                &file.parent_path,
+                &file.cached_at,
+                &file.mtime,
+            ],
+        )
+        .map_err(|e| format!("Failed to insert file: {}", e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(())
+}
+
+/// Remove cached entries for deleted paths, including everything below deleted directories,
+/// then prune directory entries left without children. Returns the number of removed rows.
+pub fn delete_workspace_files(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+    file_paths: &[String],
+) -> Result<usize, String> {
+    let mut conn = get_connection(repo_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut removed = 0;
+    for file_path in file_paths {
+        // substr instead of LIKE so '%' and '_' in paths are not wildcards
+        removed += tx
+            .execute(
+                "DELETE FROM workspace_files
+                 WHERE workspace_id IS ?1
+                   AND (file_path = ?2 OR substr(file_path, 1, length(?2) + 1) = ?2 || '/')",
+                params![workspace_id, file_path],
+            )
+            .map_err(|e| format!("Failed to delete file: {}", e))?;
+    }
+
+    // Each pass removes one level of newly empty directories
+    if removed > 0 {
+        loop {
+            let pruned = tx
+                .execute(
+                    "DELETE FROM workspace_files
+                     WHERE workspace_id IS ?1 AND is_directory = 1
+                       AND NOT EXISTS (
+                           SELECT 1 FROM workspace_files AS child
+                           WHERE child.workspace_id IS ?1
+                             AND child.parent_path = workspace_files.file_path
+                       )",
+                    params![workspace_id],
+                )
+                .map_err(|e| format!("Failed to prune directories: {}", e))?;
+            if pruned == 0 {
+                break;
+            }
+            removed += pruned;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(removed)
+}
+
+/// List the absolute paths of all cached (non-directory) files for a workspace
+pub fn list_workspace_file_paths(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+) -> Result<Vec<String>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare("SELECT file_path FROM workspace_files WHERE workspace_id IS ?1 AND is_directory = 0")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let paths = stmt
+        .query_map(params![workspace_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to query cached files: {}", e))?;
+
+    paths
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Language Stats Cache Functions
+// ============================================================================
+
+/// Get cached per-extension stats for a workspace, largest first
+pub fn get_language_stats(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+) -> Result<Vec<ExtensionStat>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT extension, language, file_count, total_bytes, computed_at
+             FROM language_stats
+             WHERE workspace_id IS ?1
+             ORDER BY total_bytes DESC, extension",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let stats = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok(ExtensionStat {
+                extension: row.get(0)?,
+                language: row.get(1)?,
+                file_count: row.get(2)?,
+                total_bytes: row.get(3)?,
+                computed_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query language stats: {}", e))?;
+
+    stats
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Replace the cached per-extension stats for a workspace
+pub fn save_language_stats(
+    repo_path: &str,
+    workspace_id: Option<i64>,
+    stats: &[ExtensionStat],
+) -> Result<(), String> {
+    let mut conn = get_connection(repo_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    tx.execute(
+        "DELETE FROM language_stats WHERE workspace_id IS ?1",
+        params![workspace_id],
+    )
+    .map_err(|e| format!("Failed to delete existing language stats: {}", e))?;
+
+    for stat in stats {
+        tx.execute(
+            "INSERT INTO language_stats
+             (workspace_id, extension, language, file_count, total_bytes, computed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                workspace_id,
+                &stat.extension,
+                &stat.language,
+                stat.file_count,
+                stat.total_bytes,
+                &stat.computed_at,
+            ],
+        )
+        .map_err(|e| format!("Failed to insert language stat: {}", e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Pending Review Functions
+// ============================================================================
+
+/// Get pending review for a workspace
+pub fn get_pending_review(
+    repo_path: &str,
+    workspace_id: i64,
+) -> Result<Option<PendingReview>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, workspace_id, comments, viewed_files, summary_text, created_at, updated_at
+             FROM pending_reviews
+             WHERE workspace_id = ?1",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let review = stmt
+        .query_row([workspace_id], |row| {
+            Ok(PendingReview {
+                id: row.get(0)?,
+                workspace_id: row.get(1)?,
+                comments: row.get(2)?,
+                viewed_files: row.get(3)?,
+                summary_text: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        })
+        .optional()
+        .map_err(|e| format!("Failed to get pending review: {}", e))?;
+
+    Ok(review)
+}
+
+/// Save or update pending review for a workspace
+pub fn save_pending_review(
+    repo_path: &str,
+    workspace_id: i64,
+    comments: &str,
+    viewed_files: Option<&str>,
+    summary_text: Option<&str>,
+) -> Result<i64, String> {
+    let conn = get_connection(repo_path)?;
+    let now = timestamps::now();
+
+    // Use INSERT OR REPLACE to handle both insert and update
+    conn.execute(
+        "INSERT OR REPLACE INTO pending_reviews (workspace_id, comments, viewed_files, summary_text, created_at, updated_at)
+         VALUES (
+             ?1,
+             ?2,
+             ?3,
+             ?4,
+             COALESCE((SELECT created_at FROM pending_reviews WHERE workspace_id = ?1), ?5),
+             ?5
+         )",
+        params![workspace_id, comments, viewed_files, summary_text, now],
+    )
+    .map_err(|e| format!("Failed to save pending review: {}", e))?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Clear pending review for a workspace
+pub fn clear_pending_review(repo_path: &str, workspace_id: i64) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute(
+        "DELETE FROM pending_reviews WHERE workspace_id = ?1",
+        [workspace_id],
+    )
+    .map_err(|e| format!("Failed to clear pending review: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// PTY Scrollback Functions
+// ============================================================================
+
+/// Save a terminal session's scrollback so it can be restored after a restart
+pub fn save_pty_scrollback(
+    repo_path: &str,
+    session_id: &str,
+    working_dir: Option<&str>,
+    content: &str,
+) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
+    let now = timestamps::now();
+
+    conn.execute(
+        "INSERT OR REPLACE INTO pty_scrollback (session_id, working_dir, content, updated_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![session_id, working_dir, content, now],
+    )
+    .map_err(|e| format!("Failed to save pty scrollback: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the saved scrollback of a terminal session
+pub fn get_pty_scrollback(repo_path: &str, session_id: &str) -> Result<Option<String>, String> {
+    let conn = get_connection(repo_path)?;
+    conn.query_row(
+        "SELECT content FROM pty_scrollback WHERE session_id = ?1",
+        [session_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get pty scrollback: {}", e))
+}
+
+// ============================================================================
+// Operation Journal Functions
+// ============================================================================
+
+/// A recorded background operation, e.g. an automation hook run.
+///
+/// Entries are not tied to the workspace row, so they outlive deleted workspaces.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub id: i64,
+    /// What produced the entry, e.g. "hook"
+    pub kind: String,
+    /// Event or operation name, e.g. "commit-made"
+    pub name: String,
+    pub workspace_id: Option<i64>,
+    pub command: Option<String>,
+    pub success: bool,
+    pub output: String,
+    pub duration_ms: Option<i64>,
+    pub created_at: String,
+}
+
+/// Append an entry to the operation journal, returning its id
+pub fn add_journal_entry(repo_path: &str, entry: &JournalEntry) -> Result<i64, String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute(
+        "INSERT INTO operation_journal
+         (kind, name, workspace_id, command, success, output, duration_ms, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            &entry.kind,
+            &entry.name,
+            entry.workspace_id,
+            &entry.command,
+            entry.success as i64,
+            &entry.output,
+            entry.duration_ms,
+            &entry.created_at,
+        ],
+    )
+    .map_err(|e| format!("Failed to add journal entry: {}", e))?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Most recent journal entries first
+pub fn get_journal_entries(repo_path: &str, limit: usize) -> Result<Vec<JournalEntry>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, kind, name, workspace_id, command, success, output, duration_ms, created_at
+             FROM operation_journal
+             ORDER BY id DESC
+             LIMIT ?1",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-    tx.commit()
-        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    let entries = stmt
+        .query_map(params![limit as i64], |row| {
+            Ok(JournalEntry {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                name: row.get(2)?,
+                workspace_id: row.get(3)?,
+                command: row.get(4)?,
+                success: row.get::<_, i64>(5)? != 0,
+                output: row.get(6)?,
+                duration_ms: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query journal: {}", e))?;
 
-    Ok(())
+    entries
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
 }
 
-// ============================================================================
-// Pending Review Functions
-// ============================================================================
-
-/// Get pending review for a workspace
-pub fn get_pending_review(
+/// Most recent journal entries of one kind first, optionally only those named `name`
+pub fn get_journal_entries_of_kind(
     repo_path: &str,
-    workspace_id: i64,
-) -> Result<Option<PendingReview>, String> {
+    kind: &str,
+    name: Option<&str>,
+    limit: usize,
+) -> Result<Vec<JournalEntry>, String> {
     let conn = get_connection(repo_path)?;
     let mut stmt = conn
         .prepare(
-            "SELECT id, workspace_id, comments, viewed_files, summary_text, created_at, updated_at
-             FROM pending_reviews
-             WHERE workspace_id = ?1",
+            "SELECT id, kind, name, workspace_id, command, success, output, duration_ms, created_at
+             FROM operation_journal
+             WHERE kind = ?1 AND (?2 IS NULL OR name = ?2)
+             ORDER BY id DESC
+             LIMIT ?3",
         )
         .map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-    let review = stmt
-        .query_row([workspace_id], |row| {
-            Ok(PendingReview {
+    let entries = stmt
+        .query_map(params![kind, name, limit as i64], |row| {
+            Ok(JournalEntry {
                 id: row.get(0)?,
-                workspace_id: row.get(1)?,
-                comments: row.get(2)?,
-                viewed_files: row.get(3)?,
-                summary_text: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
+                kind: row.get(1)?,
+                name: row.get(2)?,
+                workspace_id: row.get(3)?,
+                command: row.get(4)?,
+                success: row.get::<_, i64>(5)? != 0,
+                output: row.get(6)?,
+                duration_ms: row.get(7)?,
+                created_at: row.get(8)?,
             })
         })
-        .optional()
-        .map_err(|e| format!("Failed to get pending review: {}", e))?;
+        .map_err(|e| format!("Failed to query journal: {}", e))?;
 
-    Ok(review)
+    entries
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
 }
 
-/// Save or update pending review for a workspace
-pub fn save_pending_review(
-    repo_path: &str,
-    workspace_id: i64,
-    comments: &str,
-    viewed_files: Option<&str>,
-    summary_text: Option<&str>,
-) -> Result<i64, String> {
+/// CI results for a commit as last fetched from the forge
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedChecks {
+    pub sha: String,
+    /// Combined state; None when no checks report
+    pub state: Option<String>,
+    pub checks: String, // JSON string
+    pub fetched_at: String,
+}
+
+pub fn get_cached_checks(repo_path: &str, sha: &str) -> Result<Option<CachedChecks>, String> {
     let conn = get_connection(repo_path)?;
-    let now = Utc::now().to_rfc3339();
+    conn.query_row(
+        "SELECT sha, state, checks, fetched_at FROM forge_checks WHERE sha = ?1",
+        params![sha],
+        |row| {
+            Ok(CachedChecks {
+                sha: row.get(0)?,
+                state: row.get(1)?,
+                checks: row.get(2)?,
+                fetched_at: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to query cached checks: {}", e))
+}
 
-    // Use INSERT OR REPLACE to handle both insert and update
+pub fn save_cached_checks(repo_path: &str, cached: &CachedChecks) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
     conn.execute(
-        "INSERT OR REPLACE INTO pending_reviews (workspace_id, comments, viewed_files, summary_text, created_at, updated_at)
-         VALUES (
-             ?1,
-             ?2,
-             ?3,
-             ?4,
-             COALESCE((SELECT created_at FROM pending_reviews WHERE workspace_id = ?1), ?5),
-             ?5
-         )",
-        params![workspace_id, comments, viewed_files, summary_text, now],
+        "INSERT INTO forge_checks (sha, state, checks, fetched_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(sha) DO UPDATE SET
+            state = excluded.state,
+            checks = excluded.checks,
+            fetched_at = excluded.fetched_at",
+        params![&cached.sha, &cached.state, &cached.checks, &cached.fetched_at],
     )
-    .map_err(|e| format!("Failed to save pending review: {}", e))?;
+    .map_err(|e| format!("Failed to save cached checks: {}", e))?;
+    Ok(())
+}
+
+/// Patch of uncommitted changes saved before a destructive operation. Like journal
+/// entries, snapshots outlive the workspace row they were taken from.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    pub id: i64,
+    pub workspace_id: Option<i64>,
+    pub workspace_path: String,
+    /// What was about to discard the changes, e.g. "discard all changes"
+    pub operation: String,
+    /// Commit the patch applies on top of
+    pub base_commit: String,
+    /// File name under `.treq/snapshots`
+    pub patch_file: String,
+    pub file_count: i64,
+    pub size_bytes: i64,
+    pub created_at: String,
+}
+
+const SNAPSHOT_COLUMNS: &str = "id, workspace_id, workspace_path, operation, base_commit, patch_file, file_count, size_bytes, created_at";
+
+fn snapshot_from_row(row: &rusqlite::Row) -> rusqlite::Result<Snapshot> {
+    Ok(Snapshot {
+        id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        workspace_path: row.get(2)?,
+        operation: row.get(3)?,
+        base_commit: row.get(4)?,
+        patch_file: row.get(5)?,
+        file_count: row.get(6)?,
+        size_bytes: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+/// Record a snapshot, returning its id
+pub fn add_snapshot(repo_path: &str, snapshot: &Snapshot) -> Result<i64, String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute(
+        "INSERT INTO snapshots
+         (workspace_id, workspace_path, operation, base_commit, patch_file, file_count, size_bytes, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            snapshot.workspace_id,
+            &snapshot.workspace_path,
+            &snapshot.operation,
+            &snapshot.base_commit,
+            &snapshot.patch_file,
+            snapshot.file_count,
+            snapshot.size_bytes,
+            &snapshot.created_at,
+        ],
+    )
+    .map_err(|e| format!("Failed to add snapshot: {}", e))?;
 
     Ok(conn.last_insert_rowid())
 }
 
-/// Clear pending review for a workspace
-pub fn clear_pending_review(repo_path: &str, workspace_id: i64) -> Result<(), String> {
+/// Most recent snapshots first
+pub fn get_snapshots(repo_path: &str) -> Result<Vec<Snapshot>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM snapshots ORDER BY id DESC",
+            SNAPSHOT_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let snapshots = stmt
+        .query_map([], snapshot_from_row)
+        .map_err(|e| format!("Failed to query snapshots: {}", e))?;
+
+    snapshots
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+pub fn get_snapshot(repo_path: &str, id: i64) -> Result<Option<Snapshot>, String> {
+    let conn = get_connection(repo_path)?;
+    conn.query_row(
+        &format!("SELECT {} FROM snapshots WHERE id = ?1", SNAPSHOT_COLUMNS),
+        params![id],
+        snapshot_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to query snapshot: {}", e))
+}
+
+pub fn delete_snapshot(repo_path: &str, id: i64) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute("DELETE FROM snapshots WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete snapshot: {}", e))?;
+    Ok(())
+}
+
+/// Cached result of an expensive git/jj query, as (state key, JSON data)
+pub fn get_git_cache(
+    repo_path: &str,
+    workspace_path: &str,
+    kind: &str,
+    params: &str,
+) -> Result<Option<(String, String)>, String> {
+    let conn = get_connection(repo_path)?;
+    conn.query_row(
+        "SELECT state_key, data FROM git_cache WHERE workspace_path = ?1 AND kind = ?2 AND params = ?3",
+        params![workspace_path, kind, params],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to query git cache: {}", e))
+}
+
+/// Store a result, replacing the previous one for the same query
+pub fn set_git_cache(
+    repo_path: &str,
+    workspace_path: &str,
+    kind: &str,
+    params: &str,
+    state_key: &str,
+    data: &str,
+) -> Result<(), String> {
     let conn = get_connection(repo_path)?;
     conn.execute(
-        "DELETE FROM pending_reviews WHERE workspace_id = ?1",
-        [workspace_id],
+        "INSERT OR REPLACE INTO git_cache (workspace_path, kind, params, state_key, data, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![workspace_path, kind, params, state_key, data, timestamps::now()],
     )
-    .map_err(|e| format!("Failed to clear pending review: {}", e))?;
+    .map_err(|e| format!("Failed to write git cache: {}", e))?;
+    prune_git_cache(&conn, GIT_CACHE_MAX_ENTRIES)
+}
+
+/// Entries kept in `git_cache`; every distinct query, e.g. each commit's diff, adds one
+const GIT_CACHE_MAX_ENTRIES: usize = 2000;
+
+/// Delete all but the `keep` most recently written cache entries
+fn prune_git_cache(conn: &Connection, keep: usize) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM git_cache WHERE rowid NOT IN
+         (SELECT rowid FROM git_cache ORDER BY updated_at DESC, rowid DESC LIMIT ?1)",
+        params![keep as i64],
+    )
+    .map_err(|e| format!("Failed to prune git cache: {}", e))?;
+    Ok(())
+}
+
+/// A workspace whose directory was removed, with what is needed to recreate it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedWorkspace {
+    pub id: i64,
+    pub workspace_name: String,
+    pub branch_name: String,
+    pub target_branch: Option<String>,
+    pub metadata: Option<String>,
+    /// Commit the patch applies on top of
+    pub base_commit: String,
+    /// File name under `.treq/archive`; None when there were no uncommitted changes
+    pub patch_file: Option<String>,
+    pub file_count: i64,
+    pub archived_at: String,
+}
+
+const ARCHIVE_COLUMNS: &str = "id, workspace_name, branch_name, target_branch, metadata, base_commit, patch_file, file_count, archived_at";
+
+fn archive_from_row(row: &rusqlite::Row) -> rusqlite::Result<ArchivedWorkspace> {
+    Ok(ArchivedWorkspace {
+        id: row.get(0)?,
+        workspace_name: row.get(1)?,
+        branch_name: row.get(2)?,
+        target_branch: row.get(3)?,
+        metadata: row.get(4)?,
+        base_commit: row.get(5)?,
+        patch_file: row.get(6)?,
+        file_count: row.get(7)?,
+        archived_at: row.get(8)?,
+    })
+}
+
+/// Record an archived workspace, returning its id
+pub fn add_workspace_archive(repo_path: &str, archive: &ArchivedWorkspace) -> Result<i64, String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute(
+        "INSERT INTO workspace_archives
+         (workspace_name, branch_name, target_branch, metadata, base_commit, patch_file, file_count, archived_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            &archive.workspace_name,
+            &archive.branch_name,
+            &archive.target_branch,
+            &archive.metadata,
+            &archive.base_commit,
+            &archive.patch_file,
+            archive.file_count,
+            &archive.archived_at,
+        ],
+    )
+    .map_err(|e| format!("Failed to add workspace archive: {}", e))?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Most recently archived first
+pub fn get_workspace_archives(repo_path: &str) -> Result<Vec<ArchivedWorkspace>, String> {
+    let conn = get_connection(repo_path)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM workspace_archives ORDER BY id DESC",
+            ARCHIVE_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let archives = stmt
+        .query_map([], archive_from_row)
+        .map_err(|e| format!("Failed to query workspace archives: {}", e))?;
+
+    archives
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+pub fn get_workspace_archive(repo_path: &str, id: i64) -> Result<Option<ArchivedWorkspace>, String> {
+    let conn = get_connection(repo_path)?;
+    conn.query_row(
+        &format!("SELECT {} FROM workspace_archives WHERE id = ?1", ARCHIVE_COLUMNS),
+        params![id],
+        archive_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to query workspace archive: {}", e))
+}
+
+pub fn delete_workspace_archive(repo_path: &str, id: i64) -> Result<(), String> {
+    let conn = get_connection(repo_path)?;
+    conn.execute("DELETE FROM workspace_archives WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete workspace archive: {}", e))?;
     Ok(())
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
 
+    /// A workspace row named `name` under `/repo`, for tests that never touch the db
+    pub(crate) fn workspace_row(id: i64, name: &str) -> Workspace {
+        Workspace {
+            id,
+            repo_path: "/repo".to_string(),
+            workspace_name: name.to_string(),
+            workspace_path: format!("/repo/.treq/workspaces/{}", name),
+            branch_name: name.to_string(),
+            created_at: String::new(),
+            metadata: None,
+            target_branch: None,
+            has_conflicts: false,
+        }
+    }
+
+    #[test]
+    fn test_git_cache_keeps_most_recent_entries() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = temp_dir.path().to_str().unwrap();
+
+        for commit in ["a", "b", "c"] {
+            set_git_cache(repo_path, repo_path, "commit_diff", commit, "key", "{}").unwrap();
+        }
+        let conn = get_connection(repo_path).unwrap();
+        prune_git_cache(&conn, 2).unwrap();
+
+        assert!(get_git_cache(repo_path, repo_path, "commit_diff", "a")
+            .unwrap()
+            .is_none());
+        for commit in ["b", "c"] {
+            assert!(get_git_cache(repo_path, repo_path, "commit_diff", commit)
+                .unwrap()
+                .is_some());
+        }
+
+        if let Some(initialized) = INITIALIZED_DBS.get() {
+            initialized.lock().unwrap().remove(repo_path);
+        }
+    }
+
     #[test]
     fn test_add_workspace_persists_to_db() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -1582,8 +2736,8 @@ mod tests {
         assert_eq!(review.0, test_comments, "comments should match");
         assert_eq!(review.1, Some(test_viewed_files.to_string()), "viewed_files should match");
         assert_eq!(review.2, Some(test_summary.to_string()), "summary_text should match");
-        assert_eq!(review.3, test_created, "created_at should match");
-        assert_eq!(review.4, test_updated, "updated_at should match");
+        assert_eq!(review.3, timestamps::normalize(test_created), "created_at should match");
+        assert_eq!(review.4, timestamps::normalize(test_updated), "updated_at should match");
 
         // Verify old columns don't exist
         let old_columns: i64 = conn
@@ -1912,4 +3066,223 @@ mod tests {
             initialized.lock().unwrap().remove(repo_path);
         }
     }
+
+    #[test]
+    fn test_save_and_load_pty_scrollback() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = temp_dir.path().to_str().unwrap();
+
+        assert_eq!(get_pty_scrollback(repo_path, "term-1").unwrap(), None);
+
+        save_pty_scrollback(repo_path, "term-1", Some(repo_path), "$ ls\nfile.txt\n")
+            .expect("save_pty_scrollback should succeed");
+        save_pty_scrollback(repo_path, "term-1", Some(repo_path), "$ pwd\n")
+            .expect("Second save should replace the first");
+
+        assert_eq!(
+            get_pty_scrollback(repo_path, "term-1").unwrap().as_deref(),
+            Some("$ pwd\n")
+        );
+    }
+
+    #[test]
+    fn test_operation_journal_lists_newest_first() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = temp_dir.path().to_str().unwrap();
+
+        for (name, success) in [("workspace-created", true), ("commit-made", false)] {
+            add_journal_entry(
+                repo_path,
+                &JournalEntry {
+                    id: 0,
+                    kind: "hook".to_string(),
+                    name: name.to_string(),
+                    workspace_id: Some(1),
+                    command: Some("./notify.sh".to_string()),
+                    success,
+                    output: "done\n".to_string(),
+                    duration_ms: Some(12),
+                    created_at: timestamps::now(),
+                },
+            )
+            .expect("add_journal_entry should succeed");
+        }
+
+        let entries = get_journal_entries(repo_path, 10).expect("get_journal_entries should succeed");
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["commit-made", "workspace-created"]);
+        assert!(!entries[0].success);
+        assert_eq!(get_journal_entries(repo_path, 1).unwrap().len(), 1);
+
+        let hooks = get_journal_entries_of_kind(repo_path, "hook", Some("commit-made"), 10).unwrap();
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(get_journal_entries_of_kind(repo_path, "hook", None, 10).unwrap().len(), 2);
+        assert!(get_journal_entries_of_kind(repo_path, "task", None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cached_checks_are_replaced_per_commit() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = temp_dir.path().to_str().unwrap();
+        assert!(get_cached_checks(repo_path, "abc").unwrap().is_none());
+
+        for state in ["pending", "success"] {
+            save_cached_checks(
+                repo_path,
+                &CachedChecks {
+                    sha: "abc".to_string(),
+                    state: Some(state.to_string()),
+                    checks: "[]".to_string(),
+                    fetched_at: timestamps::now(),
+                },
+            )
+            .expect("save_cached_checks should succeed");
+        }
+
+        let cached = get_cached_checks(repo_path, "abc").unwrap().unwrap();
+        assert_eq!(cached.state.as_deref(), Some("success"));
+        assert_eq!(cached.checks, "[]");
+    }
+
+    #[test]
+    fn test_snapshots_list_newest_first_and_delete() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = temp_dir.path().to_str().unwrap();
+
+        let mut ids = Vec::new();
+        for operation in ["discard file", "discard all changes"] {
+            let snapshot = Snapshot {
+                id: 0,
+                workspace_id: Some(1),
+                workspace_path: "/repo/.treq/workspaces/feature".to_string(),
+                operation: operation.to_string(),
+                base_commit: "abc123".to_string(),
+                patch_file: format!("{}.patch", operation.replace(' ', "-")),
+                file_count: 2,
+                size_bytes: 512,
+                created_at: timestamps::now(),
+            };
+            ids.push(add_snapshot(repo_path, &snapshot).expect("add_snapshot should succeed"));
+        }
+
+        let snapshots = get_snapshots(repo_path).expect("get_snapshots should succeed");
+        let operations: Vec<&str> = snapshots.iter().map(|s| s.operation.as_str()).collect();
+        assert_eq!(operations, vec!["discard all changes", "discard file"]);
+
+        delete_snapshot(repo_path, ids[0]).expect("delete_snapshot should succeed");
+        assert!(get_snapshot(repo_path, ids[0]).unwrap().is_none());
+        assert_eq!(get_snapshot(repo_path, ids[1]).unwrap().unwrap().file_count, 2);
+    }
+
+    #[test]
+    fn test_timestamp_migration_normalizes_rows_and_orders_sessions() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = temp_dir.path().to_str().unwrap();
+        let db_path = get_local_db_path(repo_path);
+        fs::create_dir_all(db_path.parent().unwrap()).expect("Failed to create .treq dir");
+
+        // Sessions as written before timestamps were standardized
+        let conn = Connection::open(&db_path).expect("Failed to open database");
+        conn.execute(
+            "CREATE TABLE sessions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                workspace_id INTEGER,
+                name TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                last_accessed TEXT NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create sessions table");
+        for (name, created_at) in [
+            ("local offset", "2025-01-15T12:30:00+02:00"),
+            ("sqlite", "2025-01-15 10:00:00"),
+            ("utc", "2025-01-15T10:15:00.123456+00:00"),
+        ] {
+            conn.execute(
+                "INSERT INTO sessions (name, created_at, last_accessed) VALUES (?1, ?2, ?2)",
+                params![name, created_at],
+            )
+            .expect("Failed to insert session");
+        }
+        drop(conn);
+
+        init_local_db(repo_path).expect("init_local_db should succeed");
+
+        let sessions = get_sessions(repo_path).expect("get_sessions should succeed");
+        let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["sqlite", "utc", "local offset"]);
+        assert_eq!(sessions[0].created_at, "2025-01-15T10:00:00.000Z");
+        assert_eq!(sessions[2].last_accessed, "2025-01-15T10:30:00.000Z");
+        assert!(sessions.windows(2).all(|w| w[0].sort_key < w[1].sort_key));
+
+        // New sessions always sort after existing ones
+        add_session(repo_path, None, "new".to_string()).expect("add_session should succeed");
+        let sessions = get_sessions(repo_path).expect("get_sessions should succeed");
+        assert_eq!(sessions.last().unwrap().name, "new");
+
+        // Already migrated databases are left alone
+        let conn = Connection::open(&db_path).expect("Failed to open database");
+        assert!(!migrations::has_column(&conn, "workspaces", "archived").unwrap());
+        conn.execute(
+            "UPDATE sessions SET created_at = '2025-01-15 09:00:00' WHERE name = 'new'",
+            [],
+        )
+        .expect("Failed to update session");
+        drop(conn);
+        init_local_db(repo_path).expect("init_local_db should succeed");
+        let sessions = get_sessions(repo_path).expect("get_sessions should succeed");
+        assert_eq!(sessions.last().unwrap().created_at, "2025-01-15 09:00:00");
+
+        if let Some(initialized) = INITIALIZED_DBS.get() {
+            initialized.lock().unwrap().remove(repo_path);
+        }
+    }
+
+    #[test]
+    fn test_session_tabs_are_ordered_renamed_and_restored() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = temp_dir.path().to_str().unwrap();
+
+        let workspace_id = add_workspace(
+            repo_path,
+            "tabs".to_string(),
+            format!("{}/.treq/workspaces/tabs", repo_path),
+            "tabs".to_string(),
+            None,
+        )
+        .expect("add_workspace should succeed");
+        let session = add_session(repo_path, Some(workspace_id), "agent".to_string())
+            .expect("add_session should succeed");
+        let other = add_session(repo_path, None, "main".to_string())
+            .expect("add_session should succeed");
+        let shell = add_session_tab(repo_path, session, "shell", Some("/repo"))
+            .expect("add_session_tab should succeed");
+        let server = add_session_tab(repo_path, session, "server", None)
+            .expect("add_session_tab should succeed");
+        add_session_tab(repo_path, other, "main shell", None).expect("add_session_tab should succeed");
+        assert_eq!((shell.position, server.position), (0, 1));
+        assert_eq!(server.pty_session_id, format!("session-{}-tab-{}", session, server.id));
+
+        rename_session_tab(repo_path, server.id, "dev server").expect("rename should succeed");
+        update_session_tab_state(repo_path, server.id, Some("/repo/web"), Some("npm run dev"))
+            .expect("update should succeed");
+        update_session_tab_state(repo_path, server.id, None, Some("npm test"))
+            .expect("update should succeed");
+        reorder_session_tabs(repo_path, session, &[server.id, shell.id])
+            .expect("reorder should succeed");
+        assert!(reorder_session_tabs(repo_path, session, &[server.id]).is_err());
+
+        let tabs = get_workspace_session_tabs(repo_path, Some(workspace_id))
+            .expect("get_workspace_session_tabs should succeed");
+        let titles: Vec<&str> = tabs.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["dev server", "shell"]);
+        assert_eq!(tabs[0].cwd.as_deref(), Some("/repo/web"));
+        assert_eq!(tabs[0].last_command.as_deref(), Some("npm test"));
+
+        // Tabs go with their session
+        delete_session(repo_path, session).expect("delete_session should succeed");
+        assert!(get_session_tabs(repo_path, session).unwrap().is_empty());
+        assert_eq!(get_workspace_session_tabs(repo_path, None).unwrap().len(), 1);
+    }
 }
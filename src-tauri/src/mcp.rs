@@ -0,0 +1,332 @@
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::commands;
+use crate::git_ops;
+use crate::AppState;
+
+/// Settings key: "true" to serve MCP on the local API
+pub const MCP_ENABLED_KEY: &str = "mcp_enabled";
+
+/// Settings key: "true" to allow tools that modify repositories
+pub const MCP_ALLOW_WRITES_KEY: &str = "mcp_allow_writes";
+
+/// Path of the MCP endpoint on the local API server
+pub const MCP_PATH: &str = "/mcp";
+
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct ToolDef {
+    name: &'static str,
+    description: &'static str,
+    /// Write tools are only listed and callable when MCP_ALLOW_WRITES_KEY is enabled
+    write: bool,
+    /// (name, description, required) for each string argument
+    args: &'static [(&'static str, &'static str, bool)],
+}
+
+const TOOLS: &[ToolDef] = &[
+    ToolDef {
+        name: "list_workspaces",
+        description: "List treq workspaces of a repository",
+        write: false,
+        args: &[("repo_path", "Absolute path of the repository", true)],
+    },
+    ToolDef {
+        name: "get_changed_files",
+        description: "List changed files in a workspace's working copy",
+        write: false,
+        args: &[("workspace_path", "Absolute path of the workspace", true)],
+    },
+    ToolDef {
+        name: "get_file_diff",
+        description: "Get diff hunks of a changed file in a workspace",
+        write: false,
+        args: &[
+            ("workspace_path", "Absolute path of the workspace", true),
+            ("file_path", "File path relative to the workspace", true),
+        ],
+    },
+    ToolDef {
+        name: "get_log",
+        description: "List recent commits of a repository or workspace",
+        write: false,
+        args: &[
+            (
+                "repo_path",
+                "Absolute path of the repository or workspace",
+                true,
+            ),
+            (
+                "revision",
+                "Revision to list history from (default HEAD)",
+                false,
+            ),
+            ("limit", "Maximum number of commits (default 20)", false),
+        ],
+    },
+    ToolDef {
+        name: "create_workspace",
+        description: "Create a new workspace for a branch",
+        write: true,
+        args: &[
+            ("repo_path", "Absolute path of the repository", true),
+            ("branch_name", "Branch for the workspace", true),
+            ("source_branch", "Branch to start a new branch from", false),
+            ("template", "Name of a workspace template to apply", false),
+        ],
+    },
+    ToolDef {
+        name: "commit",
+        description: "Commit the working copy changes of a workspace",
+        write: true,
+        args: &[
+            ("workspace_path", "Absolute path of the workspace", true),
+            ("message", "Commit message", true),
+        ],
+    },
+];
+
+fn setting_enabled(app: &AppHandle, key: &str) -> bool {
+    let state = app.state::<AppState>();
+    let db = state.db.lock().unwrap();
+    db.get_setting(key)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true")
+}
+
+/// Whether the MCP endpoint is enabled
+pub fn is_enabled(app: &AppHandle) -> bool {
+    setting_enabled(app, MCP_ENABLED_KEY)
+}
+
+fn tool_schema(tool: &ToolDef) -> Value {
+    let properties: serde_json::Map<String, Value> = tool
+        .args
+        .iter()
+        .map(|(name, description, _)| {
+            (
+                name.to_string(),
+                json!({ "type": "string", "description": description }),
+            )
+        })
+        .collect();
+    let required: Vec<&str> = tool
+        .args
+        .iter()
+        .filter(|(_, _, required)| *required)
+        .map(|(name, _, _)| *name)
+        .collect();
+
+    json!({
+        "name": tool.name,
+        "description": tool.description,
+        "inputSchema": { "type": "object", "properties": properties, "required": required },
+        "annotations": { "readOnlyHint": !tool.write },
+    })
+}
+
+fn list_tools(allow_writes: bool) -> Value {
+    let tools: Vec<Value> = TOOLS
+        .iter()
+        .filter(|t| allow_writes || !t.write)
+        .map(tool_schema)
+        .collect();
+    json!({ "tools": tools })
+}
+
+fn string_arg(args: &Value, name: &str) -> Result<String, String> {
+    args.get(name)
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+        .ok_or_else(|| format!("Missing argument: {}", name))
+}
+
+fn optional_arg(args: &Value, name: &str) -> Option<String> {
+    match args.get(name) {
+        Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Run a tool through the same functions the Tauri commands use
+fn call_tool(app: &AppHandle, name: &str, args: &Value) -> Result<Value, String> {
+    match name {
+        "list_workspaces" => {
+            commands::get_workspaces(string_arg(args, "repo_path")?).map(|w| json!(w))
+        }
+        "get_changed_files" => {
+            commands::jj_get_changed_files(string_arg(args, "workspace_path")?).map(|f| json!(f))
+        }
+        "get_file_diff" => commands::jj_get_file_hunks(
+            string_arg(args, "workspace_path")?,
+            string_arg(args, "file_path")?,
+        )
+        .map(|h| json!(h)),
+        "get_log" => {
+            let limit = optional_arg(args, "limit")
+                .map(|l| l.parse::<usize>().map_err(|_| "Invalid limit".to_string()))
+                .transpose()?
+                .unwrap_or(20);
+            let options = git_ops::GitLogOptions {
+                revision: optional_arg(args, "revision"),
+                limit: Some(limit),
+                ..Default::default()
+            };
+            commands::git_log(string_arg(args, "repo_path")?, Some(options))
+                .map(|l| json!(l))
+                .map_err(|e| e.to_string())
+        }
+        "create_workspace" => {
+            let source_branch = optional_arg(args, "source_branch");
+            commands::create_workspace(
+                app.clone(),
+                string_arg(args, "repo_path")?,
+                string_arg(args, "branch_name")?,
+                source_branch.is_some(),
+                source_branch,
+                None,
+                optional_arg(args, "template"),
+                None,
+            )
+            .map(|id| json!({ "workspace_id": id }))
+        }
+        "commit" => commands::jj_commit(
+            app.state::<AppState>(),
+            string_arg(args, "workspace_path")?,
+            string_arg(args, "message")?,
+            None,
+        )
+        .map(|attempt| json!({ "output": attempt.message, "hook_failure": attempt.hook_failure })),
+        _ => Err(format!("Unknown tool: {}", name)),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn handle_tools_call(app: &AppHandle, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(|n| n.as_str())
+        .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+    let tool = TOOLS
+        .iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {}", name)))?;
+    if tool.write && !setting_enabled(app, MCP_ALLOW_WRITES_KEY) {
+        return Err((
+            INVALID_PARAMS,
+            format!(
+                "Tool '{}' requires write access to be enabled in treq settings",
+                name
+            ),
+        ));
+    }
+
+    let args = params.get("arguments").cloned().unwrap_or(json!({}));
+    // Tool failures are reported in the result so the model can see and react to them
+    Ok(match call_tool(app, name, &args) {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": value.to_string() }],
+            "structuredContent": { "result": value },
+            "isError": false,
+        }),
+        Err(e) => json!({
+            "content": [{ "type": "text", "text": e }],
+            "isError": true,
+        }),
+    })
+}
+
+/// Handle one JSON-RPC message; returns None for notifications
+fn handle_message(app: &AppHandle, message: &Value) -> Option<Value> {
+    let method = message.get("method").and_then(|m| m.as_str());
+    let Some(id) = message.get("id").cloned() else {
+        // Notifications (e.g. notifications/initialized) need no response
+        return None;
+    };
+    let Some(method) = method else {
+        return Some(error_response(id, INVALID_REQUEST, "Missing method"));
+    };
+    let params = message.get("params").cloned().unwrap_or(json!({}));
+
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "treq", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(list_tools(setting_enabled(app, MCP_ALLOW_WRITES_KEY))),
+        "tools/call" => handle_tools_call(app, &params),
+        _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+    };
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+/// Handle an MCP POST body, returning the HTTP status and optional JSON response
+pub fn handle_request(app: &AppHandle, body: &[u8]) -> (u16, Option<Value>) {
+    let message: Value = match serde_json::from_slice(body) {
+        Ok(message) => message,
+        Err(e) => {
+            return (
+                400,
+                Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            )
+        }
+    };
+
+    match handle_message(app, &message) {
+        Some(response) => (200, Some(response)),
+        None => (202, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_tools_hides_write_tools() {
+        let read_only = list_tools(false);
+        let names: Vec<&str> = read_only["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"get_changed_files"));
+        assert!(!names.contains(&"commit"));
+
+        let all = list_tools(true);
+        assert_eq!(all["tools"].as_array().unwrap().len(), TOOLS.len());
+        assert_eq!(
+            all["tools"][1]["inputSchema"]["required"][0],
+            "workspace_path"
+        );
+    }
+
+    #[test]
+    fn test_tool_args() {
+        let args = json!({ "repo_path": "/tmp/repo", "limit": 5, "empty": "" });
+        assert_eq!(string_arg(&args, "repo_path").unwrap(), "/tmp/repo");
+        assert!(string_arg(&args, "empty").is_err());
+        assert_eq!(optional_arg(&args, "limit").as_deref(), Some("5"));
+        assert_eq!(optional_arg(&args, "missing"), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Optional checks run before a workspace is merged into its target branch.
+//!
+//! The cheap gates run first: the working copy must be clean when configured, and the
+//! target branch must still be at the commit the merge preview was computed against.
+//! Only when those pass are the configured check tasks run, in order, in the workspace.
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::git_ops;
+use crate::jj::JjMergeResult;
+use crate::operations;
+use crate::task_runner::{self, TaskConfig};
+use crate::vcs;
+
+/// Repo settings key holding the merge checks, as a JSON `MergeCheckConfig`
+pub const MERGE_CHECKS_KEY: &str = "merge_checks";
+
+/// Output kept per failed check; the end is kept, as that is where failures are
+const MAX_CHECK_OUTPUT: usize = 8 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MergeCheckConfig {
+    /// Names of tasks (see `task_runner`) that must exit successfully
+    #[serde(default)]
+    pub tasks: Vec<String>,
+    /// Refuse to merge while the workspace has uncommitted changes
+    #[serde(default)]
+    pub require_clean: bool,
+}
+
+/// A gate that stopped a merge
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "gate", rename_all = "snake_case")]
+pub enum GateFailure {
+    DirtyWorkingCopy {
+        files: Vec<String>,
+    },
+    /// The target branch no longer points at the commit the diff was computed against
+    TargetMoved {
+        expected: String,
+        actual: String,
+    },
+    UnknownCheck {
+        task: String,
+    },
+    CheckFailed {
+        task: String,
+        exit_code: Option<i32>,
+        output: String,
+    },
+}
+
+impl GateFailure {
+    fn describe(&self) -> String {
+        match self {
+            GateFailure::DirtyWorkingCopy { files } => {
+                format!("working copy has {} uncommitted change(s)", files.len())
+            }
+            GateFailure::TargetMoved { .. } => "target branch moved since the preview".to_string(),
+            GateFailure::UnknownCheck { task } => format!("check '{}' is not a task", task),
+            GateFailure::CheckFailed {
+                task, exit_code, ..
+            } => match exit_code {
+                Some(code) => format!("check '{}' exited with {}", task, code),
+                None => format!("check '{}' did not finish", task),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct MergeBlocked {
+    pub failed: Vec<GateFailure>,
+}
+
+impl MergeBlocked {
+    pub fn summary(&self) -> String {
+        let reasons: Vec<String> = self.failed.iter().map(GateFailure::describe).collect();
+        format!("Merge blocked: {}", reasons.join("; "))
+    }
+}
+
+/// Result of `jj_create_merge`: the merge result, or an unsuccessful one with the
+/// failed gates when the merge did not run
+#[derive(Debug, Serialize, Clone)]
+pub struct MergeAttempt {
+    #[serde(flatten)]
+    pub result: JjMergeResult,
+    pub blocked: Option<MergeBlocked>,
+}
+
+impl From<JjMergeResult> for MergeAttempt {
+    fn from(result: JjMergeResult) -> Self {
+        MergeAttempt {
+            result,
+            blocked: None,
+        }
+    }
+}
+
+impl From<MergeBlocked> for MergeAttempt {
+    fn from(blocked: MergeBlocked) -> Self {
+        MergeAttempt {
+            result: JjMergeResult {
+                success: false,
+                message: blocked.summary(),
+                has_conflicts: false,
+                conflicted_files: Vec::new(),
+                merge_commit_id: None,
+            },
+            blocked: Some(blocked),
+        }
+    }
+}
+
+pub fn parse_merge_checks(value: Option<&str>) -> Result<MergeCheckConfig, String> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(MergeCheckConfig::default()),
+        Some(value) => {
+            serde_json::from_str(value).map_err(|e| format!("Invalid merge checks setting: {}", e))
+        }
+    }
+}
+
+pub fn load_merge_checks(db: &Database, repo_path: &str) -> Result<MergeCheckConfig, String> {
+    let value = db
+        .get_repo_setting(repo_path, MERGE_CHECKS_KEY)
+        .map_err(|e| e.to_string())?;
+    parse_merge_checks(value.as_deref())
+}
+
+/// Save the checks with task names trimmed, dropping blank ones
+pub fn save_merge_checks(
+    db: &Database,
+    repo_path: &str,
+    config: &MergeCheckConfig,
+) -> Result<(), String> {
+    let cleaned = MergeCheckConfig {
+        tasks: config
+            .tasks
+            .iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        require_clean: config.require_clean,
+    };
+    let value = serde_json::to_string(&cleaned)
+        .map_err(|e| format!("Failed to serialize merge checks: {}", e))?;
+    db.set_repo_setting(repo_path, MERGE_CHECKS_KEY, &value)
+        .map_err(|e| e.to_string())
+}
+
+/// Full commit id the target branch points at, as seen from the workspace
+pub fn target_commit(workspace_path: &str, target_branch: &str) -> Result<String, String> {
+    git_ops::git_resolve_commit(workspace_path, target_branch).map_err(|e| e.to_string())
+}
+
+/// Whether `expected`, a full or abbreviated id, names `actual`
+fn same_commit(expected: &str, actual: &str) -> bool {
+    let expected = expected.trim().to_ascii_lowercase();
+    let actual = actual.to_ascii_lowercase();
+    !expected.is_empty() && (actual.starts_with(&expected) || expected.starts_with(&actual))
+}
+
+/// Keep the last `MAX_CHECK_OUTPUT` bytes of a check's output
+fn output_tail(output: &str) -> String {
+    if output.len() <= MAX_CHECK_OUTPUT {
+        return output.to_string();
+    }
+    let mut start = output.len() - MAX_CHECK_OUTPUT;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[output truncated]\n{}", &output[start..])
+}
+
+/// Run the configured gates for merging the workspace into `target_branch`. Returns the
+/// failed gates, or None when the merge may proceed. Checks are skipped when a cheaper
+/// gate already failed.
+pub async fn check(
+    workspace_path: &str,
+    target_branch: &str,
+    expected_target_commit: Option<&str>,
+    config: &MergeCheckConfig,
+    tasks: &TaskConfig,
+) -> Result<Option<MergeBlocked>, String> {
+    let mut failed = Vec::new();
+
+    if config.require_clean {
+        let changes = vcs::backend_for_workspace(workspace_path).changed_files(workspace_path)?;
+        if !changes.is_empty() {
+            failed.push(GateFailure::DirtyWorkingCopy {
+                files: changes.into_iter().map(|c| c.path).collect(),
+            });
+        }
+    }
+
+    if let Some(expected) = expected_target_commit {
+        let actual = target_commit(workspace_path, target_branch)?;
+        if !same_commit(expected, &actual) {
+            failed.push(GateFailure::TargetMoved {
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+    }
+
+    if failed.is_empty() {
+        for name in &config.tasks {
+            let Some(task) = tasks.get(name) else {
+                failed.push(GateFailure::UnknownCheck { task: name.clone() });
+                continue;
+            };
+            operations::report_phase(&format!("Running check '{}'", name));
+            let (exit_code, output) =
+                task_runner::run_task_and_wait(workspace_path, name, task).await?;
+            if exit_code != Some(0) {
+                failed.push(GateFailure::CheckFailed {
+                    task: name.clone(),
+                    exit_code,
+                    output: output_tail(&output),
+                });
+            }
+        }
+    }
+
+    Ok((!failed.is_empty()).then_some(MergeBlocked { failed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_process;
+    use crate::task_runner::TaskDefinition;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_merge_checks_and_same_commit() {
+        let config = parse_merge_checks(Some(r#"{"tasks":["test","lint"]}"#)).unwrap();
+        assert_eq!(config.tasks, vec!["test", "lint"]);
+        assert!(!config.require_clean);
+        assert_eq!(
+            parse_merge_checks(None).unwrap(),
+            MergeCheckConfig::default()
+        );
+        assert!(parse_merge_checks(Some(r#"{"tasks":"test"}"#)).is_err());
+
+        assert!(same_commit("ABC123", "abc123def456"));
+        assert!(same_commit("abc123def456", "abc123"));
+        assert!(!same_commit("abc124", "abc123def456"));
+        assert!(!same_commit("", "abc123"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_reports_failed_and_unknown_tasks() {
+        let dir = TempDir::new().unwrap();
+        let workspace = dir.path().to_str().unwrap();
+        let task = |command: &str| TaskDefinition {
+            command: command.to_string(),
+            cwd: None,
+            env: BTreeMap::new(),
+        };
+        let tasks = TaskConfig::from([
+            ("lint".to_string(), task("true")),
+            (
+                "test".to_string(),
+                task("echo \"$TREQ_TASK broke\"; exit 4"),
+            ),
+        ]);
+        let config = MergeCheckConfig {
+            tasks: vec!["lint".to_string(), "test".to_string(), "e2e".to_string()],
+            require_clean: false,
+        };
+
+        let blocked = async_process::block_on(check(workspace, "main", None, &config, &tasks))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            blocked.failed,
+            vec![
+                GateFailure::CheckFailed {
+                    task: "test".to_string(),
+                    exit_code: Some(4),
+                    output: "test broke\n".to_string(),
+                },
+                GateFailure::UnknownCheck {
+                    task: "e2e".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            blocked.summary(),
+            "Merge blocked: check 'test' exited with 4; check 'e2e' is not a task"
+        );
+
+        let passing = MergeCheckConfig {
+            tasks: vec!["lint".to_string()],
+            require_clean: false,
+        };
+        assert!(
+            async_process::block_on(check(workspace, "main", None, &passing, &tasks))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
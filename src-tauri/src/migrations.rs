@@ -0,0 +1,295 @@
+//! Versioned schema migrations for the app database (`db.rs`) and the per-repository
+//! databases (`local_db.rs`).
+//!
+//! Applied versions are recorded in a `schema_version` table. Pending migrations are
+//! first run in a transaction that is rolled back, so a broken migration fails before
+//! anything changes; then the database file is backed up and each migration is
+//! applied in its own transaction.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::timestamps;
+
+/// One schema change. Migrations are listed in ascending `version` order and must not
+/// manage transactions themselves.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub apply: fn(&Connection) -> Result<(), String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub name: String,
+}
+
+/// What `migrate` would do to a database
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct MigrationPlan {
+    pub current_version: i64,
+    pub pending: Vec<PendingMigration>,
+}
+
+fn ensure_version_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Failed to create schema_version table: {}", e))
+}
+
+/// Highest applied version, 0 for a database that predates versioning
+pub fn current_version(conn: &Connection) -> Result<i64, String> {
+    ensure_version_table(conn)?;
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to read schema version: {}", e))
+}
+
+fn pending(current: i64, migrations: &[Migration]) -> Vec<&Migration> {
+    debug_assert!(
+        migrations.windows(2).all(|w| w[0].version < w[1].version),
+        "migrations must be in ascending version order"
+    );
+    migrations.iter().filter(|m| m.version > current).collect()
+}
+
+/// Whether `table` has `column`
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ),
+        [column],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+/// Add a column unless an older schema already has it
+pub fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), String> {
+    let exists = has_column(conn, table, column)
+        .map_err(|e| format!("Failed to inspect {}: {}", table, e))?;
+    if exists {
+        return Ok(());
+    }
+    conn.execute(
+        &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+        [],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Failed to add {}.{}: {}", table, column, e))
+}
+
+/// Run the pending migrations and roll them back, reporting the first one that fails
+pub fn dry_run(conn: &Connection, migrations: &[Migration]) -> Result<MigrationPlan, String> {
+    let current_version = current_version(conn)?;
+    let pending = pending(current_version, migrations);
+    if !pending.is_empty() {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start migration dry run: {}", e))?;
+        for migration in &pending {
+            (migration.apply)(&tx).map_err(|e| {
+                format!(
+                    "Migration {} ({}) would fail: {}",
+                    migration.version, migration.name, e
+                )
+            })?;
+        }
+        // Dropping the transaction rolls it back
+    }
+    Ok(MigrationPlan {
+        current_version,
+        pending: pending
+            .into_iter()
+            .map(|m| PendingMigration {
+                version: m.version,
+                name: m.name.to_string(),
+            })
+            .collect(),
+    })
+}
+
+/// Whether the database has tables other than `schema_version`, i.e. data worth backing up
+fn has_data(conn: &Connection) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+         AND name NOT IN ('schema_version', 'sqlite_sequence')",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(|e| format!("Failed to inspect database: {}", e))
+}
+
+/// Path of the backup taken before migrating from `version`
+pub fn backup_path(db_path: &Path, version: i64) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    db_path.with_file_name(name)
+}
+
+/// Copy the database to `backup_path`, replacing an older backup of the same version
+fn backup(conn: &Connection, db_path: &Path, version: i64) -> Result<PathBuf, String> {
+    let path = backup_path(db_path, version);
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to replace database backup: {}", e))?;
+    }
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])
+        .map_err(|e| format!("Failed to back up database: {}", e))?;
+    Ok(path)
+}
+
+/// Bring the database up to the latest migration, returning the versions applied.
+/// File databases that already hold data are backed up first.
+pub fn migrate(conn: &Connection, migrations: &[Migration]) -> Result<Vec<i64>, String> {
+    let plan = dry_run(conn, migrations)?;
+    if plan.pending.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let db_path = conn.path().filter(|p| !p.is_empty()).map(PathBuf::from);
+    if let Some(db_path) = db_path {
+        if has_data(conn)? {
+            let path = backup(conn, &db_path, plan.current_version)?;
+            log::info!(
+                "Backed up {} to {} before migrating",
+                db_path.display(),
+                path.display()
+            );
+        }
+    }
+
+    let mut applied = Vec::new();
+    for migration in pending(plan.current_version, migrations) {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start migration: {}", e))?;
+        (migration.apply)(&tx).map_err(|e| {
+            format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.name, e
+            )
+        })?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, timestamps::now()],
+        )
+        .map_err(|e| format!("Failed to record migration {}: {}", migration.version, e))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit migration {}: {}", migration.version, e))?;
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_items(conn: &Connection) -> Result<(), String> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS items (id INTEGER PRIMARY KEY, name TEXT)",
+            [],
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+
+    fn add_note(conn: &Connection) -> Result<(), String> {
+        add_column_if_missing(conn, "items", "note", "TEXT")
+    }
+
+    fn broken(conn: &Connection) -> Result<(), String> {
+        conn.execute("INSERT INTO items (id, name) VALUES (1, 'kept?')", [])
+            .map_err(|e| e.to_string())?;
+        conn.execute("ALTER TABLE missing ADD COLUMN x TEXT", [])
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            name: "create items",
+            apply: create_items,
+        },
+        Migration {
+            version: 2,
+            name: "add note",
+            apply: add_note,
+        },
+    ];
+
+    #[test]
+    fn test_migrate_records_versions_and_backs_up() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let conn = Connection::open(&db_path).unwrap();
+
+        let plan = dry_run(&conn, MIGRATIONS).unwrap();
+        assert_eq!(plan.current_version, 0);
+        assert_eq!(plan.pending.len(), 2);
+        assert!(!has_column(&conn, "items", "id").unwrap());
+
+        // A fresh database has nothing to back up
+        assert_eq!(migrate(&conn, &MIGRATIONS[..1]).unwrap(), vec![1]);
+        assert!(!backup_path(&db_path, 0).exists());
+
+        assert_eq!(migrate(&conn, MIGRATIONS).unwrap(), vec![2]);
+        assert!(backup_path(&db_path, 1).exists());
+        assert!(has_column(&conn, "items", "note").unwrap());
+        assert_eq!(current_version(&conn).unwrap(), 2);
+        assert!(migrate(&conn, MIGRATIONS).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_dry_run_changes_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let conn = Connection::open(&db_path).unwrap();
+        migrate(&conn, &MIGRATIONS[..1]).unwrap();
+
+        let with_broken = [
+            Migration {
+                version: 1,
+                name: "create items",
+                apply: create_items,
+            },
+            Migration {
+                version: 2,
+                name: "broken",
+                apply: broken,
+            },
+        ];
+        let err = migrate(&conn, &with_broken).unwrap_err();
+        assert!(err.contains("Migration 2 (broken) would fail"), "{}", err);
+
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 0);
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        assert!(!backup_path(&db_path, 1).exists());
+    }
+}
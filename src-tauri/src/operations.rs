@@ -0,0 +1,360 @@
+//! Registry of long-running operations: push, pull, fetch, clone, rebase, merge,
+//! workspace creation and tasks.
+//!
+//! Each operation has an id, chosen by the frontend or assigned here, that
+//! `cancel_operation` accepts. While it runs, git/jj progress lines from its processes
+//! are emitted as `operation-progress` events, and `operation-finished` reports the
+//! outcome, so the UI can show progress without polling.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio_util::sync::CancellationToken;
+
+use crate::git_ops;
+
+/// Handle used to emit operation events, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Git redraws its progress meters many times a second; within one phase, events are
+/// sent at most this often
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Push,
+    Pull,
+    Fetch,
+    Clone,
+    Rebase,
+    Merge,
+    WorkspaceCreate,
+    Task,
+}
+
+/// Payload of the `operation-progress` event
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OperationProgress {
+    pub op_id: String,
+    pub kind: OperationKind,
+    /// Workspace or repository the operation runs in
+    pub target: String,
+    /// e.g. "Started", "Writing objects", "Rebased commits"
+    pub phase: String,
+    pub percent: Option<u32>,
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+}
+
+/// Payload of the `operation-finished` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OperationFinished {
+    pub op_id: String,
+    pub kind: OperationKind,
+    pub target: String,
+    pub success: bool,
+    pub cancelled: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Progress parsed from one line of git or jj output
+#[derive(Debug, PartialEq)]
+struct ProgressUpdate {
+    phase: String,
+    percent: Option<u32>,
+    current: Option<u64>,
+    total: Option<u64>,
+    done: bool,
+}
+
+impl ProgressUpdate {
+    fn phase(phase: &str) -> Self {
+        ProgressUpdate {
+            phase: phase.to_string(),
+            percent: None,
+            current: None,
+            total: None,
+            done: false,
+        }
+    }
+}
+
+struct Operation {
+    id: String,
+    kind: OperationKind,
+    target: String,
+    token: CancellationToken,
+    /// Phase of the last emitted progress event and when it was sent
+    last_progress: Mutex<Option<(String, Instant)>>,
+}
+
+impl Operation {
+    /// Emit `update` unless the same phase was reported within `PROGRESS_INTERVAL`.
+    /// Returns whether an event was sent.
+    fn report(&self, update: ProgressUpdate) -> bool {
+        {
+            let mut last = self.last_progress.lock().unwrap();
+            let now = Instant::now();
+            if let Some((phase, at)) = last.as_ref() {
+                if *phase == update.phase
+                    && !update.done
+                    && now.duration_since(*at) < PROGRESS_INTERVAL
+                {
+                    return false;
+                }
+            }
+            *last = Some((update.phase.clone(), now));
+        }
+        emit(
+            "operation-progress",
+            OperationProgress {
+                op_id: self.id.clone(),
+                kind: self.kind,
+                target: self.target.clone(),
+                phase: update.phase,
+                percent: update.percent,
+                current: update.current,
+                total: update.total,
+            },
+        );
+        true
+    }
+}
+
+static OPERATIONS: OnceLock<Mutex<HashMap<String, Arc<Operation>>>> = OnceLock::new();
+
+fn operations() -> &'static Mutex<HashMap<String, Arc<Operation>>> {
+    OPERATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+tokio::task_local! {
+    /// Operation the current task runs for
+    static CURRENT_OPERATION: Arc<Operation>;
+}
+
+/// Removes an operation from the registry when it finishes, unless a newer operation
+/// took over its id
+struct Registration(Arc<Operation>);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut operations = operations().lock().unwrap();
+        if operations
+            .get(&self.0.id)
+            .is_some_and(|op| Arc::ptr_eq(op, &self.0))
+        {
+            operations.remove(&self.0.id);
+        }
+    }
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+fn emit<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit(event, payload);
+    }
+}
+
+fn next_operation_id() -> String {
+    static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
+    format!("op-{}", NEXT_OPERATION_ID.fetch_add(1, Ordering::SeqCst))
+}
+
+/// Run `future` as an operation of `kind` on `target`, under `op_id` or a generated id.
+/// Processes it starts through `AsyncCommand` report their progress, are killed once
+/// `cancel_operation` is called with the id, and later ones fail right away.
+pub async fn run_operation<T, E, F>(
+    kind: OperationKind,
+    target: &str,
+    op_id: Option<String>,
+    future: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: Future<Output = Result<T, E>>,
+{
+    let operation = Arc::new(Operation {
+        id: op_id.unwrap_or_else(next_operation_id),
+        kind,
+        target: target.to_string(),
+        token: CancellationToken::new(),
+        last_progress: Mutex::new(None),
+    });
+    operations()
+        .lock()
+        .unwrap()
+        .insert(operation.id.clone(), operation.clone());
+    let _registration = Registration(operation.clone());
+
+    let started = Instant::now();
+    operation.report(ProgressUpdate::phase("Started"));
+    let result = CURRENT_OPERATION.scope(operation.clone(), future).await;
+    emit(
+        "operation-finished",
+        OperationFinished {
+            op_id: operation.id.clone(),
+            kind,
+            target: operation.target.clone(),
+            success: result.is_ok(),
+            cancelled: operation.token.is_cancelled(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            duration_ms: started.elapsed().as_millis() as u64,
+        },
+    );
+    result
+}
+
+/// Cancel a running operation. Returns false when no operation has that id, e.g. because
+/// it already finished.
+pub fn cancel_operation(op_id: &str) -> bool {
+    match operations().lock().unwrap().get(op_id) {
+        Some(op) => {
+            op.token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Cancellation token of the operation the current task runs for, if any
+pub(crate) fn current_token() -> Option<CancellationToken> {
+    CURRENT_OPERATION.try_with(|op| op.token.clone()).ok()
+}
+
+/// Report a step of the current operation that no process output describes, e.g.
+/// "Registering workspace"
+pub fn report_phase(phase: &str) {
+    let _ = CURRENT_OPERATION.try_with(|op| op.report(ProgressUpdate::phase(phase)));
+}
+
+/// Pass a line of process output to the current operation, emitting progress when it
+/// is a progress line
+pub(crate) fn report_line(line: &str) {
+    let _ = CURRENT_OPERATION.try_with(|op| {
+        if let Some(update) = parse_progress_line(line) {
+            op.report(update);
+        }
+    });
+}
+
+/// Parse git's "Writing objects:  45% (9/20)" style meters, as printed by push, fetch
+/// and clone, and jj's "Rebased 3 commits to destination" summaries
+fn parse_progress_line(line: &str) -> Option<ProgressUpdate> {
+    if let Some(progress) = git_ops::parse_git_progress(line) {
+        return Some(ProgressUpdate {
+            phase: progress.phase,
+            percent: progress.percent,
+            current: progress.current,
+            total: progress.total,
+            done: progress.done,
+        });
+    }
+
+    let rest = line.trim().strip_prefix("Rebased ")?;
+    let count = rest.split(' ').next()?.parse().ok()?;
+    let phase = if rest.contains(" descendant ") {
+        "Rebased descendant commits"
+    } else {
+        "Rebased commits"
+    };
+    Some(ProgressUpdate {
+        current: Some(count),
+        done: true,
+        ..ProgressUpdate::phase(phase)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_process::{block_on, AsyncCommand};
+    use std::io;
+
+    #[test]
+    fn test_parse_progress_line() {
+        let update =
+            parse_progress_line("Writing objects:  45% (9/20), 1.00 KiB | 1.00 MiB/s").unwrap();
+        assert_eq!(update.phase, "Writing objects");
+        assert_eq!(
+            (update.percent, update.current, update.total, update.done),
+            (Some(45), Some(9), Some(20), false)
+        );
+
+        let update = parse_progress_line("Rebased 3 commits to destination").unwrap();
+        assert_eq!(
+            (update.phase.as_str(), update.current, update.done),
+            ("Rebased commits", Some(3), true)
+        );
+        let update = parse_progress_line("Rebased 2 descendant commits").unwrap();
+        assert_eq!(update.phase, "Rebased descendant commits");
+
+        assert_eq!(parse_progress_line("Working copy  (@) now at: abc"), None);
+        assert_eq!(parse_progress_line("Rebased some commits"), None);
+    }
+
+    #[test]
+    fn test_progress_is_throttled_within_a_phase() {
+        let operation = Operation {
+            id: "test".to_string(),
+            kind: OperationKind::Push,
+            target: "/repo".to_string(),
+            token: CancellationToken::new(),
+            last_progress: Mutex::new(None),
+        };
+        assert!(operation.report(ProgressUpdate::phase("Writing objects")));
+        assert!(!operation.report(ProgressUpdate::phase("Writing objects")));
+        assert!(operation.report(ProgressUpdate::phase("Resolving deltas")));
+        assert!(operation.report(ProgressUpdate {
+            done: true,
+            ..ProgressUpdate::phase("Resolving deltas")
+        }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_operation_kills_its_process() {
+        let cancelled = block_on(run_operation(
+            OperationKind::Fetch,
+            "/repo",
+            Some("test-cancel".to_string()),
+            async {
+                let sleep = async { AsyncCommand::new("sleep").arg("10").output().await };
+                let cancel = async {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    assert!(cancel_operation("test-cancel"));
+                    std::future::pending::<()>().await
+                };
+                tokio::select! {
+                    result = sleep => result,
+                    _ = cancel => unreachable!(),
+                }
+            },
+        ));
+        assert_eq!(cancelled.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        // Finished operations are unregistered
+        assert!(!cancel_operation("test-cancel"));
+
+        let result: Result<(), String> = block_on(run_operation(
+            OperationKind::WorkspaceCreate,
+            "/repo",
+            None,
+            async {
+                assert!(current_token().is_some());
+                report_phase("Registering workspace");
+                Ok(())
+            },
+        ));
+        assert!(result.is_ok());
+        assert!(current_token().is_none());
+    }
+}
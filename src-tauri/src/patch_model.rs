@@ -0,0 +1,454 @@
+//! Typed model of unified diff hunks, for building patches from a selection of lines.
+//!
+//! Lines keep their exact text, including the "\r" of CRLF files, and whether they end
+//! the file without a newline, so rebuilt hunks round-trip both. Headers are always
+//! recomputed from the line records, and partial hunks are laid out by a real diff of
+//! their two sides rather than by editing the original hunk in place.
+
+use imara_diff::intern::{Interner, Token};
+use imara_diff::{diff_with_tokens, Algorithm};
+use std::collections::HashSet;
+use std::ops::Range;
+
+const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+impl LineKind {
+    fn marker(self) -> char {
+        match self {
+            LineKind::Context => ' ',
+            LineKind::Added => '+',
+            LineKind::Removed => '-',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HunkLine {
+    pub kind: LineKind,
+    /// Content without the marker column or the "\n"; CRLF lines keep their "\r"
+    pub text: String,
+    /// Followed by "\ No newline at end of file"
+    pub no_newline: bool,
+    /// Index in the parsed hunk body, markers included, matching `JjDiffHunk::lines`.
+    /// None for lines of a rebuilt hunk.
+    pub source_index: Option<usize>,
+}
+
+impl HunkLine {
+    fn rebuilt(kind: LineKind, line: &HunkLine) -> HunkLine {
+        HunkLine {
+            kind,
+            text: line.text.clone(),
+            no_newline: line.no_newline,
+            source_index: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub new_start: usize,
+    /// Text after the closing "@@", e.g. the enclosing function
+    pub section: String,
+    pub lines: Vec<HunkLine>,
+}
+
+/// Start and count of one side of a "@@ -a,b +c,d @@" header; a missing count is 1
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+impl Hunk {
+    /// Parse a hunk as returned by `git_get_file_hunks`: its "@@" header followed by the
+    /// body. Lines are split on "\n" only so CRLF lines keep their "\r", and the body is
+    /// read up to the counts in the header, which must add up.
+    pub fn parse(text: &str) -> Result<Hunk, String> {
+        let mut raw = text.strip_suffix('\n').unwrap_or(text).split('\n');
+        let header = raw
+            .next()
+            .filter(|h| h.starts_with("@@ -"))
+            .ok_or_else(|| "Invalid hunk: missing header".to_string())?;
+        let invalid_header = || format!("Invalid hunk header: {}", header);
+        let (ranges, section) = header[4..].split_once(" @@").ok_or_else(invalid_header)?;
+        let (old, new) = ranges.split_once(" +").ok_or_else(invalid_header)?;
+        let (old_start, mut old_left) = parse_range(old).ok_or_else(invalid_header)?;
+        let (new_start, mut new_left) = parse_range(new).ok_or_else(invalid_header)?;
+
+        let mut lines: Vec<HunkLine> = Vec::new();
+        for (index, line) in raw.enumerate() {
+            if line.starts_with('\\') {
+                // Belongs to the line before it, which may be the last counted one
+                match lines.last_mut() {
+                    Some(last) => last.no_newline = true,
+                    None => return Err("Invalid hunk: marker before any line".to_string()),
+                }
+                continue;
+            }
+            if old_left == 0 && new_left == 0 {
+                if line.is_empty() {
+                    continue;
+                }
+                return Err("Invalid hunk: more lines than its header counts".to_string());
+            }
+
+            let (kind, text) = match line.chars().next() {
+                Some('+') => (LineKind::Added, &line[1..]),
+                Some('-') => (LineKind::Removed, &line[1..]),
+                Some(' ') => (LineKind::Context, &line[1..]),
+                // Some tools strip the leading space from empty context lines
+                None => (LineKind::Context, ""),
+                Some(_) => return Err(format!("Invalid hunk line: {}", line)),
+            };
+            let (uses_old, uses_new) = match kind {
+                LineKind::Context => (true, true),
+                LineKind::Added => (false, true),
+                LineKind::Removed => (true, false),
+            };
+            if (uses_old && old_left == 0) || (uses_new && new_left == 0) {
+                return Err("Invalid hunk: lines don't match its header counts".to_string());
+            }
+            old_left -= uses_old as usize;
+            new_left -= uses_new as usize;
+            lines.push(HunkLine {
+                kind,
+                text: text.to_string(),
+                no_newline: false,
+                source_index: Some(index),
+            });
+        }
+
+        if old_left > 0 || new_left > 0 {
+            return Err("Invalid hunk: fewer lines than its header counts".to_string());
+        }
+        Ok(Hunk {
+            old_start,
+            new_start,
+            section: section.to_string(),
+            lines,
+        })
+    }
+
+    pub fn old_count(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|l| l.kind != LineKind::Added)
+            .count()
+    }
+
+    pub fn new_count(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|l| l.kind != LineKind::Removed)
+            .count()
+    }
+
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@{}",
+            self.old_start,
+            self.old_count(),
+            self.new_start,
+            self.new_count(),
+            self.section
+        )
+    }
+
+    /// Unified diff text of the hunk, ending with a newline
+    pub fn to_text(&self) -> String {
+        let mut text = self.header();
+        text.push('\n');
+        for line in &self.lines {
+            text.push(line.kind.marker());
+            text.push_str(&line.text);
+            text.push('\n');
+            if line.no_newline {
+                text.push_str(NO_NEWLINE_MARKER);
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// Hunk that undoes only the `selected` changes when reverse-applied. `selected` are
+    /// body indices as in `JjDiffHunk::lines`. Its new side is this hunk's new side, so
+    /// it applies to the working tree: unselected additions stay and unselected removals
+    /// stay removed.
+    pub fn select_for_discard(&self, selected: &[usize]) -> Result<Hunk, String> {
+        let selected: HashSet<usize> = selected.iter().copied().collect();
+        let mut old: Vec<HunkLine> = Vec::new();
+        let mut new: Vec<HunkLine> = Vec::new();
+        let mut changes = 0;
+        for line in &self.lines {
+            let picked = line.source_index.is_some_and(|i| selected.contains(&i));
+            match line.kind {
+                LineKind::Context => {
+                    old.push(line.clone());
+                    new.push(line.clone());
+                }
+                LineKind::Added => {
+                    new.push(line.clone());
+                    if picked {
+                        changes += 1;
+                    } else {
+                        old.push(line.clone());
+                    }
+                }
+                LineKind::Removed => {
+                    if picked {
+                        changes += 1;
+                        old.push(line.clone());
+                    }
+                }
+            }
+        }
+        if changes == 0 {
+            return Err("No changed lines selected".to_string());
+        }
+
+        // A restored removal can end up ahead of a kept addition; only the file's last
+        // line may lack a newline
+        let last = old.len().saturating_sub(1);
+        for line in &mut old[..last] {
+            line.no_newline = false;
+        }
+
+        // An empty side starts at the line before it, as git writes pure insertions
+        let old_start = match (old.is_empty(), self.old_count() == 0) {
+            (true, false) => self.old_start.saturating_sub(1),
+            (false, true) => self.old_start + 1,
+            _ => self.old_start,
+        };
+        Ok(Hunk {
+            old_start,
+            new_start: self.new_start,
+            section: self.section.clone(),
+            lines: diff_lines(&old, &new),
+        })
+    }
+
+    /// Give lines the "\r" that `content`, the file holding this hunk's new side, ends
+    /// them with, for hunks that came from a parser which dropped it. Removed lines
+    /// follow the file's usual line ending, except a last line without a newline.
+    pub fn restore_crlf(&mut self, content: &str) {
+        let file_lines: Vec<&str> = content.split('\n').collect();
+        let crlf_lines = file_lines.iter().filter(|l| l.ends_with('\r')).count();
+        let mostly_crlf = crlf_lines * 2 > file_lines.len().saturating_sub(1);
+
+        let mut next = self.new_start.saturating_sub(1);
+        for line in &mut self.lines {
+            if line.text.ends_with('\r') {
+                next += (line.kind != LineKind::Removed) as usize;
+                continue;
+            }
+            let crlf = match line.kind {
+                // The last line of a file has no line ending to take "\r" from
+                LineKind::Removed => mostly_crlf && !line.no_newline,
+                LineKind::Context | LineKind::Added => {
+                    let file_line = file_lines.get(next).copied().unwrap_or("");
+                    next += 1;
+                    file_line.strip_suffix('\r') == Some(line.text.as_str())
+                }
+            };
+            if crlf {
+                line.text.push('\r');
+            }
+        }
+    }
+}
+
+/// Line records turning `old` into `new`, with the lines both share as context
+fn diff_lines(old: &[HunkLine], new: &[HunkLine]) -> Vec<HunkLine> {
+    let mut interner = Interner::new(old.len() + new.len());
+    let before: Vec<Token> = old
+        .iter()
+        .map(|l| interner.intern((l.text.as_str(), l.no_newline)))
+        .collect();
+    let after: Vec<Token> = new
+        .iter()
+        .map(|l| interner.intern((l.text.as_str(), l.no_newline)))
+        .collect();
+
+    let mut changes: Vec<(Range<u32>, Range<u32>)> = Vec::new();
+    diff_with_tokens(
+        Algorithm::Histogram,
+        &before,
+        &after,
+        interner.num_tokens(),
+        |removed: Range<u32>, added: Range<u32>| changes.push((removed, added)),
+    );
+    changes.sort_by_key(|(removed, added)| (removed.start, added.start));
+
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    let mut cursor = 0;
+    for (removed, added) in changes {
+        let (removed, added) = (
+            removed.start as usize..removed.end as usize,
+            added.start as usize..added.end as usize,
+        );
+        lines.extend(
+            old[cursor..removed.start]
+                .iter()
+                .map(|l| HunkLine::rebuilt(LineKind::Context, l)),
+        );
+        lines.extend(
+            old[removed.clone()]
+                .iter()
+                .map(|l| HunkLine::rebuilt(LineKind::Removed, l)),
+        );
+        lines.extend(
+            new[added]
+                .iter()
+                .map(|l| HunkLine::rebuilt(LineKind::Added, l)),
+        );
+        cursor = removed.end;
+    }
+    lines.extend(
+        old[cursor..]
+            .iter()
+            .map(|l| HunkLine::rebuilt(LineKind::Context, l)),
+    );
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(hunk: &Hunk) -> Vec<String> {
+        hunk.to_text().lines().skip(1).map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_round_trips_text() {
+        let text = "@@ -10,4 +10,4 @@ fn main() {\n a\n-b\n+B\n c\n-d\n\\ No newline at end of file\n+D\n\\ No newline at end of file\n";
+        let hunk = Hunk::parse(text).unwrap();
+        assert_eq!((hunk.old_start, hunk.new_start), (10, 10));
+        assert_eq!(hunk.section, " fn main() {");
+        assert_eq!((hunk.old_count(), hunk.new_count()), (4, 4));
+        let d = &hunk.lines[4];
+        assert_eq!(
+            (d.kind, d.text.as_str(), d.no_newline),
+            (LineKind::Removed, "d", true)
+        );
+        assert_eq!(d.source_index, Some(4));
+        assert_eq!(hunk.lines[5].source_index, Some(6));
+        assert_eq!(hunk.to_text(), text);
+    }
+
+    #[test]
+    fn test_parse_keeps_crlf_and_bare_empty_context() {
+        let hunk = Hunk::parse("@@ -1,2 +1,2 @@\n a\r\n-b\r\n+c\r\n").unwrap();
+        assert_eq!(hunk.lines[1].text, "b\r");
+        assert_eq!(hunk.to_text(), "@@ -1,2 +1,2 @@\n a\r\n-b\r\n+c\r\n");
+
+        // Without the trailing newline and with the empty context line's space stripped
+        let hunk = Hunk::parse("@@ -1 +1,2 @@\n\n+x").unwrap();
+        assert_eq!(hunk.lines[0].kind, LineKind::Context);
+        assert_eq!(hunk.lines[0].text, "");
+        assert_eq!(hunk.header(), "@@ -1,1 +1,2 @@");
+    }
+
+    #[test]
+    fn test_parse_rejects_miscounted_hunks() {
+        assert!(Hunk::parse("a\n-b\n").is_err());
+        assert!(Hunk::parse("@@ -1,2 +1,2 @@\n-a\n+b\n").is_err());
+        assert!(Hunk::parse("@@ -1,1 +1,1 @@\n-a\n+b\n+c\n").is_err());
+        assert!(Hunk::parse("@@ -1,1 +1,1 @@\n\\ No newline at end of file\n-a\n+b\n").is_err());
+        assert!(Hunk::parse("@@ -1,1 +1,1 @@\n*a\n").is_err());
+        assert!(Hunk::parse("@@ -x +1 @@\n+a\n").is_err());
+    }
+
+    #[test]
+    fn test_select_for_discard_recomputes_header() {
+        let hunk = Hunk::parse("@@ -2,3 +2,4 @@\n a\n-b\n+B\n+new\n c\n").unwrap();
+        // Only the "+new" line: it becomes a removal against the rest of the new side
+        let partial = hunk.select_for_discard(&[3]).unwrap();
+        assert_eq!(partial.header(), "@@ -2,3 +2,4 @@");
+        assert_eq!(body(&partial), vec![" a", " B", "+new", " c"]);
+
+        // Only the "-b" line: b comes back next to the kept addition
+        let partial = hunk.select_for_discard(&[1]).unwrap();
+        assert_eq!(partial.header(), "@@ -2,5 +2,4 @@");
+        assert_eq!(body(&partial), vec![" a", "-b", " B", " new", " c"]);
+
+        let partial = hunk.select_for_discard(&[1, 2, 3]).unwrap();
+        assert_eq!(partial.to_text(), hunk.to_text());
+        assert!(hunk.select_for_discard(&[0, 4]).is_err());
+        assert!(hunk.select_for_discard(&[]).is_err());
+    }
+
+    #[test]
+    fn test_select_for_discard_keeps_newline_metadata() {
+        let text = "@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n";
+        let hunk = Hunk::parse(text).unwrap();
+
+        // Restoring b without removing c: b is no longer the last line
+        let partial = hunk.select_for_discard(&[1]).unwrap();
+        assert_eq!(
+            partial.to_text(),
+            "@@ -1,3 +1,2 @@\n a\n-b\n c\n\\ No newline at end of file\n"
+        );
+
+        let partial = hunk.select_for_discard(&[3]).unwrap();
+        assert_eq!(
+            partial.to_text(),
+            "@@ -1,1 +1,2 @@\n a\n+c\n\\ No newline at end of file\n"
+        );
+
+        // Newline added at the end: the marker stays with the old line
+        let hunk = Hunk::parse("@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+a\n").unwrap();
+        let partial = hunk.select_for_discard(&[2]).unwrap();
+        assert_eq!(partial.header(), "@@ -0,0 +1,1 @@");
+        assert_eq!(body(&partial), vec!["+a"]);
+    }
+
+    #[test]
+    fn test_select_for_discard_adjusts_empty_sides() {
+        // Pure insertion after line 5
+        let hunk = Hunk::parse("@@ -5,0 +6,2 @@\n+x\n+y\n").unwrap();
+        let partial = hunk.select_for_discard(&[1]).unwrap();
+        assert_eq!(partial.header(), "@@ -6,1 +6,2 @@");
+        assert_eq!(body(&partial), vec![" x", "+y"]);
+
+        // Pure removal of lines 3 and 4
+        let hunk = Hunk::parse("@@ -3,2 +2,0 @@\n-x\n-y\n").unwrap();
+        let partial = hunk.select_for_discard(&[0]).unwrap();
+        assert_eq!(partial.header(), "@@ -3,1 +2,0 @@");
+        assert_eq!(body(&partial), vec!["-x"]);
+    }
+
+    #[test]
+    fn test_restore_crlf_from_file_content() {
+        let mut hunk = Hunk::parse("@@ -1,3 +1,3 @@\n a\n-b\n+c\n d\n").unwrap();
+        hunk.restore_crlf("a\r\nc\r\nd\n");
+        let texts: Vec<&str> = hunk.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["a\r", "b\r", "c\r", "d"]);
+
+        // Lines that already carry "\r" are left alone
+        let before = hunk.clone();
+        hunk.restore_crlf("a\r\nc\r\nd\n");
+        assert_eq!(hunk, before);
+
+        let mut hunk = Hunk::parse("@@ -1,2 +1,1 @@\n a\n-b\n").unwrap();
+        hunk.restore_crlf("a\nz\n");
+        assert_eq!(hunk.lines[1].text, "b");
+
+        let mut hunk = Hunk::parse("@@ -2 +2 @@\n-b\n\\ No newline at end of file\n+b\n").unwrap();
+        hunk.restore_crlf("a\r\nb\r\n");
+        assert_eq!(
+            (hunk.lines[0].text.as_str(), hunk.lines[1].text.as_str()),
+            ("b", "b\r")
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Paths handed to git and jj processes.
+//!
+//! Workspaces live under `.treq/workspaces` inside the repository, so on Windows a
+//! workspace of a deep monorepo easily passes MAX_PATH (260). Directories that long
+//! are given to processes in the `\\?\` extended-length form, and git is told to
+//! accept long paths with `core.longpaths`. Elsewhere paths pass through unchanged.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::git_ops;
+
+/// Longest directory the legacy Windows APIs accept: MAX_PATH minus room for an
+/// 8.3 file name
+const MAX_DIR_PATH: usize = 248;
+
+/// Prefix of extended-length paths
+const EXTENDED_PREFIX: &str = r"\\?\";
+
+/// Resolve `.` and `..` components, which extended-length paths do not allow
+fn collapse_dots(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for (i, part) in path.split('\\').enumerate() {
+        match part {
+            "." => {}
+            // Never step above the drive or share
+            ".." if parts.len() > 1 => {
+                parts.pop();
+            }
+            ".." => {}
+            "" if i > 0 => {}
+            _ => parts.push(part),
+        }
+    }
+    parts.join("\\")
+}
+
+/// The extended-length form of an absolute Windows path too long for the legacy APIs;
+/// None when it is short enough, relative or already extended
+fn extended_length(path: &str) -> Option<String> {
+    if path.starts_with(EXTENDED_PREFIX) || path.encode_utf16().count() < MAX_DIR_PATH {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!(r"{}UNC\{}", EXTENDED_PREFIX, collapse_dots(unc)));
+    }
+    let bytes = path.as_bytes();
+    let is_drive_path =
+        bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    is_drive_path.then(|| format!("{}{}", EXTENDED_PREFIX, collapse_dots(&path)))
+}
+
+/// Directory to start a process in. Long paths become extended-length on Windows;
+/// other paths, and every path on other platforms, are returned unchanged.
+pub fn for_command(dir: &Path) -> Cow<'_, Path> {
+    if cfg!(windows) {
+        if let Some(extended) = dir.to_str().and_then(extended_length) {
+            return Cow::Owned(extended.into());
+        }
+    }
+    Cow::Borrowed(dir)
+}
+
+/// Whether `path` is too long for programs that do not handle long paths themselves
+pub fn exceeds_max_path(path: &str) -> bool {
+    path.encode_utf16().count() >= MAX_DIR_PATH
+}
+
+/// Whether git in `repo_path` accepts paths longer than MAX_PATH. Always true off
+/// Windows, where there is no such limit.
+pub fn git_longpaths_enabled(repo_path: &str) -> bool {
+    !cfg!(windows)
+        || git_ops::run_git(repo_path, &["config", "--bool", "--get", "core.longpaths"])
+            .is_ok_and(|value| value.trim() == "true")
+}
+
+/// Enable `core.longpaths` in the repository when a workspace at `workspace_path`
+/// would need it; git otherwise fails to check out files below it on Windows
+pub fn ensure_git_longpaths(repo_path: &str, workspace_path: &str) -> Result<(), String> {
+    if !exceeds_max_path(workspace_path) || git_longpaths_enabled(repo_path) {
+        return Ok(());
+    }
+    log::info!(
+        "Enabling core.longpaths in {} for workspace {}",
+        repo_path,
+        workspace_path
+    );
+    git_ops::run_git(repo_path, &["config", "core.longpaths", "true"])
+        .map(|_| ())
+        .map_err(|e| format!("Failed to enable core.longpaths: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extended_length() {
+        let long_name = "a".repeat(MAX_DIR_PATH);
+        assert_eq!(extended_length(r"C:\repo"), None);
+        assert_eq!(
+            extended_length(&format!(r"C:\repo\.treq\workspaces\..\{}", long_name)),
+            Some(format!(r"\\?\C:\repo\.treq\{}", long_name))
+        );
+        assert_eq!(
+            extended_length(&format!("D:/src/./{}", long_name)),
+            Some(format!(r"\\?\D:\src\{}", long_name))
+        );
+        assert_eq!(
+            extended_length(&format!(r"\\server\share\{}", long_name)),
+            Some(format!(r"\\?\UNC\server\share\{}", long_name))
+        );
+        let extended = format!(r"\\?\C:\{}", long_name);
+        assert_eq!(extended_length(&extended), None);
+        assert_eq!(extended_length(&format!(r"repo\{}", long_name)), None);
+    }
+
+    #[test]
+    fn test_git_runs_in_directory_beyond_max_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut dir = temp_dir.path().join(".treq").join("workspaces");
+        while dir.to_str().unwrap().len() <= 300 {
+            dir = dir.join("a-deeply-nested-monorepo-package");
+        }
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap();
+        assert!(exceeds_max_path(dir));
+
+        git_ops::run_git(dir, &["init", "-q"]).unwrap();
+        ensure_git_longpaths(dir, dir).unwrap();
+        // Only Windows needs the setting; elsewhere the repository is left alone
+        let longpaths = git_ops::run_git(dir, &["config", "--bool", "--get", "core.longpaths"]);
+        assert_eq!(
+            longpaths.ok().map(|value| value.trim().to_string()),
+            cfg!(windows).then(|| "true".to_string())
+        );
+        assert!(git_longpaths_enabled(dir));
+
+        let file = format!("{}.txt", "f".repeat(40));
+        fs::write(Path::new(dir).join(&file), "content").unwrap();
+        git_ops::run_git(dir, &["add", &file]).unwrap();
+        let status = git_ops::run_git(dir, &["status", "--porcelain"]).unwrap();
+        assert_eq!(status.trim(), format!("A  {}", file));
+    }
+}
@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::paths;
+
+/// Settings key for the maximum number of concurrent git/jj processes
+pub const MAX_PARALLEL_PROCESSES_KEY: &str = "max_parallel_processes";
+
+/// Upper bound for the configured limit, to keep a bad setting from disabling the limiter
+const MAX_PARALLEL_PROCESSES_CAP: usize = 64;
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    max_concurrent: usize,
+    active: usize,
+    waiting: usize,
+    peak_active: usize,
+    total_started: u64,
+    total_queued: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+/// Global counting semaphore governing spawned git/jj processes and user scripts
+struct ProcessLimiter {
+    state: Mutex<LimiterState>,
+    available: Condvar,
+}
+
+/// Snapshot of process limiter queueing metrics
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProcessLimiterMetrics {
+    pub max_concurrent: usize,
+    pub active: usize,
+    pub waiting: usize,
+    pub peak_active: usize,
+    pub total_started: u64,
+    /// Processes that had to wait for a free slot
+    pub total_queued: u64,
+    pub average_wait_ms: f64,
+    pub max_wait_ms: u64,
+}
+
+static LIMITER: OnceLock<ProcessLimiter> = OnceLock::new();
+
+/// Default limit: one process per core, but at least 2 so a slow command can't block everything
+fn default_max_concurrent() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .clamp(2, 8)
+}
+
+fn limiter() -> &'static ProcessLimiter {
+    LIMITER.get_or_init(|| ProcessLimiter {
+        state: Mutex::new(LimiterState {
+            max_concurrent: default_max_concurrent(),
+            ..Default::default()
+        }),
+        available: Condvar::new(),
+    })
+}
+
+/// Set the maximum number of concurrent processes. `None` restores the default.
+pub fn set_max_concurrent(max: Option<usize>) {
+    let limiter = limiter();
+    let mut state = limiter.state.lock().unwrap();
+    state.max_concurrent = max
+        .unwrap_or_else(default_max_concurrent)
+        .clamp(1, MAX_PARALLEL_PROCESSES_CAP);
+    drop(state);
+    // Raising the limit may let queued processes start
+    limiter.available.notify_all();
+}
+
+/// Parse the stored setting value; empty or invalid values mean "use the default"
+pub fn parse_max_concurrent_setting(value: Option<&str>) -> Option<usize> {
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+}
+
+/// Get current limiter metrics
+pub fn get_metrics() -> ProcessLimiterMetrics {
+    let state = limiter().state.lock().unwrap();
+    let average_wait_ms = if state.total_queued > 0 {
+        state.total_wait.as_secs_f64() * 1000.0 / state.total_queued as f64
+    } else {
+        0.0
+    };
+    ProcessLimiterMetrics {
+        max_concurrent: state.max_concurrent,
+        active: state.active,
+        waiting: state.waiting,
+        peak_active: state.peak_active,
+        total_started: state.total_started,
+        total_queued: state.total_queued,
+        average_wait_ms,
+        max_wait_ms: state.max_wait.as_millis() as u64,
+    }
+}
+
+/// A slot in the process limiter, released on drop
+pub struct ProcessPermit {
+    _private: (),
+}
+
+impl Drop for ProcessPermit {
+    fn drop(&mut self) {
+        let limiter = limiter();
+        let mut state = limiter.state.lock().unwrap();
+        state.active = state.active.saturating_sub(1);
+        drop(state);
+        limiter.available.notify_one();
+    }
+}
+
+/// Block until a process slot is available
+pub fn acquire() -> ProcessPermit {
+    let limiter = limiter();
+    let mut state = limiter.state.lock().unwrap();
+
+    if state.active >= state.max_concurrent {
+        let started_waiting = Instant::now();
+        state.waiting += 1;
+        state.total_queued += 1;
+        while state.active >= state.max_concurrent {
+            state = limiter.available.wait(state).unwrap();
+        }
+        state.waiting -= 1;
+        let waited = started_waiting.elapsed();
+        state.total_wait += waited;
+        state.max_wait = state.max_wait.max(waited);
+    }
+
+    state.active += 1;
+    state.total_started += 1;
+    state.peak_active = state.peak_active.max(state.active);
+
+    ProcessPermit { _private: () }
+}
+
+/// A `Command` that holds a process limiter slot while its process runs.
+///
+/// Mirrors the builder methods of `std::process::Command` so existing call sites
+/// keep working unchanged.
+pub struct LimitedCommand {
+    inner: Command,
+}
+
+impl LimitedCommand {
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Self {
+            inner: Command::new(program),
+        }
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.inner.arg(arg);
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.inner.args(args);
+        self
+    }
+
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.inner.current_dir(paths::for_command(dir.as_ref()));
+        self
+    }
+
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, val: V) -> &mut Self {
+        self.inner.env(key, val);
+        self
+    }
+
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.envs(vars);
+        self
+    }
+
+    pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stdin(cfg);
+        self
+    }
+
+    pub fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stdout(cfg);
+        self
+    }
+
+    pub fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stderr(cfg);
+        self
+    }
+
+    /// Start the process in its own process group; see `CommandExt::process_group`
+    #[cfg(unix)]
+    pub fn process_group(&mut self, pgroup: i32) -> &mut Self {
+        std::os::unix::process::CommandExt::process_group(&mut self.inner, pgroup);
+        self
+    }
+
+    /// Start the process once a slot is free. The slot stays taken until the returned
+    /// permit is dropped, so hold it for as long as the child runs.
+    pub fn spawn(&mut self) -> io::Result<(Child, ProcessPermit)> {
+        let permit = acquire();
+        Ok((self.inner.spawn()?, permit))
+    }
+
+    pub fn output(&mut self) -> io::Result<Output> {
+        let _permit = acquire();
+        self.inner.output()
+    }
+
+    /// Like `output`, writing `input` to the process's stdin
+    pub fn output_with_input(&mut self, input: &[u8]) -> io::Result<Output> {
+        let _permit = acquire();
+        let mut child = self
+            .inner
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = input.to_vec();
+        // Write from another thread so a child filling its stdout can't deadlock us
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        let _ = writer.join();
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_max_concurrent_setting() {
+        assert_eq!(parse_max_concurrent_setting(Some("4")), Some(4));
+        assert_eq!(parse_max_concurrent_setting(Some(" 12 ")), Some(12));
+        assert_eq!(parse_max_concurrent_setting(Some("0")), None);
+        assert_eq!(parse_max_concurrent_setting(Some("many")), None);
+        assert_eq!(parse_max_concurrent_setting(None), None);
+    }
+
+    #[test]
+    fn test_permits_are_released_on_drop() {
+        let before = get_metrics();
+        {
+            let _a = acquire();
+            let _b = acquire();
+        }
+        let after = get_metrics();
+        assert!(after.total_started >= before.total_started + 2);
+        assert!(after.peak_active >= 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_input() {
+        let output = LimitedCommand::new("cat")
+            .output_with_input(b"hello")
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello");
+    }
+}
@@ -1,8 +1,24 @@
 use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// Settings key: scrollback kept per terminal session, in bytes
+pub const SCROLLBACK_BYTES_KEY: &str = "terminal_scrollback_bytes";
+
+/// Default scrollback per session (256 KiB)
+pub const DEFAULT_SCROLLBACK_BYTES: usize = 256 * 1024;
+
+/// Parse the SCROLLBACK_BYTES_KEY setting, falling back to the default
+pub fn parse_scrollback_setting(value: Option<&str>) -> usize {
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_SCROLLBACK_BYTES)
+}
 
 /// Process a chunk of bytes, handling incomplete UTF-8 sequences at boundaries.
 ///
@@ -41,10 +57,89 @@ fn process_utf8_chunk(pending: &mut Vec<u8>, new_bytes: &[u8]) -> String {
     }
 }
 
+/// Extra environment applied when a session's shell spawns
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PtyEnvironment {
+    /// Variables to set; `$PATH` inside a PATH value expands to the default PATH
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Command run before `initial_command`, e.g. `source ~/.nvm/nvm.sh && nvm use`
+    pub startup_command: Option<String>,
+}
+
+/// Expand `$PATH`/`${PATH}` in a PATH override so entries can be prepended or appended
+fn expand_path_value(value: &str, default_path: &str) -> String {
+    value
+        .replace("${PATH}", default_path)
+        .replace("$PATH", default_path)
+}
+
+/// Ring buffer of recent terminal output, bounded by byte size
+struct ScrollbackBuffer {
+    chunks: VecDeque<String>,
+    len: usize,
+    capacity: usize,
+    /// When the session last produced output (restored history doesn't count)
+    last_output: Option<Instant>,
+}
+
+impl ScrollbackBuffer {
+    fn new(capacity: usize) -> Self {
+        ScrollbackBuffer {
+            chunks: VecDeque::new(),
+            len: 0,
+            capacity,
+            last_output: None,
+        }
+    }
+
+    fn push(&mut self, data: &str) {
+        self.len += data.len();
+        self.chunks.push_back(data.to_string());
+        self.last_output = Some(Instant::now());
+        self.trim();
+    }
+
+    /// Insert output that preceded everything in the buffer (restored history)
+    fn push_front(&mut self, data: &str) {
+        self.len += data.len();
+        self.chunks.push_front(data.to_string());
+        self.trim();
+    }
+
+    /// Drop the oldest output until the buffer fits its capacity
+    fn trim(&mut self) {
+        while self.len > self.capacity {
+            let Some(front) = self.chunks.front_mut() else {
+                break;
+            };
+            let excess = self.len - self.capacity;
+            if front.len() <= excess {
+                self.len -= front.len();
+                self.chunks.pop_front();
+            } else {
+                // Cut inside the chunk at the next char boundary
+                let mut cut = excess;
+                while !front.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                front.drain(..cut);
+                self.len -= cut;
+            }
+        }
+    }
+
+    fn contents(&self) -> String {
+        self.chunks.iter().map(String::as_str).collect()
+    }
+}
+
 pub struct PtySession {
     writer: Box<dyn Write + Send>,
     master: Box<dyn MasterPty + Send>,
-    _child: Box<dyn Child + Send>,
+    child: Box<dyn Child + Send>,
+    working_dir: Option<String>,
+    scrollback: Arc<Mutex<ScrollbackBuffer>>,
 }
 
 impl PtySession {
@@ -67,21 +162,29 @@ impl PtySession {
 
 pub struct PtyManager {
     sessions: Arc<Mutex<HashMap<String, PtySession>>>,
+    scrollback_capacity: AtomicUsize,
 }
 
 impl PtyManager {
     pub fn new() -> Self {
         PtyManager {
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            scrollback_capacity: AtomicUsize::new(DEFAULT_SCROLLBACK_BYTES),
         }
     }
 
+    /// Scrollback size for sessions created from now on
+    pub fn set_scrollback_capacity(&self, bytes: usize) {
+        self.scrollback_capacity.store(bytes, Ordering::Relaxed);
+    }
+
     pub fn create_session(
         &self,
         session_id: String,
         working_dir: Option<String>,
         shell: Option<String>,
         initial_command: Option<String>,
+        environment: PtyEnvironment,
         callback: Box<dyn Fn(String) + Send + 'static>,
     ) -> Result<(), String> {
         let pty_system = native_pty_system();
@@ -106,13 +209,23 @@ impl PtyManager {
         });
 
         let mut cmd = CommandBuilder::new(&shell_cmd);
-        if let Some(dir) = working_dir {
+        if let Some(dir) = &working_dir {
             cmd.cwd(dir);
         }
         cmd.env("TERM", "xterm-256color");
 
         // Set extended PATH so terminal can find jj, git, claude binaries
-        cmd.env("PATH", crate::binary_paths::get_extended_path());
+        let default_path = crate::binary_paths::get_extended_path();
+        cmd.env("PATH", &default_path);
+
+        // Caller-provided variables override the defaults above
+        for (key, value) in &environment.env {
+            if key == "PATH" {
+                cmd.env(key, expand_path_value(value, &default_path));
+            } else {
+                cmd.env(key, value);
+            }
+        }
 
         let child = pair.slave.spawn_command(cmd).map_err(|e| e.to_string())?;
         drop(pair.slave);
@@ -120,6 +233,9 @@ impl PtyManager {
         let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
         let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
         let master = pair.master;
+        let scrollback = Arc::new(Mutex::new(ScrollbackBuffer::new(
+            self.scrollback_capacity.load(Ordering::Relaxed),
+        )));
 
         // Store session with master for resizing
         {
@@ -129,17 +245,27 @@ impl PtyManager {
                 PtySession {
                     writer,
                     master,
-                    _child: child,
+                    child,
+                    working_dir,
+                    scrollback: scrollback.clone(),
                 },
             );
         }
 
-        // Execute initial command if provided
-        if let Some(cmd) = initial_command {
+        // Execute startup and initial commands if provided
+        let commands: Vec<String> = environment
+            .startup_command
+            .into_iter()
+            .chain(initial_command)
+            .filter(|c| !c.trim().is_empty())
+            .collect();
+        if !commands.is_empty() {
             // Wait a bit for shell to be ready
-            thread::sleep(std::time::Duration::from_millis(100));
-            let cmd_with_newline = format!("{}\n", cmd);
-            self.write_to_session(&session_id, &cmd_with_newline)?;
+            thread::sleep(Duration::from_millis(100));
+            for cmd in commands {
+                let cmd_with_newline = format!("{}\n", cmd);
+                self.write_to_session(&session_id, &cmd_with_newline)?;
+            }
         }
 
         // Spawn reader thread
@@ -154,6 +280,7 @@ impl PtyManager {
                         if !pending_bytes.is_empty() {
                             let data = String::from_utf8_lossy(&pending_bytes).to_string();
                             if !data.is_empty() {
+                                scrollback.lock().unwrap().push(&data);
                                 callback(data);
                             }
                         }
@@ -162,6 +289,7 @@ impl PtyManager {
                     Ok(n) => {
                         let data = process_utf8_chunk(&mut pending_bytes, &buffer[..n]);
                         if !data.is_empty() {
+                            scrollback.lock().unwrap().push(&data);
                             callback(data);
                         }
                     }
@@ -201,4 +329,88 @@ impl PtyManager {
         let sessions = self.sessions.lock().unwrap();
         sessions.contains_key(session_id)
     }
+
+    pub fn session_ids(&self) -> Vec<String> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.keys().cloned().collect()
+    }
+
+    /// Number of sessions that produced output within `window`, e.g. a running agent or build
+    pub fn active_session_count(&self, window: Duration) -> usize {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .values()
+            .filter(|session| {
+                session
+                    .scrollback
+                    .lock()
+                    .unwrap()
+                    .last_output
+                    .is_some_and(|at| at.elapsed() < window)
+            })
+            .count()
+    }
+
+    /// Kill every session's shell and close its PTY (on exit).
+    /// Closing the master hangs up the terminal, which signals the shell's process group.
+    pub fn terminate_all(&self) {
+        let mut sessions = self.sessions.lock().unwrap();
+        for (session_id, mut session) in sessions.drain() {
+            if let Err(e) = session.child.kill() {
+                log::warn!("Failed to terminate terminal session {}: {}", session_id, e);
+            }
+        }
+    }
+
+    /// Buffered output of a live session
+    pub fn get_scrollback(&self, session_id: &str) -> Option<String> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(session_id)
+            .map(|session| session.scrollback.lock().unwrap().contents())
+    }
+
+    /// Working directory and buffered output of a live session, for persisting
+    pub fn scrollback_snapshot(&self, session_id: &str) -> Option<(Option<String>, String)> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.get(session_id).map(|session| {
+            (
+                session.working_dir.clone(),
+                session.scrollback.lock().unwrap().contents(),
+            )
+        })
+    }
+
+    /// Put output from a previous run of the session ahead of its current output
+    pub fn restore_scrollback(&self, session_id: &str, content: &str) -> Result<(), String> {
+        let sessions = self.sessions.lock().unwrap();
+        match sessions.get(session_id) {
+            Some(session) => {
+                session.scrollback.lock().unwrap().push_front(content);
+                Ok(())
+            }
+            None => Err("Session not found".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrollback_buffer_drops_oldest_output() {
+        let mut buffer = ScrollbackBuffer::new(10);
+        buffer.push("hello ");
+        buffer.push("world");
+        assert_eq!(buffer.contents(), "ello world");
+
+        buffer.push_front("restored");
+        assert_eq!(buffer.contents(), "ello world");
+
+        // Trimming never splits a multi-byte character
+        let mut buffer = ScrollbackBuffer::new(4);
+        buffer.push("aé日b");
+        assert_eq!(buffer.contents(), "日b");
+    }
 }
@@ -0,0 +1,613 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::db::Database;
+use crate::git_ops::{self, run_git, CloneProgress};
+use crate::jj;
+
+const DEFAULT_INITIAL_BRANCH: &str = "main";
+const DEFAULT_COMMIT_MESSAGE: &str = "Initial commit";
+const DEFAULT_REMOTE_NAME: &str = "origin";
+
+/// Built-in .gitignore templates by name
+const GITIGNORE_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "node",
+        "node_modules/\ndist/\nbuild/\ncoverage/\n.env\n.env.local\nnpm-debug.log*\n",
+    ),
+    ("rust", "/target/\n**/*.rs.bk\n"),
+    (
+        "python",
+        "__pycache__/\n*.py[cod]\n.venv/\nvenv/\n.pytest_cache/\ndist/\n*.egg-info/\n",
+    ),
+    ("go", "/bin/\n*.test\n*.out\n"),
+];
+
+/// Author identity written to the new repository's git config
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitIdentity {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BootstrapOptions {
+    /// Defaults to "main"
+    pub initial_branch: Option<String>,
+    /// Name of a built-in .gitignore template (node, rust, python, go)
+    pub gitignore_template: Option<String>,
+    /// README.md content; an empty string writes a title from the directory name.
+    /// An existing README.md is kept.
+    pub readme: Option<String>,
+    /// Commit the initial files; defaults to true
+    pub initial_commit: Option<bool>,
+    pub commit_message: Option<String>,
+    /// Initialize jj colocated with git; defaults to true
+    pub init_jj: Option<bool>,
+    pub identity: Option<GitIdentity>,
+    pub remote_url: Option<String>,
+    /// Defaults to "origin"
+    pub remote_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BootstrapResult {
+    pub path: String,
+    pub initial_branch: String,
+    /// Hash of the initial commit, when one was made
+    pub initial_commit: Option<String>,
+    /// Files written by the bootstrap, relative to the repository root
+    pub created_files: Vec<String>,
+    pub jj_initialized: bool,
+    /// Name of the remote that was added
+    pub remote: Option<String>,
+}
+
+/// Names of the available .gitignore templates
+pub fn gitignore_template_names() -> Vec<String> {
+    GITIGNORE_TEMPLATES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+fn validate_arg(value: &str, label: &str) -> Result<(), String> {
+    if value.trim().is_empty() || value.starts_with('-') || value.contains('\0') {
+        return Err(format!("Invalid {}", label));
+    }
+    Ok(())
+}
+
+fn gitignore_template(name: &str) -> Result<&'static str, String> {
+    GITIGNORE_TEMPLATES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, content)| *content)
+        .ok_or_else(|| format!("Unknown .gitignore template '{}'", name))
+}
+
+/// Write the template and the .jj/.treq ignore entries, keeping an existing .gitignore
+fn write_gitignore(path: &str, template: Option<&str>) -> Result<(), String> {
+    let gitignore_path = Path::new(path).join(".gitignore");
+    if let Some(content) = template.filter(|_| !gitignore_path.exists()) {
+        fs::write(&gitignore_path, content)
+            .map_err(|e| format!("Failed to write .gitignore: {}", e))?;
+    }
+    jj::ensure_gitignore_entries(path).map_err(|e| e.to_string())
+}
+
+fn readme_content(repo_path: &Path, content: &str) -> String {
+    if !content.trim().is_empty() {
+        return content.to_string();
+    }
+    let title = repo_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Project".to_string());
+    format!("# {}\n", title)
+}
+
+/// Create a repository ready for treq: git init, .gitignore, identity, an optional
+/// README, an initial commit, jj and optionally a remote. `path` may already contain files, which are
+/// included in the initial commit, but must not already be a repository. If a step fails,
+/// everything created so far is removed again so the bootstrap can be retried.
+pub fn bootstrap_repository(
+    db: &Database,
+    path: &str,
+    options: &BootstrapOptions,
+) -> Result<BootstrapResult, String> {
+    let repo_path = Path::new(path);
+    if repo_path.join(".git").exists() || repo_path.join(".jj").exists() {
+        return Err(format!("{} is already a repository", path));
+    }
+
+    let initial_branch = options
+        .initial_branch
+        .as_deref()
+        .unwrap_or(DEFAULT_INITIAL_BRANCH);
+    validate_arg(initial_branch, "initial branch name")?;
+    let remote = match &options.remote_url {
+        Some(url) => {
+            let name = options
+                .remote_name
+                .as_deref()
+                .unwrap_or(DEFAULT_REMOTE_NAME);
+            validate_arg(name, "remote name")?;
+            validate_arg(url, "remote URL")?;
+            Some((name, url.as_str()))
+        }
+        None => None,
+    };
+    let message = options
+        .commit_message
+        .as_deref()
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or(DEFAULT_COMMIT_MESSAGE);
+    if message.contains('\0') {
+        return Err("Invalid commit message".to_string());
+    }
+    let template = options
+        .gitignore_template
+        .as_deref()
+        .map(gitignore_template)
+        .transpose()?;
+
+    let created_dir = !repo_path.exists();
+    let original_gitignore = fs::read(repo_path.join(".gitignore")).ok();
+    let mut created_files = Vec::new();
+    let result = initialize_repository(
+        db,
+        path,
+        options,
+        initial_branch,
+        message,
+        template,
+        &mut created_files,
+    );
+    let (initial_commit, jj_initialized) = match result {
+        Ok(initialized) => initialized,
+        Err(e) => {
+            rollback_bootstrap(repo_path, created_dir, original_gitignore, &created_files);
+            return Err(e);
+        }
+    };
+
+    if let Some((name, url)) = remote {
+        if let Err(e) = run_git(path, &["remote", "add", name, url]) {
+            rollback_bootstrap(repo_path, created_dir, original_gitignore, &created_files);
+            return Err(e.to_string());
+        }
+    }
+
+    Ok(BootstrapResult {
+        path: path.to_string(),
+        initial_branch: initial_branch.to_string(),
+        initial_commit,
+        created_files,
+        jj_initialized,
+        remote: remote.map(|(name, _)| name.to_string()),
+    })
+}
+
+/// The steps of `bootstrap_repository` that change the disk, returning the initial
+/// commit and whether jj was initialized. Files it writes are added to `created_files`.
+fn initialize_repository(
+    db: &Database,
+    path: &str,
+    options: &BootstrapOptions,
+    initial_branch: &str,
+    message: &str,
+    template: Option<&str>,
+    created_files: &mut Vec<String>,
+) -> Result<(Option<String>, bool), String> {
+    let repo_path = Path::new(path);
+    fs::create_dir_all(repo_path).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let branch_arg = format!("--initial-branch={}", initial_branch);
+    run_git(path, &["init", "-q", &branch_arg]).map_err(|e| e.to_string())?;
+
+    // Before committing, so the initial commit and jj pick up the identity
+    if let Some(identity) = &options.identity {
+        for (key, value) in [
+            ("user.name", &identity.name),
+            ("user.email", &identity.email),
+        ] {
+            if value.trim().is_empty() || value.contains('\0') {
+                return Err(format!("Invalid {}", key));
+            }
+            run_git(path, &["config", key, value]).map_err(|e| e.to_string())?;
+        }
+    }
+
+    if let Some(content) = &options.readme {
+        let readme_path = repo_path.join("README.md");
+        if !readme_path.exists() {
+            fs::write(&readme_path, readme_content(repo_path, content))
+                .map_err(|e| format!("Failed to write README.md: {}", e))?;
+            created_files.push("README.md".to_string());
+        }
+    }
+    let had_gitignore = repo_path.join(".gitignore").exists();
+    write_gitignore(path, template)?;
+    if !had_gitignore {
+        created_files.push(".gitignore".to_string());
+    }
+
+    let initial_commit = if options.initial_commit.unwrap_or(true) {
+        run_git(path, &["add", "-A"]).map_err(|e| e.to_string())?;
+        run_git(path, &["commit", "-q", "-m", message]).map_err(|e| e.to_string())?;
+        let hash = run_git(path, &["rev-parse", "HEAD"]).map_err(|e| e.to_string())?;
+        Some(hash.trim().to_string())
+    } else {
+        None
+    };
+
+    let jj_initialized = if options.init_jj.unwrap_or(true) {
+        jj::ensure_jj_initialized(db, path).map_err(|e| e.to_string())?;
+        true
+    } else {
+        false
+    };
+    Ok((initial_commit, jj_initialized))
+}
+
+/// Undo a failed bootstrap: remove the git and jj metadata and the files it wrote,
+/// restore an existing .gitignore, and remove the directory if it created it
+fn rollback_bootstrap(
+    repo_path: &Path,
+    created_dir: bool,
+    original_gitignore: Option<Vec<u8>>,
+    created_files: &[String],
+) {
+    for dir in [".jj", ".git"] {
+        let _ = fs::remove_dir_all(repo_path.join(dir));
+    }
+    for file in created_files {
+        let _ = fs::remove_file(repo_path.join(file));
+    }
+    if let Some(content) = original_gitignore {
+        let _ = fs::write(repo_path.join(".gitignore"), content);
+    }
+    if created_dir {
+        // Only removes the directory if nothing else was put in it meanwhile
+        let _ = fs::remove_dir(repo_path);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CloneOptions {
+    /// Branch to check out instead of the remote's HEAD
+    pub branch: Option<String>,
+    /// Create a shallow clone with this many commits
+    pub depth: Option<u32>,
+    /// Defaults to "origin"
+    pub remote_name: Option<String>,
+    pub recurse_submodules: Option<bool>,
+    /// Initialize jj colocated with git; defaults to true
+    pub init_jj: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CloneResult {
+    pub path: String,
+    /// Checked out branch; None for an empty repository
+    pub branch: Option<String>,
+    pub jj_initialized: bool,
+}
+
+/// `clone-progress` event payload
+#[derive(Debug, Serialize, Clone)]
+pub struct CloneProgressEvent {
+    pub clone_id: String,
+    pub dest_path: String,
+    #[serde(flatten)]
+    pub progress: CloneProgress,
+}
+
+/// `clone-complete` event payload; exactly one of `result` and `error` is set
+#[derive(Debug, Serialize, Clone)]
+pub struct CloneComplete {
+    pub clone_id: String,
+    pub dest_path: String,
+    pub result: Option<CloneResult>,
+    pub error: Option<String>,
+}
+
+/// Id for a clone started in the background, used to match its events
+pub fn next_clone_id() -> String {
+    static NEXT_CLONE_ID: AtomicU64 = AtomicU64::new(1);
+    format!("clone-{}", NEXT_CLONE_ID.fetch_add(1, Ordering::SeqCst))
+}
+
+fn clone_args(options: &CloneOptions) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    if let Some(branch) = &options.branch {
+        validate_arg(branch, "branch name")?;
+        args.push(format!("--branch={}", branch));
+    }
+    if let Some(depth) = options.depth {
+        if depth == 0 {
+            return Err("Clone depth must be at least 1".to_string());
+        }
+        args.push(format!("--depth={}", depth));
+    }
+    if let Some(name) = &options.remote_name {
+        validate_arg(name, "remote name")?;
+        args.push(format!("--origin={}", name));
+    }
+    if options.recurse_submodules.unwrap_or(false) {
+        args.push("--recurse-submodules".to_string());
+    }
+    Ok(args)
+}
+
+/// Removes what an unfinished clone left in its destination, so it can be retried.
+/// Runs on drop, which also covers a cancelled clone whose future is dropped.
+struct CloneCleanup<'a> {
+    dest: &'a Path,
+    /// The destination was an existing empty directory, which is kept
+    existed: bool,
+    finished: bool,
+}
+
+impl Drop for CloneCleanup<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let _ = fs::remove_dir_all(self.dest);
+        if self.existed {
+            let _ = fs::create_dir(self.dest);
+        }
+    }
+}
+
+/// Clone `url` into `dest_path` and prepare it like any repository opened in treq.
+/// `dest_path` must not exist or be an empty directory; a failed or cancelled clone
+/// leaves it as it was.
+pub async fn clone_repository(
+    db: &Mutex<Database>,
+    url: &str,
+    dest_path: &str,
+    options: &CloneOptions,
+    on_progress: &mut dyn FnMut(CloneProgress),
+) -> Result<CloneResult, String> {
+    validate_arg(url, "repository URL")?;
+    validate_arg(dest_path, "destination path")?;
+    let dest = Path::new(dest_path);
+    let existed = dest.exists();
+    if existed {
+        let is_empty_dir = fs::read_dir(dest)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if !is_empty_dir {
+            return Err(format!("{} already exists and is not empty", dest_path));
+        }
+    }
+    let args = clone_args(options)?;
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let mut cleanup = CloneCleanup {
+        dest,
+        existed,
+        finished: false,
+    };
+    git_ops::git_clone(url, dest_path, &args, on_progress)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // An empty clone has an unborn HEAD, which rev-parse rejects
+    let branch = run_git(dest_path, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .ok()
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty() && b != "HEAD");
+
+    let jj_initialized = if options.init_jj.unwrap_or(true) {
+        let db = db.lock().unwrap();
+        jj::ensure_jj_initialized(&db, dest_path).map_err(|e| e.to_string())?;
+        true
+    } else {
+        false
+    };
+    cleanup.finished = true;
+
+    Ok(CloneResult {
+        path: dest_path.to_string(),
+        branch,
+        jj_initialized,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_process;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bootstrap_repository_without_jj() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().join("treq.db")).unwrap();
+        db.init().unwrap();
+        let path = temp_dir.path().join("project");
+        let path = path.to_str().unwrap();
+        fs::create_dir_all(path).unwrap();
+        fs::write(Path::new(path).join("README.md"), "# Project\n").unwrap();
+
+        let options = BootstrapOptions {
+            initial_branch: Some("trunk".to_string()),
+            gitignore_template: Some("rust".to_string()),
+            init_jj: Some(false),
+            identity: Some(GitIdentity {
+                name: "Ada".to_string(),
+                email: "ada@example.com".to_string(),
+            }),
+            remote_url: Some("https://example.com/project.git".to_string()),
+            ..Default::default()
+        };
+        let result = bootstrap_repository(&db, path, &options).unwrap();
+        assert_eq!(result.initial_branch, "trunk");
+        assert_eq!(result.remote.as_deref(), Some("origin"));
+        assert_eq!(result.created_files, [".gitignore"]);
+        assert!(result.initial_commit.is_some());
+        assert!(!result.jj_initialized);
+
+        let files = run_git(path, &["ls-files"]).unwrap();
+        assert_eq!(
+            files.lines().collect::<Vec<_>>(),
+            [".gitignore", "README.md"]
+        );
+        let gitignore = fs::read_to_string(Path::new(path).join(".gitignore")).unwrap();
+        assert!(gitignore.starts_with("/target/"));
+        assert!(gitignore.contains(".treq/"));
+
+        let author = run_git(path, &["log", "-1", "--format=%an <%ae> %s"]).unwrap();
+        assert_eq!(author.trim(), "Ada <ada@example.com> Initial commit");
+        let branch = run_git(path, &["symbolic-ref", "--short", "HEAD"]).unwrap();
+        assert_eq!(branch.trim(), "trunk");
+
+        let err = bootstrap_repository(&db, path, &options).unwrap_err();
+        assert!(err.contains("already a repository"));
+    }
+
+    #[test]
+    fn test_failed_bootstrap_is_rolled_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().join("treq.db")).unwrap();
+        db.init().unwrap();
+        let path = temp_dir.path().join("project");
+        let path = path.to_str().unwrap();
+
+        let mut options = BootstrapOptions {
+            readme: Some(String::new()),
+            init_jj: Some(false),
+            identity: Some(GitIdentity {
+                name: String::new(),
+                email: "ada@example.com".to_string(),
+            }),
+            ..Default::default()
+        };
+        let err = bootstrap_repository(&db, path, &options).unwrap_err();
+        assert!(err.contains("user.name"));
+        assert!(!Path::new(path).exists());
+
+        options.identity = Some(GitIdentity {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+        });
+        let result = bootstrap_repository(&db, path, &options).unwrap();
+        assert_eq!(result.created_files, ["README.md", ".gitignore"]);
+    }
+
+    #[test]
+    fn test_bootstrap_repository_with_readme_and_no_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().join("treq.db")).unwrap();
+        db.init().unwrap();
+        let path = temp_dir.path().join("widget");
+        let path = path.to_str().unwrap();
+
+        let options = BootstrapOptions {
+            readme: Some(String::new()),
+            initial_commit: Some(false),
+            init_jj: Some(false),
+            ..Default::default()
+        };
+        let result = bootstrap_repository(&db, path, &options).unwrap();
+        assert_eq!(result.created_files, ["README.md", ".gitignore"]);
+        assert_eq!(result.initial_commit, None);
+        let readme = fs::read_to_string(Path::new(path).join("README.md")).unwrap();
+        assert_eq!(readme, "# widget\n");
+        assert!(run_git(path, &["rev-parse", "--verify", "HEAD"]).is_err());
+        let branch = run_git(path, &["symbolic-ref", "--short", "HEAD"]).unwrap();
+        assert_eq!(branch.trim(), "main");
+    }
+
+    #[test]
+    fn test_clone_repository_without_jj() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().join("treq.db")).unwrap();
+        db.init().unwrap();
+        let source = temp_dir.path().join("source");
+        let source = source.to_str().unwrap();
+        let options = BootstrapOptions {
+            initial_branch: Some("trunk".to_string()),
+            init_jj: Some(false),
+            identity: Some(GitIdentity {
+                name: "Ada".to_string(),
+                email: "ada@example.com".to_string(),
+            }),
+            ..Default::default()
+        };
+        bootstrap_repository(&db, source, &options).unwrap();
+
+        let db = Mutex::new(db);
+        let url = format!("file://{}", source);
+        let dest = temp_dir.path().join("clones").join("copy");
+        let dest = dest.to_str().unwrap();
+        let clone_options = CloneOptions {
+            depth: Some(1),
+            init_jj: Some(false),
+            ..Default::default()
+        };
+        let mut phases = Vec::new();
+        let result = async_process::block_on(clone_repository(
+            &db,
+            &url,
+            dest,
+            &clone_options,
+            &mut |progress| phases.push(progress.phase),
+        ))
+        .unwrap();
+        assert_eq!(result.path, dest);
+        assert_eq!(result.branch.as_deref(), Some("trunk"));
+        assert!(!result.jj_initialized);
+        assert!(Path::new(dest).join(".gitignore").exists());
+        assert!(phases.iter().all(|phase| !phase.is_empty()));
+
+        let err = async_process::block_on(clone_repository(
+            &db,
+            &url,
+            dest,
+            &clone_options,
+            &mut |_| {},
+        ))
+        .unwrap_err();
+        assert!(err.contains("not empty"));
+    }
+
+    #[test]
+    fn test_unfinished_clone_is_cleaned_up() {
+        let temp_dir = TempDir::new().unwrap();
+        let created = temp_dir.path().join("created");
+        fs::create_dir_all(created.join(".git")).unwrap();
+        drop(CloneCleanup {
+            dest: &created,
+            existed: false,
+            finished: false,
+        });
+        assert!(!created.exists());
+
+        let existing = temp_dir.path().join("existing");
+        fs::create_dir_all(existing.join(".git")).unwrap();
+        drop(CloneCleanup {
+            dest: &existing,
+            existed: true,
+            finished: false,
+        });
+        assert_eq!(fs::read_dir(&existing).unwrap().count(), 0);
+
+        let finished = temp_dir.path().join("finished");
+        fs::create_dir_all(finished.join(".git")).unwrap();
+        drop(CloneCleanup {
+            dest: &finished,
+            existed: false,
+            finished: true,
+        });
+        assert!(finished.join(".git").exists());
+    }
+}
@@ -0,0 +1,243 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::timestamps;
+
+/// Kinds of cached results returned to the frontend
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheKind {
+    /// Working copy changed files
+    Status,
+    /// Ahead/behind counts against a branch (key = branch name)
+    Divergence,
+    /// Diff hunks for a file (key = file path)
+    Hunks,
+    /// Size of every workspace directory (workspace path = repository path)
+    DiskUsage,
+}
+
+impl CacheKind {
+    /// How long a result stays fresh before the UI should flag it as stale
+    pub fn max_age_ms(self) -> u64 {
+        match self {
+            CacheKind::Status => 5_000,
+            CacheKind::Divergence => 60_000,
+            CacheKind::Hunks => 10_000,
+            CacheKind::DiskUsage => 600_000,
+        }
+    }
+}
+
+/// A cached result with the time it was computed and how long it stays fresh
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedPayload<T> {
+    pub data: T,
+    /// When the data was computed (RFC 3339)
+    pub computed_at: String,
+    pub max_age_ms: u64,
+    /// True when computed_at + max_age is already in the past
+    pub is_stale: bool,
+}
+
+/// Outcome of refreshing one stale cache entry
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheRefreshResult {
+    pub kind: CacheKind,
+    pub key: String,
+    pub refreshed: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Clone)]
+struct CacheEntry {
+    value: serde_json::Value,
+    computed_at: DateTime<Utc>,
+}
+
+type CacheKey = (CacheKind, String, String);
+
+/// Entries computed longer ago than this are dropped whenever a new one is stored. It
+/// is past every kind's max age, so only results nobody asked for again are lost.
+const EVICT_AFTER_MS: i64 = 60 * 60 * 1000;
+
+static RESULT_CACHE: OnceLock<Mutex<HashMap<CacheKey, CacheEntry>>> = OnceLock::new();
+
+fn cache() -> &'static Mutex<HashMap<CacheKey, CacheEntry>> {
+    RESULT_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Wall-clock time is used deliberately: monotonic clocks may not advance while the
+// machine sleeps, which would make hours-old data look fresh after wake.
+fn is_expired(kind: CacheKind, computed_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    let age_ms = (now - computed_at).num_milliseconds();
+    age_ms < 0 || age_ms as u64 >= kind.max_age_ms()
+}
+
+fn to_payload<T: DeserializeOwned>(
+    kind: CacheKind,
+    entry: &CacheEntry,
+) -> Result<CachedPayload<T>, String> {
+    Ok(CachedPayload {
+        data: serde_json::from_value(entry.value.clone())
+            .map_err(|e| format!("Failed to decode cached value: {}", e))?,
+        computed_at: timestamps::format(entry.computed_at),
+        max_age_ms: kind.max_age_ms(),
+        is_stale: is_expired(kind, entry.computed_at, Utc::now()),
+    })
+}
+
+/// Return the cached result for (kind, workspace, key) if still fresh, otherwise compute
+/// and store a new one. When recomputing an expired result fails, the old result is
+/// returned flagged `is_stale` rather than failing.
+pub fn get_or_compute<T, F>(
+    kind: CacheKind,
+    workspace_path: &str,
+    key: &str,
+    compute: F,
+) -> Result<CachedPayload<T>, String>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Result<T, String>,
+{
+    let cache_key = (kind, workspace_path.to_string(), key.to_string());
+
+    let existing = cache().lock().unwrap().get(&cache_key).cloned();
+    if let Some(entry) = &existing {
+        if !is_expired(kind, entry.computed_at, Utc::now()) {
+            return to_payload(kind, entry);
+        }
+    }
+
+    let data = match (compute(), existing) {
+        (Ok(data), _) => data,
+        (Err(e), Some(entry)) => {
+            log::warn!("Serving stale {:?} for {}: {}", kind, workspace_path, e);
+            return to_payload(kind, &entry);
+        }
+        (Err(e), None) => return Err(e),
+    };
+    let entry = CacheEntry {
+        value: serde_json::to_value(&data)
+            .map_err(|e| format!("Failed to encode cached value: {}", e))?,
+        computed_at: Utc::now(),
+    };
+    let mut entries = cache().lock().unwrap();
+    entries.retain(|_, e| {
+        (entry.computed_at - e.computed_at).num_milliseconds() < EVICT_AFTER_MS
+    });
+    entries.insert(cache_key, entry.clone());
+    drop(entries);
+
+    Ok(CachedPayload {
+        data,
+        computed_at: timestamps::format(entry.computed_at),
+        max_age_ms: kind.max_age_ms(),
+        is_stale: false,
+    })
+}
+
+/// Drop entries of the given kinds for `path` and for workspaces inside it, so a repo
+/// path also covers the workspaces under its `.treq` directory
+pub fn invalidate(path: &str, kinds: &[CacheKind]) {
+    let root = Path::new(path);
+    cache().lock().unwrap().retain(|(kind, workspace, _), _| {
+        !(kinds.contains(kind) && Path::new(workspace).starts_with(root))
+    });
+}
+
+/// Keys of expired entries for a workspace, limited to the given kinds
+pub fn stale_keys(workspace_path: &str, kinds: &[CacheKind]) -> Vec<(CacheKind, String)> {
+    let now = Utc::now();
+    let mut keys: Vec<(CacheKind, String)> = cache()
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|((kind, workspace, _), entry)| {
+            workspace == workspace_path
+                && kinds.contains(kind)
+                && is_expired(*kind, entry.computed_at, now)
+        })
+        .map(|((kind, _, key), _)| (*kind, key.clone()))
+        .collect();
+    keys.sort_by(|a, b| a.1.cmp(&b.1));
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_is_expired_uses_kind_max_age() {
+        let now = Utc::now();
+        assert!(!is_expired(CacheKind::Status, now - Duration::seconds(1), now));
+        assert!(is_expired(CacheKind::Status, now - Duration::seconds(6), now));
+        assert!(!is_expired(CacheKind::Divergence, now - Duration::seconds(6), now));
+        // Clock moved backwards: treat as stale rather than trusting the entry
+        assert!(is_expired(CacheKind::Hunks, now + Duration::seconds(5), now));
+    }
+
+    #[test]
+    fn test_get_or_compute_reuses_fresh_entries() {
+        let workspace = "/tmp/result-cache-test";
+        let first = get_or_compute(CacheKind::Hunks, workspace, "a.rs", || Ok(1)).unwrap();
+        let second = get_or_compute(CacheKind::Hunks, workspace, "a.rs", || Ok(2)).unwrap();
+
+        assert_eq!(first.data, 1);
+        assert_eq!(second.data, 1);
+        assert_eq!(first.computed_at, second.computed_at);
+        assert!(!second.is_stale);
+        assert!(stale_keys(workspace, &[CacheKind::Hunks]).is_empty());
+    }
+
+    #[test]
+    fn test_expired_entries_are_served_stale_when_refresh_fails() {
+        let workspace = "/tmp/result-cache-stale";
+        let key = (CacheKind::Status, workspace.to_string(), String::new());
+        let computed_at = Utc::now() - Duration::seconds(30);
+        cache().lock().unwrap().insert(
+            key,
+            CacheEntry {
+                value: serde_json::json!(1),
+                computed_at,
+            },
+        );
+
+        let stale: CachedPayload<i32> =
+            get_or_compute(CacheKind::Status, workspace, "", || Err("offline".to_string()))
+                .unwrap();
+        assert_eq!(stale.data, 1);
+        assert!(stale.is_stale);
+
+        let fresh = get_or_compute(CacheKind::Status, workspace, "", || Ok(2)).unwrap();
+        assert_eq!(fresh.data, 2);
+        assert!(!fresh.is_stale);
+    }
+
+    #[test]
+    fn test_invalidate_covers_workspaces_inside_the_repo() {
+        let repo = "/tmp/result-cache-repo";
+        let workspace = "/tmp/result-cache-repo/.treq/workspaces/a";
+        get_or_compute(CacheKind::Divergence, workspace, "main", || Ok(1)).unwrap();
+        get_or_compute(CacheKind::Hunks, workspace, "a.rs", || Ok(1)).unwrap();
+        get_or_compute(CacheKind::Divergence, "/tmp/result-cache-other", "main", || Ok(1))
+            .unwrap();
+
+        invalidate(repo, &[CacheKind::Divergence]);
+        let recomputed =
+            get_or_compute(CacheKind::Divergence, workspace, "main", || Ok(2)).unwrap();
+        assert_eq!(recomputed.data, 2);
+        let kept = get_or_compute(CacheKind::Hunks, workspace, "a.rs", || Ok(2)).unwrap();
+        assert_eq!(kept.data, 1);
+        let other =
+            get_or_compute(CacheKind::Divergence, "/tmp/result-cache-other", "main", || Ok(2))
+                .unwrap();
+        assert_eq!(other.data, 1);
+    }
+}
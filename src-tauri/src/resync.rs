@@ -0,0 +1,117 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands;
+use crate::AppState;
+
+/// How often the wake monitor checks the wall clock
+const WAKE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A wall-clock jump larger than this between checks means the machine was asleep
+const WAKE_GAP_THRESHOLD_SECS: i64 = 30;
+
+/// Focus regained after being unfocused this long triggers a resync
+const FOCUS_INACTIVITY_THRESHOLD_SECS: i64 = 5 * 60;
+
+/// Unix timestamp of the last time all windows lost focus (0 = focused)
+static LAST_BLUR_AT: AtomicI64 = AtomicI64::new(0);
+
+/// Prevents overlapping resyncs when wake and focus fire together
+static RESYNC_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Why a resync was triggered
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ResyncReason {
+    Wake,
+    Focus,
+    Manual,
+}
+
+/// Payload of the `resynced` event
+#[derive(Debug, Serialize, Clone)]
+pub struct ResyncSummary {
+    pub reason: ResyncReason,
+    /// Watched workspaces whose status was refreshed
+    pub workspaces: Vec<String>,
+    /// Watchers that could not be re-armed (path, error)
+    pub watcher_failures: Vec<(String, String)>,
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Start a background thread that detects wake-from-sleep by watching for wall-clock jumps.
+///
+/// Sleeping threads don't run while the machine is suspended, so a check that wakes up
+/// much later than scheduled means the system was asleep.
+pub fn start_wake_monitor(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_check = now_secs();
+        loop {
+            std::thread::sleep(WAKE_CHECK_INTERVAL);
+            let now = now_secs();
+            let gap = now - last_check;
+            last_check = now;
+
+            if gap > WAKE_GAP_THRESHOLD_SECS {
+                log::info!("Detected wake from sleep ({}s gap), resyncing", gap);
+                resync(&app, ResyncReason::Wake);
+            }
+        }
+    });
+}
+
+/// Record a window focus change, resyncing when focus returns after a long absence
+pub fn handle_focus_changed(app: &AppHandle, focused: bool) {
+    if !focused {
+        LAST_BLUR_AT.store(now_secs(), Ordering::SeqCst);
+        return;
+    }
+
+    let blurred_at = LAST_BLUR_AT.swap(0, Ordering::SeqCst);
+    if blurred_at > 0 && now_secs() - blurred_at >= FOCUS_INACTIVITY_THRESHOLD_SECS {
+        let app = app.clone();
+        std::thread::spawn(move || resync(&app, ResyncReason::Focus));
+    }
+}
+
+/// Re-arm file watchers, refresh working copy status of watched workspaces and emit
+/// a single `resynced` event. Returns None if a resync was already running.
+pub fn resync(app: &AppHandle, reason: ResyncReason) -> Option<ResyncSummary> {
+    if RESYNC_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+
+    let state = app.state::<AppState>();
+    let watcher_failures = state.watcher_manager.rearm_all();
+
+    // Light refresh: re-snapshot the working copy, updating the status cache if it expired
+    let workspaces: Vec<String> = state
+        .watcher_manager
+        .watched_workspaces()
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect();
+    for workspace_path in &workspaces {
+        if let Err(e) = commands::jj_get_changed_files_cached(workspace_path.clone()) {
+            log::warn!("Resync status refresh failed for {}: {}", workspace_path, e);
+        }
+    }
+
+    let summary = ResyncSummary {
+        reason,
+        workspaces,
+        watcher_failures,
+    };
+    let _ = app.emit("resynced", summary.clone());
+
+    RESYNC_IN_PROGRESS.store(false, Ordering::SeqCst);
+    Some(summary)
+}
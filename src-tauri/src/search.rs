@@ -0,0 +1,385 @@
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::file_guard;
+
+/// Matches collected before a batch is handed to the caller
+const BATCH_SIZE: usize = 100;
+
+const DEFAULT_MAX_RESULTS: usize = 2000;
+const DEFAULT_CONTEXT_LINES: usize = 2;
+const MAX_CONTEXT_LINES: usize = 10;
+
+/// Directories never searched, even with `include_hidden`
+const SKIPPED_DIRS: &[&str] = &[".git", ".jj", ".treq"];
+
+static NEXT_SEARCH_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Cancellation flags of running searches
+static RUNNING_SEARCHES: OnceLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Lines shown before and after each match; defaults to 2
+    pub context_lines: Option<usize>,
+    /// Stop after this many matches; defaults to 2000
+    pub max_results: Option<usize>,
+    /// Only search paths matching these globs
+    pub include: Vec<String>,
+    /// Skip paths matching these globs
+    pub exclude: Vec<String>,
+    /// Search dotfiles and dot-directories (except .git, .jj and .treq)
+    pub include_hidden: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SearchMatch {
+    /// Relative to the workspace root
+    pub path: String,
+    /// 1-based
+    pub line_number: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub line: String,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+/// Payload of the `search-results` event
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchBatch {
+    pub search_id: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Payload of the `search-complete` event
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchSummary {
+    pub search_id: String,
+    pub total_matches: usize,
+    pub files_searched: usize,
+    pub files_with_matches: usize,
+    /// Stopped at max_results
+    pub truncated: bool,
+    pub cancelled: bool,
+    pub error: Option<String>,
+}
+
+fn running_searches() -> &'static Mutex<HashMap<String, Arc<AtomicBool>>> {
+    RUNNING_SEARCHES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Register a new search and return its id and cancellation flag
+pub fn start_search() -> (String, Arc<AtomicBool>) {
+    let search_id = format!("search-{}", NEXT_SEARCH_ID.fetch_add(1, Ordering::SeqCst));
+    let cancelled = Arc::new(AtomicBool::new(false));
+    running_searches()
+        .lock()
+        .unwrap()
+        .insert(search_id.clone(), cancelled.clone());
+    (search_id, cancelled)
+}
+
+pub fn finish_search(search_id: &str) {
+    running_searches().lock().unwrap().remove(search_id);
+}
+
+/// Ask a running search to stop; returns false if it already finished
+pub fn cancel_search(search_id: &str) -> bool {
+    match running_searches().lock().unwrap().get(search_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+fn is_word_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Byte offsets of the matches of `query` in `line`. Case-insensitive matching folds
+/// ASCII only, so offsets stay valid for the original line.
+fn find_in_line(line: &str, query: &str, options: &SearchOptions) -> Vec<usize> {
+    let (haystack, needle) = if options.case_sensitive {
+        (line.to_string(), query.to_string())
+    } else {
+        (line.to_ascii_lowercase(), query.to_ascii_lowercase())
+    };
+
+    haystack
+        .match_indices(&needle)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            !options.whole_word
+                || (!is_word_char(line[..start].chars().next_back())
+                    && !is_word_char(line[start + needle.len()..].chars().next()))
+        })
+        .collect()
+}
+
+/// Search the lines of one file's content
+fn search_content(
+    path: &str,
+    content: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<SearchMatch> {
+    let context = options
+        .context_lines
+        .unwrap_or(DEFAULT_CONTEXT_LINES)
+        .min(MAX_CONTEXT_LINES);
+    let lines: Vec<&str> = content.lines().collect();
+    let mut matches = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        for start in find_in_line(line, query, options) {
+            matches.push(SearchMatch {
+                path: path.to_string(),
+                line_number: index + 1,
+                column: line[..start].chars().count() + 1,
+                line: line.to_string(),
+                context_before: lines[index.saturating_sub(context)..index]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+                context_after: lines
+                    [(index + 1).min(lines.len())..(index + 1 + context).min(lines.len())]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            });
+        }
+    }
+
+    matches
+}
+
+/// Search file contents under `workspace_path`, respecting .gitignore, on parallel
+/// walker threads. Matches are passed to `on_batch` in batches as they are found, so
+/// their order across files is not deterministic.
+pub fn search_in_workspace(
+    search_id: &str,
+    workspace_path: &str,
+    query: &str,
+    options: &SearchOptions,
+    cancelled: &AtomicBool,
+    on_batch: &(dyn Fn(Vec<SearchMatch>) + Sync),
+) -> Result<SearchSummary, String> {
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+    let root = Path::new(workspace_path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", workspace_path));
+    }
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.include {
+        overrides
+            .add(glob)
+            .map_err(|e| format!("Invalid include glob '{}': {}", glob, e))?;
+    }
+    for glob in &options.exclude {
+        overrides
+            .add(&format!("!{}", glob))
+            .map_err(|e| format!("Invalid exclude glob '{}': {}", glob, e))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| format!("Invalid search globs: {}", e))?;
+
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let total_matches = AtomicUsize::new(0);
+    let files_searched = AtomicUsize::new(0);
+    let files_with_matches = AtomicUsize::new(0);
+    let truncated = AtomicBool::new(false);
+    let pending: Mutex<Vec<SearchMatch>> = Mutex::new(Vec::new());
+
+    WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .overrides(overrides)
+        .filter_entry(|entry| {
+            !(entry.file_type().is_some_and(|t| t.is_dir())
+                && SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        })
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| {
+                if cancelled.load(Ordering::SeqCst) || truncated.load(Ordering::SeqCst) {
+                    return WalkState::Quit;
+                }
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    return WalkState::Continue;
+                }
+
+                // Binary and oversized files are skipped like in the file viewer
+                let content = match file_guard::read_guarded(entry.path()) {
+                    Ok(file_guard::FileContent::Text { content }) => content,
+                    _ => return WalkState::Continue,
+                };
+                files_searched.fetch_add(1, Ordering::SeqCst);
+
+                let relative = entry
+                    .path()
+                    .strip_prefix(root)
+                    .unwrap_or(entry.path())
+                    .to_string_lossy()
+                    .to_string();
+                let mut matches = search_content(&relative, &content, query, options);
+                if matches.is_empty() {
+                    return WalkState::Continue;
+                }
+                files_with_matches.fetch_add(1, Ordering::SeqCst);
+
+                // Reserve room under max_results before handing the matches out
+                let previous = total_matches.fetch_add(matches.len(), Ordering::SeqCst);
+                if previous + matches.len() > max_results {
+                    matches.truncate(max_results.saturating_sub(previous));
+                    truncated.store(true, Ordering::SeqCst);
+                }
+
+                let batch = {
+                    let mut pending = pending.lock().unwrap();
+                    pending.extend(matches);
+                    if pending.len() >= BATCH_SIZE {
+                        std::mem::take(&mut *pending)
+                    } else {
+                        Vec::new()
+                    }
+                };
+                if !batch.is_empty() {
+                    on_batch(batch);
+                }
+                WalkState::Continue
+            })
+        });
+
+    let rest = pending.into_inner().unwrap();
+    if !rest.is_empty() {
+        on_batch(rest);
+    }
+
+    Ok(SearchSummary {
+        search_id: search_id.to_string(),
+        total_matches: total_matches.load(Ordering::SeqCst).min(max_results),
+        files_searched: files_searched.load(Ordering::SeqCst),
+        files_with_matches: files_with_matches.load(Ordering::SeqCst),
+        truncated: truncated.load(Ordering::SeqCst),
+        cancelled: cancelled.load(Ordering::SeqCst),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_search_content_context_and_options() {
+        let content = "one\nlet Foo = 1;\nfoo_bar();\nfoo();\nend";
+        let options = SearchOptions {
+            context_lines: Some(1),
+            ..Default::default()
+        };
+        let matches = search_content("a.rs", content, "foo", &options);
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].line_number, 2);
+        assert_eq!(matches[0].column, 5);
+        assert_eq!(matches[0].context_before, ["one"]);
+        assert_eq!(matches[0].context_after, ["foo_bar();"]);
+        assert_eq!(matches[2].context_after, ["end"]);
+
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: true,
+            ..Default::default()
+        };
+        let matches = search_content("a.rs", content, "foo", &options);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, "foo();");
+    }
+
+    #[test]
+    fn test_search_in_workspace_respects_gitignore() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        // The ignore crate only applies .gitignore inside a repository
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn needle() {}\nneedle();\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "// needle\n").unwrap();
+        fs::write(root.join("build/out.js"), "needle").unwrap();
+        fs::write(root.join("src/blob.bin"), b"needle\0\x01").unwrap();
+
+        let batches = Mutex::new(Vec::new());
+        let summary = search_in_workspace(
+            "search-test",
+            root.to_str().unwrap(),
+            "needle",
+            &SearchOptions::default(),
+            &AtomicBool::new(false),
+            &|batch| batches.lock().unwrap().extend(batch),
+        )
+        .unwrap();
+
+        let mut found: Vec<(String, usize)> = batches
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.path, m.line_number))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                ("src/lib.rs".to_string(), 1),
+                ("src/main.rs".to_string(), 1),
+                ("src/main.rs".to_string(), 2)
+            ]
+        );
+        assert_eq!(summary.total_matches, 3);
+        assert_eq!(summary.files_with_matches, 2);
+        assert!(!summary.truncated);
+
+        let options = SearchOptions {
+            include: vec!["*.rs".to_string()],
+            exclude: vec!["lib.rs".to_string()],
+            max_results: Some(1),
+            ..Default::default()
+        };
+        let count = AtomicUsize::new(0);
+        let summary = search_in_workspace(
+            "search-test",
+            root.to_str().unwrap(),
+            "needle",
+            &options,
+            &AtomicBool::new(false),
+            &|batch| {
+                count.fetch_add(batch.len(), Ordering::SeqCst);
+            },
+        )
+        .unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(summary.truncated);
+    }
+}
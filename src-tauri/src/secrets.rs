@@ -0,0 +1,287 @@
+//! Credentials kept out of the settings database.
+//!
+//! Secrets go to the OS keychain (Keychain, Credential Manager or the Secret Service).
+//! Headless Linux machines often have no Secret Service running; there they are kept in
+//! an encrypted file in the app data directory instead, with its key in a separate file
+//! only the user can read. The key sits next to the secrets, so the encryption is only
+//! obfuscation: it keeps tokens out of plain sight, e.g. in grep output, but anyone who
+//! can read the user's files can decrypt them. File permissions are the real protection.
+//! Token settings written by older versions are moved here at startup.
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::db::Database;
+use crate::forge;
+use crate::local_api;
+
+/// Keychain service name secrets are stored under
+const SERVICE: &str = "treq";
+
+/// Settings keys holding credentials
+pub const TOKEN_KEYS: &[&str] = &[
+    forge::GITHUB_TOKEN_KEY,
+    forge::GITLAB_TOKEN_KEY,
+    forge::BITBUCKET_TOKEN_KEY,
+    local_api::LOCAL_API_TOKEN_KEY,
+];
+
+const SECRETS_FILE: &str = "secrets.enc";
+const KEY_FILE: &str = "secrets.key";
+const NONCE_LEN: usize = 12;
+
+pub fn is_token_key(key: &str) -> bool {
+    TOKEN_KEYS.contains(&key)
+}
+
+/// Encrypted file used when no keychain is available. Its key is stored beside it, so
+/// this obfuscates rather than protects; see the module docs.
+pub struct FileStore {
+    path: PathBuf,
+    key_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    pub fn new(dir: &Path) -> Self {
+        FileStore {
+            path: dir.join(SECRETS_FILE),
+            key_path: dir.join(KEY_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn cipher(&self) -> Result<ChaCha20Poly1305, String> {
+        let key = match fs::read(&self.key_path) {
+            Ok(key) if key.len() == 32 => key,
+            Ok(_) => return Err("Secrets key file is corrupt".to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut key = vec![0u8; 32];
+                getrandom::fill(&mut key)
+                    .map_err(|e| format!("Failed to generate secrets key: {}", e))?;
+                write_private(&self.key_path, &key)?;
+                key
+            }
+            Err(e) => return Err(format!("Failed to read secrets key: {}", e)),
+        };
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>, String> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(format!("Failed to read secrets: {}", e)),
+        };
+        if data.len() < NONCE_LEN {
+            return Err("Secrets file is corrupt".to_string());
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt secrets".to_string())?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Secrets file is corrupt: {}", e))
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        let plaintext = serde_json::to_vec(secrets)
+            .map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::fill(&mut nonce).map_err(|e| format!("Failed to generate nonce: {}", e))?;
+        let ciphertext = self
+            .cipher()?
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt secrets".to_string())?;
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        write_private(&self.path, &data)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<String, String>)) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.load()?;
+        f(&mut secrets);
+        self.save(&secrets)
+    }
+}
+
+/// Write a file readable only by the current user
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    std::io::Write::write_all(&mut file, contents)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub enum Store {
+    Keychain,
+    File(FileStore),
+}
+
+impl Store {
+    fn entry(key: &str) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(SERVICE, key).map_err(|e| format!("Invalid secret {}: {}", key, e))
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        match self {
+            Store::Keychain => match Self::entry(key)?.get_password() {
+                Ok(value) => Ok(Some(value)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(format!("Failed to read {} from the keychain: {}", key, e)),
+            },
+            Store::File(file) => {
+                let _guard = file.lock.lock().unwrap();
+                Ok(file.load()?.get(key).cloned())
+            }
+        }
+    }
+
+    pub fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        match self {
+            Store::Keychain => Self::entry(key)?
+                .set_password(value)
+                .map_err(|e| format!("Failed to save {} to the keychain: {}", key, e)),
+            Store::File(file) => file.update(|secrets| {
+                secrets.insert(key.to_string(), value.to_string());
+            }),
+        }
+    }
+
+    pub fn delete(&self, key: &str) -> Result<(), String> {
+        match self {
+            Store::Keychain => match Self::entry(key)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(format!("Failed to delete {} from the keychain: {}", key, e)),
+            },
+            Store::File(file) => file.update(|secrets| {
+                secrets.remove(key);
+            }),
+        }
+    }
+
+    /// Move token settings written by older versions out of the settings table
+    pub fn take_settings(&self, db: &Database) -> Result<(), String> {
+        for key in TOKEN_KEYS {
+            let Some(value) = db.get_setting(key).map_err(|e| e.to_string())? else {
+                continue;
+            };
+            if !value.trim().is_empty() {
+                self.set(key, &value)?;
+            }
+            db.delete_setting(key).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+static STORE: OnceLock<Store> = OnceLock::new();
+
+/// Whether the OS keychain can be used; a missing entry still means it answered
+fn keychain_available() -> bool {
+    let error = match Store::entry("availability-check").map(|entry| entry.get_password()) {
+        Ok(Ok(_)) | Ok(Err(keyring::Error::NoEntry)) => return true,
+        Ok(Err(e)) => e.to_string(),
+        Err(e) => e,
+    };
+    log::warn!(
+        "OS keychain unavailable, keeping secrets in a file protected only by its permissions: {}",
+        error
+    );
+    false
+}
+
+/// Pick the backend and move existing token settings into it
+pub fn init(app_dir: &Path, db: &Database) {
+    let store = if keychain_available() {
+        Store::Keychain
+    } else {
+        Store::File(FileStore::new(app_dir))
+    };
+    if let Err(e) = store.take_settings(db) {
+        log::warn!("Failed to move tokens out of settings: {}", e);
+    }
+    let _ = STORE.set(store);
+}
+
+fn store() -> Result<&'static Store, String> {
+    STORE
+        .get()
+        .ok_or_else(|| "Secret storage is not initialized".to_string())
+}
+
+pub fn get(key: &str) -> Result<Option<String>, String> {
+    store()?.get(key)
+}
+
+/// Save a secret; an empty value deletes it
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return delete(key);
+    }
+    store()?.set(key, value)
+}
+
+pub fn delete(key: &str) -> Result<(), String> {
+    store()?.delete(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_store_encrypts_and_takes_token_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().join("treq.db")).unwrap();
+        db.init().unwrap();
+        db.set_setting(forge::GITHUB_TOKEN_KEY, "ghp_plaintext")
+            .unwrap();
+        db.set_setting(forge::GITLAB_TOKEN_KEY, "").unwrap();
+        db.set_setting("theme", "dark").unwrap();
+
+        let store = Store::File(FileStore::new(temp_dir.path()));
+        store.take_settings(&db).unwrap();
+        assert_eq!(db.get_setting(forge::GITHUB_TOKEN_KEY).unwrap(), None);
+        assert_eq!(db.get_setting(forge::GITLAB_TOKEN_KEY).unwrap(), None);
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
+        assert_eq!(
+            store.get(forge::GITHUB_TOKEN_KEY).unwrap().as_deref(),
+            Some("ghp_plaintext")
+        );
+        assert_eq!(store.get(forge::GITLAB_TOKEN_KEY).unwrap(), None);
+
+        let on_disk = fs::read(temp_dir.path().join(SECRETS_FILE)).unwrap();
+        assert!(!String::from_utf8_lossy(&on_disk).contains("ghp_plaintext"));
+
+        // A fresh store reads what the first one wrote
+        let reopened = Store::File(FileStore::new(temp_dir.path()));
+        reopened.delete(forge::GITHUB_TOKEN_KEY).unwrap();
+        reopened
+            .set(forge::BITBUCKET_TOKEN_KEY, "user:pass")
+            .unwrap();
+        assert_eq!(store.get(forge::GITHUB_TOKEN_KEY).unwrap(), None);
+        assert_eq!(
+            store.get(forge::BITBUCKET_TOKEN_KEY).unwrap().as_deref(),
+            Some("user:pass")
+        );
+
+        // The wrong key fails loudly rather than reading as empty
+        fs::write(temp_dir.path().join(KEY_FILE), [7u8; 32]).unwrap();
+        assert!(store.get(forge::BITBUCKET_TOKEN_KEY).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Export and import of treq's configuration, for carrying it to another machine.
+//!
+//! Global settings, each known repo's settings and its workspace templates are written
+//! to one JSON file. Tokens, which older versions kept in settings, are never exported,
+//! and settings that only make sense on this machine are left out. Repo settings are
+//! matched to repos by path on import.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::db::Database;
+use crate::secrets;
+use crate::timestamps;
+use crate::workspace_config::{self, WorkspaceTemplate};
+
+/// Format version written to exports; newer files are refused
+pub const SETTINGS_EXPORT_VERSION: u32 = 1;
+
+/// Settings describing this machine rather than preferences
+const MACHINE_KEYS: &[&str] = &["repo_path"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RepoSettingsExport {
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+    #[serde(default)]
+    pub workspace_templates: Vec<WorkspaceTemplate>,
+}
+
+/// Contents of an exported settings file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SettingsExport {
+    pub version: u32,
+    pub exported_at: String,
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+    /// Keyed by repo path
+    #[serde(default)]
+    pub repos: BTreeMap<String, RepoSettingsExport>,
+}
+
+/// What `import` changed
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SettingsImportSummary {
+    /// Global setting keys written
+    pub settings: Vec<String>,
+    /// Repo paths whose settings or templates were written
+    pub repos: Vec<String>,
+    pub templates: usize,
+    /// Keys in the file that are never imported
+    pub skipped: Vec<String>,
+}
+
+fn is_portable(key: &str) -> bool {
+    // Credentials stay on the machine they were entered on
+    !secrets::is_token_key(key) && !MACHINE_KEYS.contains(&key)
+}
+
+/// Collect the portable configuration
+pub fn export(db: &Database) -> Result<SettingsExport, String> {
+    let settings = db
+        .list_settings()
+        .map_err(|e| format!("Failed to read settings: {}", e))?
+        .into_iter()
+        .filter(|(key, _)| is_portable(key))
+        .collect();
+
+    let mut repos = BTreeMap::new();
+    for repo_path in db
+        .list_repo_paths()
+        .map_err(|e| format!("Failed to list repositories: {}", e))?
+    {
+        let settings: BTreeMap<String, String> = db
+            .list_repo_settings(&repo_path)
+            .map_err(|e| format!("Failed to read settings of {}: {}", repo_path, e))?
+            .into_iter()
+            .filter(|(key, _)| is_portable(key) && key != workspace_config::WORKSPACE_TEMPLATES_KEY)
+            .collect();
+        let workspace_templates = workspace_config::load_templates(db, &repo_path)?;
+        if settings.is_empty() && workspace_templates.is_empty() {
+            continue;
+        }
+        repos.insert(
+            repo_path,
+            RepoSettingsExport {
+                settings,
+                workspace_templates,
+            },
+        );
+    }
+
+    Ok(SettingsExport {
+        version: SETTINGS_EXPORT_VERSION,
+        exported_at: timestamps::now(),
+        settings,
+        repos,
+    })
+}
+
+/// Write the settings in `export`. Existing settings not in the file are kept, and
+/// templates replace those with the same name.
+pub fn import(db: &Database, export: &SettingsExport) -> Result<SettingsImportSummary, String> {
+    if export.version > SETTINGS_EXPORT_VERSION {
+        return Err(format!(
+            "Settings file version {} is newer than this version of treq supports ({})",
+            export.version, SETTINGS_EXPORT_VERSION
+        ));
+    }
+
+    let mut summary = SettingsImportSummary::default();
+    for (key, value) in &export.settings {
+        if !is_portable(key) {
+            summary.skipped.push(key.clone());
+            continue;
+        }
+        db.set_setting(key, value)
+            .map_err(|e| format!("Failed to import setting {}: {}", key, e))?;
+        summary.settings.push(key.clone());
+    }
+
+    for (repo_path, repo) in &export.repos {
+        for (key, value) in &repo.settings {
+            if !is_portable(key) || key == workspace_config::WORKSPACE_TEMPLATES_KEY {
+                summary.skipped.push(format!("{}:{}", repo_path, key));
+                continue;
+            }
+            db.set_repo_setting(repo_path, key, value)
+                .map_err(|e| format!("Failed to import setting {} of {}: {}", key, repo_path, e))?;
+        }
+        for template in &repo.workspace_templates {
+            workspace_config::save_template(db, repo_path, template.clone())?;
+            summary.templates += 1;
+        }
+        summary.repos.push(repo_path.clone());
+    }
+
+    Ok(summary)
+}
+
+pub fn write_export(dest_path: &str, export: &SettingsExport) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(export)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(dest_path, json).map_err(|e| format!("Failed to write {}: {}", dest_path, e))
+}
+
+pub fn read_export(src_path: &str) -> Result<SettingsExport, String> {
+    let json =
+        fs::read_to_string(src_path).map_err(|e| format!("Failed to read {}: {}", src_path, e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid settings file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge;
+    use tempfile::TempDir;
+
+    fn open_db(dir: &TempDir, name: &str) -> Database {
+        let db = Database::new(dir.path().join(name)).unwrap();
+        db.init().unwrap();
+        db
+    }
+
+    #[test]
+    fn test_settings_roundtrip_without_secrets() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = open_db(&temp_dir, "source.db");
+        source.set_setting("theme", "dark").unwrap();
+        source.set_setting("repo_path", "/src/app").unwrap();
+        source
+            .set_setting(forge::GITHUB_TOKEN_KEY, "ghp_secret")
+            .unwrap();
+        source
+            .set_repo_setting("/src/app", "default_branch", "develop")
+            .unwrap();
+        workspace_config::save_template(
+            &source,
+            "/src/app",
+            WorkspaceTemplate {
+                name: "frontend".to_string(),
+                post_create_commands: vec!["npm ci".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let exported = export(&source).unwrap();
+        assert_eq!(
+            exported.settings,
+            BTreeMap::from([("theme".to_string(), "dark".to_string())])
+        );
+        let repo = &exported.repos["/src/app"];
+        assert_eq!(
+            repo.settings,
+            BTreeMap::from([("default_branch".to_string(), "develop".to_string())])
+        );
+        assert_eq!(repo.workspace_templates[0].name, "frontend");
+
+        let path = temp_dir.path().join("treq-settings.json");
+        write_export(path.to_str().unwrap(), &exported).unwrap();
+        let mut read_back = read_export(path.to_str().unwrap()).unwrap();
+        assert_eq!(read_back, exported);
+
+        // A hand-edited file cannot smuggle in a token
+        read_back
+            .settings
+            .insert(forge::GITHUB_TOKEN_KEY.to_string(), "ghp_other".to_string());
+        let target = open_db(&temp_dir, "target.db");
+        let summary = import(&target, &read_back).unwrap();
+        assert_eq!(summary.skipped, vec![forge::GITHUB_TOKEN_KEY.to_string()]);
+        assert_eq!(summary.repos, vec!["/src/app".to_string()]);
+        assert_eq!(summary.templates, 1);
+        assert_eq!(
+            target.get_setting("theme").unwrap().as_deref(),
+            Some("dark")
+        );
+        assert_eq!(target.get_setting(forge::GITHUB_TOKEN_KEY).unwrap(), None);
+        assert_eq!(
+            target
+                .get_repo_setting("/src/app", "default_branch")
+                .unwrap()
+                .as_deref(),
+            Some("develop")
+        );
+        assert_eq!(
+            workspace_config::load_templates(&target, "/src/app").unwrap(),
+            repo.workspace_templates
+        );
+
+        read_back.version = SETTINGS_EXPORT_VERSION + 1;
+        assert!(import(&target, &read_back).is_err());
+    }
+}
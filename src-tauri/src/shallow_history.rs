@@ -0,0 +1,96 @@
+//! Divergence counts on shallow clones.
+//!
+//! When the merge base of two branches lies beyond a shallow clone's boundary,
+//! `git rev-list --left-right --count` and `target..@` ranges walk every fetched
+//! commit and silently over-count. Before such calculations the history is deepened
+//! until the merge base is present, unless the repo turned that off, and results say
+//! whether they can be trusted.
+
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+use crate::git_ops;
+use crate::jj;
+use crate::vcs;
+use crate::AppState;
+
+/// Repo settings key that, when "false", stops divergence calculations from fetching
+/// more history
+pub const AUTO_DEEPEN_KEY: &str = "auto_deepen_history";
+
+/// Commits fetched by the first deepening; each further round doubles it
+const DEEPEN_STEP: usize = 100;
+
+/// Deepening rounds before giving up on finding the merge base
+const MAX_DEEPEN_ROUNDS: u32 = 5;
+
+/// Handle used to read settings, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+fn auto_deepen_enabled(repo_path: &str) -> bool {
+    let Some(app) = APP_HANDLE.get() else {
+        return true;
+    };
+    let state = app.state::<AppState>();
+    let value = state
+        .db
+        .lock()
+        .unwrap()
+        .get_repo_setting(repo_path, AUTO_DEEPEN_KEY);
+    !matches!(value, Ok(Some(value)) if value.trim() == "false")
+}
+
+/// Make sure the history between `base` and `head` is present in the repository at
+/// `path` (a repo or one of its workspaces), deepening a shallow clone when allowed.
+/// Returns whether divergence between them can be counted accurately.
+pub async fn ensure_merge_base(path: &str, base: &str, head: &str) -> bool {
+    let repo_path = jj::derive_repo_path_from_workspace(path).unwrap_or_else(|| path.to_string());
+    match git_ops::git_repo_capabilities(&repo_path) {
+        Ok(capabilities) if capabilities.shallow => {}
+        // Complete history, or not a git repository at all
+        _ => return true,
+    }
+    if git_ops::git_has_merge_base(path, base, head) {
+        return true;
+    }
+    if !auto_deepen_enabled(&repo_path) {
+        return false;
+    }
+
+    let remote = jj::default_remote(&repo_path);
+    for round in 0..MAX_DEEPEN_ROUNDS {
+        let commits = DEEPEN_STEP << round;
+        if let Err(e) = git_ops::git_deepen(&repo_path, &remote, commits).await {
+            log::warn!("Failed to deepen {}: {}", repo_path, e);
+            return false;
+        }
+        if git_ops::git_has_merge_base(path, base, head) {
+            return true;
+        }
+        // With the full history present the counts are right even without a merge base
+        if !git_ops::git_repo_capabilities(&repo_path).is_ok_and(|c| c.shallow) {
+            return true;
+        }
+    }
+    false
+}
+
+/// `ensure_merge_base` between `base` and the commit the workspace's changes sit on.
+/// A jj workspace's git `HEAD` is the main repo's, so the VCS backend resolves it.
+pub async fn ensure_workspace_merge_base(workspace_path: &str, base: &str) -> bool {
+    let head = vcs::backend_for_workspace(workspace_path)
+        .head_commit(workspace_path)
+        .unwrap_or_else(|_| "HEAD".to_string());
+    ensure_merge_base(workspace_path, base, &head).await
+}
+
+/// `ensure_merge_base` between a branch and its counterpart on the default remote,
+/// before counting how far the branch is ahead of and behind it
+pub async fn ensure_remote_merge_base(workspace_path: &str, branch: &str) -> bool {
+    let remote_branch = format!("{}/{}", jj::default_remote(workspace_path), branch);
+    ensure_merge_base(workspace_path, &remote_branch, branch).await
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::commands::pty_commands;
+use crate::file_indexer;
+use crate::instance_lock;
+use crate::timestamps;
+use crate::AppState;
+
+/// File in the app data dir written as the last step of a clean exit
+const CLEAN_SHUTDOWN_MARKER: &str = "clean_shutdown";
+
+/// How long exit waits for in-progress index writes
+const PENDING_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+static PREVIOUS_SHUTDOWN_CLEAN: AtomicBool = AtomicBool::new(false);
+
+fn marker_path(app_dir: &Path) -> PathBuf {
+    app_dir.join(CLEAN_SHUTDOWN_MARKER)
+}
+
+/// Remove the marker, returning whether it existed. Removing it up front means a crash
+/// during this run is detected at the next startup.
+fn consume_marker(app_dir: &Path) -> bool {
+    let path = marker_path(app_dir);
+    let clean = path.exists();
+    if clean {
+        if let Err(e) = fs::remove_file(&path) {
+            log::warn!("Failed to remove clean shutdown marker: {}", e);
+        }
+    }
+    clean
+}
+
+fn write_marker(app_dir: &Path) -> Result<(), String> {
+    fs::write(marker_path(app_dir), timestamps::now())
+        .map_err(|e| format!("Failed to write clean shutdown marker: {}", e))
+}
+
+/// Record whether the previous run exited cleanly (call once at startup)
+pub fn check_previous_shutdown(app_dir: &Path) {
+    let clean = consume_marker(app_dir);
+    if !clean {
+        log::info!("Previous run did not exit cleanly; running crash-recovery scans");
+    }
+    PREVIOUS_SHUTDOWN_CLEAN.store(clean, Ordering::SeqCst);
+}
+
+/// Whether the previous run exited cleanly, so crash-recovery scans can be skipped
+pub fn previous_shutdown_was_clean() -> bool {
+    PREVIOUS_SHUTDOWN_CLEAN.load(Ordering::SeqCst)
+}
+
+/// Stop background work and persist state before the process exits
+pub fn run_exit_sequence(app: &AppHandle) {
+    let state = app.state::<AppState>();
+
+    // No more change events or index refreshes once teardown starts
+    state.watcher_manager.stop_all();
+
+    if !file_indexer::wait_for_pending_writes(PENDING_WRITE_TIMEOUT) {
+        log::warn!("Exiting with file index writes still in progress");
+    }
+
+    {
+        let pty_manager = state.pty_manager.lock().unwrap();
+        for session_id in pty_manager.session_ids() {
+            if let Err(e) = pty_commands::persist_scrollback(&pty_manager, &session_id) {
+                log::warn!("Failed to save terminal scrollback: {}", e);
+            }
+        }
+        pty_manager.terminate_all();
+    }
+
+    // Let other instances open our repos right away instead of waiting for staleness
+    instance_lock::release_all();
+
+    match app.path().app_data_dir() {
+        Ok(app_dir) => {
+            if let Err(e) = write_marker(&app_dir) {
+                log::warn!("{}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to get app data dir: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_clean_shutdown_marker_is_consumed() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(!consume_marker(temp_dir.path()));
+
+        write_marker(temp_dir.path()).unwrap();
+        assert!(consume_marker(temp_dir.path()));
+        // A crash after startup leaves no marker behind
+        assert!(!consume_marker(temp_dir.path()));
+    }
+}
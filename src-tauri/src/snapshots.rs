@@ -0,0 +1,259 @@
+use chrono::Utc;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::binary_paths;
+use crate::jj;
+use crate::local_db::{self, Snapshot};
+use crate::process_limiter::LimitedCommand;
+use crate::timestamps;
+
+/// Settings key: snapshots kept per repository; 0 stops taking them
+pub const SNAPSHOT_RETENTION_KEY: &str = "snapshot_retention";
+
+pub const DEFAULT_SNAPSHOT_RETENTION: usize = 50;
+
+static RETENTION: AtomicUsize = AtomicUsize::new(DEFAULT_SNAPSHOT_RETENTION);
+
+/// Helper function to create Command for a binary using cached path
+fn command_for(binary: &str) -> LimitedCommand {
+    let path = binary_paths::get_binary_path(binary).unwrap_or_else(|| binary.to_string());
+    LimitedCommand::new(path)
+}
+
+pub fn set_retention(keep: Option<usize>) {
+    RETENTION.store(
+        keep.unwrap_or(DEFAULT_SNAPSHOT_RETENTION),
+        Ordering::Relaxed,
+    );
+}
+
+/// Parse the SNAPSHOT_RETENTION_KEY setting; empty or invalid values mean "use the default"
+pub fn parse_retention_setting(value: Option<&str>) -> Option<usize> {
+    value.and_then(|v| v.trim().parse::<usize>().ok())
+}
+
+fn snapshots_dir(repo_path: &str) -> PathBuf {
+    Path::new(repo_path).join(".treq").join("snapshots")
+}
+
+fn run(command: &mut LimitedCommand, binary: &str) -> Result<Vec<u8>, String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to execute {}: {}", binary, e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(output.stdout)
+}
+
+/// Working-copy commit and its first parent. Reading the log snapshots the working copy,
+/// so edits jj has not recorded yet are included.
+fn working_copy_commits(workspace_path: &str) -> Result<(String, String), String> {
+    let template = "commit_id ++ \" \" ++ parents.map(|c| c.commit_id()).join(\" \")";
+    let output = jj::jj_command(workspace_path)
+        .args(["log", "-r", "@", "--no-graph", "-T", template])
+        .output()
+        .map_err(|e| format!("Failed to execute jj: {}", e))?;
+    if !output.status.success() {
+        return Err(jj::command_error(&output.stderr).to_string());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut ids = stdout.split_whitespace();
+    match (ids.next(), ids.next()) {
+        (Some(commit), Some(parent)) => Ok((commit.to_string(), parent.to_string())),
+        _ => Err(format!("Unexpected jj log output: {}", stdout.trim())),
+    }
+}
+
+pub(crate) fn count_patch_files(patch: &[u8]) -> i64 {
+    patch
+        .split(|b| *b == b'\n')
+        .filter(|line| line.starts_with(b"diff --git "))
+        .count() as i64
+}
+
+/// Binary git patch of the jj working copy's changes to `paths` (all changes when
+/// empty), with the first parent it applies to
+pub(crate) fn working_copy_patch(
+    repo_path: &str,
+    workspace_path: &str,
+    paths: &[String],
+) -> Result<(Vec<u8>, String), String> {
+    let (commit, parent) = working_copy_commits(workspace_path)?;
+
+    // jj writes every commit to the colocated git store, including other workspaces'
+    let mut args = vec![
+        "diff",
+        "--binary",
+        "--no-color",
+        "--no-ext-diff",
+        parent.as_str(),
+        commit.as_str(),
+        "--",
+    ];
+    args.extend(paths.iter().map(String::as_str));
+    let patch = run(command_for("git").current_dir(repo_path).args(&args), "git")?;
+    Ok((patch, parent))
+}
+
+/// Apply a patch file to a workspace's files without touching any index
+pub(crate) fn apply_patch(
+    repo_path: &str,
+    workspace_path: &str,
+    patch_path: &Path,
+) -> Result<(), String> {
+    // Workspaces live inside the main repo, so point git at the workspace explicitly
+    // rather than letting it discover the enclosing repository
+    let git_dir = Path::new(repo_path).join(".git");
+    run(
+        command_for("git")
+            .current_dir(workspace_path)
+            .arg(format!("--git-dir={}", git_dir.display()))
+            .arg(format!("--work-tree={}", workspace_path))
+            .args(["apply", "--binary", "--whitespace=nowarn"])
+            .arg(patch_path),
+        "git",
+    )
+    .map(|_| ())
+}
+
+/// Delete all but the `keep` most recent snapshots
+fn prune(repo_path: &str, keep: usize) -> Result<(), String> {
+    for snapshot in local_db::get_snapshots(repo_path)?.into_iter().skip(keep) {
+        delete(repo_path, snapshot.id)?;
+    }
+    Ok(())
+}
+
+/// Save the workspace's uncommitted changes to `paths` (all changes when empty) as a
+/// binary git patch before `operation` discards them. Returns None when there is
+/// nothing to save or snapshots are turned off.
+///
+/// Patches are taken against the working copy's first parent, so restoring a merge
+/// working copy may not apply cleanly.
+pub fn take(
+    workspace_path: &str,
+    operation: &str,
+    paths: &[String],
+) -> Result<Option<Snapshot>, String> {
+    let keep = RETENTION.load(Ordering::Relaxed);
+    if keep == 0 {
+        return Ok(None);
+    }
+
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    let (patch, parent) = working_copy_patch(&repo_path, workspace_path, paths)?;
+    if patch.is_empty() {
+        return Ok(None);
+    }
+
+    let dir = snapshots_dir(&repo_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create snapshots directory: {}", e))?;
+    let workspace_name = Path::new(workspace_path)
+        .file_name()
+        .map(|n| jj::sanitize_workspace_name(&n.to_string_lossy()))
+        .unwrap_or_default();
+    let patch_file = format!(
+        "{}-{}.patch",
+        Utc::now().format("%Y%m%dT%H%M%S%3f"),
+        workspace_name
+    );
+    fs::write(dir.join(&patch_file), &patch)
+        .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+
+    let mut snapshot = Snapshot {
+        id: 0,
+        workspace_id: local_db::get_workspace_by_path(&repo_path, workspace_path)
+            .ok()
+            .flatten()
+            .map(|w| w.id),
+        workspace_path: workspace_path.to_string(),
+        operation: operation.to_string(),
+        base_commit: parent,
+        patch_file,
+        file_count: count_patch_files(&patch),
+        size_bytes: patch.len() as i64,
+        created_at: timestamps::now(),
+    };
+    snapshot.id = local_db::add_snapshot(&repo_path, &snapshot)?;
+
+    if let Err(e) = prune(&repo_path, keep) {
+        eprintln!("Failed to prune snapshots: {}", e);
+    }
+    Ok(Some(snapshot))
+}
+
+/// Take a snapshot before a destructive command, turning failures into an error that
+/// stops the command
+pub fn take_before(workspace_path: &str, operation: &str, paths: &[String]) -> Result<(), String> {
+    take(workspace_path, operation, paths)
+        .map(|_| ())
+        .map_err(|e| {
+            format!(
+                "Failed to snapshot changes before {}, nothing was discarded: {}",
+                operation, e
+            )
+        })
+}
+
+pub fn list(repo_path: &str) -> Result<Vec<Snapshot>, String> {
+    local_db::get_snapshots(repo_path)
+}
+
+fn find(repo_path: &str, id: i64) -> Result<Snapshot, String> {
+    local_db::get_snapshot(repo_path, id)?.ok_or_else(|| format!("Snapshot {} not found", id))
+}
+
+/// Re-apply a snapshot's patch to the workspace it was taken from. Fails without
+/// changing anything if the files it touches were edited since.
+pub fn restore(repo_path: &str, id: i64) -> Result<Snapshot, String> {
+    let snapshot = find(repo_path, id)?;
+    if !Path::new(&snapshot.workspace_path).is_dir() {
+        return Err(format!(
+            "Workspace {} no longer exists",
+            snapshot.workspace_path
+        ));
+    }
+    let patch_path = snapshots_dir(repo_path).join(&snapshot.patch_file);
+    if !patch_path.is_file() {
+        return Err(format!("Snapshot file {} is missing", snapshot.patch_file));
+    }
+
+    apply_patch(repo_path, &snapshot.workspace_path, &patch_path)
+        .map_err(|e| format!("Failed to restore snapshot: {}", e))?;
+    Ok(snapshot)
+}
+
+pub fn delete(repo_path: &str, id: i64) -> Result<(), String> {
+    let snapshot = find(repo_path, id)?;
+    match fs::remove_file(snapshots_dir(repo_path).join(&snapshot.patch_file)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to delete snapshot file: {}", e)),
+    }
+    local_db::delete_snapshot(repo_path, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention_setting() {
+        assert_eq!(parse_retention_setting(Some("10")), Some(10));
+        assert_eq!(parse_retention_setting(Some(" 0 ")), Some(0));
+        assert_eq!(parse_retention_setting(Some("-1")), None);
+        assert_eq!(parse_retention_setting(Some("")), None);
+        assert_eq!(parse_retention_setting(None), None);
+    }
+
+    #[test]
+    fn test_count_patch_files() {
+        let patch = b"diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-x\n+diff --git y\ndiff --git a/logo.png b/logo.png\nGIT binary patch\n";
+        assert_eq!(count_patch_files(patch), 2);
+    }
+}
@@ -0,0 +1,138 @@
+//! Checks for moving a workspace to a different target branch: the branch must exist,
+//! and the UI is shown how far the workspace and the new target have diverged before
+//! the change is saved.
+
+use serde::Serialize;
+
+use crate::git_ops::{self, BranchCommitInfo, GitLogOptions};
+use crate::jj::{self, BranchStatus};
+use crate::local_db;
+use crate::shallow_history;
+
+/// Workspace commits listed in a preview; `ahead` has the full count
+const PREVIEW_COMMITS: usize = 20;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TargetBranchPreview {
+    pub target_branch: String,
+    pub branch_status: BranchStatus,
+    /// Workspace commits not on the target, newest first
+    pub commits_ahead: Vec<BranchCommitInfo>,
+    pub ahead: usize,
+    /// Target commits the workspace does not have yet
+    pub behind: usize,
+    /// False when a shallow clone lacks the merge base, so the counts are too high
+    pub accurate: bool,
+}
+
+/// Where `target` exists, accepting local branches, branches on a remote, and remote
+/// branches written as "<remote>/<branch>"
+pub fn validate_target_branch(repo_path: &str, target: &str) -> Result<BranchStatus, String> {
+    let target = target.trim();
+    if target.is_empty() {
+        return Err("Target branch is empty".to_string());
+    }
+
+    let status = jj::check_branch_exists(repo_path, target, None).map_err(|e| e.to_string())?;
+    if status.local_exists || status.remote_exists {
+        return Ok(status);
+    }
+    if let Some((remote, branch)) = target.split_once('/') {
+        if jj::get_git_remotes(repo_path).contains(remote) {
+            let status = jj::check_branch_exists(repo_path, branch, Some(remote))
+                .map_err(|e| e.to_string())?;
+            if status.remote_exists {
+                return Ok(status);
+            }
+        }
+    }
+    Err(format!(
+        "Branch '{}' does not exist locally or on a remote",
+        target
+    ))
+}
+
+/// Commits ahead of and behind `target` for `branch`, with the newest commits ahead
+fn divergence(
+    repo_path: &str,
+    branch: &str,
+    target: &str,
+) -> Result<(Vec<BranchCommitInfo>, usize, usize), String> {
+    let (ahead, behind) =
+        git_ops::git_divergence(repo_path, target, branch).map_err(|e| e.to_string())?;
+    let options = GitLogOptions {
+        revision: Some(format!("{}..{}", target, branch)),
+        limit: Some(PREVIEW_COMMITS),
+        ..Default::default()
+    };
+    let commits = git_ops::git_log(repo_path, &options)
+        .map_err(|e| e.to_string())?
+        .commits;
+    Ok((commits, ahead, behind))
+}
+
+/// Validate `target` for a workspace and compute how its branch diverges from it,
+/// without saving anything
+pub async fn preview_target_branch(
+    repo_path: &str,
+    workspace_id: i64,
+    target: &str,
+) -> Result<TargetBranchPreview, String> {
+    let workspace = local_db::get_workspace_by_id(repo_path, workspace_id)?
+        .ok_or_else(|| format!("Workspace {} not found", workspace_id))?;
+    let target = target.trim();
+    if target == workspace.branch_name {
+        return Err("A workspace cannot target its own branch".to_string());
+    }
+
+    let branch_status = validate_target_branch(repo_path, target)?;
+    let accurate =
+        shallow_history::ensure_merge_base(repo_path, target, &workspace.branch_name).await;
+    let (commits_ahead, ahead, behind) = divergence(repo_path, &workspace.branch_name, target)?;
+    Ok(TargetBranchPreview {
+        target_branch: target.to_string(),
+        branch_status,
+        commits_ahead,
+        ahead,
+        behind,
+        accurate,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::run_git;
+    use crate::git_ops::tests::{commit_file, setup_git_repo};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_target_branch_and_divergence() {
+        let upstream_dir = TempDir::new().unwrap();
+        let upstream = &setup_git_repo(&upstream_dir);
+        commit_file(upstream, "base.txt", "base", "Base");
+        run_git(upstream, &["branch", "release"]).unwrap();
+
+        let clone_dir = TempDir::new().unwrap();
+        let repo = clone_dir.path().to_str().unwrap();
+        run_git(upstream, &["clone", "-q", upstream, repo]).unwrap();
+        run_git(repo, &["config", "user.email", "test@example.com"]).unwrap();
+        run_git(repo, &["config", "user.name", "Test"]).unwrap();
+        run_git(repo, &["checkout", "-q", "-b", "feature"]).unwrap();
+        commit_file(repo, "one.txt", "one", "One");
+        commit_file(repo, "two.txt", "two", "Two");
+        run_git(repo, &["checkout", "-q", "main"]).unwrap();
+        commit_file(repo, "main.txt", "main", "Main");
+
+        assert!(validate_target_branch(repo, "main").unwrap().local_exists);
+        let release = validate_target_branch(repo, "origin/release").unwrap();
+        assert!(release.remote_exists && !release.local_exists);
+        assert!(validate_target_branch(repo, "missing").is_err());
+        assert!(validate_target_branch(repo, " ").is_err());
+
+        let (commits, ahead, behind) = divergence(repo, "feature", "main").unwrap();
+        assert_eq!((ahead, behind), (2, 1));
+        let messages: Vec<&str> = commits.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages, vec!["Two", "One"]);
+    }
+}
@@ -0,0 +1,500 @@
+//! Named build/test/lint tasks configured per repository and run in a workspace.
+//!
+//! Tasks run as captured shell processes in the background. Each output line is emitted
+//! as a `task-output` event, and every run is recorded in the repo's operation journal
+//! (kind "task") before `task-finished` reports its exit status. A run is registered as
+//! an operation under its run id, so `cancel_operation` kills it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio_util::sync::CancellationToken;
+
+use crate::async_process;
+use crate::automation;
+use crate::db::Database;
+use crate::local_db::{self, JournalEntry};
+use crate::operations::{self, OperationKind};
+use crate::timestamps;
+
+/// Repo settings key holding the tasks, as a JSON object of name -> task
+pub const TASKS_KEY: &str = "tasks";
+
+/// Journal kind of task runs
+pub const TASK_JOURNAL_KIND: &str = "task";
+
+/// Output kept in the journal per run; the end is kept, as that is where failures are
+const MAX_JOURNAL_OUTPUT: usize = 64 * 1024;
+
+/// How often a running task checks whether it was cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Handle used to emit task events, set once during app setup
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// (workspace path, task name) of the tasks currently running
+static RUNNING: OnceLock<Mutex<HashSet<(String, String)>>> = OnceLock::new();
+
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TaskDefinition {
+    /// Shell command, e.g. "cargo test"
+    pub command: String,
+    /// Directory relative to the workspace root; the root when unset
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Tasks by name
+pub type TaskConfig = BTreeMap<String, TaskDefinition>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Payload of the `task-output` event, sent per output line
+#[derive(Debug, Serialize, Clone)]
+pub struct TaskOutput {
+    pub run_id: String,
+    pub workspace_path: String,
+    pub task_name: String,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+/// Payload of the `task-finished` event
+#[derive(Debug, Serialize, Clone)]
+pub struct TaskFinished {
+    pub run_id: String,
+    pub repo_path: String,
+    pub workspace_path: String,
+    pub task_name: String,
+    /// None when the process could not start or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub cancelled: bool,
+    pub entry: JournalEntry,
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+fn running() -> &'static Mutex<HashSet<(String, String)>> {
+    RUNNING.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+pub fn parse_tasks(value: Option<&str>) -> Result<TaskConfig, String> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(TaskConfig::new()),
+        Some(value) => {
+            serde_json::from_str(value).map_err(|e| format!("Invalid tasks setting: {}", e))
+        }
+    }
+}
+
+pub fn load_tasks(db: &Database, repo_path: &str) -> Result<TaskConfig, String> {
+    let value = db
+        .get_repo_setting(repo_path, TASKS_KEY)
+        .map_err(|e| e.to_string())?;
+    parse_tasks(value.as_deref())
+}
+
+/// Whether `cwd` stays inside the workspace
+fn is_relative_dir(cwd: &str) -> bool {
+    Path::new(cwd)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Save the tasks with names and commands trimmed. Tasks need a name and a command, and
+/// their `cwd` must be inside the workspace.
+pub fn save_tasks(db: &Database, repo_path: &str, tasks: &TaskConfig) -> Result<(), String> {
+    let mut cleaned = TaskConfig::new();
+    for (name, task) in tasks {
+        let name = name.trim();
+        let command = task.command.trim();
+        if name.is_empty() || command.is_empty() {
+            return Err("Tasks need a name and a command".to_string());
+        }
+        let cwd = task
+            .cwd
+            .as_deref()
+            .map(str::trim)
+            .filter(|cwd| !cwd.is_empty());
+        if let Some(cwd) = cwd {
+            if !is_relative_dir(cwd) {
+                return Err(format!(
+                    "Task '{}' must run inside the workspace, not in '{}'",
+                    name, cwd
+                ));
+            }
+        }
+        cleaned.insert(
+            name.to_string(),
+            TaskDefinition {
+                command: command.to_string(),
+                cwd: cwd.map(str::to_string),
+                env: task.env.clone(),
+            },
+        );
+    }
+    let value =
+        serde_json::to_string(&cleaned).map_err(|e| format!("Failed to serialize tasks: {}", e))?;
+    db.set_repo_setting(repo_path, TASKS_KEY, &value)
+        .map_err(|e| e.to_string())
+}
+
+/// Keep the last `MAX_JOURNAL_OUTPUT` bytes of a run's output
+fn keep_tail(output: &mut String) {
+    if output.len() <= MAX_JOURNAL_OUTPUT {
+        return;
+    }
+    let mut start = output.len() - MAX_JOURNAL_OUTPUT;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    output.replace_range(..start, "[output truncated]\n");
+}
+
+fn read_lines<R: Read>(
+    reader: R,
+    stream: OutputStream,
+    output: &Mutex<String>,
+    on_line: &(dyn Fn(OutputStream, &str) + Sync),
+) {
+    for line in BufReader::new(reader).split(b'\n').map_while(Result::ok) {
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches('\r');
+        on_line(stream, line);
+        let mut output = output.lock().unwrap();
+        output.push_str(line);
+        output.push('\n');
+        keep_tail(&mut output);
+    }
+}
+
+/// Kill `child` along with anything its shell started, since those hold the output pipes
+fn kill_tree(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let _ = std::process::Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", child.id())])
+            .status();
+    }
+    let _ = child.kill();
+}
+
+/// Wait for `child`, killing it once `cancel` fires
+fn wait_or_kill(child: &mut Child, cancel: &CancellationToken) -> std::io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if cancel.is_cancelled() {
+            kill_tree(child);
+            return child.wait();
+        }
+        std::thread::sleep(CANCEL_POLL_INTERVAL);
+    }
+}
+
+/// Run `task` in `dir`, passing each output line to `on_line`. Returns the exit code and
+/// the interleaved output. Inside an operation, cancelling it kills the task.
+fn execute(
+    task: &TaskDefinition,
+    dir: &Path,
+    env: &[(String, String)],
+    on_line: &(dyn Fn(OutputStream, &str) + Sync),
+) -> (Option<i32>, String) {
+    let cancel = operations::current_token().unwrap_or_default();
+    let mut command = automation::shell_command(&task.command);
+    // Own process group, so cancelling can kill the whole tree
+    #[cfg(unix)]
+    command.process_group(0);
+    let child = command
+        .current_dir(dir)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .envs(&task.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    // The process limiter slot is held until the task exits
+    let (mut child, _permit) = match child {
+        Ok(child) => child,
+        Err(e) => return (None, format!("Failed to run task: {}", e)),
+    };
+
+    let output = Mutex::new(String::new());
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let status = std::thread::scope(|scope| {
+        scope.spawn(|| read_lines(stderr, OutputStream::Stderr, &output, on_line));
+        scope.spawn(|| read_lines(stdout, OutputStream::Stdout, &output, on_line));
+        wait_or_kill(&mut child, &cancel)
+    });
+
+    let mut output = output.into_inner().unwrap();
+    if cancel.is_cancelled() {
+        output.push_str("Task cancelled\n");
+    }
+    let exit_code = match status {
+        Ok(status) => status.code(),
+        Err(e) => {
+            output.push_str(&format!("Failed to wait for task: {}", e));
+            None
+        }
+    };
+    (exit_code, output)
+}
+
+/// Directory a task runs in, refusing ones outside the workspace
+fn task_dir(workspace_path: &str, task: &TaskDefinition) -> Result<PathBuf, String> {
+    let dir = match task.cwd.as_deref() {
+        Some(cwd) if !is_relative_dir(cwd) => {
+            return Err(format!("Task directory '{}' is outside the workspace", cwd))
+        }
+        Some(cwd) => Path::new(workspace_path).join(cwd),
+        None => PathBuf::from(workspace_path),
+    };
+    if !dir.is_dir() {
+        return Err(format!("Task directory not found: {}", dir.display()));
+    }
+    Ok(dir)
+}
+
+/// Start task `name` in a workspace in the background and return the id of the run,
+/// which its `task-output` and `task-finished` events carry and `cancel_operation`
+/// accepts. A task can only run once per workspace at a time.
+pub fn run_task(
+    repo_path: &str,
+    workspace_path: &str,
+    workspace_id: Option<i64>,
+    name: &str,
+    task: TaskDefinition,
+) -> Result<String, String> {
+    let dir = task_dir(workspace_path, &task)?;
+    let key = (workspace_path.to_string(), name.to_string());
+    if !running().lock().unwrap().insert(key.clone()) {
+        return Err(format!(
+            "Task '{}' is already running in this workspace",
+            name
+        ));
+    }
+
+    let run_id = format!("task-{}", NEXT_RUN_ID.fetch_add(1, Ordering::SeqCst));
+    let repo_path = repo_path.to_string();
+    let workspace_path = workspace_path.to_string();
+    let name = name.to_string();
+    let id = run_id.clone();
+    std::thread::spawn(move || {
+        let env = vec![
+            ("TREQ_TASK".to_string(), name.clone()),
+            ("TREQ_REPO_PATH".to_string(), repo_path.clone()),
+            ("TREQ_WORKSPACE_PATH".to_string(), workspace_path.clone()),
+        ];
+        let emit_line = |stream: OutputStream, line: &str| {
+            if let Some(app) = APP_HANDLE.get() {
+                let _ = app.emit(
+                    "task-output",
+                    TaskOutput {
+                        run_id: id.clone(),
+                        workspace_path: workspace_path.clone(),
+                        task_name: name.clone(),
+                        stream,
+                        line: line.to_string(),
+                    },
+                );
+            }
+        };
+
+        let started = Instant::now();
+        let run = async {
+            let result = execute(&task, &dir, &env, &emit_line);
+            let cancelled = operations::current_token().is_some_and(|t| t.is_cancelled());
+            Ok::<_, String>((result, cancelled))
+        };
+        let ((exit_code, output), cancelled) = async_process::block_on(operations::run_operation(
+            OperationKind::Task,
+            &workspace_path,
+            Some(id.clone()),
+            run,
+        ))
+        .unwrap_or_else(|e| ((None, e), false));
+        running().lock().unwrap().remove(&key);
+
+        let mut entry = JournalEntry {
+            id: 0,
+            kind: TASK_JOURNAL_KIND.to_string(),
+            name: name.clone(),
+            workspace_id,
+            command: Some(task.command),
+            success: exit_code == Some(0),
+            output,
+            duration_ms: Some(started.elapsed().as_millis() as i64),
+            created_at: timestamps::now(),
+        };
+        match local_db::add_journal_entry(&repo_path, &entry) {
+            Ok(id) => entry.id = id,
+            Err(e) => log::warn!("Failed to journal task '{}': {}", name, e),
+        }
+        if let Some(app) = APP_HANDLE.get() {
+            let _ = app.emit(
+                "task-finished",
+                TaskFinished {
+                    run_id: id,
+                    repo_path,
+                    workspace_path,
+                    task_name: name,
+                    exit_code,
+                    cancelled,
+                    entry,
+                },
+            );
+        }
+    });
+
+    Ok(run_id)
+}
+
+/// Run `task` in a workspace and wait for it, returning the exit code and output
+/// (stdout, then stderr). Unlike `run_task` nothing is streamed or journaled; used where
+/// the caller needs the outcome, e.g. pre-merge checks. Inside an operation, cancelling
+/// it kills the task and returns an error.
+pub async fn run_task_and_wait(
+    workspace_path: &str,
+    name: &str,
+    task: &TaskDefinition,
+) -> Result<(Option<i32>, String), String> {
+    let dir = task_dir(workspace_path, task)?;
+    let mut command = automation::async_shell_command(&task.command);
+    command
+        .current_dir(&dir)
+        .env("TREQ_TASK", name)
+        .env("TREQ_WORKSPACE_PATH", workspace_path);
+    for (key, value) in &task.env {
+        command.env(key, value);
+    }
+    let output = command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run task '{}': {}", name, e))?;
+    Ok((
+        output.status.code(),
+        format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_tasks_and_task_dir() {
+        let tasks = parse_tasks(Some(
+            r#"{"test":{"command":"cargo test","cwd":"src-tauri"},"lint":{"command":"npm run lint"}}"#,
+        ))
+        .unwrap();
+        assert_eq!(tasks["test"].cwd.as_deref(), Some("src-tauri"));
+        assert!(tasks["lint"].env.is_empty());
+        assert!(parse_tasks(None).unwrap().is_empty());
+        assert!(parse_tasks(Some(r#"{"test":{}}"#)).is_err());
+
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src-tauri")).unwrap();
+        let workspace = dir.path().to_str().unwrap();
+        assert_eq!(
+            task_dir(workspace, &tasks["test"]).unwrap(),
+            dir.path().join("src-tauri")
+        );
+        let escaping = TaskDefinition {
+            cwd: Some("../other".to_string()),
+            ..tasks["lint"].clone()
+        };
+        assert!(task_dir(workspace, &escaping).is_err());
+    }
+
+    #[test]
+    fn test_keep_tail() {
+        let mut output = "a".repeat(MAX_JOURNAL_OUTPUT) + "end";
+        keep_tail(&mut output);
+        assert!(output.starts_with("[output truncated]\n"));
+        assert!(output.ends_with("aend"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_streams_lines_and_reports_exit_code() {
+        let dir = TempDir::new().unwrap();
+        let task = TaskDefinition {
+            command: "echo \"$TREQ_TASK $GREETING\"; echo warn >&2; exit 2".to_string(),
+            cwd: None,
+            env: BTreeMap::from([("GREETING".to_string(), "hi".to_string())]),
+        };
+        let lines = Mutex::new(Vec::new());
+        let (exit_code, output) = execute(
+            &task,
+            dir.path(),
+            &[("TREQ_TASK".to_string(), "build".to_string())],
+            &|stream, line| lines.lock().unwrap().push((stream, line.to_string())),
+        );
+
+        assert_eq!(exit_code, Some(2));
+        let mut lines = lines.into_inner().unwrap();
+        lines.sort_by_key(|(stream, _)| *stream == OutputStream::Stderr);
+        assert_eq!(
+            lines,
+            [
+                (OutputStream::Stdout, "build hi".to_string()),
+                (OutputStream::Stderr, "warn".to_string()),
+            ]
+        );
+        assert!(output.contains("build hi\n") && output.contains("warn\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancelled_task_is_killed() {
+        let dir = TempDir::new().unwrap();
+        let task = TaskDefinition {
+            command: "sleep 10".to_string(),
+            cwd: None,
+            env: BTreeMap::new(),
+        };
+        let started = Instant::now();
+        let (exit_code, output) = async_process::block_on(operations::run_operation(
+            OperationKind::Task,
+            "/repo",
+            Some("test-task-cancel".to_string()),
+            async {
+                std::thread::spawn(|| {
+                    std::thread::sleep(Duration::from_millis(200));
+                    assert!(operations::cancel_operation("test-task-cancel"));
+                });
+                Ok::<_, String>(execute(&task, dir.path(), &[], &|_, _| {}))
+            },
+        ))
+        .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(exit_code, None);
+        assert!(output.ends_with("Task cancelled\n"));
+    }
+}
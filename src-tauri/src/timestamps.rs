@@ -0,0 +1,69 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+
+/// jj's default timestamp rendering, e.g. `2024-05-01 14:00:00.000 +02:00`
+const JJ_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f %:z";
+
+/// SQLite `CURRENT_TIMESTAMP` style, always UTC
+const SQLITE_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Format as RFC3339 UTC with millisecond precision, e.g. `2024-05-01T12:00:00.000Z`
+pub fn format(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Current time in the normalized format used for every stored and serialized timestamp
+pub fn now() -> String {
+    format(Utc::now())
+}
+
+/// Parse RFC3339 (any offset), jj's default rendering or a SQLite UTC timestamp
+pub fn parse(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = DateTime::parse_from_str(value, JJ_TIMESTAMP_FORMAT) {
+        return Some(time.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(value, SQLITE_TIMESTAMP_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// Rewrite a timestamp as RFC3339 UTC; unrecognized values are returned unchanged
+pub fn normalize(value: &str) -> String {
+    parse(value)
+        .map(format)
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Milliseconds since the epoch, for ordering; unparseable values sort first
+pub fn sort_key(value: &str) -> i64 {
+    parse(value).map(|t| t.timestamp_millis()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_formats() {
+        let expected = "2024-05-01T12:00:00.000Z";
+        assert_eq!(normalize("2024-05-01T14:00:00+02:00"), expected);
+        assert_eq!(normalize("2024-05-01 14:00:00.000 +02:00"), expected);
+        assert_eq!(normalize("2024-05-01 12:00:00"), expected);
+        assert_eq!(normalize("2024-05-01T12:00:00.000123456+00:00"), expected);
+        assert_eq!(normalize(expected), expected);
+        assert_eq!(normalize("yesterday"), "yesterday");
+    }
+
+    #[test]
+    fn test_sort_key_orders_across_offsets() {
+        // Later in wall-clock terms but earlier in UTC
+        let a = sort_key("2024-05-01 13:00:00.000 +05:00");
+        let b = sort_key("2024-05-01T10:00:00Z");
+        assert!(a < b);
+        assert_eq!(b, 1_714_557_600_000);
+        assert_eq!(sort_key("not a date"), 0);
+    }
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering as CmpOrdering;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::file_indexer;
+use crate::forge;
+use crate::process_limiter;
+use crate::AppState;
+
+/// Settings key selecting the update channel: "stable" (default) or "beta"
+pub const UPDATE_CHANNEL_KEY: &str = "update_channel";
+
+/// Release feed, newest first
+const RELEASES_PATH: &str = "repos/Ziinc/treq/releases?per_page=30";
+
+/// A terminal that printed output this recently is treated as busy (agent run, build)
+const TERMINAL_QUIET_PERIOD: Duration = Duration::from_secs(10);
+
+/// How often a deferred restart re-checks for running work
+const RESTART_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Release channel to look for updates on
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    Stable,
+    /// Stable releases plus prereleases
+    Beta,
+}
+
+impl UpdateChannel {
+    /// Parse the UPDATE_CHANNEL_KEY setting; anything unrecognized means stable
+    pub fn from_setting(value: Option<&str>) -> UpdateChannel {
+        match value.map(str::trim) {
+            Some("beta") => UpdateChannel::Beta,
+            _ => UpdateChannel::Stable,
+        }
+    }
+}
+
+/// A release as returned by the forge API
+#[derive(Debug, Deserialize)]
+struct FeedRelease {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    html_url: String,
+    published_at: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReleaseInfo {
+    /// Version without a leading "v"
+    pub version: String,
+    pub name: Option<String>,
+    /// Release notes (markdown)
+    pub notes: String,
+    pub url: String,
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateCheckResult {
+    pub channel: UpdateChannel,
+    pub current_version: String,
+    /// Newest release on the channel, if newer than the running version
+    pub update: Option<ReleaseInfo>,
+}
+
+/// Work that would be interrupted by restarting now
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RestartBlockers {
+    /// Terminals with recent output, e.g. a running agent
+    pub busy_terminals: usize,
+    /// git/jj processes running or queued
+    pub running_processes: usize,
+    pub pending_index_writes: usize,
+    /// Multi-step operations such as rebases
+    pub active_operations: usize,
+}
+
+impl RestartBlockers {
+    pub fn is_clear(&self) -> bool {
+        *self == RestartBlockers::default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestartStatus {
+    /// A restart is waiting for the blockers to clear
+    pub pending: bool,
+    pub blockers: RestartBlockers,
+}
+
+static ACTIVE_OPERATIONS: AtomicUsize = AtomicUsize::new(0);
+static RESTART_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Marks a multi-step operation (e.g. a rebase across workspaces) as running so a
+/// deferred restart waits for it, even between the git/jj processes it spawns
+pub struct OperationGuard;
+
+impl OperationGuard {
+    pub fn start() -> Self {
+        ACTIVE_OPERATIONS.fetch_add(1, Ordering::SeqCst);
+        OperationGuard
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        ACTIVE_OPERATIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Parse "v1.2.3" or "1.2.3-beta.1" into numeric parts and an optional prerelease tag
+fn parse_version(version: &str) -> Option<(Vec<u64>, Option<&str>)> {
+    let version = version.trim().trim_start_matches('v');
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+    let parts = core
+        .split('.')
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    Some((parts, pre))
+}
+
+/// Compare two versions; a prerelease sorts before the release it precedes
+fn compare_versions(a: &str, b: &str) -> Option<CmpOrdering> {
+    let (a_parts, a_pre) = parse_version(a)?;
+    let (b_parts, b_pre) = parse_version(b)?;
+    let len = a_parts.len().max(b_parts.len());
+    for i in 0..len {
+        let ord = a_parts
+            .get(i)
+            .unwrap_or(&0)
+            .cmp(b_parts.get(i).unwrap_or(&0));
+        if ord != CmpOrdering::Equal {
+            return Some(ord);
+        }
+    }
+    Some(match (a_pre, b_pre) {
+        (None, None) => CmpOrdering::Equal,
+        (None, Some(_)) => CmpOrdering::Greater,
+        (Some(_), None) => CmpOrdering::Less,
+        (Some(a), Some(b)) => a.cmp(b),
+    })
+}
+
+/// Newest release on the channel that is newer than `current_version`
+fn select_update(
+    releases: Vec<FeedRelease>,
+    channel: UpdateChannel,
+    current_version: &str,
+) -> Option<ReleaseInfo> {
+    releases
+        .into_iter()
+        .filter(|r| !r.draft && (channel == UpdateChannel::Beta || !r.prerelease))
+        .filter(|r| compare_versions(&r.tag_name, current_version) == Some(CmpOrdering::Greater))
+        .max_by(|a, b| compare_versions(&a.tag_name, &b.tag_name).unwrap_or(CmpOrdering::Equal))
+        .map(|r| ReleaseInfo {
+            version: r.tag_name.trim_start_matches('v').to_string(),
+            name: r.name,
+            notes: r.body.unwrap_or_default(),
+            url: r.html_url,
+            published_at: r.published_at,
+            prerelease: r.prerelease,
+        })
+}
+
+/// Query the release feed for a newer version on the channel
+pub fn check_for_updates(channel: UpdateChannel) -> Result<UpdateCheckResult, String> {
+    let response = forge::forge_get(forge::ForgeKind::Github, RELEASES_PATH)?;
+    if !(200..300).contains(&response.status) {
+        return Err(format!(
+            "Failed to fetch releases: HTTP {}",
+            response.status
+        ));
+    }
+    let releases: Vec<FeedRelease> = serde_json::from_str(&response.body)
+        .map_err(|e| format!("Failed to parse releases: {}", e))?;
+
+    let current_version = env!("CARGO_PKG_VERSION");
+    Ok(UpdateCheckResult {
+        channel,
+        current_version: current_version.to_string(),
+        update: select_update(releases, channel, current_version),
+    })
+}
+
+/// Running work that a restart would interrupt
+pub fn restart_blockers(app: &AppHandle) -> RestartBlockers {
+    let state = app.state::<AppState>();
+    let busy_terminals = state
+        .pty_manager
+        .lock()
+        .unwrap()
+        .active_session_count(TERMINAL_QUIET_PERIOD);
+    let metrics = process_limiter::get_metrics();
+
+    RestartBlockers {
+        busy_terminals,
+        running_processes: metrics.active + metrics.waiting,
+        pending_index_writes: file_indexer::pending_write_count(),
+        active_operations: ACTIVE_OPERATIONS.load(Ordering::SeqCst),
+    }
+}
+
+/// Restart once nothing is running. The restart is requested rather than forced, so
+/// the exit sequence still stops watchers and saves terminal scrollback as on a
+/// normal quit.
+pub fn schedule_restart(app: &AppHandle) -> RestartStatus {
+    let blockers = restart_blockers(app);
+    if blockers.is_clear() {
+        app.request_restart();
+        return RestartStatus {
+            pending: true,
+            blockers,
+        };
+    }
+
+    // Only one waiter thread at a time
+    if !RESTART_PENDING.swap(true, Ordering::SeqCst) {
+        let app = app.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(RESTART_POLL_INTERVAL);
+            if !RESTART_PENDING.load(Ordering::SeqCst) {
+                break;
+            }
+            let blockers = restart_blockers(&app);
+            if blockers.is_clear() {
+                let _ = app.emit("update-restarting", ());
+                app.request_restart();
+                break;
+            }
+            let _ = app.emit("update-restart-deferred", blockers);
+        });
+    }
+
+    RestartStatus {
+        pending: true,
+        blockers,
+    }
+}
+
+/// Drop a deferred restart
+pub fn cancel_restart() {
+    RESTART_PENDING.store(false, Ordering::SeqCst);
+}
+
+pub fn restart_status(app: &AppHandle) -> RestartStatus {
+    RestartStatus {
+        pending: RESTART_PENDING.load(Ordering::SeqCst),
+        blockers: restart_blockers(app),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool, draft: bool) -> FeedRelease {
+        FeedRelease {
+            tag_name: tag.to_string(),
+            name: None,
+            body: Some(format!("Notes for {}", tag)),
+            html_url: format!("https://github.com/Ziinc/treq/releases/tag/{}", tag),
+            published_at: None,
+            draft,
+            prerelease,
+        }
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(
+            compare_versions("v0.2.0", "0.1.9"),
+            Some(CmpOrdering::Greater)
+        );
+        assert_eq!(
+            compare_versions("0.1.10", "0.1.9"),
+            Some(CmpOrdering::Greater)
+        );
+        assert_eq!(compare_versions("v1.0", "1.0.0"), Some(CmpOrdering::Equal));
+        assert_eq!(
+            compare_versions("1.0.0-beta.1", "1.0.0"),
+            Some(CmpOrdering::Less)
+        );
+        assert_eq!(compare_versions("nightly", "1.0.0"), None);
+    }
+
+    #[test]
+    fn test_select_update_by_channel() {
+        let releases = || {
+            vec![
+                release("v0.3.0-beta.1", true, false),
+                release("v0.2.0", false, false),
+                release("v0.4.0", false, true),
+                release("v0.1.0", false, false),
+            ]
+        };
+
+        let stable = select_update(releases(), UpdateChannel::Stable, "0.1.0").unwrap();
+        assert_eq!(stable.version, "0.2.0");
+        assert_eq!(stable.notes, "Notes for v0.2.0");
+
+        let beta = select_update(releases(), UpdateChannel::Beta, "0.1.0").unwrap();
+        assert_eq!(beta.version, "0.3.0-beta.1");
+        assert!(beta.prerelease);
+
+        assert!(select_update(releases(), UpdateChannel::Stable, "0.2.0").is_none());
+    }
+}
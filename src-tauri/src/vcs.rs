@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::async_process;
+use crate::commit_signing::SigningConfig;
+use crate::db::Database;
+use crate::git_ops;
+use crate::jj::{self, FileHunks, JjFileChange, JjMergeResult, JjRebaseResult};
+
+/// Repo settings key selecting the backend: "jj", "git", or unset/"auto"
+pub const VCS_BACKEND_KEY: &str = "vcs_backend";
+
+/// Version control backend used for a repository's workspaces
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VcsKind {
+    /// jj colocated with git; workspaces are jj workspaces
+    JjColocated,
+    /// Plain git; workspaces are git worktrees
+    PlainGit,
+}
+
+impl VcsKind {
+    /// Parse the VCS_BACKEND_KEY setting; None means auto-detect
+    pub fn from_setting(value: &str) -> Option<VcsKind> {
+        match value.trim() {
+            "jj" => Some(VcsKind::JjColocated),
+            "git" => Some(VcsKind::PlainGit),
+            _ => None,
+        }
+    }
+}
+
+/// Workspace and commit operations shared by the command layer
+pub trait VcsBackend: Send + Sync {
+    fn kind(&self) -> VcsKind;
+
+    /// Create a workspace under `.treq/workspaces`, returning its sanitized name
+    fn create_workspace(
+        &self,
+        repo_path: &str,
+        workspace_name: &str,
+        branch_name: &str,
+        new_branch: bool,
+        source_branch: Option<&str>,
+        inclusion_patterns: Option<Vec<String>>,
+    ) -> Result<String, String>;
+
+    fn remove_workspace(&self, repo_path: &str, workspace_path: &str) -> Result<(), String>;
+
+    fn changed_files(&self, workspace_path: &str) -> Result<Vec<JjFileChange>, String>;
+
+    fn file_hunks(&self, workspace_path: &str, file_path: &str) -> Result<FileHunks, String>;
+
+    /// Lines added and removed by the uncommitted changes, as (insertions, deletions)
+    fn diff_stat(&self, workspace_path: &str) -> Result<(usize, usize), String>;
+
+    /// Commit the workspace's uncommitted changes sit on
+    fn head_commit(&self, workspace_path: &str) -> Result<String, String>;
+
+    /// Commit all working copy changes to the workspace's branch, signed when `signing`
+    /// is set
+    fn commit(
+        &self,
+        workspace_path: &str,
+        message: &str,
+        signing: Option<&SigningConfig>,
+    ) -> Result<String, String>;
+
+    /// Create a merge commit of `workspace_branch` into `target_branch`
+    fn merge(
+        &self,
+        workspace_path: &str,
+        workspace_branch: &str,
+        target_branch: &str,
+        message: &str,
+    ) -> Result<JjMergeResult, String>;
+
+    /// Rebase the workspace branch's commits onto `target_branch`
+    fn rebase_onto(
+        &self,
+        workspace_path: &str,
+        branch_name: &str,
+        target_branch: &str,
+    ) -> Result<JjRebaseResult, String>;
+
+    /// Fetch from remotes; all workspaces of a repo share the fetched refs
+    fn fetch(&self, repo_path: &str) -> Result<String, String>;
+
+    /// Commits the branch is (ahead, behind) its remote counterpart
+    fn sync_status(
+        &self,
+        workspace_path: &str,
+        branch_name: &str,
+    ) -> Result<(usize, usize), String>;
+}
+
+pub struct JjColocated;
+
+impl VcsBackend for JjColocated {
+    fn kind(&self) -> VcsKind {
+        VcsKind::JjColocated
+    }
+
+    fn create_workspace(
+        &self,
+        repo_path: &str,
+        workspace_name: &str,
+        branch_name: &str,
+        new_branch: bool,
+        source_branch: Option<&str>,
+        inclusion_patterns: Option<Vec<String>>,
+    ) -> Result<String, String> {
+        jj::create_workspace(
+            repo_path,
+            workspace_name,
+            branch_name,
+            new_branch,
+            source_branch,
+            inclusion_patterns,
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    fn remove_workspace(&self, repo_path: &str, workspace_path: &str) -> Result<(), String> {
+        jj::remove_workspace(repo_path, workspace_path).map_err(|e| e.to_string())
+    }
+
+    fn changed_files(&self, workspace_path: &str) -> Result<Vec<JjFileChange>, String> {
+        jj::jj_get_changed_files(workspace_path).map_err(|e| e.to_string())
+    }
+
+    fn file_hunks(&self, workspace_path: &str, file_path: &str) -> Result<FileHunks, String> {
+        jj::jj_get_file_hunks(workspace_path, file_path).map_err(|e| e.to_string())
+    }
+
+    fn diff_stat(&self, workspace_path: &str) -> Result<(usize, usize), String> {
+        jj::jj_diff_stat(workspace_path).map_err(|e| e.to_string())
+    }
+
+    fn head_commit(&self, workspace_path: &str) -> Result<String, String> {
+        // Secondary jj workspaces share the main repo's git HEAD, so ask jj for
+        // the parent of this workspace's working-copy commit
+        jj::jj_get_commit_id(workspace_path, "@-").map_err(|e| e.to_string())
+    }
+
+    fn commit(
+        &self,
+        workspace_path: &str,
+        message: &str,
+        signing: Option<&SigningConfig>,
+    ) -> Result<String, String> {
+        jj::jj_commit(workspace_path, message, signing).map_err(|e| e.to_string())
+    }
+
+    fn merge(
+        &self,
+        workspace_path: &str,
+        workspace_branch: &str,
+        target_branch: &str,
+        message: &str,
+    ) -> Result<JjMergeResult, String> {
+        async_process::block_on(jj::jj_create_merge_commit(
+            workspace_path,
+            workspace_branch,
+            target_branch,
+            message,
+        ))
+        .map_err(|e| e.to_string())
+    }
+
+    fn rebase_onto(
+        &self,
+        workspace_path: &str,
+        branch_name: &str,
+        target_branch: &str,
+    ) -> Result<JjRebaseResult, String> {
+        let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+            .unwrap_or_else(|| workspace_path.to_string());
+        let jj_target = jj::convert_git_branch_to_jj_format_public(target_branch, &repo_path);
+        // Same revset as auto-rebase: only the branch's committed changes move
+        let revset = format!("roots({}..{})", jj_target, branch_name);
+        jj::jj_rebase_with_revset(workspace_path, &revset, &jj_target, branch_name)
+            .map_err(|e| e.to_string())
+    }
+
+    fn fetch(&self, repo_path: &str) -> Result<String, String> {
+        async_process::block_on(jj::jj_git_fetch(repo_path, None)).map_err(|e| e.to_string())
+    }
+
+    fn sync_status(
+        &self,
+        workspace_path: &str,
+        branch_name: &str,
+    ) -> Result<(usize, usize), String> {
+        jj::jj_get_sync_status(workspace_path, branch_name).map_err(|e| e.to_string())
+    }
+}
+
+pub struct PlainGit;
+
+impl VcsBackend for PlainGit {
+    fn kind(&self) -> VcsKind {
+        VcsKind::PlainGit
+    }
+
+    fn create_workspace(
+        &self,
+        repo_path: &str,
+        workspace_name: &str,
+        branch_name: &str,
+        new_branch: bool,
+        source_branch: Option<&str>,
+        _inclusion_patterns: Option<Vec<String>>,
+    ) -> Result<String, String> {
+        // Keep worktrees under .treq out of the main repo's status
+        jj::ensure_gitignore_entries(repo_path).map_err(|e| e.to_string())?;
+
+        let sanitized_name = jj::sanitize_workspace_name(workspace_name);
+        let workspace_path = Path::new(repo_path)
+            .join(".treq")
+            .join("workspaces")
+            .join(&sanitized_name)
+            .to_string_lossy()
+            .to_string();
+
+        git_ops::git_worktree_add(
+            repo_path,
+            &workspace_path,
+            branch_name,
+            new_branch,
+            source_branch,
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(sanitized_name)
+    }
+
+    fn remove_workspace(&self, repo_path: &str, workspace_path: &str) -> Result<(), String> {
+        git_ops::git_worktree_remove(repo_path, workspace_path).map_err(|e| e.to_string())
+    }
+
+    fn changed_files(&self, workspace_path: &str) -> Result<Vec<JjFileChange>, String> {
+        git_ops::git_get_changed_files(workspace_path).map_err(|e| e.to_string())
+    }
+
+    fn file_hunks(&self, workspace_path: &str, file_path: &str) -> Result<FileHunks, String> {
+        git_ops::git_get_file_hunks(workspace_path, file_path).map_err(|e| e.to_string())
+    }
+
+    fn diff_stat(&self, workspace_path: &str) -> Result<(usize, usize), String> {
+        let stats =
+            git_ops::git_get_changed_files_with_stats(workspace_path).map_err(|e| e.to_string())?;
+        Ok((
+            stats.total_insertions as usize,
+            stats.total_deletions as usize,
+        ))
+    }
+
+    fn head_commit(&self, workspace_path: &str) -> Result<String, String> {
+        git_ops::git_head_commit(workspace_path).map_err(|e| e.to_string())
+    }
+
+    fn commit(
+        &self,
+        workspace_path: &str,
+        message: &str,
+        signing: Option<&SigningConfig>,
+    ) -> Result<String, String> {
+        git_ops::git_commit_all(workspace_path, message, signing).map_err(|e| e.to_string())
+    }
+
+    fn merge(
+        &self,
+        workspace_path: &str,
+        workspace_branch: &str,
+        target_branch: &str,
+        message: &str,
+    ) -> Result<JjMergeResult, String> {
+        async_process::block_on(git_ops::git_merge_branches(
+            workspace_path,
+            workspace_branch,
+            target_branch,
+            message,
+        ))
+        .map_err(|e| e.to_string())
+    }
+
+    fn rebase_onto(
+        &self,
+        workspace_path: &str,
+        _branch_name: &str,
+        target_branch: &str,
+    ) -> Result<JjRebaseResult, String> {
+        // Worktrees have their branch checked out, so a plain rebase moves it
+        async_process::block_on(git_ops::git_rebase_onto(workspace_path, target_branch))
+            .map(|r| JjRebaseResult {
+                success: r.success,
+                message: r.message,
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    fn fetch(&self, repo_path: &str) -> Result<String, String> {
+        async_process::block_on(git_ops::git_fetch_all(repo_path)).map_err(|e| e.to_string())
+    }
+
+    fn sync_status(
+        &self,
+        workspace_path: &str,
+        branch_name: &str,
+    ) -> Result<(usize, usize), String> {
+        git_ops::git_ahead_behind(workspace_path, branch_name).map_err(|e| e.to_string())
+    }
+}
+
+static JJ_COLOCATED: JjColocated = JjColocated;
+static PLAIN_GIT: PlainGit = PlainGit;
+
+/// Backend choices from repo settings, keyed by repo path.
+/// Commands that only receive a path can't reach the database, so the setting is
+/// recorded here whenever it is loaded or changed.
+static PREFERENCES: OnceLock<Mutex<HashMap<String, VcsKind>>> = OnceLock::new();
+
+fn preferences() -> &'static Mutex<HashMap<String, VcsKind>> {
+    PREFERENCES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Record the backend setting value for a repo (None/"auto" clears it)
+pub fn set_preference(repo_path: &str, value: Option<&str>) {
+    let mut prefs = preferences().lock().unwrap();
+    match value.and_then(VcsKind::from_setting) {
+        Some(kind) => prefs.insert(repo_path.to_string(), kind),
+        None => prefs.remove(repo_path),
+    };
+}
+
+/// Load the repo's backend setting from the database, returning the explicit choice
+pub fn load_preference(db: &Database, repo_path: &str) -> Option<VcsKind> {
+    let value = db
+        .get_repo_setting(repo_path, VCS_BACKEND_KEY)
+        .ok()
+        .flatten();
+    set_preference(repo_path, value.as_deref());
+    value.as_deref().and_then(VcsKind::from_setting)
+}
+
+/// Load the backend settings of every repo with settings
+pub fn load_preferences(db: &Database) {
+    for repo_path in db.list_repo_paths().unwrap_or_default() {
+        load_preference(db, &repo_path);
+    }
+}
+
+/// Backend kind for a repo: the explicit setting, otherwise jj when the repo has
+/// been jj-initialized and plain git when it hasn't (e.g. jj init failed)
+pub fn backend_kind(repo_path: &str) -> VcsKind {
+    if let Some(kind) = preferences().lock().unwrap().get(repo_path) {
+        return *kind;
+    }
+    if jj::is_jj_workspace(repo_path) {
+        VcsKind::JjColocated
+    } else {
+        VcsKind::PlainGit
+    }
+}
+
+pub fn backend_for_repo(repo_path: &str) -> &'static dyn VcsBackend {
+    match backend_kind(repo_path) {
+        VcsKind::JjColocated => &JJ_COLOCATED,
+        VcsKind::PlainGit => &PLAIN_GIT,
+    }
+}
+
+/// Backend for a workspace path or the repo root itself
+pub fn backend_for_workspace(workspace_path: &str) -> &'static dyn VcsBackend {
+    let repo_path = jj::derive_repo_path_from_workspace(workspace_path)
+        .unwrap_or_else(|| workspace_path.to_string());
+    backend_for_repo(&repo_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backend_selection() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_path = temp_dir.path().to_str().unwrap();
+        let workspace_path = temp_dir.path().join(".treq/workspaces/feature");
+        let workspace_path = workspace_path.to_str().unwrap();
+
+        assert_eq!(backend_kind(repo_path), VcsKind::PlainGit);
+
+        std::fs::create_dir_all(temp_dir.path().join(".jj")).unwrap();
+        assert_eq!(
+            backend_for_workspace(workspace_path).kind(),
+            VcsKind::JjColocated
+        );
+
+        set_preference(repo_path, Some("git"));
+        assert_eq!(
+            backend_for_workspace(workspace_path).kind(),
+            VcsKind::PlainGit
+        );
+
+        set_preference(repo_path, Some("auto"));
+        assert_eq!(backend_kind(repo_path), VcsKind::JjColocated);
+    }
+}
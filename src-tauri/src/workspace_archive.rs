@@ -0,0 +1,224 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::git_ops;
+use crate::jj;
+use crate::local_db::{self, ArchivedWorkspace, Workspace};
+use crate::snapshots;
+use crate::timestamps;
+use crate::vcs::{self, VcsKind};
+use crate::workspace_disk_usage;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnarchiveResult {
+    pub workspace_id: i64,
+    pub workspace_path: String,
+    pub patch_applied: bool,
+    /// Path of the kept patch when it could not be applied, so it can be applied by hand
+    pub unapplied_patch: Option<String>,
+}
+
+fn archive_dir(repo_path: &str) -> PathBuf {
+    Path::new(repo_path).join(".treq").join("archive")
+}
+
+fn find_workspace(repo_path: &str, workspace_id: i64) -> Result<Workspace, String> {
+    local_db::get_workspace_by_id(repo_path, workspace_id)?
+        .ok_or_else(|| format!("Workspace {} not found", workspace_id))
+}
+
+fn find_archive(repo_path: &str, id: i64) -> Result<ArchivedWorkspace, String> {
+    local_db::get_workspace_archive(repo_path, id)?
+        .ok_or_else(|| format!("Archived workspace {} not found", id))
+}
+
+/// Uncommitted changes of a workspace and the commit they apply to
+fn uncommitted_patch(repo_path: &str, workspace_path: &str) -> Result<(Vec<u8>, String), String> {
+    match vcs::backend_kind(repo_path) {
+        VcsKind::JjColocated => snapshots::working_copy_patch(repo_path, workspace_path, &[]),
+        VcsKind::PlainGit => {
+            git_ops::git_working_tree_patch(workspace_path, &[]).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Park a workspace: save its uncommitted changes under `.treq/archive`, forget the
+/// jj workspace (or remove the git worktree) and delete its directory. The branch and
+/// its commits are kept, and the workspace row is replaced by an archive record.
+pub fn archive_workspace(repo_path: &str, workspace_id: i64) -> Result<ArchivedWorkspace, String> {
+    let workspace = find_workspace(repo_path, workspace_id)?;
+    let (patch, base_commit) = uncommitted_patch(repo_path, &workspace.workspace_path)
+        .map_err(|e| format!("Failed to save uncommitted changes, nothing was archived: {}", e))?;
+
+    let patch_file = if patch.is_empty() {
+        None
+    } else {
+        let dir = archive_dir(repo_path);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create archive directory: {}", e))?;
+        let patch_file = format!(
+            "{}-{}.patch",
+            Utc::now().format("%Y%m%dT%H%M%S%3f"),
+            jj::sanitize_workspace_name(&workspace.workspace_name)
+        );
+        fs::write(dir.join(&patch_file), &patch)
+            .map_err(|e| format!("Failed to write archive patch: {}", e))?;
+        Some(patch_file)
+    };
+
+    let mut archive = ArchivedWorkspace {
+        id: 0,
+        workspace_name: workspace.workspace_name.clone(),
+        branch_name: workspace.branch_name.clone(),
+        target_branch: workspace.target_branch.clone(),
+        metadata: workspace.metadata.clone(),
+        base_commit,
+        file_count: snapshots::count_patch_files(&patch),
+        patch_file,
+        archived_at: timestamps::now(),
+    };
+    // Record the archive only once the workspace is gone, so a failed removal leaves
+    // no entry for a workspace that still exists. The patch is kept either way, as a
+    // partial removal may already have deleted the changes it holds.
+    vcs::backend_for_repo(repo_path).remove_workspace(repo_path, &workspace.workspace_path)?;
+    workspace_disk_usage::invalidate_disk_usage(repo_path);
+    archive.id = local_db::add_workspace_archive(repo_path, &archive)?;
+    local_db::delete_workspace(repo_path, workspace.id)?;
+    Ok(archive)
+}
+
+pub fn list_archived_workspaces(repo_path: &str) -> Result<Vec<ArchivedWorkspace>, String> {
+    local_db::get_workspace_archives(repo_path)
+}
+
+/// Recreate an archived workspace on its branch and re-apply its saved changes.
+/// When the patch no longer applies the workspace is still created and the patch file
+/// is kept for applying by hand.
+pub fn unarchive_workspace(
+    repo_path: &str,
+    archive_id: i64,
+    inclusion_patterns: Option<Vec<String>>,
+) -> Result<UnarchiveResult, String> {
+    let archive = find_archive(repo_path, archive_id)?;
+
+    let workspace_name = vcs::backend_for_repo(repo_path).create_workspace(
+        repo_path,
+        &archive.workspace_name,
+        &archive.branch_name,
+        false,
+        None,
+        inclusion_patterns,
+    )?;
+    let workspace_path = Path::new(repo_path)
+        .join(".treq")
+        .join("workspaces")
+        .join(&workspace_name)
+        .to_string_lossy()
+        .to_string();
+    let workspace_id = local_db::add_workspace(
+        repo_path,
+        workspace_name,
+        workspace_path.clone(),
+        archive.branch_name.clone(),
+        archive.metadata.clone(),
+    )?;
+    if let Some(target_branch) = &archive.target_branch {
+        local_db::update_workspace_target_branch(repo_path, workspace_id, target_branch)?;
+    }
+
+    let mut result = UnarchiveResult {
+        workspace_id,
+        workspace_path,
+        patch_applied: true,
+        unapplied_patch: None,
+    };
+    if let Some(patch_file) = &archive.patch_file {
+        let patch_path = archive_dir(repo_path).join(patch_file);
+        match snapshots::apply_patch(repo_path, &result.workspace_path, &patch_path) {
+            Ok(()) => remove_patch(&patch_path)?,
+            Err(e) => {
+                log::warn!("Failed to re-apply archived changes for {}: {}", archive.branch_name, e);
+                result.patch_applied = false;
+                result.unapplied_patch = Some(patch_path.to_string_lossy().to_string());
+            }
+        }
+    }
+    local_db::delete_workspace_archive(repo_path, archive.id)?;
+    Ok(result)
+}
+
+/// Drop an archive record and its saved changes for good
+pub fn delete_archived_workspace(repo_path: &str, archive_id: i64) -> Result<(), String> {
+    let archive = find_archive(repo_path, archive_id)?;
+    if let Some(patch_file) = &archive.patch_file {
+        remove_patch(&archive_dir(repo_path).join(patch_file))?;
+    }
+    local_db::delete_workspace_archive(repo_path, archive.id)
+}
+
+fn remove_patch(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to delete archive patch: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_ops::tests::{commit_file, setup_git_repo};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_archive_and_unarchive_git_worktree() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = &setup_git_repo(&temp_dir);
+        commit_file(repo, "a.txt", "one\n", "Initial");
+        vcs::set_preference(repo, Some("git"));
+        local_db::init_local_db(repo).unwrap();
+
+        let backend = vcs::backend_for_repo(repo);
+        let name = backend
+            .create_workspace(repo, "feature", "feature", true, Some("main"), None)
+            .unwrap();
+        let workspace_path = temp_dir.path().join(".treq/workspaces").join(&name);
+        let id = local_db::add_workspace(
+            repo,
+            name,
+            workspace_path.to_string_lossy().to_string(),
+            "feature".to_string(),
+            None,
+        )
+        .unwrap();
+        local_db::update_workspace_target_branch(repo, id, "main").unwrap();
+        fs::write(workspace_path.join("a.txt"), "two\n").unwrap();
+        fs::write(workspace_path.join("new.txt"), "new\n").unwrap();
+
+        let archive = archive_workspace(repo, id).unwrap();
+        assert_eq!(archive.branch_name, "feature");
+        assert_eq!(archive.file_count, 2);
+        assert!(!workspace_path.exists());
+        assert!(local_db::get_workspace_by_id(repo, id).unwrap().is_none());
+        let patch_path = archive_dir(repo).join(archive.patch_file.as_ref().unwrap());
+        assert!(patch_path.is_file());
+
+        let result = unarchive_workspace(repo, archive.id, None).unwrap();
+        assert!(result.patch_applied);
+        assert_eq!(
+            fs::read_to_string(workspace_path.join("a.txt")).unwrap(),
+            "two\n"
+        );
+        assert!(workspace_path.join("new.txt").is_file());
+        let restored = local_db::get_workspace_by_id(repo, result.workspace_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.target_branch.as_deref(), Some("main"));
+        assert!(!patch_path.exists());
+        assert!(list_archived_workspaces(repo).unwrap().is_empty());
+        vcs::set_preference(repo, None);
+    }
+}
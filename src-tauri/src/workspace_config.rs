@@ -0,0 +1,483 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::automation;
+use crate::db::Database;
+use crate::local_db::{self, Workspace};
+
+/// Settings key (global and per-repo) for default environment variables, one KEY=VALUE per line
+pub const WORKSPACE_ENV_KEY: &str = "workspace_env";
+
+/// Settings key (global and per-repo) for excluded paths, one glob per line
+pub const EXCLUDED_PATHS_KEY: &str = "excluded_paths";
+
+/// A named command that can be run inside the workspace
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RunConfig {
+    pub name: String,
+    pub command: String,
+    /// Working directory relative to the workspace root
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Contents of `.treq/workspace.toml` inside a workspace
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct WorkspaceConfig {
+    pub target_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_paths: Vec<String>,
+    #[serde(default, rename = "run", skip_serializing_if = "Vec::is_empty")]
+    pub run_configs: Vec<RunConfig>,
+}
+
+/// Workspace settings after merging global, repo and workspace layers
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EffectiveWorkspaceConfig {
+    pub target_branch: Option<String>,
+    /// Where target_branch came from: "workspace_file", "workspace" or "none"
+    pub target_branch_source: String,
+    pub env: BTreeMap<String, String>,
+    pub excluded_paths: Vec<String>,
+    pub run_configs: Vec<RunConfig>,
+}
+
+/// Path of the per-workspace config file
+pub fn workspace_config_path(workspace_path: &str) -> PathBuf {
+    Path::new(workspace_path)
+        .join(".treq")
+        .join("workspace.toml")
+}
+
+/// Load `.treq/workspace.toml`, returning None if the workspace has no config file
+pub fn load_workspace_config(workspace_path: &str) -> Result<Option<WorkspaceConfig>, String> {
+    let path = workspace_config_path(workspace_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Write `.treq/workspace.toml`, creating the .treq directory if needed
+pub fn save_workspace_config(workspace_path: &str, config: &WorkspaceConfig) -> Result<(), String> {
+    let path = workspace_config_path(workspace_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create .treq directory: {}", e))?;
+    }
+
+    let content = toml::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize workspace config: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Target branch override from the workspace config file, if any
+pub fn target_branch_override(workspace_path: &str) -> Option<String> {
+    match load_workspace_config(workspace_path) {
+        Ok(config) => config
+            .and_then(|c| c.target_branch)
+            .filter(|b| !b.trim().is_empty()),
+        Err(e) => {
+            log::warn!("Ignoring workspace config: {}", e);
+            None
+        }
+    }
+}
+
+/// Target branch for a workspace, preferring `.treq/workspace.toml` over the stored value
+pub fn effective_target_branch(workspace: &Workspace) -> Option<String> {
+    target_branch_override(&workspace.workspace_path).or_else(|| workspace.target_branch.clone())
+}
+
+/// Parse KEY=VALUE lines, ignoring blanks and comments
+fn parse_env_lines(value: &str) -> BTreeMap<String, String> {
+    value
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .filter(|(k, _)| !k.is_empty())
+        .collect()
+}
+
+fn parse_path_lines(value: &str) -> Vec<String> {
+    value
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect()
+}
+
+/// Merge layers in order of increasing precedence: global settings, repo settings,
+/// then the workspace file. Env keys are overridden; excluded paths accumulate.
+fn merge_layers(
+    global_env: Option<&str>,
+    repo_env: Option<&str>,
+    global_excluded: Option<&str>,
+    repo_excluded: Option<&str>,
+    workspace_target_branch: Option<String>,
+    file: Option<WorkspaceConfig>,
+) -> EffectiveWorkspaceConfig {
+    let file = file.unwrap_or_default();
+
+    let mut env = BTreeMap::new();
+    for layer in [global_env, repo_env].into_iter().flatten() {
+        env.extend(parse_env_lines(layer));
+    }
+    env.extend(file.env);
+
+    let mut excluded_paths: Vec<String> = Vec::new();
+    let layers = [global_excluded, repo_excluded]
+        .into_iter()
+        .flatten()
+        .flat_map(parse_path_lines)
+        .chain(file.excluded_paths);
+    for path in layers {
+        if !excluded_paths.contains(&path) {
+            excluded_paths.push(path);
+        }
+    }
+
+    let (target_branch, target_branch_source) = match file.target_branch {
+        Some(branch) if !branch.trim().is_empty() => (Some(branch), "workspace_file"),
+        _ => match workspace_target_branch {
+            Some(branch) => (Some(branch), "workspace"),
+            None => (None, "none"),
+        },
+    };
+
+    EffectiveWorkspaceConfig {
+        target_branch,
+        target_branch_source: target_branch_source.to_string(),
+        env,
+        excluded_paths,
+        run_configs: file.run_configs,
+    }
+}
+
+/// Resolve the effective config for a workspace from global/repo settings, the
+/// workspace record in local_db, and `.treq/workspace.toml`.
+pub fn resolve_workspace_config(
+    db: &Database,
+    repo_path: &str,
+    workspace_path: &str,
+) -> Result<EffectiveWorkspaceConfig, String> {
+    let global_env = db.get_setting(WORKSPACE_ENV_KEY).ok().flatten();
+    let repo_env = db
+        .get_repo_setting(repo_path, WORKSPACE_ENV_KEY)
+        .ok()
+        .flatten();
+    let global_excluded = db.get_setting(EXCLUDED_PATHS_KEY).ok().flatten();
+    let repo_excluded = db
+        .get_repo_setting(repo_path, EXCLUDED_PATHS_KEY)
+        .ok()
+        .flatten();
+
+    let workspace_target_branch =
+        local_db::get_workspace_by_path(repo_path, workspace_path)?.and_then(|w| w.target_branch);
+
+    Ok(merge_layers(
+        global_env.as_deref(),
+        repo_env.as_deref(),
+        global_excluded.as_deref(),
+        repo_excluded.as_deref(),
+        workspace_target_branch,
+        load_workspace_config(workspace_path)?,
+    ))
+}
+
+/// Named setup profile applied when a workspace is created, stored as a JSON list
+/// in the WORKSPACE_TEMPLATES_KEY repo setting
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct WorkspaceTemplate {
+    pub name: String,
+    /// Ignored files copied into the workspace, in addition to the repo's included_copy_files
+    #[serde(default)]
+    pub inclusion_patterns: Vec<String>,
+    /// Shell commands run in the new workspace, in order, stopping at the first failure
+    #[serde(default)]
+    pub post_create_commands: Vec<String>,
+    pub target_branch: Option<String>,
+    /// Written to the workspace's `.treq/workspace.toml`
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Outcome of one post-create command
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostCreateCommandResult {
+    pub command: String,
+    pub success: bool,
+    pub output: String,
+}
+
+/// Repo settings key holding the repo's workspace templates
+pub const WORKSPACE_TEMPLATES_KEY: &str = "workspace_templates";
+
+fn parse_templates(value: Option<&str>) -> Result<Vec<WorkspaceTemplate>, String> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(Vec::new()),
+        Some(value) => serde_json::from_str(value)
+            .map_err(|e| format!("Invalid workspace templates setting: {}", e)),
+    }
+}
+
+pub fn load_templates(db: &Database, repo_path: &str) -> Result<Vec<WorkspaceTemplate>, String> {
+    let value = db
+        .get_repo_setting(repo_path, WORKSPACE_TEMPLATES_KEY)
+        .map_err(|e| e.to_string())?;
+    parse_templates(value.as_deref())
+}
+
+fn save_templates(
+    db: &Database,
+    repo_path: &str,
+    templates: &[WorkspaceTemplate],
+) -> Result<(), String> {
+    let value = serde_json::to_string(templates)
+        .map_err(|e| format!("Failed to serialize workspace templates: {}", e))?;
+    db.set_repo_setting(repo_path, WORKSPACE_TEMPLATES_KEY, &value)
+        .map_err(|e| e.to_string())
+}
+
+pub fn find_template(
+    db: &Database,
+    repo_path: &str,
+    name: &str,
+) -> Result<WorkspaceTemplate, String> {
+    load_templates(db, repo_path)?
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| format!("Workspace template '{}' not found", name))
+}
+
+/// Replace the template with the same name, or add it
+fn upsert_template(templates: &mut Vec<WorkspaceTemplate>, template: WorkspaceTemplate) {
+    match templates.iter_mut().find(|t| t.name == template.name) {
+        Some(existing) => *existing = template,
+        None => templates.push(template),
+    }
+}
+
+pub fn save_template(
+    db: &Database,
+    repo_path: &str,
+    mut template: WorkspaceTemplate,
+) -> Result<Vec<WorkspaceTemplate>, String> {
+    template.name = template.name.trim().to_string();
+    if template.name.is_empty() {
+        return Err("Workspace template name is required".to_string());
+    }
+    let mut templates = load_templates(db, repo_path)?;
+    upsert_template(&mut templates, template);
+    save_templates(db, repo_path, &templates)?;
+    Ok(templates)
+}
+
+pub fn delete_template(
+    db: &Database,
+    repo_path: &str,
+    name: &str,
+) -> Result<Vec<WorkspaceTemplate>, String> {
+    let mut templates = load_templates(db, repo_path)?;
+    templates.retain(|t| t.name != name);
+    save_templates(db, repo_path, &templates)?;
+    Ok(templates)
+}
+
+/// Add the template's environment to the workspace's `.treq/workspace.toml`
+pub fn apply_template_env(
+    workspace_path: &str,
+    template: &WorkspaceTemplate,
+) -> Result<(), String> {
+    if template.env.is_empty() {
+        return Ok(());
+    }
+    let mut config = load_workspace_config(workspace_path)?.unwrap_or_default();
+    for (key, value) in &template.env {
+        config
+            .env
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+    save_workspace_config(workspace_path, &config)
+}
+
+/// Run the template's post-create commands in the workspace with its environment
+pub fn run_post_create_commands(
+    workspace_path: &str,
+    template: &WorkspaceTemplate,
+) -> Vec<PostCreateCommandResult> {
+    let mut results = Vec::new();
+    for command in template
+        .post_create_commands
+        .iter()
+        .filter(|c| !c.trim().is_empty())
+    {
+        let output = automation::shell_command(command)
+            .current_dir(workspace_path)
+            .envs(&template.env)
+            .output();
+
+        let result = match output {
+            Ok(output) => PostCreateCommandResult {
+                command: command.clone(),
+                success: output.status.success(),
+                output: format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            },
+            Err(e) => PostCreateCommandResult {
+                command: command.clone(),
+                success: false,
+                output: format!("Failed to run command: {}", e),
+            },
+        };
+        let success = result.success;
+        results.push(result);
+        if !success {
+            break;
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_workspace_config_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace_path = temp_dir.path().to_str().unwrap();
+        assert_eq!(load_workspace_config(workspace_path).unwrap(), None);
+
+        let config = WorkspaceConfig {
+            target_branch: Some("release".to_string()),
+            env: BTreeMap::from([("RUST_LOG".to_string(), "debug".to_string())]),
+            excluded_paths: vec!["dist/**".to_string()],
+            run_configs: vec![RunConfig {
+                name: "test".to_string(),
+                command: "cargo test".to_string(),
+                cwd: Some("src-tauri".to_string()),
+                env: BTreeMap::new(),
+            }],
+        };
+        save_workspace_config(workspace_path, &config).unwrap();
+
+        let content = fs::read_to_string(workspace_config_path(workspace_path)).unwrap();
+        assert!(content.contains("[[run]]"));
+        assert_eq!(load_workspace_config(workspace_path).unwrap(), Some(config));
+        assert_eq!(
+            target_branch_override(workspace_path),
+            Some("release".to_string())
+        );
+    }
+
+    #[test]
+    fn test_templates_upsert_and_apply_env() {
+        let mut templates = parse_templates(None).unwrap();
+        upsert_template(
+            &mut templates,
+            WorkspaceTemplate {
+                name: "frontend".to_string(),
+                post_create_commands: vec!["npm ci".to_string()],
+                ..Default::default()
+            },
+        );
+        upsert_template(
+            &mut templates,
+            WorkspaceTemplate {
+                name: "frontend".to_string(),
+                target_branch: Some("develop".to_string()),
+                env: BTreeMap::from([("PORT".to_string(), "3001".to_string())]),
+                ..Default::default()
+            },
+        );
+        assert_eq!(templates.len(), 1);
+
+        let stored = serde_json::to_string(&templates).unwrap();
+        let parsed = parse_templates(Some(&stored)).unwrap();
+        assert_eq!(parsed, templates);
+        assert!(parsed[0].post_create_commands.is_empty());
+
+        let temp_dir = TempDir::new().unwrap();
+        let workspace_path = temp_dir.path().to_str().unwrap();
+        save_workspace_config(
+            workspace_path,
+            &WorkspaceConfig {
+                env: BTreeMap::from([("PORT".to_string(), "4000".to_string())]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        apply_template_env(workspace_path, &parsed[0]).unwrap();
+        // Values already in the workspace file win
+        let config = load_workspace_config(workspace_path).unwrap().unwrap();
+        assert_eq!(config.env.get("PORT").map(String::as_str), Some("4000"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_post_create_commands_stop_at_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let template = WorkspaceTemplate {
+            name: "setup".to_string(),
+            post_create_commands: vec![
+                "echo $GREETING > greeting.txt".to_string(),
+                "exit 3".to_string(),
+                "touch never.txt".to_string(),
+            ],
+            env: BTreeMap::from([("GREETING".to_string(), "hi".to_string())]),
+            ..Default::default()
+        };
+
+        let results = run_post_create_commands(temp_dir.path().to_str().unwrap(), &template);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].success);
+        assert!(!results[1].success);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("greeting.txt")).unwrap(),
+            "hi\n"
+        );
+        assert!(!temp_dir.path().join("never.txt").exists());
+    }
+
+    #[test]
+    fn test_merge_layers_precedence() {
+        let file = WorkspaceConfig {
+            env: BTreeMap::from([("A".to_string(), "workspace".to_string())]),
+            excluded_paths: vec!["tmp/".to_string(), "dist/".to_string()],
+            ..Default::default()
+        };
+
+        let merged = merge_layers(
+            Some("A=global\nB=global"),
+            Some("B=repo\n# comment"),
+            Some("dist/"),
+            None,
+            Some("main".to_string()),
+            Some(file),
+        );
+
+        assert_eq!(merged.env.get("A").map(String::as_str), Some("workspace"));
+        assert_eq!(merged.env.get("B").map(String::as_str), Some("repo"));
+        assert_eq!(merged.excluded_paths, vec!["dist/", "tmp/"]);
+        assert_eq!(merged.target_branch.as_deref(), Some("main"));
+        assert_eq!(merged.target_branch_source, "workspace");
+    }
+}
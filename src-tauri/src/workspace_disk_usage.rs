@@ -0,0 +1,413 @@
+use ignore::{WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::git_ops;
+use crate::local_db;
+use crate::result_cache::{self, CacheKind, CachedPayload};
+
+/// Workspaces at least this large are flagged so the dashboard can suggest archiving them
+pub const ARCHIVE_SUGGESTION_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Ignored directories smaller than this aren't worth offering for cleanup
+pub const CLEANABLE_MIN_BYTES: u64 = 50 * 1024 * 1024;
+
+/// VCS and treq metadata, never offered for cleanup nor deleted
+const PROTECTED_DIRS: &[&str] = &[".git", ".jj", ".treq"];
+
+/// Disk usage of one directory under `.treq/workspaces`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkspaceDiskUsage {
+    /// None for directories that aren't tracked as a workspace
+    pub workspace_id: Option<i64>,
+    pub workspace_name: String,
+    pub workspace_path: String,
+    /// Bytes only this workspace holds
+    pub total_bytes: u64,
+    pub file_count: u64,
+    /// Bytes of hardlinked files (e.g. git objects shared with the main repository),
+    /// not included in `total_bytes`
+    pub shared_bytes: u64,
+    pub suggest_archive: bool,
+}
+
+#[derive(Debug, PartialEq)]
+struct DirectoryUsage {
+    bytes: u64,
+    files: u64,
+    shared_bytes: u64,
+}
+
+#[cfg(unix)]
+fn is_hardlinked(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn is_hardlinked(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Walk `path` in parallel and add up its file sizes. Ignore files are not applied:
+/// build output and dependencies are usually what makes a workspace large.
+fn directory_usage(path: &Path) -> DirectoryUsage {
+    let bytes = AtomicU64::new(0);
+    let files = AtomicU64::new(0);
+    let shared_bytes = AtomicU64::new(0);
+
+    WalkBuilder::new(path)
+        .standard_filters(false)
+        .follow_links(false)
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| {
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    return WalkState::Continue;
+                }
+                let Ok(metadata) = entry.metadata() else {
+                    return WalkState::Continue;
+                };
+                if is_hardlinked(&metadata) {
+                    shared_bytes.fetch_add(metadata.len(), Ordering::Relaxed);
+                } else {
+                    bytes.fetch_add(metadata.len(), Ordering::Relaxed);
+                    files.fetch_add(1, Ordering::Relaxed);
+                }
+                WalkState::Continue
+            })
+        });
+
+    DirectoryUsage {
+        bytes: bytes.into_inner(),
+        files: files.into_inner(),
+        shared_bytes: shared_bytes.into_inner(),
+    }
+}
+
+/// Size of each directory under `.treq/workspaces`, largest first
+pub fn workspace_disk_usage(repo_path: &str) -> Result<Vec<WorkspaceDiskUsage>, String> {
+    let workspaces_dir = Path::new(repo_path).join(".treq").join("workspaces");
+    if !workspaces_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let workspaces = local_db::get_workspaces(repo_path)?;
+
+    let entries = fs::read_dir(&workspaces_dir)
+        .map_err(|e| format!("Failed to read workspaces directory: {}", e))?;
+    let mut usages = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let path = entry.path();
+        let workspace_path = path.to_string_lossy().to_string();
+        let workspace = workspaces
+            .iter()
+            .find(|w| Path::new(&w.workspace_path) == path);
+
+        let usage = directory_usage(&path);
+        usages.push(WorkspaceDiskUsage {
+            workspace_id: workspace.map(|w| w.id),
+            workspace_name: workspace
+                .map(|w| w.workspace_name.clone())
+                .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string()),
+            workspace_path,
+            total_bytes: usage.bytes,
+            file_count: usage.files,
+            shared_bytes: usage.shared_bytes,
+            suggest_archive: usage.bytes >= ARCHIVE_SUGGESTION_BYTES,
+        });
+    }
+
+    usages.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.workspace_name.cmp(&b.workspace_name))
+    });
+    Ok(usages)
+}
+
+/// A gitignored directory of a workspace, e.g. `node_modules`, `target` or `dist`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CleanablePath {
+    /// Relative to the workspace
+    pub path: String,
+    pub total_bytes: u64,
+    pub file_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CleanResult {
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+/// Topmost gitignored directories of a workspace. The walker skips ignored entries, so
+/// a child directory it never yields while its parent is yielded is an ignored one.
+fn ignored_directories(workspace: &Path) -> Vec<PathBuf> {
+    let walked: HashSet<PathBuf> = WalkBuilder::new(workspace)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| !PROTECTED_DIRS.iter().any(|d| entry.file_name() == *d))
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_dir()))
+        .map(|entry| entry.into_path())
+        .collect();
+
+    let mut ignored = Vec::new();
+    for dir in &walked {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_dir())
+                && !walked.contains(&path)
+                && !PROTECTED_DIRS.iter().any(|d| entry.file_name() == *d)
+            {
+                ignored.push(path);
+            }
+        }
+    }
+    ignored
+}
+
+/// Gitignored directories of a workspace of at least `CLEANABLE_MIN_BYTES`, largest
+/// first
+pub fn list_cleanable_paths(workspace_path: &str) -> Result<Vec<CleanablePath>, String> {
+    let workspace = Path::new(workspace_path);
+    if !workspace.is_dir() {
+        return Err(format!("Workspace not found: {}", workspace_path));
+    }
+
+    let mut cleanable: Vec<CleanablePath> = ignored_directories(workspace)
+        .into_iter()
+        .filter_map(|dir| {
+            let usage = directory_usage(&dir);
+            if usage.bytes < CLEANABLE_MIN_BYTES {
+                return None;
+            }
+            Some(CleanablePath {
+                path: dir
+                    .strip_prefix(workspace)
+                    .ok()?
+                    .to_string_lossy()
+                    .to_string(),
+                total_bytes: usage.bytes,
+                file_count: usage.files,
+            })
+        })
+        .collect();
+    cleanable.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(cleanable)
+}
+
+/// Resolve a relative path to delete, refusing anything that could reach outside the
+/// workspace or into VCS metadata, and anything that isn't ignored: it must be one of
+/// `ignored` or inside one, or git must confirm it is ignored
+fn resolve_cleanable(workspace: &Path, ignored: &[PathBuf], path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(path);
+    let is_plain = relative.components().all(
+        |c| matches!(c, Component::Normal(name) if !PROTECTED_DIRS.iter().any(|d| name == *d)),
+    );
+    if path.is_empty() || !is_plain {
+        return Err(format!(
+            "Refusing to delete '{}': not a path inside the workspace",
+            path
+        ));
+    }
+
+    // Symlinked parents could point anywhere, so check where the parent really is
+    let full = workspace.join(relative);
+    let parent = full
+        .parent()
+        .and_then(|p| p.canonicalize().ok())
+        .ok_or_else(|| format!("Path not found: {}", path))?;
+    if !parent.starts_with(workspace) {
+        return Err(format!(
+            "Refusing to delete '{}': it is outside the workspace",
+            path
+        ));
+    }
+
+    let is_ignored = ignored.iter().any(|dir| full.starts_with(dir))
+        || git_ops::run_git(
+            &workspace.to_string_lossy(),
+            &["check-ignore", "-q", "--", path],
+        )
+        .is_ok();
+    if !is_ignored {
+        return Err(format!(
+            "Refusing to delete '{}': it is not ignored by git",
+            path
+        ));
+    }
+    Ok(full)
+}
+
+/// Delete build artifacts of a workspace. All paths are checked before anything is
+/// deleted and only ignored paths are accepted; symlinks are removed, not followed.
+pub fn clean_paths(workspace_path: &str, paths: &[String]) -> Result<CleanResult, String> {
+    let workspace = Path::new(workspace_path)
+        .canonicalize()
+        .map_err(|e| format!("Workspace not found: {}", e))?;
+    let ignored = ignored_directories(&workspace);
+    let targets = paths
+        .iter()
+        .map(|path| resolve_cleanable(&workspace, &ignored, path).map(|full| (path, full)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut result = CleanResult {
+        removed: Vec::new(),
+        freed_bytes: 0,
+    };
+    for (path, full) in targets {
+        let Ok(metadata) = fs::symlink_metadata(&full) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            let usage = directory_usage(&full);
+            fs::remove_dir_all(&full).map_err(|e| format!("Failed to delete {}: {}", path, e))?;
+            result.freed_bytes += usage.bytes;
+        } else {
+            fs::remove_file(&full).map_err(|e| format!("Failed to delete {}: {}", path, e))?;
+            if !metadata.is_symlink() {
+                result.freed_bytes += metadata.len();
+            }
+        }
+        result.removed.push(path.clone());
+    }
+    Ok(result)
+}
+
+/// `workspace_disk_usage`, reusing a result computed in the last ten minutes
+pub fn cached_workspace_disk_usage(
+    repo_path: &str,
+) -> Result<CachedPayload<Vec<WorkspaceDiskUsage>>, String> {
+    result_cache::get_or_compute(CacheKind::DiskUsage, repo_path, "", || {
+        workspace_disk_usage(repo_path)
+    })
+}
+
+/// Drop the cached sizes after a workspace directory is removed
+pub fn invalidate_disk_usage(repo_path: &str) {
+    result_cache::invalidate(repo_path, &[CacheKind::DiskUsage]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_directory_usage_counts_hidden_and_ignored_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        fs::write(dir.path().join("target/debug/app"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let usage = directory_usage(dir.path());
+        assert_eq!(usage.files, 3);
+        assert_eq!(usage.bytes, 8 + 100 + 12);
+        assert_eq!(usage.shared_bytes, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_usage_excludes_hardlinked_files() {
+        let dir = TempDir::new().unwrap();
+        let objects = dir.path().join(".git/objects/ab");
+        fs::create_dir_all(&objects).unwrap();
+        fs::write(objects.join("cdef"), vec![0u8; 50]).unwrap();
+        fs::hard_link(objects.join("cdef"), dir.path().join("linked")).unwrap();
+        fs::write(dir.path().join("own"), vec![0u8; 10]).unwrap();
+
+        let usage = directory_usage(dir.path());
+        assert_eq!(
+            usage,
+            DirectoryUsage {
+                bytes: 10,
+                files: 1,
+                shared_bytes: 100,
+            }
+        );
+    }
+
+    #[test]
+    fn test_ignored_directories_are_the_topmost_ignored_ones() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join(".gitignore"), "node_modules/\ntarget/\n").unwrap();
+        fs::create_dir_all(root.join("target/debug/deps")).unwrap();
+        fs::create_dir_all(root.join("web/node_modules/react")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join(".git/objects")).unwrap();
+
+        let mut ignored = ignored_directories(&root);
+        ignored.sort();
+        assert_eq!(
+            ignored,
+            [root.join("target"), root.join("web/node_modules")]
+        );
+    }
+
+    #[test]
+    fn test_clean_paths_refuses_paths_outside_the_workspace() {
+        let dir = TempDir::new().unwrap();
+        let workspace = dir.path().join("workspace");
+        fs::create_dir_all(workspace.join("target/debug")).unwrap();
+        fs::write(workspace.join("target/debug/app"), vec![0u8; 100]).unwrap();
+        fs::write(workspace.join(".gitignore"), "target/\n").unwrap();
+        fs::create_dir_all(workspace.join("src")).unwrap();
+        fs::write(workspace.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::create_dir_all(dir.path().join("outside")).unwrap();
+        let workspace_path = workspace.to_string_lossy().to_string();
+
+        // Tracked source is never deleted, only ignored paths
+        for path in ["src", "src/main.rs", ".gitignore"] {
+            assert!(
+                clean_paths(&workspace_path, &[path.to_string()]).is_err(),
+                "{}",
+                path
+            );
+        }
+        assert!(workspace.join("src/main.rs").exists());
+
+        for path in ["../outside", "/tmp", ".git", "target/../../outside", ""] {
+            assert!(
+                clean_paths(&workspace_path, &[path.to_string()]).is_err(),
+                "{}",
+                path
+            );
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("outside"), workspace.join("link")).unwrap();
+            let result = clean_paths(&workspace_path, &["link/x".to_string()]);
+            assert!(result.is_err());
+        }
+        // Nothing is deleted when one of the paths is refused
+        assert!(clean_paths(&workspace_path, &["target".to_string(), "..".to_string()]).is_err());
+        assert!(workspace.join("target").exists());
+
+        let result = clean_paths(&workspace_path, &["target".to_string()]).unwrap();
+        assert_eq!(result.removed, ["target"]);
+        assert_eq!(result.freed_bytes, 100);
+        assert!(!workspace.join("target").exists());
+        assert!(dir.path().join("outside").exists());
+    }
+}
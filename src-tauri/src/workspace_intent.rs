@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+use crate::jj::JjFileChange;
+use crate::local_db::{self, Workspace};
+use crate::vcs;
+use crate::workspace_config;
+
+/// Changed files listed in an exported context before the rest are summarized
+const MAX_EXPORTED_FILES: usize = 200;
+
+/// What a workspace is for, stored in its metadata JSON next to any other keys
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct WorkspaceIntent {
+    /// Goal text; kept under the `intent` key used by earlier metadata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub acceptance_criteria: Vec<String>,
+    /// Issue URL or reference, e.g. "#123"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_issue: Option<String>,
+}
+
+/// Read the intent fields of workspace metadata; missing or malformed metadata is empty
+pub fn parse_intent(metadata: Option<&str>) -> WorkspaceIntent {
+    metadata
+        .and_then(|m| serde_json::from_str(m).ok())
+        .unwrap_or_default()
+}
+
+/// Write the intent fields into workspace metadata, keeping unrelated keys
+pub fn merge_intent(metadata: Option<&str>, intent: &WorkspaceIntent) -> Result<String, String> {
+    let mut object = metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|v| v.as_object().cloned())
+        .unwrap_or_default();
+    for key in ["intent", "acceptance_criteria", "linked_issue"] {
+        object.remove(key);
+    }
+
+    let intent = WorkspaceIntent {
+        intent: intent
+            .intent
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from),
+        acceptance_criteria: intent
+            .acceptance_criteria
+            .iter()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect(),
+        linked_issue: intent
+            .linked_issue
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from),
+    };
+    if let serde_json::Value::Object(fields) =
+        serde_json::to_value(&intent).map_err(|e| format!("Failed to serialize intent: {}", e))?
+    {
+        object.extend(fields);
+    }
+
+    serde_json::to_string(&object).map_err(|e| format!("Failed to serialize metadata: {}", e))
+}
+
+fn find_workspace(repo_path: &str, workspace_id: i64) -> Result<Workspace, String> {
+    local_db::get_workspace_by_id(repo_path, workspace_id)?
+        .ok_or_else(|| format!("Workspace {} not found", workspace_id))
+}
+
+pub fn get_workspace_intent(repo_path: &str, workspace_id: i64) -> Result<WorkspaceIntent, String> {
+    let workspace = find_workspace(repo_path, workspace_id)?;
+    Ok(parse_intent(workspace.metadata.as_deref()))
+}
+
+pub fn set_workspace_intent(
+    repo_path: &str,
+    workspace_id: i64,
+    intent: &WorkspaceIntent,
+) -> Result<(), String> {
+    let workspace = find_workspace(repo_path, workspace_id)?;
+    let metadata = merge_intent(workspace.metadata.as_deref(), intent)?;
+    local_db::update_workspace_metadata(repo_path, workspace_id, &metadata)
+}
+
+fn status_label(status: &str) -> &str {
+    match status {
+        "A" => "added",
+        "D" => "deleted",
+        "R" => "renamed",
+        "?" => "untracked",
+        _ => "modified",
+    }
+}
+
+/// Render the prompt-ready Markdown for a workspace
+fn render_context(
+    workspace: &Workspace,
+    target_branch: Option<&str>,
+    changes: &[JjFileChange],
+) -> String {
+    let intent = parse_intent(workspace.metadata.as_deref());
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# Workspace: {}\n", workspace.workspace_name);
+    match target_branch {
+        Some(target) => {
+            let _ = writeln!(
+                out,
+                "Branch `{}`, to be merged into `{}`.\n",
+                workspace.branch_name, target
+            );
+        }
+        None => {
+            let _ = writeln!(out, "Branch `{}`.\n", workspace.branch_name);
+        }
+    }
+
+    if let Some(goal) = &intent.intent {
+        let _ = writeln!(out, "## Goal\n\n{}\n", goal);
+    }
+    if !intent.acceptance_criteria.is_empty() {
+        out.push_str("## Acceptance criteria\n\n");
+        for criterion in &intent.acceptance_criteria {
+            let _ = writeln!(out, "- [ ] {}", criterion);
+        }
+        out.push('\n');
+    }
+    if let Some(issue) = &intent.linked_issue {
+        let _ = writeln!(out, "## Linked issue\n\n{}\n", issue);
+    }
+
+    out.push_str("## Changed files\n\n");
+    if changes.is_empty() {
+        out.push_str("No uncommitted changes.\n");
+    }
+    for change in changes.iter().take(MAX_EXPORTED_FILES) {
+        match &change.previous_path {
+            Some(previous) => {
+                let _ = writeln!(
+                    out,
+                    "- `{}` ({} from `{}`)",
+                    change.path,
+                    status_label(&change.status),
+                    previous
+                );
+            }
+            None => {
+                let _ = writeln!(
+                    out,
+                    "- `{}` ({})",
+                    change.path,
+                    status_label(&change.status)
+                );
+            }
+        }
+    }
+    if changes.len() > MAX_EXPORTED_FILES {
+        let _ = writeln!(out, "- ...and {} more", changes.len() - MAX_EXPORTED_FILES);
+    }
+
+    out
+}
+
+/// Bundle the intent, changed files and target branch into Markdown for an agent prompt
+pub fn export_workspace_context(repo_path: &str, workspace_id: i64) -> Result<String, String> {
+    let workspace = find_workspace(repo_path, workspace_id)?;
+    let target_branch = workspace_config::effective_target_branch(&workspace);
+    let changes = vcs::backend_for_workspace(&workspace.workspace_path)
+        .changed_files(&workspace.workspace_path)?;
+    Ok(render_context(
+        &workspace,
+        target_branch.as_deref(),
+        &changes,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_intent_keeps_other_keys() {
+        let intent = WorkspaceIntent {
+            intent: Some(" Add dark mode ".to_string()),
+            acceptance_criteria: vec!["Toggle in settings".to_string(), " ".to_string()],
+            linked_issue: Some(String::new()),
+        };
+        let metadata = merge_intent(Some(r#"{"intent":"old","pinned":true}"#), &intent).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(value["pinned"], true);
+        assert!(value.get("linked_issue").is_none());
+
+        assert_eq!(
+            parse_intent(Some(&metadata)),
+            WorkspaceIntent {
+                intent: Some("Add dark mode".to_string()),
+                acceptance_criteria: vec!["Toggle in settings".to_string()],
+                linked_issue: None,
+            }
+        );
+        assert_eq!(parse_intent(Some("not json")), WorkspaceIntent::default());
+    }
+
+    #[test]
+    fn test_render_context() {
+        let workspace = Workspace {
+            id: 1,
+            repo_path: "/repo".to_string(),
+            workspace_name: "dark-mode".to_string(),
+            workspace_path: "/repo/.treq/workspaces/dark-mode".to_string(),
+            branch_name: "treq/dark-mode".to_string(),
+            created_at: String::new(),
+            metadata: Some(
+                r##"{"intent":"Add dark mode","acceptance_criteria":["Toggle in settings"],"linked_issue":"#42"}"##
+                    .to_string(),
+            ),
+            target_branch: None,
+            has_conflicts: false,
+        };
+        let changes = vec![
+            JjFileChange {
+                path: "src/theme.ts".to_string(),
+                status: "A".to_string(),
+                previous_path: None,
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
+            },
+            JjFileChange {
+                path: "src/settings.tsx".to_string(),
+                status: "R".to_string(),
+                previous_path: Some("src/prefs.tsx".to_string()),
+                submodule: None,
+                index_status: None,
+                worktree_status: None,
+            },
+        ];
+
+        let markdown = render_context(&workspace, Some("main"), &changes);
+        assert_eq!(
+            markdown,
+            "# Workspace: dark-mode\n\n\
+             Branch `treq/dark-mode`, to be merged into `main`.\n\n\
+             ## Goal\n\nAdd dark mode\n\n\
+             ## Acceptance criteria\n\n- [ ] Toggle in settings\n\n\
+             ## Linked issue\n\n#42\n\n\
+             ## Changed files\n\n\
+             - `src/theme.ts` (added)\n\
+             - `src/settings.tsx` (renamed from `src/prefs.tsx`)\n"
+        );
+    }
+}
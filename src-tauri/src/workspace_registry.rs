@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::jj;
+use crate::local_db::{self, Workspace};
+use crate::vcs::{self, VcsKind};
+
+/// How the three sources of truth about a workspace disagree
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MismatchKind {
+    /// local_db row whose directory no longer exists
+    MissingDirectory,
+    /// Workspace directory without a local_db row
+    UnregisteredDirectory,
+    /// jj tracks a workspace whose directory no longer exists
+    JjOrphan,
+    /// Directory exists but jj no longer tracks it, so jj commands fail there
+    NotInJj,
+}
+
+/// Suggested way to resolve a mismatch
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryFix {
+    /// Add a local_db row for the directory
+    Register,
+    /// `jj workspace forget`
+    Forget,
+    /// Delete the local_db row
+    DeleteRow,
+    /// Add the workspace to jj again, keeping the directory's files as working copy changes
+    Reattach,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RegistryMismatch {
+    pub kind: MismatchKind,
+    pub workspace_name: String,
+    pub workspace_path: String,
+    pub workspace_id: Option<i64>,
+    pub suggested_fix: RegistryFix,
+}
+
+/// Compare local_db rows, workspace directories (name -> path) and, for jj repos, the
+/// names jj tracks
+fn find_mismatches(
+    repo_path: &str,
+    rows: &[Workspace],
+    directories: &HashMap<String, String>,
+    jj_names: Option<&HashSet<String>>,
+) -> Vec<RegistryMismatch> {
+    let mut mismatches = Vec::new();
+    let directory_paths: HashSet<&str> = directories.values().map(String::as_str).collect();
+
+    for row in rows {
+        if !directory_paths.contains(row.workspace_path.as_str()) {
+            mismatches.push(RegistryMismatch {
+                kind: MismatchKind::MissingDirectory,
+                workspace_name: row.workspace_name.clone(),
+                workspace_path: row.workspace_path.clone(),
+                workspace_id: Some(row.id),
+                suggested_fix: RegistryFix::DeleteRow,
+            });
+        }
+    }
+
+    let mut names: Vec<&String> = directories.keys().collect();
+    names.sort();
+    for name in names {
+        let path = &directories[name];
+        let row = rows.iter().find(|w| &w.workspace_path == path);
+        if row.is_none() {
+            mismatches.push(RegistryMismatch {
+                kind: MismatchKind::UnregisteredDirectory,
+                workspace_name: name.clone(),
+                workspace_path: path.clone(),
+                workspace_id: None,
+                suggested_fix: RegistryFix::Register,
+            });
+        }
+        if jj_names.is_some_and(|jj_names| !jj_names.contains(name)) {
+            mismatches.push(RegistryMismatch {
+                kind: MismatchKind::NotInJj,
+                workspace_name: name.clone(),
+                workspace_path: path.clone(),
+                workspace_id: row.map(|w| w.id),
+                suggested_fix: RegistryFix::Reattach,
+            });
+        }
+    }
+
+    if let Some(jj_names) = jj_names {
+        let mut orphans: Vec<&String> = jj_names
+            .iter()
+            .filter(|name| !directories.contains_key(*name))
+            .collect();
+        orphans.sort();
+        for name in orphans {
+            let path = Path::new(repo_path)
+                .join(".treq")
+                .join("workspaces")
+                .join(name)
+                .to_string_lossy()
+                .to_string();
+            mismatches.push(RegistryMismatch {
+                kind: MismatchKind::JjOrphan,
+                workspace_name: name.clone(),
+                workspace_id: rows.iter().find(|w| w.workspace_path == path).map(|w| w.id),
+                workspace_path: path,
+                suggested_fix: RegistryFix::Forget,
+            });
+        }
+    }
+
+    mismatches
+}
+
+/// Cross-check `jj workspace list`, the workspace directories and local_db rows
+pub fn reconcile_workspace_registry(repo_path: &str) -> Result<Vec<RegistryMismatch>, String> {
+    let rows = local_db::get_workspaces(repo_path)?;
+    let directories: HashMap<String, String> = jj::list_workspaces(repo_path)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|w| (w.name, w.path))
+        .collect();
+    // Plain git workspaces are worktrees jj never tracks
+    let jj_names: Option<HashSet<String>> = if vcs::backend_kind(repo_path) == VcsKind::JjColocated
+    {
+        Some(
+            jj::list_jj_workspace_names(repo_path)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .collect(),
+        )
+    } else {
+        None
+    };
+
+    Ok(find_mismatches(
+        repo_path,
+        &rows,
+        &directories,
+        jj_names.as_ref(),
+    ))
+}
+
+/// Apply the suggested fix of a mismatch returned by [`reconcile_workspace_registry`]
+pub fn apply_fix(repo_path: &str, mismatch: &RegistryMismatch) -> Result<(), String> {
+    match mismatch.suggested_fix {
+        RegistryFix::Register => {
+            let branch_name = jj::get_workspace_branch(&mismatch.workspace_path)
+                .unwrap_or_else(|_| mismatch.workspace_name.clone());
+            local_db::add_workspace(
+                repo_path,
+                mismatch.workspace_name.clone(),
+                mismatch.workspace_path.clone(),
+                branch_name,
+                None,
+            )
+            .map(|_| ())
+        }
+        RegistryFix::Forget => {
+            jj::forget_workspace(repo_path, &mismatch.workspace_name).map_err(|e| e.to_string())
+        }
+        RegistryFix::DeleteRow => match mismatch.workspace_id {
+            Some(id) => local_db::delete_workspace(repo_path, id),
+            None => Ok(()),
+        },
+        RegistryFix::Reattach => {
+            let branch_name = local_db::get_workspaces(repo_path)?
+                .into_iter()
+                .find(|w| w.workspace_path == mismatch.workspace_path)
+                .map(|w| w.branch_name)
+                .unwrap_or_else(|| mismatch.workspace_name.clone());
+            jj::reattach_workspace(
+                repo_path,
+                &mismatch.workspace_name,
+                &mismatch.workspace_path,
+                &branch_name,
+            )
+            .map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_db::tests::workspace_row as row;
+
+    #[test]
+    fn test_find_mismatches() {
+        let rows = vec![row(1, "ok"), row(2, "deleted"), row(3, "forgotten")];
+        let directories: HashMap<String, String> = ["ok", "forgotten", "manual"]
+            .iter()
+            .map(|n| (n.to_string(), format!("/repo/.treq/workspaces/{}", n)))
+            .collect();
+        let jj_names: HashSet<String> = ["ok", "deleted", "manual", "stale"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+
+        let mismatches = find_mismatches("/repo", &rows, &directories, Some(&jj_names));
+        let found: Vec<(MismatchKind, &str, RegistryFix, Option<i64>)> = mismatches
+            .iter()
+            .map(|m| {
+                (
+                    m.kind,
+                    m.workspace_name.as_str(),
+                    m.suggested_fix,
+                    m.workspace_id,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (
+                    MismatchKind::MissingDirectory,
+                    "deleted",
+                    RegistryFix::DeleteRow,
+                    Some(2)
+                ),
+                (
+                    MismatchKind::NotInJj,
+                    "forgotten",
+                    RegistryFix::Reattach,
+                    Some(3)
+                ),
+                (
+                    MismatchKind::UnregisteredDirectory,
+                    "manual",
+                    RegistryFix::Register,
+                    None
+                ),
+                (
+                    MismatchKind::JjOrphan,
+                    "deleted",
+                    RegistryFix::Forget,
+                    Some(2)
+                ),
+                (MismatchKind::JjOrphan, "stale", RegistryFix::Forget, None),
+            ]
+        );
+
+        // Without jj only the database and filesystem are compared
+        let mismatches = find_mismatches("/repo", &rows, &directories, None);
+        assert_eq!(mismatches.len(), 2);
+    }
+}
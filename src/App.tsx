@@ -8,6 +8,7 @@ import { EditorAppsProvider } from "./hooks/useEditorApps";
 import { useSettingsPreloader } from "./hooks/useSettingsPreloader";
 import { ErrorBoundary } from "./components/ErrorBoundary";
 import { PrismThemeLoader } from "./components/PrismThemeLoader";
+import { AuthPromptDialog } from "./components/AuthPromptDialog";
 import "./index.css";
 
 const queryClient = new QueryClient({
@@ -35,6 +36,7 @@ function AppContent() {
       >
         <Dashboard />
       </ErrorBoundary>
+      <AuthPromptDialog />
     </div>
   );
 }
@@ -0,0 +1,77 @@
+import { useState, useEffect } from "react";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+} from "./ui/dialog";
+import { Button } from "./ui/button";
+import { Input } from "./ui/input";
+import { authPromptListen, submitAuthResponse, type AuthPrompt } from "../lib/api";
+
+/** Answers credential prompts from git/ssh, one at a time in arrival order */
+export const AuthPromptDialog: React.FC = () => {
+  const [queue, setQueue] = useState<AuthPrompt[]>([]);
+  const [answer, setAnswer] = useState("");
+  const [remember, setRemember] = useState(true);
+  const current = queue[0];
+
+  useEffect(() => {
+    const unlisten = authPromptListen((prompt) => {
+      setQueue((prev) => [...prev, prompt]);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const respond = (response: string | null) => {
+    if (!current) return;
+    submitAuthResponse(current.id, response, remember).catch((err) =>
+      console.error("Failed to submit credentials:", err)
+    );
+    setQueue((prev) => prev.slice(1));
+    setAnswer("");
+  };
+
+  return (
+    <Dialog open={!!current} onOpenChange={(open) => !open && respond(null)}>
+      <DialogContent className="sm:max-w-[440px]">
+        <DialogHeader>
+          <DialogTitle>Credentials required</DialogTitle>
+          <DialogDescription>Git needs credentials to {current?.operation}.</DialogDescription>
+        </DialogHeader>
+        <form
+          className="space-y-3"
+          onSubmit={(e) => {
+            e.preventDefault();
+            respond(answer);
+          }}
+        >
+          <p className="text-sm font-mono break-all">{current?.prompt}</p>
+          <Input
+            type={current?.secret ? "password" : "text"}
+            value={answer}
+            onChange={(e) => setAnswer(e.target.value)}
+            autoFocus
+          />
+          <label className="flex items-center gap-2 text-sm">
+            <input
+              type="checkbox"
+              checked={remember}
+              onChange={(e) => setRemember(e.target.checked)}
+            />
+            <span>Remember until treq restarts</span>
+          </label>
+          <div className="flex justify-end gap-2">
+            <Button type="button" variant="outline" onClick={() => respond(null)}>
+              Cancel
+            </Button>
+            <Button type="submit">Continue</Button>
+          </div>
+        </form>
+      </DialogContent>
+    </Dialog>
+  );
+};
@@ -1,7 +1,8 @@
 import { useState, useEffect } from "react";
 import { Command } from "cmdk";
 import { GitBranch, Check, ArrowRight } from "lucide-react";
-import { jjGetBranches, jjEditBookmark } from "../lib/api";
+import { jjGetBranches, jjEditBookmark, isJjError } from "../lib/api";
+import { errorMessage } from "../lib/toast-helpers";
 
 // Type definition - Git API removed, needs JJ equivalent
 interface BranchListItem {
@@ -46,7 +47,7 @@ export const BranchSwitcher: React.FC<BranchSwitcherProps> = ({
       }));
       setBranches(branchList);
     } catch (err) {
-      setError(err instanceof Error ? err.message : String(err));
+      setError(errorMessage(err));
     } finally {
       setLoading(false);
     }
@@ -66,7 +67,12 @@ export const BranchSwitcher: React.FC<BranchSwitcherProps> = ({
       onBranchChanged?.(branch.name);
       onOpenChange(false);
     } catch (err) {
-      setError(err instanceof Error ? err.message : String(err));
+      if (isJjError(err) && err.kind === "bookmark_not_found") {
+        setError(`Branch ${branch.name} no longer exists`);
+        loadBranches();
+      } else {
+        setError(errorMessage(err));
+      }
     } finally {
       setSwitching(false);
     }
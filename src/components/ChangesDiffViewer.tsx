@@ -19,15 +19,20 @@ import {
   jjGetFileHunks,
   jjRestoreFile,
   jjRestoreAll,
+  requestDestructiveConfirmation,
+  type DestructiveConfirmation,
+  type HookFailure,
   jjCommit,
   jjSplit,
   getDiffCache,
   markFileViewed,
   unmarkFileViewed,
+  getViewedFiles,
   loadPendingReview,
   savePendingReview,
   clearPendingReview,
   jjGetMergeDiff,
+  type FileHunks,
   type JjDiffHunk,
   type JjFileChange,
   type JjRevisionDiff,
@@ -94,6 +99,7 @@ import { ConflictsSection } from "./ConflictsSection";
 import { ConflictCommentCard } from "./ConflictCommentCard";
 import { MoveToWorkspaceDialog } from "./MoveToWorkspaceDialog";
 import { FileContextMenu } from "./FileContextMenu";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface ChangesDiffViewerProps {
   workspacePath: string;
@@ -164,8 +170,17 @@ interface FileHunksData {
   hunks: JjDiffHunk[];
   isLoading: boolean;
   error?: string;
+  /** Set when the backend skipped diffing the file */
+  notice?: string;
 }
 
+const skippedDiffNotice = (data: FileHunks): string | undefined => {
+  if (data.is_lfs) return "Git LFS file - no diff available";
+  if (data.is_binary) return "Binary file - no diff available";
+  if (data.too_large) return "File too large to diff";
+  return undefined;
+};
+
 // Helper to get line type styling (background only, text color handled by syntax highlighting)
 const getLineTypeClass = (line: string): string => {
   if (line.startsWith("+")) return "bg-emerald-500/20";
@@ -969,8 +984,7 @@ const FileRowComponent: React.FC<FileRowComponentProps> = memo((props) => {
                           try {
                             await openUrl(`cursor://file/${workspacePath}/${filePath}`);
                           } catch (err) {
-                            const msg =
-                              err instanceof Error ? err.message : String(err);
+                            const msg = errorMessage(err);
                             addToast({
                               title: "Open Failed",
                               description: msg,
@@ -990,8 +1004,7 @@ const FileRowComponent: React.FC<FileRowComponentProps> = memo((props) => {
                           try {
                             await openUrl(`vscode://file/${workspacePath}/${filePath}`);
                           } catch (err) {
-                            const msg =
-                              err instanceof Error ? err.message : String(err);
+                            const msg = errorMessage(err);
                             addToast({
                               title: "Open Failed",
                               description: msg,
@@ -1011,8 +1024,7 @@ const FileRowComponent: React.FC<FileRowComponentProps> = memo((props) => {
                           try {
                             await openUrl(`zed://file/${workspacePath}/${filePath}`);
                           } catch (err) {
-                            const msg =
-                              err instanceof Error ? err.message : String(err);
+                            const msg = errorMessage(err);
                             addToast({
                               title: "Open Failed",
                               description: msg,
@@ -1051,6 +1063,11 @@ const FileRowComponent: React.FC<FileRowComponentProps> = memo((props) => {
             <div className="text-sm text-destructive px-[12px] py-[8px]">
               {fileData.error}
             </div>
+          ) : fileData.notice ? (
+            <div className="flex items-center justify-center py-[32px] text-muted-foreground">
+              <FileText className="w-5 h-5 mr-[8px] opacity-50" />
+              <span>{fileData.notice}</span>
+            </div>
           ) : fileData.hunks.length === 0 ? (
             <div className="text-sm text-muted-foreground px-[12px] py-[24px] text-center">
               No diff hunks available
@@ -1219,6 +1236,12 @@ export const ChangesDiffViewer = memo(
       const [reviewPopoverOpen, setReviewPopoverOpen] = useState(false);
       const [finalReviewComment, setFinalReviewComment] = useState("");
       const [showCancelDialog, setShowCancelDialog] = useState(false);
+      const [pendingDiscardAll, setPendingDiscardAll] =
+        useState<DestructiveConfirmation | null>(null);
+      const [rejectedCommit, setRejectedCommit] = useState<{
+        message: string;
+        failure: HookFailure;
+      } | null>(null);
       const [copiedReview, setCopiedReview] = useState(false);
       const [hasUserAddedComments, setHasUserAddedComments] = useState(false);
       const [editingCommentId, setEditingCommentId] = useState<string | null>(
@@ -1394,6 +1417,23 @@ export const ChangesDiffViewer = memo(
       const [viewedFiles, setViewedFiles] = useState<
         Map<string, { viewedAt: string; contentHash: string }>
       >(new Map());
+      // Viewed marks belong to one diff, so each base ref has its own
+      const viewedBaseRef = showCommittedChanges && targetBranch ? targetBranch : "";
+
+      useEffect(() => {
+        getViewedFiles(workspacePath, viewedBaseRef)
+          .then((views) =>
+            setViewedFiles(
+              new Map(
+                views.map((view) => [
+                  view.file_path,
+                  { viewedAt: view.viewed_at, contentHash: view.content_hash },
+                ])
+              )
+            )
+          )
+          .catch(() => setViewedFiles(new Map()));
+      }, [workspacePath, viewedBaseRef]);
 
       // File selection state for moving to workspace
       const [selectedUnstagedFiles, setSelectedUnstagedFiles] = useState<
@@ -1499,8 +1539,7 @@ export const ChangesDiffViewer = memo(
           const parsed = parseJjChangedFiles(jjFiles);
           applyChangedFiles(parsed);
         } catch (error) {
-          const message =
-            error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addToast({ title: "JJ Error", description: message, type: "error" });
         } finally {
           setInitialLoading(false);
@@ -1592,6 +1631,7 @@ export const ChangesDiffViewer = memo(
           if (showCommittedChanges && targetBranch) {
             try {
               const mergeDiff: JjRevisionDiff = await jjGetMergeDiff(
+                repoPath,
                 workspacePath,
                 targetBranch
               );
@@ -1613,7 +1653,7 @@ export const ChangesDiffViewer = memo(
               console.error("Failed to fetch committed changes:", error);
               addToast?.({
                 title: "Failed to load committed changes",
-                description: error instanceof Error ? error.message : "Unknown error",
+                description: errorMessage(error),
                 type: "error",
               });
             }
@@ -1632,7 +1672,7 @@ export const ChangesDiffViewer = memo(
         };
 
         fetchCommittedChanges();
-      }, [showCommittedChanges, targetBranch, workspacePath, addToast]);
+      }, [showCommittedChanges, repoPath, targetBranch, workspacePath, addToast]);
 
       // Debounce comments and summary for auto-save
       const debouncedComments = useDebounce(comments, 500);
@@ -1704,11 +1744,11 @@ export const ChangesDiffViewer = memo(
 
         // Remove stale entries from database
         for (const filePath of staleFiles) {
-          unmarkFileViewed(workspacePath, filePath).catch(() => {
+          unmarkFileViewed(workspacePath, viewedBaseRef, filePath).catch(() => {
             // Silently ignore unmark failures
           });
         }
-      }, [files, allFileHunks, workspacePath]);
+      }, [files, allFileHunks, workspacePath, viewedBaseRef]);
 
       // Note: We no longer auto-close comment input when file changes.
       // Instead, stale files are tracked and the user is shown a reload banner.
@@ -1723,7 +1763,7 @@ export const ChangesDiffViewer = memo(
             : "";
 
           try {
-            await markFileViewed(workspacePath, filePath, contentHash);
+            await markFileViewed(workspacePath, viewedBaseRef, filePath, contentHash);
             const now = new Date().toISOString();
             setViewedFiles((prev) =>
               new Map(prev).set(filePath, { viewedAt: now, contentHash })
@@ -1734,13 +1774,13 @@ export const ChangesDiffViewer = memo(
             // Silently ignore mark failures
           }
         },
-        [workspacePath, allFileHunks]
+        [workspacePath, viewedBaseRef, allFileHunks]
       );
 
       const handleUnmarkFileViewed = useCallback(
         async (filePath: string) => {
           try {
-            await unmarkFileViewed(workspacePath, filePath);
+            await unmarkFileViewed(workspacePath, viewedBaseRef, filePath);
             setViewedFiles((prev) => {
               const next = new Map(prev);
               next.delete(filePath);
@@ -1756,7 +1796,7 @@ export const ChangesDiffViewer = memo(
             // Silently ignore unmark failures
           }
         },
-        [workspacePath]
+        [workspacePath, viewedBaseRef]
       );
 
       const loadAllFileHunks = useCallback(
@@ -1836,18 +1876,19 @@ export const ChangesDiffViewer = memo(
             const results = await Promise.all(
               filesToLoad.map(async (file) => {
                 try {
-                  const hunks = await jjGetFileHunks(workspacePath, file.path);
+                  const data = await jjGetFileHunks(workspacePath, file.path);
                   return {
                     filePath: file.path,
-                    hunks,
+                    hunks: data.hunks,
+                    notice: skippedDiffNotice(data),
                     error: null as string | null,
                   };
                 } catch (error) {
-                  const message =
-                    error instanceof Error ? error.message : String(error);
+                  const message = errorMessage(error);
                   return {
                     filePath: file.path,
                     hunks: [] as JjDiffHunk[],
+                    notice: undefined,
                     error: message,
                   };
                 }
@@ -1872,6 +1913,7 @@ export const ChangesDiffViewer = memo(
                       filePath: result.filePath,
                       hunks: result.hunks,
                       isLoading: false,
+                      notice: result.notice,
                     };
 
                 newHunksMap.set(result.filePath, newData);
@@ -1919,6 +1961,7 @@ export const ChangesDiffViewer = memo(
                 if (
                   !existing ||
                   existing.isLoading ||
+                  existing.notice !== result.notice ||
                   !hunksEqual(existing.hunks, result.hunks)
                 ) {
                   hasChanges = true;
@@ -1926,6 +1969,7 @@ export const ChangesDiffViewer = memo(
                     filePath: result.filePath,
                     hunks: result.hunks,
                     isLoading: false,
+                    notice: result.notice,
                   });
                 }
               }
@@ -2060,10 +2104,9 @@ export const ChangesDiffViewer = memo(
         addToast,
       ]);
 
-      const handleDiscardAll = useCallback(async () => {
-        if (readOnly) return;
+      const discardAll = useCallback(async (confirmToken: string) => {
         try {
-          await jjRestoreAll(workspacePath);
+          await jjRestoreAll(workspacePath, confirmToken);
           addToast({
             title: "Discarded",
             description: "All changes discarded",
@@ -2072,15 +2115,37 @@ export const ChangesDiffViewer = memo(
           await invalidateCache();
           refresh();
         } catch (error) {
-          const message =
-            error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addToast({
             title: "Discard All Failed",
             description: message,
             type: "error",
           });
         }
-      }, [workspacePath, readOnly, refresh, addToast, invalidateCache]);
+      }, [workspacePath, refresh, addToast, invalidateCache]);
+
+      const handleDiscardAll = useCallback(async () => {
+        if (readOnly) return;
+        try {
+          // The main repo needs explicit confirmation while workspaces depend on it
+          const confirmation = await requestDestructiveConfirmation(
+            workspacePath,
+            "discard_all_changes"
+          );
+          if (confirmation.workspace_names.length > 0) {
+            setPendingDiscardAll(confirmation);
+            return;
+          }
+          await discardAll(confirmation.token);
+        } catch (error) {
+          const message = errorMessage(error);
+          addToast({
+            title: "Discard All Failed",
+            description: message,
+            type: "error",
+          });
+        }
+      }, [workspacePath, readOnly, discardAll, addToast]);
 
       const handleDiscardFiles = useCallback(
         async (filePath: string) => {
@@ -2115,8 +2180,7 @@ export const ChangesDiffViewer = memo(
             await invalidateCache();
             refresh();
           } catch (error) {
-            const message =
-              error instanceof Error ? error.message : String(error);
+            const message = errorMessage(error);
             addToast({
               title: "Discard Failed",
               description: message,
@@ -2752,8 +2816,7 @@ export const ChangesDiffViewer = memo(
           });
         } catch (error) {
           setContextMenuPosition(null);
-          const message =
-            error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addToast({
             title: "Failed to copy",
             description: message,
@@ -2781,8 +2844,7 @@ export const ChangesDiffViewer = memo(
           });
         } catch (error) {
           setContextMenuPosition(null);
-          const message =
-            error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addToast({
             title: "Failed to copy",
             description: message,
@@ -2902,8 +2964,7 @@ export const ChangesDiffViewer = memo(
             // Notify parent that review was submitted
             onReviewSubmitted?.();
           } catch (error) {
-            const message =
-              error instanceof Error ? error.message : String(error);
+            const message = errorMessage(error);
             addToast({
               title: "Failed to send review",
               description: message,
@@ -2954,8 +3015,7 @@ export const ChangesDiffViewer = memo(
             type: "success",
           });
         } catch (error) {
-          const message =
-            error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addToast({
             title: "Failed to cancel review",
             description: message,
@@ -2972,8 +3032,7 @@ export const ChangesDiffViewer = memo(
           setCopiedReview(true);
           setTimeout(() => setCopiedReview(false), 2000);
         } catch (error) {
-          const message =
-            error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addToast({
             title: "Failed to copy",
             description: message,
@@ -2983,7 +3042,7 @@ export const ChangesDiffViewer = memo(
       }, [formatReviewMarkdown, addToast]);
 
       const handleCommit = useCallback(
-        async (commitMsg: string) => {
+        async (commitMsg: string, skipHooks = false) => {
           if (!commitMsg) {
             addToast({
               title: "Commit message",
@@ -3014,7 +3073,15 @@ export const ChangesDiffViewer = memo(
               setStagedFiles(new Set()); // Clear staging after commit
             } else {
               // No staged files, commit all
-              result = await jjCommit(workspacePath, commitMsg);
+              const attempt = await jjCommit(workspacePath, commitMsg, skipHooks);
+              if (attempt.hook_failure) {
+                setRejectedCommit({
+                  message: commitMsg,
+                  failure: attempt.hook_failure,
+                });
+                return;
+              }
+              result = attempt.message;
             }
 
             await invalidateCache();
@@ -3025,8 +3092,7 @@ export const ChangesDiffViewer = memo(
             });
             loadChangedFiles();
           } catch (error) {
-            const message =
-              error instanceof Error ? error.message : String(error);
+            const message = errorMessage(error);
             addToast({
               title: "Commit failed",
               description: message,
@@ -3615,6 +3681,78 @@ export const ChangesDiffViewer = memo(
               </AlertDialogContent>
             </AlertDialog>
 
+            {/* Discard All in Main Repo Confirmation Dialog */}
+            <AlertDialog
+              open={pendingDiscardAll !== null}
+              onOpenChange={(open) => {
+                if (!open) setPendingDiscardAll(null);
+              }}
+            >
+              <AlertDialogContent>
+                <AlertDialogHeader>
+                  <AlertDialogTitle>Discard all changes?</AlertDialogTitle>
+                  <AlertDialogDescription>
+                    This is the main repository, which has{" "}
+                    {pendingDiscardAll?.workspace_names.length} workspace
+                    {pendingDiscardAll?.workspace_names.length !== 1 ? "s" : ""}.
+                    All uncommitted changes here will be discarded.
+                  </AlertDialogDescription>
+                </AlertDialogHeader>
+                <AlertDialogFooter>
+                  <AlertDialogCancel>Cancel</AlertDialogCancel>
+                  <AlertDialogAction
+                    onClick={() => {
+                      if (pendingDiscardAll) {
+                        discardAll(pendingDiscardAll.token);
+                      }
+                      setPendingDiscardAll(null);
+                    }}
+                  >
+                    Discard all
+                  </AlertDialogAction>
+                </AlertDialogFooter>
+              </AlertDialogContent>
+            </AlertDialog>
+
+            {/* Commit Rejected by Hook Dialog */}
+            <AlertDialog
+              open={rejectedCommit !== null}
+              onOpenChange={(open) => {
+                if (!open) setRejectedCommit(null);
+              }}
+            >
+              <AlertDialogContent>
+                <AlertDialogHeader>
+                  <AlertDialogTitle>
+                    Commit rejected by {rejectedCommit?.failure.hook} hook
+                  </AlertDialogTitle>
+                  <AlertDialogDescription>
+                    {rejectedCommit?.failure.exit_code !== null
+                      ? `The hook exited with ${rejectedCommit?.failure.exit_code}.`
+                      : "The hook was terminated."}
+                  </AlertDialogDescription>
+                </AlertDialogHeader>
+                {rejectedCommit?.failure.output.trim() && (
+                  <pre className="max-h-64 overflow-auto rounded bg-muted p-2 text-xs whitespace-pre-wrap">
+                    {rejectedCommit.failure.output}
+                  </pre>
+                )}
+                <AlertDialogFooter>
+                  <AlertDialogCancel>Cancel</AlertDialogCancel>
+                  <AlertDialogAction
+                    onClick={() => {
+                      if (rejectedCommit) {
+                        handleCommit(rejectedCommit.message, true);
+                      }
+                      setRejectedCommit(null);
+                    }}
+                  >
+                    Commit without hooks
+                  </AlertDialogAction>
+                </AlertDialogFooter>
+              </AlertDialogContent>
+            </AlertDialog>
+
             {/* Stale Files Warning Banner - shown when files changed during review */}
             {staleFiles.size > 0 && (
               <div className="flex items-center justify-between px-4 py-2 bg-amber-500/15 border-b border-amber-500/40">
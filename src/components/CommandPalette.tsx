@@ -16,6 +16,7 @@ import {
   Maximize2,
   ChevronsUpDown,
   Bot,
+  Undo2,
 } from "lucide-react";
 
 interface CommandItem {
@@ -42,6 +43,7 @@ interface CommandPaletteProps {
   onOpenWorkspacePicker: () => void;
   onOpenWorkspaceDeletion: () => void;
   onCreateWorkspace: () => void;
+  onUndoLastOperation?: () => void;
   onToggleTerminal?: () => void;
   onMaximizeTerminal?: () => void;
   onCreateAgentTerminal?: () => void;
@@ -84,6 +86,7 @@ export const CommandPalette: React.FC<CommandPaletteProps> = ({
   onOpenWorkspacePicker,
   onOpenWorkspaceDeletion,
   onCreateWorkspace,
+  onUndoLastOperation,
   onToggleTerminal,
   onMaximizeTerminal,
   onCreateAgentTerminal,
@@ -178,6 +181,17 @@ export const CommandPalette: React.FC<CommandPaletteProps> = ({
       });
     }
 
+    if (repoPath && onUndoLastOperation) {
+      result.push({
+        id: "undo-operation",
+        type: "action",
+        label: "Undo Last Operation",
+        description: "Revert the last jj operation, e.g. a squash, rebase or discard",
+        icon: <Undo2 className="w-4 h-4" />,
+        onSelect: onUndoLastOperation,
+      });
+    }
+
     if (hasSelectedWorkspace) {
       if (onToggleTerminal) {
         result.push({
@@ -240,6 +254,7 @@ export const CommandPalette: React.FC<CommandPaletteProps> = ({
     onOpenWorkspacePicker,
     onOpenWorkspaceDeletion,
     onCreateWorkspace,
+    onUndoLastOperation,
     onToggleTerminal,
     onMaximizeTerminal,
     onCreateAgentTerminal,
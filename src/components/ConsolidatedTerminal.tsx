@@ -25,6 +25,7 @@ import { useTerminalSettings } from "../hooks/useTerminalSettings";
 import { cn } from "../lib/utils";
 import { Loader2 } from "lucide-react";
 import { Button } from "./ui/button";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface ConsolidatedTerminalProps {
   sessionId: string;
@@ -149,7 +150,7 @@ export const ConsolidatedTerminal = forwardRef<
 
       // Local error handler
       const localHandleError = (error: unknown) => {
-        const message = error instanceof Error ? error.message : String(error);
+        const message = errorMessage(error);
         console.error("Terminal error:", message);
         const friendlyMessage = message.includes("Session not found")
           ? "Terminal session is still initializing. Please wait a moment and try again."
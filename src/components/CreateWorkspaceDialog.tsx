@@ -18,6 +18,7 @@ import {
   createWorkspace,
   getRepoSetting,
   checkBranchExists,
+  suggestWorkspaceBranchName,
   jjGitFetchBackground,
   type BranchStatus,
   jjGetBranches,
@@ -28,6 +29,7 @@ import {
 import { TargetBranchSelector } from "./TargetBranchSelector";
 import type { BranchListItem } from "./TargetBranchSelector";
 import { getValidTargets } from "../lib/workspace-tree";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface CreateWorkspaceDialogProps {
   open: boolean;
@@ -45,6 +47,8 @@ export const CreateWorkspaceDialog: React.FC<CreateWorkspaceDialogProps> = ({
   sourceBranch,
 }) => {
   const [intent, setIntent] = useState("");
+  const [acceptanceCriteria, setAcceptanceCriteria] = useState("");
+  const [linkedIssue, setLinkedIssue] = useState("");
   const [branchName, setBranchName] = useState("");
   const [branchPattern, setBranchPattern] = useState("treq/{name}");
   const [isEditingBranch, setIsEditingBranch] = useState(false);
@@ -78,6 +82,8 @@ export const CreateWorkspaceDialog: React.FC<CreateWorkspaceDialogProps> = ({
     if (open) {
       console.log("[CreateWorkspaceDialog] Resetting form (dialog opened)");
       setIntent("");
+      setAcceptanceCriteria("");
+      setLinkedIssue("");
       setBranchName("");
       setIsEditingBranch(false);
       setError("");
@@ -142,22 +148,40 @@ export const CreateWorkspaceDialog: React.FC<CreateWorkspaceDialogProps> = ({
     }
   }, [open, repoPath]);
 
-  // Auto-generate branch name from intent
+  // Auto-generate branch name from intent, skipping names already taken
   useEffect(() => {
-    if (!isEditingBranch && intent.trim()) {
-      const generatedBranch = applyBranchNamePattern(branchPattern, intent);
-      console.log("[CreateWorkspaceDialog] Auto-generating branch name:", {
-        intent: intent.trim(),
-        branchPattern,
-        generatedBranch,
-        isEditingBranch,
-      });
-      setBranchName(generatedBranch);
-    } else if (!isEditingBranch && !intent.trim()) {
+    if (isEditingBranch) {
+      return;
+    }
+    if (!intent.trim()) {
       console.log("[CreateWorkspaceDialog] Clearing branch name (no intent)");
       setBranchName("");
+      return;
     }
-  }, [intent, branchPattern, isEditingBranch]);
+
+    let cancelled = false;
+    const timeout = setTimeout(() => {
+      suggestWorkspaceBranchName(repoPath, intent)
+        .then((suggestion) => {
+          if (cancelled) return;
+          console.log("[CreateWorkspaceDialog] Auto-generating branch name:", {
+            intent: intent.trim(),
+            suggestion,
+          });
+          setBranchName(suggestion.branch_name);
+        })
+        .catch((err) => {
+          if (cancelled) return;
+          console.error("Failed to suggest branch name:", err);
+          setBranchName(applyBranchNamePattern(branchPattern, intent));
+        });
+    }, 300);
+
+    return () => {
+      cancelled = true;
+      clearTimeout(timeout);
+    };
+  }, [intent, branchPattern, isEditingBranch, repoPath]);
 
   // Check branch existence with debouncing
   useEffect(() => {
@@ -262,10 +286,16 @@ export const CreateWorkspaceDialog: React.FC<CreateWorkspaceDialogProps> = ({
         }
       }
 
-      // Step 2: Prepare metadata (only include intent if provided)
-      const metadata = intent.trim()
-        ? JSON.stringify({ intent: intent.trim() })
-        : JSON.stringify({});
+      // Step 2: Prepare metadata (only include intent fields if provided)
+      const criteria = acceptanceCriteria
+        .split("\n")
+        .map((line) => line.trim())
+        .filter(Boolean);
+      const metadata = JSON.stringify({
+        ...(intent.trim() ? { intent: intent.trim() } : {}),
+        ...(criteria.length > 0 ? { acceptance_criteria: criteria } : {}),
+        ...(linkedIssue.trim() ? { linked_issue: linkedIssue.trim() } : {}),
+      });
 
       // Determine newBranch and sourceBranch based on branch status
       let newBranch = true;
@@ -329,6 +359,8 @@ export const CreateWorkspaceDialog: React.FC<CreateWorkspaceDialogProps> = ({
 
       // Reset form
       setIntent("");
+      setAcceptanceCriteria("");
+      setLinkedIssue("");
       setBranchName("");
       setIsEditingBranch(false);
       setTargetBranch(null);
@@ -337,7 +369,7 @@ export const CreateWorkspaceDialog: React.FC<CreateWorkspaceDialogProps> = ({
       onSuccess(workspaceId);
       onOpenChange(false);
     } catch (err) {
-      const errorMsg = err instanceof Error ? err.message : String(err);
+      const errorMsg = errorMessage(err);
       setError(errorMsg);
       addToast({
         title: "Failed to create workspace",
@@ -389,6 +421,28 @@ export const CreateWorkspaceDialog: React.FC<CreateWorkspaceDialogProps> = ({
             </p>
           </div>
 
+          <div className="grid gap-2">
+            <Label htmlFor="acceptance-criteria">Acceptance Criteria (optional)</Label>
+            <Textarea
+              id="acceptance-criteria"
+              value={acceptanceCriteria}
+              onChange={(e) => setAcceptanceCriteria(e.target.value)}
+              placeholder="One per line"
+              rows={2}
+              className="resize-none"
+            />
+          </div>
+
+          <div className="grid gap-2">
+            <Label htmlFor="linked-issue">Linked Issue (optional)</Label>
+            <Input
+              id="linked-issue"
+              value={linkedIssue}
+              onChange={(e) => setLinkedIssue(e.target.value)}
+              placeholder="e.g., #123 or an issue URL"
+            />
+          </div>
+
           <div className="grid gap-2">
             <Label htmlFor="branch">Branch Name</Label>
             <div className="relative">
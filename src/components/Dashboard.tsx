@@ -48,13 +48,16 @@ import {
   jjIsWorkspace,
   jjGitFetch,
   jjGetCurrentBranch,
+  jjOpUndo,
   checkAndRebaseWorkspaces,
   startFileWatcher,
   stopFileWatcher,
+  watcherDegradedListen,
   jjTrackWorkspaceBookmarks,
   listConflictedWorkspaceIds,
 } from "../lib/api";
 import { Loader2 } from "lucide-react";
+import { errorMessage } from "../lib/toast-helpers";
 
 // Loading spinner component for Suspense fallback
 const LoadingSpinner = () => (
@@ -400,6 +403,20 @@ export const Dashboard: React.FC<DashboardProps> = ({ initialViewMode = "show-wo
     };
   }, [repoPath, queryClient]);
 
+  // The backend rescans and restarts a watcher that dropped events; let the user know
+  useEffect(() => {
+    const unlisten = watcherDegradedListen((payload) => {
+      addToast({
+        title: "File watcher recovered",
+        description: `Changes may have been missed, so the workspace was rescanned: ${payload.reason}`,
+        type: "warning",
+      });
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [addToast]);
+
   // Fetch remote branches on app startup when repo is loaded
   useEffect(() => {
     const fetchRemotes = async () => {
@@ -473,7 +490,7 @@ export const Dashboard: React.FC<DashboardProps> = ({ initialViewMode = "show-wo
     onError: (error) => {
       addToast({
         title: "Delete Failed",
-        description: error instanceof Error ? error.message : String(error),
+        description: errorMessage(error),
         type: "error",
       });
     },
@@ -737,7 +754,7 @@ export const Dashboard: React.FC<DashboardProps> = ({ initialViewMode = "show-wo
       } catch (error) {
         addToast({
           title: "Delete Failed",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
       }
@@ -774,6 +791,26 @@ export const Dashboard: React.FC<DashboardProps> = ({ initialViewMode = "show-wo
     queryClient.invalidateQueries({ queryKey: ["workspaces", repoPath] });
   }, [repoPath, queryClient]);
 
+  const handleUndoLastOperation = useCallback(async () => {
+    const path = selectedWorkspace?.workspace_path ?? repoPath;
+    if (!path) return;
+    try {
+      const result = await jjOpUndo(path);
+      queryClient.invalidateQueries();
+      addToast({
+        title: "Operation Undone",
+        description: result.trim() || "The last jj operation was reverted",
+        type: "success",
+      });
+    } catch (error) {
+      addToast({
+        title: "Undo Failed",
+        description: errorMessage(error),
+        type: "error",
+      });
+    }
+  }, [selectedWorkspace?.workspace_path, repoPath, queryClient, addToast]);
+
 
   const isSessionView =
     viewMode === "session" || viewMode === "show-workspace";
@@ -958,8 +995,7 @@ export const Dashboard: React.FC<DashboardProps> = ({ initialViewMode = "show-wo
               } catch (error) {
                 addToast({
                   title: "Failed to rename session",
-                  description:
-                    error instanceof Error ? error.message : String(error),
+                  description: errorMessage(error),
                   type: "error",
                 });
               }
@@ -1066,6 +1102,7 @@ export const Dashboard: React.FC<DashboardProps> = ({ initialViewMode = "show-wo
         onOpenWorkspacePicker={() => setShowWorkspacePicker(true)}
         onOpenWorkspaceDeletion={() => setShowWorkspaceDeletion(true)}
         onCreateWorkspace={() => setShowCreateDialog(true)}
+        onUndoLastOperation={handleUndoLastOperation}
         onToggleTerminal={() => terminalPaneRef.current?.toggleCollapse()}
         onMaximizeTerminal={() => terminalPaneRef.current?.toggleMaximize()}
         onCreateAgentTerminal={() => terminalPaneRef.current?.createAgentSession()}
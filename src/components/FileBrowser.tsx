@@ -19,7 +19,7 @@ import {
   jjGetChangedFiles,
   ensureWorkspaceIndexed,
 } from "../lib/api";
-import { cn } from "../lib/utils";
+import { cn, formatBytes } from "../lib/utils";
 import { getLanguageFromPath, highlightCode } from "../lib/syntax-highlight";
 import { useToast } from "./ui/toast";
 import { Button } from "./ui/button";
@@ -43,6 +43,7 @@ import {
 } from "./ui/context-menu";
 import { revealItemInDir, openUrl } from "@tauri-apps/plugin-opener";
 import { useEditorApps } from "../hooks/useEditorApps";
+import { errorMessage } from "../lib/toast-helpers";
 
 // Helper to check if file is binary
 function isBinaryFile(path: string): boolean {
@@ -562,7 +563,7 @@ const FileTreeContextMenu = memo(function FileTreeContextMenu({
             } catch (err) {
               addToast({
                 title: "Copy Failed",
-                description: err instanceof Error ? err.message : String(err),
+                description: errorMessage(err),
                 type: "error",
               });
             }
@@ -585,7 +586,7 @@ const FileTreeContextMenu = memo(function FileTreeContextMenu({
             } catch (err) {
               addToast({
                 title: "Copy Failed",
-                description: err instanceof Error ? err.message : String(err),
+                description: errorMessage(err),
                 type: "error",
               });
             }
@@ -610,7 +611,7 @@ const FileTreeContextMenu = memo(function FileTreeContextMenu({
                 } catch (err) {
                   addToast({
                     title: "Open Failed",
-                    description: err instanceof Error ? err.message : String(err),
+                    description: errorMessage(err),
                     type: "error",
                   });
                 }
@@ -628,7 +629,7 @@ const FileTreeContextMenu = memo(function FileTreeContextMenu({
                   } catch (err) {
                     addToast({
                       title: "Open Failed",
-                      description: err instanceof Error ? err.message : String(err),
+                      description: errorMessage(err),
                       type: "error",
                     });
                   }
@@ -646,7 +647,7 @@ const FileTreeContextMenu = memo(function FileTreeContextMenu({
                   } catch (err) {
                     addToast({
                       title: "Open Failed",
-                      description: err instanceof Error ? err.message : String(err),
+                      description: errorMessage(err),
                       type: "error",
                     });
                   }
@@ -664,7 +665,7 @@ const FileTreeContextMenu = memo(function FileTreeContextMenu({
                   } catch (err) {
                     addToast({
                       title: "Open Failed",
-                      description: err instanceof Error ? err.message : String(err),
+                      description: errorMessage(err),
                       type: "error",
                     });
                   }
@@ -866,7 +867,7 @@ export const FileBrowser = memo(function FileBrowser({
       .catch((error) => {
         addToast({
           title: "Failed to load directory",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
       })
@@ -975,13 +976,16 @@ export const FileBrowser = memo(function FileBrowser({
 
       setIsLoadingFile(true);
       try {
-        const content = await readFile(path);
+        const file = await readFile(path);
 
-        // Don't load large files
-        if (content.length > 1024 * 1024) {
+        // Large and binary files are refused by the backend
+        if (file.kind !== "text") {
           addToast({
-            title: "File too large",
-            description: "Files larger than 1MB cannot be displayed.",
+            title: file.kind === "file_too_large" ? "File too large" : "Binary file",
+            description:
+              file.kind === "file_too_large"
+                ? `Files larger than ${formatBytes(file.max_size)} cannot be displayed.`
+                : "Binary files cannot be displayed.",
             type: "warning",
           });
           setSelectedFile(null);
@@ -989,12 +993,12 @@ export const FileBrowser = memo(function FileBrowser({
           return;
         }
 
-        setFileContent(content);
+        setFileContent(file.content);
 
         // Load hunks for line-level indicators
         if (changedFiles.has(path)) {
           try {
-            const hunks = await jjGetFileHunks(
+            const { hunks } = await jjGetFileHunks(
               basePath,
               path.replace(`${basePath}/`, "")
             );
@@ -1042,7 +1046,7 @@ export const FileBrowser = memo(function FileBrowser({
       } catch (error) {
         addToast({
           title: "Failed to read file",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
         setFileContent("");
@@ -1199,7 +1203,7 @@ export const FileBrowser = memo(function FileBrowser({
       } catch (error) {
         addToast({
           title: "Failed to load directory",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
         return [];
@@ -13,6 +13,7 @@ import {
 import { revealItemInDir, openUrl } from "@tauri-apps/plugin-opener";
 import { useToast } from "./ui/toast";
 import { useEditorApps } from "../hooks/useEditorApps";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface FileContextMenuProps {
   filePath: string;
@@ -70,7 +71,7 @@ export const FileContextMenu = memo(function FileContextMenu({
             } catch (err) {
               addToast({
                 title: "Copy Failed",
-                description: err instanceof Error ? err.message : String(err),
+                description: errorMessage(err),
                 type: "error",
               });
             }
@@ -93,7 +94,7 @@ export const FileContextMenu = memo(function FileContextMenu({
             } catch (err) {
               addToast({
                 title: "Copy Failed",
-                description: err instanceof Error ? err.message : String(err),
+                description: errorMessage(err),
                 type: "error",
               });
             }
@@ -118,7 +119,7 @@ export const FileContextMenu = memo(function FileContextMenu({
                 } catch (err) {
                   addToast({
                     title: "Open Failed",
-                    description: err instanceof Error ? err.message : String(err),
+                    description: errorMessage(err),
                     type: "error",
                   });
                 }
@@ -136,7 +137,7 @@ export const FileContextMenu = memo(function FileContextMenu({
                   } catch (err) {
                     addToast({
                       title: "Open Failed",
-                      description: err instanceof Error ? err.message : String(err),
+                      description: errorMessage(err),
                       type: "error",
                     });
                   }
@@ -154,7 +155,7 @@ export const FileContextMenu = memo(function FileContextMenu({
                   } catch (err) {
                     addToast({
                       title: "Open Failed",
-                      description: err instanceof Error ? err.message : String(err),
+                      description: errorMessage(err),
                       type: "error",
                     });
                   }
@@ -172,7 +173,7 @@ export const FileContextMenu = memo(function FileContextMenu({
                   } catch (err) {
                     addToast({
                       title: "Open Failed",
-                      description: err instanceof Error ? err.message : String(err),
+                      description: errorMessage(err),
                       type: "error",
                     });
                   }
@@ -61,7 +61,7 @@ export const GitFileRow = memo<GitFileRowProps>(({
         onClick={(e) => {
           onFileClick?.(file.path, e);
         }}
-        title={file.path}
+        title={file.previous_path ? `${file.previous_path} → ${file.path}` : file.path}
       >
         <div className="ml-1 flex-1 flex items-center gap-2 min-w-0 font-sans">
           <span className={cn("font-medium truncate flex-shrink-0", isActive && "text-blue-500")}>{label.name}</span>
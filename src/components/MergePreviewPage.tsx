@@ -4,6 +4,7 @@ import {
   jjGetCommitsAhead,
   jjGetMergeDiff,
   jjCreateMerge,
+  getMergeTargetCommit,
   type JjCommitsAhead,
   type JjRevisionDiff,
 } from "../lib/api";
@@ -19,6 +20,7 @@ import {
   ChevronDown,
 } from "lucide-react";
 import { cn } from "../lib/utils";
+import { errorMessage } from "../lib/toast-helpers";
 
 export interface MergePreviewPageProps {
   workspace: Workspace;
@@ -30,7 +32,7 @@ export interface MergePreviewPageProps {
 export const MergePreviewPage = memo<MergePreviewPageProps>(
   function MergePreviewPage({
     workspace,
-    repoPath: _repoPath,
+    repoPath,
     onCancel,
     onMergeComplete,
   }) {
@@ -42,6 +44,7 @@ export const MergePreviewPage = memo<MergePreviewPageProps>(
       null
     );
     const [diff, setDiff] = useState<JjRevisionDiff | null>(null);
+    const [targetCommit, setTargetCommit] = useState<string | undefined>();
     const [commitMessage, setCommitMessage] = useState("");
     const [merging, setMerging] = useState(false);
     const [expandedFiles, setExpandedFiles] = useState<Set<string>>(new Set());
@@ -53,13 +56,17 @@ export const MergePreviewPage = memo<MergePreviewPageProps>(
       const loadPreview = async () => {
         setLoading(true);
         try {
-          const [commits, diffData] = await Promise.all([
+          const [commits, diffData, targetCommitId] = await Promise.all([
             jjGetCommitsAhead(workspace.workspace_path, targetBranch),
-            jjGetMergeDiff(workspace.workspace_path, targetBranch),
+            jjGetMergeDiff(repoPath, workspace.workspace_path, targetBranch),
+            getMergeTargetCommit(workspace.workspace_path, targetBranch).catch(
+              () => undefined
+            ),
           ]);
 
           setCommitsAhead(commits);
           setDiff(diffData);
+          setTargetCommit(targetCommitId);
 
           // Expand all files by default
           if (diffData && diffData.hunks_by_file) {
@@ -73,8 +80,7 @@ export const MergePreviewPage = memo<MergePreviewPageProps>(
         } catch (error) {
           addToast({
             title: "Failed to load merge preview",
-            description:
-              error instanceof Error ? error.message : String(error),
+            description: errorMessage(error),
             type: "error",
           });
         } finally {
@@ -85,7 +91,7 @@ export const MergePreviewPage = memo<MergePreviewPageProps>(
       loadPreview().catch((error) => {
         console.error("Unexpected error loading merge preview:", error);
       });
-    }, [workspace.workspace_path, workspace.branch_name, targetBranch, addToast]);
+    }, [repoPath, workspace.workspace_path, workspace.branch_name, targetBranch, addToast]);
 
     // Handle merge
     const handleMerge = useCallback(async () => {
@@ -103,12 +109,13 @@ export const MergePreviewPage = memo<MergePreviewPageProps>(
           workspace.workspace_path,
           workspace.branch_name,
           targetBranch,
-          commitMessage
+          commitMessage,
+          targetCommit
         );
 
         if (!result.success) {
           addToast({
-            title: "Merge failed",
+            title: result.blocked ? "Merge blocked" : "Merge failed",
             description: result.message,
             type: "error",
           });
@@ -126,7 +133,7 @@ export const MergePreviewPage = memo<MergePreviewPageProps>(
       } catch (error) {
         addToast({
           title: "Merge failed",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
       } finally {
@@ -136,6 +143,7 @@ export const MergePreviewPage = memo<MergePreviewPageProps>(
       workspace,
       targetBranch,
       commitMessage,
+      targetCommit,
       addToast,
       onMergeComplete,
       onCancel,
@@ -1,4 +1,4 @@
-import { useCallback } from "react";
+import { useCallback, useEffect, useState } from "react";
 import { Sparkles } from "lucide-react";
 import { Tooltip, TooltipContent, TooltipProvider, TooltipTrigger } from "./ui/tooltip";
 import {
@@ -7,6 +7,7 @@ import {
   DropdownMenuContent,
   DropdownMenuItem,
 } from "./ui/dropdown-menu";
+import { defaultSessionModel, listSessionModels } from "../lib/api";
 
 interface ModelSelectorProps {
   currentModel: string | null;
@@ -14,23 +15,32 @@ interface ModelSelectorProps {
   disabled?: boolean;
 }
 
-const AVAILABLE_MODELS = [
-  { value: "default", label: "Default" },
-  { value: "sonnet", label: "Sonnet" },
-  { value: "opus", label: "Opus" },
-  { value: "haiku", label: "Haiku" },
-  { value: "sonnet[1m]", label: "Sonnet (1M)" },
-  { value: "opusplan", label: "Opus Plan" },
-];
+const DEFAULT_OPTION = { value: "default", label: "Default" };
 
 export function ModelSelector({ currentModel, onModelChange, disabled }: ModelSelectorProps) {
+  const [availableModels, setAvailableModels] = useState([DEFAULT_OPTION]);
+
+  useEffect(() => {
+    listSessionModels()
+      .then((models) => {
+        const fallback = defaultSessionModel(models);
+        setAvailableModels([
+          fallback
+            ? { ...DEFAULT_OPTION, label: `Default (${fallback.display_name})` }
+            : DEFAULT_OPTION,
+          ...models.map((m) => ({ value: m.model_id, label: m.display_name })),
+        ]);
+      })
+      .catch((error) => console.error("Failed to load models:", error));
+  }, []);
+
   const getCurrentModelLabel = useCallback(() => {
     if (currentModel) {
-      const model = AVAILABLE_MODELS.find(m => m.value === currentModel);
+      const model = availableModels.find(m => m.value === currentModel);
       return model?.label || currentModel;
     }
-    return "Default";
-  }, [currentModel]);
+    return availableModels[0].label;
+  }, [currentModel, availableModels]);
 
   const handleModelSelect = useCallback(async (modelValue: string) => {
     await onModelChange(modelValue);
@@ -61,7 +71,7 @@ export function ModelSelector({ currentModel, onModelChange, disabled }: ModelSe
         </Tooltip>
       </TooltipProvider>
       <DropdownMenuContent align="end" sideOffset={4}>
-        {AVAILABLE_MODELS.map((model) => (
+        {availableModels.map((model) => (
           <DropdownMenuItem
             key={model.value}
             onSelect={() => handleModelSelect(model.value)}
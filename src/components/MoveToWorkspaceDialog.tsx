@@ -19,6 +19,7 @@ import {
   getRepoSetting,
 } from "../lib/api";
 import { ChevronDown, ChevronRight, FileText } from "lucide-react";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface MoveToWorkspaceDialogProps {
   open: boolean;
@@ -158,7 +159,7 @@ export const MoveToWorkspaceDialog: React.FC<MoveToWorkspaceDialogProps> = ({
       });
       onOpenChange(false);
     } catch (err) {
-      const errorMsg = err instanceof Error ? err.message : String(err);
+      const errorMsg = errorMessage(err);
       setError(errorMsg);
 
       addToast({
@@ -3,8 +3,22 @@ import { Button } from "./ui/button";
 import { Input } from "./ui/input";
 import { Textarea } from "./ui/textarea";
 import { Label } from "./ui/label";
-import { getRepoSetting, setRepoSetting } from "../lib/api";
+import {
+  getRepoSetting,
+  setRepoSetting,
+  listSessionModels,
+  COMMIT_SIGNING_FORMAT_KEY,
+  COMMIT_SIGNING_KEY_KEY,
+  WATCHER_DEBOUNCE_MS_KEY,
+  WATCHER_IGNORE_KEY,
+  JJ_MAX_NEW_FILE_SIZE_KEY,
+  DIFF_RENAME_THRESHOLD_KEY,
+  DIFF_FIND_COPIES_KEY,
+  DIFF_IGNORE_WHITESPACE_KEY,
+  type SessionModel,
+} from "../lib/api";
 import { useToast } from "./ui/toast";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface RepositorySettingsContentProps {
   repoPath: string;
@@ -18,12 +32,27 @@ export const RepositorySettingsContent: React.FC<RepositorySettingsContentProps>
   const [branchNamePattern, setBranchNamePattern] = useState("treq/{name}");
   const [includedFiles, setIncludedFiles] = useState("");
   const [defaultModel, setDefaultModel] = useState<string>("");
+  const [signingFormat, setSigningFormat] = useState<string>("");
+  const [signingKey, setSigningKey] = useState("");
+  const [watcherDebounce, setWatcherDebounce] = useState("");
+  const [watcherIgnore, setWatcherIgnore] = useState("");
+  const [maxNewFileSize, setMaxNewFileSize] = useState("");
+  const [renameThreshold, setRenameThreshold] = useState("");
+  const [findCopies, setFindCopies] = useState(false);
+  const [ignoreWhitespace, setIgnoreWhitespace] = useState(false);
+  const [models, setModels] = useState<SessionModel[]>([]);
   const [loading, setLoading] = useState(false);
   const [saving, setSaving] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [availableFiles, setAvailableFiles] = useState<string[]>([]);
   const { addToast } = useToast();
 
+  useEffect(() => {
+    listSessionModels()
+      .then(setModels)
+      .catch((err) => console.error("Failed to load models:", err));
+  }, []);
+
   // Load settings and available gitignored files when repo path changes
   useEffect(() => {
     if (repoPath) {
@@ -34,19 +63,55 @@ export const RepositorySettingsContent: React.FC<RepositorySettingsContentProps>
         getRepoSetting(repoPath, "branch_name_pattern"),
         getRepoSetting(repoPath, "included_copy_files"),
         getRepoSetting(repoPath, "default_model"),
+        getRepoSetting(repoPath, COMMIT_SIGNING_FORMAT_KEY),
+        getRepoSetting(repoPath, COMMIT_SIGNING_KEY_KEY),
+        getRepoSetting(repoPath, WATCHER_DEBOUNCE_MS_KEY),
+        getRepoSetting(repoPath, WATCHER_IGNORE_KEY),
+        getRepoSetting(repoPath, JJ_MAX_NEW_FILE_SIZE_KEY),
+        getRepoSetting(repoPath, DIFF_RENAME_THRESHOLD_KEY),
+        getRepoSetting(repoPath, DIFF_FIND_COPIES_KEY),
+        getRepoSetting(repoPath, DIFF_IGNORE_WHITESPACE_KEY),
       ])
-        .then(([branchPattern, includedPatterns, model]) => {
+        .then(([
+          branchPattern,
+          includedPatterns,
+          model,
+          format,
+          key,
+          debounce,
+          ignore,
+          maxFileSize,
+          threshold,
+          copies,
+          whitespace,
+        ]) => {
           setBranchNamePattern(branchPattern || "treq/{name}");
           setIncludedFiles(includedPatterns || "");
           setDefaultModel(model || "");
+          setSigningFormat(format || "");
+          setSigningKey(key || "");
+          setWatcherDebounce(debounce || "");
+          setWatcherIgnore(ignore || "");
+          setMaxNewFileSize(maxFileSize || "");
+          setRenameThreshold(threshold || "");
+          setFindCopies(copies === "true");
+          setIgnoreWhitespace(whitespace === "true");
           // Note: gitignored files listing removed - was git-specific
           setAvailableFiles([]);
         })
         .catch((err) => {
-          setError(`Failed to load settings: ${err}`);
+          setError(`Failed to load settings: ${errorMessage(err)}`);
           setBranchNamePattern("treq/{name}");
           setIncludedFiles("");
           setDefaultModel("");
+          setSigningFormat("");
+          setSigningKey("");
+          setWatcherDebounce("");
+          setWatcherIgnore("");
+          setMaxNewFileSize("");
+          setRenameThreshold("");
+          setFindCopies(false);
+          setIgnoreWhitespace(false);
           setAvailableFiles([]);
         })
         .finally(() => {
@@ -64,6 +129,14 @@ export const RepositorySettingsContent: React.FC<RepositorySettingsContentProps>
         setRepoSetting(repoPath, "branch_name_pattern", branchNamePattern),
         setRepoSetting(repoPath, "included_copy_files", includedFiles),
         setRepoSetting(repoPath, "default_model", defaultModel),
+        setRepoSetting(repoPath, COMMIT_SIGNING_FORMAT_KEY, signingFormat),
+        setRepoSetting(repoPath, COMMIT_SIGNING_KEY_KEY, signingKey.trim()),
+        setRepoSetting(repoPath, WATCHER_DEBOUNCE_MS_KEY, watcherDebounce.trim()),
+        setRepoSetting(repoPath, WATCHER_IGNORE_KEY, watcherIgnore),
+        setRepoSetting(repoPath, JJ_MAX_NEW_FILE_SIZE_KEY, maxNewFileSize.trim()),
+        setRepoSetting(repoPath, DIFF_RENAME_THRESHOLD_KEY, renameThreshold.trim()),
+        setRepoSetting(repoPath, DIFF_FIND_COPIES_KEY, findCopies ? "true" : ""),
+        setRepoSetting(repoPath, DIFF_IGNORE_WHITESPACE_KEY, ignoreWhitespace ? "true" : ""),
       ]);
       addToast({
         title: "Settings saved",
@@ -72,7 +145,7 @@ export const RepositorySettingsContent: React.FC<RepositorySettingsContentProps>
       });
       onClose?.();
     } catch (err) {
-      const errorMsg = err instanceof Error ? err.message : String(err);
+      const errorMsg = errorMessage(err);
       setError(`Failed to save settings: ${errorMsg}`);
       addToast({
         title: "Error",
@@ -165,17 +238,118 @@ export const RepositorySettingsContent: React.FC<RepositorySettingsContentProps>
           className="mt-2 w-full px-3 py-2 border rounded-md bg-background text-foreground"
         >
           <option value="">Use Application Default</option>
-          <option value="sonnet">Sonnet</option>
-          <option value="opus">Opus</option>
-          <option value="haiku">Haiku</option>
-          <option value="sonnet[1m]">Sonnet (1M)</option>
-          <option value="opusplan">Opus Plan</option>
+          {models.map((model) => (
+            <option key={model.id} value={model.model_id}>
+              {model.display_name}
+            </option>
+          ))}
         </select>
         <p className="text-sm text-muted-foreground mt-1">
           Default model for new Claude Code sessions in this repository (overrides application default)
         </p>
       </div>
 
+      <div>
+        <Label htmlFor="repo-signing-format">Commit Signing</Label>
+        <select
+          id="repo-signing-format"
+          value={signingFormat}
+          onChange={(e) => setSigningFormat(e.target.value)}
+          className="mt-2 w-full px-3 py-2 border rounded-md bg-background text-foreground"
+        >
+          <option value="">Use Application Default</option>
+          <option value="gpg">GPG</option>
+          <option value="ssh">SSH</option>
+          <option value="none">Don't sign</option>
+        </select>
+        {signingFormat !== "none" && (
+          <Input
+            id="repo-signing-key"
+            value={signingKey}
+            onChange={(e) => setSigningKey(e.target.value)}
+            placeholder={signingFormat === "ssh" ? "~/.ssh/id_ed25519.pub" : "GPG key ID"}
+            className="mt-2 font-mono"
+          />
+        )}
+        <p className="text-sm text-muted-foreground mt-1">
+          Sign commits made from treq; leave the key empty to use git's user.signingkey
+        </p>
+      </div>
+
+      <div>
+        <Label htmlFor="watcher-debounce">File Watcher</Label>
+        <Input
+          id="watcher-debounce"
+          type="number"
+          min={100}
+          max={30000}
+          value={watcherDebounce}
+          onChange={(e) => setWatcherDebounce(e.target.value)}
+          placeholder="1000"
+          className="mt-2"
+        />
+        <p className="text-sm text-muted-foreground mt-1">
+          Milliseconds to wait for file changes to settle before refreshing
+        </p>
+        <Textarea
+          id="watcher-ignore"
+          value={watcherIgnore}
+          onChange={(e) => setWatcherIgnore(e.target.value)}
+          placeholder="e.g., dist/&#10;*.log&#10;coverage/"
+          rows={4}
+          className="font-mono text-sm mt-2"
+        />
+        <p className="text-sm text-muted-foreground mt-1">
+          Changes to these paths are ignored, in addition to .gitignore
+        </p>
+      </div>
+
+      <div>
+        <Label htmlFor="max-new-file-size">Max New File Size</Label>
+        <Input
+          id="max-new-file-size"
+          value={maxNewFileSize}
+          onChange={(e) => setMaxNewFileSize(e.target.value)}
+          placeholder="1MiB"
+          className="mt-2 font-mono"
+        />
+        <p className="text-sm text-muted-foreground mt-1">
+          Largest untracked file jj will snapshot, in bytes or e.g. 20MiB; raise it for repos
+          with big generated files
+        </p>
+      </div>
+
+      <div>
+        <Label htmlFor="diff-rename-threshold">Rename Detection</Label>
+        <Input
+          id="diff-rename-threshold"
+          value={renameThreshold}
+          onChange={(e) => setRenameThreshold(e.target.value)}
+          placeholder="50"
+          className="mt-2 font-mono"
+        />
+        <p className="text-sm text-muted-foreground mt-1">
+          How similar, in percent, a moved file must stay to be shown as a rename in commit and
+          stash diffs; lower it for refactors that move and edit files, or enter "off"
+        </p>
+        <label className="flex items-center gap-2 text-sm mt-2">
+          <input
+            type="checkbox"
+            checked={findCopies}
+            onChange={(e) => setFindCopies(e.target.checked)}
+          />
+          <span>Detect copied files</span>
+        </label>
+        <label className="flex items-center gap-2 text-sm mt-1">
+          <input
+            type="checkbox"
+            checked={ignoreWhitespace}
+            onChange={(e) => setIgnoreWhitespace(e.target.checked)}
+          />
+          <span>Ignore whitespace changes</span>
+        </label>
+      </div>
+
       {error && (
         <div className="text-sm text-destructive">
           {error}
@@ -7,8 +7,27 @@ import { RepositorySettingsContent } from "./RepositorySettingsContent";
 import { useTheme } from "../hooks/useTheme";
 import { useTerminalSettings } from "../hooks/useTerminalSettings";
 import { useToast } from "./ui/toast";
-import { getSetting, setSetting } from "../lib/api";
+import {
+  getSetting,
+  setSetting,
+  getSecret,
+  setSecret,
+  listSessionModels,
+  defaultSessionModel,
+  COMMIT_SIGNING_FORMAT_KEY,
+  COMMIT_SIGNING_KEY_KEY,
+  GITHUB_TOKEN_KEY,
+  GITLAB_TOKEN_KEY,
+  BITBUCKET_TOKEN_KEY,
+  SNAPSHOT_RETENTION_KEY,
+  exportSettings,
+  importSettings,
+  selectSettingsExportPath,
+  selectSettingsImportPath,
+  type SessionModel,
+} from "../lib/api";
 import { Settings, FolderGit2, GitBranch } from "lucide-react";
+import { errorMessage } from "../lib/toast-helpers";
 
 type TabValue = "application" | "repository";
 
@@ -25,6 +44,14 @@ export const SettingsPage: React.FC<SettingsPageProps> = ({
 }) => {
   const [currentTab, setCurrentTab] = useState<TabValue>("repository");
   const [defaultModel, setDefaultModel] = useState<string>("");
+  const [models, setModels] = useState<SessionModel[]>([]);
+  const fallbackModel = defaultSessionModel(models);
+  const [signingFormat, setSigningFormat] = useState<string>("");
+  const [signingKey, setSigningKey] = useState("");
+  const [githubToken, setGithubToken] = useState("");
+  const [gitlabToken, setGitlabToken] = useState("");
+  const [bitbucketToken, setBitbucketToken] = useState("");
+  const [snapshotRetention, setSnapshotRetention] = useState("");
   const [originalFontSize, setOriginalFontSize] = useState<number | null>(null);
   const [localFontSize, setLocalFontSize] = useState<number>(12);
 
@@ -37,6 +64,15 @@ export const SettingsPage: React.FC<SettingsPageProps> = ({
     getSetting("default_model").then((model: string | null) => {
       if (model) setDefaultModel(model);
     });
+    listSessionModels()
+      .then(setModels)
+      .catch((error) => console.error("Failed to load models:", error));
+    getSetting(COMMIT_SIGNING_FORMAT_KEY).then((format) => setSigningFormat(format || ""));
+    getSetting(COMMIT_SIGNING_KEY_KEY).then((key) => setSigningKey(key || ""));
+    getSecret(GITHUB_TOKEN_KEY).then((token) => setGithubToken(token || ""));
+    getSecret(GITLAB_TOKEN_KEY).then((token) => setGitlabToken(token || ""));
+    getSecret(BITBUCKET_TOKEN_KEY).then((token) => setBitbucketToken(token || ""));
+    getSetting(SNAPSHOT_RETENTION_KEY).then((value) => setSnapshotRetention(value || ""));
     // Store original font size and initialize local font size
     setOriginalFontSize(fontSize);
     setLocalFontSize(fontSize);
@@ -45,6 +81,12 @@ export const SettingsPage: React.FC<SettingsPageProps> = ({
   const handleSaveApplicationSettings = async () => {
     try {
       await setSetting("default_model", defaultModel);
+      await setSetting(COMMIT_SIGNING_FORMAT_KEY, signingFormat);
+      await setSetting(COMMIT_SIGNING_KEY_KEY, signingKey.trim());
+      await setSecret(GITHUB_TOKEN_KEY, githubToken.trim());
+      await setSecret(GITLAB_TOKEN_KEY, gitlabToken.trim());
+      await setSecret(BITBUCKET_TOKEN_KEY, bitbucketToken.trim());
+      await setSetting(SNAPSHOT_RETENTION_KEY, snapshotRetention.trim());
       await setFontSize(localFontSize);
 
       addToast({
@@ -55,7 +97,45 @@ export const SettingsPage: React.FC<SettingsPageProps> = ({
     } catch (error) {
       addToast({
         title: "Error",
-        description: error instanceof Error ? error.message : String(error),
+        description: errorMessage(error),
+        type: "error",
+      });
+    }
+  };
+
+  const handleExportSettings = async () => {
+    try {
+      const path = await selectSettingsExportPath();
+      if (!path) return;
+      await exportSettings(path);
+      addToast({
+        title: "Settings Exported",
+        description: `Saved to ${path}`,
+        type: "success",
+      });
+    } catch (error) {
+      addToast({
+        title: "Export Failed",
+        description: errorMessage(error),
+        type: "error",
+      });
+    }
+  };
+
+  const handleImportSettings = async () => {
+    try {
+      const path = await selectSettingsImportPath();
+      if (!path) return;
+      const summary = await importSettings(path);
+      addToast({
+        title: "Settings Imported",
+        description: `${summary.settings.length} settings, ${summary.repos.length} repositories and ${summary.templates} templates imported. Reopen settings to see the changes.`,
+        type: "success",
+      });
+    } catch (error) {
+      addToast({
+        title: "Import Failed",
+        description: errorMessage(error),
         type: "error",
       });
     }
@@ -168,17 +248,123 @@ export const SettingsPage: React.FC<SettingsPageProps> = ({
                         onChange={(e) => setDefaultModel(e.target.value)}
                         className="mt-2 w-full px-3 py-2 border rounded-md bg-background text-foreground"
                       >
-                        <option value="">Default</option>
-                        <option value="sonnet">Sonnet</option>
-                        <option value="opus">Opus</option>
-                        <option value="haiku">Haiku</option>
-                        <option value="sonnet[1m]">Sonnet (1M)</option>
-                        <option value="opusplan">Opus Plan</option>
+                        <option value="">
+                          {fallbackModel ? `Default (${fallbackModel.display_name})` : "Default"}
+                        </option>
+                        {models.map((model) => (
+                          <option key={model.id} value={model.model_id}>
+                            {model.display_name}
+                          </option>
+                        ))}
                       </select>
                       <p className="text-sm text-muted-foreground mt-1">
                         Default model for new Claude Code sessions
                       </p>
                     </div>
+
+                    <div>
+                      <Label htmlFor="snapshot-retention">Discard Snapshots</Label>
+                      <Input
+                        id="snapshot-retention"
+                        type="number"
+                        min={0}
+                        value={snapshotRetention}
+                        onChange={(e) => setSnapshotRetention(e.target.value)}
+                        placeholder="50"
+                        className="mt-2"
+                      />
+                      <p className="text-sm text-muted-foreground mt-1">
+                        Discarded changes are saved to .treq/snapshots first; this many are kept per
+                        repository, 0 turns snapshots off
+                      </p>
+                    </div>
+
+                    <div>
+                      <Label htmlFor="signing-format">Commit Signing</Label>
+                      <select
+                        id="signing-format"
+                        value={signingFormat}
+                        onChange={(e) => setSigningFormat(e.target.value)}
+                        className="mt-2 w-full px-3 py-2 border rounded-md bg-background text-foreground"
+                      >
+                        <option value="">Off</option>
+                        <option value="gpg">GPG</option>
+                        <option value="ssh">SSH</option>
+                      </select>
+                      {signingFormat && (
+                        <Input
+                          id="signing-key"
+                          value={signingKey}
+                          onChange={(e) => setSigningKey(e.target.value)}
+                          placeholder={signingFormat === "ssh" ? "~/.ssh/id_ed25519.pub" : "GPG key ID"}
+                          className="mt-2 font-mono"
+                        />
+                      )}
+                      <p className="text-sm text-muted-foreground mt-1">
+                        Sign commits made from treq; repositories can override this
+                      </p>
+                    </div>
+
+                    <div>
+                      <Label htmlFor="github-token">GitHub Token</Label>
+                      <Input
+                        id="github-token"
+                        type="password"
+                        value={githubToken}
+                        onChange={(e) => setGithubToken(e.target.value)}
+                        placeholder="Use gh CLI login"
+                        className="mt-2 font-mono"
+                      />
+                      <p className="text-sm text-muted-foreground mt-1">
+                        Used to open and check pull requests; needs the repo scope
+                      </p>
+                    </div>
+
+                    <div>
+                      <Label htmlFor="gitlab-token">GitLab Token</Label>
+                      <Input
+                        id="gitlab-token"
+                        type="password"
+                        value={gitlabToken}
+                        onChange={(e) => setGitlabToken(e.target.value)}
+                        placeholder="Use glab CLI login"
+                        className="mt-2 font-mono"
+                      />
+                      <p className="text-sm text-muted-foreground mt-1">
+                        Used to open and check merge requests; needs the api scope
+                      </p>
+                    </div>
+
+                    <div>
+                      <Label htmlFor="bitbucket-token">Bitbucket Token</Label>
+                      <Input
+                        id="bitbucket-token"
+                        type="password"
+                        value={bitbucketToken}
+                        onChange={(e) => setBitbucketToken(e.target.value)}
+                        placeholder="Access token or username:app_password"
+                        className="mt-2 font-mono"
+                      />
+                      <p className="text-sm text-muted-foreground mt-1">
+                        Used to open and check pull requests on bitbucket.org
+                      </p>
+                    </div>
+
+                    <div>
+                      <Label>Sync</Label>
+                      <div className="mt-2 flex gap-2">
+                        <Button variant="outline" size="sm" onClick={handleExportSettings}>
+                          Export Settings
+                        </Button>
+                        <Button variant="outline" size="sm" onClick={handleImportSettings}>
+                          Import Settings
+                        </Button>
+                      </div>
+                      <p className="text-sm text-muted-foreground mt-1">
+                        Copy settings, repository settings and workspace templates to another
+                        machine; tokens are not included
+                      </p>
+                    </div>
                   </div>
                 </TabsContent>
 
@@ -12,11 +12,15 @@ import {
   jjGetConflictedFiles,
   jjGetBranches,
   setWorkspaceTargetBranch,
+  previewWorkspaceTargetBranch,
+  type TargetBranchPreview,
   jjGetChangedFiles,
   createSession,
   checkAndRebaseWorkspaces,
   jjPush,
-  jjGetSyncStatus,
+  requestDestructiveConfirmation,
+  type DestructiveConfirmation,
+  jjGetSyncStatusCached,
 } from "../lib/api";
 import { getStatusBgColor } from "../lib/git-status-colors";
 import { parseJjChangedFiles, type ParsedFileChange } from "../lib/git-utils";
@@ -85,6 +89,7 @@ import { TargetBranchSelector } from "./TargetBranchSelector";
 import { cn } from "../lib/utils";
 import { useTerminalSettings } from "../hooks/useTerminalSettings";
 import type { SessionCreationInfo } from "../types/sessions";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface ShowWorkspaceProps {
   repositoryPath?: string;
@@ -134,9 +139,17 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
     "push" | "merge" | "forcePush" | null
   >(null);
   const [showForcePushDialog, setShowForcePushDialog] = useState(false);
+  const [forcePushConfirmation, setForcePushConfirmation] =
+    useState<DestructiveConfirmation | null>(null);
+  const [targetPreview, setTargetPreview] =
+    useState<TargetBranchPreview | null>(null);
 
   // Sync status state (ahead/behind counts)
-  const [syncStatus, setSyncStatus] = useState<{ ahead: number; behind: number } | null>(null);
+  const [syncStatus, setSyncStatus] = useState<{
+    ahead: number;
+    behind: number;
+    isStale: boolean;
+  } | null>(null);
 
   // Target branch and conflicts state
   const [targetBranch, setTargetBranch] = useState<string | null>(null);
@@ -177,7 +190,7 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
 
     // Fetch README.md
     readFile(`${workingDirectory}/README.md`)
-      .then(setReadmeContent)
+      .then((file) => setReadmeContent(file.kind === "text" ? file.content : null))
       .catch(() => setReadmeContent(null));
 
     jjGetDefaultBranch(effectiveRepoPath)
@@ -220,8 +233,9 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
     if (!path || !branch) return;
 
     try {
-      const [ahead, behind] = await jjGetSyncStatus(path, branch);
-      setSyncStatus({ ahead, behind });
+      const payload = await jjGetSyncStatusCached(path, branch);
+      const [ahead, behind] = payload.data;
+      setSyncStatus({ ahead, behind, isStale: payload.is_stale });
     } catch (error) {
       console.error("Failed to fetch sync status:", error);
       setSyncStatus(null);
@@ -354,10 +368,35 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
     };
   }, [workspace?.id, workspace?.branch_name, targetBranch, effectiveRepoPath, addToast]);
 
+  // Show how the workspace diverges from the new target before switching to it
   const handleTargetBranchSelect = useCallback(
     async (branch: string) => {
       if (branch === targetBranch || !workspace) return;
 
+      try {
+        setTargetPreview(
+          await previewWorkspaceTargetBranch(
+            effectiveRepoPath,
+            workspace.id,
+            branch
+          )
+        );
+      } catch (error) {
+        addToast({
+          title: "Cannot change target branch",
+          description: errorMessage(error),
+          type: "error",
+        });
+      }
+    },
+    [targetBranch, workspace, effectiveRepoPath, addToast]
+  );
+
+  const handleTargetBranchConfirm = useCallback(
+    async (branch: string) => {
+      if (!workspace) return;
+
+      setTargetPreview(null);
       setRebasing(true);
       try {
         const result = await setWorkspaceTargetBranch(
@@ -386,14 +425,14 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
       } catch (error) {
         addToast({
           title: "Rebase failed",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
       } finally {
         setRebasing(false);
       }
     },
-    [targetBranch, workspace, effectiveRepoPath, workingDirectory, addToast]
+    [workspace, effectiveRepoPath, workingDirectory, addToast]
   );
 
   // Helper to get status for a directory entry
@@ -455,7 +494,7 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
       console.error("Push failed:", error);
       addToast({
         title: "Push failed",
-        description: String(error),
+        description: errorMessage(error),
         type: "error",
       });
     } finally {
@@ -463,32 +502,61 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
     }
   }, [workspace, workingDirectory, addToast, fetchSyncStatus]);
 
+  // Use workspace path if available, otherwise use working directory (for home repo)
+  const forcePushPath = workspace?.workspace_path || workingDirectory;
+
+  const handleOpenForcePush = useCallback(async () => {
+    if (!forcePushPath) return;
+    try {
+      // The force push dialog is the confirmation for pushing from the main repo, so
+      // it lists the workspaces that depend on it
+      const confirmation = await requestDestructiveConfirmation(
+        forcePushPath,
+        "force_push"
+      );
+      setForcePushConfirmation(confirmation);
+      setShowForcePushDialog(true);
+    } catch (error) {
+      addToast({
+        title: "Force push failed",
+        description: errorMessage(error),
+        type: "error",
+      });
+    }
+  }, [forcePushPath, addToast]);
+
   const handleForcePush = useCallback(async () => {
-    // Use workspace path if available, otherwise use working directory (for home repo)
-    const pushPath = workspace?.workspace_path || workingDirectory;
-    if (!pushPath) return;
+    if (!forcePushPath || !forcePushConfirmation) return;
 
     _setActionPending("forcePush");
     try {
-      await jjPush(pushPath, true);
+      await jjPush(
+        forcePushPath,
+        true,
+        undefined,
+        undefined,
+        forcePushConfirmation.token
+      );
       addToast({
         title: "Force pushed to remote",
         description: "Changes force pushed successfully",
         type: "success",
       });
-      setShowForcePushDialog(false);
       // Refresh sync status after force push
       fetchSyncStatus();
     } catch (error) {
       addToast({
         title: "Force push failed",
-        description: String(error),
+        description: errorMessage(error),
         type: "error",
       });
     } finally {
+      // The token is single-use, so a retry asks for a new one
+      setShowForcePushDialog(false);
+      setForcePushConfirmation(null);
       _setActionPending(null);
     }
-  }, [workspace, workingDirectory, addToast, fetchSyncStatus]);
+  }, [forcePushPath, forcePushConfirmation, addToast, fetchSyncStatus]);
 
   const handleForceRebase = useCallback(async () => {
     if (!workspace || !targetBranch || !effectiveRepoPath) {
@@ -533,7 +601,7 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
     } catch (error) {
       addToast({
         title: "Force rebase failed",
-        description: error instanceof Error ? error.message : String(error),
+        description: errorMessage(error),
         type: "error",
       });
     } finally {
@@ -605,7 +673,7 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
       } catch (error) {
         addToast({
           title: "Failed to create agent",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
       }
@@ -646,7 +714,7 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
       } catch (error) {
         addToast({
           title: "Failed to create agent",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
         throw error;
@@ -991,7 +1059,13 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
           <div className="flex items-center gap-2">
             {/* Sync status indicator */}
             {syncStatus && (syncStatus.ahead > 0 || syncStatus.behind > 0) && (
-              <div className="flex items-center gap-1 text-xs text-muted-foreground">
+              <div
+                className={cn(
+                  "flex items-center gap-1 text-xs text-muted-foreground",
+                  syncStatus.isStale && "opacity-60"
+                )}
+                title={syncStatus.isStale ? "Could not refresh; may be out of date" : undefined}
+              >
                 {syncStatus.behind > 0 && (
                   <span className="flex items-center">
                     ↓{syncStatus.behind}
@@ -1056,7 +1130,7 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
                 <DropdownMenuItem
                   onSelect={(e) => {
                     e.preventDefault();
-                    setShowForcePushDialog(true);
+                    handleOpenForcePush();
                   }}
                   className="text-destructive focus:text-destructive"
                 >
@@ -1108,6 +1182,52 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
         </div>
       </div>
 
+      {/* Target Branch Change Confirmation Dialog */}
+      <Dialog
+        open={targetPreview !== null}
+        onOpenChange={(open) => !open && setTargetPreview(null)}
+      >
+        <DialogContent>
+          <DialogHeader>
+            <DialogTitle>
+              Change target to {targetPreview?.target_branch}?
+            </DialogTitle>
+            <DialogDescription className="pt-2">
+              {targetPreview &&
+                `${targetPreview.ahead} commit(s) will be rebased onto ${targetPreview.target_branch}, which has ${targetPreview.behind} commit(s) this workspace does not.`}
+              {targetPreview &&
+                !targetPreview.accurate &&
+                " This is a shallow clone missing the shared history, so these counts may be too high."}
+            </DialogDescription>
+          </DialogHeader>
+          {targetPreview && targetPreview.commits_ahead.length > 0 && (
+            <ul className="max-h-48 overflow-y-auto text-sm space-y-1">
+              {targetPreview.commits_ahead.map((commit) => (
+                <li key={commit.hash} className="flex gap-2">
+                  <span className="font-mono text-muted-foreground">
+                    {commit.short_hash}
+                  </span>
+                  <span className="truncate">{commit.message}</span>
+                </li>
+              ))}
+            </ul>
+          )}
+          <div className="flex justify-end gap-2 mt-4">
+            <Button variant="outline" onClick={() => setTargetPreview(null)}>
+              Cancel
+            </Button>
+            <Button
+              onClick={() =>
+                targetPreview &&
+                handleTargetBranchConfirm(targetPreview.target_branch)
+              }
+            >
+              Change target
+            </Button>
+          </div>
+        </DialogContent>
+      </Dialog>
+
       {/* Force Push Confirmation Dialog */}
       <Dialog open={showForcePushDialog} onOpenChange={setShowForcePushDialog}>
         <DialogContent>
@@ -1121,6 +1241,18 @@ export const ShowWorkspace = memo<ShowWorkspaceProps>(function ShowWorkspace({
               action cannot be undone and may cause issues for other
               collaborators who have pulled from this branch.
             </DialogDescription>
+            {forcePushConfirmation &&
+              forcePushConfirmation.workspace_names.length > 0 && (
+                <div className="text-sm text-muted-foreground">
+                  This is the main repository, which these workspaces depend
+                  on:
+                  <ul className="mt-1 list-disc pl-5">
+                    {forcePushConfirmation.workspace_names.map((name) => (
+                      <li key={name}>{name}</li>
+                    ))}
+                  </ul>
+                </div>
+              )}
           </DialogHeader>
           <div className="flex justify-end gap-2 mt-4">
             <Button
@@ -18,7 +18,13 @@ import {
 } from "../ui/tooltip";
 import { Button } from "../ui/button";
 import { cn } from "../../lib/utils";
-import { ptyClose, setSessionModel, getSessionModel } from "../../lib/api";
+import {
+  ptyClose,
+  setSessionModel,
+  getSessionModel,
+  listSessionModels,
+  defaultSessionModel,
+} from "../../lib/api";
 import {
   ChevronDown,
   ChevronUp,
@@ -32,6 +38,7 @@ import { ModelSelector } from "../ModelSelector";
 import { Input } from "../ui/input";
 import { useToast } from "../ui/toast";
 import { MIN_TERMINAL_WIDTH, type ClaudeSessionData } from "./types";
+import { errorMessage } from "../../lib/toast-helpers";
 
 // Claude terminal panel with header
 export interface ClaudeTerminalPanelProps {
@@ -67,6 +74,8 @@ export const ClaudeTerminalPanel = memo<ClaudeTerminalPanelProps>(
     const [searchQuery, setSearchQuery] = useState("");
     const [isResetting, setIsResetting] = useState(false);
     const [sessionModel, setSessionModelState] = useState<string | null>(null);
+    // The registry's default, used while the session has no model of its own
+    const [fallbackModel, setFallbackModel] = useState<string | null>(null);
     const [isChangingModel, setIsChangingModel] = useState(false);
     const [isModelLoaded, setIsModelLoaded] = useState(false);
     const [terminalInstanceKey, setTerminalInstanceKey] = useState(0);
@@ -81,11 +90,12 @@ export const ClaudeTerminalPanel = memo<ClaudeTerminalPanelProps>(
     useEffect(() => {
       const loadModel = async () => {
         try {
-          const model = await getSessionModel(
-            sessionData.repoPath,
-            sessionData.sessionId
-          );
+          const [model, models] = await Promise.all([
+            getSessionModel(sessionData.repoPath, sessionData.sessionId),
+            listSessionModels(),
+          ]);
           setSessionModelState(model);
+          setFallbackModel(defaultSessionModel(models)?.model_id ?? null);
         } catch (error) {
           console.error("Failed to load session model:", error);
         } finally {
@@ -201,7 +211,7 @@ export const ClaudeTerminalPanel = memo<ClaudeTerminalPanelProps>(
       } catch (error) {
         addToast({
           title: "Reset Failed",
-          description: error instanceof Error ? error.message : String(error),
+          description: errorMessage(error),
           type: "error",
         });
       } finally {
@@ -225,7 +235,7 @@ export const ClaudeTerminalPanel = memo<ClaudeTerminalPanelProps>(
         } catch (error) {
           addToast({
             title: "Failed to change model",
-            description: error instanceof Error ? error.message : String(error),
+            description: errorMessage(error),
             type: "error",
           });
           setIsChangingModel(false);
@@ -241,14 +251,14 @@ export const ClaudeTerminalPanel = memo<ClaudeTerminalPanelProps>(
         await handleReset({ silent: true });
         addToast({
           title: "Terminal Restarting",
-          description: `Using model: ${sessionModel || "default"}`,
+          description: `Using model: ${sessionModel || fallbackModel || "default"}`,
           type: "info",
         });
         setIsChangingModel(false);
         setPendingModelReset(false);
       };
       performReset();
-    }, [pendingModelReset, handleReset, sessionModel, addToast]);
+    }, [pendingModelReset, handleReset, sessionModel, fallbackModel, addToast]);
 
     // Build Claude command with optional pending prompt
     const permissionModeArg = sessionData.permissionMode === 'plan'
@@ -259,8 +269,9 @@ export const ClaudeTerminalPanel = memo<ClaudeTerminalPanelProps>(
 
     // Add permission mode and model flags first
     autoCommand += permissionModeArg;
-    if (sessionModel) {
-      autoCommand += ` --model="${sessionModel}"`;
+    const launchModel = sessionModel || fallbackModel;
+    if (launchModel) {
+      autoCommand += ` --model="${launchModel}"`;
     }
 
     // If there's a pending prompt, add it as a positional argument after --
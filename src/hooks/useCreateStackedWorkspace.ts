@@ -9,6 +9,7 @@ import {
 } from "../lib/api";
 import { generateStackedIntent, generateStackedBranchName } from "../lib/utils";
 import { useToast } from "../components/ui/toast";
+import { errorMessage } from "../lib/toast-helpers";
 
 interface CreateStackedWorkspaceOptions {
   repoPath: string;
@@ -101,7 +102,7 @@ export function useCreateStackedWorkspace() {
         // Step 8: Return the workspace ID
         return workspaceId;
       } catch (error) {
-        const errorMsg = error instanceof Error ? error.message : String(error);
+        const errorMsg = errorMessage(error);
         addToast({
           title: "Failed to create stacked workspace",
           description: errorMsg,
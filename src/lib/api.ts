@@ -1,6 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
 
 export interface Workspace {
   id: number;
@@ -21,6 +21,20 @@ export interface Session {
   created_at: string;
   last_accessed: string;
   model?: string | null;
+  sort_key: number;
+}
+
+/** A named terminal tab of a session */
+export interface SessionTab {
+  id: number;
+  session_id: number;
+  title: string;
+  cwd: string | null;
+  last_command: string | null;
+  position: number;
+  /** Id of the PTY backing the tab, stable across restarts */
+  pty_session_id: string;
+  created_at: string;
 }
 
 export interface WorkspaceInfo {
@@ -31,17 +45,50 @@ export interface WorkspaceInfo {
 }
 
 // JJ Diff Types (no staging concept - working copy only)
+/** Indices into `JjDiffHunk.lines` of a removed line and the added line replacing it */
+export interface DiffLinePair {
+  deletion: number;
+  addition: number;
+}
+
 export interface JjDiffHunk {
   id: string;
   header: string;
   lines: string[];
   patch: string;
+  line_pairs?: DiffLinePair[];
+  /** e.g. "3 added, 2 removed in function foo" */
+  summary?: string;
+  /** Stable across rebases that only shift line numbers; keys hunk review state */
+  content_hash?: string;
+}
+
+/** Why a file's diff was not computed; all false when it was */
+export interface SkippedDiff {
+  is_binary: boolean;
+  is_lfs: boolean;
+  too_large: boolean;
+}
+
+export interface FileHunks extends SkippedDiff {
+  hunks: JjDiffHunk[];
+}
+
+export interface SubmoduleChange {
+  old_commit: string | null;
+  new_commit: string | null;
 }
 
 export interface JjFileChange {
   path: string;
   status: string;
   previous_path?: string | null;
+  /** Set for submodule entries in plain git repositories */
+  submodule?: SubmoduleChange | null;
+  /** Staged change in plain git repositories as a porcelain letter, null when nothing is staged */
+  index_status?: string | null;
+  /** Unstaged change in plain git repositories, "?" for untracked files */
+  worktree_status?: string | null;
 }
 
 export interface JjFileLines {
@@ -67,6 +114,8 @@ export interface JjLogCommit {
   bookmarks: string[];
   insertions: number;
   deletions: number;
+  /** Epoch milliseconds of the commit time, for ordering */
+  sort_key: number;
 }
 
 export interface JjLogResult {
@@ -75,9 +124,28 @@ export interface JjLogResult {
   workspace_branch: string;
 }
 
+export interface GraphEdge {
+  child_id: string;
+  parent_id: string;
+}
+
+/** Payload of the `graph-delta` event emitted after operations that move commits */
+export interface GraphDelta {
+  workspace_path: string;
+  added: JjLogCommit[];
+  removed: string[];
+  updated: JjLogCommit[];
+  added_edges: GraphEdge[];
+  removed_edges: GraphEdge[];
+  target_branch: string;
+  workspace_branch: string;
+}
+
 export interface JjCommitsAhead {
   commits: JjLogCommit[];
   total_count: number;
+  /** False when a shallow clone lacks the merge base, so the range over-counts */
+  accurate: boolean;
 }
 
 export interface JjMergeResult {
@@ -88,6 +156,33 @@ export interface JjMergeResult {
   merge_commit_id: string | null;
 }
 
+/** A pre-merge gate that stopped a merge */
+export type MergeGateFailure =
+  | { gate: "dirty_working_copy"; files: string[] }
+  | { gate: "target_moved"; expected: string; actual: string }
+  | { gate: "unknown_check"; task: string }
+  | {
+      gate: "check_failed";
+      task: string;
+      exit_code: number | null;
+      output: string;
+    };
+
+export interface MergeBlocked {
+  failed: MergeGateFailure[];
+}
+
+/** Merge result; `blocked` is set when a pre-merge gate failed and nothing was merged */
+export interface MergeAttempt extends JjMergeResult {
+  blocked: MergeBlocked | null;
+}
+
+/** Checks run before merging a workspace; `tasks` are names of configured tasks */
+export interface MergeCheckConfig {
+  tasks: string[];
+  require_clean: boolean;
+}
+
 export interface JjFileDiff {
   path: string;
   hunks: JjDiffHunk[];
@@ -123,6 +218,33 @@ export const getWorkspaces = (repo_path: string): Promise<Workspace[]> =>
 export const rebuildWorkspaces = (repo_path: string): Promise<Workspace[]> =>
   invoke("rebuild_workspaces", { repoPath: repo_path });
 
+export type RegistryMismatchKind =
+  | "missing_directory"
+  | "unregistered_directory"
+  | "jj_orphan"
+  | "not_in_jj";
+
+export type RegistryFix = "register" | "forget" | "delete_row" | "reattach";
+
+export interface RegistryMismatch {
+  kind: RegistryMismatchKind;
+  workspace_name: string;
+  workspace_path: string;
+  workspace_id: number | null;
+  suggested_fix: RegistryFix;
+}
+
+export const reconcileWorkspaceRegistry = (
+  repo_path: string
+): Promise<RegistryMismatch[]> =>
+  invoke("reconcile_workspace_registry", { repoPath: repo_path });
+
+export const applyWorkspaceRegistryFix = (
+  repo_path: string,
+  mismatch: RegistryMismatch
+): Promise<void> =>
+  invoke("apply_workspace_registry_fix", { repoPath: repo_path, mismatch });
+
 export const addWorkspaceToDb = (
   repo_path: string,
   workspace_name: string,
@@ -137,7 +259,9 @@ export const createWorkspace = (
   branch_name: string,
   new_branch: boolean,
   source_branch?: string,
-  metadata?: string
+  metadata?: string,
+  template?: string,
+  opId?: string
 ): Promise<number> =>
   invoke("create_workspace", {
     repoPath: repo_path,
@@ -145,8 +269,145 @@ export const createWorkspace = (
     newBranch: new_branch,
     sourceBranch: source_branch ?? null,
     metadata: metadata ?? null,
+    template: template ?? null,
+    opId,
+  });
+
+export interface WorkspaceTemplate {
+  name: string;
+  inclusion_patterns: string[];
+  post_create_commands: string[];
+  target_branch: string | null;
+  env: Record<string, string>;
+}
+
+export interface PostCreateCommandResult {
+  command: string;
+  success: boolean;
+  output: string;
+}
+
+/** Payload of the `workspace-post-create` event */
+export interface WorkspacePostCreateResult {
+  workspace_id: number;
+  template: string;
+  results: PostCreateCommandResult[];
+}
+
+export const listWorkspaceTemplates = (repo_path: string): Promise<WorkspaceTemplate[]> =>
+  invoke("list_workspace_templates", { repoPath: repo_path });
+
+export const saveWorkspaceTemplate = (
+  repo_path: string,
+  template: WorkspaceTemplate
+): Promise<WorkspaceTemplate[]> =>
+  invoke("save_workspace_template", { repoPath: repo_path, template });
+
+export const deleteWorkspaceTemplate = (
+  repo_path: string,
+  name: string
+): Promise<WorkspaceTemplate[]> =>
+  invoke("delete_workspace_template", { repoPath: repo_path, name });
+
+// Automation hooks API
+
+export type HookEvent = "workspace-created" | "commit-made" | "rebase-conflict" | "merge-landed";
+
+/** Shell scripts run for each event, in order */
+export type HookConfig = Partial<Record<HookEvent, string[]>>;
+
+export interface JournalEntry {
+  id: number;
+  kind: string;
+  name: string;
+  workspace_id: number | null;
+  command: string | null;
+  success: boolean;
+  output: string;
+  duration_ms: number | null;
+  created_at: string;
+}
+
+/** Payload of the `hook-finished` event */
+export interface HookRunResult {
+  repo_path: string;
+  event: HookEvent;
+  entry: JournalEntry;
+}
+
+export const getAutomationHooks = (repo_path: string): Promise<HookConfig> =>
+  invoke("get_automation_hooks", { repoPath: repo_path });
+
+export const setAutomationHooks = (repo_path: string, hooks: HookConfig): Promise<void> =>
+  invoke("set_automation_hooks", { repoPath: repo_path, hooks });
+
+export const getOperationJournal = (repo_path: string, limit?: number): Promise<JournalEntry[]> =>
+  invoke("get_operation_journal", { repoPath: repo_path, limit: limit ?? null });
+
+export const hookFinishedListen = (callback: (result: HookRunResult) => void) =>
+  listen<HookRunResult>("hook-finished", (event) => callback(event.payload));
+
+export interface TaskDefinition {
+  command: string;
+  /** Directory relative to the workspace root */
+  cwd?: string | null;
+  env?: Record<string, string>;
+}
+
+/** Tasks by name */
+export type TaskConfig = Record<string, TaskDefinition>;
+
+/** Payload of the `task-output` event, one per output line */
+export interface TaskOutput {
+  run_id: string;
+  workspace_path: string;
+  task_name: string;
+  stream: "stdout" | "stderr";
+  line: string;
+}
+
+/** Payload of the `task-finished` event */
+export interface TaskFinished {
+  run_id: string;
+  repo_path: string;
+  workspace_path: string;
+  task_name: string;
+  exit_code: number | null;
+  cancelled: boolean;
+  entry: JournalEntry;
+}
+
+export const listTasks = (repo_path: string): Promise<TaskConfig> =>
+  invoke("list_tasks", { repoPath: repo_path });
+
+export const setTasks = (repo_path: string, tasks: TaskConfig): Promise<void> =>
+  invoke("set_tasks", { repoPath: repo_path, tasks });
+
+/** Starts a task in the background and resolves to its run id; output arrives via listeners */
+export const runTask = (workspace_path: string, task_name: string): Promise<string> =>
+  invoke("run_task", { workspacePath: workspace_path, taskName: task_name });
+
+/** Kills a running task; resolves to false when the run already finished */
+export const cancelTask = (run_id: string): Promise<boolean> =>
+  invoke("cancel_task", { runId: run_id });
+
+export const getTaskHistory = (
+  repo_path: string,
+  task_name?: string,
+  limit?: number
+): Promise<JournalEntry[]> =>
+  invoke("get_task_history", {
+    repoPath: repo_path,
+    taskName: task_name ?? null,
+    limit: limit ?? null,
   });
 
+export const taskOutputListen = (callback: (output: TaskOutput) => void) =>
+  listen<TaskOutput>("task-output", (event) => callback(event.payload));
+
+export const taskFinishedListen = (callback: (finished: TaskFinished) => void) =>
+  listen<TaskFinished>("task-finished", (event) => callback(event.payload));
+
 export const deleteWorkspaceFromDb = (repo_path: string, id: number): Promise<void> =>
   invoke("delete_workspace_from_db", { repoPath: repo_path, id });
 
@@ -161,6 +422,87 @@ export const deleteWorkspace = (
     id,
   });
 
+/** Workspace whose directory was removed, kept as its branch plus a patch of uncommitted changes */
+export interface ArchivedWorkspace {
+  id: number;
+  workspace_name: string;
+  branch_name: string;
+  target_branch: string | null;
+  metadata: string | null;
+  base_commit: string;
+  patch_file: string | null;
+  file_count: number;
+  archived_at: string;
+}
+
+export interface UnarchiveResult {
+  workspace_id: number;
+  workspace_path: string;
+  patch_applied: boolean;
+  /** Kept patch path when the changes no longer apply */
+  unapplied_patch: string | null;
+}
+
+export const archiveWorkspace = (
+  repo_path: string,
+  workspace_id: number
+): Promise<ArchivedWorkspace> =>
+  invoke("archive_workspace", { repoPath: repo_path, workspaceId: workspace_id });
+
+export const unarchiveWorkspace = (
+  repo_path: string,
+  archive_id: number
+): Promise<UnarchiveResult> =>
+  invoke("unarchive_workspace", { repoPath: repo_path, archiveId: archive_id });
+
+export const listArchivedWorkspaces = (repo_path: string): Promise<ArchivedWorkspace[]> =>
+  invoke("list_archived_workspaces", { repoPath: repo_path });
+
+export interface WorkspaceDiskUsage {
+  workspace_id: number | null;
+  workspace_name: string;
+  workspace_path: string;
+  total_bytes: number;
+  file_count: number;
+  shared_bytes: number;
+  suggest_archive: boolean;
+}
+
+export interface CachedPayload<T> {
+  data: T;
+  computed_at: string;
+  max_age_ms: number;
+  is_stale: boolean;
+}
+
+/** Size of each workspace directory, largest first; cached for ten minutes */
+export const getWorkspaceDiskUsage = (
+  repo_path: string
+): Promise<CachedPayload<WorkspaceDiskUsage[]>> =>
+  invoke("get_workspace_disk_usage", { repoPath: repo_path });
+
+export interface CleanablePath {
+  path: string;
+  total_bytes: number;
+  file_count: number;
+}
+
+export interface CleanResult {
+  removed: string[];
+  freed_bytes: number;
+}
+
+/** Gitignored directories (node_modules, target, dist...) large enough to be worth deleting */
+export const listCleanablePaths = (workspace_path: string): Promise<CleanablePath[]> =>
+  invoke("list_cleanable_paths", { workspacePath: workspace_path });
+
+/** Delete paths relative to the workspace; refuses anything outside it */
+export const cleanPaths = (workspace_path: string, paths: string[]): Promise<CleanResult> =>
+  invoke("clean_paths", { workspacePath: workspace_path, paths });
+
+export const deleteArchivedWorkspace = (repo_path: string, archive_id: number): Promise<void> =>
+  invoke("delete_archived_workspace", { repoPath: repo_path, archiveId: archive_id });
+
 export const cleanupStaleWorkspaces = (repo_path: string): Promise<void> =>
   invoke("cleanup_stale_workspaces", { repoPath: repo_path });
 
@@ -184,12 +526,39 @@ export const getSettingsBatch = (keys: string[]): Promise<Record<string, string
 export const setSetting = (key: string, value: string): Promise<void> =>
   invoke("set_setting", { key, value });
 
+/** Read a secret from the OS keychain (or the encrypted fallback file) */
+export const getSecret = (key: string): Promise<string | null> =>
+  invoke("get_secret", { key });
+
+/** Store a secret; an empty value deletes it */
+export const setSecret = (key: string, value: string): Promise<void> =>
+  invoke("set_secret", { key, value });
+
+export const deleteSecret = (key: string): Promise<void> =>
+  invoke("delete_secret", { key });
+
 export const getRepoSetting = (repo_path: string, key: string): Promise<string | null> =>
   invoke("get_repo_setting", { repoPath: repo_path, key });
 
 export const setRepoSetting = (repo_path: string, key: string, value: string): Promise<void> =>
   invoke("set_repo_setting", { repoPath: repo_path, key, value });
 
+export interface SettingsImportSummary {
+  settings: string[];
+  repos: string[];
+  templates: number;
+  /** Tokens and machine-specific keys found in the file, which are never imported */
+  skipped: string[];
+}
+
+/** Write global settings, repo settings and workspace templates to a JSON file; tokens are left out */
+export const exportSettings = (destPath: string): Promise<void> =>
+  invoke("export_settings", { destPath });
+
+/** Apply a file written by exportSettings on top of the current settings */
+export const importSettings = (srcPath: string): Promise<SettingsImportSummary> =>
+  invoke("import_settings", { srcPath });
+
 // Editor Apps API
 export interface EditorAppsResponse {
   cursor: boolean;
@@ -234,10 +603,92 @@ export const jjSquashToWorkspace = (
 export const jjGetChangedFiles = (workspace_path: string): Promise<JjFileChange[]> =>
   invoke("jj_get_changed_files", { workspacePath: workspace_path });
 
+export interface ChangedFileStats extends JjFileChange {
+  insertions: number;
+  deletions: number;
+  /** Line counts are zero for binary files */
+  is_binary: boolean;
+}
+
+export interface ChangedFilesWithStats {
+  files: ChangedFileStats[];
+  total_insertions: number;
+  total_deletions: number;
+}
+
+/** Changed files of a plain git worktree with "+N −M" line counts, in one call */
+export const gitGetChangedFilesWithStats = (
+  workspace_path: string
+): Promise<ChangedFilesWithStats> =>
+  invoke("git_get_changed_files_with_stats", { workspacePath: workspace_path });
+
+export interface DiffHunkHeader {
+  id: string;
+  header: string;
+  additions: number;
+  deletions: number;
+  line_count: number;
+}
+
+export interface FileHunkHeaders extends SkippedDiff {
+  headers: DiffHunkHeader[];
+}
+
+/** Hunk headers of a file without their lines; fetch each body with gitGetHunkBody */
+export const gitGetFileHunkHeaders = (
+  workspace_path: string,
+  file_path: string
+): Promise<FileHunkHeaders> =>
+  invoke("git_get_file_hunk_headers", { workspacePath: workspace_path, filePath: file_path });
+
+export const gitGetHunkBody = (
+  workspace_path: string,
+  file_path: string,
+  hunk_header: string
+): Promise<JjDiffHunk> =>
+  invoke("git_get_hunk_body", {
+    workspacePath: workspace_path,
+    filePath: file_path,
+    hunkHeader: hunk_header,
+  });
+
+/**
+ * Revert one hunk (`JjDiffHunk.patch`) of a file in a git worktree; staged changes are kept.
+ * In a main repo with workspaces, pass a token from `requestDestructiveConfirmation`.
+ */
+export const gitDiscardHunk = (
+  workspace_path: string,
+  file_path: string,
+  patch: string,
+  confirm_token?: string
+): Promise<void> =>
+  invoke("git_discard_hunk", {
+    workspacePath: workspace_path,
+    filePath: file_path,
+    patch,
+    confirmToken: confirm_token,
+  });
+
+/** Revert only the selected lines of a hunk, as indices into `JjDiffHunk.lines` */
+export const gitDiscardSelectedLines = (
+  workspace_path: string,
+  file_path: string,
+  patch: string,
+  line_indices: number[],
+  confirm_token?: string
+): Promise<void> =>
+  invoke("git_discard_selected_lines", {
+    workspacePath: workspace_path,
+    filePath: file_path,
+    patch,
+    lineIndices: line_indices,
+    confirmToken: confirm_token,
+  });
+
 export const jjGetFileHunks = (
   workspace_path: string,
   file_path: string
-): Promise<JjDiffHunk[]> =>
+): Promise<FileHunks> =>
   invoke("jj_get_file_hunks", {
     workspacePath: workspace_path,
     filePath: file_path,
@@ -258,6 +709,7 @@ export const jjGetFileLines = (
     endLine,
   });
 
+/** Discards the file's changes; they are saved to a snapshot first */
 export const jjRestoreFile = (
   workspace_path: string,
   file_path: string
@@ -267,21 +719,84 @@ export const jjRestoreFile = (
     filePath: file_path,
   });
 
-export const jjRestoreAll = (workspace_path: string): Promise<string> =>
-  invoke("jj_restore_all", { workspacePath: workspace_path });
+/** Discards all changes; they are saved to a snapshot first */
+/** In a main repo with workspaces, pass a token from `requestDestructiveConfirmation` */
+export const jjRestoreAll = (
+  workspace_path: string,
+  confirmToken?: string
+): Promise<string> =>
+  invoke("jj_restore_all", { workspacePath: workspace_path, confirmToken });
+
+// Safety snapshots API
+/** Global setting: snapshots kept per repository; "0" stops taking them */
+export const SNAPSHOT_RETENTION_KEY = "snapshot_retention";
+
+/** Patch of uncommitted changes saved before they were discarded */
+export interface Snapshot {
+  id: number;
+  workspace_id: number | null;
+  workspace_path: string;
+  operation: string;
+  base_commit: string;
+  patch_file: string;
+  file_count: number;
+  size_bytes: number;
+  created_at: string;
+}
+
+export const listSnapshots = (repo_path: string): Promise<Snapshot[]> =>
+  invoke("list_snapshots", { repoPath: repo_path });
+
+/** Re-applies the patch; fails without changes if the files were edited since */
+export const restoreSnapshot = (repo_path: string, id: number): Promise<Snapshot> =>
+  invoke("restore_snapshot", { repoPath: repo_path, id });
+
+export const deleteSnapshot = (repo_path: string, id: number): Promise<void> =>
+  invoke("delete_snapshot", { repoPath: repo_path, id });
 
 export const jjIsWorkspace = (repo_path: string): Promise<boolean> =>
   invoke("jj_is_workspace", { repoPath: repo_path });
 
+/** A client-side git hook that rejected a commit */
+export interface HookFailure {
+  hook: string;
+  exit_code: number | null;
+  /** Stdout followed by stderr */
+  output: string;
+}
+
+export interface CommitAttempt {
+  message: string;
+  hook_failure: HookFailure | null;
+}
+
+/** Runs the pre-commit and commit-msg hooks first unless `skipHooks` is set */
 export const jjCommit = (
   workspace_path: string,
-  message: string
-): Promise<string> =>
+  message: string,
+  skipHooks?: boolean
+): Promise<CommitAttempt> =>
   invoke("jj_commit", {
     workspacePath: workspace_path,
     message,
+    skipHooks: skipHooks ?? false,
   });
 
+/** Rewrite the message of any mutable commit, e.g. "@-" or a change id */
+export const jjDescribe = (
+  workspace_path: string,
+  revision: string,
+  message: string
+): Promise<string> =>
+  invoke("jj_describe", { workspacePath: workspace_path, revision, message });
+
+export const jjGetCommitMessage = (workspace_path: string, revision: string): Promise<string> =>
+  invoke("jj_get_commit_message", { workspacePath: workspace_path, revision });
+
+/** Undo a landed change with an inverse commit; success is false when it has conflicts */
+export const jjBackout = (workspace_path: string, revision: string): Promise<JjRebaseResult> =>
+  invoke("jj_backout", { workspacePath: workspace_path, revision });
+
 export const jjSplit = (
   workspace_path: string,
   message: string,
@@ -295,11 +810,13 @@ export const jjSplit = (
 
 export const jjRebaseOnto = (
   workspace_path: string,
-  target_branch: string
+  target_branch: string,
+  opId?: string
 ): Promise<JjRebaseResult> =>
   invoke("jj_rebase_onto", {
     workspacePath: workspace_path,
     targetBranch: target_branch,
+    opId,
   });
 
 export const jjGetConflictedFiles = (
@@ -310,6 +827,26 @@ export const jjGetConflictedFiles = (
 export const jjGetDefaultBranch = (repo_path: string): Promise<string> =>
   invoke("jj_get_default_branch", { repoPath: repo_path });
 
+/** Re-query the remote's default branch, e.g. after a fetch. Returns the branch. */
+export const refreshDefaultBranch = (repo_path: string): Promise<string> =>
+  invoke("refresh_default_branch", { repoPath: repo_path });
+
+/** Payload of the `default-branch-changed` event */
+export interface DefaultBranchChanged {
+  repo_path: string;
+  previous: string;
+  current: string;
+  /** Workspaces moved from the old target branch to the new one */
+  retargeted_workspace_ids: number[];
+}
+
+export const defaultBranchChangedListen = (
+  callback: (payload: DefaultBranchChanged) => void
+) =>
+  listen<DefaultBranchChanged>("default-branch-changed", (event) =>
+    callback(event.payload)
+  );
+
 export const jjGetCurrentBranch = (workspace_path: string): Promise<string> =>
   invoke("jj_get_current_branch", { workspacePath: workspace_path });
 
@@ -334,12 +871,27 @@ export interface BookmarkTrackingResult {
 }
 
 export const jjTrackWorkspaceBookmarks = (
-  repo_path: string
+  repo_path: string,
+  remote?: string
 ): Promise<BookmarkTrackingResult> =>
-  invoke("jj_track_workspace_bookmarks", { repoPath: repo_path });
+  invoke("jj_track_workspace_bookmarks", { repoPath: repo_path, remote });
 
-export const jjPush = (workspace_path: string, force?: boolean): Promise<string> =>
-  invoke("jj_push", { workspacePath: workspace_path, force: force ?? false });
+/** Pushes to `remote`, or the repo's default remote ("origin" when it exists) */
+/** Force pushes from a main repo with workspaces need a `confirmToken` */
+export const jjPush = (
+  workspace_path: string,
+  force?: boolean,
+  remote?: string,
+  opId?: string,
+  confirmToken?: string
+): Promise<string> =>
+  invoke("jj_push", {
+    workspacePath: workspace_path,
+    force: force ?? false,
+    remote,
+    opId,
+    confirmToken,
+  });
 
 export interface SyncStatus {
   ahead: number;
@@ -349,14 +901,61 @@ export interface SyncStatus {
 export const jjGetSyncStatus = (workspace_path: string, branch_name: string): Promise<[number, number]> =>
   invoke("jj_get_sync_status", { workspacePath: workspace_path, branchName: branch_name });
 
-export const jjGitFetch = (repo_path: string): Promise<string> =>
-  invoke("jj_git_fetch", { repoPath: repo_path });
+/** Sync status reused for a minute; stale when the last refresh failed */
+export const jjGetSyncStatusCached = (
+  workspace_path: string,
+  branch_name: string
+): Promise<CachedPayload<[number, number]>> =>
+  invoke("jj_get_sync_status_cached", { workspacePath: workspace_path, branchName: branch_name });
+
+export const jjGitFetch = (repo_path: string, remote?: string, opId?: string): Promise<string> =>
+  invoke("jj_git_fetch", { repoPath: repo_path, remote, opId });
 
 export const jjGitFetchBackground = (repo_path: string): Promise<void> =>
   invoke("jj_git_fetch_background", { repoPath: repo_path });
 
-export const jjPull = (workspace_path: string): Promise<string> =>
-  invoke("jj_pull", { workspacePath: workspace_path });
+export const jjPull = (workspace_path: string, remote?: string, opId?: string): Promise<string> =>
+  invoke("jj_pull", { workspacePath: workspace_path, remote, opId });
+
+/** Stop a push, pull, fetch, clone, rebase or merge started with `opId`; false once it finished */
+export const cancelOperation = (opId: string): Promise<boolean> =>
+  invoke("cancel_operation", { opId });
+
+export type OperationKind =
+  | "push"
+  | "pull"
+  | "fetch"
+  | "clone"
+  | "rebase"
+  | "merge"
+  | "workspace_create"
+  | "task";
+
+export interface OperationProgress {
+  op_id: string;
+  kind: OperationKind;
+  target: string;
+  phase: string;
+  percent: number | null;
+  current: number | null;
+  total: number | null;
+}
+
+export interface OperationFinished {
+  op_id: string;
+  kind: OperationKind;
+  target: string;
+  success: boolean;
+  cancelled: boolean;
+  error: string | null;
+  duration_ms: number;
+}
+
+export const operationProgressListen = (callback: (progress: OperationProgress) => void) =>
+  listen<OperationProgress>("operation-progress", (event) => callback(event.payload));
+
+export const operationFinishedListen = (callback: (finished: OperationFinished) => void) =>
+  listen<OperationFinished>("operation-finished", (event) => callback(event.payload));
 
 export interface BranchStatus {
   local_exists: boolean;
@@ -365,11 +964,26 @@ export interface BranchStatus {
   remote_ref?: string;   // Full remote ref (e.g., "origin/branch") if remote exists
 }
 
+/** Without `remote`, the default remote is checked first, then the others */
 export const checkBranchExists = (
   repo_path: string,
-  branch_name: string
+  branch_name: string,
+  remote?: string
 ): Promise<BranchStatus> =>
-  invoke("jj_check_branch_exists", { repoPath: repo_path, branchName: branch_name });
+  invoke("jj_check_branch_exists", { repoPath: repo_path, branchName: branch_name, remote });
+
+export interface BranchNameSuggestion {
+  branch_name: string;
+  /** Pattern applied to the slug, before any -2, -3 suffix */
+  base_name: string;
+  collided: boolean;
+}
+
+export const suggestWorkspaceBranchName = (
+  repo_path: string,
+  text: string
+): Promise<BranchNameSuggestion> =>
+  invoke("suggest_workspace_branch_name", { repoPath: repo_path, text });
 
 export const jjGetLog = (
   workspacePath: string,
@@ -378,9 +992,89 @@ export const jjGetLog = (
 ): Promise<JjLogResult> =>
   invoke("jj_get_log", { workspacePath, targetBranch, isHomeRepo: isHomeRepo ?? null });
 
+/** An entry in jj's operation log */
+export interface JjOperation {
+  id: string;
+  description: string;
+  /** When the operation finished, RFC3339 UTC */
+  time: string;
+  user: string;
+  is_current: boolean;
+}
+
+/** Most recent operations first; `limit` defaults to 50 */
+export const jjOpLog = (workspace_path: string, limit?: number): Promise<JjOperation[]> =>
+  invoke("jj_op_log", { workspacePath: workspace_path, limit: limit ?? null });
+
+/** Undo the most recent operation; calling it again steps further back */
+export const jjOpUndo = (workspace_path: string): Promise<string> =>
+  invoke("jj_op_undo", { workspacePath: workspace_path });
+
+export const jjOpRestore = (workspace_path: string, op_id: string): Promise<string> =>
+  invoke("jj_op_restore", { workspacePath: workspace_path, opId: op_id });
+
+export type JjErrorKind =
+  | "already_initialized"
+  | "not_git_repository"
+  | "init_failed"
+  | "config_error"
+  | "workspace_not_found"
+  | "git_workspace_error"
+  | "io_error"
+  | "snapshot_file_too_large"
+  | "conflict_detected"
+  | "immutable_commit"
+  | "bookmark_not_found"
+  | "revset_evaluation_error"
+  | "concurrent_operation";
+
+/** Error rejected by jj commands; `path`, `size` and `limit` are set for `snapshot_file_too_large` */
+export interface JjCommandError {
+  kind: JjErrorKind;
+  message: string;
+  path?: string;
+  size?: number;
+  limit?: number;
+}
+
+export const isJjError = (error: unknown): error is JjCommandError =>
+  typeof error === "object" &&
+  error !== null &&
+  typeof (error as JjCommandError).kind === "string" &&
+  typeof (error as JjCommandError).message === "string";
+
+/** Repo setting passed to jj as `snapshot.max-new-file-size`, in bytes or e.g. "20MiB" */
+export const JJ_MAX_NEW_FILE_SIZE_KEY = "jj_max_new_file_size";
+
+/** Repo setting: rename similarity threshold in percent for commit and stash diffs, or "off" */
+export const DIFF_RENAME_THRESHOLD_KEY = "diff_rename_threshold";
+
+/** Repo setting: "true" to detect copied files in commit and stash diffs */
+export const DIFF_FIND_COPIES_KEY = "diff_find_copies";
+
+/** Repo setting: "true" to ignore whitespace changes in commit and stash diffs */
+export const DIFF_IGNORE_WHITESPACE_KEY = "diff_ignore_whitespace";
+
 export const jjInit = (repo_path: string): Promise<string> =>
   invoke("jj_init", { repoPath: repo_path });
 
+/** An option in the repo's jj config layer; `value` is TOML, so strings keep their quotes */
+export interface JjConfigEntry {
+  key: string;
+  value: string;
+}
+
+/** Options set in `.jj/repo/config.toml` */
+export const getJjConfig = (repo_path: string): Promise<JjConfigEntry[]> =>
+  invoke("get_jj_config", { repoPath: repo_path });
+
+/** Set a repo-level jj option, e.g. `snapshot.max-new-file-size`; an empty value removes it */
+export const setJjConfig = (
+  repo_path: string,
+  key: string,
+  value: string
+): Promise<JjConfigEntry[]> => invoke("set_jj_config", { repoPath: repo_path, key, value });
+
 export const jjGetCommitsAhead = (
   workspacePath: string,
   targetBranch: string
@@ -388,18 +1082,43 @@ export const jjGetCommitsAhead = (
   invoke("jj_get_commits_ahead", { workspacePath, targetBranch });
 
 export const jjGetMergeDiff = (
+  repoPath: string,
   workspacePath: string,
   targetBranch: string
 ): Promise<JjRevisionDiff> =>
-  invoke("jj_get_merge_diff", { workspacePath, targetBranch });
+  invoke("jj_get_merge_diff", { repoPath, workspacePath, targetBranch });
 
 export const jjCreateMerge = (
   workspacePath: string,
   workspaceBranch: string,
   targetBranch: string,
-  message: string
-): Promise<JjMergeResult> =>
-  invoke("jj_create_merge", { workspacePath, workspaceBranch, targetBranch, message });
+  message: string,
+  expectedTargetCommit?: string,
+  opId?: string
+): Promise<MergeAttempt> =>
+  invoke("jj_create_merge", {
+    workspacePath,
+    workspaceBranch,
+    targetBranch,
+    message,
+    expectedTargetCommit,
+    opId,
+  });
+
+/** Commit the target branch points at, to detect it moving before a merge */
+export const getMergeTargetCommit = (
+  workspacePath: string,
+  targetBranch: string
+): Promise<string> =>
+  invoke("get_merge_target_commit", { workspacePath, targetBranch });
+
+export const getMergeChecks = (repoPath: string): Promise<MergeCheckConfig> =>
+  invoke("get_merge_checks", { repoPath });
+
+export const setMergeChecks = (
+  repoPath: string,
+  config: MergeCheckConfig
+): Promise<void> => invoke("set_merge_checks", { repoPath, config });
 
 export const updateWorkspaceMetadata = (
   repo_path: string,
@@ -412,9 +1131,69 @@ export const updateWorkspaceMetadata = (
     metadata,
   });
 
-export const updateWorkspaceConflicts = (
+/** Goal, acceptance criteria and linked issue stored in workspace metadata */
+export interface WorkspaceIntent {
+  intent?: string | null;
+  acceptance_criteria?: string[];
+  linked_issue?: string | null;
+}
+
+export const getWorkspaceIntent = (
   repo_path: string,
-  workspace_id: number,
+  id: number
+): Promise<WorkspaceIntent> =>
+  invoke("get_workspace_intent", { repoPath: repo_path, id });
+
+export const setWorkspaceIntent = (
+  repo_path: string,
+  id: number,
+  intent: WorkspaceIntent
+): Promise<void> =>
+  invoke("set_workspace_intent", { repoPath: repo_path, id, intent });
+
+/** Rebase the workspace in the background when a fetch advances its target branch */
+export const setWorkspaceAutoRebase = (
+  repo_path: string,
+  id: number,
+  enabled: boolean
+): Promise<void> =>
+  invoke("set_workspace_auto_rebase", { repoPath: repo_path, id, enabled });
+
+/** Payload of the `workspace-auto-rebased` and `workspace-rebase-conflict` events */
+export interface TargetUpdateRebase {
+  repo_path: string;
+  workspace_id: number;
+  workspace_path: string;
+  target_branch: string;
+  target_commit: string;
+  conflicted_files: string[];
+  message: string;
+}
+
+export const workspaceAutoRebasedListen = (
+  callback: (payload: TargetUpdateRebase) => void
+) =>
+  listen<TargetUpdateRebase>("workspace-auto-rebased", (event) =>
+    callback(event.payload)
+  );
+
+export const workspaceRebaseConflictListen = (
+  callback: (payload: TargetUpdateRebase) => void
+) =>
+  listen<TargetUpdateRebase>("workspace-rebase-conflict", (event) =>
+    callback(event.payload)
+  );
+
+/** Prompt-ready Markdown with the intent, target branch and changed files */
+export const exportWorkspaceContext = (
+  repo_path: string,
+  id: number
+): Promise<string> =>
+  invoke("export_workspace_context", { repoPath: repo_path, id });
+
+export const updateWorkspaceConflicts = (
+  repo_path: string,
+  workspace_id: number,
   has_conflicts: boolean
 ): Promise<void> =>
   invoke("update_workspace_conflicts", {
@@ -437,6 +1216,51 @@ export const listWorkspacesWithChanges = (
     repoPath: repo_path,
   });
 
+/** How the workspace diverges from a candidate target branch, before switching to it */
+export interface TargetBranchPreview {
+  target_branch: string;
+  branch_status: BranchStatus;
+  /** Newest first, capped; `ahead` has the full count */
+  commits_ahead: {
+    hash: string;
+    short_hash: string;
+    author_name: string;
+    author_email: string;
+    date: string;
+    sort_key: number;
+    message: string;
+    parent_hashes: string[];
+  }[];
+  ahead: number;
+  behind: number;
+  /** False when a shallow clone lacks the merge base, so the counts are too high */
+  accurate: boolean;
+}
+
+export interface RepoCapabilities {
+  shallow: boolean;
+  /** Objects are fetched on demand from `promisor_remotes` */
+  partial: boolean;
+  promisor_remotes: string[];
+}
+
+/** Repo setting that, when "false", stops divergence checks from deepening shallow clones */
+export const AUTO_DEEPEN_HISTORY_KEY = "auto_deepen_history";
+
+export const getRepoCapabilities = (repo_path: string): Promise<RepoCapabilities> =>
+  invoke("get_repo_capabilities", { repoPath: repo_path });
+
+export const previewWorkspaceTargetBranch = (
+  repo_path: string,
+  id: number,
+  target_branch: string
+): Promise<TargetBranchPreview> =>
+  invoke("preview_workspace_target_branch", {
+    repoPath: repo_path,
+    id,
+    targetBranch: target_branch,
+  });
+
 export const setWorkspaceTargetBranch = (
   repo_path: string,
   workspace_path: string,
@@ -479,14 +1303,563 @@ export const checkAndRebaseWorkspaces = (
     force: force ?? null,
   });
 
+// Workspace batch API
+export interface WorkspaceBatchResult<T> {
+  workspace_id: number;
+  workspace_name: string;
+  success: boolean;
+  result: T | null;
+  error: string | null;
+}
+
+export interface WorkspaceSyncStatus {
+  ahead: number;
+  behind: number;
+}
+
+export interface WorkspaceStatusSummary {
+  branch_name: string;
+  target_branch: string | null;
+  changed_files: number;
+  ahead: number;
+  behind: number;
+  has_conflicts: boolean;
+}
+
+export const workspacesRebaseAllOnto = (
+  repo_path: string,
+  target_branch: string
+): Promise<WorkspaceBatchResult<string>[]> =>
+  invoke("workspaces_rebase_all_onto", { repoPath: repo_path, targetBranch: target_branch });
+
+export const workspacesFetchAll = (
+  repo_path: string
+): Promise<WorkspaceBatchResult<WorkspaceSyncStatus>[]> =>
+  invoke("workspaces_fetch_all", { repoPath: repo_path });
+
+export const workspacesStatusAll = (
+  repo_path: string
+): Promise<WorkspaceBatchResult<WorkspaceStatusSummary>[]> =>
+  invoke("workspaces_status_all", { repoPath: repo_path });
+
+/** Everything the dashboard shows for one workspace */
+export interface WorkspaceCard {
+  workspace_path: string;
+  branch_name: string;
+  target_branch: string | null;
+  ahead: number;
+  behind: number;
+  insertions: number;
+  deletions: number;
+  changed_files: JjFileChange[];
+  has_conflicts: boolean;
+}
+
+export interface DashboardSnapshot {
+  repo_path: string;
+  generated_at: string;
+  workspaces: WorkspaceBatchResult<WorkspaceCard>[];
+}
+
+/** All workspace cards gathered in parallel on the backend, in one call */
+export const getDashboardSnapshot = (repo_path: string): Promise<DashboardSnapshot> =>
+  invoke("get_dashboard_snapshot", { repoPath: repo_path });
+
+// Git LFS API
+export interface GitLfsFile {
+  path: string;
+  oid: string;
+  downloaded: boolean;
+}
+
+export interface GitLfsStatus {
+  installed: boolean;
+  tracked_patterns: string[];
+  files: GitLfsFile[];
+}
+
+export const gitLfsStatus = (repo_path: string): Promise<GitLfsStatus> =>
+  invoke("git_lfs_status", { repoPath: repo_path });
+
+export const gitLfsPull = (
+  repo_path: string,
+  paths?: string[]
+): Promise<string> =>
+  invoke("git_lfs_pull", { repoPath: repo_path, paths: paths ?? null });
+
+// Git submodule API
+export type SubmoduleState = "clean" | "modified" | "uninitialized" | "conflict";
+
+export interface GitSubmodule {
+  path: string;
+  url: string | null;
+  commit: string;
+  state: SubmoduleState;
+  describe: string | null;
+}
+
+export const gitSubmoduleList = (repo_path: string): Promise<GitSubmodule[]> =>
+  invoke("git_submodule_list", { repoPath: repo_path });
+
+export const gitSubmoduleUpdate = (
+  repo_path: string,
+  init?: boolean,
+  recursive?: boolean
+): Promise<string> =>
+  invoke("git_submodule_update", {
+    repoPath: repo_path,
+    init: init ?? null,
+    recursive: recursive ?? null,
+  });
+
+// Patch files API
+export type PatchSelection =
+  /** A single commit, or a range such as "main..HEAD", as format-patch mails */
+  | { kind: "revisions"; revspec: string }
+  /** Uncommitted changes to these files; all changes when empty */
+  | { kind: "files"; paths: string[] };
+
+export interface PatchExportResult {
+  dest_path: string;
+  files: string[];
+  commit_count: number;
+}
+
+export interface PatchHunkFailure {
+  path: string;
+  line: number;
+  header: string | null;
+}
+
+export interface PatchApplyResult {
+  success: boolean;
+  dry_run: boolean;
+  files: string[];
+  failed_hunks: PatchHunkFailure[];
+  conflicted_files: string[];
+  errors: string[];
+}
+
+export const exportPatch = (
+  workspace_path: string,
+  selection: PatchSelection,
+  dest_path: string
+): Promise<PatchExportResult> =>
+  invoke("export_patch", { workspacePath: workspace_path, selection, destPath: dest_path });
+
+export const applyPatchFile = (
+  workspace_path: string,
+  patch_path: string,
+  three_way: boolean,
+  dry_run?: boolean
+): Promise<PatchApplyResult> =>
+  invoke("apply_patch_file", {
+    workspacePath: workspace_path,
+    patchPath: patch_path,
+    threeWay: three_way,
+    dryRun: dry_run ?? null,
+  });
+
+export interface PatchEmailFile {
+  path: string;
+  subject: string;
+  is_cover_letter: boolean;
+  sent: boolean;
+  error: string | null;
+}
+
+export interface PatchEmailResult {
+  output_dir: string;
+  patches: PatchEmailFile[];
+  send_attempted: boolean;
+  send_output: string | null;
+}
+
+/** Numbered mails for the commits ahead of target_branch; output_dir defaults to .treq/patches */
+export const formatPatchSeries = (
+  workspace_path: string,
+  target_branch: string,
+  cover_letter: boolean,
+  output_dir?: string
+): Promise<PatchEmailResult> =>
+  invoke("format_patch_series", {
+    workspacePath: workspace_path,
+    targetBranch: target_branch,
+    outputDir: output_dir ?? null,
+    coverLetter: cover_letter,
+  });
+
+// Bisect API
+export type BisectVerdict = "good" | "bad" | "skip";
+
+export interface BisectStatus {
+  active: boolean;
+  /** Commit checked out for testing; null once the first bad commit is found */
+  candidate: BranchCommitInfo | null;
+  first_bad: BranchCommitInfo | null;
+  bad: string | null;
+  good: string[];
+  skipped: string[];
+  remaining_revisions: number;
+  /** Estimated verdicts still needed */
+  remaining_steps: number;
+}
+
+export const bisectStart = (repo_path: string, good: string, bad: string): Promise<BisectStatus> =>
+  invoke("bisect_start", { repoPath: repo_path, good, bad });
+
+export const bisectMark = (repo_path: string, verdict: BisectVerdict): Promise<BisectStatus> =>
+  invoke("bisect_mark", { repoPath: repo_path, verdict });
+
+export const bisectStatus = (repo_path: string): Promise<BisectStatus> =>
+  invoke("bisect_status", { repoPath: repo_path });
+
+export const bisectAbort = (repo_path: string): Promise<void> =>
+  invoke("bisect_abort", { repoPath: repo_path });
+
+// Branch cleanup API
+/** Repo setting that, when "true", allows force pushing the default branch */
+export const ALLOW_FORCE_PUSH_DEFAULT_BRANCH_KEY = "allow_force_push_default_branch";
+
+export type DestructiveOperation =
+  | "discard_all_changes"
+  | "discard_changes"
+  | "force_push"
+  | "delete_branch"
+  | "delete_remote_branch";
+
+export interface DestructiveConfirmation {
+  token: string;
+  operation: DestructiveOperation;
+  /** Workspaces of the repo, empty when no confirmation is needed */
+  workspace_names: string[];
+  expires_in_secs: number;
+}
+
+/** Single-use token for one destructive operation in a main repo with workspaces */
+export const requestDestructiveConfirmation = (
+  repo_path: string,
+  operation: DestructiveOperation
+): Promise<DestructiveConfirmation> =>
+  invoke("request_destructive_confirmation", { repoPath: repo_path, operation });
+
+export const gitDeleteBranch = (
+  repo_path: string,
+  branch: string,
+  force?: boolean,
+  confirmToken?: string
+): Promise<string> =>
+  invoke("git_delete_branch", {
+    repoPath: repo_path,
+    branch,
+    force: force ?? null,
+    confirmToken,
+  });
+
+export const gitDeleteRemoteBranch = (
+  repo_path: string,
+  remote: string,
+  branch: string,
+  confirmToken?: string
+): Promise<string> =>
+  invoke("git_delete_remote_branch", {
+    repoPath: repo_path,
+    remote,
+    branch,
+    confirmToken,
+  });
+
+/** Returns the pruned remote-tracking refs, e.g. "origin/feature" */
+export const gitPruneRemote = (repo_path: string, remote: string): Promise<string[]> =>
+  invoke("git_prune_remote", { repoPath: repo_path, remote });
+
+// Revert API
+export interface GitRevertResult {
+  success: boolean;
+  message: string;
+  /** Files that conflicted; the revert was aborted */
+  conflicted_files: string[];
+}
+
+export const gitRevert = (
+  repo_path: string,
+  commit_hash: string,
+  no_commit?: boolean
+): Promise<GitRevertResult> =>
+  invoke("git_revert", { repoPath: repo_path, commitHash: commit_hash, noCommit: no_commit ?? false });
+
+// Commit signing API
+/** Global and repo setting keys; the repo value overrides the global one */
+export const COMMIT_SIGNING_FORMAT_KEY = "commit_signing_format";
+export const COMMIT_SIGNING_KEY_KEY = "commit_signing_key";
+
+/** Stored in COMMIT_SIGNING_FORMAT_KEY; "none" turns signing off for a repo */
+export type CommitSigningFormat = "gpg" | "ssh" | "none";
+
+export type SignatureStatus =
+  | "good"
+  | "bad"
+  | "unknown_validity"
+  | "expired"
+  | "expired_key"
+  | "revoked_key"
+  | "cannot_verify"
+  | "unsigned";
+
+export interface CommitSignature {
+  hash: string;
+  short_hash: string;
+  subject: string;
+  status: SignatureStatus;
+  signer: string | null;
+  key: string | null;
+}
+
+/** `range` is e.g. "main..HEAD", or a single revision for its history */
+export const verifyCommitSignatures = (
+  repo_path: string,
+  range: string
+): Promise<CommitSignature[]> =>
+  invoke("verify_commit_signatures", { repoPath: repo_path, range });
+
+// Forge API (GitHub through gh, GitLab through glab, Bitbucket through curl)
+/** Secret holding the GitHub token; unset uses gh's own login */
+export const GITHUB_TOKEN_KEY = "github_token";
+/** Secret holding the GitLab token; unset uses glab's own login */
+export const GITLAB_TOKEN_KEY = "gitlab_token";
+/** Secret holding a Bitbucket access token or `username:app_password` */
+export const BITBUCKET_TOKEN_KEY = "bitbucket_token";
+
+export type ForgeKind = "github" | "gitlab" | "bitbucket";
+
+export interface ForgeRepo {
+  provider: ForgeKind;
+  /** GitLab owners may be nested groups (`group/subgroup`) */
+  owner: string;
+  name: string;
+  web_url: string;
+}
+
+/** Pull request, or merge request on GitLab */
+export interface PullRequest {
+  number: number;
+  title: string;
+  url: string;
+  state: "open" | "closed" | "merged";
+  draft: boolean;
+  head_branch: string;
+  base_branch: string;
+  head_sha: string;
+  /** Combined status of the head commit; null when no checks report */
+  checks: "success" | "pending" | "failure" | "error" | null;
+}
+
+export const forgeDetectProvider = (repo_path: string): Promise<ForgeRepo | null> =>
+  invoke("forge_detect_provider", { repoPath: repo_path });
+
+/** `base` defaults to the workspace's target branch; the branch must be pushed first */
+export const forgeCreatePr = (
+  workspace_path: string,
+  title: string,
+  body: string,
+  base?: string
+): Promise<PullRequest> =>
+  invoke("forge_create_pr", { workspacePath: workspace_path, title, body, base: base ?? null });
+
+export const forgeGetPrStatus = (
+  repo_path: string,
+  branch: string
+): Promise<PullRequest | null> =>
+  invoke("forge_get_pr_status", { repoPath: repo_path, branch });
+
+/** Pull requests from `branch` in any state, newest first, without checks */
+export const forgeListPrsForBranch = (
+  repo_path: string,
+  branch: string
+): Promise<PullRequest[]> =>
+  invoke("forge_list_prs_for_branch", { repoPath: repo_path, branch });
+
+export interface CheckRun {
+  name: string;
+  state: "success" | "pending" | "failure" | "error";
+  url: string | null;
+}
+
+export interface CommitChecks {
+  sha: string;
+  /** Combined state of all checks; null when none report */
+  state: CheckRun["state"] | null;
+  checks: CheckRun[];
+  fetched_at: string;
+  from_cache: boolean;
+}
+
+/** Payload of `checks-changed`, emitted when a background fetch finds new CI results */
+export interface ChecksChanged {
+  repo_path: string;
+  branch: string;
+  checks: CommitChecks;
+}
+
+/** Branch names resolve to the pushed branch when there is one */
+export const forgeGetChecks = (
+  repo_path: string,
+  branch_or_sha: string
+): Promise<CommitChecks> =>
+  invoke("forge_get_checks", { repoPath: repo_path, branchOrSha: branch_or_sha });
+
+export const checksChangedListen = (callback: (payload: ChecksChanged) => void) =>
+  listen<ChecksChanged>("checks-changed", (event) => callback(event.payload));
+
+// Credential prompts API
+/** Payload of the `auth-prompt` event, sent when git or ssh needs credentials */
+export interface AuthPrompt {
+  id: number;
+  prompt: string;
+  /** Mask the input (password, passphrase or token) */
+  secret: boolean;
+  operation: string;
+}
+
+export const authPromptListen = (callback: (prompt: AuthPrompt) => void) =>
+  listen<AuthPrompt>("auth-prompt", (event) => callback(event.payload));
+
+/** Pass `null` to cancel; remembered answers last until treq restarts */
+export const submitAuthResponse = (
+  id: number,
+  response: string | null,
+  remember?: boolean
+): Promise<void> =>
+  invoke("submit_auth_response", { id, response, remember: remember ?? null });
+
+export const clearAuthCache = (): Promise<void> => invoke("clear_auth_cache");
+
+// Revision browsing API (read-only, nothing is checked out)
+export interface RevisionTreeEntry {
+  name: string;
+  path: string;
+  kind: "file" | "directory" | "symlink" | "submodule";
+  size: number | null;
+}
+
+export interface RevisionTree {
+  commit_id: string;
+  path: string;
+  entries: RevisionTreeEntry[];
+}
+
+export const browseRevision = (
+  repo_path: string,
+  revision: string,
+  path?: string
+): Promise<RevisionTree> =>
+  invoke("browse_revision", { repoPath: repo_path, revision, path: path ?? null });
+
+export const readFileAtRevision = (
+  repo_path: string,
+  revision: string,
+  file_path: string
+): Promise<FileContent> =>
+  invoke("read_file_at_revision", { repoPath: repo_path, revision, filePath: file_path });
+
+// Repository bootstrap API
+export interface GitIdentity {
+  name: string;
+  email: string;
+}
+
+export interface BootstrapOptions {
+  initial_branch?: string | null;
+  gitignore_template?: string | null;
+  /** README.md content; an empty string writes a title from the directory name */
+  readme?: string | null;
+  /** Defaults to true */
+  initial_commit?: boolean | null;
+  commit_message?: string | null;
+  init_jj?: boolean | null;
+  identity?: GitIdentity | null;
+  remote_url?: string | null;
+  remote_name?: string | null;
+}
+
+export interface BootstrapResult {
+  path: string;
+  initial_branch: string;
+  initial_commit: string | null;
+  created_files: string[];
+  jj_initialized: boolean;
+  remote: string | null;
+}
+
+export const bootstrapRepository = (
+  path: string,
+  options?: BootstrapOptions
+): Promise<BootstrapResult> =>
+  invoke("bootstrap_repository", { path, options: options ?? null });
+
+export const listGitignoreTemplates = (): Promise<string[]> =>
+  invoke("list_gitignore_templates");
+
+export interface CloneOptions {
+  branch?: string;
+  depth?: number;
+  remote_name?: string;
+  recurse_submodules?: boolean;
+  init_jj?: boolean;
+}
+
+export interface CloneResult {
+  path: string;
+  branch: string | null;
+  jj_initialized: boolean;
+}
+
+export interface CloneProgress {
+  clone_id: string;
+  dest_path: string;
+  phase: string;
+  percent: number | null;
+  current: number | null;
+  total: number | null;
+  received_bytes: number | null;
+  done: boolean;
+}
+
+export interface CloneComplete {
+  clone_id: string;
+  dest_path: string;
+  result: CloneResult | null;
+  error: string | null;
+}
+
+/** Starts a background clone and resolves to its id; progress arrives via listeners */
+export const gitClone = (
+  url: string,
+  destPath: string,
+  options?: CloneOptions
+): Promise<string> =>
+  invoke("git_clone", { url, destPath, options: options ?? null });
+
+export const cloneProgressListen = (callback: (progress: CloneProgress) => void) =>
+  listen<CloneProgress>("clone-progress", (event) => callback(event.payload));
+
+export const cloneCompleteListen = (callback: (complete: CloneComplete) => void) =>
+  listen<CloneComplete>("clone-complete", (event) => callback(event.payload));
+
 // PTY API
+export interface PtyEnvironment {
+  env?: Record<string, string>;
+  startup_command?: string;
+}
+
 export const ptyCreateSession = (
   session_id: string,
   working_dir?: string,
   shell?: string,
-  initial_command?: string
+  initial_command?: string,
+  environment?: PtyEnvironment
 ): Promise<void> =>
-  invoke("pty_create_session", { sessionId: session_id, workingDir: working_dir, shell, initialCommand: initial_command });
+  invoke("pty_create_session", { sessionId: session_id, workingDir: working_dir, shell, initialCommand: initial_command, environment });
 
 export const ptyWrite = (session_id: string, data: string): Promise<void> =>
   invoke("pty_write", { sessionId: session_id, data });
@@ -497,16 +1870,31 @@ export const ptyResize = (session_id: string, rows: number, cols: number): Promi
 export const ptyClose = (session_id: string): Promise<void> =>
   invoke("pty_close", { sessionId: session_id });
 
+export const ptyGetScrollback = (session_id: string): Promise<string | null> =>
+  invoke("pty_get_scrollback", { sessionId: session_id });
+
 export const ptySessionExists = (session_id: string): Promise<boolean> =>
   invoke("pty_session_exists", { sessionId: session_id });
 
 export const ptyListen = (session_id: string, callback: (data: string) => void) =>
   listen<string>(`pty-data-${session_id}`, (event) => callback(event.payload));
 
+export const graphDeltaListen = (callback: (delta: GraphDelta) => void) =>
+  listen<GraphDelta>("graph-delta", (event) => callback(event.payload));
+
 // File System API
-export const readFile = (path: string): Promise<string> =>
+/** File content, or why it was not loaded */
+export type FileContent =
+  | { kind: "text"; content: string }
+  | { kind: "file_too_large"; size: number; max_size: number; mime: string | null }
+  | { kind: "binary_file"; size: number; mime: string | null };
+
+export const readFile = (path: string): Promise<FileContent> =>
   invoke("read_file", { path });
 
+export const checkFileGuard = (path: string): Promise<FileContent | null> =>
+  invoke("check_file_guard", { path });
+
 export const listDirectory = (path: string): Promise<DirectoryEntry[]> =>
   invoke("list_directory", { path });
 
@@ -534,6 +1922,75 @@ export const searchWorkspaceFiles = (
     limit: limit ?? 50,
   });
 
+export interface FuzzyFileMatch {
+  file_path: string;
+  relative_path: string;
+  score: number;
+  /** Character indices into relative_path, for highlighting */
+  matched_indices: number[];
+}
+
+/** Fuzzy quick-open search over the file index, best matches first */
+export const searchFiles = (
+  repoPath: string,
+  workspaceId: number | null,
+  query: string,
+  limit?: number
+): Promise<FuzzyFileMatch[]> =>
+  invoke("search_files", { repoPath, workspaceId, query, limit: limit ?? null });
+
+// Content search API
+export interface SearchOptions {
+  case_sensitive?: boolean;
+  whole_word?: boolean;
+  context_lines?: number;
+  max_results?: number;
+  include?: string[];
+  exclude?: string[];
+  include_hidden?: boolean;
+}
+
+export interface SearchMatch {
+  path: string;
+  line_number: number;
+  column: number;
+  line: string;
+  context_before: string[];
+  context_after: string[];
+}
+
+export interface SearchBatch {
+  search_id: string;
+  matches: SearchMatch[];
+}
+
+export interface SearchSummary {
+  search_id: string;
+  total_matches: number;
+  files_searched: number;
+  files_with_matches: number;
+  truncated: boolean;
+  cancelled: boolean;
+  error: string | null;
+}
+
+/** Starts a background search and resolves to its id; results arrive via listeners */
+export const searchInWorkspace = (
+  workspacePath: string,
+  query: string,
+  options?: SearchOptions
+): Promise<string> =>
+  invoke("search_in_workspace", { workspacePath, query, options: options ?? null });
+
+export const cancelSearch = (searchId: string): Promise<boolean> =>
+  invoke("cancel_search", { searchId });
+
+export const searchResultsListen = (callback: (batch: SearchBatch) => void) =>
+  listen<SearchBatch>("search-results", (event) => callback(event.payload));
+
+export const searchCompleteListen = (callback: (summary: SearchSummary) => void) =>
+  listen<SearchSummary>("search-complete", (event) => callback(event.payload));
+
 // Folder picker
 export const selectFolder = async (): Promise<string | null> => {
   const selected = await open({
@@ -544,6 +2001,20 @@ export const selectFolder = async (): Promise<string | null> => {
   return selected;
 };
 
+export const selectSettingsExportPath = (): Promise<string | null> =>
+  save({
+    title: "Export Settings",
+    defaultPath: "treq-settings.json",
+    filters: [{ name: "JSON", extensions: ["json"] }],
+  });
+
+export const selectSettingsImportPath = (): Promise<string | null> =>
+  open({
+    multiple: false,
+    title: "Import Settings",
+    filters: [{ name: "JSON", extensions: ["json"] }],
+  });
+
 // Session management API
 export const createSession = (
   repo_path: string,
@@ -564,43 +2035,179 @@ export const updateSessionName = (repo_path: string, id: number, name: string):
 export const deleteSession = (repo_path: string, id: number): Promise<void> =>
   invoke("delete_session", { repoPath: repo_path, id });
 
+export const createSessionTab = (
+  repo_path: string,
+  session_id: number,
+  title: string,
+  cwd?: string
+): Promise<SessionTab> =>
+  invoke("create_session_tab", { repoPath: repo_path, sessionId: session_id, title, cwd: cwd ?? null });
+
+export const getSessionTabs = (repo_path: string, session_id: number): Promise<SessionTab[]> =>
+  invoke("get_session_tabs", { repoPath: repo_path, sessionId: session_id });
+
+/** Tabs of all sessions of a workspace, to restore its layout when reopened */
+export const getWorkspaceSessionTabs = (
+  repo_path: string,
+  workspace_id: number | null
+): Promise<SessionTab[]> =>
+  invoke("get_workspace_session_tabs", { repoPath: repo_path, workspaceId: workspace_id });
+
+export const renameSessionTab = (repo_path: string, tab_id: number, title: string): Promise<void> =>
+  invoke("rename_session_tab", { repoPath: repo_path, tabId: tab_id, title });
+
+/** Omitted fields keep their stored value */
+export const updateSessionTabState = (
+  repo_path: string,
+  tab_id: number,
+  cwd?: string,
+  last_command?: string
+): Promise<void> =>
+  invoke("update_session_tab_state", {
+    repoPath: repo_path,
+    tabId: tab_id,
+    cwd: cwd ?? null,
+    lastCommand: last_command ?? null,
+  });
+
+export const reorderSessionTabs = (
+  repo_path: string,
+  session_id: number,
+  tab_ids: number[]
+): Promise<void> =>
+  invoke("reorder_session_tabs", { repoPath: repo_path, sessionId: session_id, tabIds: tab_ids });
+
+export const deleteSessionTab = (repo_path: string, tab_id: number): Promise<void> =>
+  invoke("delete_session_tab", { repoPath: repo_path, tabId: tab_id });
+
 export const getSessionModel = (repo_path: string, id: number): Promise<string | null> =>
   invoke("get_session_model", { repoPath: repo_path, id });
 
 export const setSessionModel = (repo_path: string, id: number, model: string | null): Promise<void> =>
   invoke("set_session_model", { repoPath: repo_path, id, model });
 
+// Session model registry API
+export interface SessionModel {
+  id: number;
+  provider: string;
+  model_id: string;
+  display_name: string;
+  is_default: boolean;
+}
+
+export const listSessionModels = (): Promise<SessionModel[]> =>
+  invoke("list_session_models");
+
+/** The model a provider's sessions run with when no model is chosen for them */
+export const defaultSessionModel = (
+  models: SessionModel[],
+  provider = "claude"
+): SessionModel | undefined =>
+  models.find((m) => m.provider === provider && m.is_default);
+
+export const saveSessionModel = (
+  provider: string,
+  model_id: string,
+  display_name: string
+): Promise<number> =>
+  invoke("save_session_model", { provider, modelId: model_id, displayName: display_name });
+
+export const deleteSessionModel = (id: number): Promise<void> =>
+  invoke("delete_session_model", { id });
+
+export const setDefaultSessionModel = (id: number): Promise<void> =>
+  invoke("set_default_session_model", { id });
+
 // File view tracking API
 export interface FileView {
   id: number;
   workspace_path: string;
+  base_ref: string;
+  head_sha: string;
   file_path: string;
   viewed_at: string;
   content_hash: string;
 }
 
+export interface HunkReviewState {
+  hunk_id: string;
+  content_hash: string;
+  summary: string;
+  viewed: boolean;
+}
+
+export interface FileHunkReview {
+  file_path: string;
+  total_hunks: number;
+  viewed_hunks: number;
+  hunks: HunkReviewState[];
+}
+
+/**
+ * Viewed state is scoped to a diff: `baseRef` is the ref it compares against (empty for
+ * uncommitted changes), and marks reset once the workspace HEAD moves.
+ */
 export const markFileViewed = (
   workspacePath: string,
+  baseRef: string,
   filePath: string,
   contentHash: string
 ): Promise<void> =>
-  invoke("mark_file_viewed", { workspacePath, filePath, contentHash });
+  invoke("mark_file_viewed", { workspacePath, baseRef, filePath, contentHash });
 
 export const unmarkFileViewed = (
   workspacePath: string,
+  baseRef: string,
   filePath: string
 ): Promise<void> =>
-  invoke("unmark_file_viewed", { workspacePath, filePath });
+  invoke("unmark_file_viewed", { workspacePath, baseRef, filePath });
 
-export const getViewedFiles = (workspacePath: string): Promise<FileView[]> =>
-  invoke("get_viewed_files", { workspacePath });
+/** Files whose hash in `currentHashes` changed since they were viewed are un-marked */
+export const getViewedFiles = (
+  workspacePath: string,
+  baseRef: string,
+  currentHashes?: Record<string, string>
+): Promise<FileView[]> =>
+  invoke("get_viewed_files", { workspacePath, baseRef, currentHashes: currentHashes ?? null });
 
 export const clearAllViewedFiles = (workspacePath: string): Promise<void> =>
   invoke("clear_all_viewed_files", { workspacePath });
 
-// Git remotes API (stub - backend not implemented)
-export const gitListRemotes = (_repoPath: string): Promise<string[]> =>
-  Promise.resolve(["origin"]);
+export const markHunkViewed = (
+  workspacePath: string,
+  filePath: string,
+  contentHash: string
+): Promise<void> =>
+  invoke("mark_hunk_viewed", { workspacePath, filePath, contentHash });
+
+export const unmarkHunkViewed = (
+  workspacePath: string,
+  filePath: string,
+  contentHash: string
+): Promise<void> =>
+  invoke("unmark_hunk_viewed", { workspacePath, filePath, contentHash });
+
+export const getFileHunkReview = (
+  workspacePath: string,
+  filePath: string
+): Promise<FileHunkReview> =>
+  invoke("get_file_hunk_review", { workspacePath, filePath });
+
+// Git remotes API
+export interface GitRemote {
+  name: string;
+  fetch_url: string | null;
+  /** pushurl, or the fetch URL when unset */
+  push_url: string | null;
+  fetch_refspecs: string[];
+  push_refspecs: string[];
+}
+
+export const gitListRemotesDetailed = (repoPath: string): Promise<GitRemote[]> =>
+  invoke("git_list_remotes_detailed", { repoPath });
+
+export const gitListRemotes = (repoPath: string): Promise<string[]> =>
+  gitListRemotesDetailed(repoPath).then((remotes) => remotes.map((r) => r.name));
 
 // Diff cache API (in-memory stub implementation)
 const diffCache = new Map<string, { data: string; timestamp: number }>();
@@ -663,6 +2270,7 @@ export interface BranchCommitInfo {
   author_name: string;
   author_email: string;
   timestamp: string;
+  sort_key: number;
 }
 
 // Branch diff functions (stub implementations - backend not yet implemented)
@@ -688,6 +2296,36 @@ export const gitGetCommitsBetweenBranches = (
 ): Promise<BranchCommitInfo[]> =>
   Promise.resolve([]);
 
+// Commit graph
+/** A commit in a commit graph and the column it is drawn in */
+export interface GraphNode {
+  hash: string;
+  short_hash: string;
+  author_name: string;
+  author_email: string;
+  date: string;
+  sort_key: number;
+  message: string;
+  parent_hashes: string[];
+  refs: string[];
+  lane: number;
+  /** Column of the edge to each parent, in `parent_hashes` order */
+  parent_lanes: number[];
+}
+
+export interface CommitGraph {
+  nodes: GraphNode[];
+  lane_count: number;
+  has_more: boolean;
+}
+
+/** Commit DAG of `refs`; defaults to the default branch and all workspace branches */
+export const gitGetGraph = (
+  repoPath: string,
+  refs?: string[],
+  limit?: number
+): Promise<CommitGraph> => invoke("git_get_graph", { repoPath, refs, limit });
+
 // Pending review persistence
 export interface LineComment {
   id: string;
@@ -749,6 +2387,11 @@ export const clearPendingReview = (
   invoke("clear_pending_review", { repoPath, workspaceId });
 
 // File Watcher API
+/** Global and repo setting keys; the repo value overrides the global one */
+export const WATCHER_DEBOUNCE_MS_KEY = "watcher_debounce_ms";
+/** Gitignore-style patterns, one per line, skipped in addition to .gitignore */
+export const WATCHER_IGNORE_KEY = "watcher_ignore";
+
 export const startFileWatcher = (
   workspaceId: number,
   workspacePath: string
@@ -758,3 +2401,74 @@ export const stopFileWatcher = (
   workspaceId: number,
   workspacePath: string
 ): Promise<void> => invoke("stop_file_watcher", { workspaceId, workspacePath });
+
+export type WatcherState = "healthy" | "degraded" | "failed";
+
+export interface WatcherHealth {
+  workspace_id: number;
+  workspace_path: string;
+  state: WatcherState;
+  overflow_count: number;
+  error_count: number;
+  restart_count: number;
+  last_error: string | null;
+  last_event_at: string | null;
+  last_degraded_at: string | null;
+}
+
+/** Payload of the `watcher-degraded` event; the workspace is rescanned automatically */
+export interface WatcherDegraded {
+  workspace_id: number;
+  workspace_path: string;
+  reason: string;
+}
+
+export const getWatcherStatus = (repoPath: string): Promise<WatcherHealth[]> =>
+  invoke("get_watcher_status", { repoPath });
+
+export const watcherDegradedListen = (callback: (payload: WatcherDegraded) => void) =>
+  listen<WatcherDegraded>("watcher-degraded", (event) => callback(event.payload));
+
+// Updates API
+export type UpdateChannel = "stable" | "beta";
+
+export interface ReleaseInfo {
+  version: string;
+  name: string | null;
+  notes: string;
+  url: string;
+  published_at: string | null;
+  prerelease: boolean;
+}
+
+export interface UpdateCheckResult {
+  channel: UpdateChannel;
+  current_version: string;
+  update: ReleaseInfo | null;
+}
+
+export interface RestartBlockers {
+  busy_terminals: number;
+  running_processes: number;
+  pending_index_writes: number;
+  active_operations: number;
+}
+
+export interface RestartStatus {
+  pending: boolean;
+  blockers: RestartBlockers;
+}
+
+export const checkForUpdates = (
+  channel?: UpdateChannel
+): Promise<UpdateCheckResult> =>
+  invoke("check_for_updates", { channel: channel ?? null });
+
+export const scheduleUpdateRestart = (): Promise<RestartStatus> =>
+  invoke("schedule_update_restart");
+
+export const cancelUpdateRestart = (): Promise<RestartStatus> =>
+  invoke("cancel_update_restart");
+
+export const getUpdateRestartStatus = (): Promise<RestartStatus> =>
+  invoke("get_update_restart_status");
@@ -18,6 +18,10 @@ export const STATUS_COLORS = {
     bg: "bg-blue-500",
     text: "text-blue-500",
   },
+  C: {
+    bg: "bg-green-500",
+    text: "text-green-500",
+  },
   "??": {
     bg: "bg-green-500",
     text: "text-green-500",
@@ -50,5 +54,7 @@ export function getFileStatusTextColor(status?: string, isUntracked = false): st
   if (status === "M") return "text-yellow-500";
   if (status === "A") return "text-green-500";
   if (status === "D") return "text-red-500";
+  if (status === "R") return "text-blue-500";
+  if (status === "C") return "text-green-500";
   return "text-yellow-500"; // default for any other change
 }
@@ -8,14 +8,25 @@ interface ToastFn {
   (toast: { title: string; description: string; type: ToastType }): void;
 }
 
+/**
+ * Message of a rejected command: Error objects, typed `{ kind, message }` errors or strings
+ */
+export function errorMessage(error: unknown): string {
+  if (error instanceof Error) return error.message;
+  if (typeof error === "object" && error !== null && "message" in error) {
+    return String((error as { message: unknown }).message);
+  }
+  return String(error);
+}
+
 /**
  * Show an error toast with consistent formatting
- * Automatically extracts error message from Error objects
+ * Automatically extracts the message from Error objects and typed command errors
  */
 export function showErrorToast(addToast: ToastFn, title: string, error: unknown): void {
   addToast({
     title,
-    description: error instanceof Error ? error.message : String(error),
+    description: errorMessage(error),
     type: "error",
   });
 }